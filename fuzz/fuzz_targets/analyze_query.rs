@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use whois_server::analyze_query;
+use whois_server::core::sanitize::{MAX_QUERY_LENGTH, sanitize_query};
+
+fuzz_target!(|data: &str| {
+    // The raw parser must never panic, even on unsanitized input
+    let _ = analyze_query(data);
+
+    // Sanitized input must stay bounded
+    let sanitized = sanitize_query(data);
+    assert!(sanitized.len() <= MAX_QUERY_LENGTH);
+    let _ = analyze_query(&sanitized);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use whois_server::core::ColorProtocol;
+
+fuzz_target!(|data: &str| {
+    let mut protocol = ColorProtocol::new();
+    let _ = protocol.parse_headers(data);
+    let _ = protocol.should_colorize();
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use whois_server::core::parse_patch;
+
+fuzz_target!(|data: &str| {
+    // Parse errors (e.g. invalid regex conditions) are fine, panics are not
+    let _ = parse_patch("fuzz.patch", data);
+});
@@ -0,0 +1,140 @@
+// WHOIS Server - LDAP Authentication
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! LDAP simple bind and group lookup
+//!
+//! A simple bind as the user's DN checks the password, then a base-object
+//! search of that DN reads its `memberOf` attribute. The LDAP protocol is
+//! spoken by the `ldap3` crate. `ldaps://` URLs are verified against the
+//! Mozilla root store; plain `ldap://` should only be used towards a
+//! directory on a trusted network, as the password crosses it in the clear.
+
+use anyhow::{Result, anyhow};
+use ldap3::{LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::log_debug;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// LDAP resultCode invalidCredentials
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// The `[ldap]` section of the auth file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LdapConfig {
+    /// `ldap://host[:port]` or `ldaps://host[:port]`
+    url: String,
+    /// DN to bind as, `{user}` is replaced by the user name
+    bind_dn: String,
+}
+
+impl LdapConfig {
+    pub(crate) fn validate(&self) -> Result<()> {
+        let url = url::Url::parse(&self.url).map_err(|e| anyhow!("Invalid ldap.url {}: {}", self.url, e))?;
+        if !matches!(url.scheme(), "ldap" | "ldaps") {
+            return Err(anyhow!("ldap.url must be ldap:// or ldaps://, got {}://", url.scheme()));
+        }
+        if url.host_str().is_none() {
+            return Err(anyhow!("ldap.url has no host: {}", self.url));
+        }
+        if !self.bind_dn.contains("{user}") {
+            return Err(anyhow!("ldap.bind_dn must contain {{user}}"));
+        }
+        Ok(())
+    }
+}
+
+/// CN of a group DN (`cn=noc,ou=groups,...` -> `noc`)
+pub(crate) fn group_cn(dn: &str) -> Option<&str> {
+    let (attribute, value) = dn.split(',').next()?.split_once('=')?;
+    attribute.trim().eq_ignore_ascii_case("cn").then(|| value.trim())
+}
+
+/// User names are put into a DN, so only plain ones are accepted
+fn valid_user(user: &str) -> bool {
+    !user.is_empty() && user.len() <= 64 && user.chars().all(|c| c.is_ascii_alphanumeric() || "._-@".contains(c))
+}
+
+/// Bind as `user`, returning its groups, or `None` for wrong credentials
+pub(crate) async fn login(config: &LdapConfig, user: &str, password: &str) -> Result<Option<Vec<String>>> {
+    // An empty password would be an unauthenticated bind, which most directories accept
+    if !valid_user(user) || password.is_empty() {
+        return Ok(None);
+    }
+    let dn = config.bind_dn.replace("{user}", user);
+
+    let settings = LdapConnSettings::new().set_conn_timeout(TIMEOUT).set_config(Arc::new(tls_config()));
+    let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &config.url).await?;
+    tokio::spawn(async move {
+        if let Err(e) = conn.drive().await {
+            log_debug!("LDAP connection error: {}", e);
+        }
+    });
+
+    let bind = ldap.with_timeout(TIMEOUT).simple_bind(&dn, password).await?;
+    match bind.rc {
+        0 => {}
+        INVALID_CREDENTIALS => return Ok(None),
+        code => return Err(anyhow!("LDAP bind failed with result code {}", code)),
+    }
+
+    let (entries, _) = ldap
+        .with_timeout(TIMEOUT)
+        .search(&dn, Scope::Base, "(objectClass=*)", vec!["memberOf"])
+        .await?
+        .success()?;
+    let groups = entries.into_iter().flat_map(|entry| member_of(SearchEntry::construct(entry).attrs)).collect();
+
+    let _ = ldap.unbind().await;
+    Ok(Some(groups))
+}
+
+/// Client TLS settings trusting the Mozilla root store
+fn tls_config() -> rustls::ClientConfig {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|root| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(root.subject, root.spki, root.name_constraints)
+    }));
+    rustls::ClientConfig::builder().with_safe_defaults().with_root_certificates(roots).with_no_client_auth()
+}
+
+/// memberOf values of a search entry (attribute names are case-insensitive)
+fn member_of(attrs: HashMap<String, Vec<String>>) -> Vec<String> {
+    attrs
+        .into_iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("memberOf"))
+        .flat_map(|(_, values)| values)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups_and_users() {
+        let attrs = HashMap::from([
+            ("memberof".to_string(), vec!["cn=noc,ou=groups".to_string(), "cn=staff,ou=groups".to_string()]),
+            ("mail".to_string(), vec!["a@example.net".to_string()]),
+        ]);
+        assert_eq!(member_of(attrs), ["cn=noc,ou=groups", "cn=staff,ou=groups"]);
+        assert!(member_of(HashMap::new()).is_empty());
+
+        assert_eq!(group_cn("CN=Staff, ou=groups"), Some("Staff"));
+        assert!(!valid_user("a,ou=admins") && !valid_user("*") && valid_user("alice.b@example.net"));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let config = |url: &str, bind_dn: &str| LdapConfig { url: url.to_string(), bind_dn: bind_dn.to_string() };
+        assert!(config("ldaps://ldap.example.net", "uid={user},ou=people").validate().is_ok());
+        assert!(config("ldap://10.0.0.5:389", "uid={user},ou=people").validate().is_ok());
+        assert!(config("https://ldap.example.net", "uid={user},ou=people").validate().is_err());
+        assert!(config("ldaps://ldap.example.net", "uid=admin,ou=people").validate().is_err());
+    }
+}
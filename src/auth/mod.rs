@@ -0,0 +1,233 @@
+// WHOIS Server - Authentication
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! LDAP and OIDC logins for the web and SSH frontends (`--auth-file`)
+//!
+//! Internal deployments can require a login instead of serving everyone:
+//!
+//! ```toml
+//! # Frontends that require a login
+//! web = true
+//! ssh = true
+//!
+//! # Simple bind as the user, groups from its memberOf attribute
+//! [ldap]
+//! url = "ldaps://ldap.example.net"
+//! bind_dn = "uid={user},ou=people,dc=example,dc=net"
+//!
+//! # Bearer tokens issued by an OpenID Connect provider
+//! [oidc]
+//! issuer = "https://sso.example.net/realms/noc"
+//! audience = "whois"
+//!
+//! # Permission class of each group; users in none of them are refused
+//! [groups]
+//! noc = "admin"
+//! staff = "query"
+//! ```
+//!
+//! The `query` class may run queries; `admin` may also read the web
+//! statistics and `/metrics`. The web frontend accepts HTTP Basic credentials
+//! (checked against LDAP) and OIDC bearer tokens, SSH accepts passwords
+//! (checked against LDAP). Groups match by name, or for LDAP by full DN or
+//! the group's CN, case-insensitively. Successful LDAP logins are reused
+//! for a minute and repeated failures lock the user or client out for a
+//! while (`throttle`).
+
+pub mod ldap;
+pub mod oidc;
+mod throttle;
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use ldap::LdapConfig;
+use oidc::OidcConfig;
+use throttle::LoginGuard;
+
+/// What an authenticated user may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// Run queries
+    Query,
+    /// Run queries and read statistics and metrics
+    Admin,
+}
+
+/// A user who logged in or presented a valid token
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthenticatedUser {
+    pub name: String,
+    pub permission: Permission,
+}
+
+/// The loaded `--auth-file`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    #[serde(default)]
+    web: bool,
+    #[serde(default)]
+    ssh: bool,
+    #[serde(default)]
+    ldap: Option<LdapConfig>,
+    #[serde(default)]
+    oidc: Option<OidcConfig>,
+    #[serde(default)]
+    groups: HashMap<String, Permission>,
+    #[serde(skip)]
+    logins: LoginGuard,
+}
+
+static AUTH: OnceLock<AuthConfig> = OnceLock::new();
+
+/// Install the authentication settings used by all frontends
+pub fn init_auth(config: AuthConfig) {
+    let _ = AUTH.set(config);
+}
+
+/// Authentication settings, `None` when no `--auth-file` is configured
+pub fn auth() -> Option<&'static AuthConfig> {
+    AUTH.get()
+}
+
+/// Parse an authentication file
+pub fn parse_auth(content: &str) -> Result<AuthConfig> {
+    let config: AuthConfig = toml::from_str(content)?;
+    if config.ssh && config.ldap.is_none() {
+        return Err(anyhow!("ssh = true needs an [ldap] section to check passwords"));
+    }
+    if config.web && config.ldap.is_none() && config.oidc.is_none() {
+        return Err(anyhow!("web = true needs an [ldap] or [oidc] section"));
+    }
+    if (config.web || config.ssh) && config.groups.is_empty() {
+        return Err(anyhow!("[groups] must map at least one group to a permission class"));
+    }
+    if let Some(ldap) = &config.ldap {
+        ldap.validate()?;
+    }
+    Ok(config)
+}
+
+/// Load authentication settings from a TOML file
+pub fn load_auth_file(path: &Path) -> Result<AuthConfig> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read auth file {}: {}", path.display(), e))?;
+    parse_auth(&content).map_err(|e| anyhow!("Invalid auth file {}: {}", path.display(), e))
+}
+
+impl AuthConfig {
+    /// Whether the web frontend requires a login
+    pub fn requires_web(&self) -> bool {
+        self.web
+    }
+
+    /// Whether the SSH frontend requires a login
+    pub fn requires_ssh(&self) -> bool {
+        self.ssh
+    }
+
+    pub fn has_ldap(&self) -> bool {
+        self.ldap.is_some()
+    }
+
+    pub fn has_oidc(&self) -> bool {
+        self.oidc.is_some()
+    }
+
+    /// Highest permission class of any of `groups`
+    fn permission_for(&self, groups: &[String]) -> Option<Permission> {
+        let matches = |configured: &str, group: &str| {
+            configured.eq_ignore_ascii_case(group) || ldap::group_cn(group).is_some_and(|cn| configured.eq_ignore_ascii_case(cn))
+        };
+        self.groups
+            .iter()
+            .filter(|(configured, _)| groups.iter().any(|group| matches(configured, group)))
+            .map(|(_, permission)| *permission)
+            .max()
+    }
+
+    fn user(&self, name: String, groups: Vec<String>) -> Option<AuthenticatedUser> {
+        let permission = self.permission_for(&groups)?;
+        Some(AuthenticatedUser { name, permission })
+    }
+
+    /// Whether `user` or `client` failed to log in too often to try again yet
+    pub fn login_blocked(&self, user: &str, client: Option<IpAddr>) -> bool {
+        self.logins.blocked(user, client, Instant::now())
+    }
+
+    /// Check a user name and password against LDAP
+    ///
+    /// `Ok(None)` when the credentials are wrong, the user is in none of the
+    /// configured groups or the user or client is locked out after failed
+    /// logins, `Err` when the directory cannot be asked.
+    pub async fn login(&self, user: &str, password: &str, client: Option<IpAddr>) -> Result<Option<AuthenticatedUser>> {
+        let ldap = self.ldap.as_ref().ok_or_else(|| anyhow!("LDAP is not configured"))?;
+        if let Some(login) = self.logins.cached(user, password, Instant::now()) {
+            return Ok(Some(login));
+        }
+        if self.login_blocked(user, client) {
+            return Ok(None);
+        }
+
+        let login = ldap::login(ldap, user, password).await?.and_then(|groups| self.user(user.to_string(), groups));
+        match &login {
+            Some(login) => self.logins.succeeded(user, password, login, Instant::now()),
+            None => self.logins.failed(user, client, Instant::now()),
+        }
+        Ok(login)
+    }
+
+    /// Check an OIDC bearer token
+    ///
+    /// `Ok(None)` when the token is invalid, expired, meant for another
+    /// audience or its user is in none of the configured groups.
+    pub async fn verify_token(&self, token: &str) -> Result<Option<AuthenticatedUser>> {
+        let oidc = self.oidc.as_ref().ok_or_else(|| anyhow!("OIDC is not configured"))?;
+        Ok(oidc::verify_token(oidc, token).await?.and_then(|verified| self.user(verified.username, verified.groups)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_file_and_group_mapping() {
+        let config = parse_auth(
+            r#"
+            web = true
+            ssh = true
+
+            [ldap]
+            url = "ldaps://ldap.example.net"
+            bind_dn = "uid={user},ou=people,dc=example,dc=net"
+
+            [groups]
+            noc = "admin"
+            staff = "query"
+            "#,
+        )
+        .unwrap();
+        assert!(config.requires_web() && config.requires_ssh());
+
+        let groups = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        assert_eq!(config.permission_for(&groups(&["cn=staff,ou=groups,dc=example,dc=net"])), Some(Permission::Query));
+        assert_eq!(config.permission_for(&groups(&["CN=Staff,ou=groups", "NOC"])), Some(Permission::Admin));
+        assert_eq!(config.permission_for(&groups(&["cn=sales,ou=groups"])), None);
+        assert_eq!(config.user("alice".to_string(), groups(&["sales"])), None);
+
+        assert!(parse_auth("ssh = true\n[groups]\nnoc = \"admin\"\n").is_err());
+        assert!(parse_auth("web = true\n[ldap]\nurl = \"ldap://ldap\"\nbind_dn = \"uid={user}\"\n").is_err());
+        assert!(parse_auth("[groups]\nnoc = \"root\"\n").is_err());
+        assert!(parse_auth("web = true\n[ldap]\nurl = \"ldap://ldap\"\nbind_dn = \"uid=fixed\"\n[groups]\nnoc = \"admin\"\n").is_err());
+    }
+}
@@ -0,0 +1,319 @@
+// WHOIS Server - OIDC Authentication
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! OpenID Connect bearer tokens
+//!
+//! Tokens are JWTs signed by the issuer (RS256 or ES256). The signing keys
+//! come from the `jwks_uri` of the issuer's discovery document and are kept
+//! for an hour, or refetched early when a token names a key not seen yet
+//! (key rotation). The keys are fetched at most once a minute, so tokens with
+//! made-up key IDs cannot make the server hammer the issuer. The issuer, audience, expiry and not-before time are
+//! checked; the user name is the `preferred_username` claim (or `sub`) and
+//! the groups come from the `groups` claim unless configured otherwise.
+
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use once_cell::sync::Lazy;
+use ring::signature::{self, UnparsedPublicKey};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::core::body_limit::LimitedBody;
+use crate::log_debug;
+
+/// How long fetched signing keys are used
+const JWKS_TTL: Duration = Duration::from_secs(3600);
+/// Shortest time between two fetches of the signing keys
+const JWKS_MIN_REFETCH: Duration = Duration::from_secs(60);
+/// Clock skew tolerated on exp and nbf, in seconds
+const LEEWAY: u64 = 60;
+
+fn default_username_claim() -> String {
+    "preferred_username".to_string()
+}
+
+fn default_groups_claim() -> String {
+    "groups".to_string()
+}
+
+/// The `[oidc]` section of the auth file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    issuer: String,
+    audience: String,
+    #[serde(default = "default_username_claim")]
+    username_claim: String,
+    #[serde(default = "default_groups_claim")]
+    groups_claim: String,
+}
+
+/// A public key from the issuer's JWKS
+#[derive(Debug, Clone, Deserialize)]
+struct Jwk {
+    kty: String,
+    #[serde(default)]
+    kid: Option<String>,
+    #[serde(default)]
+    n: Option<String>,
+    #[serde(default)]
+    e: Option<String>,
+    #[serde(default)]
+    crv: Option<String>,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+}
+
+/// User name and groups of a valid token
+#[derive(Debug, PartialEq)]
+pub(crate) struct VerifiedToken {
+    pub(crate) username: String,
+    pub(crate) groups: Vec<String>,
+}
+
+/// Signing keys of the issuer
+#[derive(Debug, Default)]
+struct JwksCache {
+    /// Keys and when they were fetched
+    keys: Option<(Instant, Arc<Vec<Jwk>>)>,
+    /// Start of the last fetch, whether it succeeded or not
+    last_attempt: Option<Instant>,
+}
+
+impl JwksCache {
+    /// Whether the keys should be fetched for a token naming `kid`
+    fn fetch_due(&self, kid: Option<&str>, now: Instant) -> bool {
+        if self.last_attempt.is_some_and(|at| now.duration_since(at) < JWKS_MIN_REFETCH) {
+            return false;
+        }
+        match &self.keys {
+            Some((fetched, keys)) =>
+                now.duration_since(*fetched) >= JWKS_TTL ||
+                    kid.is_some_and(|kid| !keys.iter().any(|key| key.kid.as_deref() == Some(kid))),
+            None => true,
+        }
+    }
+}
+
+static JWKS: Lazy<Mutex<JwksCache>> = Lazy::new(|| Mutex::new(JwksCache::default()));
+
+async fn fetch_jwks(issuer: &str) -> Result<Vec<Jwk>> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent("whois-server/1.0")
+        .build()?;
+    let discovery_url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
+    let discovery: Value = client.get(&discovery_url).send().await?.error_for_status()?.limited_json().await?;
+    let jwks_uri = discovery["jwks_uri"]
+        .as_str()
+        .ok_or_else(|| anyhow!("OIDC discovery document of {} has no jwks_uri", issuer))?;
+
+    #[derive(Deserialize)]
+    struct JwkSet {
+        keys: Vec<Jwk>,
+    }
+    let set: JwkSet = client.get(jwks_uri).send().await?.error_for_status()?.limited_json().await?;
+    Ok(set.keys)
+}
+
+/// Signing keys, refetched when stale or when `kid` is not among them, but
+/// at most once per `JWKS_MIN_REFETCH`
+async fn signing_keys(config: &OidcConfig, kid: Option<&str>) -> Result<Arc<Vec<Jwk>>> {
+    {
+        let mut cache = JWKS.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if !cache.fetch_due(kid, now) {
+            return cache
+                .keys
+                .as_ref()
+                .map(|(_, keys)| keys.clone())
+                .ok_or_else(|| anyhow!("OIDC signing keys of {} are not available", config.issuer));
+        }
+        cache.last_attempt = Some(now);
+    }
+    log_debug!("Fetching OIDC signing keys of {}", config.issuer);
+    let keys = Arc::new(fetch_jwks(&config.issuer).await?);
+    JWKS.lock().unwrap_or_else(|e| e.into_inner()).keys = Some((Instant::now(), keys.clone()));
+    Ok(keys)
+}
+
+/// Check a bearer token, `Ok(None)` when it is not acceptable
+pub(crate) async fn verify_token(config: &OidcConfig, token: &str) -> Result<Option<VerifiedToken>> {
+    let Some(header) = token.split('.').next().and_then(|header| decode_json(header).ok()) else {
+        return Ok(None);
+    };
+    let keys = signing_keys(config, header["kid"].as_str()).await?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(verify_with_keys(config, token, &keys, now))
+}
+
+fn decode_json(part: &str) -> Result<Value> {
+    Ok(serde_json::from_slice(&URL_SAFE_NO_PAD.decode(part)?)?)
+}
+
+fn verify_signature(key: &Jwk, alg: &str, message: &[u8], signature: &[u8]) -> bool {
+    let decode = |field: &Option<String>| field.as_deref().and_then(|value| URL_SAFE_NO_PAD.decode(value).ok());
+    match (alg, key.kty.as_str()) {
+        ("RS256", "RSA") => {
+            let (Some(n), Some(e)) = (decode(&key.n), decode(&key.e)) else {
+                return false;
+            };
+            signature::RsaPublicKeyComponents { n, e }
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+                .is_ok()
+        }
+        ("ES256", "EC") if key.crv.as_deref() == Some("P-256") => {
+            let (Some(x), Some(y)) = (decode(&key.x), decode(&key.y)) else {
+                return false;
+            };
+            let point = [&[0x04][..], &x, &y].concat();
+            UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                .verify(message, signature)
+                .is_ok()
+        }
+        _ => false,
+    }
+}
+
+fn verify_with_keys(config: &OidcConfig, token: &str, keys: &[Jwk], now: u64) -> Option<VerifiedToken> {
+    let (message, signature) = token.rsplit_once('.')?;
+    let (header, payload) = message.split_once('.')?;
+    let header = decode_json(header).ok()?;
+    let claims = decode_json(payload).ok()?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+
+    // Only asymmetric algorithms, never "none" or a shared secret
+    let alg = header["alg"].as_str()?;
+    let kid = header["kid"].as_str();
+    let signed = keys
+        .iter()
+        .filter(|key| kid.is_none() || key.kid.as_deref() == kid)
+        .any(|key| verify_signature(key, alg, message.as_bytes(), &signature));
+    if !signed {
+        log_debug!("OIDC token signature not valid for any {} key", alg);
+        return None;
+    }
+
+    if claims["iss"].as_str()?.trim_end_matches('/') != config.issuer.trim_end_matches('/') {
+        return None;
+    }
+    let audience_ok = match &claims["aud"] {
+        Value::String(aud) => aud == &config.audience,
+        Value::Array(auds) => auds.iter().any(|aud| aud.as_str() == Some(config.audience.as_str())),
+        _ => false,
+    };
+    if !audience_ok {
+        return None;
+    }
+    if now > claims["exp"].as_u64()? + LEEWAY {
+        return None;
+    }
+    if let Some(nbf) = claims["nbf"].as_u64()
+        && now + LEEWAY < nbf
+    {
+        return None;
+    }
+
+    let username = claims[config.username_claim.as_str()].as_str().or_else(|| claims["sub"].as_str())?;
+    let groups = match &claims[config.groups_claim.as_str()] {
+        Value::Array(groups) => groups.iter().filter_map(|group| group.as_str().map(str::to_string)).collect(),
+        Value::String(group) => vec![group.clone()],
+        _ => Vec::new(),
+    };
+    Some(VerifiedToken { username: username.to_string(), groups })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair};
+    use serde_json::json;
+
+    #[test]
+    fn test_es256_token() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let point = pair.public_key().as_ref();
+        let key = Jwk {
+            kty: "EC".to_string(),
+            kid: Some("k1".to_string()),
+            n: None,
+            e: None,
+            crv: Some("P-256".to_string()),
+            x: Some(URL_SAFE_NO_PAD.encode(&point[1..33])),
+            y: Some(URL_SAFE_NO_PAD.encode(&point[33..])),
+        };
+        let config = OidcConfig {
+            issuer: "https://sso.example.net/realms/noc".to_string(),
+            audience: "whois".to_string(),
+            username_claim: default_username_claim(),
+            groups_claim: default_groups_claim(),
+        };
+        let sign = |header: Value, claims: Value| {
+            let message = format!(
+                "{}.{}",
+                URL_SAFE_NO_PAD.encode(header.to_string()),
+                URL_SAFE_NO_PAD.encode(claims.to_string())
+            );
+            let signature = pair.sign(&rng, message.as_bytes()).unwrap();
+            format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+        };
+        let header = json!({"alg": "ES256", "kid": "k1"});
+        let claims = json!({
+            "iss": "https://sso.example.net/realms/noc/",
+            "aud": ["account", "whois"],
+            "exp": 2000,
+            "preferred_username": "alice",
+            "groups": ["noc", "staff"],
+        });
+
+        let token = sign(header.clone(), claims.clone());
+        assert_eq!(
+            verify_with_keys(&config, &token, std::slice::from_ref(&key), 1000),
+            Some(VerifiedToken { username: "alice".to_string(), groups: vec!["noc".to_string(), "staff".to_string()] })
+        );
+        // Expired, tampered, unsigned and foreign-audience tokens
+        assert_eq!(verify_with_keys(&config, &token, std::slice::from_ref(&key), 2100), None);
+        let (message, signature) = token.rsplit_once('.').unwrap();
+        let tampered = format!("{}x.{}", message, signature);
+        assert_eq!(verify_with_keys(&config, &tampered, std::slice::from_ref(&key), 1000), None);
+        let unsigned = format!("{}.", sign(json!({"alg": "none"}), claims.clone()).rsplit_once('.').unwrap().0);
+        assert_eq!(verify_with_keys(&config, &unsigned, std::slice::from_ref(&key), 1000), None);
+        let mut other = claims.clone();
+        other["aud"] = json!("grafana");
+        assert_eq!(verify_with_keys(&config, &sign(header, other), &[key], 1000), None);
+    }
+
+    #[test]
+    fn test_jwks_refetch_is_rate_limited() {
+        let now = Instant::now();
+        let key = |kid: &str| Jwk {
+            kty: "EC".to_string(),
+            kid: Some(kid.to_string()),
+            n: None,
+            e: None,
+            crv: None,
+            x: None,
+            y: None,
+        };
+        let mut cache = JwksCache::default();
+        assert!(cache.fetch_due(None, now));
+
+        cache.keys = Some((now, Arc::new(vec![key("k1")])));
+        cache.last_attempt = Some(now);
+        assert!(!cache.fetch_due(Some("k1"), now));
+        // Unknown key IDs refetch only once the interval has passed
+        assert!(!cache.fetch_due(Some("forged"), now + Duration::from_secs(1)));
+        assert!(cache.fetch_due(Some("forged"), now + JWKS_MIN_REFETCH));
+        assert!(!cache.fetch_due(Some("k1"), now + JWKS_MIN_REFETCH));
+        assert!(cache.fetch_due(Some("k1"), now + JWKS_TTL));
+    }
+}
@@ -0,0 +1,154 @@
+// WHOIS Server - Login Throttling
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Cache of recent LDAP logins and throttling of failed ones
+//!
+//! A successful bind is remembered for a minute under a salted hash of the
+//! credentials, so clients that send Basic credentials with every request do
+//! not bind once per request. Failed logins are counted per user and per
+//! client address; once either reaches its limit, further attempts are
+//! refused without asking the directory until the window has passed.
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::AuthenticatedUser;
+
+/// How long a successful login is reused
+const SUCCESS_TTL: Duration = Duration::from_secs(60);
+/// Window in which failed logins are counted
+const FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+/// Failed logins for one user name before it is locked for the window
+const MAX_USER_FAILURES: u32 = 5;
+/// Failed logins from one client address before it is locked for the window
+const MAX_CLIENT_FAILURES: u32 = 20;
+/// Upper bound on remembered logins and failure counters
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug)]
+pub(crate) struct LoginGuard {
+    salt: [u8; 32],
+    successes: Mutex<HashMap<[u8; 32], (AuthenticatedUser, Instant)>>,
+    /// Failure count and start of its window, keyed by `user:` or `client:`
+    failures: Mutex<HashMap<String, (u32, Instant)>>,
+}
+
+impl Default for LoginGuard {
+    fn default() -> Self {
+        let mut salt = [0; 32];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self { salt, successes: Mutex::default(), failures: Mutex::default() }
+    }
+}
+
+fn user_key(user: &str) -> String {
+    format!("user:{}", user.to_ascii_lowercase())
+}
+
+fn client_key(client: IpAddr) -> String {
+    format!("client:{}", client)
+}
+
+impl LoginGuard {
+    fn credentials_hash(&self, user: &str, password: &str) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt);
+        hasher.update(user.as_bytes());
+        hasher.update([0]);
+        hasher.update(password.as_bytes());
+        hasher.finalize().into()
+    }
+
+    /// The user of a login that succeeded with these credentials less than
+    /// `SUCCESS_TTL` ago
+    pub(crate) fn cached(&self, user: &str, password: &str, now: Instant) -> Option<AuthenticatedUser> {
+        let successes = self.successes.lock().unwrap_or_else(|e| e.into_inner());
+        successes
+            .get(&self.credentials_hash(user, password))
+            .filter(|(_, at)| now.duration_since(*at) < SUCCESS_TTL)
+            .map(|(login, _)| login.clone())
+    }
+
+    /// Remember a successful login and forget the user's failures
+    pub(crate) fn succeeded(&self, user: &str, password: &str, login: &AuthenticatedUser, now: Instant) {
+        let mut successes = self.successes.lock().unwrap_or_else(|e| e.into_inner());
+        if successes.len() >= MAX_ENTRIES {
+            successes.retain(|_, (_, at)| now.duration_since(*at) < SUCCESS_TTL);
+        }
+        if successes.len() < MAX_ENTRIES {
+            successes.insert(self.credentials_hash(user, password), (login.clone(), now));
+        }
+        drop(successes);
+        self.failures.lock().unwrap_or_else(|e| e.into_inner()).remove(&user_key(user));
+    }
+
+    /// Count a failed login against the user and the client
+    pub(crate) fn failed(&self, user: &str, client: Option<IpAddr>, now: Instant) {
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        if failures.len() >= MAX_ENTRIES {
+            failures.retain(|_, (_, since)| now.duration_since(*since) < FAILURE_WINDOW);
+        }
+        for key in [Some(user_key(user)), client.map(client_key)].into_iter().flatten() {
+            let entry = failures.entry(key).or_insert((0, now));
+            if now.duration_since(entry.1) >= FAILURE_WINDOW {
+                *entry = (0, now);
+            }
+            entry.0 += 1;
+        }
+    }
+
+    /// Whether the user or the client failed too often to try again yet
+    pub(crate) fn blocked(&self, user: &str, client: Option<IpAddr>, now: Instant) -> bool {
+        let failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        let over = |key: String, limit: u32| {
+            failures.get(&key).is_some_and(|(count, since)| *count >= limit && now.duration_since(*since) < FAILURE_WINDOW)
+        };
+        over(user_key(user), MAX_USER_FAILURES) || client.is_some_and(|client| over(client_key(client), MAX_CLIENT_FAILURES))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Permission;
+
+    #[test]
+    fn test_successes_are_cached_briefly() {
+        let guard = LoginGuard::default();
+        let now = Instant::now();
+        let alice = AuthenticatedUser { name: "alice".to_string(), permission: Permission::Query };
+
+        assert_eq!(guard.cached("alice", "secret", now), None);
+        guard.succeeded("alice", "secret", &alice, now);
+        assert_eq!(guard.cached("alice", "secret", now + Duration::from_secs(30)), Some(alice));
+        assert_eq!(guard.cached("alice", "wrong", now), None);
+        assert_eq!(guard.cached("alice", "secret", now + SUCCESS_TTL), None);
+    }
+
+    #[test]
+    fn test_failures_block_user_and_client() {
+        let guard = LoginGuard::default();
+        let now = Instant::now();
+        let client: IpAddr = "192.0.2.7".parse().unwrap();
+
+        for _ in 0..MAX_USER_FAILURES {
+            assert!(!guard.blocked("bob", Some(client), now));
+            guard.failed("bob", Some(client), now);
+        }
+        assert!(guard.blocked("Bob", None, now));
+        assert!(!guard.blocked("carol", Some(client), now));
+        assert!(!guard.blocked("bob", None, now + FAILURE_WINDOW));
+
+        // Guessing across many user names is stopped by the client limit
+        for i in 0..MAX_CLIENT_FAILURES {
+            guard.failed(&format!("user{}", i), Some(client), now);
+        }
+        assert!(guard.blocked("carol", Some(client), now));
+        assert!(!guard.blocked("carol", Some("192.0.2.8".parse().unwrap()), now));
+    }
+}
@@ -0,0 +1,252 @@
+// WHOIS Server - Command-Line Client
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Bundled WHOIS client (`whois-server query`)
+//!
+//! Without `--server` the query is resolved in-process through the same
+//! pipeline the daemon uses, so every suffix works without a running server.
+//! With `--server` the query is sent over TCP, including the WHOIS-COLOR
+//! protocol header when a color scheme is requested. With `-B` the target is
+//! a file of queries, sent to a server as one batch in a single connection.
+
+use anyhow::{Context, Result, anyhow};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::config::{DEFAULT_WHOIS_PORT, QueryArgs, TIMEOUT_SECONDS};
+use crate::core::request_context::{Frontend, RequestContext};
+use crate::core::sanitize::sanitize_query;
+use crate::core::telemetry::query_type_to_string;
+use crate::core::{ColorScheme, analyze_query, process_query};
+use crate::server::connection::batch_separator;
+use crate::web::json_formatter::JsonFormatter;
+
+/// Remote queries may take as long as the slowest upstream behind the server
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(TIMEOUT_SECONDS * 6);
+
+/// Run the `query` subcommand
+pub async fn run_query_command(args: &QueryArgs) -> Result<()> {
+    let scheme = match &args.color {
+        Some(name) => Some(
+            ColorScheme::from_string(name)
+                .ok_or_else(|| anyhow!("Unknown color scheme '{}' ({})", name, ColorScheme::names()))?
+        ),
+        None => None,
+    };
+    if args.batch {
+        return run_batch(args, scheme).await;
+    }
+
+    let query = sanitize_query(&args.target);
+    if query.is_empty() {
+        return Err(anyhow!("Empty query"));
+    }
+
+    let start_time = Instant::now();
+    let result = match &args.server {
+        Some(server) => query_remote(server, &query, args.color.as_deref()).await,
+        None => query_local(&query, scheme).await,
+    };
+    let elapsed_ms = start_time.elapsed().as_millis() as u64;
+
+    if args.json {
+        let query_type = query_type_to_string(&analyze_query(&query));
+        let formatter = JsonFormatter::new();
+        let response = match &result {
+            Ok(output) => formatter.format_response(&query, output.clone(), &query_type, elapsed_ms),
+            Err(e) => formatter.format_error(&query, &e.to_string(), &query_type, elapsed_ms),
+        };
+        println!("{}", serde_json::to_string_pretty(&response)?);
+        return result.map(|_| ());
+    }
+
+    print_output(&result?);
+    Ok(())
+}
+
+fn print_output(output: &str) {
+    print!("{}", output.replace("\r\n", "\n"));
+    if !output.ends_with('\n') {
+        println!();
+    }
+}
+
+/// Queries of a batch file: one per line, `#` starts a comment line
+fn batch_queries(text: &str) -> Vec<String> {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .map(sanitize_query)
+        .filter(|query| !query.is_empty())
+        .collect()
+}
+
+/// Run every query of the batch file `args.target` (`-` for stdin) in order
+async fn run_batch(args: &QueryArgs, scheme: Option<ColorScheme>) -> Result<()> {
+    let text = if args.target == "-" {
+        let mut text = String::new();
+        tokio::io::stdin().read_to_string(&mut text).await?;
+        text
+    } else {
+        tokio::fs::read_to_string(&args.target)
+            .await
+            .with_context(|| format!("Failed to read batch file {}", args.target))?
+    };
+    let queries = batch_queries(&text);
+    if queries.is_empty() {
+        return Err(anyhow!("Batch contains no queries"));
+    }
+
+    // A server answers the whole batch over one connection
+    if let Some(server) = &args.server {
+        print_output(&query_remote_batch(server, &queries, args.color.as_deref()).await?);
+        return Ok(());
+    }
+
+    for (index, query) in queries.iter().enumerate() {
+        print!("{}", batch_separator(index + 1, queries.len(), query).replace("\r\n", "\n"));
+        match query_local(query, scheme.clone()).await {
+            Ok(output) => print_output(&output),
+            Err(e) => println!("% Error: {}\n", e),
+        }
+    }
+    Ok(())
+}
+
+/// Resolve a query in-process
+async fn query_local(query: &str, scheme: Option<ColorScheme>) -> Result<String> {
+    // Plugin suffixes are only known once plugins are loaded; do not create
+    // the plugins directory just for a one-off query
+    if std::path::Path::new("plugins").exists()
+        && let Ok(registry) = crate::plugins::load_all_plugins().await
+    {
+        crate::core::query::set_plugin_registry(Arc::new(registry));
+    }
+
+    let query_type = analyze_query(query);
+    let ctx = RequestContext::new(Frontend::Library).with_color(scheme);
+    process_query(query, &query_type, &ctx).await
+}
+
+/// Add the default WHOIS port if `server` has none
+fn server_address(server: &str) -> String {
+    let has_port = match server.rsplit_once(':') {
+        // Bracketed IPv6 literal, with or without a port
+        _ if server.starts_with('[') => server.contains("]:"),
+        // A bare IPv6 literal contains more than one colon and no port
+        Some((host, port)) => !host.contains(':') && port.parse::<u16>().is_ok(),
+        None => false,
+    };
+
+    if has_port {
+        server.to_string()
+    } else if server.contains(':') && !server.starts_with('[') {
+        format!("[{}]:{}", server, DEFAULT_WHOIS_PORT)
+    } else {
+        format!("{}:{}", server, DEFAULT_WHOIS_PORT)
+    }
+}
+
+/// Build the request line(s), with a WHOIS-COLOR header when a scheme is requested
+fn build_request(query: &str, color: Option<&str>) -> String {
+    match color {
+        Some(scheme) => format!("X-WHOIS-COLOR: {}\r\n{}\r\n", scheme.to_lowercase(), query),
+        None => format!("{}\r\n", query),
+    }
+}
+
+/// Build a batch request: the batch header, one query per line and an empty line
+fn build_batch_request(queries: &[String], color: Option<&str>) -> String {
+    let mut request = String::from("X-WHOIS-BATCH: on\r\n");
+    if let Some(scheme) = color {
+        request.push_str(&format!("X-WHOIS-COLOR: {}\r\n", scheme.to_lowercase()));
+    }
+    for query in queries {
+        request.push_str(query);
+        request.push_str("\r\n");
+    }
+    request.push_str("\r\n");
+    request
+}
+
+/// Send a query to a WHOIS server and read the full response
+async fn query_remote(server: &str, query: &str, color: Option<&str>) -> Result<String> {
+    send_request(server, build_request(query, color)).await
+}
+
+/// Send several queries to a WHOIS server in one connection
+async fn query_remote_batch(server: &str, queries: &[String], color: Option<&str>) -> Result<String> {
+    send_request(server, build_batch_request(queries, color)).await
+}
+
+/// Send a request and read the full response
+async fn send_request(server: &str, request: String) -> Result<String> {
+    let address = server_address(server);
+    let exchange = async {
+        let mut stream = TcpStream::connect(&address)
+            .await
+            .with_context(|| format!("Failed to connect to {}", address))?;
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok::<_, anyhow::Error>(String::from_utf8_lossy(&response).into_owned())
+    };
+
+    tokio::time::timeout(REMOTE_TIMEOUT, exchange)
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for {}", address))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_address_adds_default_port() {
+        assert_eq!(server_address("whois.akae.re"), "whois.akae.re:43");
+        assert_eq!(server_address("127.0.0.1:4343"), "127.0.0.1:4343");
+        assert_eq!(server_address("2001:db8::1"), "[2001:db8::1]:43");
+        assert_eq!(server_address("[2001:db8::1]"), "[2001:db8::1]:43");
+        assert_eq!(server_address("[2001:db8::1]:4343"), "[2001:db8::1]:4343");
+    }
+
+    #[test]
+    fn test_build_request_with_color_header() {
+        assert_eq!(build_request("example.com", None), "example.com\r\n");
+        assert_eq!(
+            build_request("example.com", Some("RIPE")),
+            "X-WHOIS-COLOR: ripe\r\nexample.com\r\n"
+        );
+    }
+
+    #[test]
+    fn test_batch_request() {
+        let queries = batch_queries("# resources\nexample.com\n\n  AS13335\r\n");
+        assert_eq!(queries, vec!["example.com", "AS13335"]);
+        assert_eq!(
+            build_batch_request(&queries, Some("RIPE")),
+            "X-WHOIS-BATCH: on\r\nX-WHOIS-COLOR: ripe\r\nexample.com\r\nAS13335\r\n\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_remote_sends_color_header() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = [0u8; 256];
+            let n = socket.read(&mut buffer).await.unwrap();
+            socket.write_all(b"% test\r\naut-num: AS13335\r\n").await.unwrap();
+            String::from_utf8_lossy(&buffer[..n]).into_owned()
+        });
+
+        let response = query_remote(&address, "AS13335", Some("ripe")).await.unwrap();
+        assert_eq!(response, "% test\r\naut-num: AS13335\r\n");
+        assert_eq!(server.await.unwrap(), "X-WHOIS-COLOR: ripe\r\nAS13335\r\n");
+    }
+}
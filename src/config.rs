@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 // WHOIS server constants
 pub const DEFAULT_WHOIS_SERVER: &str = "whois.ripe.net";
@@ -19,6 +20,24 @@ pub const ICP_CACHE_TTL: u64 = 86400; // 1 day in seconds
 // Statistics LMDB configuration
 pub const STATS_LMDB_PATH: &str = "./cache/stats-lmdb";
 
+// Shared cache configuration (LMDB backend; see --cache-backend)
+pub const CACHE_LMDB_PATH: &str = "./cache/shared-cache-lmdb";
+
+// Service cache LMDB paths
+pub const PEN_LMDB_PATH: &str = "./cache/pen_cache";
+pub const IANA_LMDB_PATH: &str = "./cache/iana_cache";
+pub const MANRS_LMDB_PATH: &str = "./cache/manrs_lmdb";
+pub const PATCHES_LMDB_PATH: &str = "./cache/patches_cache";
+pub const REGISTRAR_LMDB_PATH: &str = "./cache/registrar_cache";
+pub const MONITOR_LMDB_PATH: &str = "./cache/monitor_state";
+pub const PRIVATE_REGISTRY_LMDB_PATH: &str = "./cache/private_registries";
+pub const VERIFY_LMDB_PATH: &str = "./cache/verified_resources";
+pub const DIGEST_LMDB_PATH: &str = "./cache/digest_state";
+pub const CRTWATCH_LMDB_PATH: &str = "./cache/crtwatch_state";
+pub const HISTORY_LMDB_PATH: &str = "./cache/whois_history";
+pub const USAGE_LMDB_PATH: &str = "./cache/usage_counts";
+pub const TLD_BOOTSTRAP_LMDB_PATH: &str = "./cache/tld_bootstrap";
+
 // Internet Routing Registry (IRR) servers
 pub const RADB_WHOIS_SERVER: &str = "whois.radb.net";
 pub const RADB_WHOIS_PORT: u16 = 43;
@@ -49,8 +68,10 @@ pub const TC_WHOIS_PORT: u16 = 43;
 pub const RIS_WHOIS_SERVER: &str = "riswhois.ripe.net";
 pub const RIS_WHOIS_PORT: u16 = 43;
 
-// Server identification banner
-pub const SERVER_BANNER: &str = "% Akaere NetWorks Whois Server";
+// Default response header (see --banner-file for the template variables)
+pub const DEFAULT_BANNER_TEMPLATE: &str = "% Akaere NetWorks Whois Server
+% The objects are in RPSL format
+% Please report any issues to noc@akae.re";
 
 // Pixiv image proxy configuration
 pub fn pixiv_proxy_enabled() -> bool {
@@ -60,6 +81,14 @@ pub fn pixiv_proxy_enabled() -> bool {
         .unwrap_or(false)
 }
 
+// GraphQL endpoint (/graphql) configuration
+pub fn graphql_enabled() -> bool {
+    std::env::var("GRAPHQL_ENABLED")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()
+        .unwrap_or(false)
+}
+
 // Private IP range definitions
 pub const PRIVATE_IPV4_RANGES: &[&str] = &[
     "10.0.0.0/8",      // RFC1918
@@ -104,10 +133,58 @@ pub struct Cli {
     #[arg(long, default_value_t = 100)]
     pub max_connections: usize,
 
-    /// Connection timeout in seconds
+    /// Connection timeout in seconds (deadline for reading the request and writing the response)
     #[arg(long, default_value_t = 10)]
     pub timeout: u64,
 
+    /// Maximum idle time in seconds between chunks of request data
+    #[arg(long, default_value_t = 5)]
+    pub idle_timeout: u64,
+
+    /// Maximum request size in bytes
+    #[arg(long, default_value_t = 1024)]
+    pub max_request_size: usize,
+
+    /// Maximum number of newline-separated queries answered in one connection
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    pub max_batch: usize,
+
+    /// Seconds a connection opened with X-WHOIS-KEEPALIVE waits for the next request (0 disables keep-alive)
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    pub keepalive_timeout: u64,
+
+    /// Connections per minute allowed from one client (0 disables rate limiting)
+    #[arg(long, value_name = "N", default_value_t = 60)]
+    pub rate_limit: u32,
+
+    /// Connections a client may open in a burst before the rate limit applies
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    pub rate_limit_burst: u32,
+
+    /// Prefix length IPv6 clients are grouped by for rate limiting
+    #[arg(long, value_name = "LEN", default_value_t = 64, value_parser = clap::value_parser!(u8).range(1..=128))]
+    pub rate_limit_ipv6_prefix: u8,
+
+    /// Answer clients over their rate limit slowly with watermarked decoy data instead of rejecting them
+    #[arg(long)]
+    pub tarpit: bool,
+
+    /// Delay between the lines of a tarpit response in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 2000)]
+    pub tarpit_delay: u64,
+
+    /// JSON-lines file tarpit sessions (client, query, watermark) are logged to
+    #[arg(long, value_name = "FILE", default_value = "tarpit.log")]
+    pub tarpit_log: PathBuf,
+
+    /// Largest upstream HTTP response body read for a query, in megabytes (larger ones are aborted)
+    #[arg(long, value_name = "MB", default_value_t = 32)]
+    pub max_upstream_body: u64,
+
+    /// Truncate plain-text responses longer than this many lines, continued with -MORE=<token> (0 disables)
+    #[arg(long, value_name = "LINES", default_value_t = 1000)]
+    pub truncate_lines: usize,
+
     /// Write raw queries and responses to files for debugging
     #[arg(long)]
     pub dump_traffic: bool,
@@ -116,6 +193,22 @@ pub struct Cli {
     #[arg(long, default_value = "dumps")]
     pub dump_dir: String,
 
+    /// Disk cap for the LMDB databases in ./cache, in megabytes; expired cache entries are purged above it (0: no cap)
+    #[arg(long, value_name = "MB", default_value_t = 0)]
+    pub max_lmdb_size: u64,
+
+    /// Disk cap for the DN42 registry checkout, in megabytes; it is garbage collected above it (0: no cap)
+    #[arg(long, value_name = "MB", default_value_t = 0)]
+    pub max_dn42_size: u64,
+
+    /// Disk cap for the SSH cache directory, in megabytes; the oldest saved exports are removed above it (0: no cap)
+    #[arg(long, value_name = "MB", default_value_t = 0)]
+    pub max_ssh_cache_size: u64,
+
+    /// Disk cap for traffic dumps, in megabytes; the oldest dumps are removed above it (0: no cap)
+    #[arg(long, value_name = "MB", default_value_t = 0)]
+    pub max_dump_size: u64,
+
     /// Web dashboard port
     #[arg(long, default_value_t = 9999)]
     pub web_port: u16,
@@ -135,4 +228,161 @@ pub struct Cli {
     /// SSH cache directory
     #[arg(long, default_value = "./cache/ssh")]
     pub ssh_cache_dir: String,
+
+    /// Disk space per SSH client for saved query results, in megabytes
+    #[arg(long, value_name = "MB", default_value_t = 10)]
+    pub ssh_export_quota: u64,
+
+    /// Hours to keep query results saved in SSH sessions
+    #[arg(long, value_name = "HOURS", default_value_t = 24)]
+    pub ssh_export_ttl: u64,
+
+    /// Keep the most popular queries warm by refreshing them in the background
+    #[arg(long)]
+    pub prefetch: bool,
+
+    /// Number of most popular queries to prefetch
+    #[arg(long, default_value_t = 50)]
+    pub prefetch_top: usize,
+
+    /// How often popular queries are re-ranked and refreshed, in seconds
+    #[arg(long, default_value_t = 60)]
+    pub prefetch_interval: u64,
+
+    /// Cache backend for shared responses, counters and locks (lmdb, redis)
+    #[arg(long, default_value = "lmdb")]
+    pub cache_backend: String,
+
+    /// Redis connection URL for the redis cache backend (e.g. redis://127.0.0.1/)
+    #[arg(long)]
+    pub redis_url: Option<String>,
+
+    /// File with the response header template (variables: {query}, {query_type}, {timestamp}, {server_id}, {version}, {sources})
+    #[arg(long, value_name = "FILE")]
+    pub banner_file: Option<PathBuf>,
+
+    /// File with the response footer template (same variables as --banner-file)
+    #[arg(long, value_name = "FILE")]
+    pub footer_file: Option<PathBuf>,
+
+    /// Identifier of this instance, available to banner templates as {server_id}
+    #[arg(long, default_value = "whois-server")]
+    pub server_id: String,
+
+    /// TOML file with suffix aliases, e.g. "-G" = "-GEO" under [aliases]
+    #[arg(long, value_name = "FILE")]
+    pub alias_file: Option<PathBuf>,
+
+    /// TOML file with tags and notes on ASNs, prefixes and domains, shown in responses and by -TAGGED
+    #[arg(long, value_name = "FILE")]
+    pub annotations_file: Option<PathBuf>,
+
+    /// TOML file requiring LDAP or OIDC logins on the web and SSH frontends
+    #[arg(long, value_name = "FILE")]
+    pub auth_file: Option<PathBuf>,
+
+    /// TOML file with query macros, e.g. AUDIT = ["{}", "{}-DNS"] under [macros]
+    #[arg(long, value_name = "FILE")]
+    pub macro_file: Option<PathBuf>,
+
+    /// TOML file with per-query-type timeouts and concurrency limits, e.g. [limits.TRACE] max_concurrent = 4
+    #[arg(long, value_name = "FILE")]
+    pub query_limits_file: Option<PathBuf>,
+
+    /// Append data sources, cache status and stage timings to every response (per query: X-WHOIS-DEBUG: 1 header)
+    #[arg(long)]
+    pub provenance: bool,
+
+    /// How -MC responses show sampled player names: show, mask (N***h) or hide
+    #[arg(long, value_name = "MODE", default_value = "show")]
+    pub minecraft_players: String,
+
+    /// Allow -TRACE@local from this server and use it by default instead of Globalping probes
+    #[arg(long)]
+    pub native_traceroute: bool,
+
+    /// Steam store region for prices when a query has no -CC=<region> (two-letter country code)
+    #[arg(long, value_name = "CC", default_value = "US")]
+    pub steam_region: String,
+
+    /// Mirror a sample of queries to this WHOIS server (HOST:PORT) and log response differences
+    #[arg(long, value_name = "HOST:PORT")]
+    pub shadow_server: Option<String>,
+
+    /// Percentage of queries mirrored to the shadow server
+    #[arg(long, default_value_t = 10.0)]
+    pub shadow_percent: f64,
+
+    /// Ed25519 key file for signing responses (-SIGNED / X-WHOIS-SIGNATURE: 1), created if missing
+    #[arg(long, value_name = "FILE")]
+    pub signing_key: Option<PathBuf>,
+
+    /// TOML file with ASNs to monitor for BGP neighbour and origin changes, alerting via webhooks
+    #[arg(long, value_name = "FILE")]
+    pub monitor_file: Option<PathBuf>,
+
+    /// TOML file with scheduled digests sent over the notification channels
+    #[arg(long, value_name = "FILE")]
+    pub digest_file: Option<PathBuf>,
+
+    /// Services to switch off, by name (see `whois-server usage`), e.g. imdb,steam
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    pub disable_services: Vec<String>,
+
+    /// Maintenance command to run instead of starting the server
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Show schema versions of the LMDB databases, or migrate, export and import them
+    Db(DbArgs),
+    /// Run a query and print the response, locally or against a running server
+    Query(QueryArgs),
+    /// Check connectivity, configuration and state directories, and print a report
+    Doctor,
+    /// Show how often each service was queried and which services never were
+    Usage,
+}
+
+#[derive(Args)]
+pub struct QueryArgs {
+    /// Query, including any suffix (e.g. example.com, AS13335, 1.1.1.0-GEO)
+    pub target: String,
+
+    /// Color scheme for the response (ripe, ripe-dark, bgptools, bgptools-dark)
+    #[arg(long, value_name = "SCHEME")]
+    pub color: Option<String>,
+
+    /// Send the query to a WHOIS server instead of resolving it locally
+    #[arg(long, value_name = "HOST:PORT")]
+    pub server: Option<String>,
+
+    /// Print the response as JSON, in the format of the web API
+    #[arg(long, conflicts_with = "color")]
+    pub json: bool,
+
+    /// Treat the target as a file with one query per line (`-` for stdin) and run them all
+    #[arg(short = 'B', long, conflicts_with = "json")]
+    pub batch: bool,
+}
+
+#[derive(Args)]
+pub struct DbArgs {
+    /// Export databases as JSON Lines files into this directory
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["import", "migrate"])]
+    pub export: Option<PathBuf>,
+
+    /// Import databases from JSON Lines files in this directory
+    #[arg(long, value_name = "DIR", conflicts_with = "migrate")]
+    pub import: Option<PathBuf>,
+
+    /// Migrate databases to the schema version of this build
+    #[arg(long)]
+    pub migrate: bool,
+
+    /// Only operate on these databases (default: all)
+    #[arg(long = "database", value_name = "NAME")]
+    pub databases: Vec<String>,
 }
@@ -0,0 +1,172 @@
+// WHOIS Server - Suffix Aliases
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Operator-defined aliases for query suffixes
+//!
+//! Aliases are loaded once at startup from a TOML file (`--alias-file`):
+//!
+//! ```toml
+//! [aliases]
+//! "-地理" = "-GEO"
+//! "-证书" = "-SSL"
+//! "-G" = "-GEO"
+//! ```
+//!
+//! They are resolved before query type detection, so `1.1.1.1-地理` behaves
+//! exactly like `1.1.1.1-GEO`. Only the trailing suffixes of a query are
+//! rewritten, which keeps aliases composable with output modifiers
+//! (`1.1.1.1-G-JSON`) without touching dashes inside the queried resource.
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::core::modifiers::is_modifier_suffix;
+
+/// A suffix alias, both sides including the leading dash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuffixAlias {
+    pub alias: String,
+    pub target: String,
+}
+
+#[derive(Deserialize)]
+struct AliasFile {
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+}
+
+static ALIASES: OnceLock<Vec<SuffixAlias>> = OnceLock::new();
+
+/// Install the aliases used for all queries
+pub fn init_aliases(aliases: Vec<SuffixAlias>) {
+    let _ = ALIASES.set(aliases);
+}
+
+/// Configured aliases, sorted by alias
+pub fn aliases() -> &'static [SuffixAlias] {
+    ALIASES.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn validate(alias: &str, target: &str) -> Result<()> {
+    let name = alias.strip_prefix('-').ok_or_else(|| anyhow!("alias '{}' must start with '-'", alias))?;
+    if name.is_empty() || name.contains('-') || name.chars().any(char::is_whitespace) {
+        return Err(anyhow!("alias '{}' must be a single suffix without spaces or further dashes", alias));
+    }
+
+    let target_name = target
+        .strip_prefix('-')
+        .ok_or_else(|| anyhow!("target '{}' of alias '{}' must start with '-'", target, alias))?;
+    if target_name.is_empty() || target_name.chars().any(char::is_whitespace) {
+        return Err(anyhow!("target '{}' of alias '{}' is not a valid suffix", target, alias));
+    }
+    Ok(())
+}
+
+/// Parse alias definitions from TOML
+pub fn parse_aliases(content: &str) -> Result<Vec<SuffixAlias>> {
+    let file: AliasFile = toml::from_str(content)?;
+
+    let mut aliases = Vec::with_capacity(file.aliases.len());
+    for (alias, target) in file.aliases {
+        validate(&alias, &target)?;
+        aliases.push(SuffixAlias { alias, target });
+    }
+
+    // Aliases are applied once, so chains would silently stop halfway
+    for entry in &aliases {
+        if aliases.iter().any(|a| a.alias.to_uppercase() == entry.target.to_uppercase()) {
+            return Err(anyhow!("alias '{}' points to another alias ('{}')", entry.alias, entry.target));
+        }
+    }
+    Ok(aliases)
+}
+
+/// Load alias definitions from a TOML file
+pub fn load_alias_file(path: &Path) -> Result<Vec<SuffixAlias>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read alias file {}: {}", path.display(), e))?;
+    parse_aliases(&content).map_err(|e| anyhow!("Invalid alias file {}: {}", path.display(), e))
+}
+
+fn resolve_with<'a>(aliases: &[SuffixAlias], query: &'a str) -> Cow<'a, str> {
+    if aliases.is_empty() {
+        return Cow::Borrowed(query);
+    }
+
+    // Walk the trailing suffixes from the end, rewriting aliases and passing
+    // over output modifiers, until the first suffix that is neither
+    let mut base = query;
+    let mut suffixes: Vec<&str> = Vec::new();
+    let mut changed = false;
+    while let Some((rest, suffix)) = base.rsplit_once('-') {
+        if rest.is_empty() {
+            break;
+        }
+        let upper = suffix.to_uppercase();
+        if let Some(entry) = aliases.iter().find(|a| a.alias[1..].to_uppercase() == upper) {
+            suffixes.push(&entry.target[1..]);
+            changed = true;
+        } else if is_modifier_suffix(suffix) {
+            suffixes.push(suffix);
+        } else {
+            break;
+        }
+        base = rest;
+    }
+
+    if !changed {
+        return Cow::Borrowed(query);
+    }
+
+    let mut resolved = base.to_string();
+    for suffix in suffixes.iter().rev() {
+        resolved.push('-');
+        resolved.push_str(suffix);
+    }
+    Cow::Owned(resolved)
+}
+
+/// Rewrite aliased suffixes of a query to their targets
+pub fn resolve_aliases(query: &str) -> Cow<'_, str> {
+    resolve_with(aliases(), query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<SuffixAlias> {
+        parse_aliases("[aliases]\n\"-地理\" = \"-GEO\"\n\"-G\" = \"-GEO\"\n\"-证书\" = \"-SSL\"\n").unwrap()
+    }
+
+    #[test]
+    fn test_resolve_trailing_alias() {
+        let aliases = sample();
+        assert_eq!(resolve_with(&aliases, "1.1.1.1-地理"), "1.1.1.1-GEO");
+        assert_eq!(resolve_with(&aliases, "example.com-证书"), "example.com-SSL");
+        assert_eq!(resolve_with(&aliases, "1.1.1.1-g-JSON"), "1.1.1.1-GEO-JSON");
+        assert!(matches!(resolve_with(&aliases, "AS13335"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_inner_dashes_are_untouched() {
+        let aliases = sample();
+        // Only trailing suffixes are rewritten
+        assert_eq!(resolve_with(&aliases, "my-g-site.com"), "my-g-site.com");
+        assert_eq!(resolve_with(&aliases, "AS-G-RADB"), "AS-G-RADB");
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        assert!(parse_aliases("[aliases]\n\"G\" = \"-GEO\"\n").is_err());
+        assert!(parse_aliases("[aliases]\n\"-A-B\" = \"-GEO\"\n").is_err());
+        assert!(parse_aliases("[aliases]\n\"-G\" = \"GEO\"\n").is_err());
+        assert!(parse_aliases("[aliases]\n\"-G\" = \"-X\"\n\"-X\" = \"-GEO\"\n").is_err());
+        assert!(parse_aliases("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+// WHOIS Server - Usage Analytics
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Per-service usage counts and opt-in popularity reports
+//!
+//! Every processed query counts towards its service (the query type name
+//! used by metrics and telemetry). Counts are kept in memory and merged into
+//! LMDB every few minutes, so `whois-server usage` can show how often each
+//! service was used and which registered services never were; those can be
+//! switched off with `--disable-services`.
+//!
+//! Reporting is opt-in: only when `USAGE_REPORT_URL` is set are the counts of
+//! the past day POSTed there once a day, as
+//! `{"version": "...", "period_hours": 24, "counts": {"geo": 12, ...}}`.
+//! Nothing else is sent: no queries, client addresses or instance names, and
+//! plugin queries only count as `plugin`.
+
+use anyhow::Result;
+use chrono::DateTime;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::USAGE_LMDB_PATH;
+use crate::core::QueryType;
+use crate::core::scheduler::{Job, Schedule};
+use crate::core::telemetry::query_type_to_string;
+use crate::storage::lmdb::LmdbStorage;
+use crate::{log_debug, log_info};
+
+/// How often in-memory counts are merged into LMDB
+const FLUSH_INTERVAL: Duration = Duration::from_secs(300);
+/// How often counts are reported when reporting is enabled
+const REPORT_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Queries per service not yet merged into LMDB
+static PENDING: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+/// Queries per service not yet reported
+static PERIOD: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Stored usage of one service
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageCount {
+    pub total: u64,
+    /// Unix time of the last merge that included a query
+    pub last_used: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time should be after Unix epoch")
+        .as_secs()
+}
+
+/// Count a processed query
+pub fn record_usage(query_type: &QueryType) {
+    *PENDING.lock().unwrap().entry(query_type_to_string(query_type)).or_default() += 1;
+}
+
+fn take_pending() -> HashMap<String, u64> {
+    std::mem::take(&mut *PENDING.lock().unwrap())
+}
+
+/// Add `counts` to the stored totals
+fn merge(storage: &LmdbStorage, counts: &HashMap<String, u64>, now: u64) -> Result<()> {
+    for (service, count) in counts {
+        let key = format!("usage:{}", service);
+        let mut usage: UsageCount = storage.get_json(&key)?.unwrap_or_default();
+        usage.total += count;
+        usage.last_used = now;
+        storage.put_json(&key, &usage)?;
+    }
+    Ok(())
+}
+
+/// Stored usage of every service that was queried at least once
+pub fn load_usage(storage: &LmdbStorage) -> Result<BTreeMap<String, UsageCount>> {
+    let mut usage = BTreeMap::new();
+    for key in storage.get_keys_with_prefix("usage:")? {
+        if let Some(count) = storage.get_json::<UsageCount>(&key)? {
+            usage.insert(key.trim_start_matches("usage:").to_string(), count);
+        }
+    }
+    Ok(usage)
+}
+
+fn report_payload(counts: &HashMap<String, u64>, period: Duration) -> Value {
+    let counts: BTreeMap<&String, &u64> = counts.iter().collect();
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "period_hours": period.as_secs() / 3600,
+        "counts": counts,
+    })
+}
+
+async fn send_report(url: &str, counts: &HashMap<String, u64>, period: Duration) -> Result<()> {
+    let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).user_agent("whois-server/1.0").build()?;
+    client.post(url).json(&report_payload(counts, period)).send().await?.error_for_status()?;
+    Ok(())
+}
+
+/// Merge pending counts into LMDB, adding them to the report period when reporting
+fn flush(reporting: bool) -> Result<()> {
+    let counts = take_pending();
+    if counts.is_empty() {
+        return Ok(());
+    }
+    if reporting {
+        let mut period = PERIOD.lock().unwrap();
+        for (service, count) in &counts {
+            *period.entry(service.clone()).or_default() += count;
+        }
+    }
+    merge(&LmdbStorage::new(USAGE_LMDB_PATH)?, &counts, now_secs())
+}
+
+/// Send the counts of the past period; they are kept for the next attempt on failure
+async fn report(url: &str) -> Result<()> {
+    let counts = PERIOD.lock().unwrap().clone();
+    send_report(url, &counts, REPORT_INTERVAL).await?;
+    let mut period = PERIOD.lock().unwrap();
+    for (service, sent) in &counts {
+        if let Some(count) = period.get_mut(service) {
+            *count = count.saturating_sub(*sent);
+        }
+    }
+    period.retain(|_, count| *count > 0);
+    log_debug!("Reported usage of {} services", counts.len());
+    Ok(())
+}
+
+/// The `usage-flush` job and, when `USAGE_REPORT_URL` is set, the daily `usage-report` job
+pub fn usage_jobs() -> Vec<Job> {
+    let _ = dotenv::dotenv();
+    let report_url = std::env::var("USAGE_REPORT_URL").ok().map(|url| url.trim().to_string()).filter(|url| !url.is_empty());
+    let reporting = report_url.is_some();
+    let mut jobs = vec![Job::new("usage-flush", Schedule::Every(FLUSH_INTERVAL), move || async move { flush(reporting) })];
+    if let Some(url) = report_url {
+        log_info!("Reporting anonymized per-service usage counts to {} daily", url);
+        let url = Arc::new(url);
+        jobs.push(
+            Job::new("usage-report", Schedule::Every(REPORT_INTERVAL), move || {
+                let url = url.clone();
+                async move { report(&url).await }
+            })
+            .with_jitter(Duration::from_secs(3600)),
+        );
+    }
+    jobs
+}
+
+fn format_date(secs: u64) -> String {
+    DateTime::from_timestamp(secs as i64, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+/// Usage table, most used first, and the registered services never used
+pub fn format_usage_report(usage: &BTreeMap<String, UsageCount>, registered: &[&str]) -> String {
+    let mut output = String::from("Service usage since counting started\n\n");
+    let mut used: Vec<(&String, &UsageCount)> = usage.iter().collect();
+    used.sort_by(|a, b| b.1.total.cmp(&a.1.total).then(a.0.cmp(b.0)));
+    if used.is_empty() {
+        output.push_str("No queries counted yet\n");
+    } else {
+        output.push_str(&format!("{:<28} {:>10}  {}\n", "SERVICE", "QUERIES", "LAST USED"));
+        for (service, count) in used {
+            output.push_str(&format!("{:<28} {:>10}  {}\n", service, count.total, format_date(count.last_used)));
+        }
+    }
+
+    let unused: Vec<&str> = registered.iter().copied().filter(|name| !usage.contains_key(*name)).collect();
+    if !unused.is_empty() {
+        output.push_str(&format!("\nNever used ({}), can be switched off with --disable-services:\n", unused.len()));
+        for name in &unused {
+            output.push_str(&format!("  {}\n", name));
+        }
+        output.push_str(&format!("\n  --disable-services {}\n", unused.join(",")));
+    }
+    output
+}
+
+/// `whois-server usage`: print the stored usage counts
+pub fn run_usage_command() -> Result<()> {
+    let registry = crate::services::registry::builtin_registry()?;
+    let registered: Vec<&str> = registry.names().collect();
+    let storage = LmdbStorage::new(USAGE_LMDB_PATH)?;
+    print!("{}", format_usage_report(&load_usage(&storage)?, &registered));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_merge_and_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LmdbStorage::new(dir.path().to_str().unwrap()).unwrap();
+        let counts = |pairs: &[(&str, u64)]| pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect::<HashMap<_, _>>();
+        merge(&storage, &counts(&[("geo", 2), ("domain", 5)]), 1_700_000_000).unwrap();
+        merge(&storage, &counts(&[("geo", 1)]), 1_700_003_600).unwrap();
+
+        let usage = load_usage(&storage).unwrap();
+        assert_eq!(usage["geo"], UsageCount { total: 3, last_used: 1_700_003_600 });
+
+        let report = format_usage_report(&usage, &["geo", "hibp", "cargo"]);
+        let rows: Vec<&str> = report.lines().skip(3).take(2).collect();
+        assert_eq!(rows[0].split_whitespace().take(2).collect::<Vec<_>>(), ["domain", "5"]);
+        assert_eq!(rows[1].split_whitespace().take(2).collect::<Vec<_>>(), ["geo", "3"]);
+        assert!(report.ends_with("  --disable-services hibp,cargo\n"));
+
+        let payload = report_payload(&counts(&[("geo", 3)]), REPORT_INTERVAL);
+        assert_eq!(payload["period_hours"], 24);
+        assert_eq!(payload["counts"], json!({"geo": 3}));
+    }
+}
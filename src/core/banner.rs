@@ -0,0 +1,159 @@
+// WHOIS Server - Response Banner Templates
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Greeting banner and footer around WHOIS responses
+//!
+//! The header and footer are templates configured once at startup (see
+//! `--banner-file` / `--footer-file`) so operators can brand their instance.
+//! Templates may use these variables:
+//!
+//! - `{query}`: the query as received
+//! - `{query_type}`: detected query type (e.g. `domain`, `asn`, `geo`)
+//! - `{timestamp}`: response time in RFC 3339 (UTC)
+//! - `{server_id}`: identifier set with `--server-id`
+//! - `{version}`: server version
+//! - `{sources}`: data sources used for the response
+
+use chrono::{SecondsFormat, Utc};
+use std::sync::OnceLock;
+
+use crate::config::DEFAULT_BANNER_TEMPLATE;
+use crate::core::QueryType;
+use crate::core::telemetry::query_type_to_string;
+
+/// Banner settings, set once at startup
+#[derive(Debug, Clone)]
+pub struct BannerConfig {
+    pub header: String,
+    pub footer: String,
+    pub server_id: String,
+}
+
+impl Default for BannerConfig {
+    fn default() -> Self {
+        Self {
+            header: DEFAULT_BANNER_TEMPLATE.to_string(),
+            footer: String::new(),
+            server_id: "whois-server".to_string(),
+        }
+    }
+}
+
+static BANNER_CONFIG: OnceLock<BannerConfig> = OnceLock::new();
+
+/// Install the banner templates used for all responses
+pub fn init_banner(config: BannerConfig) {
+    let _ = BANNER_CONFIG.set(config);
+}
+
+fn config() -> &'static BannerConfig {
+    BANNER_CONFIG.get_or_init(BannerConfig::default)
+}
+
+/// Values available to banner templates for one response
+pub struct ResponseContext<'a> {
+    pub query: &'a str,
+    pub query_type: &'a QueryType,
+    pub sources: Vec<String>,
+}
+
+impl<'a> ResponseContext<'a> {
+    /// Context whose data source is the service handling the query type
+    pub fn new(query: &'a str, query_type: &'a QueryType) -> Self {
+        Self { query, query_type, sources: vec![query_type_to_string(query_type)] }
+    }
+}
+
+/// Substitute template variables
+fn render(template: &str, ctx: &ResponseContext, server_id: &str) -> String {
+    let sources = if ctx.sources.is_empty() { "-".to_string() } else { ctx.sources.join(", ") };
+
+    // The query is substituted last so client input cannot expand other variables
+    template
+        .replace("{query_type}", &query_type_to_string(ctx.query_type))
+        .replace("{timestamp}", &Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true))
+        .replace("{server_id}", server_id)
+        .replace("{version}", env!("CARGO_PKG_VERSION"))
+        .replace("{sources}", &sources)
+        .replace("{query}", ctx.query)
+}
+
+fn push_lines(out: &mut String, text: &str) {
+    for line in text.lines() {
+        out.push_str(line);
+        out.push_str("\r\n");
+    }
+}
+
+fn frame_with(config: &BannerConfig, body: &str, ctx: &ResponseContext) -> String {
+    let header = render(&config.header, ctx, &config.server_id);
+    let footer = render(&config.footer, ctx, &config.server_id);
+
+    let mut out = String::with_capacity(header.len() + body.len() + footer.len() + 8);
+    if !header.trim().is_empty() {
+        push_lines(&mut out, &header);
+        out.push_str("\r\n");
+    }
+
+    out.push_str(body);
+    // Ensure response ends with a CRLF
+    if !out.ends_with("\r\n") {
+        out.push_str("\r\n");
+    }
+
+    if !footer.trim().is_empty() {
+        out.push_str("\r\n");
+        push_lines(&mut out, &footer);
+    }
+
+    out
+}
+
+/// Wrap a response body in the configured header and footer
+pub fn frame_response(body: &str, ctx: &ResponseContext) -> String {
+    frame_with(config(), body, ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_banner() {
+        let query_type = QueryType::ASN("AS13335".to_string());
+        let ctx = ResponseContext::new("AS13335", &query_type);
+        let framed = frame_with(&BannerConfig::default(), "aut-num: AS13335", &ctx);
+        assert!(framed.starts_with("% Akaere NetWorks Whois Server\r\n"));
+        assert!(framed.ends_with("\r\n\r\naut-num: AS13335\r\n"));
+    }
+
+    #[test]
+    fn test_template_variables() {
+        let config = BannerConfig {
+            header: "% {server_id} v{version}\n% query {query} ({query_type})".to_string(),
+            footer: "% sources: {sources}".to_string(),
+            server_id: "edge-1".to_string(),
+        };
+        let query_type = QueryType::Domain("example.com".to_string());
+        let mut ctx = ResponseContext::new("example.com", &query_type);
+        ctx.sources = vec!["whois.verisign-grs.com".to_string()];
+
+        let framed = frame_with(&config, "domain: example.com\r\n", &ctx);
+        assert_eq!(
+            framed,
+            format!(
+                "% edge-1 v{}\r\n% query example.com (domain)\r\n\r\ndomain: example.com\r\n\r\n% sources: whois.verisign-grs.com\r\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+
+    #[test]
+    fn test_empty_templates_leave_body_alone() {
+        let config = BannerConfig { header: String::new(), footer: String::new(), server_id: String::new() };
+        let query_type = QueryType::Help;
+        let ctx = ResponseContext::new("HELP", &query_type);
+        assert_eq!(frame_with(&config, "body", &ctx), "body\r\n");
+    }
+}
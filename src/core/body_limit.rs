@@ -0,0 +1,114 @@
+// WHOIS Server - Upstream Body Limit
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Size limit for upstream HTTP response bodies
+//!
+//! Every HTTP fetch reads its body through [`LimitedBody`] instead of
+//! reqwest's `json()`, `text()` and `bytes()`. The body is read chunk by chunk
+//! and the read is aborted as soon as it passes the limit, or before reading
+//! anything when `Content-Length` already announces more, so a malicious or
+//! misconfigured upstream cannot make the server buffer hundreds of megabytes
+//! for one query. The limit is set with `--max-upstream-body`; aborted reads
+//! are counted per host in `whois_upstream_body_truncations_total`.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use std::sync::OnceLock;
+
+use crate::core::metrics::record_body_truncation;
+use crate::log_warn;
+
+/// Limit used until `init_body_limit` is called, in megabytes
+pub const DEFAULT_MAX_UPSTREAM_BODY_MB: u64 = 32;
+
+const MEGABYTE: usize = 1024 * 1024;
+
+static BODY_LIMIT: OnceLock<usize> = OnceLock::new();
+
+/// Set the upstream body limit
+pub fn init_body_limit(megabytes: u64) {
+    let _ = BODY_LIMIT.set((megabytes as usize).saturating_mul(MEGABYTE));
+}
+
+/// Largest upstream body read, in bytes
+pub fn body_limit() -> usize {
+    BODY_LIMIT.get().copied().unwrap_or(DEFAULT_MAX_UPSTREAM_BODY_MB as usize * MEGABYTE)
+}
+
+fn limit_exceeded(host: &str, limit: usize) -> anyhow::Error {
+    record_body_truncation(host);
+    log_warn!("Aborted response from {}: body exceeds {} bytes", host, limit);
+    anyhow!("Response from {} is larger than the {} MB upstream body limit", host, limit.div_ceil(MEGABYTE))
+}
+
+/// Read a response body of at most `limit` bytes
+///
+/// For the few upstreams that legitimately send more than the global limit,
+/// such as whole RPKI exports; everything else uses [`LimitedBody`].
+pub async fn read_body(mut response: reqwest::Response, limit: usize) -> Result<Vec<u8>> {
+    let host = response.url().host_str().unwrap_or("unknown").to_string();
+    if response.content_length().is_some_and(|length| length > limit as u64) {
+        return Err(limit_exceeded(&host, limit));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(limit_exceeded(&host, limit));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Body readers of `reqwest::Response` that enforce the upstream body limit
+#[async_trait]
+pub trait LimitedBody {
+    async fn limited_bytes(self) -> Result<Vec<u8>>;
+
+    /// Body as text; invalid UTF-8 is replaced rather than rejected
+    async fn limited_text(self) -> Result<String>;
+
+    async fn limited_json<T: DeserializeOwned>(self) -> Result<T>;
+}
+
+#[async_trait]
+impl LimitedBody for reqwest::Response {
+    async fn limited_bytes(self) -> Result<Vec<u8>> {
+        read_body(self, body_limit()).await
+    }
+
+    async fn limited_text(self) -> Result<String> {
+        let body = self.limited_bytes().await?;
+        Ok(String::from_utf8(body).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()))
+    }
+
+    async fn limited_json<T: DeserializeOwned>(self) -> Result<T> {
+        let body = self.limited_bytes().await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_bodies_over_the_limit_are_aborted() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(4096)))
+            .mount(&server)
+            .await;
+
+        let fetch = || async { reqwest::get(server.uri()).await.unwrap() };
+        assert_eq!(read_body(fetch().await, 4096).await.unwrap().len(), 4096);
+        let error = read_body(fetch().await, 1024).await.unwrap_err();
+        assert!(error.to_string().contains("upstream body limit"), "{}", error);
+        assert_eq!(fetch().await.limited_text().await.unwrap().len(), 4096);
+    }
+}
@@ -18,7 +18,60 @@
 
 use crate::core::QueryType;
 use crate::core::color::scheme::ColorScheme;
-use regex::Regex;
+use crate::core::regex_cache::CachedRegex;
+use crate::services::expiry::Urgency;
+
+static URL_RE: CachedRegex = CachedRegex::new(r"(https?://[^\s]+)");
+static NUMBER_RE: CachedRegex = CachedRegex::new(r"(\d+)");
+// Store prices in any currency: $19.99, CDN$ 20.99, ¥ 1,980, 19,50€, Rp 108 999
+static PRICE_OR_FREE_RE: CachedRegex = CachedRegex::new(
+    r"((?:[A-Z]{0,3}\$|[€£¥₩₹₽₺₴₪₫฿]|CHF|Rp|RM|kr|zł) ?\d+(?:[.,' ]\d+)*|\d+(?:[.,' ]\d+)* ?(?:€|zł|kr|TL|₽|₴|₫)|Free)"
+);
+static DISCOUNT_RE: CachedRegex = CachedRegex::new(r"(\d+%↓)");
+static PRICE_RE: CachedRegex = CachedRegex::new(
+    r"((?:[A-Z]{0,3}\$|[€£¥₩₹₽₺₴₪₫฿]|CHF|Rp|RM|kr|zł) ?\d+(?:[.,' ]\d+)*|\d+(?:[.,' ]\d+)* ?(?:€|zł|kr|TL|₽|₴|₫))"
+);
+static MILLISECONDS_RE: CachedRegex = CachedRegex::new(r"(\d+)\s*ms");
+static IMDB_ID_RE: CachedRegex = CachedRegex::new(r"(tt\d+)");
+static YEAR_RE: CachedRegex = CachedRegex::new(r"(\d{4})");
+static BYTES_RE: CachedRegex = CachedRegex::new(r"(\d+)\s*bytes");
+static MEASUREMENT_RE: CachedRegex = CachedRegex::new(r"(\d+[\.\d]*\s*(cm|kg|m|ft|in))");
+static DURATION_RE: CachedRegex = CachedRegex::new(r"(\d+\s*min|\d+\s*hours?)");
+static SIGNED_MILLISECONDS_RE: CachedRegex = CachedRegex::new(r"(-?\d+\.?\d*)\s*ms");
+static ASN_RE: CachedRegex = CachedRegex::new(r"(AS\d+)");
+static IP_PREFIX_RE: CachedRegex = CachedRegex::new(
+    r"(\d+\.\d+\.\d+\.\d+(?:/\d+)?|[0-9a-fA-F:]+::[0-9a-fA-F:]*(?:/\d+)?)"
+);
+static DOMAIN_RE: CachedRegex = CachedRegex::new(
+    r"([a-zA-Z0-9]([a-zA-Z0-9\-]{0,61}[a-zA-Z0-9])?\.)+[a-zA-Z]{2,}"
+);
+static IPV4_RE: CachedRegex = CachedRegex::new(r"(\d+\.\d+\.\d+\.\d+)");
+static IPV6_RE: CachedRegex = CachedRegex::new(r"([0-9a-fA-F:]+::[0-9a-fA-F:]*)");
+static RATING_RE: CachedRegex = CachedRegex::new(r"(\d+\.\d+/10)");
+
+/// Patterns checked by `regex_cache::precompile_builtin_regexes` at startup
+pub(crate) fn builtin_regexes() -> Vec<&'static CachedRegex> {
+    vec![
+        &URL_RE,
+        &NUMBER_RE,
+        &PRICE_OR_FREE_RE,
+        &DISCOUNT_RE,
+        &PRICE_RE,
+        &MILLISECONDS_RE,
+        &IMDB_ID_RE,
+        &YEAR_RE,
+        &BYTES_RE,
+        &MEASUREMENT_RE,
+        &DURATION_RE,
+        &SIGNED_MILLISECONDS_RE,
+        &ASN_RE,
+        &IP_PREFIX_RE,
+        &DOMAIN_RE,
+        &IPV4_RE,
+        &IPV6_RE,
+        &RATING_RE,
+    ]
+}
 
 pub struct Colorizer {
     scheme: ColorScheme,
@@ -287,22 +340,21 @@ impl Colorizer {
             | "metacritic-url"
             | "wikipedia-url"
             | "edit-url" => {
-                let url_regex = Regex::new(r"(https?://[^\s]+)").expect("Invalid regex pattern");
+                let url_regex = &URL_RE;
                 let colored_value = url_regex.replace_all(value, "\x1b[4;94m$1\x1b[0m").to_string();
                 format!("\x1b[1;94m{}:\x1b[0m {}", attr, colored_value)
             }
             // Gaming specific
             "app-id" | "steamid" | "game-id" | "metacritic-score" => {
-                let id_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                let id_regex = &NUMBER_RE;
                 let colored_value = id_regex.replace_all(value, "\x1b[1;93m$1\x1b[0m").to_string();
                 format!("\x1b[1;93m{}:\x1b[0m {}", attr, colored_value)
             }
             "price" | "original-price" => {
-                if value.contains("(%↓)") || value.contains("Free") {
+                if value.contains("%↓)") || value.contains("Free") {
                     // Green for discounted games and free games
-                    let price_regex =
-                        Regex::new(r"(\$[\d,]+\.?\d*|Free)").expect("Invalid regex pattern");
-                    let discount_regex = Regex::new(r"(\d+%↓)").expect("Invalid regex pattern");
+                    let price_regex = &PRICE_OR_FREE_RE;
+                    let discount_regex = &DISCOUNT_RE;
                     let colored_value = price_regex
                         .replace_all(value, "\x1b[1;92m$1\x1b[0m")
                         .to_string();
@@ -312,8 +364,7 @@ impl Colorizer {
                     format!("\x1b[1;95m{}:\x1b[0m{}", attr, final_value)
                 } else {
                     // White for full-price games (no discount)
-                    let price_regex =
-                        Regex::new(r"(\$[\d,]+\.?\d*)").expect("Invalid regex pattern");
+                    let price_regex = &PRICE_RE;
                     let colored_value = price_regex
                         .replace_all(value, "\x1b[97m$1\x1b[0m")
                         .to_string();
@@ -321,14 +372,14 @@ impl Colorizer {
                 }
             }
             "players" | "players-online" | "max-players" => {
-                let player_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                let player_regex = &NUMBER_RE;
                 let colored_value = player_regex
                     .replace_all(value, "\x1b[1;95m$1\x1b[0m")
                     .to_string();
                 format!("\x1b[95m{}:\x1b[0m {}", attr, colored_value)
             }
             "latency" | "ping" | "round-trip" => {
-                let ms_regex = Regex::new(r"(\d+)\s*ms").expect("Invalid regex pattern");
+                let ms_regex = &MILLISECONDS_RE;
                 let colored_value = ms_regex
                     .replace_all(value, |caps: &regex::Captures| {
                         let ms: u32 = caps[1].parse().unwrap_or(0);
@@ -345,7 +396,7 @@ impl Colorizer {
             }
             // IMDb specific
             "imdb-id" | "tt-id" => {
-                let id_regex = Regex::new(r"(tt\d+)").expect("Invalid regex pattern");
+                let id_regex = &IMDB_ID_RE;
                 let colored_value = id_regex.replace_all(value, "\x1b[1;93m$1\x1b[0m").to_string();
                 format!("\x1b[1;93m{}:\x1b[0m {}", attr, colored_value)
             }
@@ -357,7 +408,7 @@ impl Colorizer {
                 }
             }
             "year" | "release-year" | "release-date" => {
-                let year_regex = Regex::new(r"(\d{4})").expect("Invalid regex pattern");
+                let year_regex = &YEAR_RE;
                 let colored_value = year_regex
                     .replace_all(value, "\x1b[1;93m$1\x1b[0m")
                     .to_string();
@@ -409,12 +460,12 @@ impl Colorizer {
             }
             // Wikipedia specific
             "page-id" | "article-id" => {
-                let id_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                let id_regex = &NUMBER_RE;
                 let colored_value = id_regex.replace_all(value, "\x1b[1;93m$1\x1b[0m").to_string();
                 format!("\x1b[1;93m{}:\x1b[0m {}", attr, colored_value)
             }
             "article-length" | "page-length" => {
-                let size_regex = Regex::new(r"(\d+)\s*bytes").expect("Invalid regex pattern");
+                let size_regex = &BYTES_RE;
                 let colored_value = size_regex
                     .replace_all(value, "\x1b[1;93m$1 bytes\x1b[0m")
                     .to_string();
@@ -432,12 +483,12 @@ impl Colorizer {
             }
             // Pixiv specific
             "artwork-id" | "illust-id" => {
-                let id_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                let id_regex = &NUMBER_RE;
                 let colored_value = id_regex.replace_all(value, "\x1b[1;94m$1\x1b[0m").to_string();
                 format!("\x1b[1;94m{}:\x1b[0m {}", attr, colored_value) // Bright blue
             }
             "user-id" | "artist-id" => {
-                let id_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                let id_regex = &NUMBER_RE;
                 let colored_value = id_regex.replace_all(value, "\x1b[1;95m$1\x1b[0m").to_string();
                 format!("\x1b[1;95m{}:\x1b[0m {}", attr, colored_value) // Bright magenta
             }
@@ -445,7 +496,7 @@ impl Colorizer {
                 format!("\x1b[1;96m{}:\x1b[0m \x1b[96m{}\x1b[0m", attr, value) // Bright cyan
             }
             "artwork-url" | "profile-url" => {
-                let url_regex = Regex::new(r"(https?://[^\s]+)").expect("Invalid regex pattern");
+                let url_regex = &URL_RE;
                 let colored_value = url_regex.replace_all(value, "\x1b[4;94m$1\x1b[0m").to_string();
                 format!("\x1b[1;94m{}:\x1b[0m {}", attr, colored_value)
             }
@@ -484,16 +535,14 @@ impl Colorizer {
                 format!("\x1b[93m{}:\x1b[0m \x1b[93m{}\x1b[0m", attr, value) // Yellow
             }
             "age" | "birthday" => {
-                let number_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                let number_regex = &NUMBER_RE;
                 let colored_value = number_regex
                     .replace_all(value, "\x1b[1;93m$1\x1b[0m")
                     .to_string();
                 format!("\x1b[95m{}:\x1b[0m {}", attr, colored_value)
             }
             "height" | "weight" | "bwh" => {
-                let measurement_regex = Regex::new(r"(\d+[\.\d]*\s*(cm|kg|m|ft|in))").expect(
-                    "Invalid regex pattern"
-                );
+                let measurement_regex = &MEASUREMENT_RE;
                 let colored_value = measurement_regex
                     .replace_all(value, "\x1b[1;92m$1\x1b[0m")
                     .to_string();
@@ -545,9 +594,7 @@ impl Colorizer {
                 format!("\x1b[96m{}:\x1b[0m \x1b[96m{}\x1b[0m", attr, value) // Cyan
             }
             "cooking-time" | "prep-time" => {
-                let time_regex = Regex::new(r"(\d+\s*min|\d+\s*hours?)").expect(
-                    "Invalid regex pattern"
-                );
+                let time_regex = &DURATION_RE;
                 let colored_value = time_regex.replace_all(value, "\x1b[93m$1\x1b[0m").to_string();
                 format!("\x1b[93m{}:\x1b[0m {}", attr, colored_value)
             }
@@ -566,7 +613,7 @@ impl Colorizer {
             }
             // NTP specific
             "stratum" => {
-                let stratum_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                let stratum_regex = &NUMBER_RE;
                 let colored_value = stratum_regex
                     .replace_all(value, |caps: &regex::Captures| {
                         let stratum: u32 = caps[1].parse().unwrap_or(16);
@@ -582,8 +629,7 @@ impl Colorizer {
                 format!("\x1b[95m{}:\x1b[0m {}", attr, colored_value)
             }
             "offset" | "root-delay" | "root-dispersion" => {
-                let offset_regex =
-                    Regex::new(r"(-?\d+\.?\d*)\s*ms").expect("Invalid regex pattern");
+                let offset_regex = &SIGNED_MILLISECONDS_RE;
                 let colored_value = offset_regex
                     .replace_all(value, |caps: &regex::Captures| {
                         let offset: f64 = caps[1].parse().unwrap_or(999.0);
@@ -602,6 +648,8 @@ impl Colorizer {
             "delay" | "reach" | "jitter" => {
                 format!("\x1b[93m{}:\x1b[0m \x1b[93m{}\x1b[0m", attr, value) // Yellow
             }
+            // Domain expiry
+            "days-left" | "urgency" => colorize_urgency(attr, value),
             // Default - rainbow gradient effect for unknown attributes
             _ => {
                 let hash = attr
@@ -625,13 +673,9 @@ impl Colorizer {
         let value = parts[1];
 
         // Apply regex patterns to value for network elements
-        let asn_regex = Regex::new(r"(AS\d+)").expect("Invalid regex pattern");
-        let ip_regex = Regex::new(
-            r"(\d+\.\d+\.\d+\.\d+(?:/\d+)?|[0-9a-fA-F:]+::[0-9a-fA-F:]*(?:/\d+)?)"
-        ).unwrap();
-        let domain_regex = Regex::new(
-            r"([a-zA-Z0-9]([a-zA-Z0-9\-]{0,61}[a-zA-Z0-9])?\.)+[a-zA-Z]{2,}"
-        ).unwrap();
+        let asn_regex = &ASN_RE;
+        let ip_regex = &IP_PREFIX_RE;
+        let domain_regex = &DOMAIN_RE;
 
         let asn_color = if bold_colors { "\x1b[93m" } else { "\x1b[93m" }; // Yellow
         let ip_color = if bold_colors { "\x1b[92m" } else { "\x1b[92m" }; // Green
@@ -698,6 +742,8 @@ impl Colorizer {
                     format!("\x1b[1;97m{}:\x1b[0m \x1b[1;97m{}\x1b[0m", attr, styled_value) // Bright white
                 }
             }
+            // Domain expiry
+            "days-left" | "urgency" => colorize_urgency(attr, value),
             // Dates - gray (non-allocation dates)
             "last-modified" | "expires" | "updated" => {
                 format!("\x1b[90m{}:\x1b[0m \x1b[90m{}\x1b[0m", attr, styled_value)
@@ -847,10 +893,8 @@ impl Colorizer {
                 }
             }
             QueryType::BGPTool(_) | QueryType::Prefixes(_) => {
-                let asn_regex = Regex::new(r"(AS\d+)").expect("Invalid regex pattern");
-                let ip_regex = Regex::new(
-                    r"(\d+\.\d+\.\d+\.\d+(?:/\d+)?|[0-9a-fA-F:]+::[0-9a-fA-F:]*(?:/\d+)?)"
-                ).unwrap();
+                let asn_regex = &ASN_RE;
+                let ip_regex = &IP_PREFIX_RE;
                 let mut result = asn_regex.replace_all(line, "\x1b[93m$1\x1b[0m").to_string();
                 result = ip_regex.replace_all(&result, "\x1b[92m$1\x1b[0m").to_string();
                 result
@@ -863,13 +907,10 @@ impl Colorizer {
                         format!("\x1b[1;96m{}\x1b[0m", line) // Bright cyan for light
                     }
                 } else if line.contains(" A ") && !line.contains("AAAA") {
-                    let ip_regex =
-                        Regex::new(r"(\d+\.\d+\.\d+\.\d+)").expect("Invalid regex pattern");
+                    let ip_regex = &IPV4_RE;
                     ip_regex.replace_all(line, "\x1b[92m$1\x1b[0m").to_string()
                 } else if line.contains(" AAAA ") {
-                    let ipv6_regex = Regex::new(r"([0-9a-fA-F:]+::[0-9a-fA-F:]*)").expect(
-                        "Invalid regex pattern"
-                    );
+                    let ipv6_regex = &IPV6_RE;
                     ipv6_regex.replace_all(line, "\x1b[92m$1\x1b[0m").to_string()
                 } else if line.contains(" CNAME ") || line.contains(" DNAME ") {
                     format!("\x1b[94m{}\x1b[0m", line) // Blue for aliases
@@ -916,7 +957,7 @@ impl Colorizer {
                     line.to_string()
                 }
             }
-            QueryType::Steam(_) | QueryType::SteamSearch(_) => {
+            QueryType::Steam(..) | QueryType::SteamSearch(..) => {
                 if
                     line.contains("Steam Application Information") ||
                     line.contains("Steam Game Search Results")
@@ -927,19 +968,17 @@ impl Colorizer {
                         format!("\x1b[1;96m{}\x1b[0m", line) // Bright cyan for light
                     }
                 } else if line.contains("price:") {
-                    if line.contains("(%↓)") || line.contains("Free") {
+                    if line.contains("%↓)") || line.contains("Free") {
                         // Green for discounted games and free games
-                        let price_regex =
-                            Regex::new(r"(\$[\d,]+\.?\d*|Free)").expect("Invalid regex pattern");
-                        let discount_regex = Regex::new(r"(\d+%↓)").expect("Invalid regex pattern");
+                        let price_regex = &PRICE_OR_FREE_RE;
+                        let discount_regex = &DISCOUNT_RE;
                         let colored = price_regex
                             .replace_all(line, "\x1b[1;92m$1\x1b[0m")
                             .to_string();
                         discount_regex.replace_all(&colored, "\x1b[1;92m$1\x1b[0m").to_string()
                     } else {
                         // White/Red for full-price games
-                        let price_regex =
-                            Regex::new(r"(\$[\d,]+\.?\d*)").expect("Invalid regex pattern");
+                        let price_regex = &PRICE_RE;
                         if bold_colors {
                             price_regex.replace_all(line, "\x1b[91m$1\x1b[0m").to_string() // Red for dark mode
                         } else {
@@ -966,7 +1005,7 @@ impl Colorizer {
                         format!("\x1b[1;96m{}\x1b[0m", line) // Bright cyan for light
                     }
                 } else if line.contains("imdb-rating:") {
-                    let rating_regex = Regex::new(r"(\d+\.\d+/10)").expect("Invalid regex pattern");
+                    let rating_regex = &RATING_RE;
                     if line.contains("8.") || line.contains("9.") {
                         rating_regex.replace_all(line, "\x1b[1;92m$1\x1b[0m").to_string() // Green for high ratings
                     } else if line.contains("7.") {
@@ -1011,10 +1050,10 @@ impl Colorizer {
                         format!("\x1b[1;91m{}\x1b[0m", line) // Bright red for offline
                     }
                 } else if line.contains("players:") || line.contains("Players:") {
-                    let player_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                    let player_regex = &NUMBER_RE;
                     player_regex.replace_all(line, "\x1b[1;95m$1\x1b[0m").to_string()
                 } else if line.contains("latency:") || line.contains("ms") {
-                    let ms_regex = Regex::new(r"(\d+)\s*ms").expect("Invalid regex pattern");
+                    let ms_regex = &MILLISECONDS_RE;
                     ms_regex
                         .replace_all(line, |caps: &regex::Captures| {
                             let ms: u32 = caps[1].parse().unwrap_or(0);
@@ -1051,7 +1090,7 @@ impl Colorizer {
                     line.contains("watchers:") ||
                     line.contains("forks:")
                 {
-                    let stats_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                    let stats_regex = &NUMBER_RE;
                     stats_regex.replace_all(line, "\x1b[1;95m$1\x1b[0m").to_string()
                 } else if line.starts_with("%") {
                     format!("{}{}\x1b[0m", comment_color, line)
@@ -1067,7 +1106,7 @@ impl Colorizer {
                         format!("\x1b[1;96m{}\x1b[0m", line) // Bright cyan for light
                     }
                 } else if line.contains("article-length:") {
-                    let size_regex = Regex::new(r"(\d+)\s*bytes").expect("Invalid regex pattern");
+                    let size_regex = &BYTES_RE;
                     size_regex.replace_all(line, "\x1b[1;93m$1 bytes\x1b[0m").to_string()
                 } else if line.starts_with("%") {
                     format!("{}{}\x1b[0m", comment_color, line)
@@ -1095,7 +1134,7 @@ impl Colorizer {
                     line.contains("likes:") ||
                     line.contains("bookmarks:")
                 {
-                    let stats_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                    let stats_regex = &NUMBER_RE;
                     stats_regex.replace_all(line, "\x1b[1;95m$1\x1b[0m").to_string()
                 } else if line.starts_with("%") {
                     format!("{}{}\x1b[0m", comment_color, line)
@@ -1178,7 +1217,7 @@ impl Colorizer {
                         format!("\x1b[1;96m{}\x1b[0m", line) // Bright cyan for light
                     }
                 } else if line.contains("stratum:") {
-                    let stratum_regex = Regex::new(r"(\d+)").expect("Invalid regex pattern");
+                    let stratum_regex = &NUMBER_RE;
                     stratum_regex
                         .replace_all(line, |caps: &regex::Captures| {
                             let stratum: u32 = caps[1].parse().unwrap_or(16);
@@ -1192,8 +1231,7 @@ impl Colorizer {
                         })
                         .to_string()
                 } else if line.contains("offset:") {
-                    let offset_regex =
-                        Regex::new(r"(-?\d+\.?\d*)\s*ms").expect("Invalid regex pattern");
+                    let offset_regex = &SIGNED_MILLISECONDS_RE;
                     offset_regex
                         .replace_all(line, |caps: &regex::Captures| {
                             let offset: f64 = caps[1].parse().unwrap_or(999.0);
@@ -1213,6 +1251,23 @@ impl Colorizer {
                     line.to_string()
                 }
             }
+            QueryType::Service("neighbors", _) => {
+                // Relationship column of the neighbour table
+                let mut cells: Vec<String> = line.split(" | ").map(str::to_string).collect();
+                if let Some(cell) = cells.get_mut(1) {
+                    let color = match cell.trim() {
+                        "upstream" => Some("\x1b[92m"), // Green
+                        "peer" => Some("\x1b[95m"), // Magenta
+                        "downstream" => Some("\x1b[96m"), // Cyan
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        *cell = format!("{}{}\x1b[0m", color, cell);
+                    }
+                }
+                let asn_regex = &ASN_RE;
+                asn_regex.replace_all(&cells.join(" | "), "\x1b[93m$1\x1b[0m").to_string()
+            }
             QueryType::UpdatePatch | QueryType::Plugin(_, _) => {
                 // Use general formatting for update patch and plugins
                 if line.starts_with("%") {
@@ -1223,13 +1278,9 @@ impl Colorizer {
             }
             _ => {
                 // General network highlighting for all other query types
-                let asn_regex = Regex::new(r"(AS\d+)").expect("Invalid regex pattern");
-                let ip_regex = Regex::new(
-                    r"(\d+\.\d+\.\d+\.\d+(?:/\d+)?|[0-9a-fA-F:]+::[0-9a-fA-F:]*(?:/\d+)?)"
-                ).unwrap();
-                let domain_regex = Regex::new(
-                    r"([a-zA-Z0-9]([a-zA-Z0-9\-]{0,61}[a-zA-Z0-9])?\.)+[a-zA-Z]{2,}"
-                ).unwrap();
+                let asn_regex = &ASN_RE;
+                let ip_regex = &IP_PREFIX_RE;
+                let domain_regex = &DOMAIN_RE;
 
                 let mut result = asn_regex.replace_all(line, "\x1b[93m$1\x1b[0m").to_string();
                 result = ip_regex.replace_all(&result, "\x1b[92m$1\x1b[0m").to_string();
@@ -1244,3 +1295,18 @@ impl Colorizer {
         }
     }
 }
+
+/// Red, yellow or green by how soon a domain registration expires
+fn colorize_urgency(attr: &str, value: &str) -> String {
+    let urgency = match attr {
+        "days-left" => value.trim().parse().ok().map(Urgency::from_days),
+        _ => value.split_whitespace().next().and_then(Urgency::from_label),
+    };
+    let color = match urgency {
+        Some(Urgency::Expired | Urgency::Critical) => "\x1b[1;91m", // Red
+        Some(Urgency::RenewSoon) => "\x1b[1;93m", // Yellow
+        Some(Urgency::Ok) => "\x1b[1;92m", // Green
+        None => "\x1b[37m",
+    };
+    format!("\x1b[95m{}:\x1b[0m {}{}\x1b[0m", attr, color, value)
+}
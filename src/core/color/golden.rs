@@ -0,0 +1,108 @@
+// WHOIS Server - Colorizer Golden Files
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Golden-file tests of colorized output
+//!
+//! Every case colorizes a recorded raw response from
+//! `tests/fixtures/color/<name>.txt` with all color schemes and compares the
+//! result with `tests/fixtures/color/<name>.golden`. Escape characters are
+//! written as `\e` and CRLF line endings as plain newlines, so the golden
+//! files can be read and diffed.
+//!
+//! After an intended change to the colorizer, regenerate the golden files
+//! with `UPDATE_GOLDEN=1 cargo test golden` and review the diff.
+
+use std::path::PathBuf;
+
+use crate::core::QueryType;
+use crate::core::color::{ColorScheme, Colorizer};
+
+/// Fixture name and the query type its response belongs to
+fn corpus() -> Vec<(&'static str, QueryType)> {
+    vec![
+        ("ipv4", QueryType::IPv4("193.0.0.1".parse().unwrap())),
+        ("ipv6", QueryType::IPv6("2001:67c:2e8::1".parse().unwrap())),
+        ("asn", QueryType::ASN("AS13335".to_string())),
+        ("domain", QueryType::Domain("example.com".to_string())),
+        ("dn42", QueryType::ASN("AS4242420000".to_string())),
+        ("bgptool", QueryType::BGPTool("1.1.1.1".to_string())),
+        ("geo", QueryType::Geo("1.1.1.1".to_string())),
+        ("dns", QueryType::Dns("example.com".to_string())),
+        ("ssl", QueryType::Ssl("example.com".to_string())),
+        ("steam", QueryType::Steam("570".to_string(), None)),
+        ("minecraft", QueryType::Minecraft("mc.hypixel.net".to_string())),
+        ("ntp", QueryType::Ntp("pool.ntp.org".to_string())),
+        ("help", QueryType::Help),
+        ("plugin", QueryType::Plugin("-EXAMPLE".to_string(), "AS64496".to_string())),
+    ]
+}
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/color").join(name)
+}
+
+/// Whether golden files should be rewritten instead of compared
+fn updating() -> bool {
+    std::env::var("UPDATE_GOLDEN").is_ok_and(|v| v == "1")
+}
+
+/// Readable form of colorized output
+fn escape(output: &str) -> String {
+    output.replace("\r\n", "\n").replace('\x1b', "\\e")
+}
+
+/// Colorized output of a response in every scheme
+fn render(response: &str, query_type: &QueryType) -> String {
+    let mut rendered = String::new();
+    for scheme in ColorScheme::ALL {
+        rendered.push_str(&format!("=== {} ===\n", scheme.name()));
+        rendered.push_str(&escape(&Colorizer::new(scheme).colorize_response(response, query_type)));
+        rendered.push('\n');
+    }
+    rendered
+}
+
+#[test]
+fn test_colorizer_golden_files() {
+    let mut mismatches = Vec::new();
+    for (name, query_type) in corpus() {
+        let response = std::fs::read_to_string(fixture(&format!("{}.txt", name)))
+            .unwrap_or_else(|e| panic!("missing color fixture {}.txt: {}", name, e));
+        let actual = render(&response, &query_type);
+        let golden_path = fixture(&format!("{}.golden", name));
+
+        if updating() {
+            std::fs::write(&golden_path, &actual).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&golden_path).unwrap_or_default();
+        if actual != expected {
+            let line = actual
+                .lines()
+                .zip(expected.lines())
+                .position(|(a, e)| a != e)
+                .unwrap_or_else(|| actual.lines().count().min(expected.lines().count()));
+            mismatches.push(format!(
+                "{}.golden differs at line {}:\n  expected: {}\n  actual:   {}",
+                name,
+                line + 1,
+                expected.lines().nth(line).unwrap_or("<end of file>"),
+                actual.lines().nth(line).unwrap_or("<end of output>")
+            ));
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "colorized output changed (regenerate with UPDATE_GOLDEN=1 cargo test golden):\n{}",
+        mismatches.join("\n")
+    );
+}
+
+#[test]
+fn test_every_case_has_fixtures() {
+    for (name, _) in corpus() {
+        assert!(fixture(&format!("{}.txt", name)).exists(), "missing {}.txt", name);
+        assert!(updating() || fixture(&format!("{}.golden", name)).exists(), "missing {}.golden", name);
+    }
+}
@@ -22,6 +22,8 @@ pub mod colorizer;
 
 #[cfg(test)]
 mod tests;
+#[cfg(test)]
+mod golden;
 
 pub use scheme::ColorScheme;
 pub use protocol::ColorProtocol;
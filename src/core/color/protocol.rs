@@ -81,7 +81,7 @@ impl ColorProtocol {
 
     pub fn get_capability_response(&self) -> String {
         if self.enabled {
-            "X-WHOIS-COLOR-SUPPORT: 1.0 schemes=ripe,ripe-dark,bgptools,bgptools-dark\r\n\r\n".to_string()
+            format!("X-WHOIS-COLOR-SUPPORT: 1.0 schemes={}\r\n\r\n", ColorScheme::names())
         } else {
             "X-WHOIS-COLOR-SUPPORT: no\r\n\r\n".to_string()
         }
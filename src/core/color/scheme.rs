@@ -16,7 +16,11 @@
  * along with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
-#[derive(Debug, Clone, PartialEq)]
+/// Color schemes, in light and dark terminal variants
+///
+/// This is the single list of implemented schemes: header parsing, the
+/// capability response and the client all go through [`ColorScheme::ALL`].
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ColorScheme {
     Ripe,
     RipeDark,
@@ -25,14 +29,36 @@ pub enum ColorScheme {
 }
 
 impl ColorScheme {
-    pub fn from_string(s: &str) -> Option<Self> {
-        match s.to_lowercase().as_str() {
-            "ripe" => Some(ColorScheme::Ripe),
-            "ripe-dark" | "dark-ripe" => Some(ColorScheme::RipeDark),
-            "bgptools" => Some(ColorScheme::BgpTools),
-            "bgptools-dark" | "dark-bgptools" => Some(ColorScheme::BgpToolsDark),
-            _ => None,
+    /// Every implemented scheme, in the order advertised to clients
+    pub const ALL: [ColorScheme; 4] = [
+        ColorScheme::Ripe,
+        ColorScheme::RipeDark,
+        ColorScheme::BgpTools,
+        ColorScheme::BgpToolsDark,
+    ];
+
+    /// Name used in `X-WHOIS-COLOR` headers and `--color`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorScheme::Ripe => "ripe",
+            ColorScheme::RipeDark => "ripe-dark",
+            ColorScheme::BgpTools => "bgptools",
+            ColorScheme::BgpToolsDark => "bgptools-dark",
         }
     }
 
-  }
\ No newline at end of file
+    /// Comma-separated names of all schemes
+    pub fn names() -> String {
+        Self::ALL.iter().map(ColorScheme::name).collect::<Vec<_>>().join(",")
+    }
+
+    /// Parse a scheme name; `dark-<scheme>` is accepted for `<scheme>-dark`
+    pub fn from_string(s: &str) -> Option<Self> {
+        let name = s.trim().to_lowercase();
+        let name = match name.strip_prefix("dark-") {
+            Some(base) => format!("{}-dark", base),
+            None => name,
+        };
+        Self::ALL.into_iter().find(|scheme| scheme.name() == name)
+    }
+}
@@ -75,6 +75,14 @@ use crate::core::QueryType;
         assert!(response.contains("bgptools-dark"));
     }
 
+    #[test]
+    fn test_capability_response_lists_implemented_schemes() {
+        let response = ColorProtocol::new().get_capability_response();
+        let schemes = response.trim().split("schemes=").nth(1).unwrap();
+        let advertised: Vec<ColorScheme> = schemes.split(',').filter_map(ColorScheme::from_string).collect();
+        assert_eq!(advertised, ColorScheme::ALL.to_vec());
+    }
+
     #[test]
     fn test_dark_vs_light_coloring() {
         let sample = "% Test\ninetnum: 192.0.2.0 - 192.0.2.255\nnetname: EXAMPLE-NET\ndescr: Example network";
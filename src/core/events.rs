@@ -0,0 +1,228 @@
+// WHOIS Server - Query Event Stream
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Structured query events for SIEM integration
+//!
+//! When `QUERY_EVENTS_URL` is set, every processed query produces an event
+//! (who asked, through which frontend, what, the outcome and the latency)
+//! that is POSTed to the URL as newline-delimited JSON, one event per line,
+//! which HTTP inputs of Logstash, Vector, Fluent Bit and most SIEM collectors
+//! accept directly. `QUERY_EVENTS_TOKEN` is sent as a bearer token if set.
+//!
+//! Events are queued in memory and sent in batches from a background task,
+//! so a slow or unavailable collector never delays a response. When the
+//! queue is full, events are dropped and the number dropped is logged.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::core::QueryType;
+use crate::core::request_context::RequestContext;
+use crate::core::telemetry::query_type_to_string;
+use crate::{log_debug, log_info, log_warn};
+
+/// Events held in memory while the collector is slow or down
+const QUEUE_CAPACITY: usize = 10_000;
+/// Most events sent in one request
+const BATCH_SIZE: usize = 100;
+/// Longest time an event waits for its batch to fill up
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sender of the event queue, `None` when no collector is configured
+static EVENT_QUEUE: OnceLock<Option<mpsc::Sender<QueryEvent>>> = OnceLock::new();
+
+/// Events dropped because the queue was full, reported with the next batch
+static DROPPED_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Collector settings loaded from environment variables
+///
+/// Environment variables:
+/// - `QUERY_EVENTS_URL`: URL events are POSTed to (events are off when unset)
+/// - `QUERY_EVENTS_TOKEN`: Bearer token for the collector (optional)
+struct EventsConfig {
+    url: String,
+    token: Option<String>,
+}
+
+impl EventsConfig {
+    fn from_env() -> Option<Self> {
+        let url = std::env::var("QUERY_EVENTS_URL").ok().filter(|u| !u.trim().is_empty())?;
+        let token = std::env::var("QUERY_EVENTS_TOKEN").ok().filter(|t| !t.trim().is_empty());
+        Some(Self { url: url.trim().to_string(), token: token.map(|t| t.trim().to_string()) })
+    }
+}
+
+/// Outcome of a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOutcome {
+    Success,
+    Error,
+}
+
+/// One processed query
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryEvent {
+    pub timestamp: DateTime<Utc>,
+    pub request_id: Uuid,
+    /// Frontend the query arrived through (`whois`, `ssh`, `web`, ...)
+    pub frontend: &'static str,
+    pub client_ip: Option<String>,
+    /// Authenticated identity, e.g. the SSH user
+    pub identity: Option<String>,
+    pub query: String,
+    pub query_type: String,
+    pub outcome: QueryOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    pub response_bytes: usize,
+}
+
+impl QueryEvent {
+    /// Event for a query answered (or failed) after `elapsed`
+    pub fn new(
+        ctx: &RequestContext,
+        query: &str,
+        query_type: &QueryType,
+        elapsed: Duration,
+        result: &anyhow::Result<String>
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            request_id: ctx.request_id,
+            frontend: ctx.frontend.as_str(),
+            client_ip: ctx.client_ip(),
+            identity: ctx.identity.clone(),
+            query: query.to_string(),
+            query_type: query_type_to_string(query_type),
+            outcome: if result.is_ok() { QueryOutcome::Success } else { QueryOutcome::Error },
+            error: result.as_ref().err().map(|e| e.to_string()),
+            latency_ms: elapsed.as_millis() as u64,
+            response_bytes: result.as_ref().map_or(0, String::len),
+        }
+    }
+}
+
+/// The event queue, starting the publisher on first use
+fn event_queue() -> Option<&'static mpsc::Sender<QueryEvent>> {
+    EVENT_QUEUE.get_or_init(|| {
+        let config = EventsConfig::from_env()?;
+        log_info!("Publishing query events to {}", config.url);
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(run_publisher(config, receiver));
+        Some(sender)
+    }).as_ref()
+}
+
+/// Queue an event for the query event stream, if it is enabled
+pub fn record_query_event(
+    ctx: &RequestContext,
+    query: &str,
+    query_type: &QueryType,
+    elapsed: Duration,
+    result: &anyhow::Result<String>
+) {
+    let Some(queue) = event_queue() else {
+        return;
+    };
+    if queue.try_send(QueryEvent::new(ctx, query, query_type, elapsed, result)).is_err() {
+        DROPPED_EVENTS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Newline-delimited JSON body of a batch
+fn ndjson(events: &[QueryEvent]) -> String {
+    let mut body = String::new();
+    for event in events {
+        if let Ok(line) = serde_json::to_string(event) {
+            body.push_str(&line);
+            body.push('\n');
+        }
+    }
+    body
+}
+
+async fn publish(client: &reqwest::Client, config: &EventsConfig, events: &[QueryEvent]) -> anyhow::Result<()> {
+    let mut request = client
+        .post(&config.url)
+        .header("Content-Type", "application/x-ndjson")
+        .header("User-Agent", "Akaere-Networks-Whois")
+        .body(ndjson(events));
+    if let Some(token) = &config.token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("collector returned HTTP {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Send queued events in batches until the queue is closed
+async fn run_publisher(config: EventsConfig, mut receiver: mpsc::Receiver<QueryEvent>) {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+    // Wait for the first event of a batch, then collect more until the batch
+    // is full or the flush interval has passed
+    while let Some(event) = receiver.recv().await {
+        batch.push(event);
+        let flush_at = tokio::time::Instant::now() + FLUSH_INTERVAL;
+        while batch.len() < BATCH_SIZE {
+            match tokio::time::timeout_at(flush_at, receiver.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        let dropped = DROPPED_EVENTS.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            log_warn!("Query event queue was full, {} events dropped", dropped);
+        }
+        match publish(&client, &config, &batch).await {
+            Ok(()) => log_debug!("Published {} query events", batch.len()),
+            Err(e) => log_warn!("Failed to publish {} query events: {}", batch.len(), e),
+        }
+        batch.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::request_context::Frontend;
+
+    #[test]
+    fn test_query_event_lines() {
+        let ctx = RequestContext::new(Frontend::Whois)
+            .with_client_addr("192.0.2.10".parse().unwrap())
+            .with_identity(Some("alice".to_string()));
+        let ok = QueryEvent::new(&ctx, "AS13335", &QueryType::ASN("AS13335".to_string()), Duration::from_millis(42), &Ok("aut-num: AS13335\n".to_string()));
+        let failed = QueryEvent::new(&ctx, "example.com", &QueryType::Domain("example.com".to_string()), Duration::from_millis(7), &Err(anyhow::anyhow!("Query deadline exceeded")));
+
+        let body = ndjson(&[ok, failed]);
+        let lines: Vec<serde_json::Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["frontend"], "whois");
+        assert_eq!(lines[0]["client_ip"], "192.0.2.10");
+        assert_eq!(lines[0]["identity"], "alice");
+        assert_eq!(lines[0]["outcome"], "success");
+        assert_eq!(lines[0]["latency_ms"], 42);
+        assert_eq!(lines[0]["response_bytes"], 17);
+        assert!(lines[0].get("error").is_none());
+        assert_eq!(lines[1]["outcome"], "error");
+        assert_eq!(lines[1]["error"], "Query deadline exceeded");
+        assert_eq!(lines[1]["request_id"], lines[0]["request_id"]);
+    }
+}
@@ -0,0 +1,231 @@
+// WHOIS Server - Query Explanation
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Routing introspection for `-EXPLAIN` queries
+//!
+//! `example.com-EXPLAIN` does not execute the query. It reports how the query
+//! would be processed: the detected query type, the module and upstream that
+//! would answer it, the cache key and the response patches that could apply.
+
+use crate::config::{
+    AFRINIC_WHOIS_PORT,
+    AFRINIC_WHOIS_SERVER,
+    ALTDB_WHOIS_PORT,
+    ALTDB_WHOIS_SERVER,
+    APNIC_WHOIS_PORT,
+    APNIC_WHOIS_SERVER,
+    ARIN_WHOIS_PORT,
+    ARIN_WHOIS_SERVER,
+    BELL_WHOIS_PORT,
+    BELL_WHOIS_SERVER,
+    DEFAULT_WHOIS_PORT,
+    DEFAULT_WHOIS_SERVER,
+    JPIRR_WHOIS_PORT,
+    JPIRR_WHOIS_SERVER,
+    LACNIC_WHOIS_PORT,
+    LACNIC_WHOIS_SERVER,
+    LEVEL3_WHOIS_PORT,
+    LEVEL3_WHOIS_SERVER,
+    NTTCOM_WHOIS_PORT,
+    NTTCOM_WHOIS_SERVER,
+    RADB_WHOIS_PORT,
+    RADB_WHOIS_SERVER,
+    RIPE_WHOIS_PORT,
+    RIPE_WHOIS_SERVER,
+    RIS_WHOIS_PORT,
+    RIS_WHOIS_SERVER,
+    TC_WHOIS_PORT,
+    TC_WHOIS_SERVER,
+};
+use crate::core::alias::resolve_aliases;
+use crate::core::modifiers::split_modifiers;
+use crate::core::patch::patch_candidates;
+use crate::core::single_flight::flight_key;
+use crate::core::telemetry::query_type_to_string;
+use crate::core::{
+    QueryType,
+    analyze_query,
+    cache_ttl,
+    is_private_asn,
+    is_private_ipv4,
+    is_private_ipv6,
+    parse_asn,
+};
+use crate::services::iana_cache::IanaCache;
+use crate::services::registry::service_registry;
+
+/// Where a query would be sent
+struct Route {
+    /// Module handling the query
+    handler: String,
+    /// Upstream server or data source
+    upstream: String,
+}
+
+fn route(handler: &str, upstream: impl Into<String>) -> Route {
+    Route { handler: handler.to_string(), upstream: upstream.into() }
+}
+
+fn whois_route(server: &str, port: u16) -> Route {
+    route("services::whois", format!("{}:{}", server, port))
+}
+
+fn dn42_route() -> Route {
+    route("dn42", "DN42 registry (local)")
+}
+
+/// Route through an IANA referral, using the cached referral if there is one
+fn iana_route(query: &str) -> Route {
+    let cached = IanaCache::new().ok().and_then(|cache| cache.cached_whois_server(query));
+    let upstream = match cached {
+        Some(server) => format!("{}:{} (IANA referral, cached)", server, DEFAULT_WHOIS_PORT),
+        None => "referral from whois.iana.org:43 (not cached yet)".to_string(),
+    };
+    route("services::whois (IANA referral)", upstream)
+}
+
+fn describe_route(query: &str, query_type: &QueryType) -> Route {
+    match query_type {
+        QueryType::Domain(domain) if domain.to_lowercase().ends_with(".dn42") => dn42_route(),
+        QueryType::Domain(domain) => iana_route(domain),
+        QueryType::IPv4(ip) if is_private_ipv4(*ip) => dn42_route(),
+        QueryType::IPv6(ip) if is_private_ipv6(*ip) => dn42_route(),
+        QueryType::IPv4(_) | QueryType::IPv6(_) => iana_route(query),
+        QueryType::ASN(asn) if parse_asn(asn).is_some_and(is_private_asn) => dn42_route(),
+        QueryType::ASN(asn) => iana_route(asn),
+        QueryType::Radb(_) => whois_route(RADB_WHOIS_SERVER, RADB_WHOIS_PORT),
+        QueryType::Altdb(_) => whois_route(ALTDB_WHOIS_SERVER, ALTDB_WHOIS_PORT),
+        QueryType::Afrinic(_) => whois_route(AFRINIC_WHOIS_SERVER, AFRINIC_WHOIS_PORT),
+        QueryType::Apnic(_) => whois_route(APNIC_WHOIS_SERVER, APNIC_WHOIS_PORT),
+        QueryType::ArinIrr(_) => whois_route(ARIN_WHOIS_SERVER, ARIN_WHOIS_PORT),
+        QueryType::Bell(_) => whois_route(BELL_WHOIS_SERVER, BELL_WHOIS_PORT),
+        QueryType::Jpirr(_) => whois_route(JPIRR_WHOIS_SERVER, JPIRR_WHOIS_PORT),
+        QueryType::Lacnic(_) => whois_route(LACNIC_WHOIS_SERVER, LACNIC_WHOIS_PORT),
+        QueryType::Level3(_) => whois_route(LEVEL3_WHOIS_SERVER, LEVEL3_WHOIS_PORT),
+        QueryType::Nttcom(_) => whois_route(NTTCOM_WHOIS_SERVER, NTTCOM_WHOIS_PORT),
+        QueryType::RipeIrr(_) => whois_route(RIPE_WHOIS_SERVER, RIPE_WHOIS_PORT),
+        QueryType::Ris(_) => whois_route(RIS_WHOIS_SERVER, RIS_WHOIS_PORT),
+        QueryType::Tc(_) => whois_route(TC_WHOIS_SERVER, TC_WHOIS_PORT),
+        QueryType::EmailSearch(_) => route("services::email", "DN42 registry (local)"),
+        QueryType::BGPTool(_) => route("services::bgptool", format!("bgp.tools:{}", DEFAULT_WHOIS_PORT)),
+        QueryType::Geo(_) => route("services::geo", "RIPEstat, ipinfo.io, ip-api.com"),
+        QueryType::RirGeo(_) => route("services::geo", "RIPEstat RIR geolocation"),
+        QueryType::Prefixes(_) => route("services::geo", "RIPEstat announced prefixes"),
+        QueryType::Irr(_) => route("services::irr", "IRR Explorer (irrexplorer.nlnog.net)"),
+        QueryType::LookingGlass(..) => route("services::looking_glass", "RIPEstat looking glass (live)"),
+        QueryType::Rpki(_, _) => route("services::rpki", "rpki.akae.re"),
+        QueryType::Manrs(_) => route("services::manrs", "MANRS (cached in LMDB)"),
+        QueryType::Dns(_) => route("services::dns", "Cloudflare DNS over HTTPS"),
+        QueryType::Ntp(_) => route("services::ntp", "NTP server (live)"),
+        QueryType::Ping(..) => route("services::ping", "Globalping (live)"),
+        QueryType::Trace(..) => route("services::traceroute", "Globalping (live)"),
+        QueryType::Probes(_) => route("services::probes", "Globalping probe list (live)"),
+        QueryType::Ssl(_) => route("services::ssl", "TLS handshake with the host"),
+        QueryType::Crt(_) => route("services::crt", "crt.sh"),
+        QueryType::CfStatus(_) => route("services::cfstatus", "Cloudflare status page"),
+        QueryType::Minecraft(_) => route("services::minecraft", "Minecraft server ping"),
+        QueryType::MinecraftUser(_) => route("services::minecraft", "Mojang API"),
+        QueryType::Steam(..) | QueryType::SteamSearch(..) => route("services::steam", "Steam API"),
+        QueryType::Imdb(_) | QueryType::ImdbSearch(_) => route("services::imdb", "OMDb API"),
+        QueryType::Acgc(_) => route("services::acgc", "Moegirlpedia"),
+        QueryType::Service(name, _) => match service_registry().get(name) {
+            Some(service) => route(&format!("services::registry ({})", name), service.upstream()),
+            None => route("services::registry", "service not registered"),
+        },
+        QueryType::GitHub(_) => route("services::github", "GitHub API"),
+        QueryType::Wikipedia(_) => route("services::wikipedia", "Wikipedia API"),
+        QueryType::Lyric(_) => route("services::lyric", "lty.vc (random)"),
+        QueryType::Desc(_) => route("services::desc", "IANA referral WHOIS (descr fields only)"),
+        QueryType::PeeringDB(_) => route("services::peeringdb", "PeeringDB API (cached in LMDB)"),
+        QueryType::Pen(_) => route("services::pen", "IANA enterprise numbers (cached in LMDB)"),
+        QueryType::Rdap(_) => route("services::rdap", "RDAP bootstrap servers"),
+        QueryType::Pixiv(_) => route("services::pixiv", "Pixiv API"),
+        QueryType::Icp(_) => route("services::icp", "ICP filing lookup (cached in LMDB)"),
+        QueryType::Meal | QueryType::MealCN => route("services::meal", "TheMealDB (random)"),
+        QueryType::Help => route("services::help", "built-in"),
+        QueryType::UpdatePatch => route("core::patch", "patch repository (updates patches)"),
+        QueryType::Explain(_) => route("core::explain", "built-in"),
+        QueryType::Plugin(suffix, _) => route("plugins", format!("Lua plugin for {}", suffix)),
+        QueryType::Unknown(q) => {
+            let upper = q.to_uppercase();
+            if ["-DN42", "-MNT", "-NEONETWORK", "-CRXN"].iter().any(|s| upper.ends_with(s)) {
+                dn42_route()
+            } else {
+                let mut route = iana_route(q);
+                route.upstream.push_str(", then DN42 registry if not found");
+                route
+            }
+        }
+    }
+}
+
+/// Describe how `base_query` would be processed, without executing it
+pub fn explain_query(base_query: &str) -> String {
+    let query_type = analyze_query(base_query);
+    let resolved = resolve_aliases(base_query);
+    let (primary_query, modifiers) = split_modifiers(&resolved);
+    let route = describe_route(primary_query, &query_type);
+
+    let mut output = String::new();
+    output.push_str(&format!("% Query explanation for: {}\n", base_query));
+    output.push_str("% The query was not executed.\n");
+    output.push('\n');
+    output.push_str(&format!("query:          {}\n", base_query));
+    if resolved != base_query {
+        output.push_str(&format!("resolved:       {} (suffix alias)\n", resolved));
+    }
+    output.push_str(&format!("query-type:     {}\n", query_type_to_string(&query_type)));
+    output.push_str(&format!("parsed-as:      {:?}\n", query_type));
+    if !modifiers.is_empty() {
+        output.push_str(&format!("modifiers:      {} (applied to the response)\n", modifiers.to_suffix()));
+    }
+    output.push_str(&format!("handler:        {}\n", route.handler));
+    output.push_str(&format!("upstream:       {}\n", route.upstream));
+    if route.upstream.contains("IANA referral") || route.upstream.contains("whois.iana.org") {
+        output.push_str(&format!(
+            "fallback:       {}:{}, then {}:{}\n",
+            RADB_WHOIS_SERVER,
+            RADB_WHOIS_PORT,
+            DEFAULT_WHOIS_SERVER,
+            DEFAULT_WHOIS_PORT
+        ));
+    }
+    output.push_str(&format!("cache-key:      {}\n", flight_key(primary_query, &query_type)));
+    let cache = match cache_ttl(&query_type) {
+        Some(ttl) => format!("yes (response cache for {}s, prefetch, in-flight deduplication)", ttl.as_secs()),
+        None => "no (answer changes on every call)".to_string(),
+    };
+    output.push_str(&format!("cacheable:      {}\n", cache));
+
+    let candidates = patch_candidates(primary_query);
+    if candidates.is_empty() {
+        output.push_str("patches:        none\n");
+    }
+    for candidate in candidates {
+        output.push_str(&format!("patch:          {} #{} ({})\n", candidate.file, candidate.index, candidate.reason));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_suffix_route() {
+        let output = explain_query("AS13335-RADB");
+        assert!(output.contains("query-type:     radb\n"));
+        assert!(output.contains("handler:        services::whois\n"));
+        assert!(output.contains("upstream:       whois.radb.net:43\n"));
+        assert!(output.contains("cache-key:      radb:AS13335\n"));
+        assert!(output.contains("cacheable:      yes (response cache for 1800s"));
+    }
+
+    #[test]
+    fn test_explain_dn42_and_live_queries() {
+        assert_eq!(describe_route("AS4242420000", &analyze_query("AS4242420000")).handler, "dn42");
+        assert!(explain_query("1.1.1.1-PING").contains("cacheable:      no"));
+    }
+}
@@ -0,0 +1,348 @@
+// WHOIS Server - Disk Janitor
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Disk usage caps for caches, registries, SSH data and traffic dumps
+//!
+//! A long-running public instance keeps writing: LMDB caches, the DN42
+//! registry checkout (every fetch adds objects), files saved in SSH
+//! sessions and, with `--dump-traffic`, two files per query. The janitor job
+//! measures each area every ten minutes and, when one is over its cap,
+//! evicts from it:
+//!
+//! - `dumps`: the oldest dump files
+//! - `ssh`: saved exports, least recently written first (the host key and
+//!   connection history are kept)
+//! - `dn42`: reflogs are expired and the checkout is garbage collected
+//! - `lmdb`: expired entries of the shared cache, then live cached responses
+//!   expiring soonest and the oldest WHOIS history snapshots. LMDB files
+//!   never shrink, but freed pages are reused, so this stops the databases
+//!   from growing; after an eviction, the area is only evicted from again
+//!   once it grew past the size measured then
+//!
+//! Usage counts allocated blocks, so sparse LMDB map files only count the
+//! pages actually written. It is exported as `whois_disk_*` metrics.
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::config::{
+    CACHE_LMDB_PATH, CRTWATCH_LMDB_PATH, DIGEST_LMDB_PATH, DN42_LMDB_PATH, DN42_REGISTRY_PATH, HISTORY_LMDB_PATH,
+    IANA_LMDB_PATH, ICP_LMDB_PATH, MANRS_LMDB_PATH, MONITOR_LMDB_PATH, PATCHES_LMDB_PATH, PEERINGDB_LMDB_PATH,
+    PEN_LMDB_PATH, PRIVATE_REGISTRY_LMDB_PATH, REGISTRAR_LMDB_PATH, STATS_LMDB_PATH, TLD_BOOTSTRAP_LMDB_PATH,
+    USAGE_LMDB_PATH, VERIFY_LMDB_PATH,
+};
+use crate::core::scheduler::{Job, Schedule};
+use crate::services::history::evict_oldest_snapshots;
+use crate::storage::cache::cache_store;
+use crate::storage::response_cache::evict_responses;
+use crate::{log_debug, log_warn};
+
+const MB: u64 = 1024 * 1024;
+
+/// LMDB environments of the server
+///
+/// The DN42 online mode cache lives below the DN42 database, the SSH
+/// connection history in the SSH cache directory.
+const LMDB_PATHS: [&str; 18] = [
+    STATS_LMDB_PATH,
+    CACHE_LMDB_PATH,
+    DN42_LMDB_PATH,
+    PEERINGDB_LMDB_PATH,
+    ICP_LMDB_PATH,
+    PEN_LMDB_PATH,
+    IANA_LMDB_PATH,
+    MANRS_LMDB_PATH,
+    PATCHES_LMDB_PATH,
+    REGISTRAR_LMDB_PATH,
+    MONITOR_LMDB_PATH,
+    PRIVATE_REGISTRY_LMDB_PATH,
+    VERIFY_LMDB_PATH,
+    DIGEST_LMDB_PATH,
+    CRTWATCH_LMDB_PATH,
+    HISTORY_LMDB_PATH,
+    USAGE_LMDB_PATH,
+    TLD_BOOTSTRAP_LMDB_PATH,
+];
+
+/// Part of the disk the janitor measures and evicts from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskArea {
+    Lmdb,
+    Dn42,
+    Ssh,
+    Dumps,
+}
+
+impl DiskArea {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lmdb => "lmdb",
+            Self::Dn42 => "dn42",
+            Self::Ssh => "ssh",
+            Self::Dumps => "dumps",
+        }
+    }
+}
+
+/// Size caps in bytes, 0 for no cap
+#[derive(Debug, Clone, Default)]
+pub struct DiskCaps {
+    pub lmdb: u64,
+    pub dn42: u64,
+    pub ssh: u64,
+    pub dumps: u64,
+    pub ssh_cache_dir: PathBuf,
+    pub dump_dir: Option<PathBuf>,
+}
+
+impl DiskCaps {
+    /// Caps given in megabytes on the command line
+    pub fn from_megabytes(lmdb: u64, dn42: u64, ssh: u64, dumps: u64) -> Self {
+        Self { lmdb: lmdb * MB, dn42: dn42 * MB, ssh: ssh * MB, dumps: dumps * MB, ..Self::default() }
+    }
+
+    fn cap(&self, area: DiskArea) -> u64 {
+        match area {
+            DiskArea::Lmdb => self.lmdb,
+            DiskArea::Dn42 => self.dn42,
+            DiskArea::Ssh => self.ssh,
+            DiskArea::Dumps => self.dumps,
+        }
+    }
+
+    fn paths(&self, area: DiskArea) -> Vec<PathBuf> {
+        match area {
+            DiskArea::Lmdb => LMDB_PATHS.iter().map(PathBuf::from).collect(),
+            DiskArea::Dn42 => vec![PathBuf::from(DN42_REGISTRY_PATH)],
+            DiskArea::Ssh => vec![self.ssh_cache_dir.clone()],
+            DiskArea::Dumps => self.dump_dir.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Last measurement of an area
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskUsage {
+    pub area: &'static str,
+    pub bytes: u64,
+    /// 0 when the area has no cap
+    pub cap_bytes: u64,
+    /// Bytes evicted since startup
+    pub evicted_bytes: u64,
+}
+
+static USAGE: Lazy<Mutex<Vec<DiskUsage>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Usage of every area as of the last janitor run
+pub fn disk_usage() -> Vec<DiskUsage> {
+    USAGE.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Bytes to evict from an area that uses `used` bytes, over its `cap`
+///
+/// Evicting from LMDB does not make its files smaller, so it is measured
+/// against the usage recorded after the last eviction: only what was added
+/// since is evicted.
+fn overshoot(area: DiskArea, used: u64, cap: u64) -> u64 {
+    let baseline = match area {
+        DiskArea::Lmdb => {
+            let usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+            let previous = usage.iter().find(|entry| entry.area == area.name()).map_or(0, |entry| entry.bytes);
+            previous.max(cap)
+        }
+        _ => cap,
+    };
+    used.saturating_sub(baseline)
+}
+
+fn record_usage(area: DiskArea, bytes: u64, cap_bytes: u64, evicted: u64) {
+    let mut usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    match usage.iter_mut().find(|entry| entry.area == area.name()) {
+        Some(entry) => {
+            entry.bytes = bytes;
+            entry.cap_bytes = cap_bytes;
+            entry.evicted_bytes += evicted;
+        }
+        None => usage.push(DiskUsage { area: area.name(), bytes, cap_bytes, evicted_bytes: evicted }),
+    }
+}
+
+/// Bytes a file occupies on disk
+fn allocated(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.blocks() * 512
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.len()
+    }
+}
+
+/// Every file below `root` with its size and modification time
+fn files_below(root: &Path, files: &mut Vec<(PathBuf, u64, SystemTime)>) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        // Symlinks are not followed (and count as nothing)
+        let Ok(metadata) = entry.path().symlink_metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            files_below(&entry.path(), files);
+        } else if metadata.is_file() {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((entry.path(), allocated(&metadata), modified));
+        }
+    }
+}
+
+/// Bytes used by the files below `paths`
+pub fn usage_of(paths: &[PathBuf]) -> u64 {
+    let mut files = Vec::new();
+    for path in paths {
+        files_below(path, &mut files);
+    }
+    files.iter().map(|(_, size, _)| size).sum()
+}
+
+/// Delete the oldest files below `root` until `bytes` were freed
+///
+/// Returns the bytes freed.
+pub fn evict_oldest(root: &Path, bytes: u64) -> u64 {
+    let mut files = Vec::new();
+    files_below(root, &mut files);
+    files.sort_by_key(|(_, _, modified)| *modified);
+
+    let mut freed = 0;
+    for (path, size, _) in files {
+        if freed >= bytes {
+            break;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => freed += size,
+            Err(e) => log_debug!("Failed to evict {:?}: {}", path, e),
+        }
+    }
+    freed
+}
+
+async fn evict(area: DiskArea, caps: &DiskCaps, bytes: u64) -> Result<()> {
+    match area {
+        DiskArea::Lmdb => {
+            if let Some(store) = cache_store() {
+                let purged = store.purge_expired().await?;
+                log_debug!("Purged {} expired shared cache entries", purged);
+            }
+            let responses = evict_responses(bytes).await?;
+            let snapshots = match bytes.checked_sub(responses) {
+                Some(rest) if rest > 0 => tokio::task::spawn_blocking(move || evict_oldest_snapshots(rest)).await??,
+                _ => 0,
+            };
+            log_debug!("Evicted {} bytes of cached responses and {} bytes of WHOIS history", responses, snapshots);
+        }
+        DiskArea::Dn42 => tokio::task::spawn_blocking(crate::dn42::compact_dn42_registry).await??,
+        DiskArea::Ssh => {
+            let exports = caps.ssh_cache_dir.join("exports");
+            tokio::task::spawn_blocking(move || evict_oldest(&exports, bytes)).await?;
+        }
+        DiskArea::Dumps => {
+            if let Some(dump_dir) = caps.dump_dir.clone() {
+                tokio::task::spawn_blocking(move || evict_oldest(&dump_dir, bytes)).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Measure every area and evict from the ones over their cap
+async fn sweep(caps: &DiskCaps) -> Result<()> {
+    for area in [DiskArea::Lmdb, DiskArea::Dn42, DiskArea::Ssh, DiskArea::Dumps] {
+        let paths = caps.paths(area);
+        let used = tokio::task::spawn_blocking(move || usage_of(&paths)).await?;
+        let cap = caps.cap(area);
+        if cap == 0 || used <= cap {
+            record_usage(area, used, cap, 0);
+            continue;
+        }
+
+        let bytes = overshoot(area, used, cap);
+        if bytes > 0
+            && let Err(e) = evict(area, caps, bytes).await
+        {
+            log_warn!("Failed to evict from {}: {}", area.name(), e);
+        }
+        let paths = caps.paths(area);
+        let now_used = tokio::task::spawn_blocking(move || usage_of(&paths)).await?;
+        let evicted = used.saturating_sub(now_used);
+        record_usage(area, now_used, cap, evicted);
+        if now_used > cap {
+            log_warn!(
+                "Disk area {} uses {} MB, over its {} MB cap ({} MB evicted)",
+                area.name(),
+                now_used / MB,
+                cap / MB,
+                evicted / MB
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Job measuring disk usage and enforcing the caps every ten minutes
+pub fn janitor_job(caps: DiskCaps) -> Job {
+    let caps = Arc::new(caps);
+    Job::new("disk-janitor", Schedule::Every(Duration::from_secs(600)), move || {
+        let caps = caps.clone();
+        async move { sweep(&caps).await }
+    })
+    .with_jitter(Duration::from_secs(60))
+    .run_at_start()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evict_oldest_first() {
+        let root = std::env::temp_dir().join(format!("whois-janitor-{}", std::process::id()));
+        fs::create_dir_all(root.join("client")).unwrap();
+        for (name, age) in [("old.txt", 300), ("client/middle.txt", 200), ("new.txt", 100)] {
+            let path = root.join(name);
+            fs::write(&path, vec![b'x'; 8192]).unwrap();
+            let modified = SystemTime::now() - Duration::from_secs(age);
+            fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        }
+
+        let total = usage_of(std::slice::from_ref(&root));
+        assert!(total >= 3 * 8192);
+        let freed = evict_oldest(&root, 1);
+        assert!(freed > 0);
+        assert!(!root.join("old.txt").exists());
+        assert!(root.join("client/middle.txt").exists());
+        assert!(root.join("new.txt").exists());
+
+        evict_oldest(&root, u64::MAX);
+        assert_eq!(usage_of(std::slice::from_ref(&root)), 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_lmdb_overshoot_after_eviction() {
+        assert_eq!(overshoot(DiskArea::Dumps, 150 * MB, 100 * MB), 50 * MB);
+
+        // LMDB kept its size after the last eviction: only growth is evicted
+        record_usage(DiskArea::Lmdb, 150 * MB, 100 * MB, 0);
+        assert_eq!(overshoot(DiskArea::Lmdb, 150 * MB, 100 * MB), 0);
+        assert_eq!(overshoot(DiskArea::Lmdb, 160 * MB, 100 * MB), 10 * MB);
+    }
+}
@@ -0,0 +1,198 @@
+// WHOIS Server - Query Macros
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Macros expanding one command into a sequence of queries
+//!
+//! `AUDIT example.com` runs `example.com`, `example.com-DNS`,
+//! `example.com-SSL` and `example.com-CRT` in order and returns the
+//! responses one after another. Macros are defined in a TOML file
+//! (`--macro-file`), globally or for one client identity (the SSH key
+//! fingerprint `SHA256:...` or login name):
+//!
+//! ```toml
+//! [macros]
+//! AUDIT = ["{}", "{}-DNS", "{}-SSL", "{}-CRT"]
+//! NET = ["{}", "{}-PREFIXES", "{}-MANRS"]
+//!
+//! [users."SHA256:3q2+7w..."]
+//! MINE = ["AS213605", "AS213605-PREFIXES"]
+//! ```
+//!
+//! `{}` in a step is replaced by the macro's argument. Per-identity macros
+//! take precedence over global ones, and `AUDIT` is built in unless the file
+//! redefines it. Macro expansion happens in `process_query`, so every
+//! frontend supports macros; steps are never expanded again.
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Most queries a macro may run
+pub const MAX_MACRO_STEPS: usize = 10;
+
+/// Placeholder replaced by the macro argument
+const ARGUMENT: &str = "{}";
+
+/// Built-in macros, available unless redefined
+const BUILTIN_MACROS: &[(&str, &[&str])] = &[("AUDIT", &["{}", "{}-DNS", "{}-SSL", "{}-CRT"])];
+
+/// Macro definitions by name (uppercase)
+pub type MacroTable = BTreeMap<String, Vec<String>>;
+
+/// Global and per-identity macros
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MacroSet {
+    pub global: MacroTable,
+    pub users: BTreeMap<String, MacroTable>,
+}
+
+#[derive(Deserialize)]
+struct MacroFile {
+    #[serde(default)]
+    macros: MacroTable,
+    #[serde(default)]
+    users: BTreeMap<String, MacroTable>,
+}
+
+/// A macro call expanded into its queries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroExpansion {
+    pub name: String,
+    pub steps: Vec<String>,
+}
+
+static MACROS: OnceLock<MacroSet> = OnceLock::new();
+
+/// Install the macros used for all queries
+pub fn init_macros(macros: MacroSet) {
+    let _ = MACROS.set(macros);
+}
+
+fn validate_table(table: MacroTable) -> Result<MacroTable> {
+    let mut validated = MacroTable::new();
+    for (name, steps) in table {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(anyhow!("macro name '{}' may only contain letters, digits and '_'", name));
+        }
+        if steps.is_empty() || steps.len() > MAX_MACRO_STEPS {
+            return Err(anyhow!("macro '{}' must have between 1 and {} steps", name, MAX_MACRO_STEPS));
+        }
+        if let Some(step) = steps.iter().find(|step| step.trim().is_empty()) {
+            return Err(anyhow!("macro '{}' has an empty step '{}'", name, step));
+        }
+        validated.insert(name.to_uppercase(), steps);
+    }
+    Ok(validated)
+}
+
+/// Parse macro definitions from TOML
+pub fn parse_macros(content: &str) -> Result<MacroSet> {
+    let file: MacroFile = toml::from_str(content)?;
+    let mut users = BTreeMap::new();
+    for (identity, table) in file.users {
+        users.insert(identity, validate_table(table)?);
+    }
+    Ok(MacroSet { global: validate_table(file.macros)?, users })
+}
+
+/// Load macro definitions from a TOML file
+pub fn load_macro_file(path: &Path) -> Result<MacroSet> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read macro file {}: {}", path.display(), e))?;
+    parse_macros(&content).map_err(|e| anyhow!("Invalid macro file {}: {}", path.display(), e))
+}
+
+impl MacroSet {
+    /// Number of macro definitions, global and per identity
+    pub fn len(&self) -> usize {
+        self.global.len() + self.users.values().map(BTreeMap::len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Steps of macro `name` for `identity`
+    fn lookup(&self, name: &str, identity: Option<&str>) -> Option<Vec<String>> {
+        let name = name.to_uppercase();
+        identity
+            .and_then(|identity| self.users.get(identity))
+            .and_then(|table| table.get(&name))
+            .or_else(|| self.global.get(&name))
+            .cloned()
+            .or_else(|| {
+                BUILTIN_MACROS
+                    .iter()
+                    .find(|(builtin, _)| *builtin == name)
+                    .map(|(_, steps)| steps.iter().map(|step| step.to_string()).collect())
+            })
+    }
+
+    /// Expand `NAME [argument]` if `NAME` is a macro
+    pub fn expand(&self, query: &str, identity: Option<&str>) -> Option<MacroExpansion> {
+        let query = query.trim();
+        let (name, argument) = match query.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (query, ""),
+        };
+        let steps = self.lookup(name, identity)?;
+
+        // A macro taking an argument is only a macro call when given one, so
+        // a plain query that happens to match a macro name is left alone
+        let takes_argument = steps.iter().any(|step| step.contains(ARGUMENT));
+        if takes_argument == argument.is_empty() {
+            return None;
+        }
+
+        Some(MacroExpansion {
+            name: name.to_uppercase(),
+            steps: steps.iter().map(|step| step.replace(ARGUMENT, argument)).collect(),
+        })
+    }
+}
+
+/// Expand a macro call using the configured macros
+pub fn expand_macro(query: &str, identity: Option<&str>) -> Option<MacroExpansion> {
+    static DEFAULT: OnceLock<MacroSet> = OnceLock::new();
+    MACROS.get().unwrap_or_else(|| DEFAULT.get_or_init(MacroSet::default)).expand(query, identity)
+}
+
+/// Header line printed before each step's response
+pub fn step_header(expansion: &MacroExpansion, index: usize) -> String {
+    format!("% [{} {}/{}] {}\n", expansion.name, index + 1, expansion.steps.len(), expansion.steps[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_builtin_and_user_macros() {
+        let macros = parse_macros(
+            "[macros]\nnet = [\"{}\", \"{}-PREFIXES\"]\n\n[users.alice]\nMINE = [\"AS213605\", \"AS213605-PREFIXES\"]\n",
+        )
+        .unwrap();
+        assert_eq!(macros.len(), 2);
+
+        let audit = macros.expand("audit example.com", None).unwrap();
+        assert_eq!(audit.name, "AUDIT");
+        assert_eq!(audit.steps, ["example.com", "example.com-DNS", "example.com-SSL", "example.com-CRT"]);
+        assert_eq!(macros.expand("NET AS13335", None).unwrap().steps, ["AS13335", "AS13335-PREFIXES"]);
+        assert_eq!(macros.expand("MINE", Some("alice")).unwrap().steps.len(), 2);
+
+        assert!(macros.expand("MINE", Some("bob")).is_none());
+        // Without an argument AUDIT is not a macro call
+        assert!(macros.expand("AUDIT", None).is_none());
+        assert!(macros.expand("example.com", None).is_none());
+    }
+
+    #[test]
+    fn test_invalid_macros() {
+        assert!(parse_macros("[macros]\n\"A-B\" = [\"{}\"]\n").is_err());
+        assert!(parse_macros("[macros]\nEMPTY = []\n").is_err());
+        assert!(parse_macros("").unwrap().is_empty());
+    }
+}
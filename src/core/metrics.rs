@@ -0,0 +1,347 @@
+// WHOIS Server - Prometheus Metrics
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Prometheus metrics served at `/metrics` on the web port
+//!
+//! Query, upstream and plugin metrics are recorded here as they happen;
+//! request totals, dropped connections and cache counters are read from
+//! `core::stats` when the endpoint is scraped. Everything is rendered in the
+//! Prometheus text exposition format, so no client library is needed.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use crate::core::janitor::disk_usage;
+use crate::core::stats::{StatsState, get_stats_response};
+use crate::core::telemetry::query_type_to_string;
+use crate::core::QueryType;
+
+/// Histogram bucket upper bounds in seconds
+const BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Latency histogram with fixed buckets
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// Observations per bucket (not cumulative)
+    counts: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if let Some(bucket) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.counts[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(self.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "{}_bucket{{{}le=\"{}\"}} {}", name, labels_prefix(labels), bound, cumulative);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}le=\"+Inf\"}} {}", name, labels_prefix(labels), self.count);
+        let _ = writeln!(out, "{}_sum{} {}", name, braced(labels), self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, braced(labels), self.count);
+    }
+}
+
+fn labels_prefix(labels: &str) -> String {
+    if labels.is_empty() { String::new() } else { format!("{},", labels) }
+}
+
+fn braced(labels: &str) -> String {
+    if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) }
+}
+
+/// Escape a label value
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[derive(Default)]
+struct QueryMetrics {
+    total: u64,
+    errors: u64,
+    latency: Histogram,
+}
+
+#[derive(Default)]
+struct Registry {
+    /// Query type -> counters and latency
+    queries: BTreeMap<String, QueryMetrics>,
+    /// Upstream source -> latency
+    upstream: BTreeMap<String, Histogram>,
+    /// (upstream source, status) -> requests
+    upstream_requests: BTreeMap<(String, &'static str), u64>,
+    /// (plugin suffix, outcome) -> invocations
+    plugins: BTreeMap<(String, &'static str), u64>,
+    /// Upstream host -> responses aborted for exceeding the body limit
+    body_truncations: BTreeMap<String, u64>,
+}
+
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::default()));
+static ACTIVE_CONNECTIONS: AtomicI64 = AtomicI64::new(0);
+static CONNECTIONS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Record a processed query
+pub fn record_query(query_type: &QueryType, elapsed: Duration, success: bool) {
+    let mut registry = registry();
+    let metrics = registry.queries.entry(query_type_to_string(query_type)).or_default();
+    metrics.total += 1;
+    if !success {
+        metrics.errors += 1;
+    }
+    metrics.latency.observe(elapsed);
+}
+
+/// Record a request to an upstream server or API
+pub fn record_upstream(source: &str, elapsed: Duration, status: &'static str) {
+    let mut registry = registry();
+    registry.upstream.entry(source.to_string()).or_default().observe(elapsed);
+    *registry.upstream_requests.entry((source.to_string(), status)).or_default() += 1;
+}
+
+/// Record a plugin invocation
+pub fn record_plugin_invocation(suffix: &str, success: bool) {
+    let outcome = if success { "success" } else { "error" };
+    *registry().plugins.entry((suffix.to_string(), outcome)).or_default() += 1;
+}
+
+/// Record an upstream response aborted for exceeding the body limit
+pub fn record_body_truncation(host: &str) {
+    *registry().body_truncations.entry(host.to_string()).or_default() += 1;
+}
+
+/// Queries processed per query type since startup
+pub fn query_totals() -> BTreeMap<String, u64> {
+    registry().queries.iter().map(|(query_type, metrics)| (query_type.clone(), metrics.total)).collect()
+}
+
+/// Requests and failed requests per upstream source since startup
+pub fn upstream_totals() -> BTreeMap<String, (u64, u64)> {
+    let mut totals: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for ((source, status), count) in &registry().upstream_requests {
+        let entry = totals.entry(source.clone()).or_default();
+        entry.0 += count;
+        if *status == "failed" {
+            entry.1 += count;
+        }
+    }
+    totals
+}
+
+/// Counts a client connection as active until dropped
+pub struct ConnectionGuard(());
+
+impl ConnectionGuard {
+    pub fn new() -> Self {
+        ACTIVE_CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+        CONNECTIONS_TOTAL.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(())
+    }
+}
+
+impl Default for ConnectionGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        ACTIVE_CONNECTIONS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Metrics recorded in this module
+fn render_recorded(out: &mut String) {
+    let registry = registry();
+
+    header(out, "whois_queries_total", "counter", "Queries processed, by query type");
+    for (query_type, metrics) in &registry.queries {
+        let _ = writeln!(out, "whois_queries_total{{type=\"{}\"}} {}", escape(query_type), metrics.total);
+    }
+    header(out, "whois_query_errors_total", "counter", "Queries that failed, by query type");
+    for (query_type, metrics) in &registry.queries {
+        let _ = writeln!(out, "whois_query_errors_total{{type=\"{}\"}} {}", escape(query_type), metrics.errors);
+    }
+    header(out, "whois_query_duration_seconds", "histogram", "Time to answer a query, by query type");
+    for (query_type, metrics) in &registry.queries {
+        let labels = format!("type=\"{}\"", escape(query_type));
+        metrics.latency.render(out, "whois_query_duration_seconds", &labels);
+    }
+
+    header(out, "whois_upstream_requests_total", "counter", "Requests to upstream servers, by source and status");
+    for ((source, status), count) in &registry.upstream_requests {
+        let _ = writeln!(
+            out,
+            "whois_upstream_requests_total{{source=\"{}\",status=\"{}\"}} {}",
+            escape(source),
+            status,
+            count
+        );
+    }
+    header(out, "whois_upstream_duration_seconds", "histogram", "Upstream request latency, by source");
+    for (source, histogram) in &registry.upstream {
+        let labels = format!("source=\"{}\"", escape(source));
+        histogram.render(out, "whois_upstream_duration_seconds", &labels);
+    }
+
+    header(
+        out,
+        "whois_upstream_body_truncations_total",
+        "counter",
+        "Upstream responses aborted for exceeding the body limit, by host"
+    );
+    for (host, count) in &registry.body_truncations {
+        let _ = writeln!(out, "whois_upstream_body_truncations_total{{host=\"{}\"}} {}", escape(host), count);
+    }
+
+    header(out, "whois_plugin_invocations_total", "counter", "Plugin invocations, by plugin suffix and outcome");
+    for ((plugin, outcome), count) in &registry.plugins {
+        let _ = writeln!(
+            out,
+            "whois_plugin_invocations_total{{plugin=\"{}\",outcome=\"{}\"}} {}",
+            escape(plugin),
+            outcome,
+            count
+        );
+    }
+
+    header(out, "whois_active_connections", "gauge", "WHOIS (port 43) connections being served");
+    let _ = writeln!(out, "whois_active_connections {}", ACTIVE_CONNECTIONS.load(Ordering::Relaxed));
+    header(out, "whois_connections_total", "counter", "WHOIS (port 43) connections accepted");
+    let _ = writeln!(out, "whois_connections_total {}", CONNECTIONS_TOTAL.load(Ordering::Relaxed));
+}
+
+/// Render all metrics in the Prometheus text format
+pub async fn render_metrics(stats: &StatsState) -> String {
+    let mut out = String::new();
+    render_recorded(&mut out);
+
+    let response = get_stats_response(stats).await;
+    header(&mut out, "whois_requests_total", "counter", "Responses served since the statistics were created");
+    let _ = writeln!(out, "whois_requests_total {}", response.total_requests);
+    header(&mut out, "whois_response_bytes_total", "counter", "Response bytes served since the statistics were created");
+    let _ = writeln!(out, "whois_response_bytes_total {}", response.total_bytes_served);
+
+    let dropped = &response.dropped_connections;
+    header(&mut out, "whois_dropped_connections_total", "counter", "Connections dropped before a response, by reason");
+    for (reason, count) in [
+        ("read-timeout", dropped.read_timeout),
+        ("idle-timeout", dropped.idle_timeout),
+        ("request-too-large", dropped.request_too_large),
+        ("read-error", dropped.read_error),
+        ("write-timeout", dropped.write_timeout),
+        ("rate-limited", dropped.rate_limited),
+    ] {
+        let _ = writeln!(out, "whois_dropped_connections_total{{reason=\"{}\"}} {}", reason, count);
+    }
+
+    header(&mut out, "whois_cache_hits_total", "counter", "Cache hits, by cache");
+    let _ = writeln!(out, "whois_cache_hits_total{{cache=\"response\"}} {}", response.response_cache.hits);
+    header(&mut out, "whois_cache_misses_total", "counter", "Cache misses, by cache");
+    let _ = writeln!(out, "whois_cache_misses_total{{cache=\"response\"}} {}", response.response_cache.misses);
+    header(&mut out, "whois_cache_hit_ratio", "gauge", "Share of lookups answered from the cache, by cache");
+    let _ = writeln!(out, "whois_cache_hit_ratio{{cache=\"response\"}} {}", response.response_cache.hit_rate);
+
+    header(&mut out, "whois_job_runs_total", "counter", "Completed runs of scheduled jobs, by job");
+    for job in &response.jobs {
+        let _ = writeln!(out, "whois_job_runs_total{{job=\"{}\"}} {}", escape(&job.name), job.runs);
+    }
+    header(&mut out, "whois_job_failures_total", "counter", "Failed runs of scheduled jobs, by job");
+    for job in &response.jobs {
+        let _ = writeln!(out, "whois_job_failures_total{{job=\"{}\"}} {}", escape(&job.name), job.failures);
+    }
+    header(&mut out, "whois_job_skipped_total", "counter", "Runs skipped because the previous run was still going, by job");
+    for job in &response.jobs {
+        let _ = writeln!(out, "whois_job_skipped_total{{job=\"{}\"}} {}", escape(&job.name), job.skipped);
+    }
+    header(&mut out, "whois_job_last_run_timestamp_seconds", "gauge", "Unix time the last run of a job started");
+    for job in &response.jobs {
+        if let Some(last_run) = job.last_run {
+            let _ = writeln!(out, "whois_job_last_run_timestamp_seconds{{job=\"{}\"}} {}", escape(&job.name), last_run);
+        }
+    }
+    header(&mut out, "whois_job_last_duration_seconds", "gauge", "Duration of the last completed run of a job");
+    for job in &response.jobs {
+        if let Some(duration) = job.last_duration_ms {
+            let _ = writeln!(out, "whois_job_last_duration_seconds{{job=\"{}\"}} {}", escape(&job.name), duration as f64 / 1000.0);
+        }
+    }
+
+    let disk = disk_usage();
+    header(&mut out, "whois_disk_usage_bytes", "gauge", "Disk space used, by area (lmdb, dn42, ssh, dumps)");
+    for area in &disk {
+        let _ = writeln!(out, "whois_disk_usage_bytes{{area=\"{}\"}} {}", area.area, area.bytes);
+    }
+    header(&mut out, "whois_disk_cap_bytes", "gauge", "Configured disk cap, by area (capped areas only)");
+    for area in disk.iter().filter(|area| area.cap_bytes > 0) {
+        let _ = writeln!(out, "whois_disk_cap_bytes{{area=\"{}\"}} {}", area.area, area.cap_bytes);
+    }
+    header(&mut out, "whois_disk_evicted_bytes_total", "counter", "Disk space freed by the janitor, by area");
+    for area in &disk {
+        let _ = writeln!(out, "whois_disk_evicted_bytes_total{{area=\"{}\"}} {}", area.area, area.evicted_bytes);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::default();
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_millis(80));
+        histogram.observe(Duration::from_secs(60));
+
+        let mut out = String::new();
+        histogram.render(&mut out, "test_seconds", "type=\"asn\"");
+        assert!(out.contains("test_seconds_bucket{type=\"asn\",le=\"0.005\"} 1\n"));
+        assert!(out.contains("test_seconds_bucket{type=\"asn\",le=\"0.1\"} 2\n"));
+        assert!(out.contains("test_seconds_bucket{type=\"asn\",le=\"30\"} 2\n"));
+        assert!(out.contains("test_seconds_bucket{type=\"asn\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("test_seconds_count{type=\"asn\"} 3\n"));
+    }
+
+    #[test]
+    fn test_recorded_metrics() {
+        record_query(&QueryType::ASN("AS64496".to_string()), Duration::from_millis(20), true);
+        record_query(&QueryType::ASN("AS64496".to_string()), Duration::from_millis(20), false);
+        record_upstream("whois.example\"net", Duration::from_millis(40), "cache miss");
+        record_plugin_invocation("-METRICSTEST", true);
+        record_body_truncation("huge.example.net");
+        let _guard = ConnectionGuard::new();
+
+        let mut out = String::new();
+        render_recorded(&mut out);
+        assert!(out.contains("# TYPE whois_queries_total counter\n"));
+        assert!(out.contains("whois_query_errors_total{type=\"asn\"}"));
+        assert!(out.contains("whois_upstream_requests_total{source=\"whois.example\\\"net\",status=\"cache miss\"} 1\n"));
+        assert!(out.contains("whois_plugin_invocations_total{plugin=\"-METRICSTEST\",outcome=\"success\"} 1\n"));
+        assert!(out.contains("whois_upstream_body_truncations_total{host=\"huge.example.net\"} 1\n"));
+        assert!(!out.contains("whois_active_connections 0\n"));
+    }
+}
@@ -1,11 +1,35 @@
+pub mod alias;
+pub mod analytics;
+pub mod banner;
+pub mod body_limit;
 pub mod color;
+pub mod events;
+pub mod explain;
+pub mod janitor;
 pub mod logger;
+pub mod macros;
+pub mod metrics;
+pub mod modifiers;
 pub mod patch;
+pub mod prefetch;
+pub mod provenance;
+pub mod public_suffix;
 pub mod query;
+pub mod query_limits;
 pub mod query_processor;
+pub mod regex_cache;
+pub mod registry_flags;
+pub mod request_context;
+pub mod sanitize;
+pub mod scheduler;
+pub mod signing;
+pub mod single_flight;
+pub mod special_purpose;
 pub mod stats;
 pub mod telemetry;
+pub mod truncation;
 pub mod utils;
+pub mod warmup;
 
 pub use color::*;
 pub use patch::*;
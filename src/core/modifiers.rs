@@ -0,0 +1,784 @@
+// WHOIS Server - Query Modifiers
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Output modifiers chained after a query's service suffix
+//!
+//! A query is parsed as an ordered pipeline: the primary service (the
+//! `QueryType` suffix, e.g. `-GEO`), then any number of trailing modifiers
+//! that only change how the service's response is presented:
+//!
+//! - `-JSON` / `-MD`: output format (JSON object or Markdown)
+//! - `-P<n>`: page `n` of a long response
+//! - `-SIGNED`: append a detached signature (see `core::signing`)
+//! - `-NOCACHE`: fetch a fresh response instead of a cached one
+//! - `-NOFOLLOW`: return only the registry's answer for a domain, without
+//!   chasing its `Registrar WHOIS Server:` referral
+//! - `-TRANSLATE`: append machine translations of non-Latin lines (see
+//!   `services::translate`)
+//! - `-RAW`: fetch a fresh response and return it as the upstream sent it,
+//!   without patches, enrichment, colors or banner, under a header naming
+//!   the servers queried
+//! - `-MORE=<token>`: the next part of a truncated response (see
+//!   `core::truncation`)
+//! - `-FIELDS=<a,b,...>`: only the named attributes, one `name: value` per
+//!   line and without the banner, for scripts. Common names match the
+//!   registry-specific spellings (`expires` finds `Registry Expiry Date`,
+//!   `paid-till`, ...)
+//! - `-GREP=<regex>`: only the lines matching the pattern, compiled with
+//!   size limits so a pattern cannot exhaust the server
+//! - `-SORT=<column>[:desc]` / `-COUNT[=<column>]`: the response's tables
+//!   sorted by a column, or replaced by their row count (per value of a
+//!   column). Tables are read back with the table model of
+//!   `services::utils::table`, so every service rendering one gets these
+//!
+//! Registry flags given as separate words after the target (`AS3333 -B`,
+//! `8.8.8.8 n +`) are split off first and carried along with the modifiers
+//! for the upstream WHOIS query (see `core::registry_flags`).
+//!
+//! The format can also be chosen by the frontend instead of the query: an
+//! `X-WHOIS-FORMAT: json` request header or `RequestContext::with_format`
+//! (used by the library's `query_json()`). A format modifier in the query
+//! takes precedence.
+//!
+//! So `1.1.1.1-GEO-JSON`, `example.com-SSL-MD` and `AS13335-PREFIXES-P2` all
+//! reuse the existing services without new `QueryType` variants. Modifiers
+//! are only split off when the remaining query is recognized, so objects
+//! whose names happen to end in e.g. `-MD` are still looked up as before.
+
+use crate::core::QueryType;
+use crate::core::query::analyze_primary;
+use crate::core::registry_flags::{RegistryFlags, split_registry_flags};
+use crate::core::patch::strip_ansi_codes;
+use crate::core::telemetry::query_type_to_string;
+use crate::services::expiry::{CREATED_KEYS, EXPIRY_KEYS, UPDATED_KEYS};
+use crate::services::utils::table::{Table, map_tables};
+use crate::web::json_formatter::{JsonFormatter, parse_objects};
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+
+/// Lines per page for `-P<n>`
+pub const PAGE_LINES: usize = 50;
+
+/// Longest `-GREP` pattern accepted
+const MAX_GREP_PATTERN: usize = 200;
+/// Compiled program and lazy DFA size limits of a `-GREP` pattern
+const GREP_SIZE_LIMIT: usize = 1 << 20;
+/// Deepest nesting of groups and repetitions in a `-GREP` pattern
+const GREP_NEST_LIMIT: u32 = 32;
+
+/// Registry-specific attribute names matched by the common `-FIELDS` names
+const FIELD_ALIASES: &[(&str, &[&str])] = &[
+    ("registrar", &["Sponsoring Registrar", "Registrar Name"]),
+    ("expires", EXPIRY_KEYS),
+    ("created", CREATED_KEYS),
+    ("updated", UPDATED_KEYS),
+    ("nserver", &["Name Server", "Nameserver", "Nameservers", "nameserver"]),
+    ("status", &["Domain Status", "state"]),
+];
+
+/// Presentation of a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Markdown,
+}
+
+impl OutputFormat {
+    /// Parse a format name as used by `X-WHOIS-FORMAT` (`text`, `json`, `md`)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "text" | "plain" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "md" | "markdown" => Some(OutputFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// Modifiers parsed from the end of a query
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryModifiers {
+    pub format: OutputFormat,
+    /// Requested page, starting at 1
+    pub page: Option<usize>,
+    /// Append a signature trailer to the response
+    pub signed: bool,
+    /// Bypass the response cache
+    pub no_cache: bool,
+    /// Do not follow registrar referrals of domain queries
+    pub no_follow: bool,
+    /// Append translations of lines in non-Latin scripts
+    pub translate: bool,
+    /// Return the upstream response unmodified
+    pub raw: bool,
+    /// Continuation token of a truncated response
+    pub more: Option<String>,
+    /// Attributes to keep, in lower case
+    pub fields: Vec<String>,
+    /// Pattern selecting the lines to keep
+    pub grep: Option<String>,
+    /// Column to sort tables by, optionally followed by `:desc`
+    pub sort: Option<String>,
+    /// Replace tables with their row count
+    pub count: bool,
+    /// Count rows per value of this column instead
+    pub count_by: Option<String>,
+    /// Flags forwarded to the upstream WHOIS server
+    pub registry_flags: RegistryFlags,
+}
+
+impl QueryModifiers {
+    pub fn is_empty(&self) -> bool {
+        self.format == OutputFormat::Text
+            && self.page.is_none()
+            && !self.signed
+            && !self.no_cache
+            && !self.no_follow
+            && !self.translate
+            && !self.raw
+            && self.more.is_none()
+            && self.fields.is_empty()
+            && self.grep.is_none()
+            && self.sort.is_none()
+            && !self.count
+            && self.registry_flags.is_empty()
+    }
+
+    /// Cache key for the fetched response, which `-NOFOLLOW` and registry flags change
+    pub fn fetch_key(&self, key: String) -> String {
+        let key = if self.no_follow { format!("{}:nofollow", key) } else { key };
+        if self.registry_flags.is_empty() { key } else { format!("{}:flags={}", key, self.registry_flags) }
+    }
+
+    /// Whether the response must be fetched fresh, `-RAW` included
+    pub fn bypasses_cache(&self) -> bool {
+        self.no_cache || self.raw
+    }
+
+    /// Use `format` when the query itself did not select an output format
+    pub fn with_default_format(mut self, format: Option<OutputFormat>) -> Self {
+        if self.format == OutputFormat::Text
+            && let Some(format) = format
+        {
+            self.format = format;
+        }
+        self
+    }
+
+    /// Whether the response may be colorized and framed by the banner
+    ///
+    /// JSON and Markdown output must stay parseable.
+    pub fn is_plain_text(&self) -> bool {
+        self.format == OutputFormat::Text
+    }
+
+    /// Whether a plain text response gets the banner and provenance footer
+    ///
+    /// Raw responses and `-FIELDS` selections are left as they are.
+    pub fn is_framed(&self) -> bool {
+        self.is_plain_text() && !self.raw && self.fields.is_empty()
+    }
+
+    /// Whether a long response may be truncated
+    ///
+    /// Pages, raw responses, field and line selections, sorted or counted
+    /// tables and JSON/Markdown output are always complete.
+    pub fn allows_truncation(&self) -> bool {
+        self.is_plain_text()
+            && self.page.is_none()
+            && !self.raw
+            && self.fields.is_empty()
+            && self.grep.is_none()
+            && self.sort.is_none()
+            && !self.count
+    }
+
+    /// Suffix form of the modifiers, in canonical order
+    pub fn to_suffix(&self) -> String {
+        let mut suffix = String::new();
+        match self.format {
+            OutputFormat::Text => {}
+            OutputFormat::Json => suffix.push_str("-JSON"),
+            OutputFormat::Markdown => suffix.push_str("-MD"),
+        }
+        if let Some(page) = self.page {
+            suffix.push_str(&format!("-P{}", page));
+        }
+        if self.signed {
+            suffix.push_str("-SIGNED");
+        }
+        if self.no_cache {
+            suffix.push_str("-NOCACHE");
+        }
+        if self.no_follow {
+            suffix.push_str("-NOFOLLOW");
+        }
+        if self.translate {
+            suffix.push_str("-TRANSLATE");
+        }
+        if self.raw {
+            suffix.push_str("-RAW");
+        }
+        if let Some(token) = &self.more {
+            suffix.push_str(&format!("-MORE={}", token));
+        }
+        if !self.fields.is_empty() {
+            suffix.push_str(&format!("-FIELDS={}", self.fields.join(",")));
+        }
+        if let Some(pattern) = &self.grep {
+            suffix.push_str(&format!("-GREP={}", pattern));
+        }
+        if let Some(column) = &self.sort {
+            suffix.push_str(&format!("-SORT={}", column));
+        }
+        match &self.count_by {
+            Some(column) => suffix.push_str(&format!("-COUNT={}", column)),
+            None if self.count => suffix.push_str("-COUNT"),
+            None => {}
+        }
+        if !self.registry_flags.is_empty() {
+            suffix.push_str(&format!(" {}", self.registry_flags));
+        }
+        suffix
+    }
+
+    /// Render a failed query in the requested format
+    pub fn format_error(&self, query: &str, query_type: &QueryType, message: &str) -> String {
+        match self.format {
+            OutputFormat::Text => format!("% Error: {}\n", message),
+            OutputFormat::Json => {
+                let formatted = JsonFormatter::new()
+                    .format_error(query, message, &query_type_to_string(query_type), 0);
+                serde_json::to_string_pretty(&formatted).unwrap_or_default() + "\n"
+            }
+            OutputFormat::Markdown => format!("## {}\n\n> Error: {}\n", query, message),
+        }
+    }
+
+    /// Run the response through the modifier pipeline: tables, field and
+    /// line selection, pagination, then format
+    pub fn apply(&self, query: &str, query_type: &QueryType, response: String) -> String {
+        let response = if self.sort.is_some() || self.count { self.arrange_tables(&response) } else { response };
+        let response = if self.fields.is_empty() { response } else { select_fields(&response, &self.fields) };
+        let response = match &self.grep {
+            Some(pattern) => grep_lines(&response, pattern),
+            None => response,
+        };
+        let response = match self.page {
+            Some(page) => paginate(&response, page),
+            None => response,
+        };
+
+        match self.format {
+            OutputFormat::Text => response,
+            OutputFormat::Json => {
+                let formatted = JsonFormatter::new()
+                    .format_response(query, response, &query_type_to_string(query_type), 0);
+                serde_json::to_string_pretty(&formatted).unwrap_or_default() + "\n"
+            }
+            OutputFormat::Markdown => to_markdown(query, &response),
+        }
+    }
+
+    /// Sort and count the tables of a response
+    fn arrange_tables(&self, response: &str) -> String {
+        let arranged = map_tables(response, |mut table| {
+            let mut output = String::new();
+            if let Some(sort) = &self.sort {
+                let (name, descending) = match sort.rsplit_once(':') {
+                    Some((name, order)) if order.eq_ignore_ascii_case("desc") => (name, true),
+                    Some((name, order)) if order.eq_ignore_ascii_case("asc") => (name, false),
+                    _ => (sort.as_str(), false),
+                };
+                match table.column(name) {
+                    Some(column) => table.sort_by(column, descending),
+                    None => output.push_str(&unknown_column(name, &table)),
+                }
+            }
+
+            match &self.count_by {
+                _ if !self.count => output.push_str(&table.render()),
+                None => output.push_str(&format!("rows: {}\n", table.rows.len())),
+                Some(name) => match table.column(name) {
+                    Some(column) => output.push_str(&table.count_by(column).render()),
+                    None => {
+                        output.push_str(&unknown_column(name, &table));
+                        output.push_str(&table.render());
+                    }
+                },
+            }
+            output
+        });
+
+        arranged.unwrap_or_else(|| {
+            let mut output = response.to_string();
+            if !output.ends_with('\n') {
+                output.push('\n');
+            }
+            output.push_str("% No table in the response to sort or count\n");
+            output
+        })
+    }
+}
+
+/// Note on a column no header of the table matches
+fn unknown_column(name: &str, table: &Table) -> String {
+    format!("% No column {} (columns: {})\n", name, table.header.join(", "))
+}
+
+/// Whether a suffix (without its dash) is an output modifier
+pub(crate) fn is_modifier_suffix(suffix: &str) -> bool {
+    let upper = suffix.to_uppercase();
+    matches!(upper.as_str(), "JSON" | "MD" | "MARKDOWN" | "SIGNED" | "NOCACHE" | "NOFOLLOW" | "TRANSLATE" | "RAW" | "COUNT") ||
+        ["SORT=", "COUNT="].iter().any(|prefix| upper.strip_prefix(prefix).is_some_and(|column| !column.is_empty())) ||
+        upper.strip_prefix("MORE=").is_some_and(|token| !token.is_empty()) ||
+        upper.strip_prefix("FIELDS=").is_some_and(|list| !list.is_empty()) ||
+        upper.strip_prefix("GREP=").is_some_and(|pattern| !pattern.is_empty()) ||
+        upper.strip_prefix('P').and_then(|n| n.parse::<usize>().ok()).is_some_and(|n| n > 0)
+}
+
+/// Parse one trailing modifier, returning the rest of the query
+fn strip_modifier<'a>(query: &'a str, modifiers: &mut QueryModifiers) -> Option<&'a str> {
+    let (rest, suffix) = query.rsplit_once('-')?;
+    if rest.is_empty() {
+        return None;
+    }
+
+    match suffix.to_uppercase().as_str() {
+        "JSON" if modifiers.format == OutputFormat::Text => {
+            modifiers.format = OutputFormat::Json;
+        }
+        "MD" | "MARKDOWN" if modifiers.format == OutputFormat::Text => {
+            modifiers.format = OutputFormat::Markdown;
+        }
+        "SIGNED" if !modifiers.signed => {
+            modifiers.signed = true;
+        }
+        "NOCACHE" if !modifiers.no_cache => {
+            modifiers.no_cache = true;
+        }
+        "NOFOLLOW" if !modifiers.no_follow => {
+            modifiers.no_follow = true;
+        }
+        "TRANSLATE" if !modifiers.translate => {
+            modifiers.translate = true;
+        }
+        "RAW" if !modifiers.raw => {
+            modifiers.raw = true;
+        }
+        upper if modifiers.more.is_none() && upper.starts_with("MORE=") && upper.len() > 5 => {
+            modifiers.more = Some(suffix[5..].to_string());
+        }
+        "COUNT" if !modifiers.count => {
+            modifiers.count = true;
+        }
+        upper if !modifiers.count && upper.starts_with("COUNT=") && upper.len() > 6 => {
+            modifiers.count = true;
+            modifiers.count_by = Some(suffix[6..].to_string());
+        }
+        upper if modifiers.sort.is_none() && upper.starts_with("SORT=") && upper.len() > 5 => {
+            modifiers.sort = Some(suffix[5..].to_string());
+        }
+        upper if modifiers.page.is_none()
+            && upper.strip_prefix('P').and_then(|n| n.parse::<usize>().ok()).is_some_and(|p| p > 0) =>
+        {
+            modifiers.page = upper[1..].parse::<usize>().ok();
+        }
+        _ => return strip_valued(query, modifiers),
+    }
+    Some(rest)
+}
+
+/// Parse a trailing `-FIELDS=` or `-GREP=` modifier, whose value may contain
+/// dashes itself (`-FIELDS=as-name,mnt-by`, `-GREP=^AS-`)
+fn strip_valued<'a>(query: &'a str, modifiers: &mut QueryModifiers) -> Option<&'a str> {
+    let upper = query.to_ascii_uppercase();
+    let fields = upper.rfind("-FIELDS=").filter(|_| modifiers.fields.is_empty());
+    let grep = upper.rfind("-GREP=").filter(|_| modifiers.grep.is_none());
+
+    // The modifier starting last is the trailing one
+    match (fields, grep) {
+        (Some(start), grep) if grep.is_none_or(|grep| grep < start) && start > 0 => {
+            let fields: Vec<String> = query[start + 8..]
+                .split(',')
+                .map(|field| field.trim().to_lowercase())
+                .filter(|field| !field.is_empty())
+                .collect();
+            if fields.is_empty() {
+                return None;
+            }
+            modifiers.fields = fields;
+            Some(&query[..start])
+        }
+        (_, Some(start)) if start > 0 && start + 6 < query.len() => {
+            modifiers.grep = Some(query[start + 6..].to_string());
+            Some(&query[..start])
+        }
+        _ => None,
+    }
+}
+
+/// Compile a `-GREP` pattern within the size and nesting limits
+///
+/// The regex engine runs in linear time, so the limits only bound the
+/// memory a pattern like `(a{100}){100}` would take to compile.
+fn compile_grep(pattern: &str) -> Result<Regex, String> {
+    if pattern.chars().count() > MAX_GREP_PATTERN {
+        return Err(format!("longer than {} characters", MAX_GREP_PATTERN));
+    }
+    RegexBuilder::new(pattern)
+        .size_limit(GREP_SIZE_LIMIT)
+        .dfa_size_limit(GREP_SIZE_LIMIT)
+        .nest_limit(GREP_NEST_LIMIT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Keep the lines of a response matching `pattern`
+///
+/// Lines are matched without their color codes and kept as they are.
+fn grep_lines(response: &str, pattern: &str) -> String {
+    let regex = match compile_grep(pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            let mut output = "% Invalid -GREP pattern:\n".to_string();
+            for line in e.lines() {
+                output.push_str(&format!("% {}\n", line));
+            }
+            return output;
+        }
+    };
+
+    let mut output = String::new();
+    for line in response.lines().filter(|line| regex.is_match(&strip_ansi_codes(line))) {
+        output.push_str(line);
+        output.push('\n');
+    }
+    if output.is_empty() {
+        return format!("% No lines match {}\n", pattern);
+    }
+    output
+}
+
+/// Whether the attribute `key` is the requested `field` or one of its aliases
+fn field_matches(field: &str, key: &str) -> bool {
+    key.eq_ignore_ascii_case(field) ||
+        FIELD_ALIASES.iter().any(|(name, keys)| *name == field && keys.iter().any(|alias| alias.eq_ignore_ascii_case(key)))
+}
+
+/// Reduce a response to the requested attributes
+///
+/// Each object of the response contributes its matching attributes as
+/// `field: value` lines in the order the fields were requested, objects
+/// separated by a blank line.
+fn select_fields(response: &str, fields: &[String]) -> String {
+    let mut blocks = Vec::new();
+    for object in parse_objects(&strip_ansi_codes(response)) {
+        let mut block = String::new();
+        for field in fields {
+            for (_, value) in object.iter().filter(|(key, _)| field_matches(field, key)) {
+                let values = match value {
+                    Value::Array(values) => values.iter().collect(),
+                    other => vec![other],
+                };
+                for value in values.into_iter().filter_map(Value::as_str) {
+                    block.push_str(&format!("{}: {}\n", field, value.replace('\n', "\n    ")));
+                }
+            }
+        }
+        if !block.is_empty() {
+            blocks.push(block);
+        }
+    }
+
+    if blocks.is_empty() {
+        return format!("% No {} attributes in the response\n", fields.join(", "));
+    }
+    blocks.join("\n")
+}
+
+/// Header of a `-RAW` response naming the servers it came from
+pub fn raw_header(servers: &[String]) -> String {
+    let servers = if servers.is_empty() { "the service".to_string() } else { servers.join(", ") };
+    format!("% Raw response from {} (no patches, enrichment or colors)\n%\n", servers)
+}
+
+/// Output format requested with an `X-WHOIS-FORMAT` header
+pub fn format_header(request: &str) -> Option<OutputFormat> {
+    request.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if !name.trim().eq_ignore_ascii_case("X-WHOIS-FORMAT") {
+            return None;
+        }
+        OutputFormat::parse(value)
+    })
+}
+
+/// Split registry flags and trailing modifiers off a query
+///
+/// Returns the query without modifiers and the parsed modifiers. When the
+/// query without modifiers is not recognized, only the registry flags are
+/// split off, unless a `-MORE=` token shows the query continues a truncated
+/// response.
+pub fn split_modifiers(query: &str) -> (&str, QueryModifiers) {
+    let (query, registry_flags) = split_registry_flags(query);
+    let mut modifiers = QueryModifiers::default();
+    let mut base = query;
+    while let Some(rest) = strip_modifier(base, &mut modifiers) {
+        base = rest;
+    }
+
+    if modifiers.is_empty() || (modifiers.more.is_none() && matches!(analyze_primary(base), QueryType::Unknown(_))) {
+        return (query, QueryModifiers { registry_flags, ..Default::default() });
+    }
+    (base, QueryModifiers { registry_flags, ..modifiers })
+}
+
+/// Select one page of a response, with a note on the position
+fn paginate(response: &str, page: usize) -> String {
+    let lines: Vec<&str> = response.lines().collect();
+    let pages = lines.len().div_ceil(PAGE_LINES).max(1);
+    if page > pages {
+        return format!("% Page {} does not exist, the response has {} page(s)\n", page, pages);
+    }
+
+    let start = (page - 1) * PAGE_LINES;
+    let end = (start + PAGE_LINES).min(lines.len());
+    let mut output = format!("% Page {} of {} (lines {}-{} of {})\n\n", page, pages, start + 1, end, lines.len());
+    for line in &lines[start..end] {
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Render a WHOIS response as Markdown
+///
+/// `%` comments become quotes and `key: value` attributes become list items;
+/// blank lines separate objects as in the original response.
+fn to_markdown(query: &str, response: &str) -> String {
+    let mut output = format!("## {}\n\n", query);
+    for line in response.lines() {
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            output.push('\n');
+        } else if let Some(comment) = trimmed.strip_prefix('%') {
+            output.push_str(&format!("> {}\n", comment.trim()));
+        } else if let Some((key, value)) = trimmed.split_once(':')
+            && !key.is_empty()
+            && !key.contains(' ')
+        {
+            output.push_str(&format!("- **{}**: {}\n", key.trim(), value.trim()));
+        } else {
+            output.push_str(&format!("{}\n", trimmed));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chained_modifiers() {
+        let (base, modifiers) = split_modifiers("1.1.1.1-GEO-JSON");
+        assert_eq!(base, "1.1.1.1-GEO");
+        assert_eq!(modifiers.format, OutputFormat::Json);
+
+        let (base, modifiers) = split_modifiers("AS13335-PREFIXES-P2");
+        assert_eq!(base, "AS13335-PREFIXES");
+        assert_eq!(modifiers.page, Some(2));
+
+        let (base, modifiers) = split_modifiers("example.com-SSL-md-p3");
+        assert_eq!(base, "example.com-SSL");
+        assert_eq!(modifiers, QueryModifiers {
+            format: OutputFormat::Markdown,
+            page: Some(3),
+            signed: false,
+            no_cache: false,
+            no_follow: false,
+            translate: false,
+            raw: false,
+            more: None,
+            fields: Vec::new(),
+            grep: None,
+            sort: None,
+            count: false,
+            count_by: None,
+            registry_flags: RegistryFlags::default(),
+        });
+        assert_eq!(modifiers.to_suffix(), "-MD-P3");
+
+        let (base, modifiers) = split_modifiers("AS13335-SIGNED");
+        assert_eq!(base, "AS13335");
+        assert!(modifiers.signed && modifiers.is_plain_text());
+
+        let (base, modifiers) = split_modifiers("1.1.1.1-GEO-NOCACHE-JSON");
+        assert_eq!(base, "1.1.1.1-GEO");
+        assert!(modifiers.no_cache);
+        assert_eq!(modifiers.to_suffix(), "-JSON-NOCACHE");
+
+        let (base, modifiers) = split_modifiers("example.com-NOFOLLOW");
+        assert_eq!(base, "example.com");
+        assert!(modifiers.no_follow);
+        assert_eq!(modifiers.fetch_key("domain:example.com".to_string()), "domain:example.com:nofollow");
+
+        let (base, modifiers) = split_modifiers("jprs.jp-TRANSLATE-MD");
+        assert_eq!(base, "jprs.jp");
+        assert!(modifiers.translate);
+        assert_eq!(modifiers.to_suffix(), "-MD-TRANSLATE");
+
+        let (base, modifiers) = split_modifiers("AS13335-RAW-P2");
+        assert_eq!(base, "AS13335");
+        assert!(modifiers.raw && modifiers.bypasses_cache());
+        assert_eq!(modifiers.to_suffix(), "-P2-RAW");
+        assert_eq!(
+            raw_header(&["whois.iana.org".to_string(), "whois.ripe.net".to_string()]),
+            "% Raw response from whois.iana.org, whois.ripe.net (no patches, enrichment or colors)\n%\n"
+        );
+
+        let (base, modifiers) = split_modifiers("ORG-EXAMPLE-MORE=3f9a0c1d2e4b5a6f.48");
+        assert_eq!(base, "ORG-EXAMPLE");
+        assert_eq!(modifiers.more.as_deref(), Some("3f9a0c1d2e4b5a6f.48"));
+        assert_eq!(modifiers.to_suffix(), "-MORE=3f9a0c1d2e4b5a6f.48");
+        assert!(!split_modifiers("AS13335-P2").1.allows_truncation());
+
+        let (base, modifiers) = split_modifiers("AS3333-JSON -B -r");
+        assert_eq!(base, "AS3333");
+        assert_eq!(modifiers.to_suffix(), "-JSON -B -r");
+        assert_eq!(modifiers.fetch_key("asn:AS3333".to_string()), "asn:AS3333:flags=-B -r");
+    }
+
+    #[test]
+    fn test_fields() {
+        let (base, modifiers) = split_modifiers("example.com-FIELDS=Registrar,expires,nserver-JSON");
+        assert_eq!(base, "example.com");
+        assert_eq!(modifiers.fields, vec!["registrar", "expires", "nserver"]);
+        assert_eq!(modifiers.format, OutputFormat::Json);
+        assert!(!modifiers.allows_truncation());
+        assert_eq!(modifiers.to_suffix(), "-JSON-FIELDS=registrar,expires,nserver");
+
+        let (base, modifiers) = split_modifiers("AS13335-FIELDS=as-name,mnt-by");
+        assert_eq!(base, "AS13335");
+        assert_eq!(modifiers.fields, vec!["as-name", "mnt-by"]);
+        assert!(!modifiers.is_framed());
+
+        let response = "% comment\nDomain Name: EXAMPLE.COM\nRegistrar: Example Registrar\n\
+                        Name Server: A.IANA-SERVERS.NET\nName Server: B.IANA-SERVERS.NET\n\
+                        Registry Expiry Date: 2026-08-13T04:00:00Z\n";
+        assert_eq!(
+            select_fields(response, &["expires".to_string(), "nserver".to_string(), "registrar".to_string()]),
+            "expires: 2026-08-13T04:00:00Z\nnserver: A.IANA-SERVERS.NET\nnserver: B.IANA-SERVERS.NET\n\
+             registrar: Example Registrar\n"
+        );
+        assert_eq!(
+            select_fields("\x1b[1maut-num:\x1b[0m AS13335\n\nas-name: CLOUDFLARENET\n", &["aut-num".to_string()]),
+            "aut-num: AS13335\n"
+        );
+        assert_eq!(select_fields(response, &["origin".to_string()]), "% No origin attributes in the response\n");
+    }
+
+    #[test]
+    fn test_grep() {
+        let (base, modifiers) = split_modifiers("AS13335-PREFIXES-GREP=^2a06");
+        assert_eq!(base, "AS13335-PREFIXES");
+        assert_eq!(modifiers.grep.as_deref(), Some("^2a06"));
+        assert!(!modifiers.allows_truncation());
+        assert_eq!(modifiers.to_suffix(), "-GREP=^2a06");
+
+        let (_, modifiers) = split_modifiers("AS13335-GREP=^AS-Peer");
+        assert_eq!(modifiers.grep.as_deref(), Some("^AS-Peer"));
+
+        let (base, modifiers) = split_modifiers("AS13335-FIELDS=mnt-by-GREP=-MNT$-MD");
+        assert_eq!(base, "AS13335");
+        assert_eq!(modifiers.fields, vec!["mnt-by"]);
+        assert_eq!(modifiers.grep.as_deref(), Some("-MNT$"));
+        assert_eq!(modifiers.format, OutputFormat::Markdown);
+
+        let response = "% Prefixes\n2a06:98c0::/29\n\x1b[32m2a06:98c1::/32\x1b[0m\n104.16.0.0/13\n";
+        assert_eq!(grep_lines(response, "^2a06"), "2a06:98c0::/29\n\x1b[32m2a06:98c1::/32\x1b[0m\n");
+        assert_eq!(grep_lines(response, "^2001"), "% No lines match ^2001\n");
+        assert!(grep_lines(response, "(").starts_with("% Invalid -GREP pattern:\n"));
+        assert!(grep_lines(response, "(a{1000}){1000}").starts_with("% Invalid -GREP pattern:\n"));
+        assert!(grep_lines(response, &"a".repeat(MAX_GREP_PATTERN + 1)).starts_with("% Invalid"));
+    }
+
+    #[test]
+    fn test_sort_and_count() {
+        let (base, modifiers) = split_modifiers("AS13335-PREFIXES-SORT=prefix:desc");
+        assert_eq!(base, "AS13335-PREFIXES");
+        assert_eq!(modifiers.sort.as_deref(), Some("prefix:desc"));
+        assert!(!modifiers.allows_truncation());
+
+        let response = "% ASN Announced Prefixes Query\n\n\
+                        Prefix        | Country\n\
+                        --------------|--------\n\
+                        104.16.0.0/13 | US\n\
+                        1.1.1.0/24    | AU\n\
+                        8.8.8.0/24    | US\n\
+                        \n% Total announced prefixes: 3\n";
+        let sorted = modifiers.apply("AS13335-PREFIXES", &QueryType::Unknown(String::new()), response.to_string());
+        assert!(sorted.contains("104.16.0.0/13 | US\n8.8.8.0/24    | US\n1.1.1.0/24    | AU\n\n% Total"));
+
+        let (_, modifiers) = split_modifiers("AS13335-PREFIXES-COUNT");
+        assert_eq!(modifiers.to_suffix(), "-COUNT");
+        let counted = modifiers.apply("AS13335-PREFIXES", &QueryType::Unknown(String::new()), response.to_string());
+        assert_eq!(counted, "% ASN Announced Prefixes Query\n\nrows: 3\n\n% Total announced prefixes: 3\n");
+
+        let (_, modifiers) = split_modifiers("AS13335-PREFIXES-SORT=asn-COUNT=country");
+        assert_eq!(modifiers.to_suffix(), "-SORT=asn-COUNT=country");
+        let counted = modifiers.apply("AS13335-PREFIXES", &QueryType::Unknown(String::new()), response.to_string());
+        assert!(counted.contains("% No column asn (columns: Prefix, Country)\nCountry | Count\n--------|------\nUS      | 2\nAU      | 1\n"));
+
+        let plain = modifiers.apply("AS13335", &QueryType::Unknown(String::new()), "aut-num: AS13335".to_string());
+        assert_eq!(plain, "aut-num: AS13335\n% No table in the response to sort or count\n");
+    }
+
+    #[test]
+    fn test_format_header() {
+        assert_eq!(format_header("X-WHOIS-FORMAT: json\r\nAS13335\r\n"), Some(OutputFormat::Json));
+        assert_eq!(format_header("x-whois-format:MD\nAS13335"), Some(OutputFormat::Markdown));
+        assert_eq!(format_header("X-WHOIS-FORMAT: xml\r\nAS13335\r\n"), None);
+
+        // A format modifier in the query wins over the header
+        let (_, modifiers) = split_modifiers("AS13335-MD");
+        assert_eq!(modifiers.with_default_format(Some(OutputFormat::Json)).format, OutputFormat::Markdown);
+        let (_, modifiers) = split_modifiers("AS13335");
+        assert_eq!(modifiers.with_default_format(Some(OutputFormat::Json)).format, OutputFormat::Json);
+    }
+
+    #[test]
+    fn test_unrecognized_base_keeps_query() {
+        // DN42 handles and objects may legitimately end in something like -P2
+        let (base, modifiers) = split_modifiers("FOO-P2");
+        assert_eq!(base, "FOO-P2");
+        assert!(modifiers.is_empty());
+
+        // A query made only of a modifier is not split
+        let (base, modifiers) = split_modifiers("-JSON");
+        assert_eq!(base, "-JSON");
+        assert!(modifiers.is_empty());
+    }
+
+    #[test]
+    fn test_pagination() {
+        let response: String = (1..=120).map(|i| format!("line {}\n", i)).collect();
+        let page = paginate(&response, 3);
+        assert!(page.starts_with("% Page 3 of 3 (lines 101-120 of 120)\n"));
+        assert!(page.ends_with("line 120\n"));
+        assert!(paginate(&response, 4).contains("does not exist"));
+    }
+
+    #[test]
+    fn test_markdown() {
+        let markdown = to_markdown("AS13335", "% comment\naut-num: AS13335\n\nas-name: CLOUDFLARENET\n");
+        assert_eq!(
+            markdown,
+            "## AS13335\n\n> comment\n- **aut-num**: AS13335\n\n- **as-name**: CLOUDFLARENET\n"
+        );
+    }
+}
@@ -16,12 +16,21 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::sync::RwLock;
+use crate::core::body_limit::LimitedBody;
+use crate::core::regex_cache::CachedRegex;
 use crate::{log_debug, log_error, log_info, log_warn};
+
+/// ANSI escape code pattern: \x1b[...m
+static ANSI_CODE_RE: CachedRegex = CachedRegex::new(r"\x1b\[[0-9;]*m");
+
+/// Patterns checked by `regex_cache::precompile_builtin_regexes` at startup
+pub(crate) fn builtin_regexes() -> Vec<&'static CachedRegex> {
+    vec![&ANSI_CODE_RE]
+}
+
 /// Strip ANSI color codes from a string
-fn strip_ansi_codes(s: &str) -> String {
-    // ANSI escape code pattern: \x1b[...m
-    let re = Regex::new(r"\x1b\[[0-9;]*m").expect("Invalid ANSI regex pattern");
-    re.replace_all(s, "").to_string()
+pub(crate) fn strip_ansi_codes(s: &str) -> String {
+    ANSI_CODE_RE.replace_all(s, "")
 }
 
 /// A single diff hunk (one replacement operation)
@@ -107,6 +116,28 @@ pub enum ConditionType {
     ResponseMatches,
 }
 
+impl ConditionType {
+    /// Header used for this condition in patch files
+    pub fn header(&self) -> &'static str {
+        match self {
+            ConditionType::QueryContains => "QUERY_CONTAINS",
+            ConditionType::ResponseContains => "RESPONSE_CONTAINS",
+            ConditionType::QueryMatches => "QUERY_MATCHES",
+            ConditionType::ResponseMatches => "RESPONSE_MATCHES",
+        }
+    }
+}
+
+/// A loaded patch that could apply to the response of a query
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchCandidate {
+    pub file: String,
+    /// Position of the patch in its file, starting at 1
+    pub index: usize,
+    /// Why the patch may apply
+    pub reason: String,
+}
+
 /// Metadata for patch updates from remote repository
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PatchMetadata {
@@ -177,7 +208,7 @@ impl PatchManager {
     /// Initialize LMDB storage for patches
     fn init_storage(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if self.storage.is_none() {
-            let storage = crate::storage::lmdb::LmdbStorage::new("./cache/patches_cache")?;
+            let storage = crate::storage::lmdb::LmdbStorage::new(crate::config::PATCHES_LMDB_PATH)?;
             self.storage = Some(storage);
         }
         Ok(())
@@ -205,7 +236,7 @@ impl PatchManager {
             .header("Expires", "0")
             .send()
             .await?;
-        let metadata: PatchMetadata = response.json().await?;
+        let metadata: PatchMetadata = response.limited_json().await?;
 
         let mut output = String::new();
         output.push_str("% Patch Update Report\n");
@@ -329,7 +360,7 @@ impl PatchManager {
             .header("Expires", "0")
             .send()
             .await?;
-        let content = response.text().await?;
+        let content = response.limited_text().await?;
 
         // Calculate SHA1
         let actual_sha1 = self.calculate_sha1(&content);
@@ -799,6 +830,43 @@ impl PatchManager {
         false // No conditions matched
     }
 
+    /// Patches whose conditions match `query` or depend on the response
+    fn candidates(&self, query: &str) -> Vec<PatchCandidate> {
+        let mut candidates = Vec::new();
+        for patch_file in &self.patch_files {
+            for (index, patch) in patch_file.patches.iter().enumerate() {
+                let query_condition = patch.conditions.iter().find(|c| match c.condition_type {
+                    ConditionType::QueryContains => query.contains(&c.value),
+                    ConditionType::QueryMatches => c.regex.as_ref().is_some_and(|r| r.is_match(query)),
+                    _ => false,
+                });
+                let response_condition = patch.conditions.iter().find(|c| {
+                    matches!(c.condition_type, ConditionType::ResponseContains | ConditionType::ResponseMatches)
+                });
+
+                // Conditions are OR-ed, so one matching query condition is enough
+                let reason = if patch.conditions.is_empty() {
+                    Some("no conditions, always applies".to_string())
+                } else if let Some(c) = query_condition {
+                    Some(format!("{} '{}' matches", c.condition_type.header(), c.value))
+                } else {
+                    response_condition.map(|c| {
+                        format!("{} '{}' depends on the response", c.condition_type.header(), c.value)
+                    })
+                };
+
+                if let Some(reason) = reason {
+                    candidates.push(PatchCandidate {
+                        file: patch_file.filename.clone(),
+                        index: index + 1,
+                        reason,
+                    });
+                }
+            }
+        }
+        candidates
+    }
+
     /// Apply a single patch
     fn apply_patch(&self, mut response: String, patch: &Patch) -> String {
         for hunk in &patch.hunks {
@@ -1098,6 +1166,13 @@ pub async fn process_update_patch_query() -> Result<String, Box<dyn std::error::
     }
 }
 
+/// Parse patch file content without loading it into the global manager
+///
+/// Used to validate patches before they are stored and by the fuzz targets.
+pub fn parse_patch(filename: &str, content: &str) -> Result<PatchFile, Box<dyn std::error::Error>> {
+    PatchManager::new().parse_patch_content(filename, content)
+}
+
 /// Apply patches to a WHOIS response
 pub fn apply_response_patches(query: &str, response: String) -> String {
     log_debug!("Applying patches for query: {}", query);
@@ -1107,6 +1182,12 @@ pub fn apply_response_patches(query: &str, response: String) -> String {
     result
 }
 
+/// Loaded patches that could apply to the response for a query
+pub fn patch_candidates(query: &str) -> Vec<PatchCandidate> {
+    let manager = PATCH_MANAGER.read().expect("Patch manager mutex poisoned in patch_candidates");
+    manager.candidates(query)
+}
+
 /// Reload all patch files from LMDB storage
 #[allow(dead_code)]
 pub fn reload_patches(_patches_dir: &str) -> Result<usize, Box<dyn std::error::Error>> {
@@ -1176,4 +1257,42 @@ mod tests {
         // Should not match
         assert!(!manager.check_conditions("", "netname: Other", &[condition]));
     }
+
+    #[test]
+    fn test_patch_candidates() {
+        let query_patch = Patch {
+            conditions: vec![PatchCondition {
+                condition_type: ConditionType::QueryContains,
+                value: "RuiNetwork".to_string(),
+                regex: None,
+            }],
+            excludes: vec![],
+            context_rules: vec![],
+            hunks: vec![],
+        };
+        let response_patch = Patch {
+            conditions: vec![PatchCondition {
+                condition_type: ConditionType::ResponseContains,
+                value: "RuiNetwork".to_string(),
+                regex: None,
+            }],
+            ..query_patch.clone()
+        };
+
+        let mut manager = PatchManager::new();
+        manager.patch_files.push(PatchFile {
+            filename: "001-ruinetwork.patch".to_string(),
+            patches: vec![query_patch, response_patch],
+        });
+
+        let candidates = manager.candidates("AS-RuiNetwork");
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].reason, "QUERY_CONTAINS 'RuiNetwork' matches");
+        assert_eq!(candidates[1].index, 2);
+        assert_eq!(candidates[1].reason, "RESPONSE_CONTAINS 'RuiNetwork' depends on the response");
+
+        let candidates = manager.candidates("AS12345");
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].index, 2);
+    }
 }
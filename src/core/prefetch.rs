@@ -0,0 +1,140 @@
+// WHOIS Server - Popular Query Prefetch
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Warm-up and prefetch of popular queries
+//!
+//! When prefetching is enabled, a background task periodically takes the
+//! top-N cacheable queries from the popularity ranking kept by
+//! [`crate::core::stats`] and refreshes their entries in the response cache
+//! ([`crate::storage::response_cache`]) before they expire, so popular
+//! lookups are always answered without waiting for the upstream. Entries
+//! keep the TTL of their query type; with a distributed cache backend every
+//! instance serves the refreshed responses.
+
+use serde::Serialize;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::core::scheduler::{Job, Schedule};
+use crate::core::query_processor::dispatch_query;
+use crate::core::request_context::RequestContext;
+use crate::core::single_flight::QUERY_FLIGHTS;
+use crate::core::stats::{PopularQuery, popular_queries, ranked_query_count};
+use crate::core::{analyze_query, cache_ttl, is_cacheable_query};
+use crate::storage::response_cache::refresh_expiring;
+use crate::{log_debug, log_info};
+
+/// Prefetch settings, set once at startup
+#[derive(Debug, Clone)]
+pub struct PrefetchConfig {
+    /// Number of most popular queries kept warm
+    pub top_n: usize,
+    /// How often the ranking is re-evaluated and entries refreshed
+    pub interval: Duration,
+}
+
+static PREFETCH_CONFIG: OnceLock<PrefetchConfig> = OnceLock::new();
+
+static REFRESHES: AtomicU64 = AtomicU64::new(0);
+static REFRESH_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+/// Prefetch counters exposed through the stats API
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PrefetchStatsEntry {
+    pub enabled: bool,
+    pub tracked_queries: usize,
+    pub refreshes: u64,
+    pub refresh_failures: u64,
+}
+
+/// Enable prefetching with the given settings
+pub fn init_prefetch(config: PrefetchConfig) {
+    let _ = PREFETCH_CONFIG.set(config);
+}
+
+fn config() -> Option<&'static PrefetchConfig> {
+    PREFETCH_CONFIG.get()
+}
+
+/// The `n` most popular cacheable queries, most popular first
+fn top_queries(n: usize) -> Vec<PopularQuery> {
+    popular_queries()
+        .into_iter()
+        .filter(|ranked| is_cacheable_query(&analyze_query(&ranked.query)))
+        .take(n)
+        .collect()
+}
+
+/// Refresh the response cache entries of the current top-N queries that
+/// expire before the next run
+async fn refresh_popular_queries(config: &PrefetchConfig) {
+    let ctx = RequestContext::default();
+
+    for PopularQuery { key, query, .. } in top_queries(config.top_n) {
+        let query_type = analyze_query(&query);
+        let Some(ttl) = cache_ttl(&query_type) else {
+            continue;
+        };
+
+        let fetch = || QUERY_FLIGHTS.run(&key, || dispatch_query(&query, &query_type, &ctx));
+        match refresh_expiring(&key, ttl, config.interval, fetch).await {
+            Ok(true) => {
+                REFRESHES.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(false) => {}
+            Err(e) => {
+                REFRESH_FAILURES.fetch_add(1, Ordering::Relaxed);
+                log_debug!("Prefetch refresh failed for {}: {}", query, e);
+            }
+        }
+    }
+}
+
+/// Job that keeps the most popular queries warm, once prefetch is configured
+pub fn prefetch_job() -> Option<Job> {
+    let config = config()?;
+    log_info!("Prefetching top {} queries every {}s", config.top_n, config.interval.as_secs());
+    Some(Job::new("prefetch", Schedule::Every(config.interval), move || async move {
+        refresh_popular_queries(config).await;
+        Ok(())
+    }))
+}
+
+/// Snapshot of prefetch counters
+pub fn prefetch_stats() -> PrefetchStatsEntry {
+    PrefetchStatsEntry {
+        enabled: config().is_some(),
+        tracked_queries: ranked_query_count(),
+        refreshes: REFRESHES.load(Ordering::Relaxed),
+        refresh_failures: REFRESH_FAILURES.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::QueryType;
+
+    #[test]
+    fn test_only_cacheable_queries_are_prefetched() {
+        assert!(is_cacheable_query(&QueryType::Domain("example.com".to_string())));
+        assert!(is_cacheable_query(&QueryType::ASN("AS13335".to_string())));
+        assert!(!is_cacheable_query(&QueryType::Meal));
+        assert!(!is_cacheable_query(&QueryType::Ping("1.1.1.1".to_string(), None)));
+        assert!(!is_cacheable_query(&QueryType::UpdatePatch));
+    }
+
+    #[test]
+    fn test_top_queries_skip_uncacheable() {
+        for _ in 0..5 {
+            crate::core::stats::rank_query("prefetch-rank.example", &analyze_query("prefetch-rank.example"));
+            crate::core::stats::rank_query("prefetch-rank-MEAL", &analyze_query("prefetch-rank-MEAL"));
+        }
+
+        let top: Vec<String> = top_queries(usize::MAX).into_iter().map(|ranked| ranked.query).collect();
+        assert!(top.contains(&"prefetch-rank.example".to_string()));
+        assert!(!top.contains(&"prefetch-rank-MEAL".to_string()));
+    }
+}
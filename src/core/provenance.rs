@@ -0,0 +1,192 @@
+// WHOIS Server - Response Provenance
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Data sources and timings behind a response
+//!
+//! While a query is processed inside [`collect`], services record the
+//! upstreams and caches they consult with [`record_source`]. The collected
+//! report fills the `{sources}` banner variable and, when enabled with
+//! `--provenance` or requested with an `X-WHOIS-DEBUG: 1` header, is appended
+//! to the response as a footer:
+//!
+//! ```text
+//! % referral: whois.iana.org 31ms (cache miss)
+//! % upstream: whois.ripe.net 142ms (cache miss)
+//! % timing: lookup 174ms, format 1ms, total 175ms
+//! ```
+//!
+//! Recording outside of [`collect`] (background tasks, the library API) only
+//! feeds the upstream latency metrics (`core::metrics`).
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+tokio::task_local! {
+    static PROVENANCE: Arc<Mutex<Vec<SourceEntry>>>;
+}
+
+/// Append the provenance footer to every response
+static FOOTER_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// One upstream or cache consulted for a response
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceEntry {
+    /// Role of the source (`upstream`, `referral`, `cache`, ...)
+    pub kind: &'static str,
+    /// Host or cache name
+    pub source: String,
+    pub elapsed: Duration,
+    /// Cache status or outcome (`cache hit`, `cache miss`, `coalesced`, `failed`)
+    pub status: &'static str,
+}
+
+/// Sources and stage timings collected for one response
+#[derive(Debug, Clone, Default)]
+pub struct ProvenanceReport {
+    pub sources: Vec<SourceEntry>,
+    pub stages: Vec<(&'static str, Duration)>,
+}
+
+/// Enable the provenance footer for all responses
+pub fn init_provenance(enabled: bool) {
+    FOOTER_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether the provenance footer should be added to this request's response
+pub fn footer_requested(request: &str) -> bool {
+    FOOTER_ENABLED.load(Ordering::Relaxed) || request.lines().any(|line| {
+        let Some((name, value)) = line.split_once(':') else {
+            return false;
+        };
+        name.trim().eq_ignore_ascii_case("X-WHOIS-DEBUG") &&
+            matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on")
+    })
+}
+
+/// Run `future`, collecting the sources recorded while it runs
+///
+/// A collection nested in another also reports its sources to the outer one.
+pub async fn collect<F: Future>(future: F) -> (F::Output, ProvenanceReport) {
+    let sources = Arc::new(Mutex::new(Vec::new()));
+    let output = PROVENANCE.scope(sources.clone(), future).await;
+    let sources = std::mem::take(&mut *sources.lock().unwrap_or_else(|e| e.into_inner()));
+    let _ = PROVENANCE.try_with(|outer| outer.lock().unwrap_or_else(|e| e.into_inner()).extend(sources.iter().cloned()));
+    (output, ProvenanceReport { sources, stages: Vec::new() })
+}
+
+/// Record a source consulted for the response being collected
+pub fn record_source(kind: &'static str, source: &str, elapsed: Duration, status: &'static str) {
+    if kind != "cache" {
+        crate::core::metrics::record_upstream(source, elapsed, status);
+    }
+    let _ = PROVENANCE.try_with(|sources| {
+        sources.lock().unwrap_or_else(|e| e.into_inner()).push(SourceEntry {
+            kind,
+            source: source.to_string(),
+            elapsed,
+            status,
+        });
+    });
+}
+
+impl ProvenanceReport {
+    /// Record the duration of a processing stage
+    pub fn add_stage(&mut self, name: &'static str, elapsed: Duration) {
+        self.stages.push((name, elapsed));
+    }
+
+    /// Distinct source names, in the order they were consulted
+    pub fn source_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for entry in &self.sources {
+            if !names.contains(&entry.source) {
+                names.push(entry.source.clone());
+            }
+        }
+        names
+    }
+
+    /// Distinct upstream servers, in the order they were consulted
+    pub fn upstream_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for entry in self.sources.iter().filter(|entry| entry.kind != "cache") {
+            if !names.contains(&entry.source) {
+                names.push(entry.source.clone());
+            }
+        }
+        names
+    }
+
+    /// Render the report as WHOIS comment lines
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.sources {
+            out.push_str(&format!(
+                "% {}: {} {}ms ({})\r\n",
+                entry.kind,
+                entry.source,
+                entry.elapsed.as_millis(),
+                entry.status
+            ));
+        }
+        if !self.stages.is_empty() {
+            let stages: Vec<String> = self.stages
+                .iter()
+                .map(|(name, elapsed)| format!("{} {}ms", name, elapsed.as_millis()))
+                .collect();
+            out.push_str(&format!("% timing: {}\r\n", stages.join(", ")));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_collect_records_sources_in_scope() {
+        let (value, mut report) = collect(async {
+            record_source("upstream", "whois.ripe.net", Duration::from_millis(142), "cache miss");
+            record_source("upstream", "whois.ripe.net", Duration::from_millis(5), "failed");
+            42
+        }).await;
+        report.add_stage("total", Duration::from_millis(150));
+
+        assert_eq!(value, 42);
+        assert_eq!(report.source_names(), vec!["whois.ripe.net"]);
+        assert_eq!(
+            report.render(),
+            "% upstream: whois.ripe.net 142ms (cache miss)\r\n% upstream: whois.ripe.net 5ms (failed)\r\n% timing: total 150ms\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nested_collect_reports_to_outer() {
+        let ((_, inner), outer) = collect(async {
+            collect(async {
+                record_source("upstream", "whois.arin.net", Duration::from_millis(80), "cache miss");
+            }).await
+        }).await;
+        assert_eq!(inner.source_names(), vec!["whois.arin.net"]);
+        assert_eq!(outer.source_names(), vec!["whois.arin.net"]);
+    }
+
+    #[tokio::test]
+    async fn test_recording_outside_scope_is_ignored() {
+        record_source("upstream", "whois.ripe.net", Duration::ZERO, "cache miss");
+        let (_, report) = collect(async {}).await;
+        assert!(report.sources.is_empty());
+    }
+
+    #[test]
+    fn test_debug_header() {
+        assert!(footer_requested("X-WHOIS-DEBUG: 1\r\nexample.com\r\n"));
+        assert!(footer_requested("x-whois-debug: true\r\nexample.com\r\n"));
+        assert!(!footer_requested("X-WHOIS-DEBUG: 0\r\nexample.com\r\n"));
+        assert!(!footer_requested("example.com\r\n"));
+    }
+}
@@ -0,0 +1,264 @@
+// WHOIS Server - Public Suffix List
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Public Suffix List (publicsuffix.org)
+//!
+//! A copy of the list is bundled in `data/public_suffix_list.dat` so lookups
+//! work offline; [`psl_refresh_job`] replaces it with the current
+//! list at startup and once a day. Lookups follow the PSL algorithm:
+//! exception rules win, otherwise the longest matching rule (including
+//! wildcards) is the public suffix, and unlisted TLDs fall back to the
+//! implicit `*` rule.
+//!
+//! Domain WHOIS uses the ICANN section only, so `www.example.co.uk` is sent
+//! upstream as `example.co.uk` while `user.github.io` stays a registry
+//! lookup for `github.io`.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::scheduler::{Job, Schedule};
+use crate::log_debug;
+
+const BUNDLED_PSL: &str = include_str!("../../data/public_suffix_list.dat");
+const PSL_URL: &str = "https://publicsuffix.org/list/public_suffix_list.dat";
+const REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+/// A downloaded list with fewer rules than this is treated as truncated
+const MIN_RULES: usize = 5000;
+
+static PSL: Lazy<RwLock<Arc<PublicSuffixList>>> = Lazy::new(|| {
+    RwLock::new(Arc::new(PublicSuffixList::parse(BUNDLED_PSL, None)))
+});
+
+/// Section of the list a rule comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuffixSection {
+    /// Delegated by ICANN (TLDs and registry-operated second levels)
+    Icann,
+    /// Submitted by private parties (`github.io`, `blogspot.com`, ...)
+    Private,
+}
+
+impl SuffixSection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuffixSection::Icann => "ICANN",
+            SuffixSection::Private => "PRIVATE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleKind {
+    Normal,
+    Wildcard,
+    Exception,
+}
+
+/// Structure of a domain name according to the list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainParts {
+    /// Lowercase ASCII (punycode) form of the name
+    pub domain: String,
+    /// Public suffix, e.g. `co.uk`
+    pub suffix: String,
+    /// Rule that selected the suffix, e.g. `*.ck` or `!www.ck`
+    pub rule: String,
+    /// Section of the rule, `None` for the implicit `*` rule
+    pub section: Option<SuffixSection>,
+    /// Suffix plus one label, `None` when the name is itself a public suffix
+    pub registrable: Option<String>,
+}
+
+/// Parsed Public Suffix List
+pub struct PublicSuffixList {
+    rules: HashMap<String, (RuleKind, SuffixSection)>,
+    /// When the list was downloaded, `None` for the bundled copy
+    pub fetched_at: Option<DateTime<Utc>>,
+}
+
+/// Lowercase ASCII form of a name, converting IDN labels to punycode
+pub fn to_ascii(name: &str) -> Option<String> {
+    let name = name.trim().trim_end_matches('.');
+    if name.is_ascii() {
+        return Some(name.to_ascii_lowercase());
+    }
+    let url = reqwest::Url::parse(&format!("http://{}/", name)).ok()?;
+    url.host_str().map(str::to_string)
+}
+
+impl PublicSuffixList {
+    /// Parse the list text (`//` comments, one rule per line)
+    pub fn parse(text: &str, fetched_at: Option<DateTime<Utc>>) -> Self {
+        let mut rules = HashMap::new();
+        let mut section = SuffixSection::Icann;
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with("// ===BEGIN PRIVATE DOMAINS===") {
+                section = SuffixSection::Private;
+            } else if line.starts_with("// ===BEGIN ICANN DOMAINS===") {
+                section = SuffixSection::Icann;
+            }
+            let Some(rule) = line.split_whitespace().next().filter(|r| !r.starts_with("//")) else {
+                continue;
+            };
+
+            let (kind, name) = if let Some(name) = rule.strip_prefix('!') {
+                (RuleKind::Exception, name)
+            } else if let Some(parent) = rule.strip_prefix("*.") {
+                (RuleKind::Wildcard, parent)
+            } else {
+                (RuleKind::Normal, rule)
+            };
+            let Some(name) = to_ascii(name) else {
+                continue;
+            };
+            let key = if kind == RuleKind::Wildcard { format!("*.{}", name) } else { name };
+            rules.insert(key, (kind, section));
+        }
+
+        Self { rules, fetched_at }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Split `name` into public suffix and registrable domain
+    ///
+    /// With `include_private` false, rules from the private section are
+    /// ignored, giving the domain as registered with an ICANN registry.
+    pub fn lookup(&self, name: &str, include_private: bool) -> Option<DomainParts> {
+        let domain = to_ascii(name)?;
+        let labels: Vec<&str> = domain.split('.').collect();
+        if labels.iter().any(|label| label.is_empty()) {
+            return None;
+        }
+        let n = labels.len();
+        let rule = |key: &str, kind: RuleKind| {
+            self.rules
+                .get(key)
+                .filter(|(k, section)| *k == kind && (include_private || *section == SuffixSection::Icann))
+                .map(|(_, section)| *section)
+        };
+
+        // (suffix length in labels, rule text, section)
+        let mut best: (usize, String, Option<SuffixSection>) = (1, "*".to_string(), None);
+        for k in 1..=n {
+            let candidate = labels[n - k..].join(".");
+            if let Some(section) = rule(&candidate, RuleKind::Exception) {
+                best = (k - 1, format!("!{}", candidate), Some(section));
+                break;
+            }
+            if let Some(section) = rule(&candidate, RuleKind::Normal) {
+                best = (k, candidate.clone(), Some(section));
+            }
+            if k >= 2 {
+                let wildcard = format!("*.{}", labels[n - k + 1..].join("."));
+                if let Some(section) = rule(&wildcard, RuleKind::Wildcard) {
+                    best = (k, wildcard, Some(section));
+                }
+            }
+        }
+
+        let (suffix_len, rule, section) = best;
+        let suffix = labels[n - suffix_len..].join(".");
+        let registrable = (n > suffix_len).then(|| labels[n - suffix_len - 1..].join("."));
+        Some(DomainParts { domain, suffix, rule, section, registrable })
+    }
+}
+
+/// The list currently in use
+pub fn public_suffix_list() -> Arc<PublicSuffixList> {
+    PSL.read().map(|psl| psl.clone()).unwrap_or_else(|e| e.into_inner().clone())
+}
+
+/// Domain as registered with its ICANN registry, e.g. `example.co.uk` for
+/// `www.example.co.uk`
+pub fn registrable_domain(name: &str) -> Option<String> {
+    public_suffix_list().lookup(name, false)?.registrable
+}
+
+/// Download the current list and swap it in
+pub async fn refresh_public_suffix_list() -> Result<usize> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent("whois-server/1.0")
+        .build()?;
+    let response = client.get(PSL_URL).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Public Suffix List download failed: HTTP {}", response.status()));
+    }
+    let text = response.limited_text().await?;
+
+    let list = PublicSuffixList::parse(&text, Some(Utc::now()));
+    if list.len() < MIN_RULES || !text.contains("===BEGIN PRIVATE DOMAINS===") {
+        return Err(anyhow!("Public Suffix List download looks truncated ({} rules)", list.len()));
+    }
+    let rules = list.len();
+    match PSL.write() {
+        Ok(mut psl) => *psl = Arc::new(list),
+        Err(e) => *e.into_inner() = Arc::new(list),
+    }
+    Ok(rules)
+}
+
+/// Daily Public Suffix List refresh, also run at startup; a failed refresh keeps the current copy
+pub fn psl_refresh_job() -> Job {
+    Job::new("psl-refresh", Schedule::Every(REFRESH_INTERVAL), || async {
+        let rules = refresh_public_suffix_list().await?;
+        log_debug!("Public Suffix List refreshed ({} rules)", rules);
+        Ok(())
+    })
+    .with_jitter(Duration::from_secs(600))
+    .run_at_start()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(name: &str, include_private: bool) -> (String, Option<String>, Option<SuffixSection>) {
+        let parts = public_suffix_list().lookup(name, include_private).unwrap();
+        (parts.suffix, parts.registrable, parts.section)
+    }
+
+    #[test]
+    fn test_bundled_list_lookups() {
+        assert!(public_suffix_list().len() > MIN_RULES);
+        assert_eq!(
+            parts("www.Example.co.uk", true),
+            ("co.uk".to_string(), Some("example.co.uk".to_string()), Some(SuffixSection::Icann))
+        );
+        assert_eq!(parts("example.com.cn", true).1, Some("example.com.cn".to_string()));
+        assert_eq!(parts("co.uk", true).1, None);
+        assert_eq!(parts("foo.example.invalidtld", true), (
+            "invalidtld".to_string(),
+            Some("example.invalidtld".to_string()),
+            None,
+        ));
+    }
+
+    #[test]
+    fn test_private_wildcard_and_exception_rules() {
+        assert_eq!(
+            parts("user.github.io", true),
+            ("github.io".to_string(), Some("user.github.io".to_string()), Some(SuffixSection::Private))
+        );
+        assert_eq!(registrable_domain("user.github.io"), Some("github.io".to_string()));
+        assert_eq!(parts("a.b.ck", true).0, "b.ck");
+        assert_eq!(parts("www.ck", true), ("ck".to_string(), Some("www.ck".to_string()), Some(SuffixSection::Icann)));
+        assert_eq!(parts("公司.cn", true).0, "xn--55qx5d.cn");
+    }
+}
@@ -1,11 +1,31 @@
 use crate::config::{ PRIVATE_IPV4_RANGES, PRIVATE_IPV6_RANGES };
+use crate::core::alias::resolve_aliases;
+use crate::core::modifiers::split_modifiers;
+use crate::core::public_suffix::to_ascii;
+use crate::core::regex_cache::CachedRegex;
+use crate::services::registry::service_registry;
+use crate::services::gameserver::split_protocol_suffix;
+use crate::services::homoglyph::to_unicode;
+use crate::services::steam::split_steam_region;
+use crate::services::utils::split_vantage_point;
+use crate::core::sanitize::{ ends_with_ignore_case, strip_prefix_ignore_case, strip_suffix_ignore_case };
 use cidr::{ Ipv4Cidr, Ipv6Cidr };
-use regex::Regex;
 use std::net::{ IpAddr, Ipv4Addr, Ipv6Addr };
 use std::sync::RwLock;
+use std::time::Duration;
+
+// Internationalized TLDs only exist in their A-label form (xn--p1ai)
+static DOMAIN_RE: CachedRegex = CachedRegex::new(
+    r"^([a-zA-Z0-9]([a-zA-Z0-9\-]{0,61}[a-zA-Z0-9])?\.)+([a-zA-Z]{2,}|[xX][nN]--[a-zA-Z0-9\-]{1,59})$"
+);
+
+/// Patterns checked by `regex_cache::precompile_builtin_regexes` at startup
+pub(crate) fn builtin_regexes() -> Vec<&'static CachedRegex> {
+    vec![&DOMAIN_RE]
+}
 
 // WHOIS query types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum QueryType {
     Domain(String),
     IPv4(Ipv4Addr),
@@ -31,35 +51,21 @@ pub enum QueryType {
     Ris(String), // For queries ending with -RIS (RIPE RIS)
     Tc(String), // For queries ending with -TC
     Irr(String), // For queries ending with -IRR (general IRR Explorer)
-    LookingGlass(String), // For queries ending with -LG
+    LookingGlass(String, Option<String>), // For queries ending with -LG, with optional @<collector location>
     Rpki(String, String), // For queries in format prefix-asn-RPKI (prefix, asn)
     Manrs(String), // For queries ending with -MANRS
     Dns(String), // For queries ending with -DNS
-    Trace(String), // For queries ending with -TRACE
+    Trace(String, Option<String>), // For queries ending with -TRACE, with optional @<vantage point>
     Ssl(String), // For queries ending with -SSL
     Crt(String), // For queries ending with -CRT (Certificate Transparency)
     CfStatus(String), // For queries ending with -CFSTATUS (Cloudflare Status)
     Minecraft(String), // For queries ending with -MINECRAFT or -MC
     MinecraftUser(String), // For queries ending with -MCU (Minecraft user info)
-    Steam(String), // For queries ending with -STEAM (Steam games/users)
-    SteamSearch(String), // For queries ending with -STEAMSEARCH (Steam game search)
+    Steam(String, Option<String>), // For queries ending with -STEAM (Steam games/users), with optional -CC=<region>
+    SteamSearch(String, Option<String>), // For queries ending with -STEAMSEARCH (Steam game search), with optional -CC=<region>
     Imdb(String), // For queries ending with -IMDB (IMDb movies/TV shows)
     ImdbSearch(String), // For queries ending with -IMDBSEARCH (IMDb title search)
     Acgc(String), // For queries ending with -ACGC (Anime/Comic/Game Characters)
-    Alma(String), // For queries ending with -ALMA (AlmaLinux packages)
-    Aosc(String), // For queries ending with -AOSC (AOSC packages)
-    Aur(String), // For queries ending with -AUR (Arch User Repository)
-    Debian(String), // For queries ending with -DEBIAN (Debian packages)
-    Epel(String), // For queries ending with -EPEL (EPEL packages)
-    Ubuntu(String), // For queries ending with -UBUNTU (Ubuntu packages)
-    NixOs(String), // For queries ending with -NIXOS (NixOS packages)
-    OpenSuse(String), // For queries ending with -OPENSUSE (OpenSUSE packages)
-    OpenWrt(String), // For queries ending with -OPENWRT (OpenWrt packages)
-    Npm(String), // For queries ending with -NPM (NPM packages)
-    Pypi(String), // For queries ending with -PYPI (PyPI packages)
-    Cargo(String), // For queries ending with -CARGO (Rust crates)
-    Modrinth(String), // For queries ending with -MODRINTH (Modrinth mods/resource packs)
-    CurseForge(String), // For queries ending with -CURSEFORGE (CurseForge mods)
     GitHub(String), // For queries ending with -GITHUB (GitHub users/repos)
     Wikipedia(String), // For queries ending with -WIKIPEDIA (Wikipedia articles)
     Lyric(String), // For queries ending with -LYRIC (Luotianyi random lyrics)
@@ -72,38 +78,65 @@ pub enum QueryType {
     Meal, // For meal suggestions (今天吃什么 or -MEAL)
     MealCN, // For Chinese meal suggestions (今天吃什么中国 or -MEAL-CN)
     Ntp(String), // For NTP time synchronization test (-NTP)
-    Ping(String), // For ICMP ping test (-PING)
+    Ping(String, Option<String>), // For ICMP ping test (-PING), with optional @<vantage point>
+    Probes(Option<String>), // For PROBES queries (Globalping probe availability, optional location filter)
     Help, // For HELP queries (show available query types)
     UpdatePatch, // For UPDATE-PATCH queries (update patches from remote repository)
+    Explain(String), // For queries ending with -EXPLAIN (show routing without executing)
+    Service(&'static str, String), // For registered services (service name, base_query)
     Plugin(String, String), // For plugin-handled queries (suffix, base_query)
     Unknown(String),
 }
 
 pub fn analyze_query(query: &str) -> QueryType {
+    // Operator aliases are resolved first; output modifiers (-JSON, -MD,
+    // -P<n>) do not change the service
+    let query = resolve_aliases(query);
+    analyze_primary(split_modifiers(&query).0)
+}
+
+/// Detect the service for a query without trailing output modifiers
+pub(crate) fn analyze_primary(query: &str) -> QueryType {
+    // Check if it's an explain query (wraps any other query, so it comes first)
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-EXPLAIN") {
+        return QueryType::Explain(base_query.to_string());
+    }
+
     // Check if it's a Chinese meal suggestion query
-    if query == "今天吃什么中国" || query.to_uppercase().ends_with("-MEAL-CN") {
+    if query == "今天吃什么中国" || ends_with_ignore_case(query, "-MEAL-CN") {
         return QueryType::MealCN;
     }
 
     // Check if it's a meal suggestion query (Chinese phrase or -MEAL suffix)
-    if query == "今天吃什么" || query.to_uppercase().ends_with("-MEAL") {
+    if query == "今天吃什么" || ends_with_ignore_case(query, "-MEAL") {
         return QueryType::Meal;
     }
 
     // Check if it's a HELP query (case-insensitive)
-    if query.to_uppercase() == "HELP" {
+    if query.eq_ignore_ascii_case("HELP") {
         return QueryType::Help;
     }
 
     // Check if it's an UPDATE-PATCH query (case-insensitive)
-    if query.to_uppercase() == "UPDATE-PATCH" || query.to_uppercase() == "-UPDATE-PATCH" {
+    if query.eq_ignore_ascii_case("UPDATE-PATCH") || query.eq_ignore_ascii_case("-UPDATE-PATCH") {
         return QueryType::UpdatePatch;
     }
 
-    // Check if it's an RPKI query in format PREFIX-ASN-RPKI
-    if query.to_uppercase().ends_with("-RPKI") {
-        let base_query = &query[..query.len() - 5]; // Remove "-RPKI" suffix
+    // Check if it's a Globalping probe listing, optionally filtered by location
+    if query.eq_ignore_ascii_case("PROBES") {
+        return QueryType::Probes(None);
+    }
+    if let Some(filter) = strip_suffix_ignore_case(query, "-PROBES") {
+        return QueryType::Probes(Some(filter.to_string()).filter(|f| !f.is_empty()));
+    }
+
+    // Game server queries may name the protocol last: host:27015-GAMESERVER-A2S
+    if let Some(target) = split_protocol_suffix(query) {
+        return QueryType::Service("gameserver", target);
+    }
 
+    // Check if it's an RPKI query in format PREFIX-ASN-RPKI
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-RPKI") {
         // Try to parse as prefix-asn format
         if let Some(dash_pos) = base_query.rfind('-') {
             let prefix_part = &base_query[..dash_pos];
@@ -142,363 +175,249 @@ pub fn analyze_query(query: &str) -> QueryType {
         return QueryType::Unknown(query.to_string());
     }
 
-    // Check if it's a Looking Glass query
-    if query.to_uppercase().ends_with("-LG") {
-        let base_query = &query[..query.len() - 3]; // Remove "-LG" suffix
-        return QueryType::LookingGlass(base_query.to_string());
+    // Check if it's a Looking Glass query, with optional @<collector location>
+    let (looking_glass, collectors) = split_vantage_point(query);
+    if let Some(base_query) = strip_suffix_ignore_case(looking_glass, "-LG") {
+        return QueryType::LookingGlass(base_query.to_string(), collectors);
     }
 
     // Check if it's an IRR Explorer query
-    if query.to_uppercase().ends_with("-IRR") {
-        let base_query = &query[..query.len() - 4]; // Remove "-IRR" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-IRR") {
         return QueryType::Irr(base_query.to_string());
     }
 
     // Check if it's a RADB query
-    if query.to_uppercase().ends_with("-RADB") {
-        let base_query = &query[..query.len() - 5]; // Remove "-RADB" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-RADB") {
         return QueryType::Radb(base_query.to_string());
     }
 
     // Check if it's an ALTDB query
-    if query.to_uppercase().ends_with("-ALTDB") {
-        let base_query = &query[..query.len() - 6]; // Remove "-ALTDB" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-ALTDB") {
         return QueryType::Altdb(base_query.to_string());
     }
 
     // Check if it's an AFRINIC IRR query
-    if query.to_uppercase().ends_with("-AFRINIC") {
-        let base_query = &query[..query.len() - 8]; // Remove "-AFRINIC" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-AFRINIC") {
         return QueryType::Afrinic(base_query.to_string());
     }
 
     // Check if it's an APNIC IRR query
-    if query.to_uppercase().ends_with("-APNIC") {
-        let base_query = &query[..query.len() - 6]; // Remove "-APNIC" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-APNIC") {
         return QueryType::Apnic(base_query.to_string());
     }
 
     // Check if it's an ARIN IRR query
-    if query.to_uppercase().ends_with("-ARIN") {
-        let base_query = &query[..query.len() - 5]; // Remove "-ARIN" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-ARIN") {
         return QueryType::ArinIrr(base_query.to_string());
     }
 
     // Check if it's a BELL IRR query
-    if query.to_uppercase().ends_with("-BELL") {
-        let base_query = &query[..query.len() - 5]; // Remove "-BELL" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-BELL") {
         return QueryType::Bell(base_query.to_string());
     }
 
     // Check if it's a JPIRR query
-    if query.to_uppercase().ends_with("-JPIRR") {
-        let base_query = &query[..query.len() - 6]; // Remove "-JPIRR" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-JPIRR") {
         return QueryType::Jpirr(base_query.to_string());
     }
 
     // Check if it's a LACNIC IRR query
-    if query.to_uppercase().ends_with("-LACNIC") {
-        let base_query = &query[..query.len() - 7]; // Remove "-LACNIC" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-LACNIC") {
         return QueryType::Lacnic(base_query.to_string());
     }
 
     // Check if it's a LEVEL3 IRR query
-    if query.to_uppercase().ends_with("-LEVEL3") {
-        let base_query = &query[..query.len() - 7]; // Remove "-LEVEL3" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-LEVEL3") {
         return QueryType::Level3(base_query.to_string());
     }
 
     // Check if it's an NTTCOM IRR query
-    if query.to_uppercase().ends_with("-NTTCOM") {
-        let base_query = &query[..query.len() - 7]; // Remove "-NTTCOM" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-NTTCOM") {
         return QueryType::Nttcom(base_query.to_string());
     }
 
     // Check if it's a RIPE IRR query
-    if query.to_uppercase().ends_with("-RIPE") {
-        let base_query = &query[..query.len() - 5]; // Remove "-RIPE" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-RIPE") {
         return QueryType::RipeIrr(base_query.to_string());
     }
 
     // Check if it's a RIS query
-    if query.to_uppercase().ends_with("-RIS") {
-        let base_query = &query[..query.len() - 4]; // Remove "-RIS" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-RIS") {
         return QueryType::Ris(base_query.to_string());
     }
 
     // Check if it's a TC IRR query
-    if query.to_uppercase().ends_with("-TC") {
-        let base_query = &query[..query.len() - 3]; // Remove "-TC" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-TC") {
         return QueryType::Tc(base_query.to_string());
     }
 
     // Check if it's a MANRS query
-    if query.to_uppercase().ends_with("-MANRS") {
-        let base_query = &query[..query.len() - 6]; // Remove "-MANRS" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-MANRS") {
         return QueryType::Manrs(base_query.to_string());
     }
 
     // Check if it's a DNS query
-    if query.to_uppercase().ends_with("-DNS") {
-        let base_query = &query[..query.len() - 4]; // Remove "-DNS" suffix
-        return QueryType::Dns(base_query.to_string());
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-DNS") {
+        return QueryType::Dns(a_label_or_original(base_query));
     }
 
     // Check if it's an NTP query
-    if query.to_uppercase().ends_with("-NTP") {
-        let base_query = &query[..query.len() - 4]; // Remove "-NTP" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-NTP") {
         return QueryType::Ntp(base_query.to_string());
     }
 
+    // Ping and traceroute accept a trailing @<vantage point> selector
+    let (measured, vantage) = split_vantage_point(query);
+
     // Check if it's a ping query
-    if query.to_uppercase().ends_with("-PING") {
-        let base_query = &query[..query.len() - 5]; // Remove "-PING" suffix
-        return QueryType::Ping(base_query.to_string());
+    if let Some(base_query) = strip_suffix_ignore_case(measured, "-PING") {
+        return QueryType::Ping(base_query.to_string(), vantage);
     }
 
     // Check if it's a traceroute query (long form)
-    if query.to_uppercase().ends_with("-TRACEROUTE") {
-        let base_query = &query[..query.len() - 11]; // Remove "-TRACEROUTE" suffix
-        return QueryType::Trace(base_query.to_string());
+    if let Some(base_query) = strip_suffix_ignore_case(measured, "-TRACEROUTE") {
+        return QueryType::Trace(base_query.to_string(), vantage);
     }
 
     // Check if it's a traceroute query (short form)
-    if query.to_uppercase().ends_with("-TRACE") {
-        let base_query = &query[..query.len() - 6]; // Remove "-TRACE" suffix
-        return QueryType::Trace(base_query.to_string());
+    if let Some(base_query) = strip_suffix_ignore_case(measured, "-TRACE") {
+        return QueryType::Trace(base_query.to_string(), vantage);
     }
 
-    // Check if it's an SSL certificate query
-    if query.to_uppercase().ends_with("-SSL") {
-        let base_query = &query[..query.len() - 4]; // Remove "-SSL" suffix
+    // Check if it's an SSL certificate query; STARTTLS queries keep the
+    // marker in the target for the SSL service
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-SSL-STARTTLS") {
+        return QueryType::Ssl(format!("{}-STARTTLS", base_query));
+    }
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-SSL") {
         return QueryType::Ssl(base_query.to_string());
     }
 
     // Check if it's a Certificate Transparency query
-    if query.to_uppercase().ends_with("-CRT") {
-        let base_query = &query[..query.len() - 4]; // Remove "-CRT" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-CRT") {
         return QueryType::Crt(base_query.to_string());
     }
 
     // Check if it's a Cloudflare Status query
-    if query.to_uppercase().ends_with("-CFSTATUS") {
-        let base_query = &query[..query.len() - 9]; // Remove "-CFSTATUS" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-CFSTATUS") {
         return QueryType::CfStatus(base_query.to_string());
     }
 
     // Check if it's a Minecraft server query
-    if query.to_uppercase().ends_with("-MINECRAFT") {
-        let base_query = &query[..query.len() - 10]; // Remove "-MINECRAFT" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-MINECRAFT") {
         return QueryType::Minecraft(base_query.to_string());
     }
 
     // Check if it's a Minecraft user query
-    if query.to_uppercase().ends_with("-MCU") {
-        let base_query = &query[..query.len() - 4]; // Remove "-MCU" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-MCU") {
         return QueryType::MinecraftUser(base_query.to_string());
     }
 
     // Check if it's a Minecraft server query (short form)
-    if query.to_uppercase().ends_with("-MC") {
-        let base_query = &query[..query.len() - 3]; // Remove "-MC" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-MC") {
         return QueryType::Minecraft(base_query.to_string());
     }
 
+    // Steam queries may select a store region for prices: 730-STEAM-CC=JP
+    let (steam_query, region) = split_steam_region(query);
+
     // Check if it's a Steam search query (must be checked before regular Steam query)
-    if query.to_uppercase().ends_with("-STEAMSEARCH") {
-        let base_query = &query[..query.len() - 12]; // Remove "-STEAMSEARCH" suffix
-        return QueryType::SteamSearch(base_query.to_string());
+    if let Some(base_query) = strip_suffix_ignore_case(steam_query, "-STEAMSEARCH") {
+        return QueryType::SteamSearch(base_query.to_string(), region);
     }
 
     // Check if it's a Steam game/user query
-    if query.to_uppercase().ends_with("-STEAM") {
-        let base_query = &query[..query.len() - 6]; // Remove "-STEAM" suffix
-        return QueryType::Steam(base_query.to_string());
+    if let Some(base_query) = strip_suffix_ignore_case(steam_query, "-STEAM") {
+        return QueryType::Steam(base_query.to_string(), region);
     }
 
     // Check if it's an IMDb search query (must be checked before regular IMDb query)
-    if query.to_uppercase().ends_with("-IMDBSEARCH") {
-        let base_query = &query[..query.len() - 11]; // Remove "-IMDBSEARCH" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-IMDBSEARCH") {
         return QueryType::ImdbSearch(base_query.to_string());
     }
 
     // Check if it's an IMDb movie/TV show query
-    if query.to_uppercase().ends_with("-IMDB") {
-        let base_query = &query[..query.len() - 5]; // Remove "-IMDB" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-IMDB") {
         return QueryType::Imdb(base_query.to_string());
     }
 
     // Check if it's an ACGC character query
-    if query.to_uppercase().ends_with("-ACGC") {
-        let base_query = &query[..query.len() - 5]; // Remove "-ACGC" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-ACGC") {
         return QueryType::Acgc(base_query.to_string());
     }
 
-    // Check if it's an AlmaLinux package query
-    if query.to_uppercase().ends_with("-ALMA") {
-        let base_query = &query[..query.len() - 5]; // Remove "-ALMA" suffix
-        return QueryType::Alma(base_query.to_string());
-    }
-
-    // Check if it's an AOSC package query
-    if query.to_uppercase().ends_with("-AOSC") {
-        let base_query = &query[..query.len() - 5]; // Remove "-AOSC" suffix
-        return QueryType::Aosc(base_query.to_string());
-    }
-
-    // Check if it's an AUR package query
-    if query.to_uppercase().ends_with("-AUR") {
-        let base_query = &query[..query.len() - 4]; // Remove "-AUR" suffix
-        return QueryType::Aur(base_query.to_string());
-    }
-
-    // Check if it's a Debian package query
-    if query.to_uppercase().ends_with("-DEBIAN") {
-        let base_query = &query[..query.len() - 7]; // Remove "-DEBIAN" suffix
-        return QueryType::Debian(base_query.to_string());
-    }
-
-    // Check if it's an EPEL package query
-    if query.to_uppercase().ends_with("-EPEL") {
-        let base_query = &query[..query.len() - 5]; // Remove "-EPEL" suffix
-        return QueryType::Epel(base_query.to_string());
-    }
-
-    // Check if it's an Ubuntu package query
-    if query.to_uppercase().ends_with("-UBUNTU") {
-        let base_query = &query[..query.len() - 7]; // Remove "-UBUNTU" suffix
-        return QueryType::Ubuntu(base_query.to_string());
-    }
-
-    // Check if it's a NixOS package query
-    if query.to_uppercase().ends_with("-NIXOS") {
-        let base_query = &query[..query.len() - 6]; // Remove "-NIXOS" suffix
-        return QueryType::NixOs(base_query.to_string());
-    }
-
-    // Check if it's an OpenSUSE package query
-    if query.to_uppercase().ends_with("-OPENSUSE") {
-        let base_query = &query[..query.len() - 9]; // Remove "-OPENSUSE" suffix
-        return QueryType::OpenSuse(base_query.to_string());
-    }
-
-    // Check if it's an OpenWrt package query
-    if query.to_uppercase().ends_with("-OPENWRT") {
-        let base_query = &query[..query.len() - 8]; // Remove "-OPENWRT" suffix
-        return QueryType::OpenWrt(base_query.to_string());
-    }
-
-    // Check if it's an NPM package query
-    if query.to_uppercase().ends_with("-NPM") {
-        let base_query = &query[..query.len() - 4]; // Remove "-NPM" suffix
-        return QueryType::Npm(base_query.to_string());
-    }
-
-    // Check if it's a PyPI package query
-    if query.to_uppercase().ends_with("-PYPI") {
-        let base_query = &query[..query.len() - 5]; // Remove "-PYPI" suffix
-        return QueryType::Pypi(base_query.to_string());
-    }
-
-    // Check if it's a Cargo (Rust crates) package query
-    if query.to_uppercase().ends_with("-CARGO") {
-        let base_query = &query[..query.len() - 6]; // Remove "-CARGO" suffix
-        return QueryType::Cargo(base_query.to_string());
-    }
-
-    // Check if it's a Modrinth mod/resource pack query
-    if query.to_uppercase().ends_with("-MODRINTH") {
-        let base_query = &query[..query.len() - 9]; // Remove "-MODRINTH" suffix
-        return QueryType::Modrinth(base_query.to_string());
-    }
-
-    // Check if it's a CurseForge mod query
-    if query.to_uppercase().ends_with("-CURSEFORGE") {
-        let base_query = &query[..query.len() - 11]; // Remove "-CURSEFORGE" suffix
-        return QueryType::CurseForge(base_query.to_string());
+    // Check if it's a registered service (package repositories, ...)
+    if let Some((service, base_query)) = service_registry().match_suffix(query) {
+        return QueryType::Service(service.name(), base_query.to_string());
     }
 
     // Check if it's a GitHub user/repository query
-    if query.to_uppercase().ends_with("-GITHUB") {
-        let base_query = &query[..query.len() - 7]; // Remove "-GITHUB" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-GITHUB") {
         return QueryType::GitHub(base_query.to_string());
     }
 
     // Check if it's a Wikipedia article query
-    if query.to_uppercase().ends_with("-WIKIPEDIA") {
-        let base_query = &query[..query.len() - 10]; // Remove "-WIKIPEDIA" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-WIKIPEDIA") {
         return QueryType::Wikipedia(base_query.to_string());
     }
 
     // Check if it's a Luotianyi lyric query
-    if query.to_uppercase().ends_with("-LYRIC") {
-        let base_query = &query[..query.len() - 6]; // Remove "-LYRIC" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-LYRIC") {
         return QueryType::Lyric(base_query.to_string());
     }
 
     // Check if it's a description-only query
-    if query.to_uppercase().ends_with("-DESC") {
-        let base_query = &query[..query.len() - 5]; // Remove "-DESC" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-DESC") {
         return QueryType::Desc(base_query.to_string());
     }
 
     // Check if it's a PeeringDB query
-    if query.to_uppercase().ends_with("-PEERINGDB") {
-        let base_query = &query[..query.len() - 10]; // Remove "-PEERINGDB" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-PEERINGDB") {
         return QueryType::PeeringDB(base_query.to_string());
     }
 
     // Check if it's a IANA Private Enterprise Numbers query
-    if query.to_uppercase().ends_with("-PEN") {
-        let base_query = &query[..query.len() - 4]; // Remove "-PEN" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-PEN") {
         return QueryType::Pen(base_query.to_string());
     }
 
     // Check if it's a RDAP query
-    if query.to_uppercase().ends_with("-RDAP") {
-        let base_query = &query[..query.len() - 5]; // Remove "-RDAP" suffix
-        return QueryType::Rdap(base_query.to_string());
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-RDAP") {
+        return QueryType::Rdap(a_label_or_original(base_query));
     }
 
     // Check if it's a Pixiv query
-    if query.to_uppercase().ends_with("-PIXIV") {
-        let base_query = &query[..query.len() - 6]; // Remove "-PIXIV" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-PIXIV") {
         return QueryType::Pixiv(base_query.to_string());
     }
 
     // Check if it's an ICP filing query
-    if query.to_uppercase().ends_with("-ICP") {
-        let base_query = &query[..query.len() - 4]; // Remove "-ICP" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-ICP") {
         return QueryType::Icp(base_query.to_string());
     }
 
     // Check if it's a BGP Tools query
-    if query.to_uppercase().ends_with("-BGPTOOL") {
-        let base_query = &query[..query.len() - 8]; // Remove "-BGPTOOL" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-BGPTOOL") {
         return QueryType::BGPTool(base_query.to_string());
     }
 
     // Check if it's a prefixes query
-    if query.to_uppercase().ends_with("-PREFIXES") {
-        let base_query = &query[..query.len() - 9]; // Remove "-PREFIXES" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-PREFIXES") {
         return QueryType::Prefixes(base_query.to_string());
     }
 
     // Check if it's a RIR geo query
-    if query.to_uppercase().ends_with("-RIRGEO") {
-        let base_query = &query[..query.len() - 7]; // Remove "-RIRGEO" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-RIRGEO") {
         return QueryType::RirGeo(base_query.to_string());
     }
 
     // Check if it's a geo query
-    if query.to_uppercase().ends_with("-GEO") {
-        let base_query = &query[..query.len() - 4]; // Remove "-GEO" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-GEO") {
         return QueryType::Geo(base_query.to_string());
     }
 
     // Check if it's an email search query
-    if query.to_uppercase().ends_with("-EMAIL") {
-        let base_query = &query[..query.len() - 6]; // Remove "-EMAIL" suffix
+    if let Some(base_query) = strip_suffix_ignore_case(query, "-EMAIL") {
         return QueryType::EmailSearch(base_query.to_string());
     }
 
@@ -508,7 +427,7 @@ pub fn analyze_query(query: &str) -> QueryType {
     }
 
     // Check if it has -DN42 suffix or ends with -MNT
-    if query.to_uppercase().ends_with("-DN42") || query.to_uppercase().ends_with("-MNT") {
+    if ends_with_ignore_case(query, "-DN42") || ends_with_ignore_case(query, "-MNT") {
         return QueryType::Unknown(query.to_string());
     }
 
@@ -533,25 +452,26 @@ pub fn analyze_query(query: &str) -> QueryType {
         return QueryType::IPv6(cidr.first_address());
     }
 
-    // Identify ASN
-    if query.to_uppercase().starts_with("AS") && query[2..].chars().all(|c| c.is_ascii_digit()) {
-        return QueryType::ASN(query.to_string());
+    // Identify ASN (AS13335, as13335, AS 13335, bare 13335 or asdot 1.10),
+    // normalized to the AS-prefixed asplain form
+    if let Some(asn) = parse_asn(query) {
+        return QueryType::ASN(format!("AS{}", asn));
     }
 
     // Check if it's a domain format
-    let domain_regex = Regex::new(
-        r"^([a-zA-Z0-9]([a-zA-Z0-9\-]{0,61}[a-zA-Z0-9])?\.)+[a-zA-Z]{2,}$"
-    ).expect("Invalid domain regex");
-    if domain_regex.is_match(query) {
+    if DOMAIN_RE.is_match(query) {
         return QueryType::Domain(query.to_string());
     }
 
+    // Unicode domains (例え.jp) are looked up by their A-label
+    if let Some(ascii) = idn_to_ascii(query) {
+        return QueryType::Domain(ascii);
+    }
+
     // Check for plugin-registered suffixes (before Unknown)
     if let Some(plugin_registry) = get_plugin_registry() {
-        let query_upper = query.to_uppercase();
         for suffix in plugin_registry.get_all_suffixes() {
-            if query_upper.ends_with(suffix.as_str()) {
-                let base_query = &query[..query.len() - suffix.len()];
+            if let Some(base_query) = strip_suffix_ignore_case(query, &suffix) {
                 return QueryType::Plugin(suffix, base_query.to_string());
             }
         }
@@ -561,6 +481,59 @@ pub fn analyze_query(query: &str) -> QueryType {
     QueryType::Unknown(query.to_string())
 }
 
+/// A-label (punycode) form of an internationalized domain name
+///
+/// `None` for ASCII input and for Unicode text that is not a domain name.
+pub fn idn_to_ascii(name: &str) -> Option<String> {
+    if name.is_ascii() {
+        return None;
+    }
+    to_ascii(name).filter(|ascii| DOMAIN_RE.is_match(ascii))
+}
+
+fn a_label_or_original(name: &str) -> String {
+    idn_to_ascii(name).unwrap_or_else(|| name.to_string())
+}
+
+/// Header naming both forms of an internationalized domain name
+///
+/// `None` unless one of the labels of `ascii` is an A-label (`xn--`).
+pub fn idn_header(ascii: &str) -> Option<String> {
+    let ascii = ascii.trim_end_matches('.').to_ascii_lowercase();
+    if !ascii.split('.').any(|label| label.starts_with("xn--")) {
+        return None;
+    }
+    Some(format!("% IDN U-label: {}\n% IDN A-label: {}\n", to_unicode(&ascii), ascii))
+}
+
+/// Parse an AS number in any common notation
+///
+/// Accepts an optional `AS` prefix (any case, optionally followed by spaces)
+/// and either asplain (`13335`, `4201273722`) or asdot (`1.10`) notation.
+pub fn parse_asn(input: &str) -> Option<u32> {
+    let number = strip_prefix_ignore_case(input, "AS").map(str::trim_start).unwrap_or(input);
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+
+    match number.split_once('.') {
+        None => number.parse::<u32>().ok(),
+        Some((high, low)) => {
+            let high = high.parse::<u16>().ok()?;
+            let low = low.parse::<u16>().ok()?;
+            Some(((high as u32) << 16) | low as u32)
+        }
+    }
+}
+
+/// Whether an ASN is reserved for private use (RFC 6996)
+///
+/// DN42, NeoNetwork and similar networks number their ASes from these ranges,
+/// so they are looked up in the DN42 registry rather than via IANA.
+pub fn is_private_asn(asn: u32) -> bool {
+    (64512..=65534).contains(&asn) || (4_200_000_000..=4_294_967_294).contains(&asn)
+}
+
 pub fn is_private_ipv4(ip: Ipv4Addr) -> bool {
     for range_str in PRIVATE_IPV4_RANGES {
         if let Ok(range) = range_str.parse::<Ipv4Cidr>() && range.contains(&ip) {
@@ -579,6 +552,94 @@ pub fn is_private_ipv6(ip: Ipv6Addr) -> bool {
     false
 }
 
+/// Addresses this server must not connect to on a client's behalf
+pub fn is_internal(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => is_private_ipv4(v4) || v4.is_unspecified() || v4.is_multicast() || v4.is_broadcast(),
+        IpAddr::V6(v6) =>
+            is_private_ipv6(v6) ||
+                v6.is_unspecified() ||
+                v6.is_multicast() ||
+                v6.to_ipv4_mapped().is_some_and(|v4| is_internal(IpAddr::V4(v4))),
+    }
+}
+
+/// Whether responses of this query type are stable enough to be reused or compared
+///
+/// Random, live-measurement and side-effecting queries give a different
+/// answer every time and are never cached or compared.
+pub fn is_cacheable_query(query_type: &QueryType) -> bool {
+    // Services without a response TTL (live measurements, challenges) are
+    // never prefetched or mirrored either
+    if let QueryType::Service(name, _) = query_type {
+        return service_registry().get(name).is_some_and(|service| service.cache_ttl().is_some());
+    }
+    !matches!(
+        query_type,
+        QueryType::Meal |
+            QueryType::MealCN |
+            QueryType::Lyric(_) |
+            QueryType::Ntp(_) |
+            QueryType::Ping(..) |
+            QueryType::Trace(..) |
+            QueryType::Probes(_) |
+            QueryType::LookingGlass(..) |
+            QueryType::Help |
+            QueryType::UpdatePatch |
+            QueryType::Explain(_) |
+            QueryType::Plugin(_, _)
+    )
+}
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+
+/// How long a response of this query type may be served from the response cache
+///
+/// Registration data changes rarely, routing data within minutes and live
+/// status pages constantly. Registered services declare their own TTL.
+/// `None` for query types that are never cached.
+pub fn cache_ttl(query_type: &QueryType) -> Option<Duration> {
+    if !is_cacheable_query(query_type) {
+        return None;
+    }
+    let secs = match query_type {
+        QueryType::Service(name, _) => {
+            return service_registry().get(name).and_then(|service| service.cache_ttl());
+        }
+        QueryType::BGPTool(_) |
+        QueryType::Prefixes(_) |
+        QueryType::Ris(_) |
+        QueryType::Rpki(..) |
+        QueryType::Manrs(_) => 10 * MINUTE,
+        QueryType::Radb(_) |
+        QueryType::Altdb(_) |
+        QueryType::Afrinic(_) |
+        QueryType::Apnic(_) |
+        QueryType::ArinIrr(_) |
+        QueryType::Bell(_) |
+        QueryType::Jpirr(_) |
+        QueryType::Lacnic(_) |
+        QueryType::Level3(_) |
+        QueryType::Nttcom(_) |
+        QueryType::RipeIrr(_) |
+        QueryType::Tc(_) |
+        QueryType::Irr(_) => 30 * MINUTE,
+        QueryType::Dns(_) => 5 * MINUTE,
+        QueryType::CfStatus(_) | QueryType::Minecraft(_) => 2 * MINUTE,
+        QueryType::Crt(_) | QueryType::PeeringDB(_) => 6 * HOUR,
+        QueryType::Imdb(_) |
+        QueryType::ImdbSearch(_) |
+        QueryType::Acgc(_) |
+        QueryType::Wikipedia(_) |
+        QueryType::Pixiv(_) |
+        QueryType::Pen(_) => 24 * HOUR,
+        // WHOIS/RDAP registration data, geolocation, certificates and the rest
+        _ => HOUR,
+    };
+    Some(Duration::from_secs(secs))
+}
+
 // Global plugin registry (shared across all threads)
 use crate::plugins::PluginRegistry;
 use std::sync::Arc;
@@ -596,3 +657,132 @@ pub fn get_plugin_registry() -> Option<Arc<PluginRegistry>> {
     let guard = PLUGIN_REGISTRY.read().unwrap();
     guard.clone()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal_addresses() {
+        assert!(is_internal("127.0.0.1".parse().unwrap()));
+        assert!(is_internal("10.1.2.3".parse().unwrap()));
+        assert!(is_internal("169.254.169.254".parse().unwrap()));
+        assert!(is_internal("::ffff:192.168.1.1".parse().unwrap()));
+        assert!(is_internal("::".parse().unwrap()));
+        assert!(!is_internal("1.1.1.1".parse().unwrap()));
+        assert!(!is_internal("2606:4700:4700::1111".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_flexible_asn_syntax() {
+        for query in ["AS13335", "as13335", "AS 13335", "13335"] {
+            assert_eq!(analyze_query(query), QueryType::ASN("AS13335".to_string()), "{}", query);
+        }
+        assert_eq!(analyze_query("4201273722"), QueryType::ASN("AS4201273722".to_string()));
+        assert_eq!(analyze_query("1.10"), QueryType::ASN("AS65546".to_string()));
+        assert_eq!(analyze_query("AS1.10"), QueryType::ASN("AS65546".to_string()));
+    }
+
+    #[test]
+    fn test_asn_lookalikes() {
+        assert_eq!(parse_asn("4294967296"), None);
+        assert_eq!(parse_asn("1.70000"), None);
+        assert_eq!(parse_asn("AS"), None);
+        assert!(matches!(analyze_query("AS-CLOUDFLARE"), QueryType::Unknown(_)));
+        assert!(matches!(analyze_query("1.1.1.1"), QueryType::IPv4(_)));
+    }
+
+    #[test]
+    fn test_idn_domains() {
+        assert_eq!(analyze_query("例え.jp"), QueryType::Domain("xn--r8jz45g.jp".to_string()));
+        assert_eq!(analyze_query("пример.рф-DNS"), QueryType::Dns("xn--e1afmkfd.xn--p1ai".to_string()));
+        assert_eq!(analyze_query("xn--e1afmkfd.xn--p1ai"), QueryType::Domain("xn--e1afmkfd.xn--p1ai".to_string()));
+        assert!(matches!(analyze_query("今天天气"), QueryType::Unknown(_)));
+        assert_eq!(
+            idn_header("xn--r8jz45g.jp").as_deref(),
+            Some("% IDN U-label: 例え.jp\n% IDN A-label: xn--r8jz45g.jp\n")
+        );
+        assert_eq!(idn_header("example.com"), None);
+    }
+
+    #[test]
+    fn test_private_asn() {
+        assert!(is_private_asn(4242420000));
+        assert!(is_private_asn(4201273722));
+        assert!(is_private_asn(64512));
+        assert!(!is_private_asn(13335));
+    }
+
+    #[test]
+    fn test_registered_services() {
+        assert_eq!(analyze_query("serde-CARGO"), QueryType::Service("cargo", "serde".to_string()));
+        assert_eq!(analyze_query("yay-aur"), QueryType::Service("aur", "yay".to_string()));
+        // Services take part in modifier splitting like built-in types
+        assert_eq!(analyze_query("react-NPM-JSON"), QueryType::Service("npm", "react".to_string()));
+        // Protocol tags after -GAMESERVER are not taken for other suffixes
+        assert_eq!(
+            analyze_query("mc.example.net-GAMESERVER-MC"),
+            QueryType::Service("gameserver", "minecraft:mc.example.net".to_string())
+        );
+        assert_eq!(analyze_query("1.1.1.1-RDNS"), QueryType::Service("rdns", "1.1.1.1".to_string()));
+        assert_eq!(analyze_query("8.8.8.8-de-MTR"), QueryType::Service("mtr", "8.8.8.8-de".to_string()));
+        assert_eq!(analyze_query("example.com:443-PORT"), QueryType::Service("port", "example.com:443".to_string()));
+        // -CRTWATCH is not taken for -CRT
+        assert_eq!(analyze_query("example.com-CRTWATCH"), QueryType::Service("crtwatch", "example.com".to_string()));
+        assert_eq!(analyze_query("AS13335-ROA"), QueryType::Service("roa", "AS13335".to_string()));
+        assert_eq!(analyze_query("AS13335-ASPA"), QueryType::Service("aspa", "AS13335".to_string()));
+        assert_eq!(analyze_query("100.64.0.0/10-BOGON"), QueryType::Service("bogon", "100.64.0.0/10".to_string()));
+        assert_eq!(analyze_query("AS-HURRICANE-ASSET"), QueryType::Service("asset", "AS-HURRICANE".to_string()));
+        assert_eq!(analyze_query("customer-TAGGED"), QueryType::Service("tagged", "customer".to_string()));
+        assert_eq!(analyze_query("10.1.2.0/24-IPAM"), QueryType::Service("ipam", "10.1.2.0/24".to_string()));
+        assert_eq!(analyze_query("AS13335-PREFIXLIST"), QueryType::Service("prefixlist", "AS13335".to_string()));
+        assert_eq!(
+            analyze_query("AS-EXAMPLE-PREFIXLIST-BIRD"),
+            QueryType::Service("prefixlist-bird", "AS-EXAMPLE".to_string())
+        );
+        assert_eq!(analyze_query("AS13335-NEIGHBORS"), QueryType::Service("neighbors", "AS13335".to_string()));
+        assert_eq!(analyze_query("AS13335-ASPATHS"), QueryType::Service("neighbors", "AS13335".to_string()));
+        assert_eq!(analyze_query("example.com-HISTORY"), QueryType::Service("history", "example.com".to_string()));
+        assert_eq!(analyze_query("example.com-EXPIRY"), QueryType::Service("expiry", "example.com".to_string()));
+        assert_eq!(
+            analyze_query("192.0.2.0/24 EXCLUDE 192.0.2.0/25-SETOP"),
+            QueryType::Service("setop", "192.0.2.0/24 EXCLUDE 192.0.2.0/25".to_string())
+        );
+    }
+
+    #[test]
+    fn test_steam_region() {
+        assert_eq!(analyze_query("730-STEAM"), QueryType::Steam("730".to_string(), None));
+        assert_eq!(analyze_query("730-STEAM-CC=JP"), QueryType::Steam("730".to_string(), Some("JP".to_string())));
+        assert_eq!(
+            analyze_query("portal-STEAMSEARCH-CC=de-JSON"),
+            QueryType::SteamSearch("portal".to_string(), Some("DE".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_globalping_vantage_points() {
+        assert_eq!(analyze_query("1.1.1.1-PING"), QueryType::Ping("1.1.1.1".to_string(), None));
+        assert_eq!(analyze_query("8.8.8.8-TRACE@de"), QueryType::Trace("8.8.8.8".to_string(), Some("de".to_string())));
+        assert_eq!(
+            analyze_query("example.com-PING@as13335-JSON"),
+            QueryType::Ping("example.com".to_string(), Some("as13335".to_string()))
+        );
+        assert_eq!(
+            analyze_query("1.1.1.0/24-LG@DE,AS13335"),
+            QueryType::LookingGlass("1.1.1.0/24".to_string(), Some("DE,AS13335".to_string()))
+        );
+        assert_eq!(analyze_query("1.1.1.0/24-LG"), QueryType::LookingGlass("1.1.1.0/24".to_string(), None));
+        assert_eq!(analyze_query("PROBES"), QueryType::Probes(None));
+        assert_eq!(analyze_query("AS13335-PROBES"), QueryType::Probes(Some("AS13335".to_string())));
+    }
+
+    #[test]
+    fn test_ssl_targets() {
+        assert_eq!(analyze_query("example.com:8443-SSL"), QueryType::Ssl("example.com:8443".to_string()));
+        assert_eq!(
+            analyze_query("mail.example.com:587-ssl-starttls"),
+            QueryType::Ssl("mail.example.com:587-STARTTLS".to_string())
+        );
+    }
+}
@@ -0,0 +1,290 @@
+// WHOIS Server - Query Limits
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Per-query-type timeouts and concurrency limits
+//!
+//! Limits are loaded once at startup from a TOML file (`--query-limits-file`):
+//!
+//! ```toml
+//! [limits.TRACE]
+//! max_concurrent = 4
+//!
+//! [limits.STEAM]
+//! timeout = 10
+//!
+//! [limits.WHOIS]
+//! timeout = 5
+//! ```
+//!
+//! Keys name either a query type, as labelled in `whois_queries_total`
+//! (`STEAM`, `TRACEROUTE`, or a registered service such as `CARGO`), or one of
+//! the classes grouping related types: `WHOIS`, `IRR`, `ROUTING`, `TRACE`
+//! (traceroute, ping, probes), `NETWORK`, `MEDIA`, `SERVICE`, `PLUGIN` and
+//! `LOCAL`. Services declare their class (`QueryService::query_class`), so
+//! `-MTR` is limited with `TRACE` and `-PORT` with `NETWORK`; unknown keys
+//! are rejected. The most specific entry applies, so `STEAM` overrides `MEDIA`
+//! for Steam queries. Limits only cover upstream lookups: cached responses are
+//! served without waiting for a slot, and the timeout includes the time spent
+//! waiting for one.
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use crate::core::QueryType;
+use crate::core::telemetry::{QUERY_TYPE_LABELS, query_type_to_string};
+use crate::services::registry::{ServiceRegistry, builtin_registry, service_registry};
+
+/// Classes grouping related query types
+const CLASSES: [&str; 9] = ["WHOIS", "IRR", "ROUTING", "TRACE", "NETWORK", "MEDIA", "SERVICE", "PLUGIN", "LOCAL"];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LimitSpec {
+    /// Seconds a lookup may take, including the wait for a slot
+    timeout: Option<u64>,
+    /// Lookups of this kind running at the same time
+    max_concurrent: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct LimitsFile {
+    #[serde(default)]
+    limits: BTreeMap<String, LimitSpec>,
+}
+
+/// Limits applied to one query type or class
+#[derive(Debug)]
+pub struct QueryLimit {
+    name: String,
+    timeout: Option<Duration>,
+    slots: Option<Arc<Semaphore>>,
+}
+
+static LIMITS: OnceLock<Vec<QueryLimit>> = OnceLock::new();
+
+/// Install the limits used for all queries
+pub fn init_query_limits(limits: Vec<QueryLimit>) {
+    let _ = LIMITS.set(limits);
+}
+
+/// Class of a query type
+pub fn query_class(query_type: &QueryType) -> &'static str {
+    match query_type {
+        QueryType::Domain(_) |
+        QueryType::IPv4(_) |
+        QueryType::IPv6(_) |
+        QueryType::ASN(_) |
+        QueryType::EmailSearch(_) |
+        QueryType::Desc(_) |
+        QueryType::Rdap(_) |
+        QueryType::Pen(_) |
+        QueryType::Icp(_) |
+        QueryType::Unknown(_) => "WHOIS",
+        QueryType::Radb(_) |
+        QueryType::Altdb(_) |
+        QueryType::Afrinic(_) |
+        QueryType::Apnic(_) |
+        QueryType::ArinIrr(_) |
+        QueryType::Bell(_) |
+        QueryType::Jpirr(_) |
+        QueryType::Lacnic(_) |
+        QueryType::Level3(_) |
+        QueryType::Nttcom(_) |
+        QueryType::RipeIrr(_) |
+        QueryType::Tc(_) |
+        QueryType::Irr(_) => "IRR",
+        QueryType::BGPTool(_) |
+        QueryType::Prefixes(_) |
+        QueryType::Ris(_) |
+        QueryType::LookingGlass(..) |
+        QueryType::Rpki(..) |
+        QueryType::Manrs(_) |
+        QueryType::PeeringDB(_) |
+        QueryType::Geo(_) |
+        QueryType::RirGeo(_) => "ROUTING",
+        QueryType::Trace(..) | QueryType::Ping(..) | QueryType::Probes(_) => "TRACE",
+        QueryType::Dns(_) |
+        QueryType::Ssl(_) |
+        QueryType::Crt(_) |
+        QueryType::CfStatus(_) |
+        QueryType::Ntp(_) |
+        QueryType::Minecraft(_) => "NETWORK",
+        QueryType::MinecraftUser(_) |
+        QueryType::Steam(..) |
+        QueryType::SteamSearch(..) |
+        QueryType::Imdb(_) |
+        QueryType::ImdbSearch(_) |
+        QueryType::Acgc(_) |
+        QueryType::GitHub(_) |
+        QueryType::Wikipedia(_) |
+        QueryType::Lyric(_) |
+        QueryType::Pixiv(_) => "MEDIA",
+        QueryType::Service(name, _) => service_registry().get(name).map_or("SERVICE", |service| service.query_class()),
+        QueryType::Plugin(..) => "PLUGIN",
+        QueryType::Meal | QueryType::MealCN | QueryType::Help | QueryType::UpdatePatch | QueryType::Explain(_) => "LOCAL",
+    }
+}
+
+/// Whether `name` is a class or the label of a query type, in upper case
+fn is_known_name(name: &str, services: &ServiceRegistry) -> bool {
+    CLASSES.contains(&name) ||
+        QUERY_TYPE_LABELS.iter().any(|label| label.eq_ignore_ascii_case(name)) ||
+        services.names().any(|service| service.eq_ignore_ascii_case(name))
+}
+
+/// Parse limit definitions from TOML
+pub fn parse_query_limits(content: &str) -> Result<Vec<QueryLimit>> {
+    let file: LimitsFile = toml::from_str(content)?;
+    // Limits are loaded before the registry is installed; service names do
+    // not depend on which services are disabled
+    let services = builtin_registry()?;
+
+    let mut limits: Vec<QueryLimit> = Vec::with_capacity(file.limits.len());
+    for (key, spec) in file.limits {
+        let name = key.trim().to_uppercase();
+        if !is_known_name(&name, &services) {
+            return Err(anyhow!("'{}' is not a query type or class", key));
+        }
+        if limits.iter().any(|l| l.name == name) {
+            return Err(anyhow!("limits for '{}' are defined twice", name));
+        }
+        if spec.timeout.is_none() && spec.max_concurrent.is_none() {
+            return Err(anyhow!("limits for '{}' set neither timeout nor max_concurrent", name));
+        }
+        if spec.timeout == Some(0) || spec.max_concurrent == Some(0) {
+            return Err(anyhow!("limits for '{}' must be greater than zero", name));
+        }
+        limits.push(QueryLimit {
+            name,
+            timeout: spec.timeout.map(Duration::from_secs),
+            slots: spec.max_concurrent.map(|n| Arc::new(Semaphore::new(n))),
+        });
+    }
+    Ok(limits)
+}
+
+/// Load limit definitions from a TOML file
+pub fn load_query_limits_file(path: &Path) -> Result<Vec<QueryLimit>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read query limits file {}: {}", path.display(), e))?;
+    parse_query_limits(&content).map_err(|e| anyhow!("Invalid query limits file {}: {}", path.display(), e))
+}
+
+fn limit_in<'a>(limits: &'a [QueryLimit], query_type: &QueryType) -> Option<&'a QueryLimit> {
+    let type_name = query_type_to_string(query_type).to_uppercase();
+    let class = query_class(query_type);
+    limits.iter().find(|l| l.name == type_name).or_else(|| limits.iter().find(|l| l.name == class))
+}
+
+async fn run_limited<F>(limit: Option<&QueryLimit>, lookup: F) -> Result<String>
+    where F: Future<Output = Result<String>>
+{
+    let Some(limit) = limit else {
+        return lookup.await;
+    };
+
+    let limited = async {
+        let _slot = match &limit.slots {
+            Some(slots) => Some(slots.acquire().await.map_err(|e| anyhow!("{}", e))?),
+            None => None,
+        };
+        lookup.await
+    };
+    match limit.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, limited)
+            .await
+            .unwrap_or_else(|_| Err(anyhow!("{} query timed out after {}s", limit.name, timeout.as_secs()))),
+        None => limited.await,
+    }
+}
+
+/// Run an upstream lookup within the limits configured for its query type
+pub async fn limited<F>(query_type: &QueryType, lookup: F) -> Result<String>
+    where F: Future<Output = Result<String>>
+{
+    let limits = LIMITS.get().map(Vec::as_slice).unwrap_or_default();
+    run_limited(limit_in(limits, query_type), lookup).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const LIMITS_TOML: &str = r#"
+[limits.trace]
+max_concurrent = 1
+
+[limits.STEAM]
+timeout = 1
+
+[limits.MEDIA]
+timeout = 30
+max_concurrent = 2
+"#;
+
+    #[test]
+    fn test_parse_and_select() {
+        let limits = parse_query_limits(LIMITS_TOML).unwrap();
+        assert_eq!(limits.len(), 3);
+
+        let steam = limit_in(&limits, &QueryType::Steam("570".to_string(), None)).unwrap();
+        assert_eq!(steam.name, "STEAM");
+        assert!(steam.slots.is_none());
+        let imdb = limit_in(&limits, &QueryType::Imdb("tt0111161".to_string())).unwrap();
+        assert_eq!(imdb.name, "MEDIA");
+        assert_eq!(limit_in(&limits, &QueryType::Ping("1.1.1.1".to_string(), None)).unwrap().name, "TRACE");
+        assert!(limit_in(&limits, &QueryType::Domain("example.com".to_string())).is_none());
+
+        assert!(parse_query_limits("[limits.WHOIS]\n").is_err());
+        assert!(parse_query_limits("[limits.WHOIS]\ntimeout = 0\n").is_err());
+        assert!(parse_query_limits("[limits.WHOIS]\ntimeuot = 5\n").is_err());
+        assert!(parse_query_limits("[limits.whois]\ntimeout = 5\n[limits.WHOIS]\ntimeout = 6\n").is_err());
+        assert!(parse_query_limits("[limits.STEEM]\ntimeout = 5\n").is_err());
+        assert!(parse_query_limits("[limits.CARGO]\ntimeout = 5\n[limits.arin_irr]\ntimeout = 5\n").is_ok());
+    }
+
+    #[test]
+    fn test_services_declare_their_class() {
+        let limits = parse_query_limits(LIMITS_TOML).unwrap();
+        let mtr = QueryType::Service("mtr", "1.1.1.1".to_string());
+        assert_eq!(limit_in(&limits, &mtr).unwrap().name, "TRACE");
+        assert_eq!(query_class(&QueryType::Service("port", "1.1.1.1:443".to_string())), "NETWORK");
+        assert_eq!(query_class(&QueryType::Service("cargo", "serde".to_string())), "SERVICE");
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_concurrency() {
+        let limits = parse_query_limits(LIMITS_TOML).unwrap();
+
+        let steam = QueryType::Steam("570".to_string(), None);
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok("late".to_string())
+        };
+        let err = run_limited(limit_in(&limits, &steam), slow).await.unwrap_err();
+        assert_eq!(err.to_string(), "STEAM query timed out after 1s");
+
+        let trace = QueryType::Trace("1.1.1.1".to_string(), None);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let lookup = || async {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok("done".to_string())
+        };
+        let limit = limit_in(&limits, &trace);
+        let (a, b, c) = tokio::join!(run_limited(limit, lookup()), run_limited(limit, lookup()), run_limited(limit, lookup()));
+        assert!(a.is_ok() && b.is_ok() && c.is_ok());
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+    }
+}
@@ -5,6 +5,7 @@
 //! Core query processing logic shared between different server implementations
 
 use anyhow::Result;
+use std::net::IpAddr;
 use crate::config::{
     AFRINIC_WHOIS_PORT,
     AFRINIC_WHOIS_SERVER,
@@ -32,31 +33,50 @@ use crate::config::{
     TC_WHOIS_SERVER,
 };
 use crate::core::{
-    ColorScheme,
     Colorizer,
     QueryType,
+    analyze_query,
     apply_response_patches,
+    cache_ttl,
+    idn_header,
+    is_private_asn,
     is_private_ipv4,
     is_private_ipv6,
+    parse_asn,
 };
+use crate::core::alias::resolve_aliases;
+use crate::core::macros::{MacroExpansion, expand_macro, step_header};
+use crate::core::modifiers::{raw_header, split_modifiers};
+use crate::core::stats::rank_query;
+use crate::core::provenance::collect;
+use crate::core::public_suffix::registrable_domain;
+use crate::core::query_limits::limited;
+use crate::core::request_context::RequestContext;
+use crate::core::single_flight::{QUERY_FLIGHTS, flight_key};
+use crate::core::special_purpose::special_purpose_entry;
+use crate::core::truncation::{continue_response, truncate_response};
+use crate::core::warmup::ensure_plugin_ready;
 use crate::log_debug;
 use crate::dn42::process_dn42_query_managed;
+use crate::dn42::registries::query_detected_registry;
+use crate::services::annotations::append_annotations;
+use crate::services::history::record_snapshot;
+use crate::services::ipam::append_ipam;
+use crate::services::registry::service_registry;
+use crate::services::translate::translate_response;
+use crate::services::verify::append_verification_badge;
+use crate::storage::response_cache::cached_lookup;
 use crate::services::{
     handle_ntp_query,
     process_ping_query,
+    process_probes_query,
     process_acgc_query,
-    process_alma_query,
-    process_aosc_query,
-    process_aur_query,
     process_bgptool_query,
-    process_cargo_query,
     process_cfstatus_query,
     process_crt_query,
-    process_debian_query,
     process_desc_query,
     process_dns_query,
     process_email_search,
-    process_epel_query,
     process_geo_query,
     process_github_query,
     process_icp_query,
@@ -68,80 +88,250 @@ use crate::services::{
     process_manrs_query,
     process_minecraft_query,
     process_minecraft_user_query,
-    process_nixos_query,
-    process_npm_query,
-    process_opensuse_query,
-    process_openwrt_query,
     process_peeringdb_query,
     process_pen_query,
     process_prefixes_query,
-    process_pypi_query,
     process_rdap_query,
     process_rir_geo_query,
     process_rpki_query,
     process_ssl_query,
-    process_steam_query,
-    process_steam_search_query,
     process_traceroute_query,
-    process_ubuntu_query,
     process_wikipedia_query,
-    query_curseforge,
-    query_modrinth,
     query_random_chinese_meal,
     query_random_meal,
+    query_domain_with_referrals,
     query_ripe_whois,
+    query_steam,
     query_whois,
-    query_with_iana_referral,
+    query_with_registry_flags,
+    search_steam,
 };
 
 /// Process a WHOIS query and return the response (for use by SSH server and other modules)
 pub async fn process_query(
     query: &str,
     query_type: &QueryType,
-    color_scheme: Option<ColorScheme>,
-    client_ip: Option<String>
+    ctx: &RequestContext
 ) -> Result<String> {
-    log_debug!("Processing query: {} (type: {:?})", query, query_type);
+    // Macros (e.g. `AUDIT example.com`) run a sequence of queries
+    if let Some(expansion) = expand_macro(query, ctx.identity.as_deref()) {
+        return run_macro(&expansion, ctx).await;
+    }
+    process_expanded_query(query, query_type, ctx).await
+}
+
+/// Run the steps of a macro in order and concatenate their responses
+///
+/// A failing step is reported in its place and does not stop the macro.
+async fn run_macro(expansion: &MacroExpansion, ctx: &RequestContext) -> Result<String> {
+    log_debug!("[{}] Running macro {} ({} steps)", ctx, expansion.name, expansion.steps.len());
+
+    let mut output = String::new();
+    for (index, step) in expansion.steps.iter().enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+        output.push_str(&step_header(expansion, index));
+        match process_expanded_query(step, &analyze_query(step), ctx).await {
+            Ok(response) => output.push_str(&response),
+            Err(e) => output.push_str(&format!("% Error: {}\n", e)),
+        }
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+    }
+    Ok(output)
+}
+
+/// Process a single query, after macro expansion
+async fn process_expanded_query(
+    query: &str,
+    query_type: &QueryType,
+    ctx: &RequestContext
+) -> Result<String> {
+    log_debug!("[{}] Processing query: {} (type: {:?})", ctx, query, query_type);
 
     // Start timing the query
     let start_time = std::time::Instant::now();
 
-    // Process the query based on its type
-    let result = match query_type {
+    // Output modifiers (-JSON, -MD, -P<n>) are applied to the service's
+    // response; the service itself only sees the query without them
+    let resolved = resolve_aliases(query);
+    let (base_query, modifiers) = split_modifiers(&resolved);
+    let modifiers = modifiers.with_default_format(ctx.format);
+    let ctx = &ctx
+        .clone()
+        .with_follow_referrals(ctx.follow_referrals && !modifiers.no_follow)
+        .with_registry_flags(modifiers.registry_flags.clone());
+
+    // -MORE=<token> continues a truncated response from its stored copy
+    if let Some(token) = &modifiers.more {
+        let chunk = continue_response(base_query, token).await;
+        crate::core::events::record_query_event(ctx, query, query_type, start_time.elapsed(), &chunk);
+        return Ok(modifiers.apply(query, query_type, chunk?));
+    }
+
+    // Serve the response cache (unless -NOCACHE), which prefetch keeps warm
+    // for popular queries; otherwise process the query based on its type,
+    // sharing the upstream fetch with identical queries that are already in
+    // flight
+    rank_query(base_query, query_type);
+    let lookup = async {
+        let key = modifiers.fetch_key(flight_key(base_query, query_type));
+        let ttl = cache_ttl(query_type);
+        let lookup = cached_lookup(&key, ttl, modifiers.bypasses_cache(), || async {
+            let fetch = limited(query_type, dispatch_query(base_query, query_type, ctx));
+            // Uncached responses may depend on the client asking, so
+            // identical queries in flight do not share them either
+            match ttl {
+                Some(_) => QUERY_FLIGHTS.run(&key, || fetch).await,
+                None => fetch.await,
+            }
+        });
+        match ctx.remaining() {
+            Some(remaining) => {
+                tokio::time::timeout(remaining, lookup)
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("Query deadline exceeded")))
+            }
+            None => lookup.await,
+        }
+    };
+    // -RAW names the servers the response came from
+    let (result, upstreams) = if modifiers.raw {
+        let (result, provenance) = collect(lookup).await;
+        (result, provenance.upstream_names())
+    } else {
+        (lookup.await, Vec::new())
+    };
+
+    // Calculate response time
+    let elapsed = start_time.elapsed();
+    let response_time = elapsed.as_millis() as u64;
+    crate::core::metrics::record_query(query_type, elapsed, result.is_ok());
+    crate::core::analytics::record_usage(query_type);
+    crate::core::events::record_query_event(ctx, query, query_type, elapsed, &result);
+
+    // Send telemetry data if client IP is provided
+    if let Some(ip) = ctx.client_ip() {
+        let query_object = query.to_string();
+        let query_type_str = crate::core::telemetry::query_type_to_string(query_type);
+
+        let telemetry_data = crate::core::telemetry::TelemetryData::new(
+            query_object,
+            query_type_str,
+            ip,
+            response_time
+        );
+
+        crate::core::telemetry::send_telemetry(telemetry_data).await;
+    }
+
+    // Apply colorization if scheme is provided, then apply patches
+    match result {
+        // Raw responses skip enrichment, colors and patches
+        Ok(response) if modifiers.raw => {
+            Ok(modifiers.apply(query, query_type, format!("{}{}", raw_header(&upstreams), response)))
+        }
+        Ok(response) => {
+            // Keep a snapshot when the record changed since it was last served
+            record_snapshot(query_type, &response);
+
+            // Mark responses about resources whose holder proved control
+            let response = append_verification_badge(response, query_type);
+
+            // Operator notes on the resource, for clients allowed to see them
+            let response = append_annotations(response, query_type, ctx);
+
+            // Internal IPAM data, for clients allowed to see it
+            let response = append_ipam(response, query_type, ctx).await;
+
+            // Translate lines in non-Latin scripts when requested
+            let response = if modifiers.translate {
+                translate_response(ctx, response).await
+            } else {
+                response
+            };
+
+            // First apply colorization if requested
+            let colored_response = match &ctx.color {
+                Some(scheme) if modifiers.is_plain_text() => {
+                    let colorizer = Colorizer::new(scheme.clone());
+                    colorizer.colorize_response(&response, query_type)
+                }
+                _ => response,
+            };
+
+            // Then apply response patches and output modifiers
+            let patched_response = apply_response_patches(base_query, colored_response);
+
+            // Cut huge responses; the rest is served by -MORE queries
+            let patched_response = if modifiers.allows_truncation() {
+                truncate_response(base_query, patched_response).await
+            } else {
+                patched_response
+            };
+            Ok(modifiers.apply(query, query_type, patched_response))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Dispatch a query to the backend for its type
+pub(crate) async fn dispatch_query(
+    query: &str,
+    query_type: &QueryType,
+    ctx: &RequestContext
+) -> Result<String> {
+    // Plugins still loading do not recognize their suffixes yet
+    ensure_plugin_ready(query)?;
+    match query_type {
         QueryType::Domain(domain) => {
             log_debug!("Processing domain query: {}", domain);
             if domain.to_lowercase().ends_with(".dn42") {
                 log_debug!("Detected .dn42 domain, using DN42 query");
                 process_dn42_query_managed(domain).await
             } else {
-                query_with_iana_referral(domain).await
+                // Registries only answer for registered names, not their subdomains
+                let registered = registrable_domain(domain).unwrap_or_else(|| domain.clone());
+                with_idn_header(domain, query_domain_with_referrals(&registered, ctx.follow_referrals)).await
             }
         }
         QueryType::IPv4(ip) => {
             log_debug!("Processing IPv4 query: {}", ip);
-            if is_private_ipv4(*ip) {
-                log_debug!("Detected private IPv4 address, using DN42 query");
-                process_dn42_query_managed(query).await
-            } else {
-                query_with_iana_referral(query).await
-            }
+            with_special_purpose_notice(IpAddr::V4(*ip), async {
+                if is_private_ipv4(*ip) {
+                    if let Some(response) = query_detected_registry(IpAddr::V4(*ip), query).await {
+                        return Ok(response);
+                    }
+                    log_debug!("Detected private IPv4 address, using DN42 query");
+                    process_dn42_query_managed(query).await
+                } else {
+                    query_with_registry_flags(query, &ctx.registry_flags).await
+                }
+            }).await
         }
         QueryType::IPv6(ip) => {
             log_debug!("Processing IPv6 query: {}", ip);
-            if is_private_ipv6(*ip) {
-                log_debug!("Detected private IPv6 address, using DN42 query");
-                process_dn42_query_managed(query).await
-            } else {
-                query_with_iana_referral(query).await
-            }
+            with_special_purpose_notice(IpAddr::V6(*ip), async {
+                if is_private_ipv6(*ip) {
+                    if let Some(response) = query_detected_registry(IpAddr::V6(*ip), query).await {
+                        return Ok(response);
+                    }
+                    log_debug!("Detected private IPv6 address, using DN42 query");
+                    process_dn42_query_managed(query).await
+                } else {
+                    query_with_registry_flags(query, &ctx.registry_flags).await
+                }
+            }).await
         }
         QueryType::ASN(asn) => {
             log_debug!("Processing ASN query: {}", asn);
-            if asn.to_uppercase().starts_with("AS42424") {
-                log_debug!("Detected DN42 ASN, using DN42 query");
+            if parse_asn(asn).is_some_and(is_private_asn) {
+                log_debug!("Detected private-use (DN42) ASN, using DN42 query");
                 process_dn42_query_managed(asn).await
             } else {
-                query_with_iana_referral(asn).await
+                query_with_registry_flags(asn, &ctx.registry_flags).await
             }
         }
         QueryType::EmailSearch(base_query) => {
@@ -220,9 +410,9 @@ pub async fn process_query(
             log_debug!("Processing IRR Explorer query: {}", resource);
             process_irr_query(resource).await
         }
-        QueryType::LookingGlass(resource) => {
-            log_debug!("Processing Looking Glass query: {}", resource);
-            process_looking_glass_query(resource).await
+        QueryType::LookingGlass(resource, collectors) => {
+            log_debug!("Processing Looking Glass query: {} (collectors: {:?})", resource, collectors);
+            process_looking_glass_query(resource, collectors.as_deref()).await
         }
         QueryType::Rpki(prefix, asn) => {
             log_debug!("Processing RPKI query: prefix={}, asn={}", prefix, asn);
@@ -234,19 +424,23 @@ pub async fn process_query(
         }
         QueryType::Dns(base_query) => {
             log_debug!("Processing DNS query: {}", base_query);
-            process_dns_query(base_query).await
+            with_idn_header(base_query, process_dns_query(base_query)).await
         }
         QueryType::Ntp(base_query) => {
             log_debug!("Processing NTP query: {}", base_query);
             handle_ntp_query(base_query).await
         }
-        QueryType::Ping(base_query) => {
+        QueryType::Ping(base_query, vantage) => {
             log_debug!("Processing ping query: {}", base_query);
-            process_ping_query(base_query).await
+            process_ping_query(base_query, vantage.as_deref()).await
         }
-        QueryType::Trace(base_query) => {
+        QueryType::Trace(base_query, vantage) => {
             log_debug!("Processing traceroute query: {}", base_query);
-            process_traceroute_query(base_query).await
+            process_traceroute_query(base_query, vantage.as_deref()).await
+        }
+        QueryType::Probes(filter) => {
+            log_debug!("Processing probes query: {:?}", filter);
+            process_probes_query(filter.as_deref()).await
         }
         QueryType::Ssl(base_query) => {
             log_debug!("Processing SSL certificate query: {}", base_query);
@@ -268,13 +462,13 @@ pub async fn process_query(
             log_debug!("Processing Minecraft user query: {}", base_query);
             process_minecraft_user_query(&format!("{}-MCU", base_query)).await
         }
-        QueryType::Steam(base_query) => {
+        QueryType::Steam(base_query, region) => {
             log_debug!("Processing Steam game/user query: {}", base_query);
-            process_steam_query(&format!("{}-STEAM", base_query)).await
+            query_steam(base_query, region.as_deref()).await
         }
-        QueryType::SteamSearch(base_query) => {
+        QueryType::SteamSearch(base_query, region) => {
             log_debug!("Processing Steam game search query: {}", base_query);
-            process_steam_search_query(&format!("{}-STEAMSEARCH", base_query)).await
+            search_steam(base_query, region.as_deref()).await
         }
         QueryType::Imdb(base_query) => {
             log_debug!("Processing IMDb movie/TV show query: {}", base_query);
@@ -288,61 +482,8 @@ pub async fn process_query(
             log_debug!("Processing ACGC character query: {}", base_query);
             process_acgc_query(&format!("{}-ACGC", base_query)).await
         }
-        QueryType::Alma(base_query) => {
-            log_debug!("Processing AlmaLinux package query: {}", base_query);
-            process_alma_query(base_query).await
-        }
-        QueryType::Aosc(base_query) => {
-            log_debug!("Processing AOSC package query: {}", base_query);
-            process_aosc_query(base_query).await
-        }
-        QueryType::Aur(base_query) => {
-            log_debug!("Processing AUR package query: {}", base_query);
-            process_aur_query(base_query).await
-        }
-        QueryType::Debian(base_query) => {
-            log_debug!("Processing Debian package query: {}", base_query);
-            process_debian_query(base_query).await
-        }
-        QueryType::Epel(base_query) => {
-            log_debug!("Processing EPEL package query: {}", base_query);
-            process_epel_query(base_query).await
-        }
-        QueryType::Ubuntu(base_query) => {
-            log_debug!("Processing Ubuntu package query: {}", base_query);
-            process_ubuntu_query(base_query).await
-        }
-        QueryType::NixOs(base_query) => {
-            log_debug!("Processing NixOS package query: {}", base_query);
-            process_nixos_query(base_query).await
-        }
-        QueryType::OpenSuse(base_query) => {
-            log_debug!("Processing OpenSUSE package query: {}", base_query);
-            process_opensuse_query(base_query).await
-        }
-        QueryType::OpenWrt(base_query) => {
-            log_debug!("Processing OpenWrt package query: {}", base_query);
-            process_openwrt_query(base_query).await
-        }
-        QueryType::Npm(base_query) => {
-            log_debug!("Processing NPM package query: {}", base_query);
-            process_npm_query(base_query).await
-        }
-        QueryType::Pypi(base_query) => {
-            log_debug!("Processing PyPI package query: {}", base_query);
-            process_pypi_query(base_query).await
-        }
-        QueryType::Cargo(base_query) => {
-            log_debug!("Processing Cargo (Rust) package query: {}", base_query);
-            process_cargo_query(base_query).await
-        }
-        QueryType::Modrinth(base_query) => {
-            log_debug!("Processing Modrinth mod/resource pack query: {}", base_query);
-            query_modrinth(base_query).await
-        }
-        QueryType::CurseForge(base_query) => {
-            log_debug!("Processing CurseForge mod query: {}", base_query);
-            query_curseforge(base_query).await
+        QueryType::Service(name, base_query) => {
+            service_registry().handle(name, ctx, base_query).await
         }
         QueryType::GitHub(base_query) => {
             log_debug!("Processing GitHub user/repository query: {}", base_query);
@@ -370,7 +511,7 @@ pub async fn process_query(
         }
         QueryType::Rdap(base_query) => {
             log_debug!("Processing RDAP query: {}", base_query);
-            process_rdap_query(base_query).await
+            with_idn_header(base_query, process_rdap_query(base_query)).await
         }
         QueryType::Pixiv(base_query) => {
             log_debug!("Processing Pixiv query: {}", base_query);
@@ -400,9 +541,13 @@ pub async fn process_query(
                 Err(e) => Ok(format!("% Error: {}\n", e)),
             }
         }
+        QueryType::Explain(base_query) => {
+            log_debug!("Processing explain query: {}", base_query);
+            Ok(crate::core::explain::explain_query(base_query))
+        }
         QueryType::Plugin(suffix, base_query) => {
             log_debug!("Processing plugin query: suffix={}, query={}", suffix, base_query);
-            process_plugin_query(suffix, base_query, client_ip.clone()).await
+            process_plugin_query(suffix, base_query, ctx).await
         }
         QueryType::Unknown(q) => {
             log_debug!("Unknown query type: {}", q);
@@ -410,7 +555,7 @@ pub async fn process_query(
                 log_debug!("Detected DN42 related query ({}), using DN42 query", q);
                 process_dn42_query_managed(q).await
             } else {
-                let public_result = query_with_iana_referral(q).await;
+                let public_result = query_with_registry_flags(q, &ctx.registry_flags).await;
                 match &public_result {
                     Ok(response) if
                         response.trim().is_empty() ||
@@ -428,45 +573,43 @@ pub async fn process_query(
                 }
             }
         }
-    };
-
-    // Calculate response time
-    let response_time = start_time.elapsed().as_millis() as u64;
-
-    // Send telemetry data if client IP is provided
-    if let Some(ip) = client_ip {
-        let query_object = query.to_string();
-        let query_type_str = crate::core::telemetry::query_type_to_string(query_type);
-
-        let telemetry_data = crate::core::telemetry::TelemetryData::new(
-            query_object,
-            query_type_str,
-            ip,
-            response_time
-        );
-
-        crate::core::telemetry::send_telemetry(telemetry_data).await;
     }
+}
 
-    // Apply colorization if scheme is provided, then apply patches
-    match result {
-        Ok(response) => {
-            // First apply colorization if requested
-            let colored_response = if let Some(scheme) = color_scheme {
-                let colorizer = Colorizer::new(scheme);
-                colorizer.colorize_response(&response, query_type)
-            } else {
-                response
-            };
-
-            // Then apply response patches
-            let patched_response = apply_response_patches(query, colored_response);
-            Ok(patched_response)
+/// Put the IANA special-purpose entry an IP falls in ahead of its response
+///
+/// Addresses that are not globally reachable have no registry holder, so a
+/// failed lookup for them is answered with the notice alone.
+async fn with_special_purpose_notice(
+    ip: IpAddr,
+    lookup: impl Future<Output = Result<String>>,
+) -> Result<String> {
+    let Some(entry) = special_purpose_entry(ip) else {
+        return lookup.await;
+    };
+    log_debug!("{} is in special-purpose range {} ({})", ip, entry.prefix, entry.name);
+    match lookup.await {
+        Ok(response) => Ok(format!("{}\n{}", entry.notice(ip), response)),
+        Err(e) if !entry.globally_reachable => {
+            log_debug!("Lookup of special-purpose address {} failed: {}", ip, e);
+            Ok(entry.notice(ip))
         }
         Err(e) => Err(e),
     }
 }
 
+/// Put the U-label and A-label of an internationalized domain above its lookup
+async fn with_idn_header(
+    name: &str,
+    lookup: impl Future<Output = Result<String>>,
+) -> Result<String> {
+    let response = lookup.await?;
+    Ok(match idn_header(name) {
+        Some(header) => format!("{}\n{}", header, response),
+        None => response,
+    })
+}
+
 /// Process a plugin query
 ///
 /// This function executes the plugin's handle_query function with the provided input.
@@ -474,7 +617,7 @@ pub async fn process_query(
 async fn process_plugin_query(
     suffix: &str,
     base_query: &str,
-    _client_ip: Option<String>,
+    ctx: &RequestContext,
 ) -> Result<String> {
     use crate::core::query::get_plugin_registry;
 
@@ -492,29 +635,60 @@ async fn process_plugin_query(
     // Execute the plugin with configured timeout
     let result = tokio::time::timeout(
         std::time::Duration::from_secs(timeout_secs),
-        execute_plugin(&plugin, base_query)
+        execute_plugin(&plugin, base_query, ctx)
     )
     .await
-    .map_err(|_| anyhow::anyhow!("Plugin execution timeout ({}s)", timeout_secs))??;
+    .map_err(|_| anyhow::anyhow!("Plugin execution timeout ({}s)", timeout_secs))
+    .and_then(|result| result);
+    crate::core::metrics::record_plugin_invocation(suffix, result.is_ok());
 
-    Ok(result)
+    result
 }
 
 /// Execute a plugin's handle_query function
+///
+/// The request context is passed as a second argument, so plugins may use
+/// `handle_query(query, ctx)` to see e.g. `ctx.language` or `ctx.client_ip`.
+/// WebAssembly plugins receive it as a JSON object.
 async fn execute_plugin(
     plugin: &std::sync::Arc<crate::plugins::LoadedPlugin>,
     query: &str,
+    ctx: &RequestContext,
 ) -> Result<String> {
+    use crate::plugins::registry::PluginEngine;
     use mlua::Function;
 
-    let lua = &plugin.lua;
+    let lua = match &plugin.engine {
+        PluginEngine::Lua(lua) => lua,
+        PluginEngine::Wasm(wasm) => {
+            let context = serde_json::json!({
+                "request_id": ctx.request_id.to_string(),
+                "frontend": ctx.frontend.as_str(),
+                "client_ip": ctx.client_ip(),
+                "language": ctx.language,
+                "identity": ctx.identity,
+            });
+            return wasm.handle_query(query, &context.to_string()).await
+                .map_err(|e| anyhow::anyhow!("Plugin execution error: {}", e));
+        }
+    };
 
     // Get the handle_query function
     let handle: Function = lua.globals().get("handle_query")
         .map_err(|e| anyhow::anyhow!("Plugin missing handle_query function: {}", e))?;
 
-    // Call the function asynchronously
-    let result: String = handle.call_async(query).await
+    // Expose the request context as a read-only view
+    let context = lua.create_table()
+        .map_err(|e| anyhow::anyhow!("Failed to create plugin context: {}", e))?;
+    context.set("request_id", ctx.request_id.to_string())?;
+    context.set("frontend", ctx.frontend.as_str())?;
+    context.set("client_ip", ctx.client_ip())?;
+    context.set("language", ctx.language.clone())?;
+    context.set("identity", ctx.identity.clone())?;
+
+    // Call the function asynchronously; the deadline stops busy loops
+    crate::plugins::sandbox::set_deadline(lua, std::time::Duration::from_secs(plugin.metadata.plugin.timeout));
+    let result: String = handle.call_async((query, context)).await
         .map_err(|e| anyhow::anyhow!("Plugin execution error: {}", e))?;
 
     Ok(result)
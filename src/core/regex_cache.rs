@@ -0,0 +1,139 @@
+// WHOIS Server - Regex Cache
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Lazily compiled, fallible regular expressions
+//!
+//! Built-in patterns are declared as `static` [`CachedRegex`] values next to
+//! the code that uses them. They are compiled once, on first use, and the
+//! compilation result (including the error) is cached. At startup
+//! [`precompile_builtin_regexes`] forces every built-in pattern so that a
+//! malformed pattern is reported at load time rather than at query time.
+
+use anyhow::{Result, anyhow};
+use regex::{Regex, Replacer};
+use std::sync::OnceLock;
+
+/// A regex pattern compiled on first use
+pub struct CachedRegex {
+    pattern: &'static str,
+    compiled: OnceLock<std::result::Result<Regex, regex::Error>>,
+}
+
+impl CachedRegex {
+    pub const fn new(pattern: &'static str) -> Self {
+        Self { pattern, compiled: OnceLock::new() }
+    }
+
+    /// Get the compiled regex, or an error describing the bad pattern
+    pub fn get(&self) -> Result<&Regex> {
+        self.compiled
+            .get_or_init(|| Regex::new(self.pattern))
+            .as_ref()
+            .map_err(|e| anyhow!("Invalid regex pattern {:?}: {}", self.pattern, e))
+    }
+
+    /// Returns false if the pattern failed to compile
+    pub fn is_match(&self, text: &str) -> bool {
+        self.get().map(|re| re.is_match(text)).unwrap_or(false)
+    }
+
+    /// Replace all matches, returning the input unchanged if the pattern failed to compile
+    pub fn replace_all<R: Replacer>(&self, text: &str, rep: R) -> String {
+        match self.get() {
+            Ok(re) => re.replace_all(text, rep).into_owned(),
+            Err(_) => text.to_string(),
+        }
+    }
+}
+
+/// All built-in patterns used on request paths
+fn builtin_regexes() -> Vec<&'static CachedRegex> {
+    let mut all = Vec::new();
+    all.extend(crate::core::color::colorizer::builtin_regexes());
+    all.extend(crate::core::patch::builtin_regexes());
+    all.extend(crate::core::query::builtin_regexes());
+    all.extend(crate::services::acgc::builtin_regexes());
+    all.extend(crate::services::fingerprint::builtin_regexes());
+    all.extend(crate::services::icp::builtin_regexes());
+    all.extend(crate::services::packages::aosc::builtin_regexes());
+    all
+}
+
+/// Compile every built-in pattern, failing on the first invalid one
+///
+/// Returns the number of patterns compiled.
+pub fn precompile_builtin_regexes() -> Result<usize> {
+    let regexes = builtin_regexes();
+    for regex in &regexes {
+        regex.get()?;
+    }
+    Ok(regexes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_builtin_regexes_compile() {
+        let count = precompile_builtin_regexes().expect("built-in regex failed to compile");
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_reported_not_panicked() {
+        static BAD: CachedRegex = CachedRegex::new(r"(unclosed");
+        assert!(BAD.get().is_err());
+        assert!(!BAD.is_match("unclosed"));
+        assert_eq!(BAD.replace_all("text", "x"), "text");
+    }
+
+    fn collect_rs_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect_rs_files(&path, files);
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                files.push(path);
+            }
+        }
+    }
+
+    /// Lint: request paths must not compile regexes with unwrap/expect
+    #[test]
+    fn test_no_panicking_regex_compilation_in_request_paths() {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut files = Vec::new();
+        for dir in ["core", "services", "server"] {
+            collect_rs_files(&src.join(dir), &mut files);
+        }
+
+        let mut offenders = Vec::new();
+        for file in files {
+            let content = std::fs::read_to_string(&file).unwrap_or_default();
+            let mut rest = content.as_str();
+            while let Some(pos) = rest.find("Regex::new(") {
+                let statement = &rest[pos..];
+                let end = statement.find(';').unwrap_or(statement.len());
+                let statement = &statement[..end];
+                if statement.contains(".unwrap()") || statement.contains(".expect(") {
+                    let offset = content.len() - rest.len() + pos;
+                    let line = content[..offset].matches('\n').count() + 1;
+                    offenders.push(format!("{}:{}", file.display(), line));
+                }
+                rest = &rest[pos + "Regex::new(".len()..];
+            }
+        }
+
+        assert!(
+            offenders.is_empty(),
+            "Regex compiled with unwrap/expect, use CachedRegex instead:\n{}",
+            offenders.join("\n")
+        );
+    }
+}
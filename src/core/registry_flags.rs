@@ -0,0 +1,175 @@
+// WHOIS Server - Registry Query Flags
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Registry-specific query flags passed through to the upstream server
+//!
+//! Power users of `whois -h` rely on flags their registry understands, such
+//! as RIPE's `-B` (no filtering) and `-r` (no referenced objects) or ARIN's
+//! `n +` (network, full output). They are accepted as space-separated tokens
+//! after the target, e.g. `193.0.0.0/21 -B -T inetnum` or `8.8.8.8 n +`, and
+//! forwarded in the syntax of the server the query is referred to:
+//!
+//! - RIPE-style servers (RIPE NCC, APNIC, AFRINIC) get the RIPE flags in
+//!   front of the target: `-B -T inetnum 193.0.0.0/21`
+//! - ARIN gets its keywords in front of the target: `n + 8.8.8.8`
+//! - Other servers (e.g. LACNIC) get the bare target
+//!
+//! Flags the chosen server does not understand are not sent; the response
+//! says which. Only the flags listed here are accepted, so a query cannot
+//! smuggle arbitrary options (e.g. RIPE's persistent `-k` mode) upstream.
+
+use std::fmt;
+
+/// RIPE-style flags without an argument
+const RIPE_FLAGS: &[&str] = &[
+    "-B", "--no-filtering",
+    "-r", "--no-referenced",
+    "-G", "--no-grouping",
+    "-b", "--brief",
+    "-x", "--exact",
+    "-l", "--one-less",
+    "-L", "--all-less",
+    "-m", "--one-more",
+    "-M", "--all-more",
+    "-c", "--irt",
+    "-d", "--reverse-domain",
+];
+
+/// RIPE-style flags taking an argument (`-T inetnum`)
+const RIPE_FLAGS_WITH_ARGUMENT: &[&str] = &[
+    "-T", "--select-types",
+    "-i", "--inverse",
+    "-s", "--sources",
+];
+
+/// ARIN query keywords: record types and output modifiers
+const ARIN_FLAGS: &[&str] = &["n", "a", "o", "p", "c", "+", "=", "<", ">", "!"];
+
+/// Query syntax of an upstream WHOIS server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagDialect {
+    Ripe,
+    Arin,
+    /// The server takes no flags
+    Plain,
+}
+
+impl FlagDialect {
+    /// Dialect spoken by a WHOIS server
+    pub fn for_server(server: &str) -> Self {
+        match server.to_lowercase().as_str() {
+            "whois.ripe.net" | "whois.apnic.net" | "whois.afrinic.net" => FlagDialect::Ripe,
+            "whois.arin.net" => FlagDialect::Arin,
+            _ => FlagDialect::Plain,
+        }
+    }
+}
+
+/// One accepted flag, with its argument if it takes one
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RegistryFlag {
+    dialect: FlagDialect,
+    text: String,
+}
+
+/// Registry flags given after a query's target
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistryFlags {
+    flags: Vec<RegistryFlag>,
+}
+
+impl RegistryFlags {
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    /// Query to send to `server`, and the flags that are left out because it
+    /// does not understand them
+    pub fn upstream_query(&self, target: &str, server: &str) -> (String, Vec<String>) {
+        let dialect = FlagDialect::for_server(server);
+        let (sent, ignored): (Vec<&RegistryFlag>, Vec<&RegistryFlag>) =
+            self.flags.iter().partition(|flag| flag.dialect == dialect);
+
+        let mut query: Vec<&str> = sent.iter().map(|flag| flag.text.as_str()).collect();
+        query.push(target);
+        (query.join(" "), ignored.iter().map(|flag| flag.text.clone()).collect())
+    }
+}
+
+impl fmt::Display for RegistryFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let texts: Vec<&str> = self.flags.iter().map(|flag| flag.text.as_str()).collect();
+        write!(f, "{}", texts.join(" "))
+    }
+}
+
+/// Split registry flags off the end of a query
+///
+/// Returns the target and the flags. Unless every token after the first is
+/// an accepted flag (or a flag's argument), the query is returned unchanged
+/// without flags, so multi-word queries such as macros are left alone.
+pub fn split_registry_flags(query: &str) -> (&str, RegistryFlags) {
+    let trimmed = query.trim();
+    let Some((target, rest)) = trimmed.split_once(char::is_whitespace) else {
+        return (query, RegistryFlags::default());
+    };
+
+    let mut flags = Vec::new();
+    let mut tokens = rest.split_whitespace();
+    while let Some(token) = tokens.next() {
+        let flag = if RIPE_FLAGS.contains(&token) {
+            RegistryFlag { dialect: FlagDialect::Ripe, text: token.to_string() }
+        } else if RIPE_FLAGS_WITH_ARGUMENT.contains(&token) {
+            let Some(argument) = tokens.next().filter(|arg| is_flag_argument(arg)) else {
+                return (query, RegistryFlags::default());
+            };
+            RegistryFlag { dialect: FlagDialect::Ripe, text: format!("{} {}", token, argument) }
+        } else if ARIN_FLAGS.contains(&token.to_lowercase().as_str()) {
+            RegistryFlag { dialect: FlagDialect::Arin, text: token.to_lowercase() }
+        } else {
+            return (query, RegistryFlags::default());
+        };
+        flags.push(flag);
+    }
+    (target, RegistryFlags { flags })
+}
+
+/// Object types, attribute names and source lists (`inetnum,route`, `RIPE`)
+fn is_flag_argument(argument: &str) -> bool {
+    !argument.starts_with('-') &&
+        argument.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == ',' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_registry_flags() {
+        let (target, flags) = split_registry_flags("193.0.0.0/21 -B -T inetnum,route");
+        assert_eq!(target, "193.0.0.0/21");
+        assert_eq!(flags.to_string(), "-B -T inetnum,route");
+
+        let (target, flags) = split_registry_flags("8.8.8.8 N +");
+        assert_eq!(target, "8.8.8.8");
+        assert_eq!(flags.to_string(), "n +");
+
+        // Anything that is not an accepted flag leaves the query alone
+        assert_eq!(split_registry_flags("AUDIT example.com"), ("AUDIT example.com", RegistryFlags::default()));
+        assert_eq!(split_registry_flags("AS3333 -k"), ("AS3333 -k", RegistryFlags::default()));
+        assert_eq!(split_registry_flags("AS3333 -T"), ("AS3333 -T", RegistryFlags::default()));
+        assert_eq!(split_registry_flags("AS3333 -i -B"), ("AS3333 -i -B", RegistryFlags::default()));
+        assert_eq!(split_registry_flags("AS3333"), ("AS3333", RegistryFlags::default()));
+    }
+
+    #[test]
+    fn test_upstream_query_per_dialect() {
+        let (_, flags) = split_registry_flags("AS3333 -B -r n +");
+
+        assert_eq!(flags.upstream_query("AS3333", "whois.ripe.net"), ("-B -r AS3333".to_string(), vec!["n".to_string(), "+".to_string()]));
+        assert_eq!(flags.upstream_query("AS3333", "WHOIS.ARIN.NET"), ("n + AS3333".to_string(), vec!["-B".to_string(), "-r".to_string()]));
+        let (query, ignored) = flags.upstream_query("AS3333", "whois.lacnic.net");
+        assert_eq!((query.as_str(), ignored.len()), ("AS3333", 4));
+    }
+}
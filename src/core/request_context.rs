@@ -0,0 +1,211 @@
+// WHOIS Server - Request Context
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Per-request context shared by all frontends
+//!
+//! Each frontend (WHOIS, SSH, web, library) builds a `RequestContext` when a
+//! query arrives and passes it through `process_query` into the services and
+//! plugins. It carries everything known about the requester, so new
+//! request-scoped features do not need another loose parameter:
+//!
+//! ```no_run
+//! use whois_server::core::request_context::{Frontend, RequestContext};
+//! use std::time::Duration;
+//!
+//! let ctx = RequestContext::new(Frontend::Whois)
+//!     .with_client_addr("192.0.2.1".parse().unwrap())
+//!     .with_language(Some("zh-CN".to_string()))
+//!     .with_timeout(Duration::from_secs(10));
+//! ```
+
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::core::ColorScheme;
+use crate::core::modifiers::OutputFormat;
+use crate::core::registry_flags::RegistryFlags;
+
+/// Frontend a request arrived through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frontend {
+    Whois,
+    Ssh,
+    Web,
+    Library,
+}
+
+impl Frontend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Frontend::Whois => "whois",
+            Frontend::Ssh => "ssh",
+            Frontend::Web => "web",
+            Frontend::Library => "library",
+        }
+    }
+}
+
+/// Request-scoped state threaded through query processing
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// Unique id of the request, used to correlate log lines
+    pub request_id: Uuid,
+    pub frontend: Frontend,
+    /// Address of the client, when the frontend knows it
+    pub client_addr: Option<IpAddr>,
+    /// Negotiated color scheme, `None` for plain output
+    pub color: Option<ColorScheme>,
+    /// Preferred response language (e.g. `en`, `zh-CN`)
+    pub language: Option<String>,
+    /// Output format chosen by the frontend, unless the query has a format modifier
+    pub format: Option<OutputFormat>,
+    /// Authenticated identity of the client (e.g. SSH user)
+    pub identity: Option<String>,
+    /// Point in time after which the request should be abandoned
+    pub deadline: Option<Instant>,
+    /// Chase registrar referrals of thin registries (off with `-NOFOLLOW`)
+    pub follow_referrals: bool,
+    /// Registry flags forwarded to the upstream WHOIS server (`AS3333 -B`)
+    pub registry_flags: RegistryFlags,
+    /// When the request was received
+    pub started: Instant,
+}
+
+impl RequestContext {
+    pub fn new(frontend: Frontend) -> Self {
+        Self {
+            request_id: Uuid::new_v4(),
+            frontend,
+            client_addr: None,
+            color: None,
+            language: None,
+            format: None,
+            identity: None,
+            deadline: None,
+            follow_referrals: true,
+            registry_flags: RegistryFlags::default(),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn with_client_addr(mut self, addr: IpAddr) -> Self {
+        self.client_addr = Some(addr);
+        self
+    }
+
+    pub fn with_color(mut self, color: Option<ColorScheme>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    pub fn with_format(mut self, format: Option<OutputFormat>) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_identity(mut self, identity: Option<String>) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    pub fn with_follow_referrals(mut self, follow: bool) -> Self {
+        self.follow_referrals = follow;
+        self
+    }
+
+    pub fn with_registry_flags(mut self, flags: RegistryFlags) -> Self {
+        self.registry_flags = flags;
+        self
+    }
+
+    /// Abandon the request `timeout` after it was received
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(self.started + timeout);
+        self
+    }
+
+    /// Client address as a string, as used by telemetry
+    pub fn client_ip(&self) -> Option<String> {
+        self.client_addr.map(|addr| addr.to_string())
+    }
+
+    /// Time left until the deadline, `None` when there is no deadline
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Short span label for log lines, e.g. `whois:1b4e28ba`
+    pub fn span(&self) -> String {
+        let id = self.request_id.simple().to_string();
+        format!("{}:{}", self.frontend.as_str(), &id[..8])
+    }
+}
+
+impl Default for RequestContext {
+    fn default() -> Self {
+        Self::new(Frontend::Library)
+    }
+}
+
+impl fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.span())?;
+        if let Some(addr) = self.client_addr {
+            write!(f, " from {}", addr)?;
+        }
+        if let Some(identity) = &self.identity {
+            write!(f, " as {}", identity)?;
+        }
+        Ok(())
+    }
+}
+
+/// Language requested with an `X-WHOIS-LANG:` header line, if any
+pub fn language_header(request: &str) -> Option<String> {
+    request.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if !name.trim().eq_ignore_ascii_case("X-WHOIS-LANG") {
+            return None;
+        }
+        let value = value.trim();
+        let valid = !value.is_empty() &&
+            value.len() <= 16 &&
+            value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| value.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_and_deadline() {
+        let ctx = RequestContext::new(Frontend::Ssh)
+            .with_client_addr("192.0.2.1".parse().unwrap())
+            .with_identity(Some("whois".to_string()))
+            .with_timeout(Duration::from_secs(30));
+
+        assert_eq!(ctx.client_ip().as_deref(), Some("192.0.2.1"));
+        assert!(ctx.remaining().unwrap() <= Duration::from_secs(30));
+        assert!(ctx.span().starts_with("ssh:"));
+        assert!(ctx.to_string().ends_with(" from 192.0.2.1 as whois"));
+        assert!(RequestContext::default().remaining().is_none());
+    }
+
+    #[test]
+    fn test_language_header() {
+        assert_eq!(language_header("X-WHOIS-LANG: zh-CN\r\nexample.com\r\n").as_deref(), Some("zh-CN"));
+        assert_eq!(language_header("x-whois-lang:en\nAS13335").as_deref(), Some("en"));
+        assert_eq!(language_header("example.com\r\n"), None);
+        assert_eq!(language_header("X-WHOIS-LANG: en; rm -rf\r\n"), None);
+    }
+}
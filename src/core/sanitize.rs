@@ -0,0 +1,183 @@
+// WHOIS Server - Input Sanitization
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Bounded input sanitization for user-supplied queries
+//!
+//! Every frontend (TCP, SSH, web API, library) hands raw client input to the
+//! query parser. The helpers in this module guarantee that the parser only
+//! ever sees a single printable line of bounded length, and provide
+//! panic-free ASCII case-insensitive suffix/prefix matching so that byte
+//! offsets are never computed from an uppercased copy of the input.
+
+/// Maximum query length in bytes after sanitization
+pub const MAX_QUERY_LENGTH: usize = 512;
+
+/// Sanitize a raw query line
+///
+/// - keeps only the first line of the input
+/// - removes control characters (including ANSI escape sequences' ESC byte)
+/// - trims surrounding whitespace
+/// - truncates to [`MAX_QUERY_LENGTH`] bytes on a character boundary
+pub fn sanitize_query(raw: &str) -> String {
+    let first_line = raw.lines().next().unwrap_or("");
+
+    let cleaned: String = first_line.chars().filter(|c| !c.is_control()).collect();
+    let trimmed = cleaned.trim();
+
+    truncate_on_char_boundary(trimmed, MAX_QUERY_LENGTH).trim_end().to_string()
+}
+
+/// Truncate a string to at most `max_len` bytes without splitting a character
+pub fn truncate_on_char_boundary(input: &str, max_len: usize) -> &str {
+    if input.len() <= max_len {
+        return input;
+    }
+
+    let mut end = max_len;
+    while !input.is_char_boundary(end) {
+        end -= 1;
+    }
+    &input[..end]
+}
+
+/// Strip an ASCII suffix from `input`, ignoring ASCII case
+///
+/// Returns `None` when the suffix does not match. Unlike
+/// `input.to_uppercase().ends_with(suffix)` followed by slicing, this never
+/// panics on inputs whose uppercase form has a different byte length.
+pub fn strip_suffix_ignore_case<'a>(input: &'a str, suffix: &str) -> Option<&'a str> {
+    let cut = input.len().checked_sub(suffix.len())?;
+    if !input.is_char_boundary(cut) {
+        return None;
+    }
+
+    let (base, tail) = input.split_at(cut);
+    if tail.eq_ignore_ascii_case(suffix) { Some(base) } else { None }
+}
+
+/// Strip an ASCII prefix from `input`, ignoring ASCII case
+pub fn strip_prefix_ignore_case<'a>(input: &'a str, prefix: &str) -> Option<&'a str> {
+    if input.len() < prefix.len() || !input.is_char_boundary(prefix.len()) {
+        return None;
+    }
+
+    let (head, rest) = input.split_at(prefix.len());
+    if head.eq_ignore_ascii_case(prefix) { Some(rest) } else { None }
+}
+
+/// Check whether `input` ends with an ASCII suffix, ignoring ASCII case
+pub fn ends_with_ignore_case(input: &str, suffix: &str) -> bool {
+    strip_suffix_ignore_case(input, suffix).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    /// Characters chosen to stress byte-length changes under case mapping
+    const TRICKY_CHARS: &[char] = &[
+        'a', 'S', '-', '.', ':', '/', '0', '9', 'ß', 'ſ', 'ı', 'İ', 'ŉ', 'K', 'ﬀ', 'ﬆ', '今', '🦀',
+        '\u{0}', '\u{1b}', '\r', '\n', '\t', ' ', '\u{3000}', '\u{200b}',
+    ];
+
+    fn random_input(rng: &mut impl Rng, max_chars: usize) -> String {
+        let len = rng.gen_range(0..=max_chars);
+        (0..len).map(|_| TRICKY_CHARS[rng.gen_range(0..TRICKY_CHARS.len())]).collect()
+    }
+
+    #[test]
+    fn test_strip_suffix_basic() {
+        assert_eq!(strip_suffix_ignore_case("example.com-ssl", "-SSL"), Some("example.com"));
+        assert_eq!(strip_suffix_ignore_case("-SSL", "-SSL"), Some(""));
+        assert_eq!(strip_suffix_ignore_case("SSL", "-SSL"), None);
+        assert_eq!(strip_suffix_ignore_case("example.com", "-SSL"), None);
+    }
+
+    #[test]
+    fn test_strip_suffix_non_ascii_does_not_panic() {
+        // "ſ" uppercases to "S" (2 bytes -> 1 byte)
+        assert_eq!(strip_suffix_ignore_case("-ſſl", "-SSL"), None);
+        assert_eq!(strip_suffix_ignore_case("今-lg", "-LG"), Some("今"));
+        assert_eq!(strip_suffix_ignore_case("今", "-LG"), None);
+    }
+
+    #[test]
+    fn test_strip_prefix() {
+        assert_eq!(strip_prefix_ignore_case("as13335", "AS"), Some("13335"));
+        assert_eq!(strip_prefix_ignore_case("aſ", "AS"), None);
+        assert_eq!(strip_prefix_ignore_case("a", "AS"), None);
+    }
+
+    #[test]
+    fn test_sanitize_query() {
+        assert_eq!(sanitize_query("  example.com \r\n"), "example.com");
+        assert_eq!(sanitize_query("exa\x1b[31mmple.com"), "exa[31mmple.com");
+        assert_eq!(sanitize_query("first\r\nsecond"), "first");
+        assert_eq!(sanitize_query(""), "");
+    }
+
+    #[test]
+    fn prop_sanitize_is_bounded_and_printable() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let input = random_input(&mut rng, 600);
+            let output = sanitize_query(&input);
+
+            assert!(output.len() <= MAX_QUERY_LENGTH);
+            assert!(!output.chars().any(|c| c.is_control()));
+            assert_eq!(output, output.trim());
+            // Sanitizing twice is a no-op
+            assert_eq!(sanitize_query(&output), output);
+        }
+    }
+
+    #[test]
+    fn prop_strip_suffix_never_panics_and_roundtrips() {
+        let mut rng = rand::thread_rng();
+        let suffixes = ["-SSL", "-LG", "-RPKI", "-MEAL-CN", "AS", ""];
+        for _ in 0..5000 {
+            let input = random_input(&mut rng, 12);
+            for suffix in suffixes {
+                if let Some(base) = strip_suffix_ignore_case(&input, suffix) {
+                    assert!(input.starts_with(base));
+                    assert!(input[base.len()..].eq_ignore_ascii_case(suffix));
+                }
+                if let Some(rest) = strip_prefix_ignore_case(&input, suffix) {
+                    assert!(input.ends_with(rest));
+                    assert!(input[..input.len() - rest.len()].eq_ignore_ascii_case(suffix));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn prop_analyze_query_never_panics() {
+        let mut rng = rand::thread_rng();
+        let tails = ["", "-SSL", "-ſſl", "-RPKI", "-13335-rpki", "-MEAL-CN", "-lg", "-DN42", ".com"];
+        let heads = ["", "AS", "aſ", "1.1.1.0/24", "2001:db8::", "example"];
+        for _ in 0..3000 {
+            let input = format!(
+                "{}{}{}",
+                heads[rng.gen_range(0..heads.len())],
+                random_input(&mut rng, 8),
+                tails[rng.gen_range(0..tails.len())]
+            );
+            let _ = crate::core::analyze_query(&input);
+            let _ = crate::core::analyze_query(&sanitize_query(&input));
+        }
+    }
+
+    #[test]
+    fn prop_truncate_respects_char_boundaries() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let input = random_input(&mut rng, 40);
+            let max = rng.gen_range(0..=input.len() + 2);
+            let output = truncate_on_char_boundary(&input, max);
+            assert!(output.len() <= max);
+            assert!(input.starts_with(output));
+        }
+    }
+}
@@ -0,0 +1,358 @@
+// WHOIS Server - Job Scheduler
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Scheduler for periodic background jobs
+//!
+//! Registry syncs, list refreshes, prefetch, monitoring and digests are named
+//! jobs that run on an interval or a cron expression (`minute hour day month
+//! weekday`, UTC). A random jitter keeps instances from hitting upstreams at
+//! the same moment, a job that is still running when it is due again skips
+//! that run instead of piling up, and the last outcome of every job is kept
+//! for `/api/stats` and `/metrics`.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, DurationRound, Timelike, Utc};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{log_debug, log_warn};
+
+/// Longest search for the next cron match
+const CRON_SEARCH_DAYS: i64 = 4 * 366;
+
+/// Minute, hour, day-of-month, month and weekday sets of a cron expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month and weekday restricted: either may match
+    day_or_weekday: bool,
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| anyhow!("bad step in '{}'", part))?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(anyhow!("step must not be 0 in '{}'", part));
+        }
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (start.parse()?, end.parse()?),
+                // `5/15` runs from 5 to the end of the range
+                None if part.contains('/') => (range.parse()?, max),
+                None => {
+                    let value = range.parse()?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(anyhow!("'{}' is outside {}-{}", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(anyhow!("cron expression '{}' needs 5 fields", expression));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            day_or_weekday: day != "*" && weekday != "*",
+        })
+    }
+}
+
+impl CronSchedule {
+    fn day_matches(&self, time: DateTime<Utc>) -> bool {
+        let day = self.days & (1 << time.day()) != 0;
+        let weekday = self.weekdays & (1 << time.weekday().num_days_from_sunday()) != 0;
+        if self.day_or_weekday { day || weekday } else { day && weekday }
+    }
+
+    /// First matching minute after `now`
+    pub fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = now.duration_trunc(ChronoDuration::minutes(1)).ok()? + ChronoDuration::minutes(1);
+        let limit = now + ChronoDuration::days(CRON_SEARCH_DAYS);
+        while time < limit {
+            if self.months & (1 << time.month()) == 0 || !self.day_matches(time) {
+                time = time.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?.and_utc();
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = time.duration_trunc(ChronoDuration::hours(1)).ok()? + ChronoDuration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += ChronoDuration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+}
+
+/// When a job runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    Every(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Every(interval) => Some(now + ChronoDuration::from_std(*interval).ok()?),
+            Self::Cron(cron) => cron.next_after(now),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Every(interval) => format!("every {}s", interval.as_secs()),
+            Self::Cron(cron) => format!("cron {}", cron.expression),
+        }
+    }
+}
+
+type JobFn = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
+
+/// A named periodic job
+pub struct Job {
+    name: String,
+    schedule: Schedule,
+    jitter: Duration,
+    run_at_start: bool,
+    task: JobFn,
+}
+
+impl Job {
+    pub fn new<F, Fut>(name: impl Into<String>, schedule: Schedule, task: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            schedule,
+            jitter: Duration::ZERO,
+            run_at_start: false,
+            task: Arc::new(move || Box::pin(task())),
+        }
+    }
+
+    /// Delay every run by a random time up to `jitter`
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Also run once right after being scheduled
+    pub fn run_at_start(mut self) -> Self {
+        self.run_at_start = true;
+        self
+    }
+}
+
+/// Outcome of a job's runs, for the stats endpoints and metrics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub schedule: String,
+    pub running: bool,
+    pub runs: u64,
+    pub failures: u64,
+    /// Runs skipped because the previous one was still going
+    pub skipped: u64,
+    /// Unix time the last run started
+    pub last_run: Option<i64>,
+    pub last_duration_ms: Option<u64>,
+    pub last_error: Option<String>,
+    /// Unix time of the next run
+    pub next_run: Option<i64>,
+}
+
+static JOBS: Lazy<Mutex<BTreeMap<String, JobStatus>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+fn update_status(name: &str, update: impl FnOnce(&mut JobStatus)) {
+    if let Some(status) = JOBS.lock().unwrap().get_mut(name) {
+        update(status);
+    }
+}
+
+/// Status of every scheduled job, by name
+pub fn job_statuses() -> Vec<JobStatus> {
+    JOBS.lock().unwrap().values().cloned().collect()
+}
+
+/// Start running `job` on its schedule
+///
+/// A second job with the same name is refused, so a task is never
+/// scheduled twice.
+pub fn schedule(job: Job) {
+    {
+        let mut jobs = JOBS.lock().unwrap();
+        if jobs.contains_key(&job.name) {
+            log_warn!("Job {} is already scheduled", job.name);
+            return;
+        }
+        jobs.insert(job.name.clone(), JobStatus {
+            name: job.name.clone(),
+            schedule: job.schedule.describe(),
+            ..Default::default()
+        });
+    }
+    log_debug!("Scheduled job {} ({})", job.name, job.schedule.describe());
+    tokio::spawn(run_schedule(job));
+}
+
+fn jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(rand::thread_rng().gen_range(0..max.as_millis().max(1) as u64))
+}
+
+/// Clears the running flag when a run ends, even by panicking
+struct RunningGuard(Arc<AtomicBool>, String);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+        update_status(&self.1, |status| status.running = false);
+    }
+}
+
+async fn run_schedule(job: Job) {
+    let running = Arc::new(AtomicBool::new(false));
+    let mut due = if job.run_at_start { Some(Utc::now()) } else { job.schedule.next_after(Utc::now()) };
+    loop {
+        let Some(next) = due else {
+            log_warn!("Job {} has no future run time, stopping it", job.name);
+            update_status(&job.name, |status| status.next_run = None);
+            return;
+        };
+        let next = next + ChronoDuration::from_std(jitter(job.jitter)).unwrap_or_default();
+        update_status(&job.name, |status| status.next_run = Some(next.timestamp()));
+        tokio::time::sleep((next - Utc::now()).to_std().unwrap_or(Duration::ZERO)).await;
+        due = job.schedule.next_after(Utc::now());
+
+        if running.swap(true, Ordering::AcqRel) {
+            log_warn!("Job {} is still running, skipping this run", job.name);
+            update_status(&job.name, |status| status.skipped += 1);
+            continue;
+        }
+        update_status(&job.name, |status| {
+            status.running = true;
+            status.last_run = Some(Utc::now().timestamp());
+        });
+
+        let guard = RunningGuard(running.clone(), job.name.clone());
+        let run = (job.task)();
+        tokio::spawn(async move {
+            let started = Instant::now();
+            let result = run.await;
+            let name = &guard.1;
+            if let Err(e) = &result {
+                log_warn!("Job {} failed: {}", name, e);
+            }
+            update_status(name, |status| {
+                status.runs += 1;
+                status.last_duration_ms = Some(started.elapsed().as_millis() as u64);
+                status.last_error = result.as_ref().err().map(|e| e.to_string());
+                if result.is_err() {
+                    status.failures += 1;
+                }
+            });
+            drop(guard);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(expression: &str, now: &str) -> String {
+        let cron: CronSchedule = expression.parse().unwrap();
+        cron.next_after(at(now)).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        assert_eq!(next("*/15 * * * *", "2025-01-01T05:31:10Z"), "2025-01-01T05:45:00+00:00");
+        assert_eq!(next("0 6 * * *", "2025-01-01T06:00:00Z"), "2025-01-02T06:00:00+00:00");
+        // 2025-01-01 was a Wednesday
+        assert_eq!(next("0 6 * * 3", "2025-01-01T06:00:00Z"), "2025-01-08T06:00:00+00:00");
+        assert_eq!(next("30 2 1 */3 *", "2025-02-10T00:00:00Z"), "2025-04-01T02:30:00+00:00");
+        assert_eq!(next("0 0 * * 7", "2025-01-01T00:00:00Z"), "2025-01-05T00:00:00+00:00");
+        // Day of month or weekday
+        assert_eq!(next("0 0 13 * 5", "2025-01-01T00:00:00Z"), "2025-01-03T00:00:00+00:00");
+
+        assert!("0 6 * *".parse::<CronSchedule>().is_err());
+        assert!("61 * * * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        assert_eq!("0 0 31 2 *".parse::<CronSchedule>().unwrap().next_after(at("2025-01-01T00:00:00Z")), None);
+    }
+
+    #[tokio::test]
+    async fn test_job_runs_and_skips_overlaps() {
+        let (release, released) = tokio::sync::watch::channel(false);
+        schedule(Job::new("test-overlap", Schedule::Every(Duration::from_millis(20)), move || {
+            let mut released = released.clone();
+            async move {
+                released.wait_for(|released| *released).await?;
+                Err(anyhow!("upstream down"))
+            }
+        }).run_at_start());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let status = job_statuses().into_iter().find(|status| status.name == "test-overlap").unwrap();
+        assert!(status.running);
+        assert!(status.skipped >= 2);
+
+        release.send(true).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let status = job_statuses().into_iter().find(|status| status.name == "test-overlap").unwrap();
+        assert!(status.runs >= 1);
+        assert_eq!(status.failures, status.runs);
+        assert_eq!(status.last_error.as_deref(), Some("upstream down"));
+    }
+}
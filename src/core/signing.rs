@@ -0,0 +1,251 @@
+// WHOIS Server - Response Signing
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Detached minisign signatures for WHOIS responses
+//!
+//! With `--signing-key <FILE>` the server holds an Ed25519 key (created on
+//! first start). Queries with the `-SIGNED` modifier, or sent with an
+//! `X-WHOIS-SIGNATURE: 1` header, get a signature trailer after the
+//! response:
+//!
+//! ```text
+//! % X-WHOIS-SIGNATURE: minisign key 3F2A9C0D8E7B6A51
+//! % untrusted comment: signature from whois-server
+//! % RUQ...
+//! % trusted comment: timestamp:1735689600	server:whois-server	query:AS13335
+//! % 7lKh...
+//! ```
+//!
+//! The signed message is everything before the `% X-WHOIS-SIGNATURE:` line
+//! with CRLF line endings replaced by LF, and the lines after it without the
+//! `% ` prefix are a standard `.minisig` file, so an archived response can
+//! be checked with `minisign -V` against the key from `/api/signing-key`.
+
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use blake2::{Blake2b512, Digest};
+use ed25519_dalek::{Signer, SigningKey};
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::log_info;
+
+/// First line of the signature trailer
+pub const SIGNATURE_MARKER: &str = "% X-WHOIS-SIGNATURE:";
+
+static SIGNER: OnceLock<ResponseSigner> = OnceLock::new();
+
+/// Ed25519 key signing responses in minisign format
+pub struct ResponseSigner {
+    key: SigningKey,
+    key_id: [u8; 8],
+    server_id: String,
+}
+
+impl ResponseSigner {
+    fn from_parts(seed: [u8; 32], key_id: [u8; 8], server_id: &str) -> Self {
+        Self { key: SigningKey::from_bytes(&seed), key_id, server_id: server_id.to_string() }
+    }
+
+    /// Load the key from `path`, generating and saving a new one if it does not exist
+    pub fn load_or_create(path: &Path, server_id: &str) -> Result<Self> {
+        if !path.exists() {
+            let seed: [u8; 32] = rand::random();
+            let key_id: [u8; 8] = rand::random();
+            let mut bytes = key_id.to_vec();
+            bytes.extend_from_slice(&seed);
+            let content = format!("# whois-server response signing key (keep secret)\n{}\n", BASE64.encode(&bytes));
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir)?;
+            }
+            // Only the owner may ever read the secret key, so the file is
+            // created with its final mode rather than restricted afterwards
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            options
+                .open(path)
+                .and_then(|mut file| file.write_all(content.as_bytes()))
+                .with_context(|| format!("Failed to write signing key to {}", path.display()))?;
+            log_info!("Generated new response signing key: {}", path.display());
+            return Ok(Self::from_parts(seed, key_id, server_id));
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read signing key {}", path.display()))?;
+        let encoded = content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .ok_or_else(|| anyhow!("signing key file {} is empty", path.display()))?;
+        let bytes = BASE64.decode(encoded).map_err(|e| anyhow!("invalid signing key {}: {}", path.display(), e))?;
+        if bytes.len() != 40 {
+            return Err(anyhow!("invalid signing key {}: expected 40 bytes, got {}", path.display(), bytes.len()));
+        }
+        let key_id: [u8; 8] = bytes[..8].try_into()?;
+        let seed: [u8; 32] = bytes[8..].try_into()?;
+        Ok(Self::from_parts(seed, key_id, server_id))
+    }
+
+    /// Key ID as minisign prints it
+    pub fn key_id(&self) -> String {
+        format!("{:016X}", u64::from_le_bytes(self.key_id))
+    }
+
+    /// Public key in minisign format (base64)
+    pub fn public_key(&self) -> String {
+        let mut bytes = b"Ed".to_vec();
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(self.key.verifying_key().as_bytes());
+        BASE64.encode(bytes)
+    }
+
+    /// Contents of a minisign public key file (`minisign.pub`)
+    pub fn public_key_file(&self) -> String {
+        format!("untrusted comment: minisign public key {}\n{}\n", self.key_id(), self.public_key())
+    }
+
+    /// Sign `message`, returning the contents of a `.minisig` file
+    fn sign(&self, message: &[u8], trusted_comment: &str) -> String {
+        let signature = self.key.sign(&Blake2b512::digest(message)).to_bytes();
+        let mut signature_bytes = b"ED".to_vec();
+        signature_bytes.extend_from_slice(&self.key_id);
+        signature_bytes.extend_from_slice(&signature);
+
+        let mut global = signature.to_vec();
+        global.extend_from_slice(trusted_comment.as_bytes());
+        let global_signature = self.key.sign(&global).to_bytes();
+
+        format!(
+            "untrusted comment: signature from {}\n{}\ntrusted comment: {}\n{}\n",
+            self.server_id,
+            BASE64.encode(signature_bytes),
+            trusted_comment,
+            BASE64.encode(global_signature)
+        )
+    }
+
+    /// Signature trailer for a response (which must end with a line break)
+    fn trailer(&self, response: &str, query: &str, timestamp: u64) -> String {
+        let query: String = query.chars().filter(|c| !c.is_control()).collect();
+        let trusted_comment = format!("timestamp:{}\tserver:{}\tquery:{}", timestamp, self.server_id, query);
+        let signature = self.sign(canonicalize(response).as_bytes(), &trusted_comment);
+
+        let mut trailer = format!("{} minisign key {}\r\n", SIGNATURE_MARKER, self.key_id());
+        for line in signature.lines() {
+            trailer.push_str(&format!("% {}\r\n", line));
+        }
+        trailer
+    }
+}
+
+/// Form of a response that is signed: CRLF line endings become LF
+pub fn canonicalize(response: &str) -> String {
+    response.replace("\r\n", "\n")
+}
+
+/// Install the key used for signed responses
+pub fn init_signer(signer: ResponseSigner) {
+    let _ = SIGNER.set(signer);
+}
+
+/// The configured signing key, if response signing is enabled
+pub fn signer() -> Option<&'static ResponseSigner> {
+    SIGNER.get()
+}
+
+/// Whether the request asked for a signature with an `X-WHOIS-SIGNATURE` header
+pub fn signature_requested(request: &str) -> bool {
+    request.lines().any(|line| {
+        let Some((name, value)) = line.split_once(':') else {
+            return false;
+        };
+        name.trim().eq_ignore_ascii_case("X-WHOIS-SIGNATURE") &&
+            matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on")
+    })
+}
+
+/// Append the signature trailer to a framed response
+pub fn sign_response(mut response: String, query: &str) -> String {
+    if !response.ends_with('\n') {
+        response.push_str("\r\n");
+    }
+    match signer() {
+        Some(signer) => {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            let trailer = signer.trailer(&response, query, timestamp);
+            response.push_str(&trailer);
+        }
+        None => response.push_str("% Response signing is not enabled on this server\r\n"),
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    fn signer() -> ResponseSigner {
+        ResponseSigner::from_parts([7; 32], [1, 2, 3, 4, 5, 6, 7, 8], "whois-test")
+    }
+
+    /// Verify a trailer the way `minisign -V` does
+    fn verify(public_key: &str, message: &str, trailer: &str) -> bool {
+        let lines: Vec<&str> = trailer.lines().skip(1).map(|l| l.strip_prefix("% ").unwrap()).collect();
+        let public_key = BASE64.decode(public_key).unwrap();
+        let signature = BASE64.decode(lines[1]).unwrap();
+        let trusted_comment = lines[2].strip_prefix("trusted comment: ").unwrap();
+        let global_signature = BASE64.decode(lines[3]).unwrap();
+        if signature[..2] != *b"ED" || signature[2..10] != public_key[2..10] {
+            return false;
+        }
+
+        let key = VerifyingKey::from_bytes(<&[u8; 32]>::try_from(&public_key[10..]).unwrap()).unwrap();
+        let signature = Signature::from_slice(&signature[10..]).unwrap();
+        let mut global = signature.to_bytes().to_vec();
+        global.extend_from_slice(trusted_comment.as_bytes());
+        key.verify(&Blake2b512::digest(message.as_bytes()), &signature).is_ok() &&
+            key.verify(&global, &Signature::from_slice(&global_signature).unwrap()).is_ok()
+    }
+
+    #[test]
+    fn test_signature_trailer_verifies() {
+        let signer = signer();
+        let response = "% Akaere NetWorks Whois Server\r\naut-num: AS13335\r\n";
+        let trailer = signer.trailer(response, "AS13335", 1735689600);
+
+        assert!(trailer.starts_with("% X-WHOIS-SIGNATURE: minisign key 0807060504030201\r\n"));
+        assert!(trailer.contains("% trusted comment: timestamp:1735689600\tserver:whois-test\tquery:AS13335\r\n"));
+        assert!(verify(&signer.public_key(), &canonicalize(response), &trailer));
+        assert!(!verify(&signer.public_key(), "aut-num: AS13336\n", &trailer));
+    }
+
+    #[test]
+    fn test_key_file_created_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signing.key");
+        let created = ResponseSigner::load_or_create(&path, "whois-test").unwrap();
+        assert_eq!(ResponseSigner::load_or_create(&path, "whois-test").unwrap().public_key(), created.public_key());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_signature_requested() {
+        assert!(signature_requested("X-WHOIS-SIGNATURE: 1\r\nAS13335\r\n"));
+        assert!(!signature_requested("X-WHOIS-DEBUG: 1\r\nAS13335\r\n"));
+    }
+}
@@ -0,0 +1,374 @@
+// WHOIS Server - Query Deduplication
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Single-flight deduplication of identical concurrent queries
+//!
+//! When many clients ask for the same resource at the same time (for example
+//! a popular domain during an incident), only the first request performs the
+//! upstream fetch. Requests arriving while that fetch is in flight wait for
+//! it and receive a copy of its result.
+//!
+//! When a distributed cache backend is configured, the leader additionally
+//! takes a lock in the shared store so that only one instance of a
+//! multi-instance deployment fetches the resource; the other instances pick
+//! up the published result.
+
+use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+use crate::core::QueryType;
+use crate::core::provenance::record_source;
+use crate::core::telemetry::query_type_to_string;
+use crate::log_debug;
+use crate::storage::cache::{SharedCacheStore, distributed_cache_store};
+
+/// How long a cross-instance flight lock is held at most
+const DISTRIBUTED_LOCK_TTL: Duration = Duration::from_secs(30);
+
+/// How long a published result stays available to other instances
+const DISTRIBUTED_RESULT_TTL: Duration = Duration::from_secs(5);
+
+/// Poll interval while waiting for another instance's result
+const DISTRIBUTED_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Result shared between the leader and its followers
+type SharedResult = std::result::Result<String, String>;
+
+/// Global in-flight table for WHOIS query dispatch
+pub static QUERY_FLIGHTS: Lazy<SingleFlight> = Lazy::new(SingleFlight::new);
+
+/// Coalesces concurrent calls with the same key into one execution
+pub struct SingleFlight {
+    inflight: Mutex<HashMap<String, watch::Receiver<Option<SharedResult>>>>,
+}
+
+/// Removes the in-flight entry if the leader is cancelled before finishing
+struct FlightGuard<'a> {
+    flights: &'a SingleFlight,
+    key: &'a str,
+    armed: bool,
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.flights.remove(self.key);
+        }
+    }
+}
+
+impl Default for SingleFlight {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SingleFlight {
+    pub fn new() -> Self {
+        Self { inflight: Mutex::new(HashMap::new()) }
+    }
+
+    /// Run `fetch` for `key`, or wait for an identical call already in flight
+    ///
+    /// Errors are shared as well: followers receive an error carrying the
+    /// leader's error message. If the leader is cancelled, one of the waiting
+    /// callers takes over and runs its own fetch.
+    pub async fn run<F, Fut>(&self, key: &str, fetch: F) -> Result<String>
+        where F: FnOnce() -> Fut, Fut: Future<Output = Result<String>>
+    {
+        let tx = loop {
+            let follower = {
+                let mut inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+                match inflight.get(key) {
+                    Some(rx) => rx.clone(),
+                    None => {
+                        let (tx, rx) = watch::channel(None);
+                        inflight.insert(key.to_string(), rx);
+                        break tx;
+                    }
+                }
+            };
+
+            let start = Instant::now();
+            if let Some(shared) = Self::wait(follower).await {
+                log_debug!("Coalesced duplicate in-flight query: {}", key);
+                record_source("upstream", "in-flight query", start.elapsed(), "coalesced");
+                return shared.map_err(|e| anyhow!(e));
+            }
+            // Leader was cancelled without a result, try again
+        };
+
+        let mut guard = FlightGuard { flights: self, key, armed: true };
+        let result = match distributed_cache_store() {
+            Some(store) => Self::run_distributed(&store, key, fetch).await,
+            None => fetch().await,
+        };
+
+        // Remove the entry before publishing so late arrivals start a fresh fetch
+        guard.armed = false;
+        self.remove(key);
+
+        let shared = match &result {
+            Ok(response) => Ok(response.clone()),
+            Err(e) => Err(e.to_string()),
+        };
+        let _ = tx.send(Some(shared));
+
+        result
+    }
+
+    /// Coordinate the fetch with other instances through the shared store
+    ///
+    /// Store errors never fail the query; the fetch simply runs locally.
+    async fn run_distributed<F, Fut>(store: &SharedCacheStore, key: &str, fetch: F) -> Result<String>
+        where F: FnOnce() -> Fut, Fut: Future<Output = Result<String>>
+    {
+        let lock_key = format!("flight:lock:{}", key);
+        let result_key = format!("flight:result:{}", key);
+
+        if !store.try_lock(&lock_key, DISTRIBUTED_LOCK_TTL).await.unwrap_or(true) {
+            // Another instance is fetching, wait for its result or for the lock to go away
+            let deadline = tokio::time::Instant::now() + DISTRIBUTED_LOCK_TTL;
+            while tokio::time::Instant::now() < deadline {
+                tokio::time::sleep(DISTRIBUTED_POLL_INTERVAL).await;
+                let lock_held = matches!(store.get(&lock_key).await, Ok(Some(_)));
+                if let Ok(Some(response)) = store.get(&result_key).await {
+                    log_debug!("Coalesced query with another instance: {}", key);
+                    return Ok(response);
+                }
+                if !lock_held {
+                    break;
+                }
+            }
+            return fetch().await;
+        }
+
+        let result = fetch().await;
+        if let Ok(response) = &result {
+            let _ = store.set(&result_key, response, DISTRIBUTED_RESULT_TTL).await;
+        }
+        let _ = store.unlock(&lock_key).await;
+        result
+    }
+
+    async fn wait(mut rx: watch::Receiver<Option<SharedResult>>) -> Option<SharedResult> {
+        match rx.wait_for(|value| value.is_some()).await {
+            Ok(value) => value.clone(),
+            Err(_) => None,
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let mut inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+        inflight.remove(key);
+    }
+}
+
+/// Build the deduplication key for a query: query type plus its normalized
+/// payload
+///
+/// `analyze_query` has already normalized the payload (AS numbers to
+/// `AS<n>`, IDNs to their A-label, modifiers and suffixes stripped), so
+/// `13335`, `AS 13335` and `as13335` or the U-label and A-label of a domain
+/// share one key. Only domains, addresses and AS numbers are
+/// case-insensitive; other services may answer `Foo` and `foo` differently,
+/// so their case is kept.
+pub fn flight_key(query: &str, query_type: &QueryType) -> String {
+    format!("{}:{}", query_type_to_string(query_type), payload(query, query_type))
+}
+
+/// The part of a query type that selects the resource
+fn payload(query: &str, query_type: &QueryType) -> String {
+    match query_type {
+        QueryType::Domain(domain) => domain.trim_end_matches('.').to_lowercase(),
+        QueryType::ASN(asn) => asn.to_uppercase(),
+        // The payload of a prefix is its first address, so the key keeps
+        // the prefix length from the query
+        QueryType::IPv4(_) | QueryType::IPv6(_) => match query.trim().parse::<IpAddr>() {
+            Ok(address) => address.to_string(),
+            Err(_) => query.trim().to_lowercase(),
+        },
+        QueryType::LookingGlass(target, location) |
+        QueryType::Trace(target, location) |
+        QueryType::Ping(target, location) => match location {
+            Some(location) => format!("{}@{}", target, location),
+            None => target.clone(),
+        },
+        QueryType::Steam(query, region) | QueryType::SteamSearch(query, region) => match region {
+            Some(region) => format!("{}-CC={}", query, region),
+            None => query.clone(),
+        },
+        QueryType::Rpki(prefix, asn) => format!("{}-{}", prefix, asn),
+        QueryType::Plugin(suffix, query) => format!("{}:{}", suffix, query),
+        QueryType::Service(_, query) => query.clone(),
+        QueryType::Probes(location) => location.clone().unwrap_or_default(),
+        QueryType::Meal | QueryType::MealCN | QueryType::Help | QueryType::UpdatePatch => String::new(),
+        QueryType::EmailSearch(query) |
+        QueryType::BGPTool(query) |
+        QueryType::Geo(query) |
+        QueryType::RirGeo(query) |
+        QueryType::Prefixes(query) |
+        QueryType::Radb(query) |
+        QueryType::Altdb(query) |
+        QueryType::Afrinic(query) |
+        QueryType::Apnic(query) |
+        QueryType::ArinIrr(query) |
+        QueryType::Bell(query) |
+        QueryType::Jpirr(query) |
+        QueryType::Lacnic(query) |
+        QueryType::Level3(query) |
+        QueryType::Nttcom(query) |
+        QueryType::RipeIrr(query) |
+        QueryType::Ris(query) |
+        QueryType::Tc(query) |
+        QueryType::Irr(query) |
+        QueryType::Manrs(query) |
+        QueryType::Dns(query) |
+        QueryType::Ssl(query) |
+        QueryType::Crt(query) |
+        QueryType::CfStatus(query) |
+        QueryType::Minecraft(query) |
+        QueryType::MinecraftUser(query) |
+        QueryType::Imdb(query) |
+        QueryType::ImdbSearch(query) |
+        QueryType::Acgc(query) |
+        QueryType::GitHub(query) |
+        QueryType::Wikipedia(query) |
+        QueryType::Lyric(query) |
+        QueryType::Desc(query) |
+        QueryType::PeeringDB(query) |
+        QueryType::Pen(query) |
+        QueryType::Rdap(query) |
+        QueryType::Pixiv(query) |
+        QueryType::Icp(query) |
+        QueryType::Ntp(query) |
+        QueryType::Explain(query) |
+        QueryType::Unknown(query) => query.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_identical_calls_share_one_fetch() {
+        let flights = Arc::new(SingleFlight::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for _ in 0..10 {
+            let flights = flights.clone();
+            let calls = calls.clone();
+            handles.push(
+                tokio::spawn(async move {
+                    flights.run("domain:example.com", || async {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        Ok("response".to_string())
+                    }).await
+                })
+            );
+        }
+
+        for handle in handles {
+            assert_eq!(handle.await.unwrap().unwrap(), "response");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(flights.inflight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sequential_calls_fetch_again() {
+        let flights = SingleFlight::new();
+        let calls = AtomicUsize::new(0);
+
+        for _ in 0..2 {
+            let result = flights.run("asn:as13335", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok("ok".to_string())
+            }).await;
+            assert!(result.is_ok());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_shared() {
+        let flights = Arc::new(SingleFlight::new());
+        let leader = {
+            let flights = flights.clone();
+            tokio::spawn(async move {
+                flights.run("key", || async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Err(anyhow!("upstream timeout"))
+                }).await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let follower = flights.run("key", || async { Ok("unused".to_string()) }).await;
+        assert_eq!(follower.unwrap_err().to_string(), "upstream timeout");
+        assert!(leader.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_leader_hands_over() {
+        let flights = Arc::new(SingleFlight::new());
+        let leader = {
+            let flights = flights.clone();
+            tokio::spawn(async move {
+                flights.run("key", || async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok("never".to_string())
+                }).await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let follower = {
+            let flights = flights.clone();
+            tokio::spawn(async move {
+                flights.run("key", || async { Ok("follower".to_string()) }).await
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        leader.abort();
+
+        assert_eq!(follower.await.unwrap().unwrap(), "follower");
+        assert!(flights.inflight.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_flight_key_normalizes_query() {
+        use crate::core::analyze_query;
+
+        let query_type = QueryType::Domain("Example.COM".to_string());
+        assert_eq!(flight_key(" Example.COM ", &query_type), "domain:example.com");
+        assert_eq!(flight_key("AS13335", &QueryType::ASN("AS13335".to_string())), "asn:AS13335");
+        assert_eq!(flight_key("2001:DB8::1", &QueryType::IPv6("2001:db8::1".parse().unwrap())), "ipv6:2001:db8::1");
+
+        // Spellings of one resource share a key
+        let key = |query: &str| flight_key(query, &analyze_query(query));
+        for query in ["13335", "AS 13335", "as13335"] {
+            assert_eq!(key(query), "asn:AS13335", "{}", query);
+        }
+        assert_eq!(key("例え.jp"), key("xn--r8jz45g.jp"));
+        assert_eq!(key("octocat-GITHUB"), key("octocat-github"));
+        assert_ne!(key("1.1.1.0/24"), key("1.1.1.0"));
+
+        // Other services keep the case of the query
+        let query_type = QueryType::GitHub("Octocat".to_string());
+        assert_ne!(flight_key("Octocat-GITHUB", &query_type), flight_key("octocat-GITHUB", &query_type));
+    }
+}
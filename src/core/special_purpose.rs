@@ -0,0 +1,171 @@
+// WHOIS Server - Special-Purpose Addresses
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! IANA special-purpose address registries
+//!
+//! Private-use, shared, documentation, benchmarking and other reserved ranges
+//! from the IANA IPv4 and IPv6 Special-Purpose Address Registries (RFC 6890),
+//! plus multicast. IP queries inside one of them get a notice naming the
+//! registry entry in front of the response; addresses that are not globally
+//! reachable get only the notice when no registry answers for them. Routing
+//! data about these ranges comes from the Team Cymru bogon lists instead
+//! (`-BOGON`, `services::bogon`).
+
+use cidr::IpCidr;
+use once_cell::sync::Lazy;
+use std::net::IpAddr;
+
+/// One entry of the special-purpose address registries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpecialPurpose {
+    pub prefix: &'static str,
+    pub name: &'static str,
+    pub rfc: &'static str,
+    /// Whether the registry marks the range as globally reachable
+    pub globally_reachable: bool,
+}
+
+const fn entry(prefix: &'static str, name: &'static str, rfc: &'static str, globally_reachable: bool) -> SpecialPurpose {
+    SpecialPurpose { prefix, name, rfc, globally_reachable }
+}
+
+const SPECIAL_PURPOSE: &[SpecialPurpose] = &[
+    entry("0.0.0.0/8", "\"This network\"", "RFC 791", false),
+    entry("0.0.0.0/32", "\"This host on this network\"", "RFC 1122", false),
+    entry("10.0.0.0/8", "Private-Use", "RFC 1918", false),
+    entry("100.64.0.0/10", "Shared Address Space", "RFC 6598", false),
+    entry("127.0.0.0/8", "Loopback", "RFC 1122", false),
+    entry("169.254.0.0/16", "Link Local", "RFC 3927", false),
+    entry("172.16.0.0/12", "Private-Use", "RFC 1918", false),
+    entry("192.0.0.0/24", "IETF Protocol Assignments", "RFC 6890", false),
+    entry("192.0.0.0/29", "IPv4 Service Continuity Prefix", "RFC 7335", false),
+    entry("192.0.0.8/32", "IPv4 dummy address", "RFC 7600", false),
+    entry("192.0.0.9/32", "Port Control Protocol Anycast", "RFC 7723", true),
+    entry("192.0.0.10/32", "Traversal Using Relays around NAT Anycast", "RFC 8155", true),
+    entry("192.0.0.170/32", "NAT64/DNS64 Discovery", "RFC 8880", false),
+    entry("192.0.0.171/32", "NAT64/DNS64 Discovery", "RFC 8880", false),
+    entry("192.0.2.0/24", "Documentation (TEST-NET-1)", "RFC 5737", false),
+    entry("192.31.196.0/24", "AS112-v4", "RFC 7535", true),
+    entry("192.52.193.0/24", "AMT", "RFC 7450", true),
+    entry("192.88.99.0/24", "Deprecated (6to4 Relay Anycast)", "RFC 7526", false),
+    entry("192.168.0.0/16", "Private-Use", "RFC 1918", false),
+    entry("192.175.48.0/24", "Direct Delegation AS112 Service", "RFC 7534", true),
+    entry("198.18.0.0/15", "Benchmarking", "RFC 2544", false),
+    entry("198.51.100.0/24", "Documentation (TEST-NET-2)", "RFC 5737", false),
+    entry("203.0.113.0/24", "Documentation (TEST-NET-3)", "RFC 5737", false),
+    entry("224.0.0.0/4", "Multicast", "RFC 5771", true),
+    entry("240.0.0.0/4", "Reserved", "RFC 1112", false),
+    entry("255.255.255.255/32", "Limited Broadcast", "RFC 919", false),
+    entry("::/128", "Unspecified Address", "RFC 4291", false),
+    entry("::1/128", "Loopback Address", "RFC 4291", false),
+    entry("::ffff:0:0/96", "IPv4-mapped Address", "RFC 4291", false),
+    entry("64:ff9b::/96", "IPv4-IPv6 Translation", "RFC 6052", true),
+    entry("64:ff9b:1::/48", "IPv4-IPv6 Translation (local use)", "RFC 8215", false),
+    entry("100::/64", "Discard-Only Address Block", "RFC 6666", false),
+    entry("2001::/23", "IETF Protocol Assignments", "RFC 2928", false),
+    entry("2001::/32", "TEREDO", "RFC 4380", true),
+    entry("2001:1::1/128", "Port Control Protocol Anycast", "RFC 7723", true),
+    entry("2001:1::2/128", "Traversal Using Relays around NAT Anycast", "RFC 8155", true),
+    entry("2001:2::/48", "Benchmarking", "RFC 5180", false),
+    entry("2001:3::/32", "AMT", "RFC 7450", true),
+    entry("2001:4:112::/48", "AS112-v6", "RFC 7535", true),
+    entry("2001:10::/28", "Deprecated (previously ORCHID)", "RFC 4843", false),
+    entry("2001:20::/28", "ORCHIDv2", "RFC 7343", true),
+    entry("2001:30::/28", "Drone Remote ID Protocol Entity Tags (DETs)", "RFC 9374", true),
+    entry("2001:db8::/32", "Documentation", "RFC 3849", false),
+    entry("2002::/16", "6to4", "RFC 3056", true),
+    entry("2620:4f:8000::/48", "Direct Delegation AS112 Service", "RFC 7534", true),
+    entry("3fff::/20", "Documentation", "RFC 9637", false),
+    entry("5f00::/16", "Segment Routing (SRv6) SIDs", "RFC 9602", false),
+    entry("fc00::/7", "Unique-Local", "RFC 4193", false),
+    entry("fe80::/10", "Link-Local Unicast", "RFC 4291", false),
+    entry("ff00::/8", "Multicast", "RFC 4291", true),
+];
+
+static PARSED: Lazy<Vec<(IpCidr, &'static SpecialPurpose)>> = Lazy::new(|| {
+    SPECIAL_PURPOSE
+        .iter()
+        .map(|entry| (entry.prefix.parse().expect("valid special-purpose prefix"), entry))
+        .collect()
+});
+
+impl SpecialPurpose {
+    /// `%` comment block identifying the entry `ip` falls in
+    pub fn notice(&self, ip: IpAddr) -> String {
+        let reachability = if self.globally_reachable {
+            "Globally reachable, but reserved for this special use"
+        } else {
+            "Not globally reachable; should never be routed on the public Internet"
+        };
+        format!(
+            "% Special-purpose address: {} is in {}\n% IANA registry entry: {} ({})\n% {}\n",
+            ip, self.prefix, self.name, self.rfc, reachability
+        )
+    }
+}
+
+/// Most specific special-purpose entry containing `ip`
+pub fn special_purpose_entry(ip: IpAddr) -> Option<&'static SpecialPurpose> {
+    PARSED
+        .iter()
+        .filter(|(cidr, _)| cidr.contains(&ip))
+        .max_by_key(|(cidr, _)| cidr.network_length())
+        .map(|(_, entry)| *entry)
+}
+
+/// Special-purpose entries overlapping `prefix`, least specific first
+pub fn special_purpose_overlapping(prefix: &IpCidr) -> Vec<&'static SpecialPurpose> {
+    let mut entries: Vec<_> = PARSED
+        .iter()
+        .filter(|(cidr, _)| cidr.contains(&prefix.first_address()) || prefix.contains(&cidr.first_address()))
+        .collect();
+    entries.sort_by_key(|(cidr, _)| cidr.network_length());
+    entries.into_iter().map(|(_, entry)| *entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_specific_entry_wins() {
+        let lookup = |ip: &str| special_purpose_entry(ip.parse().unwrap()).map(|entry| entry.prefix);
+        assert_eq!(lookup("10.1.2.3"), Some("10.0.0.0/8"));
+        assert_eq!(lookup("100.100.0.1"), Some("100.64.0.0/10"));
+        assert_eq!(lookup("192.0.0.9"), Some("192.0.0.9/32"));
+        assert_eq!(lookup("192.0.0.42"), Some("192.0.0.0/24"));
+        assert_eq!(lookup("198.19.255.255"), Some("198.18.0.0/15"));
+        assert_eq!(lookup("2001:db8::1"), Some("2001:db8::/32"));
+        assert_eq!(lookup("2001:0:4136:e378::1"), Some("2001::/32"));
+        assert_eq!(lookup("8.8.8.8"), None);
+        assert_eq!(lookup("2606:4700::1111"), None);
+
+        let ip = "192.0.2.1".parse().unwrap();
+        let notice = special_purpose_entry(ip).unwrap().notice(ip);
+        assert!(notice.contains("% IANA registry entry: Documentation (TEST-NET-1) (RFC 5737)\n"));
+        assert!(notice.contains("Not globally reachable"));
+    }
+
+    #[test]
+    fn test_overlapping_entries() {
+        let prefixes = |prefix: &str| {
+            special_purpose_overlapping(&prefix.parse().unwrap()).iter().map(|entry| entry.prefix).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            prefixes("192.0.0.0/16"),
+            [
+                "192.0.0.0/24",
+                "192.0.2.0/24",
+                "192.0.0.0/29",
+                "192.0.0.8/32",
+                "192.0.0.9/32",
+                "192.0.0.10/32",
+                "192.0.0.170/32",
+                "192.0.0.171/32",
+            ]
+        );
+        assert_eq!(prefixes("10.20.0.0/16"), ["10.0.0.0/8"]);
+        assert!(prefixes("1.1.1.0/24").is_empty());
+    }
+}
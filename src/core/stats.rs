@@ -17,19 +17,42 @@
  */
 
 use chrono::{Duration as ChronoDuration, Utc};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::fs;
 use tokio::sync::RwLock;
 use crate::config::STATS_LMDB_PATH;
+use crate::core::prefetch::{PrefetchStatsEntry, prefetch_stats};
+use crate::core::QueryType;
+use crate::core::scheduler::{JobStatus, job_statuses};
+use crate::core::single_flight::flight_key;
+use crate::storage::response_cache::{ResponseCacheStatsEntry, response_cache_stats};
+use crate::server::shadow::{ShadowStatsEntry, shadow_stats};
+use crate::storage::kv::{KvStore, MemoryStore};
 use crate::storage::lmdb::LmdbStorage;
 
 use crate::{log_error, log_info, log_warn};
 // Legacy stats file path for migration
 const LEGACY_STATS_FILE: &str = "stats.json";
 
+/// Upper bound on distinct queries kept in the popularity ranking
+const MAX_RANKED_QUERIES: usize = 10_000;
+
+/// Popularity of individual queries, keyed like the response cache
+static QUERY_RANKING: Lazy<Mutex<HashMap<String, PopularQuery>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A query and how often it was asked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PopularQuery {
+    pub key: String,
+    pub query: String,
+    pub count: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DailyStats {
     pub requests: u64,
@@ -44,9 +67,83 @@ pub struct TotalStats {
     pub hourly_stats: HashMap<String, DailyStats>, // DateTime in YYYY-MM-DD HH format
 }
 
+/// Reasons a client connection was dropped before a query could be served
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// The complete request was not received within the read deadline
+    ReadTimeout,
+    /// The client stopped sending data for longer than the idle timeout
+    IdleTimeout,
+    /// The request exceeded the maximum allowed size
+    RequestTooLarge,
+    /// The socket returned an error while reading the request
+    ReadError,
+    /// The response could not be written within the deadline
+    WriteTimeout,
+    /// The client exceeded its connection rate limit
+    RateLimited,
+}
+
+impl DropReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DropReason::ReadTimeout => "read-timeout",
+            DropReason::IdleTimeout => "idle-timeout",
+            DropReason::RequestTooLarge => "request-too-large",
+            DropReason::ReadError => "read-error",
+            DropReason::WriteTimeout => "write-timeout",
+            DropReason::RateLimited => "rate-limited",
+        }
+    }
+}
+
+/// In-memory counters of dropped connections (reset on restart)
+#[derive(Debug, Default)]
+pub struct DroppedConnections {
+    read_timeout: AtomicU64,
+    idle_timeout: AtomicU64,
+    request_too_large: AtomicU64,
+    read_error: AtomicU64,
+    write_timeout: AtomicU64,
+    rate_limited: AtomicU64,
+}
+
+impl DroppedConnections {
+    fn counter(&self, reason: DropReason) -> &AtomicU64 {
+        match reason {
+            DropReason::ReadTimeout => &self.read_timeout,
+            DropReason::IdleTimeout => &self.idle_timeout,
+            DropReason::RequestTooLarge => &self.request_too_large,
+            DropReason::ReadError => &self.read_error,
+            DropReason::WriteTimeout => &self.write_timeout,
+            DropReason::RateLimited => &self.rate_limited,
+        }
+    }
+
+    pub fn snapshot(&self) -> DroppedConnectionsEntry {
+        let read_timeout = self.read_timeout.load(Ordering::Relaxed);
+        let idle_timeout = self.idle_timeout.load(Ordering::Relaxed);
+        let request_too_large = self.request_too_large.load(Ordering::Relaxed);
+        let read_error = self.read_error.load(Ordering::Relaxed);
+        let write_timeout = self.write_timeout.load(Ordering::Relaxed);
+        let rate_limited = self.rate_limited.load(Ordering::Relaxed);
+
+        DroppedConnectionsEntry {
+            total: read_timeout + idle_timeout + request_too_large + read_error + write_timeout + rate_limited,
+            read_timeout,
+            idle_timeout,
+            request_too_large,
+            read_error,
+            write_timeout,
+            rate_limited,
+        }
+    }
+}
+
 pub struct StatsManager {
     pub stats: Arc<RwLock<TotalStats>>,
-    storage: Arc<LmdbStorage>,
+    pub dropped: DroppedConnections,
+    storage: Arc<dyn KvStore>,
 }
 
 pub type StatsState = Arc<StatsManager>;
@@ -59,18 +156,15 @@ const STATS_KEY_HOURLY_PREFIX: &str = "stats:hourly:";
 pub async fn create_stats_state() -> StatsState {
     use crate::{log_init_ok_with_details, log_init_failed};
 
-    let storage = match LmdbStorage::new(STATS_LMDB_PATH) {
+    let storage: Arc<dyn KvStore> = match LmdbStorage::new(STATS_LMDB_PATH) {
         Ok(s) => {
             log_init_ok_with_details!("Statistics Storage", &format!("LMDB at {}", STATS_LMDB_PATH));
             Arc::new(s)
         },
         Err(e) => {
             log_init_failed!("Statistics Storage", &format!("LMDB creation failed: {}", e));
-            // Create a dummy storage that doesn't persist
-            Arc::new(LmdbStorage::new("/tmp/stats_dummy").unwrap_or_else(|_| {
-                // As a last resort, create in-memory storage
-                panic!("Failed to create any storage for statistics")
-            }))
+            // Fall back to in-memory storage; statistics will not persist
+            Arc::new(MemoryStore::new())
         }
     };
 
@@ -80,16 +174,21 @@ pub async fn create_stats_state() -> StatsState {
     }
 
     let stats = load_stats_from_lmdb(&storage).await.unwrap_or_default();
+    stats_state_with(storage, stats)
+}
 
+/// Statistics starting from `stats` and saved to `storage`
+pub(crate) fn stats_state_with(storage: Arc<dyn KvStore>, stats: TotalStats) -> StatsState {
     Arc::new(StatsManager {
         stats: Arc::new(RwLock::new(stats)),
+        dropped: DroppedConnections::default(),
         storage,
     })
 }
 
 /// Migrate data from legacy stats.json file to LMDB
 async fn migrate_from_legacy_json(
-    storage: &Arc<LmdbStorage>,
+    storage: &Arc<dyn KvStore>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let legacy_path = Path::new(LEGACY_STATS_FILE);
 
@@ -138,7 +237,7 @@ async fn migrate_from_legacy_json(
 }
 
 async fn load_stats_from_lmdb(
-    storage: &Arc<LmdbStorage>,
+    storage: &Arc<dyn KvStore>,
 ) -> Result<TotalStats, Box<dyn std::error::Error>> {
     // Load total stats
     let (total_requests, total_bytes_served) =
@@ -158,7 +257,7 @@ async fn load_stats_from_lmdb(
 
     // Load daily stats
     let mut daily_stats = HashMap::new();
-    let daily_keys = storage.get_keys_with_prefix(STATS_KEY_DAILY_PREFIX)?;
+    let daily_keys = storage.scan(STATS_KEY_DAILY_PREFIX)?;
     for key in daily_keys {
         if let Some(date) = key.strip_prefix(STATS_KEY_DAILY_PREFIX) {
             if let Some(stats) = storage.get_json::<DailyStats>(&key)? {
@@ -170,7 +269,7 @@ async fn load_stats_from_lmdb(
 
     // Load hourly stats
     let mut hourly_stats = HashMap::new();
-    let hourly_keys = storage.get_keys_with_prefix(STATS_KEY_HOURLY_PREFIX)?;
+    let hourly_keys = storage.scan(STATS_KEY_HOURLY_PREFIX)?;
     for key in hourly_keys {
         if let Some(datetime) = key.strip_prefix(STATS_KEY_HOURLY_PREFIX) {
             if let Some(stats) = storage.get_json::<DailyStats>(&key)? {
@@ -192,7 +291,7 @@ async fn load_stats_from_lmdb(
 }
 
 async fn save_stats_to_lmdb(
-    storage: &Arc<LmdbStorage>,
+    storage: &Arc<dyn KvStore>,
     stats: &TotalStats,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Save total stats
@@ -216,7 +315,7 @@ async fn save_stats_to_lmdb(
     Ok(())
 }
 
-async fn cleanup_old_stats(storage: &Arc<LmdbStorage>, stats: &mut TotalStats) {
+async fn cleanup_old_stats(storage: &Arc<dyn KvStore>, stats: &mut TotalStats) {
     let now = Utc::now();
     let one_month_ago = (now - ChronoDuration::days(31))
         .format("%Y-%m-%d")
@@ -325,6 +424,44 @@ pub async fn record_request(stats_manager: &StatsState, response_size: usize) {
     }
 }
 
+/// Record a connection that was dropped before a response was sent
+pub fn record_dropped_connection(stats_manager: &StatsState, reason: DropReason) {
+    stats_manager.dropped.counter(reason).fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a query in the popularity ranking
+pub fn rank_query(query: &str, query_type: &QueryType) {
+    let key = flight_key(query, query_type);
+    let mut ranking = QUERY_RANKING.lock().unwrap_or_else(|e| e.into_inner());
+
+    if !ranking.contains_key(&key) && ranking.len() >= MAX_RANKED_QUERIES {
+        // Halve all counts and forget the rarely seen queries so recent
+        // popularity wins over old history
+        ranking.retain(|_, ranked| {
+            ranked.count /= 2;
+            ranked.count > 0
+        });
+    }
+
+    ranking
+        .entry(key.clone())
+        .or_insert_with(|| PopularQuery { key, query: query.to_string(), count: 0 })
+        .count += 1;
+}
+
+/// All ranked queries, most popular first
+pub fn popular_queries() -> Vec<PopularQuery> {
+    let ranking = QUERY_RANKING.lock().unwrap_or_else(|e| e.into_inner());
+    let mut ranked: Vec<PopularQuery> = ranking.values().cloned().collect();
+    ranked.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    ranked
+}
+
+/// Number of distinct queries in the popularity ranking
+pub fn ranked_query_count() -> usize {
+    QUERY_RANKING.lock().unwrap_or_else(|e| e.into_inner()).len()
+}
+
 pub async fn get_stats(stats_manager: &StatsState) -> TotalStats {
     stats_manager.stats.read().await.clone()
 }
@@ -336,6 +473,11 @@ pub struct StatsResponse {
     pub total_kb_served: f64,
     pub daily_stats_24h: Vec<DailyStatsEntry>,
     pub daily_stats_30d: Vec<DailyStatsEntry>,
+    pub dropped_connections: DroppedConnectionsEntry,
+    pub prefetch: PrefetchStatsEntry,
+    pub response_cache: ResponseCacheStatsEntry,
+    pub shadow: ShadowStatsEntry,
+    pub jobs: Vec<JobStatus>,
 }
 
 #[derive(Serialize)]
@@ -346,6 +488,17 @@ pub struct DailyStatsEntry {
     pub kb_served: f64,
 }
 
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DroppedConnectionsEntry {
+    pub total: u64,
+    pub read_timeout: u64,
+    pub idle_timeout: u64,
+    pub request_too_large: u64,
+    pub read_error: u64,
+    pub write_timeout: u64,
+    pub rate_limited: u64,
+}
+
 pub async fn get_stats_response(stats_manager: &StatsState) -> StatsResponse {
     let stats_data = get_stats(stats_manager).await;
     let now = Utc::now();
@@ -400,6 +553,11 @@ pub async fn get_stats_response(stats_manager: &StatsState) -> StatsResponse {
         total_kb_served: (stats_data.total_bytes_served as f64) / 1024.0,
         daily_stats_24h: daily_24h,
         daily_stats_30d: daily_30d,
+        dropped_connections: stats_manager.dropped.snapshot(),
+        prefetch: prefetch_stats(),
+        response_cache: response_cache_stats(),
+        shadow: shadow_stats(),
+        jobs: job_statuses(),
     }
 }
 
@@ -411,3 +569,59 @@ pub async fn save_stats_on_shutdown(stats_manager: &StatsState) {
         log_info!("Statistics saved successfully to LMDB on shutdown");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_save_and_load_roundtrip() {
+        let storage: Arc<dyn KvStore> = Arc::new(MemoryStore::new());
+
+        let mut stats = TotalStats { total_requests: 42, total_bytes_served: 1024, ..Default::default() };
+        stats.daily_stats.insert("2025-01-01".to_string(), DailyStats { requests: 40, bytes_served: 1000 });
+        stats.hourly_stats.insert("2025-01-01 12".to_string(), DailyStats { requests: 2, bytes_served: 24 });
+
+        save_stats_to_lmdb(&storage, &stats).await.unwrap();
+        let loaded = load_stats_from_lmdb(&storage).await.unwrap();
+
+        assert_eq!(loaded.total_requests, 42);
+        assert_eq!(loaded.total_bytes_served, 1024);
+        assert_eq!(loaded.daily_stats["2025-01-01"].requests, 40);
+        assert_eq!(loaded.hourly_stats["2025-01-01 12"].bytes_served, 24);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_removes_old_entries_from_storage() {
+        let storage: Arc<dyn KvStore> = Arc::new(MemoryStore::new());
+
+        let mut stats = TotalStats::default();
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        stats.daily_stats.insert("2000-01-01".to_string(), DailyStats { requests: 1, bytes_served: 1 });
+        stats.daily_stats.insert(today.clone(), DailyStats { requests: 1, bytes_served: 1 });
+        save_stats_to_lmdb(&storage, &stats).await.unwrap();
+
+        cleanup_old_stats(&storage, &mut stats).await;
+
+        assert!(!stats.daily_stats.contains_key("2000-01-01"));
+        assert!(stats.daily_stats.contains_key(&today));
+        assert_eq!(storage.scan(STATS_KEY_DAILY_PREFIX).unwrap(), vec![format!("{}{}", STATS_KEY_DAILY_PREFIX, today)]);
+    }
+
+    #[test]
+    fn test_ranking_orders_by_popularity() {
+        for _ in 0..3 {
+            rank_query("stats-rank-a.example", &crate::core::analyze_query("stats-rank-a.example"));
+        }
+        for _ in 0..5 {
+            rank_query("STATS-RANK-B.example", &crate::core::analyze_query("STATS-RANK-B.example"));
+        }
+
+        let top: Vec<(String, u64)> = popular_queries()
+            .into_iter()
+            .filter(|ranked| ranked.query.to_lowercase().starts_with("stats-rank-"))
+            .map(|ranked| (ranked.query, ranked.count))
+            .collect();
+        assert_eq!(top, vec![("STATS-RANK-B.example".to_string(), 5), ("stats-rank-a.example".to_string(), 3)]);
+    }
+}
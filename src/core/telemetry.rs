@@ -190,35 +190,22 @@ pub fn query_type_to_string(query_type: &crate::core::QueryType) -> String {
         crate::core::QueryType::Ris(_) => "ris".to_string(),
         crate::core::QueryType::Tc(_) => "tc".to_string(),
         crate::core::QueryType::Irr(_) => "irr".to_string(),
-        crate::core::QueryType::LookingGlass(_) => "looking_glass".to_string(),
+        crate::core::QueryType::LookingGlass(..) => "looking_glass".to_string(),
         crate::core::QueryType::Rpki(_, _) => "rpki".to_string(),
         crate::core::QueryType::Manrs(_) => "manrs".to_string(),
         crate::core::QueryType::Dns(_) => "dns".to_string(),
-        crate::core::QueryType::Trace(_) => "traceroute".to_string(),
+        crate::core::QueryType::Trace(..) => "traceroute".to_string(),
         crate::core::QueryType::Ssl(_) => "ssl".to_string(),
         crate::core::QueryType::Crt(_) => "certificate_transparency".to_string(),
         crate::core::QueryType::CfStatus(_) => "cloudflare_status".to_string(),
         crate::core::QueryType::Minecraft(_) => "minecraft".to_string(),
         crate::core::QueryType::MinecraftUser(_) => "minecraft_user".to_string(),
-        crate::core::QueryType::Steam(_) => "steam".to_string(),
-        crate::core::QueryType::SteamSearch(_) => "steam_search".to_string(),
+        crate::core::QueryType::Steam(..) => "steam".to_string(),
+        crate::core::QueryType::SteamSearch(..) => "steam_search".to_string(),
         crate::core::QueryType::Imdb(_) => "imdb".to_string(),
         crate::core::QueryType::ImdbSearch(_) => "imdb_search".to_string(),
         crate::core::QueryType::Acgc(_) => "acgc".to_string(),
-        crate::core::QueryType::Alma(_) => "alma".to_string(),
-        crate::core::QueryType::Aosc(_) => "aosc".to_string(),
-        crate::core::QueryType::Aur(_) => "aur".to_string(),
-        crate::core::QueryType::Debian(_) => "debian".to_string(),
-        crate::core::QueryType::Epel(_) => "epel".to_string(),
-        crate::core::QueryType::Ubuntu(_) => "ubuntu".to_string(),
-        crate::core::QueryType::NixOs(_) => "nixos".to_string(),
-        crate::core::QueryType::OpenSuse(_) => "opensuse".to_string(),
-        crate::core::QueryType::OpenWrt(_) => "openwrt".to_string(),
-        crate::core::QueryType::Npm(_) => "npm".to_string(),
-        crate::core::QueryType::Pypi(_) => "pypi".to_string(),
-        crate::core::QueryType::Cargo(_) => "cargo".to_string(),
-        crate::core::QueryType::Modrinth(_) => "modrinth".to_string(),
-        crate::core::QueryType::CurseForge(_) => "curseforge".to_string(),
+        crate::core::QueryType::Service(name, _) => name.to_string(),
         crate::core::QueryType::GitHub(_) => "github".to_string(),
         crate::core::QueryType::Wikipedia(_) => "wikipedia".to_string(),
         crate::core::QueryType::Lyric(_) => "lyric".to_string(),
@@ -231,14 +218,27 @@ pub fn query_type_to_string(query_type: &crate::core::QueryType) -> String {
         crate::core::QueryType::Meal => "meal".to_string(),
         crate::core::QueryType::MealCN => "meal_cn".to_string(),
         crate::core::QueryType::Ntp(_) => "ntp".to_string(),
-        crate::core::QueryType::Ping(_) => "ping".to_string(),
+        crate::core::QueryType::Ping(..) => "ping".to_string(),
+        crate::core::QueryType::Probes(_) => "probes".to_string(),
         crate::core::QueryType::Help => "help".to_string(),
         crate::core::QueryType::UpdatePatch => "update_patch".to_string(),
+        crate::core::QueryType::Explain(_) => "explain".to_string(),
         crate::core::QueryType::Plugin(_, _) => "plugin".to_string(),
         crate::core::QueryType::Unknown(_) => "unknown".to_string(),
     }
 }
 
+/// Labels [`query_type_to_string`] gives the built-in query types; services
+/// are labelled with their registered name
+pub const QUERY_TYPE_LABELS: &[&str] = &[
+    "domain", "ipv4", "ipv6", "asn", "email_search", "bgptool", "geo", "rir_geo", "prefixes", "radb", "altdb",
+    "afrinic", "apnic", "arin_irr", "bell", "jpirr", "lacnic", "level3", "nttcom", "ripe_irr", "ris", "tc", "irr",
+    "looking_glass", "rpki", "manrs", "dns", "traceroute", "ssl", "certificate_transparency", "cloudflare_status",
+    "minecraft", "minecraft_user", "steam", "steam_search", "imdb", "imdb_search", "acgc", "github", "wikipedia",
+    "lyric", "description", "peeringdb", "pen", "rdap", "pixiv", "icp", "meal", "meal_cn", "ntp", "ping", "probes",
+    "help", "update_patch", "explain", "plugin", "unknown",
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
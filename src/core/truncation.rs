@@ -0,0 +1,164 @@
+// WHOIS Server - Response Truncation
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Truncation of huge responses with continuation tokens
+//!
+//! Some registries answer with thousands of objects (an ARIN org with all of
+//! its networks, a large as-set). Plain-text responses longer than
+//! `--truncate-lines` are cut at the last object boundary before the limit
+//! and end with a `-MORE=<token>` query that continues where they stopped.
+//! The full response is kept in the cache backend for `CONTINUATION_TTL`
+//! under an id derived from its content, so the token is the same every time
+//! the same response is truncated and works on every instance sharing a
+//! Redis cache. Without a cache backend responses are never truncated.
+
+use anyhow::{Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::storage::cache::{CacheStore, cache_store};
+use crate::log_debug;
+
+/// Line limit used until `init_truncation` is called
+pub const DEFAULT_TRUNCATE_LINES: usize = 1000;
+
+/// How long a truncated response can be continued
+const CONTINUATION_TTL: Duration = Duration::from_secs(3600);
+
+static TRUNCATE_LINES: OnceLock<usize> = OnceLock::new();
+
+/// Set the line limit; 0 disables truncation
+pub fn init_truncation(lines: usize) {
+    let _ = TRUNCATE_LINES.set(lines);
+}
+
+fn truncate_lines() -> usize {
+    TRUNCATE_LINES.get().copied().unwrap_or(DEFAULT_TRUNCATE_LINES)
+}
+
+fn storage_key(id: &str) -> String {
+    format!("continuation:{}", id)
+}
+
+/// Parse `<id>.<line>` into the response id and the line to continue at
+fn parse_token(token: &str) -> Option<(String, usize)> {
+    let (id, line) = token.split_once('.')?;
+    if id.len() != 16 || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((id.to_ascii_lowercase(), line.parse().ok()?))
+}
+
+/// End of the chunk starting at `start`: after the last blank line within
+/// the limit, unless that would make the chunk less than half as long
+fn chunk_end(lines: &[&str], start: usize, limit: usize) -> usize {
+    let end = (start + limit).min(lines.len());
+    if end == lines.len() {
+        return end;
+    }
+    (start + limit / 2..end)
+        .rev()
+        .find(|&i| lines[i].trim().is_empty())
+        .map_or(end, |blank| blank + 1)
+}
+
+/// Lines `start..end` followed by the continuation note when more remain
+fn render_chunk(lines: &[&str], start: usize, end: usize, id: &str, query: &str) -> String {
+    let mut output = String::new();
+    for line in &lines[start..end] {
+        output.push_str(line);
+        output.push('\n');
+    }
+    if end < lines.len() {
+        if !output.ends_with("\n\n") {
+            output.push('\n');
+        }
+        output.push_str(&format!("% Response truncated after line {} of {}\n", end, lines.len()));
+        output.push_str(&format!("% Continue with: {}-MORE={}.{}\n", query, id, end));
+    }
+    output
+}
+
+async fn truncate_in(store: &dyn CacheStore, query: &str, response: String, limit: usize) -> String {
+    let lines: Vec<&str> = response.lines().collect();
+    if limit == 0 || lines.len() <= limit {
+        return response;
+    }
+
+    let id = format!("{:x}", Sha256::digest(response.as_bytes()))[..16].to_string();
+    if let Err(e) = store.set(&storage_key(&id), &response, CONTINUATION_TTL).await {
+        log_debug!("Failed to store truncated response {}: {}", id, e);
+        return response;
+    }
+    log_debug!("Truncated {} line response to {} as {}", lines.len(), query, id);
+    render_chunk(&lines, 0, chunk_end(&lines, 0, limit), &id, query)
+}
+
+async fn continue_in(store: &dyn CacheStore, query: &str, token: &str, limit: usize) -> Result<String> {
+    let (id, start) = parse_token(token).ok_or_else(|| anyhow!("Invalid continuation token: {}", token))?;
+    let response = store
+        .get(&storage_key(&id))
+        .await?
+        .ok_or_else(|| anyhow!("Continuation token {} has expired, run the query again", token))?;
+
+    let lines: Vec<&str> = response.lines().collect();
+    if start == 0 || start >= lines.len() {
+        return Err(anyhow!("Continuation token {} is past the end of the response", token));
+    }
+    let end = chunk_end(&lines, start, limit.max(1));
+    let header = format!("% Lines {}-{} of {}\n\n", start + 1, end, lines.len());
+    Ok(header + &render_chunk(&lines, start, end, &id, query))
+}
+
+/// Truncate a long response, keeping the full one for `-MORE`
+pub async fn truncate_response(query: &str, response: String) -> String {
+    match cache_store() {
+        Some(store) => truncate_in(store.as_ref(), query, response, truncate_lines()).await,
+        None => response,
+    }
+}
+
+/// The chunk of a truncated response a `-MORE=<token>` query asks for
+pub async fn continue_response(query: &str, token: &str) -> Result<String> {
+    let store = cache_store().ok_or_else(|| anyhow!("Continuation tokens need a cache backend"))?;
+    continue_in(store.as_ref(), query, token, truncate_lines()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::cache::KvCacheStore;
+    use crate::storage::kv::MemoryStore;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_truncate_and_continue() {
+        let store = KvCacheStore::new(Arc::new(MemoryStore::new()));
+        // 30 objects of three attributes and a blank line
+        let response: String = (1..=30).map(|i| format!("inetnum: {}\nnetname: NET-{}\nsource: ARIN\n\n", i, i)).collect();
+
+        let first = truncate_in(&store, "ORG-EXAMPLE", response.clone(), 50).await;
+        assert!(first.starts_with("inetnum: 1\n"));
+        assert!(first.contains("source: ARIN\n\n% Response truncated after line 48 of 120\n"));
+        let token = first.lines().last().unwrap().rsplit_once("-MORE=").unwrap().1.to_string();
+        assert!(token.ends_with(".48"));
+
+        // The same response always gets the same token
+        assert_eq!(truncate_in(&store, "ORG-EXAMPLE", response.clone(), 50).await, first);
+
+        let second = continue_in(&store, "ORG-EXAMPLE", &token, 50).await.unwrap();
+        assert!(second.starts_with("% Lines 49-96 of 120\n\ninetnum: 13\n"));
+        let token = second.lines().last().unwrap().rsplit_once("-MORE=").unwrap().1.to_string();
+        let last = continue_in(&store, "ORG-EXAMPLE", &token, 50).await.unwrap();
+        assert!(last.starts_with("% Lines 97-120 of 120\n"));
+        assert!(last.ends_with("source: ARIN\n\n"));
+        assert!(!last.contains("-MORE="));
+
+        // Short responses and unknown tokens
+        assert_eq!(truncate_in(&store, "AS13335", "aut-num: AS13335\n".to_string(), 50).await, "aut-num: AS13335\n");
+        assert!(continue_in(&store, "ORG-EXAMPLE", "0123456789abcdef.10", 50).await.is_err());
+        assert!(continue_in(&store, "ORG-EXAMPLE", "nonsense", 50).await.is_err());
+    }
+}
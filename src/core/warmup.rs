@@ -0,0 +1,222 @@
+// WHOIS Server - Startup Warm-up
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Background initialization of heavy subsystems
+//!
+//! Cloning the DN42 registry, downloading the PEN list and loading plugins
+//! can take minutes after a deploy. They run as steps of a small dependency
+//! graph while the listeners already accept connections: each step starts
+//! once the steps it depends on have finished, independent steps run in
+//! parallel. Queries that need a subsystem still initializing get a "still
+//! starting up" error, which is never cached, instead of waiting or being
+//! answered from incomplete data. A step that fails leaves its subsystem
+//! usable the way it was before (queries report their own errors).
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Instant;
+use tokio::sync::Notify;
+
+use crate::{log_init_ok_with_details, log_init_start, log_init_warn};
+
+/// A subsystem initialized in the background
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    Dn42,
+    Pen,
+    Plugins,
+    Prefetch,
+}
+
+impl Subsystem {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dn42 => "DN42 System",
+            Self::Pen => "PEN Registry",
+            Self::Plugins => "Plugin System",
+            Self::Prefetch => "Popular Query Prefetch",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+const READY: u8 = 0;
+const PENDING: u8 = 1;
+const FAILED: u8 = 2;
+
+/// Subsystems not part of a startup graph count as ready
+static STATES: [AtomicU8; 4] = [const { AtomicU8::new(READY) }; 4];
+/// Woken whenever a step finishes
+static SETTLED: Lazy<Notify> = Lazy::new(Notify::new);
+/// Suffixes of the plugins being loaded, upper case
+static PENDING_PLUGIN_SUFFIXES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+fn state(subsystem: Subsystem) -> u8 {
+    STATES[subsystem.index()].load(Ordering::Acquire)
+}
+
+fn set_state(subsystem: Subsystem, value: u8) {
+    STATES[subsystem.index()].store(value, Ordering::Release);
+}
+
+/// Error for queries that need a subsystem still initializing
+#[derive(Debug)]
+pub struct WarmingUp(pub Subsystem);
+
+impl fmt::Display for WarmingUp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is still starting up, please retry in a few seconds", self.0.name())
+    }
+}
+
+impl std::error::Error for WarmingUp {}
+
+/// Fail with [`WarmingUp`] while `subsystem` is initializing
+pub fn ensure_ready(subsystem: Subsystem) -> Result<()> {
+    if state(subsystem) == PENDING {
+        return Err(WarmingUp(subsystem).into());
+    }
+    Ok(())
+}
+
+/// Remember the suffixes of the plugins about to be loaded
+///
+/// Until they are, plugin queries are not recognized as such; matching the
+/// declared suffixes lets them fail with [`WarmingUp`] instead of being
+/// looked up as something else.
+pub fn expect_plugin_suffixes(suffixes: Vec<String>) {
+    *PENDING_PLUGIN_SUFFIXES.write().unwrap() = suffixes.into_iter().map(|suffix| suffix.to_uppercase()).collect();
+}
+
+/// Fail with [`WarmingUp`] when `query` names a plugin still being loaded
+pub fn ensure_plugin_ready(query: &str) -> Result<()> {
+    if state(Subsystem::Plugins) != PENDING {
+        return Ok(());
+    }
+    let query = query.to_uppercase();
+    if PENDING_PLUGIN_SUFFIXES.read().unwrap().iter().any(|suffix| query.ends_with(suffix.as_str())) {
+        return Err(WarmingUp(Subsystem::Plugins).into());
+    }
+    Ok(())
+}
+
+/// Subsystems still initializing
+pub fn pending_subsystems() -> Vec<Subsystem> {
+    [Subsystem::Dn42, Subsystem::Pen, Subsystem::Plugins, Subsystem::Prefetch]
+        .into_iter()
+        .filter(|subsystem| state(*subsystem) == PENDING)
+        .collect()
+}
+
+type InitFuture = Pin<Box<dyn Future<Output = Result<String>> + Send>>;
+
+struct Step {
+    subsystem: Subsystem,
+    after: Vec<Subsystem>,
+    init: InitFuture,
+}
+
+/// Initialization steps and their dependencies
+#[derive(Default)]
+pub struct StartupGraph {
+    steps: Vec<Step>,
+}
+
+impl StartupGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a step initializing `subsystem` once every step in `after` finished
+    ///
+    /// Dependencies must be added first, so the graph cannot have cycles.
+    /// The init future returns details for the startup log.
+    pub fn step<F>(mut self, subsystem: Subsystem, after: &[Subsystem], init: F) -> Self
+    where
+        F: Future<Output = Result<String>> + Send + 'static,
+    {
+        for dependency in after {
+            assert!(
+                self.steps.iter().any(|step| step.subsystem == *dependency),
+                "{} depends on {}, which must be added first",
+                subsystem.name(),
+                dependency.name()
+            );
+        }
+        set_state(subsystem, PENDING);
+        self.steps.push(Step { subsystem, after: after.to_vec(), init: Box::pin(init) });
+        self
+    }
+
+    /// Start every step in the background; returns immediately
+    pub fn spawn(self) {
+        for step in self.steps {
+            tokio::spawn(run_step(step));
+        }
+    }
+}
+
+async fn run_step(step: Step) {
+    loop {
+        let settled = SETTLED.notified();
+        if step.after.iter().all(|dependency| state(*dependency) != PENDING) {
+            break;
+        }
+        settled.await;
+    }
+
+    let name = step.subsystem.name();
+    log_init_start!(name);
+    let started = Instant::now();
+    match step.init.await {
+        Ok(details) => {
+            log_init_ok_with_details!(name, &format!("{} (ready after {:.1}s)", details, started.elapsed().as_secs_f64()));
+            set_state(step.subsystem, READY);
+        }
+        Err(e) => {
+            log_init_warn!(name, &format!("continuing without it: {}", e));
+            set_state(step.subsystem, FAILED);
+        }
+    }
+    SETTLED.notify_waiters();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_startup_graph_order() {
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let (done, finished) = tokio::sync::oneshot::channel::<bool>();
+        StartupGraph::new()
+            .step(Subsystem::Plugins, &[], async move {
+                released.await?;
+                Ok("loaded".to_string())
+            })
+            .step(Subsystem::Prefetch, &[Subsystem::Plugins], async move {
+                let _ = done.send(state(Subsystem::Plugins) == READY);
+                Ok("started".to_string())
+            })
+            .spawn();
+
+        expect_plugin_suffixes(vec!["-Weather".to_string()]);
+        let error = ensure_plugin_ready("london-weather").unwrap_err();
+        assert_eq!(error.to_string(), "Plugin System is still starting up, please retry in a few seconds");
+        assert!(ensure_plugin_ready("example.com").is_ok());
+        assert!(ensure_ready(Subsystem::Prefetch).is_err());
+
+        release.send(()).unwrap();
+        assert!(finished.await.unwrap());
+        assert!(ensure_plugin_ready("london-weather").is_ok());
+    }
+}
@@ -2,7 +2,6 @@ use anyhow::Result;
 use std::net::{ Ipv4Addr, Ipv6Addr };
 use std::path::Path;
 use std::process::Command;
-use tokio::time::{ Duration, interval };
 use crate::config::{ DN42_LMDB_PATH, DN42_REGISTRY_PATH };
 use crate::storage::{ SharedLmdbStorage, create_shared_storage };
 
@@ -817,6 +816,25 @@ fn pull_latest_changes() -> Result<()> {
     reset_result
 }
 
+/// Expire reflogs and garbage collect the registry checkout
+///
+/// Every fetch leaves objects behind; this is how the disk janitor evicts
+/// from the checkout when it is over its cap.
+pub fn compact_dn42_registry() -> Result<()> {
+    if !Path::new(DN42_REGISTRY_PATH).join(".git").exists() {
+        return Ok(());
+    }
+    for args in [&["reflog", "expire", "--expire=now", "--all"][..], &["gc", "--prune=now", "--quiet"][..]] {
+        let output = Command::new("git").args(args).current_dir(DN42_REGISTRY_PATH).output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("git {} failed: {}", args[0], stderr));
+        }
+    }
+    log_info!("Compacted DN42 registry checkout");
+    Ok(())
+}
+
 /// Parse ASN from query, handling various formats
 fn parse_asn(query: &str) -> Option<String> {
     let normalized = query.to_uppercase();
@@ -869,31 +887,12 @@ pub async fn initialize_dn42_system() -> Result<()> {
     registry.initialize().await
 }
 
-/// Start the periodic DN42 registry sync task
-pub async fn start_periodic_sync() {
-    log_info!("Starting periodic DN42 registry sync (every hour)");
-
-    // Initial sync at startup
-    if let Err(e) = initialize_dn42_system().await {
-        log_error!("Initial DN42 registry initialization failed: {}", e);
-    }
-
-    // Set up hourly sync
-    let mut interval = interval(Duration::from_secs(3600)); // 1 hour
-    interval.tick().await; // Skip the first tick (we just did initial sync)
-
-    loop {
-        interval.tick().await;
-
-        log_info!("Starting scheduled DN42 registry sync");
-        if let Ok(registry) = get_dn42_registry().await {
-            if let Err(e) = registry.update().await {
-                log_error!("Scheduled DN42 registry sync failed: {}", e);
-            }
-        } else {
-            log_error!("Failed to get DN42 registry instance for scheduled sync");
-        }
-    }
+/// Pull the DN42 registry (the hourly `dn42-sync` job in Git mode)
+///
+/// The initial sync is a startup step (`initialize_dn42_system`).
+pub async fn sync_dn42_registry() -> Result<()> {
+    log_info!("Starting scheduled DN42 registry sync");
+    get_dn42_registry().await?.update().await
 }
 
 /// Process DN42 query using LMDB storage
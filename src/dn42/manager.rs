@@ -1,5 +1,6 @@
 use anyhow::Result;
 use crate::config::DN42_LMDB_PATH;
+use crate::core::warmup::{ Subsystem, ensure_ready };
 use crate::dn42::online_backend::{ DN42OnlineFetcher, get_platform_info, is_windows };
 use crate::dn42::query::{
     DN42QueryType,
@@ -222,6 +223,7 @@ pub async fn initialize_dn42_manager() -> Result<()> {
 
 /// Process DN42 query using the manager
 pub async fn process_dn42_query_managed(query: &str) -> Result<String> {
+    ensure_ready(Subsystem::Dn42)?;
     let manager_mutex = get_dn42_manager().await?;
     let mut manager = manager_mutex.lock().await;
     manager.query(query).await
@@ -229,6 +231,7 @@ pub async fn process_dn42_query_managed(query: &str) -> Result<String> {
 
 /// Process DN42 raw query using the manager
 pub async fn query_dn42_raw_managed(query: &str) -> Result<String> {
+    ensure_ready(Subsystem::Dn42)?;
     let manager_mutex = get_dn42_manager().await?;
     let mut manager = manager_mutex.lock().await;
     manager.query_raw(query).await
@@ -2,6 +2,7 @@ pub mod git_backend;
 pub mod manager;
 pub mod online_backend;
 pub mod query;
+pub mod registries;
 
 pub use git_backend::*;
 pub use manager::*;
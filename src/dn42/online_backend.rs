@@ -2,6 +2,7 @@ use anyhow::Result;
 use reqwest::Client;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use crate::config::DN42_LMDB_PATH;
+use crate::core::body_limit::LimitedBody;
 use crate::storage::{SharedLmdbStorage, create_shared_storage};
 
 use crate::{log_debug, log_error, log_info, log_warn};
@@ -93,7 +94,7 @@ impl DN42OnlineFetcher {
         match self.client.get(&url).send().await {
             Ok(response) => {
                 if response.status().is_success() {
-                    match response.text().await {
+                    match response.limited_text().await {
                         Ok(content) => {
                             log_info!(
                                 "DN42 Online: Successfully fetched {}/{}, size: {} bytes",
@@ -0,0 +1,180 @@
+// WHOIS Server - CRXN Registry
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! CRXN (Community Routed eXperimental Network) registry
+//!
+//! CRXN is IPv6-only and keeps its registry in the DN42 layout: RPSL objects
+//! in `data/<type>/<name>`, with prefixes named like `fd8a:6111:3b1a::_48`.
+//! The `inet6num` and `route6` objects are cached for prefix lookups; other
+//! objects (maintainers, persons) are fetched by name. CRXN assigns from the
+//! same ULA space as DN42, so it is only queried with the `-CRXN` suffix.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use cidr::Ipv6Cidr;
+use std::net::Ipv6Addr;
+
+use super::{PrivateRegistry, cached, fetch_directory, registry_response};
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::dn42::query::format_ipv6_network_response;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+
+const CRXN_REGISTRY_API: &str = "https://codeberg.org/api/v1/repos/CRXN/registry/contents/data/";
+const CRXN_RAW_BASE: &str = "https://codeberg.org/CRXN/registry/raw/branch/master/data/";
+const CACHE_KEY: &str = "crxn";
+
+/// Prefix of an object file name such as `fd8a:6111:3b1a::_48`
+fn file_prefix(name: &str) -> Option<Ipv6Cidr> {
+    let (addr, len) = name.split_once('_')?;
+    Ipv6Cidr::new(addr.parse().ok()?, len.parse().ok()?).ok()
+}
+
+/// Most specific object whose prefix contains `query`
+fn most_specific<'a>(objects: &'a [(String, String)], query: &Ipv6Cidr) -> Option<&'a str> {
+    objects
+        .iter()
+        .filter_map(|(name, content)| file_prefix(name).map(|prefix| (prefix, content)))
+        .filter(|(prefix, _)| {
+            prefix.network_length() <= query.network_length() && prefix.contains(&query.first_address())
+        })
+        .max_by_key(|(prefix, _)| prefix.network_length())
+        .map(|(_, content)| content.as_str())
+}
+
+/// Parse an IPv6 address or prefix
+fn parse_prefix(query: &str) -> Option<Ipv6Cidr> {
+    query.parse::<Ipv6Cidr>().ok().or_else(|| query.parse::<Ipv6Addr>().ok().map(Ipv6Cidr::new_host))
+}
+
+/// Object type directory for a handle
+fn handle_types(handle: &str) -> &'static [&'static str] {
+    if handle.ends_with("-MNT") { &["mntner"] } else { &["person", "mntner", "dns"] }
+}
+
+/// Cached `inet6num` and `route6` objects
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PrefixObjects {
+    inet6num: Vec<(String, String)>,
+    route6: Vec<(String, String)>,
+}
+
+/// CRXN registry
+pub struct CrxnRegistry;
+
+impl CrxnRegistry {
+    async fn prefix_objects(&self) -> Result<PrefixObjects> {
+        cached(CACHE_KEY, || async {
+            let base = api_base(CRXN_REGISTRY_API);
+            let prefix_file = |name: &str| file_prefix(name).is_some();
+            Ok(PrefixObjects {
+                inet6num: fetch_directory(&format!("{}inet6num", base), prefix_file).await?,
+                route6: fetch_directory(&format!("{}route6", base), prefix_file).await?,
+            })
+        })
+        .await
+    }
+
+    /// Fetch one object by type and name, `None` when it does not exist
+    async fn fetch_object(&self, object_type: &str, name: &str) -> Result<Option<String>> {
+        let url = format!("{}{}/{}", api_base(CRXN_RAW_BASE), object_type, name);
+        let response = super::http_client()?.get(&url).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("CRXN registry request failed: HTTP {}", response.status()));
+        }
+        Ok(Some(response.limited_text().await?))
+    }
+}
+
+#[async_trait]
+impl PrivateRegistry for CrxnRegistry {
+    fn registry_name(&self) -> &'static str {
+        "CRXN"
+    }
+
+    fn ranges(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    async fn lookup(&self, query: &str) -> Result<Option<String>> {
+        let query = query.trim();
+        if let Some(prefix) = parse_prefix(query) {
+            let objects = self.prefix_objects().await?;
+            let inet6num = most_specific(&objects.inet6num, &prefix);
+            let route6 = most_specific(&objects.route6, &prefix);
+            if inet6num.is_none() && route6.is_none() {
+                return Ok(None);
+            }
+            return Ok(Some(format_ipv6_network_response(
+                query,
+                inet6num.map(str::to_string),
+                route6.map(str::to_string),
+            )));
+        }
+
+        let handle = query.to_uppercase();
+        if handle.is_empty() || !handle.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+            return Ok(None);
+        }
+        for object_type in handle_types(&handle) {
+            let name = if *object_type == "dns" { query.to_lowercase() } else { handle.clone() };
+            if let Some(content) = self.fetch_object(object_type, &name).await? {
+                return Ok(Some(format!("% Query: {}\n{}", query, content)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl QueryService for CrxnRegistry {
+    fn name(&self) -> &'static str {
+        "crxn"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-CRXN"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "DN42 NETWORK",
+            summary: "CRXN registry objects (inet6num/route6, maintainers)",
+            example: "fd8a:6111:3b1a::1-CRXN",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "CRXN registry (codeberg.org/CRXN/registry)"
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        registry_response(self, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_specific_prefix() {
+        let objects = vec![
+            ("fd8a::_16".to_string(), "inet6num: fd8a::/16\n".to_string()),
+            ("fd8a:6111:3b1a::_48".to_string(), "inet6num: fd8a:6111:3b1a::/48\n".to_string()),
+            ("README.md".to_string(), String::new()),
+        ];
+
+        let query = parse_prefix("fd8a:6111:3b1a::1").unwrap();
+        assert_eq!(most_specific(&objects, &query), Some("inet6num: fd8a:6111:3b1a::/48\n"));
+        let query = parse_prefix("fd8a:1::/32").unwrap();
+        assert_eq!(most_specific(&objects, &query), Some("inet6num: fd8a::/16\n"));
+        assert_eq!(most_specific(&objects, &parse_prefix("fd00::1").unwrap()), None);
+        assert!(parse_prefix("EXAMPLE-MNT").is_none());
+    }
+}
@@ -0,0 +1,249 @@
+// WHOIS Server - ICVPN Registry
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Freifunk ICVPN (inter-city VPN) registry
+//!
+//! Communities are described by one YAML file each in the `icvpn-meta`
+//! repository: AS number, IPv4/IPv6 networks, DNS zones, BGP peers on the
+//! transfer network and technical contacts. Queries can be an address or
+//! prefix, an AS number, a community name or one of its domains.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use cidr::IpCidr;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use super::{PrivateRegistry, cached, fetch_directory, registry_response};
+use crate::core::query::parse_asn;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+
+const ICVPN_META_API: &str = "https://api.github.com/repos/freifunk/icvpn-meta/contents/";
+const CACHE_KEY: &str = "icvpn";
+
+/// One community file of `icvpn-meta`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Community {
+    #[serde(skip)]
+    name: String,
+    asn: Option<u32>,
+    networks: Networks,
+    domains: Vec<String>,
+    bgp: BTreeMap<String, BgpPeer>,
+    #[serde(rename = "tech-c")]
+    tech_c: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Networks {
+    ipv4: Vec<String>,
+    ipv6: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BgpPeer {
+    ipv4: Option<String>,
+    ipv6: Option<String>,
+}
+
+impl Community {
+    fn networks(&self) -> impl Iterator<Item = IpCidr> + '_ {
+        self.networks.ipv4.iter().chain(&self.networks.ipv6).filter_map(|net| net.parse().ok())
+    }
+
+    /// Prefix length of the most specific network containing `query`
+    fn match_length(&self, query: &IpCidr) -> Option<u8> {
+        self.networks()
+            .filter(|net| net.network_length() <= query.network_length() && net.contains(&query.first_address()))
+            .map(|net| net.network_length())
+            .max()
+    }
+
+    fn has_peer_address(&self, ip: IpAddr) -> bool {
+        self.bgp
+            .values()
+            .flat_map(|peer| [&peer.ipv4, &peer.ipv6])
+            .flatten()
+            .any(|addr| addr.parse::<IpAddr>().is_ok_and(|addr| addr == ip))
+    }
+}
+
+/// Parse the community files, skipping files that are not valid YAML
+fn parse_communities(files: &[(String, String)]) -> Vec<Community> {
+    files
+        .iter()
+        .filter_map(|(name, content)| {
+            let mut community: Community = serde_yaml::from_str(content).ok()?;
+            community.name = name.clone();
+            Some(community)
+        })
+        .collect()
+}
+
+/// Find the community a query refers to
+fn find_community<'a>(communities: &'a [Community], query: &str) -> Option<&'a Community> {
+    let query = query.trim();
+    if let Ok(ip) = query.parse::<IpAddr>()
+        && let Some(peer) = communities.iter().find(|c| c.has_peer_address(ip))
+    {
+        return Some(peer);
+    }
+    let prefix = query.parse::<IpCidr>().ok().or_else(|| query.parse::<IpAddr>().ok().map(IpCidr::new_host));
+    if let Some(prefix) = prefix {
+        return communities
+            .iter()
+            .filter_map(|c| c.match_length(&prefix).map(|len| (c, len)))
+            .max_by_key(|(_, len)| *len)
+            .map(|(c, _)| c);
+    }
+    if let Some(asn) = parse_asn(query) {
+        return communities.iter().find(|c| c.asn == Some(asn));
+    }
+    let name = query.trim_end_matches('.').to_lowercase();
+    communities
+        .iter()
+        .find(|c| c.name.eq_ignore_ascii_case(&name))
+        .or_else(|| communities.iter().find(|c| c.domains.iter().any(|d| d.eq_ignore_ascii_case(&name))))
+}
+
+fn format_community(query: &str, community: &Community) -> String {
+    let mut output = format!("% Query: {}\n% ICVPN community from icvpn-meta\n\n", query);
+    output.push_str(&format!("community:       {}\n", community.name));
+    if let Some(asn) = community.asn {
+        output.push_str(&format!("asn:             AS{}\n", asn));
+    }
+    for net in community.networks.ipv4.iter().chain(&community.networks.ipv6) {
+        output.push_str(&format!("network:         {}\n", net));
+    }
+    for domain in &community.domains {
+        output.push_str(&format!("domain:          {}\n", domain));
+    }
+    for (name, peer) in &community.bgp {
+        let addresses: Vec<&str> = [&peer.ipv4, &peer.ipv6].into_iter().flatten().map(String::as_str).collect();
+        output.push_str(&format!("bgp-peer:        {} {}\n", name, addresses.join(" ")));
+    }
+    for contact in &community.tech_c {
+        output.push_str(&format!("tech-c:          {}\n", contact));
+    }
+    output.push_str("source:          ICVPN\n");
+    output
+}
+
+/// Freifunk ICVPN registry
+pub struct IcvpnRegistry;
+
+impl IcvpnRegistry {
+    async fn communities(&self) -> Result<Vec<Community>> {
+        let files = cached(CACHE_KEY, || async {
+            fetch_directory(&api_base(ICVPN_META_API), |name| !name.starts_with('.') && !name.contains('.')).await
+        })
+        .await?;
+        Ok(parse_communities(&files))
+    }
+}
+
+#[async_trait]
+impl PrivateRegistry for IcvpnRegistry {
+    fn registry_name(&self) -> &'static str {
+        "ICVPN"
+    }
+
+    fn ranges(&self) -> &'static [&'static str] {
+        // Freifunk networks and the ICVPN transfer networks
+        &["10.0.0.0/8", "fec0::/10"]
+    }
+
+    async fn lookup(&self, query: &str) -> Result<Option<String>> {
+        let communities = self.communities().await?;
+        Ok(find_community(&communities, query).map(|community| format_community(query, community)))
+    }
+}
+
+#[async_trait]
+impl QueryService for IcvpnRegistry {
+    fn name(&self) -> &'static str {
+        "icvpn"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-ICVPN"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "DN42 NETWORK",
+            summary: "Freifunk ICVPN community by address, ASN, name or domain",
+            example: "10.112.0.1-ICVPN",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "freifunk/icvpn-meta"
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        registry_response(self, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAMBURG: &str = "asn: 49009
+bgp:
+  hamburg01:
+    ipv4: 10.207.0.20
+    ipv6: fec0::a:cf:0:14
+domains:
+- ffhh
+- hamburg.freifunk.net
+networks:
+  ipv4:
+  - 10.112.0.0/16
+  ipv6:
+  - fd51:2bb2:fd0d::/48
+tech-c:
+- noc@hamburg.freifunk.net
+";
+
+    fn communities() -> Vec<Community> {
+        parse_communities(&[
+            ("hamburg".to_string(), HAMBURG.to_string()),
+            ("wide".to_string(), "asn: 65000\nnetworks:\n  ipv4:\n  - 10.0.0.0/8\n".to_string()),
+            ("broken".to_string(), "asn: [".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_find_community() {
+        let communities = communities();
+        assert_eq!(communities.len(), 2);
+
+        let find = |query| find_community(&communities, query).map(|c| c.name.as_str());
+        assert_eq!(find("10.112.42.1"), Some("hamburg"));
+        assert_eq!(find("10.112.0.0/24"), Some("hamburg"));
+        assert_eq!(find("10.113.0.1"), Some("wide"));
+        assert_eq!(find("10.207.0.20"), Some("hamburg"));
+        assert_eq!(find("AS49009"), Some("hamburg"));
+        assert_eq!(find("hamburg.freifunk.net"), Some("hamburg"));
+        assert_eq!(find("fd51:2bb2:fd0d::1"), Some("hamburg"));
+        assert_eq!(find("berlin"), None);
+    }
+
+    #[test]
+    fn test_format_community() {
+        let communities = communities();
+        let output = format_community("AS49009", &communities[0]);
+        assert!(output.contains("community:       hamburg\n"));
+        assert!(output.contains("network:         10.112.0.0/16\n"));
+        assert!(output.contains("bgp-peer:        hamburg01 10.207.0.20 fec0::a:cf:0:14\n"));
+    }
+}
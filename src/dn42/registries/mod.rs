@@ -0,0 +1,190 @@
+// WHOIS Server - Community Registries
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Registries of community networks next to DN42
+//!
+//! DN42 is not the only private-address overlay network with its own
+//! registry. A [`PrivateRegistry`] answers queries for one of them:
+//!
+//! - ICVPN, the Freifunk inter-city VPN (`-ICVPN`, `icvpn.rs`)
+//! - CRXN, the Community Routed eXperimental Network (`-CRXN`, `crxn.rs`)
+//!
+//! A registry is selected by its suffix, or for plain IP queries by
+//! [`detect_registry`] when the address lies in a range that registry
+//! assigns from. Registry data is fetched through the git hosting's contents
+//! API and cached in LMDB for a day.
+
+mod crxn;
+mod icvpn;
+
+pub use crxn::CrxnRegistry;
+pub use icvpn::IcvpnRegistry;
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use cidr::IpCidr;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::PRIVATE_REGISTRY_LMDB_PATH;
+use crate::core::body_limit::LimitedBody;
+use crate::storage::lmdb::LmdbStorage;
+use crate::{log_debug, log_info, log_warn};
+
+/// Refresh cached registry data when older than a day
+const MAX_AGE_SECS: u64 = 86400;
+
+/// A community network registry
+#[async_trait]
+pub trait PrivateRegistry: Send + Sync {
+    /// Display name, e.g. `ICVPN`
+    fn registry_name(&self) -> &'static str;
+
+    /// Address ranges only this registry assigns from
+    ///
+    /// Plain IP queries in these ranges are routed to the registry instead
+    /// of DN42. Registries sharing DN42's ranges return an empty list and
+    /// are only reachable by suffix.
+    fn ranges(&self) -> &'static [&'static str];
+
+    /// Look up a query, `None` when the registry has no matching object
+    async fn lookup(&self, query: &str) -> Result<Option<String>>;
+}
+
+/// Registries consulted for plain IP queries
+static REGISTRIES: [&dyn PrivateRegistry; 2] = [&IcvpnRegistry, &CrxnRegistry];
+
+/// Whether `ip` lies in one of `ranges`
+pub(crate) fn in_ranges(ip: IpAddr, ranges: &[&str]) -> bool {
+    ranges
+        .iter()
+        .filter_map(|range| range.parse::<IpCidr>().ok())
+        .any(|range| range.contains(&ip))
+}
+
+/// Registry assigning `ip`, if it lies in a range of a community registry
+pub fn detect_registry(ip: IpAddr) -> Option<&'static dyn PrivateRegistry> {
+    REGISTRIES.iter().copied().find(|registry| in_ranges(ip, registry.ranges()))
+}
+
+/// Answer an IP query from a detected community registry
+///
+/// Returns `None` when no registry covers the address or the registry has
+/// no object for it, so the caller can fall back to DN42.
+pub async fn query_detected_registry(ip: IpAddr, query: &str) -> Option<String> {
+    let registry = detect_registry(ip)?;
+    log_debug!("Address {} is in a {} range", ip, registry.registry_name());
+    match registry.lookup(query).await {
+        Ok(found) => found,
+        Err(e) => {
+            log_warn!("{} lookup failed for {}: {}", registry.registry_name(), query, e);
+            None
+        }
+    }
+}
+
+/// Response for a suffix query, with a note when nothing matched
+pub(crate) async fn registry_response(registry: &dyn PrivateRegistry, query: &str) -> Result<String> {
+    match registry.lookup(query).await? {
+        Some(response) => Ok(response),
+        None => Ok(format!("% Query: {}\n% No {} registry object found\n", query, registry.registry_name())),
+    }
+}
+
+/// A file listed by the GitHub or Gitea contents API
+#[derive(Debug, Deserialize)]
+struct ContentsEntry {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    download_url: Option<String>,
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent("whois-server/1.0")
+        .build()?)
+}
+
+/// Download every file of a repository directory
+///
+/// `listing_url` is a contents API URL (GitHub or Gitea/Forgejo); files
+/// whose names are rejected by `keep` are skipped.
+pub(crate) async fn fetch_directory(
+    listing_url: &str,
+    keep: impl Fn(&str) -> bool
+) -> Result<Vec<(String, String)>> {
+    let client = http_client()?;
+    let response = client.get(listing_url).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("registry listing {} failed: HTTP {}", listing_url, response.status()));
+    }
+    let entries: Vec<ContentsEntry> = response.limited_json().await?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        if entry.kind != "file" || !keep(&entry.name) {
+            continue;
+        }
+        let Some(url) = entry.download_url else {
+            continue;
+        };
+        match client.get(&url).send().await {
+            Ok(response) if response.status().is_success() => {
+                files.push((entry.name, response.limited_text().await?));
+            }
+            Ok(response) => log_warn!("Failed to fetch registry file {}: HTTP {}", url, response.status()),
+            Err(e) => log_warn!("Failed to fetch registry file {}: {}", url, e),
+        }
+    }
+    Ok(files)
+}
+
+/// Cached registry data with the time it was fetched
+#[derive(Serialize, Deserialize)]
+struct CachedData<T> {
+    fetched_at: u64,
+    data: T,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Registry data from the LMDB cache, refreshed with `fetch` once a day
+///
+/// Stale data is still served when the refresh fails.
+pub(crate) async fn cached<T, F, Fut>(key: &str, fetch: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let storage = LmdbStorage::new(PRIVATE_REGISTRY_LMDB_PATH)?;
+    let cached = match storage.get_json::<CachedData<T>>(key).ok().flatten() {
+        Some(cached) if now_secs().saturating_sub(cached.fetched_at) < MAX_AGE_SECS => {
+            return Ok(cached.data);
+        }
+        stale => stale,
+    };
+
+    match fetch().await {
+        Ok(data) => {
+            log_info!("Refreshed {} registry data", key);
+            let entry = CachedData { fetched_at: now_secs(), data };
+            storage.put_json(key, &entry)?;
+            Ok(entry.data)
+        }
+        Err(e) => match cached {
+            Some(cached) => {
+                log_warn!("Failed to refresh {} registry data, using cached copy: {}", key, e);
+                Ok(cached.data)
+            }
+            None => Err(e),
+        },
+    }
+}
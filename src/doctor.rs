@@ -0,0 +1,311 @@
+// WHOIS Server - Deployment Self-Test
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Deployment self-test (`whois-server doctor`)
+//!
+//! Checks what a new deployment most often gets wrong: outbound access to the
+//! upstream WHOIS servers, DoH and HTTP APIs, the command-line configuration,
+//! optional API keys, writable state directories and database schema
+//! versions. Prints a pass/warn/fail report and exits with an error if any
+//! check failed.
+
+use anyhow::{Result, anyhow};
+use futures::future::join_all;
+use std::path::Path;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+use crate::config::{
+    AFRINIC_WHOIS_PORT, AFRINIC_WHOIS_SERVER, APNIC_WHOIS_PORT, APNIC_WHOIS_SERVER, ARIN_WHOIS_PORT,
+    ARIN_WHOIS_SERVER, Cli, LACNIC_WHOIS_PORT, LACNIC_WHOIS_SERVER, RADB_WHOIS_PORT,
+    RADB_WHOIS_SERVER, RIPE_WHOIS_PORT, RIPE_WHOIS_SERVER,
+};
+use crate::services::utils::doh::DohClient;
+use crate::storage::migration::{managed_databases, stored_schema_version};
+
+/// Time allowed for each network check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Port 43 upstreams the server queries
+const WHOIS_UPSTREAMS: &[(&str, &str, u16)] = &[
+    ("IANA", "whois.iana.org", 43),
+    ("RIPE NCC", RIPE_WHOIS_SERVER, RIPE_WHOIS_PORT),
+    ("ARIN", ARIN_WHOIS_SERVER, ARIN_WHOIS_PORT),
+    ("APNIC", APNIC_WHOIS_SERVER, APNIC_WHOIS_PORT),
+    ("AFRINIC", AFRINIC_WHOIS_SERVER, AFRINIC_WHOIS_PORT),
+    ("LACNIC", LACNIC_WHOIS_SERVER, LACNIC_WHOIS_PORT),
+    ("RADB", RADB_WHOIS_SERVER, RADB_WHOIS_PORT),
+];
+
+/// HTTP APIs behind the most used suffixes
+const HTTP_APIS: &[(&str, &str)] = &[
+    ("RIPEstat", "https://stat.ripe.net/data/whats-my-ip/data.json"),
+    ("PeeringDB", "https://www.peeringdb.com/api/"),
+    ("IRR Explorer", "https://irrexplorer.nlnog.net/"),
+    ("RPKI validator", "https://rpki.akae.re/"),
+];
+
+/// Optional API keys and what they are needed for
+const API_KEYS: &[(&str, &str)] = &[
+    ("IPINFO_API_TOKEN", "IP details in -PING and -TRACE output"),
+    ("GLOBALPING_API_TOKEN", "higher Globalping limits for -PING and -TRACE"),
+    ("STEAM_API_KEY", "-STEAM queries"),
+    ("OMDB_API_KEY", "-IMDB queries"),
+    ("CURSEFORGE_API_KEY", "-CURSEFORGE queries"),
+    ("PIXIV_REFRESH_TOKEN", "-PIXIV queries"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug)]
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status, detail: detail.into() }
+    }
+}
+
+async fn check_whois_upstream(label: &str, host: &str, port: u16) -> Check {
+    let name = format!("{} ({}:{})", label, host, port);
+    match tokio::time::timeout(CHECK_TIMEOUT, TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => Check::new(name, Status::Pass, "reachable"),
+        Ok(Err(e)) => Check::new(name, Status::Fail, e.to_string()),
+        Err(_) => Check::new(name, Status::Fail, "connection timed out"),
+    }
+}
+
+async fn check_doh() -> Check {
+    let name = "Cloudflare DoH";
+    match tokio::time::timeout(CHECK_TIMEOUT, DohClient::new().query("example.com", "A")).await {
+        Ok(Ok(response)) if response.Answer.as_ref().is_some_and(|answers| !answers.is_empty()) => {
+            Check::new(name, Status::Pass, "resolved example.com")
+        }
+        Ok(Ok(_)) => Check::new(name, Status::Warn, "reachable, but returned no answer for example.com"),
+        Ok(Err(e)) => Check::new(name, Status::Fail, e.to_string()),
+        Err(_) => Check::new(name, Status::Fail, "request timed out"),
+    }
+}
+
+async fn check_http_api(client: &reqwest::Client, label: &str, url: &str) -> Check {
+    let name = format!("{} ({})", label, url);
+    match client.get(url).send().await {
+        Ok(response) if response.status().is_server_error() => {
+            Check::new(name, Status::Warn, format!("reachable, but returned {}", response.status()))
+        }
+        Ok(response) => Check::new(name, Status::Pass, format!("HTTP {}", response.status().as_u16())),
+        Err(e) if e.is_timeout() => Check::new(name, Status::Fail, "request timed out"),
+        Err(e) => Check::new(name, Status::Fail, e.to_string()),
+    }
+}
+
+/// Validate command-line settings that only fail at runtime
+fn check_config(args: &Cli) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let mut ports = vec![("WHOIS", args.port), ("web", args.web_port)];
+    if args.enable_ssh {
+        ports.push(("SSH", args.ssh_port));
+    }
+    for (i, (label, port)) in ports.iter().enumerate() {
+        if let Some((other, _)) = ports[..i].iter().find(|(_, other_port)| other_port == port) {
+            checks.push(Check::new("Listen ports", Status::Fail, format!("{} and {} both use port {}", other, label, port)));
+        }
+    }
+    if checks.is_empty() {
+        let summary: Vec<_> = ports.iter().map(|(label, port)| format!("{} {}", label, port)).collect();
+        checks.push(Check::new("Listen ports", Status::Pass, summary.join(", ")));
+    }
+
+    if args.timeout == 0
+        || args.idle_timeout == 0
+        || args.max_request_size == 0
+        || args.max_batch == 0
+        || args.max_connections == 0
+    {
+        checks.push(Check::new(
+            "Connection limits",
+            Status::Fail,
+            "--timeout, --idle-timeout, --max-request-size, --max-batch and --max-connections must be greater than 0",
+        ));
+    } else {
+        checks.push(Check::new("Connection limits", Status::Pass, format!("{} connections, {}s timeout", args.max_connections, args.timeout)));
+    }
+
+    let cache_backend = match args.cache_backend.as_str() {
+        "lmdb" => Check::new("Cache backend", Status::Pass, "lmdb"),
+        "redis" if args.redis_url.is_none() => Check::new("Cache backend", Status::Fail, "redis selected but --redis-url is missing"),
+        "redis" if !cfg!(feature = "redis") => Check::new("Cache backend", Status::Fail, "redis selected but the binary was built without --features redis"),
+        "redis" => Check::new("Cache backend", Status::Pass, "redis"),
+        other => Check::new("Cache backend", Status::Fail, format!("unknown backend '{}'", other)),
+    };
+    checks.push(cache_backend);
+
+    checks
+}
+
+/// Check that optional API keys are present and well-formed
+fn check_api_keys() -> Vec<Check> {
+    API_KEYS
+        .iter()
+        .map(|(variable, feature)| match std::env::var(variable) {
+            Ok(value) if value.trim().is_empty() => Check::new(*variable, Status::Warn, format!("set but empty; needed for {}", feature)),
+            Ok(value) if value.trim() != value => Check::new(*variable, Status::Fail, "contains leading or trailing whitespace"),
+            Ok(_) => Check::new(*variable, Status::Pass, "set"),
+            Err(_) => Check::new(*variable, Status::Warn, format!("not set; needed for {}", feature)),
+        })
+        .collect()
+}
+
+/// Check that a state directory exists (creating it if needed) and is writable
+fn check_writable_dir(dir: &Path) -> Check {
+    let name = dir.display().to_string();
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        return Check::new(name, Status::Fail, format!("cannot create: {}", e));
+    }
+
+    let probe = dir.join(".doctor-write-test");
+    match std::fs::write(&probe, b"ok") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            Check::new(name, Status::Pass, "writable")
+        }
+        Err(e) => Check::new(name, Status::Fail, format!("not writable: {}", e)),
+    }
+}
+
+fn check_state_dirs(args: &Cli) -> Vec<Check> {
+    let mut dirs = vec!["./cache".to_string(), "./patches".to_string(), "./plugins".to_string()];
+    if args.enable_ssh {
+        dirs.push(args.ssh_cache_dir.clone());
+    }
+    if args.dump_traffic {
+        dirs.push(args.dump_dir.clone());
+    }
+    dirs.iter().map(|dir| check_writable_dir(Path::new(dir))).collect()
+}
+
+/// Check database schema versions against this build
+fn check_databases(args: &Cli) -> Vec<Check> {
+    managed_databases(&args.ssh_cache_dir)
+        .into_iter()
+        .filter(|db| db.exists())
+        .map(|db| {
+            let store = match db.open() {
+                Ok(store) => store,
+                Err(e) => return Check::new(db.name, Status::Fail, format!("cannot open: {}", e)),
+            };
+            match stored_schema_version(store.as_ref()) {
+                Ok(Some(version)) if version > db.schema_version => Check::new(
+                    db.name,
+                    Status::Fail,
+                    format!("schema v{} is newer than this build (v{})", version, db.schema_version),
+                ),
+                Ok(Some(version)) if version < db.schema_version => {
+                    Check::new(db.name, Status::Warn, format!("schema v{}, will be migrated to v{} on start", version, db.schema_version))
+                }
+                Ok(Some(version)) => Check::new(db.name, Status::Pass, format!("schema v{}", version)),
+                Ok(None) => Check::new(db.name, Status::Warn, "unversioned, will be stamped on start"),
+                Err(e) => Check::new(db.name, Status::Fail, e.to_string()),
+            }
+        })
+        .collect()
+}
+
+fn status_label(status: Status, color: bool) -> String {
+    let (text, code) = match status {
+        Status::Pass => ("PASS", "32"),
+        Status::Warn => ("WARN", "33"),
+        Status::Fail => ("FAIL", "31"),
+    };
+    if color {
+        format!("\x1b[1;{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn print_section(title: &str, checks: &[Check], color: bool) {
+    println!("\n{}", title);
+    for check in checks {
+        println!("  [{}] {:<48} {}", status_label(check.status, color), check.name, check.detail);
+    }
+}
+
+/// Run the `doctor` subcommand
+pub async fn run_doctor(args: &Cli) -> Result<()> {
+    let color = atty::is(atty::Stream::Stdout);
+
+    let http_client = reqwest::Client::builder()
+        .timeout(CHECK_TIMEOUT)
+        .user_agent("whois-server/1.0")
+        .build()?;
+
+    let (whois, doh, http) = tokio::join!(
+        join_all(WHOIS_UPSTREAMS.iter().map(|(label, host, port)| check_whois_upstream(label, host, *port))),
+        check_doh(),
+        join_all(HTTP_APIS.iter().map(|(label, url)| check_http_api(&http_client, label, url)))
+    );
+
+    let sections = vec![
+        ("Upstream WHOIS servers (port 43)", whois),
+        ("DNS over HTTPS", vec![doh]),
+        ("HTTP APIs", http),
+        ("Configuration", check_config(args)),
+        ("API keys", check_api_keys()),
+        ("State directories", check_state_dirs(args)),
+        ("Databases", check_databases(args)),
+    ];
+
+    println!("whois-server {} doctor", env!("CARGO_PKG_VERSION"));
+    for (title, checks) in &sections {
+        print_section(title, checks, color);
+    }
+
+    let all: Vec<&Check> = sections.iter().flat_map(|(_, checks)| checks).collect();
+    let failed = all.iter().filter(|check| check.status == Status::Fail).count();
+    let warned = all.iter().filter(|check| check.status == Status::Warn).count();
+    println!("\n{} checks: {} passed, {} warnings, {} failed", all.len(), all.len() - failed - warned, warned, failed);
+
+    if failed > 0 {
+        return Err(anyhow!("{} check(s) failed", failed));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_config_detects_port_conflict() {
+        let args = Cli::parse_from(["whois-server", "--port", "4343", "--web-port", "4343"]);
+        let checks = check_config(&args);
+        assert!(checks.iter().any(|check| check.name == "Listen ports" && check.status == Status::Fail));
+    }
+
+    #[test]
+    fn test_config_defaults_pass() {
+        let args = Cli::parse_from(["whois-server"]);
+        assert!(check_config(&args).iter().all(|check| check.status == Status::Pass));
+    }
+
+    #[test]
+    fn test_writable_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = check_writable_dir(&dir.path().join("state"));
+        assert_eq!(check.status, Status::Pass);
+        assert!(!dir.path().join("state").join(".doctor-write-test").exists());
+    }
+}
@@ -61,6 +61,7 @@
 //!
 //! For complete documentation, see [LIBRARY_USAGE.md](https://github.com/Akaere-NetWorks/whois-server/blob/main/LIBRARY_USAGE.md)
 
+pub mod auth;
 pub mod config;
 pub mod core;
 pub mod dn42;
@@ -74,6 +75,8 @@ pub mod web;
 // Re-export commonly used types for convenience
 pub use core::query_processor::process_query;
 pub use core::{ ColorScheme, QueryType, analyze_query };
+pub use core::request_context::{ Frontend, RequestContext };
+pub use core::modifiers::OutputFormat;
 
 /// Simple API for querying WHOIS information
 ///
@@ -103,8 +106,9 @@ pub use core::{ ColorScheme, QueryType, analyze_query };
 /// }
 /// ```
 pub async fn query(input: &str) -> anyhow::Result<String> {
-    let query_type = analyze_query(input);
-    process_query(input, &query_type, None, None).await
+    let input = core::sanitize::sanitize_query(input);
+    let query_type = analyze_query(&input);
+    process_query(&input, &query_type, &RequestContext::default()).await
 }
 
 /// Query with color scheme support
@@ -118,7 +122,7 @@ pub async fn query(input: &str) -> anyhow::Result<String> {
 ///
 /// #[tokio::main]
 /// async fn main() -> anyhow::Result<()> {
-///     let result = query_with_color("example.com", Some(ColorScheme::Dark)).await?;
+///     let result = query_with_color("example.com", Some(ColorScheme::RipeDark)).await?;
 ///     println!("{}", result);
 ///     Ok(())
 /// }
@@ -127,6 +131,63 @@ pub async fn query_with_color(
     input: &str,
     color_scheme: Option<ColorScheme>
 ) -> anyhow::Result<String> {
-    let query_type = analyze_query(input);
-    process_query(input, &query_type, color_scheme, None).await
+    let input = core::sanitize::sanitize_query(input);
+    let query_type = analyze_query(&input);
+    let ctx = RequestContext::new(Frontend::Library).with_color(color_scheme);
+    process_query(&input, &query_type, &ctx).await
+}
+
+/// Query and return the response as structured JSON
+///
+/// Same as `query()` but the response is parsed into a JSON document with
+/// the raw output, its `key: value` fields and the response split into
+/// objects, as returned by the `-JSON` modifier or an `X-WHOIS-FORMAT: json`
+/// header.
+///
+/// # Examples
+///
+/// ```no_run
+/// use whois_server::query_json;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let result = query_json("AS13335").await?;
+///     println!("{}", result["objects"][0]["as-name"]);
+///     Ok(())
+/// }
+/// ```
+pub async fn query_json(input: &str) -> anyhow::Result<serde_json::Value> {
+    use anyhow::Context;
+
+    let input = core::sanitize::sanitize_query(input);
+    let query_type = analyze_query(&input);
+    let ctx = RequestContext::new(Frontend::Library).with_format(Some(OutputFormat::Json));
+    let response = process_query(&input, &query_type, &ctx).await?;
+    serde_json::from_str(&response).context("Response is not JSON (the query selects another output format)")
+}
+
+/// Run several queries one after another
+///
+/// Same as calling `query()` for each input; the results are in input order
+/// and a failed query does not stop the others.
+///
+/// # Examples
+///
+/// ```no_run
+/// use whois_server::query_batch;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for result in query_batch(&["example.com", "AS13335"]).await {
+///         println!("{}", result?);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub async fn query_batch<S: AsRef<str>>(inputs: &[S]) -> Vec<anyhow::Result<String>> {
+    let mut results = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        results.push(query(input.as_ref()).await);
+    }
+    results
 }
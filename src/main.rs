@@ -16,9 +16,12 @@
  * along with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+mod auth;
+mod client;
 mod config;
 mod core;
 mod dn42;
+mod doctor;
 mod plugins;
 mod server;
 mod services;
@@ -31,16 +34,17 @@ use clap::Parser;
 
 use core::logger::init_from_args;
 
-use config::Cli;
+use config::{Cli, Command};
 use core::{create_stats_state, get_patches_count, init_patches, save_stats_on_shutdown};
+use core::scheduler::{self, Job, Schedule};
+use core::warmup::{StartupGraph, Subsystem};
 use dn42::{
-    dn42_manager_maintenance, get_dn42_platform_info, initialize_dn42_manager, is_dn42_online_mode,
-    start_periodic_sync,
+    dn42_manager_maintenance, get_dn42_platform_info, initialize_dn42_manager, initialize_dn42_system,
+    is_dn42_online_mode, sync_dn42_registry,
 };
-use server::{create_dump_dir_if_needed, run_async_server};
-use services::pen::start_pen_periodic_update;
+use server::{ConnectionLimits, RateLimitConfig, RateLimiter, create_dump_dir_if_needed, run_async_server};
 use ssh::{SshServer, server::SshServerConfig};
-use tokio::time::{Duration, interval};
+use tokio::time::Duration;
 use web::run_web_server;
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -53,12 +57,202 @@ async fn main() -> Result<()> {
     init_from_args(args.debug, args.trace, false)
         .map_err(|e| anyhow::anyhow!("Failed to initialize logger: {}", e))?;
 
+    // Run maintenance and client subcommands instead of the server
+    match &args.command {
+        Some(Command::Db(db_args)) => {
+            return storage::migration::run_db_command(db_args, &args.ssh_cache_dir);
+        }
+        Some(Command::Query(query_args)) => {
+            return client::run_query_command(query_args).await;
+        }
+        Some(Command::Doctor) => {
+            return doctor::run_doctor(&args).await;
+        }
+        Some(Command::Usage) => {
+            return core::analytics::run_usage_command();
+        }
+        None => {}
+    }
+
+    // Migrate LMDB databases before any service opens them
+    log_init_start!("Database Migrations");
+    match storage::migration::run_startup_migrations(&args.ssh_cache_dir, args.enable_ssh) {
+        Ok(reports) => {
+            let migrated: Vec<_> = reports
+                .iter()
+                .filter(|report| report.from_version != report.to_version)
+                .map(|report| format!("{} v{} -> v{}", report.database, report.from_version, report.to_version))
+                .collect();
+            let details = if migrated.is_empty() {
+                format!("{} databases up to date", reports.len())
+            } else {
+                migrated.join(", ")
+            };
+            log_init_ok_with_details!("Database Migrations", &details);
+        }
+        Err(e) => {
+            log_init_failed!("Database Migrations", &e.to_string());
+            return Err(e);
+        }
+    }
+
     // Create statistics state
     let stats = create_stats_state().await;
 
     // Create dump directory if needed
     create_dump_dir_if_needed(args.dump_traffic, &args.dump_dir)?;
 
+    // Compile built-in regex patterns so a malformed one fails at load, not at query time
+    log_init_start!("Regex Patterns");
+    match core::regex_cache::precompile_builtin_regexes() {
+        Ok(count) => {
+            log_init_ok_with_details!("Regex Patterns", &format!("{} patterns compiled", count));
+        }
+        Err(e) => {
+            log_init_failed!("Regex Patterns", &e.to_string());
+            return Err(e);
+        }
+    }
+
+    // Load response banner templates
+    let mut banner = core::banner::BannerConfig { server_id: args.server_id.clone(), ..Default::default() };
+    if let Some(path) = &args.banner_file {
+        banner.header = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read banner template {}: {}", path.display(), e))?;
+    }
+    if let Some(path) = &args.footer_file {
+        banner.footer = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read footer template {}: {}", path.display(), e))?;
+    }
+    core::banner::init_banner(banner);
+    core::provenance::init_provenance(args.provenance);
+
+    // Load the response signing key
+    if let Some(path) = &args.signing_key {
+        log_init_start!("Response Signing");
+        match core::signing::ResponseSigner::load_or_create(path, &args.server_id) {
+            Ok(signer) => {
+                log_init_ok_with_details!("Response Signing", &format!("minisign key {}", signer.key_id()));
+                core::signing::init_signer(signer);
+            }
+            Err(e) => {
+                log_init_failed!("Response Signing", &e.to_string());
+                return Err(e);
+            }
+        }
+    }
+    core::body_limit::init_body_limit(args.max_upstream_body);
+    core::truncation::init_truncation(args.truncate_lines);
+    services::steam::init_steam_region(&args.steam_region)?;
+    services::traceroute::init_native_traceroute(args.native_traceroute);
+    services::minecraft::init_player_privacy(&args.minecraft_players)?;
+
+    // Load suffix aliases
+    if let Some(path) = &args.alias_file {
+        log_init_start!("Suffix Aliases");
+        match core::alias::load_alias_file(path) {
+            Ok(aliases) => {
+                log_init_ok_with_details!("Suffix Aliases", &format!("{} aliases", aliases.len()));
+                core::alias::init_aliases(aliases);
+            }
+            Err(e) => {
+                log_init_failed!("Suffix Aliases", &e.to_string());
+                return Err(e);
+            }
+        }
+    }
+
+    // Load frontend authentication
+    if let Some(path) = &args.auth_file {
+        log_init_start!("Authentication");
+        match auth::load_auth_file(path) {
+            Ok(config) => {
+                let frontends: Vec<&str> = [("web", config.requires_web()), ("ssh", config.requires_ssh())]
+                    .into_iter()
+                    .filter_map(|(frontend, required)| required.then_some(frontend))
+                    .collect();
+                let details = if frontends.is_empty() {
+                    "no frontend requires a login".to_string()
+                } else {
+                    format!("login required for {}", frontends.join(", "))
+                };
+                log_init_ok_with_details!("Authentication", &details);
+                auth::init_auth(config);
+            }
+            Err(e) => {
+                log_init_failed!("Authentication", &e.to_string());
+                return Err(e);
+            }
+        }
+    }
+
+    // Load local annotations
+    if let Some(path) = &args.annotations_file {
+        log_init_start!("Annotations");
+        match services::annotations::load_annotation_file(path) {
+            Ok(annotations) => {
+                log_init_ok_with_details!("Annotations", &format!("{} annotations", annotations.len()));
+                services::annotations::init_annotations(annotations);
+            }
+            Err(e) => {
+                log_init_failed!("Annotations", &e.to_string());
+                return Err(e);
+            }
+        }
+    }
+
+    // Load query macros
+    if let Some(path) = &args.macro_file {
+        log_init_start!("Query Macros");
+        match core::macros::load_macro_file(path) {
+            Ok(macros) => {
+                log_init_ok_with_details!("Query Macros", &format!("{} macros", macros.len()));
+                core::macros::init_macros(macros);
+            }
+            Err(e) => {
+                log_init_failed!("Query Macros", &e.to_string());
+                return Err(e);
+            }
+        }
+    }
+
+    // Load per-query-type limits
+    if let Some(path) = &args.query_limits_file {
+        log_init_start!("Query Limits");
+        match core::query_limits::load_query_limits_file(path) {
+            Ok(limits) => {
+                log_init_ok_with_details!("Query Limits", &format!("{} limits", limits.len()));
+                core::query_limits::init_query_limits(limits);
+            }
+            Err(e) => {
+                log_init_failed!("Query Limits", &e.to_string());
+                return Err(e);
+            }
+        }
+    }
+
+    // Register query services
+    log_init_start!("Service Registry");
+    match services::registry::builtin_registry().and_then(|mut registry| {
+        for name in &args.disable_services {
+            registry.disable(name.trim())?;
+        }
+        Ok(registry)
+    }) {
+        Ok(registry) => {
+            let details = match args.disable_services.len() {
+                0 => format!("{} services", registry.len()),
+                disabled => format!("{} services, {} disabled", registry.len(), disabled),
+            };
+            log_init_ok_with_details!("Service Registry", &details);
+            services::registry::init_service_registry(registry);
+        }
+        Err(e) => {
+            log_init_failed!("Service Registry", &e.to_string());
+            return Err(e);
+        }
+    }
+
     // Initialize patch system
     log_init_start!("Response Patches Loader");
     match init_patches("./patches") {
@@ -71,65 +265,111 @@ async fn main() -> Result<()> {
         }
     }
 
-    // Initialize DN42 manager (platform-aware)
-    log_init_start!("DN42 System");
-    if let Err(e) = initialize_dn42_manager().await {
-        log_init_failed!("DN42 System", &format!("manager initialization failed: {}", e));
-    } else {
-        let platform_info = get_dn42_platform_info().await.unwrap_or("Unknown");
-        let is_online = is_dn42_online_mode().await.unwrap_or(false);
-        log_init_ok_with_details!("DN42 System", &format!("Platform: {}, Mode: {}", platform_info, if is_online { "Online" } else { "Git" }));
-    }
-
-    // Start DN42 sync task (Git mode) or maintenance task (Online mode)
-    tokio::spawn(async move {
-        if let Ok(is_online) = is_dn42_online_mode().await {
-            if is_online {
-                log_info!("Starting DN42 online mode maintenance task (every hour)");
-                let mut maintenance_interval = interval(Duration::from_secs(3600)); // 1 hour
-                maintenance_interval.tick().await; // Skip the first tick
-
-                loop {
-                    maintenance_interval.tick().await;
-                    log_info!("Running scheduled DN42 online maintenance");
-                    if let Err(e) = dn42_manager_maintenance().await {
-                        log_error!("DN42 online maintenance failed: {}", e);
-                    }
-                }
-            } else {
-                log_info!("Starting DN42 git mode periodic sync");
-                start_periodic_sync().await;
-            }
-        } else {
-            log_error!("Failed to determine DN42 mode, falling back to git sync");
-            start_periodic_sync().await;
-        }
-    });
-
-    // Load plugins
-    log_init_start!("Plugin System");
-    let plugin_registry = match plugins::load_all_plugins().await {
-        Ok(registry) => {
+    // Clone the DN42 registry, check the PEN list and load plugins in the
+    // background; their queries answer "still starting up" until ready
+    core::warmup::expect_plugin_suffixes(plugins::declared_suffixes());
+    let mut startup = StartupGraph::new()
+        .step(Subsystem::Dn42, &[], initialize_dn42())
+        .step(Subsystem::Pen, &[], services::pen::pen_initial_update())
+        .step(Subsystem::Plugins, &[], async {
+            let registry = plugins::load_all_plugins().await?;
             let plugin_count = registry.len();
-            log_init_ok_with_details!("Plugin System", &format!("{} plugin(s) loaded", plugin_count));
 
             // Store plugin registry for query detection (Arc for thread-safe sharing)
-            let shared_registry = std::sync::Arc::new(registry);
-            core::query::set_plugin_registry(shared_registry.clone());
+            core::query::set_plugin_registry(std::sync::Arc::new(registry));
+            Ok(format!("{} plugin(s) loaded", plugin_count))
+        });
 
-            Some(shared_registry)
+    // Open cache backend (shared between instances when using redis)
+    log_init_start!("Cache Backend");
+    match storage::cache::open_cache_store(&args.cache_backend, args.redis_url.as_deref()).await {
+        Ok(store) => {
+            log_init_ok_with_details!("Cache Backend", &format!("backend: {}", store.name()));
+            storage::cache::init_cache_store(store);
         }
         Err(e) => {
-            log_init_warn!("Plugin System", &format!("continuing without plugins: {}", e));
-            None
+            log_init_warn!("Cache Backend", &format!("continuing with in-process caching only: {}", e));
         }
-    };
+    }
 
-    // Start PEN (Private Enterprise Numbers) periodic update task
-    tokio::spawn(async move {
-        log_task_start!("PEN Periodic Update Service");
-        start_pen_periodic_update().await;
-    });
+    // Start popular query prefetch task if enabled
+    if args.prefetch {
+        core::prefetch::init_prefetch(core::prefetch::PrefetchConfig {
+            top_n: args.prefetch_top,
+            interval: Duration::from_secs(args.prefetch_interval.max(1)),
+        });
+        // Popular queries include DN42 and plugin queries
+        let top_n = args.prefetch_top;
+        startup = startup.step(Subsystem::Prefetch, &[Subsystem::Dn42, Subsystem::Plugins], async move {
+            if let Some(job) = core::prefetch::prefetch_job() {
+                scheduler::schedule(job);
+            }
+            Ok(format!("top {} queries", top_n))
+        });
+    }
+    startup.spawn();
+
+    // Mirror a sample of queries to a canary instance if configured
+    if let Some(shadow_server) = &args.shadow_server {
+        server::shadow::init_shadow(server::shadow::ShadowConfig {
+            server: shadow_server.clone(),
+            percent: args.shadow_percent.clamp(0.0, 100.0),
+            timeout: Duration::from_secs(args.timeout),
+        });
+    }
+
+    // Start ASN monitoring if networks are registered
+    if let Some(path) = &args.monitor_file {
+        log_init_start!("ASN Monitoring");
+        match services::monitor::load_monitor_file(path).and_then(|config| {
+            let networks = config.networks.len();
+            Ok((networks, services::monitor::monitor_job(config)?))
+        }) {
+            Ok((networks, job)) => {
+                log_init_ok_with_details!("ASN Monitoring", &format!("{} network(s)", networks));
+                scheduler::schedule(job);
+            }
+            Err(e) => {
+                log_init_failed!("ASN Monitoring", &e.to_string());
+                return Err(e);
+            }
+        }
+    }
+
+    // Start scheduled digests if configured
+    if let Some(path) = &args.digest_file {
+        log_init_start!("Scheduled Digests");
+        match services::digest::load_digest_file(path) {
+            Ok(digests) => {
+                log_init_ok_with_details!("Scheduled Digests", &format!("{} digest(s)", digests.len()));
+                services::digest::digest_jobs(digests).into_iter().for_each(scheduler::schedule);
+            }
+            Err(e) => {
+                log_init_failed!("Scheduled Digests", &e.to_string());
+                return Err(e);
+            }
+        }
+    }
+
+    // Periodic jobs: usage counts, Public Suffix List, ICANN registrar list,
+    // TLD list / RDAP bootstrap and PEN (Private Enterprise Numbers) refreshes
+    core::analytics::usage_jobs().into_iter().for_each(scheduler::schedule);
+    scheduler::schedule(core::public_suffix::psl_refresh_job());
+    scheduler::schedule(services::registrar::registrar_update_job());
+    scheduler::schedule(services::tld_bootstrap::tld_bootstrap_job());
+    scheduler::schedule(services::pen::pen_update_job());
+
+    // Measure disk usage and keep caches, the DN42 checkout, SSH exports and
+    // traffic dumps under their caps
+    let mut disk_caps = core::janitor::DiskCaps::from_megabytes(
+        args.max_lmdb_size,
+        args.max_dn42_size,
+        args.max_ssh_cache_size,
+        args.max_dump_size,
+    );
+    disk_caps.ssh_cache_dir = args.ssh_cache_dir.clone().into();
+    disk_caps.dump_dir = args.dump_traffic.then(|| args.dump_dir.clone().into());
+    scheduler::schedule(core::janitor::janitor_job(disk_caps));
 
     // Start web server
     let web_stats = stats.clone();
@@ -147,6 +387,8 @@ async fn main() -> Result<()> {
             listen_addr: args.host.clone(),
             port: args.ssh_port,
             cache_dir: args.ssh_cache_dir.clone(),
+            export_quota_bytes: args.ssh_export_quota * 1024 * 1024,
+            export_ttl: std::time::Duration::from_secs(args.ssh_export_ttl * 3600),
         };
 
         tokio::spawn(async move {
@@ -174,13 +416,44 @@ async fn main() -> Result<()> {
     let addr = format!("{}:{}", args.host, args.port);
     log_task_start!(&format!("WHOIS Server on {}", addr));
 
+    // Connection limits (slowloris protection)
+    let limits = ConnectionLimits {
+        read_timeout: Duration::from_secs(args.timeout),
+        idle_timeout: Duration::from_secs(args.idle_timeout),
+        max_request_size: args.max_request_size,
+        max_batch: args.max_batch,
+        write_timeout: Duration::from_secs(args.timeout),
+        keepalive_timeout: Duration::from_secs(args.keepalive_timeout),
+    };
+
+    // Per-client connection rate limit
+    let rate_limiter = std::sync::Arc::new(RateLimiter::new(RateLimitConfig {
+        per_minute: args.rate_limit,
+        burst: args.rate_limit_burst,
+        ipv6_prefix: args.rate_limit_ipv6_prefix,
+    }));
+    if rate_limiter.is_enabled() {
+        log_info!(
+            "Rate limit: {} connections/min per client (burst {}, IPv6 per /{})",
+            args.rate_limit,
+            args.rate_limit_burst,
+            args.rate_limit_ipv6_prefix
+        );
+    }
+    if args.tarpit {
+        server::tarpit::init_tarpit(server::tarpit::TarpitConfig {
+            line_delay: Duration::from_millis(args.tarpit_delay),
+            log_file: args.tarpit_log.clone(),
+        });
+    }
+
     // Start async server
     let result = run_async_server(
         &addr,
         args.max_connections,
-        args.timeout,
-        args.dump_traffic,
-        &args.dump_dir,
+        limits,
+        rate_limiter,
+        args.dump_traffic.then_some(args.dump_dir.as_str()),
         stats.clone(),
         args.enable_color,
     )
@@ -191,7 +464,7 @@ async fn main() -> Result<()> {
     save_stats_on_shutdown(&stats).await;
 
     // Cleanup plugins
-    if let Some(registry) = plugin_registry {
+    if let Some(registry) = core::query::get_plugin_registry() {
         log_info!("Cleaning up plugins...");
         for suffix in registry.get_all_suffixes() {
             if let Some(plugin) = registry.get_plugin(&suffix) {
@@ -202,3 +475,31 @@ async fn main() -> Result<()> {
 
     result
 }
+
+/// Startup step: the DN42 manager and, in Git mode, the registry clone
+async fn initialize_dn42() -> Result<String> {
+    let initialized = initialize_dn42_manager().await;
+
+    // Hourly registry sync (Git mode) or cache maintenance (Online mode);
+    // the mode is unknown when the manager failed, which falls back to Git sync
+    scheduler::schedule(
+        Job::new("dn42-sync", Schedule::Every(Duration::from_secs(3600)), || async {
+            if is_dn42_online_mode().await.unwrap_or(false) {
+                dn42_manager_maintenance().await
+            } else {
+                sync_dn42_registry().await
+            }
+        })
+        .with_jitter(Duration::from_secs(300)),
+    );
+
+    if let Err(e) = initialized {
+        return Err(anyhow::anyhow!("manager initialization failed: {}", e));
+    }
+    let platform_info = get_dn42_platform_info().await.unwrap_or("Unknown");
+    let is_online = is_dn42_online_mode().await.unwrap_or(false);
+    if !is_online {
+        initialize_dn42_system().await?;
+    }
+    Ok(format!("Platform: {}, Mode: {}", platform_info, if is_online { "Online" } else { "Git" }))
+}
@@ -1,14 +1,96 @@
 //! Plugin-provided APIs
 //!
-//! This module provides safe APIs that plugins can use:
+//! This module provides safe APIs that plugins can use (registered as Lua
+//! globals here, and as `whois` host functions by the WASM runtime):
 //! - HTTP client (with domain whitelist enforcement and custom User-Agent)
 //! - Cache access (shared LMDB cache)
 //! - Environment variable access
 //! - Logging functions
 
+use crate::core::body_limit::LimitedBody;
 use crate::plugins::registry::PluginPermissions;
 use mlua::Lua;
+use once_cell::sync::Lazy;
 use std::collections::{ HashMap, HashSet };
+use std::sync::Mutex;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+/// Lowercased domain whitelist of a plugin
+pub fn http_whitelist(permissions: &PluginPermissions) -> HashSet<String> {
+    permissions.allowed_domains
+        .iter()
+        .map(|d| d.to_lowercase())
+        .collect()
+}
+
+/// User-Agent sent with a plugin's HTTP requests
+pub fn http_user_agent(permissions: &PluginPermissions) -> String {
+    permissions.user_agent
+        .clone()
+        .unwrap_or_else(|| { format!("whois-server-plugin/{}", env!("CARGO_PKG_VERSION")) })
+}
+
+/// Fetch a URL on behalf of a plugin
+///
+/// Only whitelisted domains can be fetched, and an empty whitelist allows
+/// none. Redirects are followed only to whitelisted domains. Returns the
+/// JSON string `{"status": ..., "body": ...}` handed to plugins.
+pub async fn http_get_allowed(
+    url: &str,
+    whitelist: &HashSet<String>,
+    user_agent: &str
+) -> anyhow::Result<String> {
+    // Extract domain from URL
+    let domain = extract_domain(url)?;
+
+    // Check against whitelist
+    if !whitelist.contains(&domain.to_lowercase()) {
+        return Err(anyhow::anyhow!("Domain '{}' is not in the allowed domains whitelist", domain));
+    }
+
+    // Make HTTP request with custom User-Agent
+    let redirect_whitelist = whitelist.clone();
+    let client = reqwest::Client
+        ::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .redirect(
+            reqwest::redirect::Policy::custom(move |attempt| {
+                let allowed = attempt
+                    .url()
+                    .host_str()
+                    .is_some_and(|host| redirect_whitelist.contains(&host.to_lowercase()));
+                if attempt.previous().len() >= 5 {
+                    attempt.error("too many redirects")
+                } else if allowed {
+                    attempt.follow()
+                } else {
+                    attempt.error("redirect to a domain outside the allowed domains whitelist")
+                }
+            })
+        )
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to create HTTP client: {}", e))?;
+
+    let response = client
+        .get(url)
+        .header("User-Agent", user_agent)
+        .send().await
+        .map_err(|e| anyhow::anyhow!("HTTP request failed: {}", e))?;
+
+    let status = response.status().as_u16();
+    let body = response
+        .limited_text().await
+        .map_err(|e| anyhow::anyhow!("Failed to read response body: {}", e))?;
+
+    // Return as JSON string
+    let result =
+        serde_json::json!({
+        "status": status,
+        "body": body
+    });
+
+    Ok(result.to_string())
+}
 
 /// Register HTTP client API with domain whitelist enforcement
 pub fn register_http_api(lua: &Lua, permissions: &PluginPermissions) -> mlua::Result<()> {
@@ -18,58 +100,17 @@ pub fn register_http_api(lua: &Lua, permissions: &PluginPermissions) -> mlua::Re
     }
 
     // Build whitelist set for fast lookup
-    let whitelist: HashSet<String> = permissions.allowed_domains
-        .iter()
-        .map(|d| d.to_lowercase())
-        .collect();
+    let whitelist = http_whitelist(permissions);
 
     // Get custom User-Agent or use default
-    let user_agent = permissions.user_agent
-        .clone()
-        .unwrap_or_else(|| { format!("whois-server-plugin/{}", env!("CARGO_PKG_VERSION")) });
+    let user_agent = http_user_agent(permissions);
 
     let http_get = lua.create_async_function(move |_lua, url: String| {
         let whitelist = whitelist.clone();
         let user_agent = user_agent.clone();
         async move {
-            // Extract domain from URL
-            let domain = extract_domain(&url)?;
-
-            // Check against whitelist
-            if !whitelist.is_empty() && !whitelist.contains(&domain.to_lowercase()) {
-                return Err(
-                    mlua::Error::runtime(
-                        format!("Domain '{}' is not in the allowed domains whitelist", domain)
-                    )
-                );
-            }
-
-            // Make HTTP request with custom User-Agent
-            let client = reqwest::Client
-                ::builder()
-                .timeout(std::time::Duration::from_secs(5))
-                .build()
-                .map_err(|e| mlua::Error::runtime(format!("Failed to create HTTP client: {}", e)))?;
-
-            let response = client
-                .get(&url)
-                .header("User-Agent", &user_agent)
-                .send().await
-                .map_err(|e| mlua::Error::runtime(format!("HTTP request failed: {}", e)))?;
-
-            let status = response.status().as_u16();
-            let body = response
-                .text().await
-                .map_err(|e| mlua::Error::runtime(format!("Failed to read response body: {}", e)))?;
-
-            // Return as JSON string
-            let result =
-                serde_json::json!({
-                "status": status,
-                "body": body
-            });
-
-            Ok(result.to_string())
+            http_get_allowed(&url, &whitelist, &user_agent).await
+                .map_err(|e| mlua::Error::runtime(e.to_string()))
         }
     })?;
 
@@ -77,6 +118,33 @@ pub fn register_http_api(lua: &Lua, permissions: &PluginPermissions) -> mlua::Re
     Ok(())
 }
 
+// Simple in-memory cache shared by all plugins
+// TODO: Integrate with LMDB storage
+static CACHE: Lazy<Mutex<HashMap<String, (String, u64)>>> = Lazy::new(||
+    Mutex::new(HashMap::new())
+);
+
+/// Read a value from the plugin cache
+pub fn cache_get(key: &str) -> Option<String> {
+    let cache = CACHE.lock().unwrap();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    match cache.get(key) {
+        Some((value, expiry)) if *expiry > now => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// Write a value to the plugin cache (default TTL: 1 hour)
+pub fn cache_set(key: String, value: String, ttl: Option<u32>) {
+    let ttl = ttl.unwrap_or(3600) as u64; // Default 1 hour
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let expiry = now + ttl;
+
+    let mut cache = CACHE.lock().unwrap();
+    cache.insert(key, (value, expiry));
+}
+
 /// Register cache access API
 ///
 /// Plugins can read/write to the shared LMDB cache used by the main server.
@@ -85,30 +153,9 @@ pub fn register_http_api(lua: &Lua, permissions: &PluginPermissions) -> mlua::Re
 /// Note: For now, cache operations are simplified and stored in-memory.
 /// Future implementation will integrate with LMDB.
 pub fn register_cache_api(lua: &Lua, permissions: &PluginPermissions) -> mlua::Result<()> {
-    use std::sync::Mutex;
-    use std::collections::HashMap;
-    use std::time::{ SystemTime, UNIX_EPOCH };
-    use once_cell::sync::Lazy;
-
-    // Simple in-memory cache for plugins
-    // TODO: Integrate with LMDB storage
-    static CACHE: Lazy<Mutex<HashMap<String, (String, u64)>>> = Lazy::new(||
-        Mutex::new(HashMap::new())
-    );
-
     // Register cache_get if read permission is granted
     if permissions.cache_read {
-        let cache_get = lua.create_function(move |_lua, key: String| {
-            let cache = CACHE.lock().unwrap();
-            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-
-            if let Some((value, expiry)) = cache.get(&key) {
-                if *expiry > now {
-                    return Ok(Some(value.clone()));
-                }
-            }
-            Ok(None)
-        })?;
+        let cache_get = lua.create_function(move |_lua, key: String| Ok(cache_get(&key)))?;
 
         lua.globals().set("cache_get", cache_get)?;
     }
@@ -117,12 +164,7 @@ pub fn register_cache_api(lua: &Lua, permissions: &PluginPermissions) -> mlua::R
     if permissions.cache_write {
         let cache_set = lua.create_function(
             move |_lua, (key, value, ttl): (String, String, Option<u32>)| {
-                let ttl = ttl.unwrap_or(3600) as u64; // Default 1 hour
-                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-                let expiry = now + ttl;
-
-                let mut cache = CACHE.lock().unwrap();
-                cache.insert(key, (value, expiry));
+                cache_set(key, value, ttl);
                 Ok(())
             }
         )?;
@@ -3,12 +3,14 @@
 //! This module handles discovering and loading plugins from the plugins directory.
 
 use crate::plugins::env::{get_plugin_env_vars, load_env_file};
-use crate::plugins::registry::{LoadedPlugin, PluginMetadata, PluginRegistry};
-use crate::plugins::sandbox::create_secure_lua_state;
+use crate::plugins::registry::{LoadedPlugin, PluginEngine, PluginMetadata, PluginRegistry, PluginRuntime};
+use crate::plugins::sandbox::{create_secure_lua_state, set_deadline};
+use crate::plugins::wasm::WasmPlugin;
 use anyhow::Context;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 
 /// Load all plugins from the plugins directory
 ///
@@ -76,27 +78,37 @@ pub async fn load_all_plugins() -> anyhow::Result<PluginRegistry> {
     Ok(registry)
 }
 
+/// Suffixes declared by the enabled plugins, without loading them
+///
+/// Used at startup to recognize plugin queries while plugins are loading.
+pub fn declared_suffixes() -> Vec<String> {
+    let Ok(entries) = fs::read_dir("plugins") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| read_metadata(&entry.path().join("meta.toml")).ok())
+        .filter(|metadata| metadata.plugin.enabled)
+        .map(|metadata| metadata.plugin.suffix)
+        .collect()
+}
+
 /// Load a plugin from a directory
 ///
 /// The directory must contain:
 /// - `meta.toml` - Plugin metadata
-/// - `init.lua` - Plugin code
+/// - `init.lua` - Plugin code, or `plugin.wasm` for `runtime = "wasm"`
 async fn load_plugin_from_dir(
     dir: &Path,
     all_env_vars: &HashMap<String, String>,
 ) -> anyhow::Result<LoadedPlugin> {
     let meta_path = dir.join("meta.toml");
-    let init_path = dir.join("init.lua");
 
     // Check that required files exist
     if !meta_path.exists() {
         return Err(anyhow::anyhow!("meta.toml not found"));
     }
 
-    if !init_path.exists() {
-        return Err(anyhow::anyhow!("init.lua not found"));
-    }
-
     // Read metadata
     let metadata = read_metadata(&meta_path)?;
 
@@ -125,15 +137,47 @@ async fn load_plugin_from_dir(
         );
     }
 
+    let engine = match metadata.plugin.runtime {
+        PluginRuntime::Lua => PluginEngine::Lua(load_lua_plugin(dir, &metadata, &plugin_env_vars)?),
+        PluginRuntime::Wasm => {
+            let wasm_path = dir.join("plugin.wasm");
+            if !wasm_path.exists() {
+                return Err(anyhow::anyhow!("plugin.wasm not found"));
+            }
+            PluginEngine::Wasm(WasmPlugin::load(&wasm_path, &metadata, &plugin_env_vars).await?)
+        }
+    };
+
+    crate::log_info!(
+        "Loaded plugin '{}' v{} (suffix: {}, runtime: {:?})",
+        metadata.plugin.name, metadata.plugin.version, suffix, metadata.plugin.runtime
+    );
+
+    Ok(LoadedPlugin { metadata, engine })
+}
+
+/// Load and run a Lua plugin's `init.lua` in a sandboxed Lua state
+fn load_lua_plugin(
+    dir: &Path,
+    metadata: &PluginMetadata,
+    plugin_env_vars: &HashMap<String, String>,
+) -> anyhow::Result<mlua::Lua> {
+    let init_path = dir.join("init.lua");
+
+    if !init_path.exists() {
+        return Err(anyhow::anyhow!("init.lua not found"));
+    }
+
     // Create secure Lua state with environment variables
-    let lua = create_secure_lua_state(&metadata, &plugin_env_vars)
+    let lua = create_secure_lua_state(metadata, plugin_env_vars)
         .map_err(|e| anyhow::anyhow!("Failed to create Lua state: {}", e))?;
 
     // Load plugin code
     let code = fs::read_to_string(&init_path)
         .context("Failed to read init.lua")?;
 
-    // Execute the plugin code
+    // Execute the plugin code, stopped like queries once its timeout passes
+    set_deadline(&lua, Duration::from_secs(metadata.plugin.timeout));
     lua.load(&code)
         .exec()
         .map_err(|e| anyhow::anyhow!("Failed to execute plugin code: {}", e))?;
@@ -148,6 +192,7 @@ async fn load_plugin_from_dir(
 
     // Call init function if it exists
     if let Ok(init) = lua.globals().get::<mlua::Function>("init") {
+        set_deadline(&lua, Duration::from_secs(metadata.plugin.timeout));
         if let Err(e) = init.call::<()>(()) {
             crate::log_warn!(
                 "Plugin {} init function failed: {}",
@@ -156,12 +201,7 @@ async fn load_plugin_from_dir(
         }
     }
 
-    crate::log_info!(
-        "Loaded plugin '{}' v{} (suffix: {})",
-        metadata.plugin.name, metadata.plugin.version, suffix
-    );
-
-    Ok(LoadedPlugin { metadata, lua })
+    Ok(lua)
 }
 
 /// Read plugin metadata from meta.toml
@@ -185,6 +225,8 @@ fn read_metadata(path: &Path) -> anyhow::Result<PluginMetadata> {
         return Err(anyhow::anyhow!("Plugin version cannot be empty"));
     }
 
+    metadata.validate().context("Invalid meta.toml")?;
+
     Ok(metadata)
 }
 
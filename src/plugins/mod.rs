@@ -1,19 +1,20 @@
 //! Plugin system for custom query suffix handlers
 //!
-//! This module provides a Lua-based plugin system that allows users to register
-//! custom query suffixes (e.g., `-WEATHER`, `-CUSTOM`) by placing plugins in the
-//! `plugins/` directory.
+//! This module provides a plugin system that allows users to register custom
+//! query suffixes (e.g., `-WEATHER`, `-CUSTOM`) by placing plugins in the
+//! `plugins/` directory. Plugins are Lua scripts or WebAssembly modules.
 //!
 //! # Plugin Structure
 //!
 //! Each plugin is a directory containing:
-//! - `meta.toml` - Plugin metadata (name, version, suffix, permissions)
-//! - `init.lua` - Lua plugin code
+//! - `meta.toml` - Plugin metadata (name, version, suffix, runtime, permissions)
+//! - `init.lua` - Lua plugin code, or
+//! - `plugin.wasm` - WebAssembly module, with `runtime = "wasm"` (see [`wasm`])
 //!
 //! # Plugin API
 //!
 //! Plugins must implement a `handle_query(query: string) -> string` function.
-//! Optional functions: `init()` and `cleanup()`.
+//! Optional functions: `init()` and `cleanup()` (Lua only).
 //!
 //! # Security
 //!
@@ -28,6 +29,7 @@ pub mod env;
 pub mod loader;
 pub mod registry;
 pub mod sandbox;
+pub mod wasm;
 
-pub use loader::load_all_plugins;
+pub use loader::{declared_suffixes, load_all_plugins};
 pub use registry::{LoadedPlugin, PluginRegistry};
@@ -2,20 +2,36 @@
 //!
 //! This module provides the central registry that stores all loaded plugins
 //! and allows querying them by their registered suffixes.
+//!
+//! `meta.toml` is the plugin's permission manifest. Everything is denied
+//! unless granted there, unknown keys are rejected, and
+//! [`PluginMetadata::validate`] checks the grants before a plugin is loaded.
 
+use crate::plugins::sandbox::set_deadline;
+use crate::plugins::wasm::WasmPlugin;
 use mlua::Lua;
 use serde::{ Deserialize, Serialize };
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Longest execution timeout a plugin may ask for, in seconds
+pub const MAX_PLUGIN_TIMEOUT_SECS: u64 = 30;
+
+/// Most memory a plugin may ask for, in MB
+pub const MAX_PLUGIN_MEMORY_MB: u64 = 64;
 
 /// Plugin metadata parsed from meta.toml
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PluginMetadata {
     pub plugin: PluginInfo,
     pub permissions: PluginPermissions,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PluginInfo {
     pub name: String,
     pub version: String,
@@ -29,6 +45,20 @@ pub struct PluginInfo {
     /// Execution timeout in seconds (default: 5)
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+    /// Runtime executing the plugin (default: lua)
+    #[serde(default)]
+    pub runtime: PluginRuntime,
+}
+
+/// Runtime a plugin is written for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginRuntime {
+    /// Lua script in `init.lua`
+    #[default]
+    Lua,
+    /// WebAssembly module in `plugin.wasm`
+    Wasm,
 }
 
 fn default_enabled() -> bool {
@@ -39,7 +69,12 @@ fn default_timeout() -> u64 {
     5
 }
 
+fn default_max_memory_mb() -> u64 {
+    10
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PluginPermissions {
     #[serde(default)]
     pub network: bool,
@@ -55,6 +90,9 @@ pub struct PluginPermissions {
     /// Environment variables to inject into the plugin (optional)
     #[serde(default)]
     pub env_vars: Vec<String>,
+    /// Memory limit of the Lua state or WebAssembly instance in MB (default: 10)
+    #[serde(default = "default_max_memory_mb")]
+    pub max_memory_mb: u64,
 }
 
 impl Default for PluginPermissions {
@@ -66,22 +104,98 @@ impl Default for PluginPermissions {
             cache_write: false,
             user_agent: None,
             env_vars: Vec::new(),
+            max_memory_mb: default_max_memory_mb(),
         }
     }
 }
 
-/// A loaded plugin with its Lua state and metadata
+impl PluginPermissions {
+    /// Memory limit in bytes
+    pub fn memory_limit(&self) -> usize {
+        (self.max_memory_mb as usize) * 1_000_000
+    }
+}
+
+/// Whether a host name may be whitelisted: a public DNS name, not an
+/// address or a local name
+fn is_allowed_domain(domain: &str) -> bool {
+    let domain = domain.to_lowercase();
+    domain.contains('.') &&
+        domain.parse::<IpAddr>().is_err() &&
+        !domain.starts_with('.') &&
+        !domain.ends_with('.') &&
+        !domain.ends_with(".localhost") &&
+        !domain.ends_with(".local") &&
+        domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+}
+
+impl PluginMetadata {
+    /// Check the manifest's limits and grants
+    ///
+    /// Rejects limits outside the allowed range, network access without a
+    /// domain whitelist (or a whitelist without network access), whitelisted
+    /// addresses and local names, and malformed environment variable names.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let info = &self.plugin;
+        let permissions = &self.permissions;
+
+        if info.timeout == 0 || info.timeout > MAX_PLUGIN_TIMEOUT_SECS {
+            return Err(anyhow::anyhow!("timeout must be 1-{} seconds, got {}", MAX_PLUGIN_TIMEOUT_SECS, info.timeout));
+        }
+        if permissions.max_memory_mb == 0 || permissions.max_memory_mb > MAX_PLUGIN_MEMORY_MB {
+            return Err(
+                anyhow::anyhow!("max_memory_mb must be 1-{}, got {}", MAX_PLUGIN_MEMORY_MB, permissions.max_memory_mb)
+            );
+        }
+
+        if permissions.network && permissions.allowed_domains.is_empty() {
+            return Err(anyhow::anyhow!("network permission requires at least one entry in allowed_domains"));
+        }
+        if !permissions.network && !permissions.allowed_domains.is_empty() {
+            return Err(anyhow::anyhow!("allowed_domains is set but the network permission is not granted"));
+        }
+        if let Some(domain) = permissions.allowed_domains.iter().find(|d| !is_allowed_domain(d)) {
+            return Err(anyhow::anyhow!("allowed_domains entry '{}' is not a public domain name", domain));
+        }
+
+        let valid_env_var = |name: &String| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        };
+        if let Some(name) = permissions.env_vars.iter().find(|name| !valid_env_var(name)) {
+            return Err(anyhow::anyhow!("env_vars entry '{}' is not a valid variable name", name));
+        }
+
+        Ok(())
+    }
+}
+
+/// Loaded code of a plugin
+pub enum PluginEngine {
+    /// Lua state for this plugin
+    Lua(Lua),
+    /// Compiled WebAssembly module, instantiated per query
+    Wasm(WasmPlugin),
+}
+
+/// A loaded plugin with its code and metadata
 pub struct LoadedPlugin {
     /// Plugin metadata
     pub metadata: PluginMetadata,
-    /// Lua state for this plugin
-    pub lua: Lua,
+    /// Lua state or WebAssembly module for this plugin
+    pub engine: PluginEngine,
 }
 
 impl LoadedPlugin {
     /// Call the plugin's cleanup function if it exists
+    ///
+    /// WebAssembly plugins keep no state between queries, so only Lua
+    /// plugins have one.
     pub fn call_cleanup(&self) {
-        if let Ok(cleanup) = self.lua.globals().get::<mlua::Function>("cleanup") {
+        let PluginEngine::Lua(lua) = &self.engine else {
+            return;
+        };
+        if let Ok(cleanup) = lua.globals().get::<mlua::Function>("cleanup") {
+            set_deadline(lua, Duration::from_secs(self.metadata.plugin.timeout));
             if let Err(e) = cleanup.call::<()>(()) {
                 eprintln!("Plugin {} cleanup error: {}", self.metadata.plugin.name, e);
             }
@@ -193,6 +307,7 @@ mod tests {
                 description: None,
                 enabled: true,
                 timeout: 5, // default timeout
+                runtime: PluginRuntime::Lua,
             },
             permissions: PluginPermissions::default(),
         };
@@ -201,7 +316,7 @@ mod tests {
         metadata.plugin.suffix = "-test".to_string();
         let plugin = LoadedPlugin {
             metadata: metadata.clone(),
-            lua,
+            engine: PluginEngine::Lua(lua),
         };
 
         registry.register(plugin).unwrap();
@@ -211,4 +326,30 @@ mod tests {
         assert!(registry.get_plugin("-test").is_some());
         assert!(registry.get_plugin("-Test").is_some());
     }
+
+    #[test]
+    fn test_manifest_denies_by_default() {
+        let manifest = |permissions: &str| -> anyhow::Result<PluginMetadata> {
+            let metadata: PluginMetadata = toml::from_str(
+                &format!("[plugin]\nname = \"t\"\nversion = \"1\"\nsuffix = \"-T\"\n\n[permissions]\n{}", permissions)
+            )?;
+            metadata.validate()?;
+            Ok(metadata)
+        };
+
+        let metadata = manifest("").unwrap();
+        assert!(!metadata.permissions.network && !metadata.permissions.cache_read && !metadata.permissions.cache_write);
+        assert_eq!(metadata.permissions.memory_limit(), 10_000_000);
+        assert!(manifest("network = true\nallowed_domains = [\"wttr.in\"]\nenv_vars = [\"API_KEY\"]").is_ok());
+
+        // Unknown permissions are not silently ignored
+        assert!(manifest("filesystem = true").is_err());
+        assert!(manifest("network = true").is_err());
+        assert!(manifest("allowed_domains = [\"wttr.in\"]").is_err());
+        assert!(manifest("network = true\nallowed_domains = [\"127.0.0.1\"]").is_err());
+        assert!(manifest("network = true\nallowed_domains = [\"metadata.google.internal.local\"]").is_err());
+        assert!(manifest("network = true\nallowed_domains = [\"*.example.com\"]").is_err());
+        assert!(manifest("max_memory_mb = 1024").is_err());
+        assert!(manifest("env_vars = [\"PATH=/bin\"]").is_err());
+    }
 }
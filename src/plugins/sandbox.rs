@@ -2,6 +2,12 @@
 //!
 //! This module creates a secure Lua environment that restricts dangerous operations
 //! while providing safe APIs for plugins.
+//!
+//! The sandbox is the security boundary between plugins and the server, so
+//! the tests below try known escapes from Lua sandboxes: reaching the removed
+//! libraries through `package.loaded` or `require`, loading bytecode with
+//! `load` or `string.dump`, endless loops (also behind `pcall` or in a
+//! coroutine), memory exhaustion and HTTP requests outside the whitelist.
 
 use crate::plugins::api::{
     register_cache_api,
@@ -10,41 +16,80 @@ use crate::plugins::api::{
     register_logging_api,
 };
 use crate::plugins::registry::PluginMetadata;
-use mlua::{ Table, Value };
+use mlua::{ HookTriggers, Lua, LuaOptions, StdLib, Value, VmState };
 use std::collections::HashMap;
+use std::time::{ Duration, Instant };
+
+/// Instructions between deadline checks of running plugin code
+const DEADLINE_CHECK_INSTRUCTIONS: u32 = 10_000;
+
+/// Error raised in plugin code that runs past its deadline
+const DEADLINE_MESSAGE: &str = "plugin exceeded its time limit";
+
+/// Wrappers that re-raise the deadline error after `pcall`, `xpcall` and
+/// `coroutine.resume`, so plugin code cannot catch it and keep running
+const PROTECTED_CALL_WRAPPERS: &str = r#"
+local expired, message = ...
+local pcall, xpcall, resume, error = pcall, xpcall, coroutine.resume, error
+local pack, unpack = table.pack, table.unpack
+local function check(results)
+    if expired() then
+        error(message, 0)
+    end
+    return unpack(results, 1, results.n)
+end
+_G.pcall = function(...) return check(pack(pcall(...))) end
+_G.xpcall = function(...) return check(pack(xpcall(...))) end
+coroutine.resume = function(...) return check(pack(resume(...))) end
+"#;
+
+/// Standard library globals that are not loaded or are removed
+const REMOVED_GLOBALS: &[&str] = &["load", "loadfile", "dofile", "print", "warn", "collectgarbage"];
+
+/// Point in time after which running plugin code is stopped
+struct Deadline(Instant);
+
+/// Allow the plugin code about to run in `lua` at most `timeout`
+///
+/// Async APIs are bounded by the caller's timeout; this stops code that
+/// keeps the CPU busy, such as an endless loop, which never yields to it.
+pub fn set_deadline(lua: &Lua, timeout: Duration) {
+    lua.set_app_data(Deadline(Instant::now() + timeout));
+}
+
+fn deadline_passed(lua: &Lua) -> bool {
+    lua.app_data_ref::<Deadline>().is_some_and(|deadline| Instant::now() >= deadline.0)
+}
 
 /// Create a secure Lua state for plugin execution
 ///
 /// This function:
-/// - Removes dangerous libraries (os, io, load, etc.)
-/// - Restricts package loading
-/// - Sets memory limits
+/// - Loads only the coroutine, table, string, utf8 and math libraries, so
+///   `os`, `io`, `debug` and `package`/`require` do not exist at all
+/// - Removes code loading (`load`, `loadfile`, `dofile`, `string.dump`)
+/// - Sets the memory limit from the plugin's manifest
+/// - Stops running code at the deadline set with [`set_deadline`]
 /// - Registers safe APIs (HTTP, cache, logging, environment variables)
 pub fn create_secure_lua_state(
     metadata: &PluginMetadata,
     env_vars: &HashMap<String, String>
 ) -> mlua::Result<mlua::Lua> {
-    let lua = mlua::Lua::new();
-
-    // Remove dangerous libraries
-    lua.globals().set("os", Value::Nil)?;
-    lua.globals().set("io", Value::Nil)?;
-    lua.globals().set("load", Value::Nil)?;
-    lua.globals().set("loadfile", Value::Nil)?;
-    lua.globals().set("dofile", Value::Nil)?;
-    lua.globals().set("print", Value::Nil)?;
-
-    // Restrict package module to prevent loading external libraries
-    if let Ok(package) = lua.globals().get::<Table>("package") {
-        package.set("loadlib", Value::Nil)?;
-        package.set("cpath", Value::Nil)?;
-    }
+    let libs = StdLib::COROUTINE | StdLib::TABLE | StdLib::STRING | StdLib::UTF8 | StdLib::MATH;
+    let lua = mlua::Lua::new_with(libs, LuaOptions::default())?;
 
-    // Remove debug library
-    lua.globals().set("debug", Value::Nil)?;
+    // Remove code loading and output from the base library
+    for name in REMOVED_GLOBALS {
+        lua.globals().set(*name, Value::Nil)?;
+    }
+    // Bytecode is only useful to load; strings share this table as metatable
+    lua.globals().get::<mlua::Table>("string")?.set("dump", Value::Nil)?;
 
-    // Set memory limit (10 MB)
-    lua.set_memory_limit(10_000_000)?;
+    lua.set_memory_limit(metadata.permissions.memory_limit())?;
+    lua.set_hook(HookTriggers::new().every_nth_instruction(DEADLINE_CHECK_INSTRUCTIONS), |lua, _| {
+        if deadline_passed(lua) { Err(mlua::Error::runtime(DEADLINE_MESSAGE)) } else { Ok(VmState::Continue) }
+    })?;
+    let expired = lua.create_function(|lua, ()| Ok(deadline_passed(lua)))?;
+    lua.load(PROTECTED_CALL_WRAPPERS).call::<()>((expired, DEADLINE_MESSAGE))?;
 
     // Register safe APIs
     register_http_api(&lua, &metadata.permissions)?;
@@ -101,7 +146,7 @@ mod tests {
     }
 
     fn create_test_metadata() -> PluginMetadata {
-        use crate::plugins::registry::{ PluginInfo, PluginPermissions };
+        use crate::plugins::registry::{ PluginInfo, PluginPermissions, PluginRuntime };
 
         PluginMetadata {
             plugin: PluginInfo {
@@ -112,6 +157,7 @@ mod tests {
                 description: None,
                 enabled: true,
                 timeout: 5,
+                runtime: PluginRuntime::Lua,
             },
             permissions: PluginPermissions {
                 network: true,
@@ -120,7 +166,73 @@ mod tests {
                 cache_write: true,
                 user_agent: None,
                 env_vars: Vec::new(),
+                max_memory_mb: 10,
             },
         }
     }
+
+    #[test]
+    fn test_known_escapes_fail() {
+        let lua = create_secure_lua_state(&create_test_metadata(), &HashMap::new()).unwrap();
+        set_deadline(&lua, Duration::from_secs(5));
+
+        // Each attempt must fail or find nothing
+        let escapes = [
+            "return os.execute('id')",
+            "return io.open('/etc/passwd')",
+            "return require('io')",
+            "return package.loaded.io",
+            "return debug.getregistry()",
+            "return rawget(_G, 'io')",
+            "return load('return 1')()",
+            "return loadfile('/etc/passwd')",
+            "return dofile('/etc/passwd')",
+            "return string.dump(function() end)",
+            "return getmetatable('').__index.dump(function() end)",
+            "return collectgarbage('stop')",
+            "return warn('@on')",
+            "return _G.jit",
+        ];
+        for code in escapes {
+            let result = lua.load(code).eval::<Value>();
+            assert!(matches!(result, Err(_) | Ok(Value::Nil)), "escape succeeded: {}", code);
+        }
+    }
+
+    #[test]
+    fn test_deadline_stops_endless_loops() {
+        let lua = create_secure_lua_state(&create_test_metadata(), &HashMap::new()).unwrap();
+
+        for code in [
+            "while true do end",
+            "while true do pcall(function() while true do end end) end",
+            "while true do xpcall(function() while true do end end, function() while true do end end) end",
+            "while true do coroutine.resume(coroutine.create(function() while true do end end)) end",
+            "coroutine.wrap(function() while true do end end)()",
+        ] {
+            set_deadline(&lua, Duration::from_millis(100));
+            let error = lua.load(code).exec().unwrap_err();
+            assert!(error.to_string().contains(DEADLINE_MESSAGE), "{}: {}", code, error);
+        }
+
+        // Catching other errors still works before the deadline
+        set_deadline(&lua, Duration::from_secs(5));
+        assert!(!lua.load("return pcall(error, 'x')").eval::<bool>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_memory_limit_and_whitelist() {
+        let mut metadata = create_test_metadata();
+        metadata.permissions.max_memory_mb = 2;
+        let lua = create_secure_lua_state(&metadata, &HashMap::new()).unwrap();
+        set_deadline(&lua, Duration::from_secs(5));
+
+        assert!(lua.load("return string.rep('x', 4000000)").eval_async::<String>().await.is_err());
+        assert!(lua.load("local t = {} for i = 1, 1e7 do t[i] = i end").exec_async().await.is_err());
+
+        for url in ["https://evil.example.net/", "http://127.0.0.1/", "http://example.com.evil.net/", "file:///etc/passwd"] {
+            let result = lua.load(format!("return http_get('{}')", url)).eval_async::<String>().await;
+            assert!(result.is_err(), "fetched {}", url);
+        }
+    }
 }
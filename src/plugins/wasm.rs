@@ -0,0 +1,413 @@
+//! WebAssembly plugin runtime
+//!
+//! Plugins compiled to WebAssembly (Rust, TinyGo, AssemblyScript, ...) are
+//! loaded from `plugin.wasm` when `meta.toml` sets `runtime = "wasm"`. They get
+//! the same sandbox as Lua plugins:
+//! - No WASI, so no file, socket or process access
+//! - Host functions only for the permissions granted in `meta.toml`
+//! - The manifest's memory limit for linear memory (10 MB by default)
+//! - The plugin timeout, enforced by epoch interruption so busy loops stop too
+//!
+//! # ABI
+//!
+//! Strings are UTF-8, passed as a pointer and a length into the plugin's
+//! memory. The module must export:
+//! - `memory`
+//! - `alloc(len: i32) -> i32`, used by the host to place strings in the plugin
+//! - `handle_query(query_ptr: i32, query_len: i32, ctx_ptr: i32, ctx_len: i32) -> i64`,
+//!   returning the response as `ptr << 32 | len`; `ctx` is the request context
+//!   as JSON (`request_id`, `frontend`, `client_ip`, `language`, `identity`)
+//!
+//! and may export `init()`, which runs once when the plugin is loaded. Each
+//! query runs in a fresh instance, so nothing is kept between queries except
+//! through the cache functions.
+//!
+//! Host functions are imported from the `whois` module. Strings returned to
+//! the plugin are allocated with its `alloc` and packed like the response:
+//! - `log(level: i32, ptr: i32, len: i32)` - 0 info, 1 warning, 2 error
+//! - `http_get(url_ptr: i32, url_len: i32) -> i64` - JSON `{"status", "body"}`,
+//!   or `{"error"}` on failure (`network`)
+//! - `cache_get(key_ptr: i32, key_len: i32) -> i64` - value, or 0 when missing (`cache_read`)
+//! - `cache_set(key_ptr: i32, key_len: i32, value_ptr: i32, value_len: i32, ttl: i32)` -
+//!   TTL in seconds, 0 for one hour (`cache_write`)
+//! - `env_get(key_ptr: i32, key_len: i32) -> i64` - value, or 0 when not allowed (`env_vars`)
+//!
+//! A module importing a function its permissions do not grant fails to load.
+
+use anyhow::{ Context, anyhow };
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use wasmtime::{
+    AsContext,
+    AsContextMut,
+    Caller,
+    Config,
+    Engine,
+    Extern,
+    Instance,
+    InstancePre,
+    Linker,
+    Memory,
+    Module,
+    Store,
+    StoreLimits,
+    StoreLimitsBuilder,
+    TypedFunc,
+};
+
+use crate::plugins::api::{ cache_get, cache_set, http_get_allowed, http_user_agent, http_whitelist };
+use crate::plugins::registry::PluginMetadata;
+
+/// Interval between epoch ticks; running plugins yield on every tick, so a
+/// timeout can stop them
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Engine shared by all WebAssembly plugins
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut config = Config::new();
+    config.async_support(true);
+    config.epoch_interruption(true);
+    let engine = Engine::new(&config).expect("Invalid WebAssembly engine configuration");
+
+    let ticker = engine.clone();
+    std::thread::Builder
+        ::new()
+        .name("wasm-epoch".to_string())
+        .spawn(move || {
+            loop {
+                std::thread::sleep(EPOCH_TICK);
+                ticker.increment_epoch();
+            }
+        })
+        .expect("Failed to start WebAssembly epoch thread");
+    engine
+});
+
+/// Per-instance state
+struct WasmState {
+    limits: StoreLimits,
+}
+
+/// A compiled WebAssembly plugin, linked against the host functions its
+/// permissions allow
+pub struct WasmPlugin {
+    instance: InstancePre<WasmState>,
+    /// Linear memory limit per instance, from the manifest
+    memory_limit: usize,
+}
+
+impl WasmPlugin {
+    /// Load a plugin from a `.wasm` file
+    pub async fn load(
+        path: &Path,
+        metadata: &PluginMetadata,
+        env_vars: &HashMap<String, String>
+    ) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path).context("Failed to read plugin.wasm")?;
+        Self::new(&bytes, metadata, env_vars).await
+    }
+
+    /// Compile and link a plugin, then run its `init` function if present
+    pub async fn new(
+        bytes: &[u8],
+        metadata: &PluginMetadata,
+        env_vars: &HashMap<String, String>
+    ) -> anyhow::Result<Self> {
+        let module = Module::new(&ENGINE, bytes).map_err(|e|
+            anyhow!("Failed to compile WebAssembly module: {}", e)
+        )?;
+        for export in ["memory", "alloc", "handle_query"] {
+            if module.get_export(export).is_none() {
+                return Err(anyhow!("Plugin must export '{}'", export));
+            }
+        }
+
+        let linker = create_linker(metadata, env_vars)?;
+        let instance = linker
+            .instantiate_pre(&module)
+            .map_err(|e| anyhow!("Plugin imports a function it has no permission for: {}", e))?;
+        let plugin = Self { instance, memory_limit: metadata.permissions.memory_limit() };
+
+        // Instantiating once also checks the module fits the memory limit
+        let (mut store, instance) = plugin.instantiate().await?;
+        if let Some(init) = instance.get_func(&mut store, "init") {
+            let timeout = Duration::from_secs(metadata.plugin.timeout);
+            tokio::time::timeout(timeout, init.typed::<(), ()>(&store)?.call_async(&mut store, ())).await
+                .map_err(|_| anyhow!("Plugin init function timed out"))?
+                .map_err(|e| anyhow!("Plugin init function failed: {}", e))?;
+        }
+
+        Ok(plugin)
+    }
+
+    async fn instantiate(&self) -> anyhow::Result<(Store<WasmState>, Instance)> {
+        let limits = StoreLimitsBuilder::new().memory_size(self.memory_limit).instances(1).build();
+        let mut store = Store::new(&ENGINE, WasmState { limits });
+        store.limiter(|state| &mut state.limits);
+        store.epoch_deadline_async_yield_and_update(1);
+
+        let instance = self.instance.instantiate_async(&mut store).await?;
+        Ok((store, instance))
+    }
+
+    /// Call the plugin's `handle_query` with a query and its JSON request context
+    pub async fn handle_query(&self, query: &str, context: &str) -> anyhow::Result<String> {
+        let (mut store, instance) = self.instantiate().await?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("Plugin must export 'memory'"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let handle = instance.get_typed_func::<(i32, i32, i32, i32), i64>(&mut store, "handle_query")?;
+
+        let (query_ptr, query_len) = write_string(&mut store, memory, &alloc, query).await?;
+        let (ctx_ptr, ctx_len) = write_string(&mut store, memory, &alloc, context).await?;
+        let packed = handle.call_async(&mut store, (query_ptr, query_len, ctx_ptr, ctx_len)).await?;
+
+        let (ptr, len) = unpack(packed);
+        read_string(&store, memory, ptr, len)
+    }
+}
+
+/// Pack a string location into the i64 returned to and from plugins
+fn pack(ptr: i32, len: i32) -> i64 {
+    (((ptr as u32) as i64) << 32) | ((len as u32) as i64)
+}
+
+fn unpack(packed: i64) -> (i32, i32) {
+    ((packed >> 32) as i32, packed as i32)
+}
+
+fn read_string(store: impl AsContext, memory: Memory, ptr: i32, len: i32) -> anyhow::Result<String> {
+    let mut buffer = vec![0; len as u32 as usize];
+    memory
+        .read(&store, ptr as u32 as usize, &mut buffer)
+        .map_err(|_| anyhow!("Plugin string at {} (length {}) is out of bounds", ptr, len))?;
+    String::from_utf8(buffer).map_err(|_| anyhow!("Plugin string is not valid UTF-8"))
+}
+
+async fn write_string(
+    mut store: impl AsContextMut<Data = WasmState>,
+    memory: Memory,
+    alloc: &TypedFunc<i32, i32>,
+    value: &str
+) -> anyhow::Result<(i32, i32)> {
+    let len = i32::try_from(value.len()).context("String too large for plugin memory")?;
+    let ptr = alloc.call_async(&mut store, len).await?;
+    memory
+        .write(&mut store, ptr as u32 as usize, value.as_bytes())
+        .map_err(|_| anyhow!("Plugin alloc returned an invalid pointer"))?;
+    Ok((ptr, len))
+}
+
+fn caller_memory(caller: &mut Caller<'_, WasmState>) -> anyhow::Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| anyhow!("Plugin must export 'memory'"))
+}
+
+/// Copy a host function's result into the calling plugin
+async fn return_string(caller: &mut Caller<'_, WasmState>, value: &str) -> anyhow::Result<i64> {
+    let memory = caller_memory(caller)?;
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| anyhow!("Plugin must export 'alloc'"))?
+        .typed::<i32, i32>(&*caller)?;
+    let (ptr, len) = write_string(&mut *caller, memory, &alloc, value).await?;
+    Ok(pack(ptr, len))
+}
+
+/// Define the host functions a plugin's permissions allow
+fn create_linker(
+    metadata: &PluginMetadata,
+    env_vars: &HashMap<String, String>
+) -> anyhow::Result<Linker<WasmState>> {
+    let mut linker = Linker::new(&ENGINE);
+    let permissions = &metadata.permissions;
+
+    linker.func_wrap(
+        "whois",
+        "log",
+        |mut caller: Caller<'_, WasmState>, level: i32, ptr: i32, len: i32| -> anyhow::Result<()> {
+            let memory = caller_memory(&mut caller)?;
+            let message = read_string(&caller, memory, ptr, len)?;
+            match level {
+                0 => crate::log_info!("[plugin] {}", message),
+                1 => crate::log_warn!("[plugin] {}", message),
+                _ => crate::log_warn!("[plugin] ERROR: {}", message),
+            }
+            Ok(())
+        }
+    )?;
+
+    if permissions.network {
+        let whitelist = Arc::new(http_whitelist(permissions));
+        let user_agent = Arc::new(http_user_agent(permissions));
+        linker.func_wrap_async(
+            "whois",
+            "http_get",
+            move |mut caller: Caller<'_, WasmState>, (ptr, len): (i32, i32)| {
+                let whitelist = whitelist.clone();
+                let user_agent = user_agent.clone();
+                Box::new(async move {
+                    let memory = caller_memory(&mut caller)?;
+                    let url = read_string(&caller, memory, ptr, len)?;
+                    let result = http_get_allowed(&url, &whitelist, &user_agent).await.unwrap_or_else(|e|
+                        serde_json::json!({ "error": e.to_string() }).to_string()
+                    );
+                    return_string(&mut caller, &result).await
+                })
+            }
+        )?;
+    }
+
+    if permissions.cache_read {
+        linker.func_wrap_async(
+            "whois",
+            "cache_get",
+            |mut caller: Caller<'_, WasmState>, (ptr, len): (i32, i32)| {
+                Box::new(async move {
+                    let memory = caller_memory(&mut caller)?;
+                    let key = read_string(&caller, memory, ptr, len)?;
+                    match cache_get(&key) {
+                        Some(value) => return_string(&mut caller, &value).await,
+                        None => Ok(0),
+                    }
+                })
+            }
+        )?;
+    }
+
+    if permissions.cache_write {
+        linker.func_wrap(
+            "whois",
+            "cache_set",
+            |
+                mut caller: Caller<'_, WasmState>,
+                key_ptr: i32,
+                key_len: i32,
+                value_ptr: i32,
+                value_len: i32,
+                ttl: i32
+            | -> anyhow::Result<()> {
+                let memory = caller_memory(&mut caller)?;
+                let key = read_string(&caller, memory, key_ptr, key_len)?;
+                let value = read_string(&caller, memory, value_ptr, value_len)?;
+                cache_set(key, value, u32::try_from(ttl).ok().filter(|ttl| *ttl > 0));
+                Ok(())
+            }
+        )?;
+    }
+
+    if !env_vars.is_empty() {
+        let env_vars = Arc::new(env_vars.clone());
+        linker.func_wrap_async(
+            "whois",
+            "env_get",
+            move |mut caller: Caller<'_, WasmState>, (ptr, len): (i32, i32)| {
+                let env_vars = env_vars.clone();
+                Box::new(async move {
+                    let memory = caller_memory(&mut caller)?;
+                    let key = read_string(&caller, memory, ptr, len)?;
+                    match env_vars.get(&key) {
+                        Some(value) => return_string(&mut caller, value).await,
+                        None => Ok(0),
+                    }
+                })
+            }
+        )?;
+    }
+
+    Ok(linker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::registry::{ PluginInfo, PluginPermissions, PluginRuntime };
+
+    /// Echoes the query after "echo: ", logging it; bump allocator from 1024
+    const ECHO: &str = r#"
+        (module
+          (import "whois" "log" (func $log (param i32 i32 i32)))
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 0) "echo: ")
+          (func $alloc (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "handle_query") (param $q i32) (param $ql i32) (param $c i32) (param $cl i32) (result i64)
+            (local $out i32)
+            (call $log (i32.const 0) (local.get $q) (local.get $ql))
+            (local.set $out (call $alloc (i32.add (local.get $ql) (i32.const 6))))
+            (memory.copy (local.get $out) (i32.const 0) (i32.const 6))
+            (memory.copy (i32.add (local.get $out) (i32.const 6)) (local.get $q) (local.get $ql))
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $out)) (i64.const 32))
+              (i64.extend_i32_u (i32.add (local.get $ql) (i32.const 6))))))
+    "#;
+
+    /// Loops forever, to check that the timeout stops running plugins
+    const SPIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "handle_query") (param i32 i32 i32 i32) (result i64)
+            (loop $spin (br $spin))
+            (i64.const 0)))
+    "#;
+
+    /// Imports http_get, which needs the network permission
+    const FETCH: &str = r#"
+        (module
+          (import "whois" "http_get" (func (param i32 i32) (result i64)))
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "handle_query") (param i32 i32 i32 i32) (result i64) (i64.const 0)))
+    "#;
+
+    fn metadata(network: bool) -> PluginMetadata {
+        PluginMetadata {
+            plugin: PluginInfo {
+                name: "test".to_string(),
+                version: "1.0.0".to_string(),
+                suffix: "-TEST".to_string(),
+                author: None,
+                description: None,
+                enabled: true,
+                timeout: 5,
+                runtime: PluginRuntime::Wasm,
+            },
+            permissions: PluginPermissions { network, ..PluginPermissions::default() },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_query() {
+        let plugin = WasmPlugin::new(ECHO.as_bytes(), &metadata(false), &HashMap::new()).await.unwrap();
+        assert_eq!(plugin.handle_query("beijing", "{}").await.unwrap(), "echo: beijing");
+        // Every query gets a fresh instance
+        assert_eq!(plugin.handle_query("上海", "{}").await.unwrap(), "echo: 上海");
+    }
+
+    #[tokio::test]
+    async fn test_timeout_stops_busy_loop() {
+        let plugin = WasmPlugin::new(SPIN.as_bytes(), &metadata(false), &HashMap::new()).await.unwrap();
+        let result = tokio::time::timeout(Duration::from_millis(200), plugin.handle_query("x", "{}")).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_imports_need_permissions() {
+        assert!(WasmPlugin::new(FETCH.as_bytes(), &metadata(false), &HashMap::new()).await.is_err());
+        assert!(WasmPlugin::new(FETCH.as_bytes(), &metadata(true), &HashMap::new()).await.is_ok());
+        assert!(WasmPlugin::new(b"(module)", &metadata(true), &HashMap::new()).await.is_err());
+        assert_eq!(unpack(pack(1024, 6)), (1024, 6));
+    }
+}
@@ -1,18 +1,22 @@
+use std::sync::Arc;
+
 use anyhow::{Context, Result};
-use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc;
 use crate::{log_error, log_info};
 
-use super::connection::handle_connection;
+use super::connection::{ConnectionLimits, handle_connection, reject_rate_limited};
+use super::rate_limit::RateLimiter;
+use super::tarpit::{admit, tarpit_connection};
 use crate::core::StatsState;
+use crate::core::metrics::ConnectionGuard;
 
 pub async fn run_async_server(
     addr: &str,
     max_connections: usize,
-    timeout: u64,
-    dump_traffic: bool,
-    dump_dir: &str,
+    limits: ConnectionLimits,
+    rate_limiter: Arc<RateLimiter>,
+    dump_dir: Option<&str>,
     stats: StatsState,
     enable_color: bool,
 ) -> Result<()> {
@@ -36,15 +40,24 @@ pub async fn run_async_server(
                         let tx_clone = tx.clone();
                         let stats_clone = stats.clone();
 
-                        // Set timeout
-                        let timeout = Duration::from_secs(timeout);
-                        let dump_traffic = dump_traffic;
-                        let dump_dir = dump_dir.to_string();
+                        let rate_limiter = rate_limiter.clone();
+                        let dump_dir = dump_dir.map(str::to_string);
 
                         // Handle connection
                         tokio::spawn(async move {
-                            if let Err(e) = handle_connection(stream, addr, timeout, dump_traffic, &dump_dir, stats_clone, enable_color).await {
-                                log_error!("Connection handling error: {}", e);
+                            // Tell clients over their rate limit when to come back,
+                            // or hold them in the tarpit if it is enabled
+                            match rate_limiter.check(addr.ip()).await {
+                                Err(limited) => match admit() {
+                                    Some(permit) => tarpit_connection(stream, addr, limits, stats_clone, permit).await,
+                                    None => reject_rate_limited(stream, addr, limits, limited, stats_clone).await,
+                                },
+                                Ok(()) => {
+                                    let _active = ConnectionGuard::new();
+                                    if let Err(e) = handle_connection(stream, addr, limits, &rate_limiter, dump_dir.as_deref(), stats_clone, enable_color).await {
+                                        log_error!("Connection handling error: {}", e);
+                                    }
+                                }
                             }
 
                             // Notify completion
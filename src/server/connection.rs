@@ -2,574 +2,489 @@ use std::net::SocketAddr;
 use std::time::Duration;
 
 use anyhow::Result;
-use tokio::io::{ AsyncReadExt, AsyncWriteExt };
+use tokio::io::{ AsyncRead, AsyncReadExt, AsyncWriteExt };
 use tokio::net::TcpStream;
-use crate::config::{
-    AFRINIC_WHOIS_PORT,
-    AFRINIC_WHOIS_SERVER,
-    ALTDB_WHOIS_PORT,
-    ALTDB_WHOIS_SERVER,
-    APNIC_WHOIS_PORT,
-    APNIC_WHOIS_SERVER,
-    ARIN_WHOIS_PORT,
-    ARIN_WHOIS_SERVER,
-    BELL_WHOIS_PORT,
-    BELL_WHOIS_SERVER,
-    JPIRR_WHOIS_PORT,
-    JPIRR_WHOIS_SERVER,
-    LACNIC_WHOIS_PORT,
-    LACNIC_WHOIS_SERVER,
-    LEVEL3_WHOIS_PORT,
-    LEVEL3_WHOIS_SERVER,
-    NTTCOM_WHOIS_PORT,
-    NTTCOM_WHOIS_SERVER,
-    RADB_WHOIS_PORT,
-    RADB_WHOIS_SERVER,
-    RIPE_WHOIS_PORT,
-    RIPE_WHOIS_SERVER,
-    RIS_WHOIS_PORT,
-    RIS_WHOIS_SERVER,
-    SERVER_BANNER,
-    TC_WHOIS_PORT,
-    TC_WHOIS_SERVER,
-};
 use crate::core::{
     ColorProtocol,
-    ColorScheme,
-    Colorizer,
+    DropReason,
     QueryType,
     StatsState,
     analyze_query,
-    apply_response_patches,
     dump_to_file,
-    is_private_ipv4,
-    is_private_ipv6,
+    process_query,
+    record_dropped_connection,
 };
 use crate::{log_debug, log_error, log_warn};
-use crate::dn42::process_dn42_query_managed;
-use crate::services::{
-    handle_ntp_query,
-    process_ping_query,
-    process_acgc_query,
-    process_alma_query,
-    process_aosc_query,
-    process_aur_query,
-    process_bgptool_query,
-    process_cargo_query,
-    process_cfstatus_query,
-    process_crt_query,
-    process_debian_query,
-    process_desc_query,
-    process_dns_query,
-    process_email_search,
-    process_epel_query,
-    process_geo_query,
-    process_github_query,
-    process_imdb_query,
-    process_imdb_search_query,
-    process_irr_query,
-    process_looking_glass_query,
-    process_lyric_query,
-    process_manrs_query,
-    process_minecraft_query,
-    process_minecraft_user_query,
-    process_nixos_query,
-    process_npm_query,
-    process_opensuse_query,
-    process_openwrt_query,
-    process_peeringdb_query,
-    process_pen_query,
-    process_prefixes_query,
-    process_pypi_query,
-    process_rdap_query,
-    process_rir_geo_query,
-    process_rpki_query,
-    process_ssl_query,
-    process_steam_query,
-    process_steam_search_query,
-    process_traceroute_query,
-    process_ubuntu_query,
-    process_wikipedia_query,
-    query_curseforge,
-    query_modrinth,
-    query_random_chinese_meal,
-    query_random_meal,
-    query_whois,
-    query_with_iana_referral,
-};
+use crate::core::banner::{ ResponseContext, frame_response };
+use crate::core::sanitize::sanitize_query;
+use crate::core::alias::resolve_aliases;
+use crate::core::modifiers::{ format_header, split_modifiers };
+use crate::core::provenance::{ collect, footer_requested };
+use crate::core::signing::{ sign_response, signature_requested };
+use crate::core::request_context::{ Frontend, RequestContext, language_header };
+use crate::server::rate_limit::{ RateLimited, RateLimiter, rate_limit_message };
+
+/// Limits applied to every client connection to keep slow or abusive
+/// clients from pinning worker tasks
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionLimits {
+    /// Total time allowed to receive the complete request
+    pub read_timeout: Duration,
+    /// Maximum gap between two chunks of request data
+    pub idle_timeout: Duration,
+    /// Maximum request size in bytes (color headers + query line)
+    pub max_request_size: usize,
+    /// Maximum number of queries in one request; a batch may be up to this
+    /// many times `max_request_size`
+    pub max_batch: usize,
+    /// Total time allowed to deliver the response
+    pub write_timeout: Duration,
+    /// How long a kept-alive connection may wait for its next request (zero
+    /// disables keep-alive)
+    pub keepalive_timeout: Duration,
+}
 
-pub async fn handle_connection(
-    mut stream: TcpStream,
-    addr: SocketAddr,
-    timeout: Duration,
-    dump_traffic: bool,
-    dump_dir: &str,
-    stats: StatsState,
-    enable_color: bool
-) -> Result<()> {
-    // Set nodelay to ensure responses are sent immediately
-    if let Err(e) = stream.set_nodelay(true) {
-        log_warn!("Failed to set TCP_NODELAY: {}", e);
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            read_timeout: Duration::from_secs(10),
+            idle_timeout: Duration::from_secs(5),
+            max_request_size: 1024,
+            max_batch: 20,
+            write_timeout: Duration::from_secs(10),
+            keepalive_timeout: Duration::from_secs(60),
+        }
     }
+}
 
-    // Read request
-    let mut buffer = [0u8; 1024];
-    let mut request = String::new();
-
-    let read_future = async {
-        let mut total_read = 0;
-        loop {
-            match stream.read(&mut buffer).await {
-                Ok(0) => {
-                    break;
-                }
-                Ok(n) => {
-                    request.push_str(&String::from_utf8_lossy(&buffer[0..n]));
-                    total_read += n;
+/// Requests answered on one kept-alive connection before it is closed
+const KEEPALIVE_MAX_REQUESTS: usize = 100;
+
+/// Line ending every response on a kept-alive connection
+pub const KEEPALIVE_TERMINATOR: &str = "% End of response\r\n\r\n";
+
+/// Whether the request asked to keep the connection open with an
+/// `X-WHOIS-KEEPALIVE` header
+pub fn keepalive_requested(request: &str) -> bool {
+    request.lines().any(|line| {
+        let Some((name, value)) = line.split_once(':') else {
+            return false;
+        };
+        name.trim().eq_ignore_ascii_case("X-WHOIS-KEEPALIVE") &&
+            matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on")
+    })
+}
 
-                    // Check for CRLF terminator
-                    if request.contains("\r\n") || total_read > 900 {
-                        break;
-                    }
-                }
-                Err(e) => {
-                    return Err(anyhow::anyhow!("Failed to read request: {}", e));
-                }
-            }
-        }
-        Ok(())
-    };
+/// Whether the request announced a batch with an `X-WHOIS-BATCH` header
+///
+/// A batch request is read up to an empty line (or EOF) instead of the
+/// first line, so scripts can send many queries in one connection.
+pub fn batch_requested(request: &str) -> bool {
+    request.lines().any(|line| {
+        let Some((name, value)) = line.split_once(':') else {
+            return false;
+        };
+        name.trim().eq_ignore_ascii_case("X-WHOIS-BATCH") &&
+            matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on")
+    })
+}
 
-    // Read with timeout
-    if let Err(_) = tokio::time::timeout(timeout, read_future).await {
-        return Err(anyhow::anyhow!("Request read timeout"));
+/// Whether `raw` holds a complete request
+fn request_complete(raw: &[u8], batch: bool) -> bool {
+    if batch {
+        raw.windows(4).any(|w| w == b"\r\n\r\n") || raw.windows(2).any(|w| w == b"\n\n")
+    } else {
+        raw.windows(2).any(|w| w == b"\r\n")
     }
+}
+
+/// Whether a request line is an `X-WHOIS-*` header rather than a query
+fn is_header(line: &str) -> bool {
+    line.trim().to_uppercase().starts_with("X-WHOIS-")
+}
 
-    // Dump query if requested
-    if dump_traffic {
-        let timestamp = std::time::SystemTime
-            ::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        dump_to_file(&format!("{}/query_{}.txt", dump_dir, timestamp), &request);
+/// The queries of a request
+///
+/// A batch request holds every non-empty line that is not a header, any
+/// other request only its first line after the headers.
+pub fn request_queries(request: &str) -> Vec<String> {
+    let lines = request.lines().filter(|line| !is_header(line));
+    let lines: Vec<&str> = if batch_requested(request) { lines.collect() } else { lines.take(1).collect() };
+    lines
+        .into_iter()
+        .map(sanitize_query)
+        .filter(|query| !query.is_empty())
+        .collect()
+}
+
+/// Split off requests a client pipelined behind the first one
+///
+/// Without an `X-WHOIS-BATCH` header a request ends after its first query
+/// line; whatever follows is returned as the next request so that it gets
+/// its own response instead of being merged into this one.
+pub fn split_pipelined(request: &mut String) -> Option<String> {
+    let mut end = 0;
+    for line in request.split_inclusive('\n') {
+        end += line.len();
+        if !is_header(line) {
+            break;
+        }
+    }
+    if batch_requested(&request[..end]) {
+        return None;
     }
+    let rest = request.split_off(end);
+    (!rest.trim().is_empty()).then_some(rest)
+}
+
+/// Read a request until CRLF or EOF, enforcing the read deadline, idle
+/// timeout and maximum request size
+///
+/// Batch requests are read until an empty line or EOF.
+pub(crate) async fn read_request<R>(stream: &mut R, limits: &ConnectionLimits) -> Result<String, DropReason>
+    where R: AsyncRead + Unpin
+{
+    let deadline = tokio::time::Instant::now() + limits.read_timeout;
+    let mut buffer = [0u8; 1024];
+    let mut raw: Vec<u8> = Vec::new();
+
+    loop {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if remaining.is_zero() {
+            return Err(DropReason::ReadTimeout);
+        }
 
-    // Parse color protocol headers
-    let mut color_protocol = ColorProtocol::new();
-    color_protocol.enabled = enable_color;
-    let is_capability_probe = color_protocol.parse_headers(&request);
+        let wait = remaining.min(limits.idle_timeout);
+        match tokio::time::timeout(wait, stream.read(&mut buffer)).await {
+            Err(_) => {
+                return Err(if wait < remaining {
+                    DropReason::IdleTimeout
+                } else {
+                    DropReason::ReadTimeout
+                });
+            }
+            Ok(Ok(0)) => {
+                break;
+            }
+            Ok(Ok(n)) => {
+                raw.extend_from_slice(&buffer[..n]);
 
-    // Handle capability probe
-    if is_capability_probe {
-        log_debug!("Received WHOIS-COLOR capability probe from {}", addr);
-        let capability_response = color_protocol.get_capability_response();
+                let batch = limits.max_batch > 1 && batch_requested(&String::from_utf8_lossy(&raw));
+                let max_size = if batch {
+                    limits.max_request_size.saturating_mul(limits.max_batch)
+                } else {
+                    limits.max_request_size
+                };
+                if raw.len() > max_size {
+                    return Err(DropReason::RequestTooLarge);
+                }
 
-        if let Err(e) = stream.write_all(capability_response.as_bytes()).await {
-            log_error!("Failed to send capability response: {}", e);
-        } else {
-            log_debug!("Sent WHOIS-COLOR capability response");
+                // Check for CRLF terminator (an empty line for batches)
+                if request_complete(&raw, batch) {
+                    break;
+                }
+            }
+            Ok(Err(e)) => {
+                log_debug!("Failed to read request: {}", e);
+                return Err(DropReason::ReadError);
+            }
         }
+    }
+
+    Ok(String::from_utf8_lossy(&raw).into_owned())
+}
 
-        return Ok(());
+pub async fn handle_connection(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    limits: ConnectionLimits,
+    rate_limiter: &RateLimiter,
+    dump_dir: Option<&str>,
+    stats: StatsState,
+    enable_color: bool
+) -> Result<()> {
+    // Set nodelay to ensure responses are sent immediately
+    if let Err(e) = stream.set_nodelay(true) {
+        log_warn!("Failed to set TCP_NODELAY: {}", e);
     }
 
-    // Clean request - trim whitespace and get first line (skip headers)
-    let query_line = request
-        .trim()
-        .lines()
-        .find(|line| !line.trim().to_uppercase().starts_with("X-WHOIS-COLOR"))
-        .unwrap_or("");
+    // Read request with deadline, idle and size limits
+    let mut request = match read_request(&mut stream, &limits).await {
+        Ok(request) => request,
+        Err(reason) => {
+            log_warn!("Dropping connection from {}: {}", addr, reason.as_str());
+            record_dropped_connection(&stats, reason);
+            return Ok(());
+        }
+    };
 
-    let query = query_line.trim().to_string();
+    let mut pipelined = split_pipelined(&mut request);
 
-    // Skip empty queries
-    if query.is_empty() {
-        log_debug!("Received empty query from {}", addr);
-        return Ok(());
+    // An X-WHOIS-KEEPALIVE header on the first request keeps the connection
+    // open for further requests until the client goes idle
+    let keep_alive = !limits.keepalive_timeout.is_zero() && keepalive_requested(&request);
+    if keep_alive {
+        log_debug!("Keeping connection from {} alive", addr);
     }
 
-    log_debug!("Received query from {}: {} (color: {:?})", addr, query, color_protocol.scheme);
+    let mut requests = 0;
+    loop {
+        if requests > 0 {
+            if !keep_alive || requests >= KEEPALIVE_MAX_REQUESTS {
+                break;
+            }
 
-    // Start timing the query
-    let start_time = std::time::Instant::now();
-
-    // Analyze query type
-    let query_type = analyze_query(&query);
-
-    // Select appropriate WHOIS server and query
-    let result = match &query_type {
-        QueryType::Domain(domain) => {
-            log_debug!("Processing domain query: {}", domain);
-            if domain.to_lowercase().ends_with(".dn42") {
-                log_debug!("Detected .dn42 domain, using DN42 query");
-                process_dn42_query_managed(domain).await
-            } else {
-                query_with_iana_referral(domain).await
+            // Answer pipelined requests first, then wait for the next one on
+            // a kept-alive connection
+            request = match pipelined.take() {
+                Some(next) => next,
+                None => match next_request(&mut stream, &limits).await {
+                    Some(next) => next,
+                    None => {
+                        log_debug!("Kept-alive connection from {} closed after {} request(s)", addr, requests);
+                        break;
+                    }
+                },
+            };
+            pipelined = split_pipelined(&mut request);
+        }
+        requests += 1;
+
+        // Dump query if requested
+        if let Some(dump_dir) = dump_dir {
+            let timestamp = std::time::SystemTime
+                ::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            dump_to_file(&format!("{}/query_{}.txt", dump_dir, timestamp), &request);
+        }
+
+        // Parse color protocol headers
+        let mut color_protocol = ColorProtocol::new();
+        color_protocol.enabled = enable_color;
+        let is_capability_probe = color_protocol.parse_headers(&request);
+
+        // Handle capability probe; a kept-alive connection goes on serving
+        // requests after it
+        if is_capability_probe {
+            log_debug!("Received WHOIS-COLOR capability probe from {}", addr);
+            let mut capability_response = color_protocol.get_capability_response();
+            if keep_alive {
+                capability_response.push_str(KEEPALIVE_TERMINATOR);
             }
-        }
-        QueryType::IPv4(ip) => {
-            log_debug!("Processing IPv4 query: {}", ip);
-            if is_private_ipv4(*ip) {
-                log_debug!("Detected private IPv4 address, using DN42 query");
-                process_dn42_query_managed(&query).await
+
+            if let Err(e) = stream.write_all(capability_response.as_bytes()).await {
+                log_error!("Failed to send capability response: {}", e);
             } else {
-                query_with_iana_referral(&query).await
+                log_debug!("Sent WHOIS-COLOR capability response");
             }
-        }
-        QueryType::IPv6(ip) => {
-            log_debug!("Processing IPv6 query: {}", ip);
-            if is_private_ipv6(*ip) {
-                log_debug!("Detected private IPv6 address, using DN42 query");
-                process_dn42_query_managed(&query).await
-            } else {
-                query_with_iana_referral(&query).await
+
+            if !keep_alive {
+                return Ok(());
             }
+            continue;
         }
-        QueryType::ASN(asn) => {
-            log_debug!("Processing ASN query: {}", asn);
-            if asn.to_uppercase().starts_with("AS42424") {
-                log_debug!("Detected DN42 ASN, using DN42 query");
-                process_dn42_query_managed(asn).await
-            } else {
-                query_with_iana_referral(asn).await
+
+        // Clean request - every line that is not a header is a query
+        let queries = request_queries(&request);
+
+        // Skip empty queries; on a kept-alive connection the client still
+        // gets the terminator so that it does not wait for a response
+        if queries.is_empty() {
+            log_debug!("Received empty query from {}", addr);
+            if !keep_alive {
+                return Ok(());
             }
-        }
-        QueryType::EmailSearch(base_query) => {
-            log_debug!("Processing email search query: {}", base_query);
-            process_email_search(base_query).await
-        }
-        QueryType::BGPTool(base_query) => {
-            log_debug!("Processing BGP Tools query: {}", base_query);
-            process_bgptool_query(base_query).await
-        }
-        QueryType::Geo(resource) => {
-            log_debug!("Processing geo location query: {}", resource);
-            process_geo_query(resource).await
-        }
-        QueryType::RirGeo(resource) => {
-            log_debug!("Processing RIR geo location query: {}", resource);
-            process_rir_geo_query(resource).await
-        }
-        QueryType::Prefixes(asn) => {
-            log_debug!("Processing ASN prefixes query: {}", asn);
-            process_prefixes_query(asn).await
-        }
-        QueryType::Radb(resource) => {
-            log_debug!("Processing RADB query: {}", resource);
-            query_whois(resource, RADB_WHOIS_SERVER, RADB_WHOIS_PORT).await
-        }
-        QueryType::Altdb(resource) => {
-            log_debug!("Processing ALTDB query: {}", resource);
-            query_whois(resource, ALTDB_WHOIS_SERVER, ALTDB_WHOIS_PORT).await
-        }
-        QueryType::Afrinic(resource) => {
-            log_debug!("Processing AFRINIC query: {}", resource);
-            query_whois(resource, AFRINIC_WHOIS_SERVER, AFRINIC_WHOIS_PORT).await
-        }
-        QueryType::Apnic(resource) => {
-            log_debug!("Processing APNIC query: {}", resource);
-            query_whois(resource, APNIC_WHOIS_SERVER, APNIC_WHOIS_PORT).await
-        }
-        QueryType::ArinIrr(resource) => {
-            log_debug!("Processing ARIN IRR query: {}", resource);
-            query_whois(resource, ARIN_WHOIS_SERVER, ARIN_WHOIS_PORT).await
-        }
-        QueryType::Bell(resource) => {
-            log_debug!("Processing BELL query: {}", resource);
-            query_whois(resource, BELL_WHOIS_SERVER, BELL_WHOIS_PORT).await
-        }
-        QueryType::Jpirr(resource) => {
-            log_debug!("Processing JPIRR query: {}", resource);
-            query_whois(resource, JPIRR_WHOIS_SERVER, JPIRR_WHOIS_PORT).await
-        }
-        QueryType::Lacnic(resource) => {
-            log_debug!("Processing LACNIC query: {}", resource);
-            query_whois(resource, LACNIC_WHOIS_SERVER, LACNIC_WHOIS_PORT).await
-        }
-        QueryType::Level3(resource) => {
-            log_debug!("Processing LEVEL3 query: {}", resource);
-            query_whois(resource, LEVEL3_WHOIS_SERVER, LEVEL3_WHOIS_PORT).await
-        }
-        QueryType::Nttcom(resource) => {
-            log_debug!("Processing NTTCOM query: {}", resource);
-            query_whois(resource, NTTCOM_WHOIS_SERVER, NTTCOM_WHOIS_PORT).await
-        }
-        QueryType::RipeIrr(resource) => {
-            log_debug!("Processing RIPE IRR query: {}", resource);
-            query_whois(resource, RIPE_WHOIS_SERVER, RIPE_WHOIS_PORT).await
-        }
-        QueryType::Ris(resource) => {
-            log_debug!("Processing RIS query: {}", resource);
-            query_whois(resource, RIS_WHOIS_SERVER, RIS_WHOIS_PORT).await
-        }
-        QueryType::Tc(resource) => {
-            log_debug!("Processing TC query: {}", resource);
-            query_whois(resource, TC_WHOIS_SERVER, TC_WHOIS_PORT).await
-        }
-        QueryType::Irr(resource) => {
-            log_debug!("Processing IRR Explorer query: {}", resource);
-            process_irr_query(resource).await
-        }
-        QueryType::LookingGlass(resource) => {
-            log_debug!("Processing Looking Glass query: {}", resource);
-            process_looking_glass_query(resource).await
-        }
-        QueryType::Rpki(prefix, asn) => {
-            log_debug!("Processing RPKI query: prefix={}, asn={}", prefix, asn);
-            process_rpki_query(prefix, asn).await
-        }
-        QueryType::Manrs(base_query) => {
-            log_debug!("Processing MANRS query: {}", base_query);
-            process_manrs_query(&format!("{}-MANRS", base_query)).await
-        }
-        QueryType::Dns(base_query) => {
-            log_debug!("Processing DNS query: {}", base_query);
-            process_dns_query(base_query).await
-        }
-        QueryType::Ntp(base_query) => {
-            log_debug!("Processing NTP query: {}", base_query);
-            handle_ntp_query(base_query).await
-        }
-        QueryType::Ping(base_query) => {
-            log_debug!("Processing ping query: {}", base_query);
-            process_ping_query(base_query).await
-        }
-        QueryType::Trace(base_query) => {
-            log_debug!("Processing traceroute query: {}", base_query);
-            process_traceroute_query(base_query).await
-        }
-        QueryType::Ssl(base_query) => {
-            log_debug!("Processing SSL certificate query: {}", base_query);
-            process_ssl_query(&format!("{}-SSL", base_query)).await
-        }
-        QueryType::Crt(base_query) => {
-            log_debug!("Processing Certificate Transparency query: {}", base_query);
-            process_crt_query(&format!("{}-CRT", base_query)).await
-        }
-        QueryType::CfStatus(base_query) => {
-            log_debug!("Processing Cloudflare Status query: {}", base_query);
-            process_cfstatus_query(&format!("{}-CFSTATUS", base_query)).await
-        }
-        QueryType::Minecraft(base_query) => {
-            log_debug!("Processing Minecraft server query: {}", base_query);
-            process_minecraft_query(&format!("{}-MC", base_query)).await
-        }
-        QueryType::MinecraftUser(base_query) => {
-            log_debug!("Processing Minecraft user query: {}", base_query);
-            process_minecraft_user_query(&format!("{}-MCU", base_query)).await
-        }
-        QueryType::Steam(base_query) => {
-            log_debug!("Processing Steam game/user query: {}", base_query);
-            process_steam_query(&format!("{}-STEAM", base_query)).await
-        }
-        QueryType::SteamSearch(base_query) => {
-            log_debug!("Processing Steam game search query: {}", base_query);
-            process_steam_search_query(&format!("{}-STEAMSEARCH", base_query)).await
-        }
-        QueryType::Imdb(base_query) => {
-            log_debug!("Processing IMDb movie/TV show query: {}", base_query);
-            process_imdb_query(&format!("{}-IMDB", base_query)).await
-        }
-        QueryType::ImdbSearch(base_query) => {
-            log_debug!("Processing IMDb search query: {}", base_query);
-            process_imdb_search_query(&format!("{}-IMDBSEARCH", base_query)).await
-        }
-        QueryType::Acgc(base_query) => {
-            log_debug!("Processing ACGC character query: {}", base_query);
-            process_acgc_query(&format!("{}-ACGC", base_query)).await
-        }
-        QueryType::Alma(base_query) => {
-            log_debug!("Processing AlmaLinux package query: {}", base_query);
-            process_alma_query(base_query).await
-        }
-        QueryType::Aosc(base_query) => {
-            log_debug!("Processing AOSC package query: {}", base_query);
-            process_aosc_query(base_query).await
-        }
-        QueryType::Aur(base_query) => {
-            log_debug!("Processing AUR package query: {}", base_query);
-            process_aur_query(base_query).await
-        }
-        QueryType::Debian(base_query) => {
-            log_debug!("Processing Debian package query: {}", base_query);
-            process_debian_query(base_query).await
-        }
-        QueryType::Epel(base_query) => {
-            log_debug!("Processing EPEL package query: {}", base_query);
-            process_epel_query(base_query).await
-        }
-        QueryType::Ubuntu(base_query) => {
-            log_debug!("Processing Ubuntu package query: {}", base_query);
-            process_ubuntu_query(base_query).await
-        }
-        QueryType::NixOs(base_query) => {
-            log_debug!("Processing NixOS package query: {}", base_query);
-            process_nixos_query(base_query).await
-        }
-        QueryType::OpenSuse(base_query) => {
-            log_debug!("Processing OpenSUSE package query: {}", base_query);
-            process_opensuse_query(base_query).await
-        }
-        QueryType::OpenWrt(base_query) => {
-            log_debug!("Processing OpenWrt package query: {}", base_query);
-            process_openwrt_query(base_query).await
-        }
-        QueryType::Npm(base_query) => {
-            log_debug!("Processing NPM package query: {}", base_query);
-            process_npm_query(base_query).await
-        }
-        QueryType::Pypi(base_query) => {
-            log_debug!("Processing PyPI package query: {}", base_query);
-            process_pypi_query(base_query).await
-        }
-        QueryType::Cargo(base_query) => {
-            log_debug!("Processing Cargo (Rust) package query: {}", base_query);
-            process_cargo_query(base_query).await
-        }
-        QueryType::Modrinth(base_query) => {
-            log_debug!("Processing Modrinth mod/resource pack query: {}", base_query);
-            query_modrinth(base_query).await
-        }
-        QueryType::CurseForge(base_query) => {
-            log_debug!("Processing CurseForge mod query: {}", base_query);
-            query_curseforge(base_query).await
-        }
-        QueryType::GitHub(base_query) => {
-            log_debug!("Processing GitHub user/repository query: {}", base_query);
-            process_github_query(base_query).await
-        }
-        QueryType::Wikipedia(base_query) => {
-            log_debug!("Processing Wikipedia article query: {}", base_query);
-            process_wikipedia_query(&format!("{}-WIKIPEDIA", base_query)).await
-        }
-        QueryType::Lyric(base_query) => {
-            log_debug!("Processing Luotianyi lyric query: {}", base_query);
-            process_lyric_query(&format!("{}-LYRIC", base_query)).await
-        }
-        QueryType::Desc(base_query) => {
-            log_debug!("Processing description query: {}", base_query);
-            process_desc_query(base_query).await
-        }
-        QueryType::PeeringDB(base_query) => {
-            log_debug!("Processing PeeringDB query: {}", base_query);
-            process_peeringdb_query(base_query).await
-        }
-        QueryType::Pen(base_query) => {
-            log_debug!("Processing IANA Private Enterprise Numbers query: {}", base_query);
-            process_pen_query(base_query).await
-        }
-        QueryType::Rdap(base_query) => {
-            log_debug!("Processing RDAP query: {}", base_query);
-            process_rdap_query(base_query).await
-        }
-        QueryType::Meal => {
-            log_debug!("Processing meal suggestion query");
-            query_random_meal().await
-        }
-        QueryType::MealCN => {
-            log_debug!("Processing Chinese meal suggestion query");
-            query_random_chinese_meal().await
-        }
-        QueryType::Help => {
-            log_debug!("Processing HELP query");
-            Ok(crate::services::help::generate_help_response())
-        }
-        QueryType::UpdatePatch => {
-            log_debug!("Processing UPDATE-PATCH query");
-            use crate::core::patch::process_update_patch_query;
-            match process_update_patch_query().await {
-                Ok(output) => Ok(output),
-                Err(e) => Ok(format!("% Error: {}\n", e)),
+            if let Ok(Err(e)) = tokio::time::timeout(limits.write_timeout, stream.write_all(KEEPALIVE_TERMINATOR.as_bytes())).await {
+                return Err(anyhow::anyhow!("Failed to send response: {}", e));
             }
-        }
-        QueryType::Pixiv(base_query) => {
-            log_debug!("Processing Pixiv query: {}", base_query);
-            crate::services::pixiv::process_pixiv_query(base_query).await
-        }
-        QueryType::Icp(base_query) => {
-            log_debug!("Processing ICP query: {}", base_query);
-            Ok(crate::services::process_icp_query(base_query).await)
-        }
-        QueryType::Plugin(_, _) => {
-            // Plugins should be handled by process_query, not here
-            // This is a fallback path
-            log_debug!("Plugin query routed to connection handler, using standard query processor");
-            crate::core::query_processor::process_query(&query, &query_type, None, None).await
-        }
-        QueryType::Unknown(q) => {
-            log_debug!("Unknown query type: {}", q);
-            let q_upper = q.to_uppercase();
-            if
-                q_upper.ends_with("-DN42") ||
-                q_upper.ends_with("-MNT") ||
-                q_upper.ends_with("-NEONETWORK") ||
-                q_upper.ends_with("-CRXN")
-            {
-                log_debug!("Detected DN42/NeoNetwork/CRXN related query ({}), using DN42 database", q);
-                process_dn42_query_managed(q).await
+            continue;
+        }
+
+        // Refuse batches over the limit before doing any lookups
+        let max_batch = limits.max_batch.max(1);
+        if queries.len() > max_batch {
+            log_warn!("Rejecting batch of {} queries from {} (limit {})", queries.len(), addr, max_batch);
+            let message = format!("% Error: Batch of {} queries exceeds the limit of {}\r\n\r\n", queries.len(), max_batch);
+            let _ = tokio::time::timeout(limits.write_timeout, stream.write_all(message.as_bytes())).await;
+            let _ = stream.shutdown().await;
+            return Ok(());
+        }
+        // The connection paid for the first query of its first request;
+        // every further query, in a batch or a kept-alive request, takes a
+        // token too
+        let prepaid = usize::from(requests == 1);
+        if let Err(limited) = rate_limiter.take(addr.ip(), queries.len().saturating_sub(prepaid) as u32).await {
+            log_warn!("Rate limit exceeded by {} with {} queries in request {}", addr, queries.len(), requests);
+            record_dropped_connection(&stats, DropReason::RateLimited);
+            let message = rate_limit_message(limited);
+            let _ = tokio::time::timeout(limits.write_timeout, stream.write_all(message.as_bytes())).await;
+            let _ = stream.shutdown().await;
+            return Ok(());
+        }
+
+        let batch = queries.len() > 1;
+        if batch {
+            log_debug!("Received batch of {} queries from {}", queries.len(), addr);
+        }
+
+        // Answer the queries in order, each response preceded by a separator
+        // line in a batch
+        let mut dumped = String::new();
+        for (index, query) in queries.iter().enumerate() {
+            let (response, query_type) = respond(query, &request, addr, &color_protocol).await;
+            let mut formatted_response = if batch {
+                format!("{}{}", batch_separator(index + 1, queries.len(), query), response)
             } else {
-                let public_result = query_with_iana_referral(q).await;
-
-                match &public_result {
-                    Ok(response) if
-                        response.trim().is_empty() ||
-                        response.contains("No entries found") ||
-                        response.contains("Not found")
-                    => {
-                        log_debug!("Public query returned no results, trying DN42 for: {}", q);
-                        process_dn42_query_managed(q).await
-                    }
-                    Err(_) => {
-                        log_debug!("Public query failed, trying DN42 for: {}", q);
-                        process_dn42_query_managed(q).await
-                    }
-                    _ => public_result,
+                response
+            };
+            // Kept-alive clients cannot wait for the connection to close
+            if keep_alive && index + 1 == queries.len() {
+                if !formatted_response.ends_with('\n') {
+                    formatted_response.push_str("\r\n");
                 }
+                formatted_response.push_str(KEEPALIVE_TERMINATOR);
             }
-        }
-    };
 
-    // Format the response with proper WHOIS format and optional colorization
-    let formatted_response = match result {
-        Ok(resp) => {
-            let mut formatted = format!("{}\r\n", SERVER_BANNER);
-            formatted.push_str("% The objects are in RPSL format\r\n");
-            formatted.push_str("% Please report any issues to noc@akae.re\r\n");
-            formatted.push_str("\r\n");
-
-            // Apply colorization if requested and supported
-            let response_content = if color_protocol.should_colorize() {
-                if let Some(scheme) = &color_protocol.scheme {
-                    let colorizer = Colorizer::new(scheme.clone());
-                    colorizer.colorize_response(&resp, &query_type)
-                } else {
-                    resp
+            if dump_dir.is_some() {
+                dumped.push_str(&formatted_response);
+            }
+
+            // Log the response size (helpful for debugging)
+            log_debug!("Sending response ({} bytes) for query: {}", formatted_response.len(), query);
+
+            // Send response - use write_all to ensure entire response is sent, bounded by the write deadline
+            let write_result = match
+                tokio::time::timeout(limits.write_timeout, stream.write_all(formatted_response.as_bytes())).await
+            {
+                Ok(result) => result,
+                Err(_) => {
+                    log_warn!("Dropping connection from {}: {}", addr, DropReason::WriteTimeout.as_str());
+                    record_dropped_connection(&stats, DropReason::WriteTimeout);
+                    return Ok(());
                 }
-            } else {
-                resp
             };
 
-            // Apply response patches (after colorization)
-            let patched_content = apply_response_patches(&query, response_content);
+            match write_result {
+                Ok(_) => {
+                    // Flush to ensure data is sent
+                    if let Err(e) = stream.flush().await {
+                        log_error!("Failed to flush response: {}", e);
+                    }
+                    log_debug!("Query response sent: {}", query);
 
-            // Add the response content (colorized and patched)
-            formatted.push_str(&patched_content);
+                    // Replay a sample of queries against the shadow instance, if configured
+                    crate::server::shadow::mirror(&request, query, &query_type, &formatted_response);
 
-            // Ensure response ends with a CRLF
-            if !formatted.ends_with("\r\n") {
-                formatted.push_str("\r\n");
+                    // Record statistics
+                    crate::core::record_request(&stats, formatted_response.len()).await;
+                }
+                Err(e) => {
+                    log_error!("Failed to send response for {}: {}", query, e);
+                    return Err(anyhow::anyhow!("Failed to send response: {}", e));
+                }
             }
+        }
 
-            formatted
+        // Dump response if requested
+        if let Some(dump_dir) = dump_dir && !dumped.is_empty() {
+            let timestamp = std::time::SystemTime
+                ::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            dump_to_file(&format!("{}/response_{}.txt", dump_dir, timestamp), &dumped);
+        }
+    }
+
+    // According to RFC 3912, the server MUST close the connection, not wait for client
+    log_debug!("Closing connection from server side (RFC 3912 requirement)");
+
+    // First shutdown write side to ensure all data is transmitted
+    if let Err(e) = stream.shutdown().await {
+        log_warn!("Error shutting down connection: {}", e);
+    }
+
+    // Drop the stream to forcibly close the connection
+    drop(stream);
+
+    Ok(())
+}
+
+/// Wait up to the keep-alive timeout for the next request of a kept-alive
+/// connection, then read it within the usual limits
+///
+/// Returns `None` when the client closed the connection or stayed idle.
+async fn next_request(stream: &mut TcpStream, limits: &ConnectionLimits) -> Option<String> {
+    let mut byte = [0u8; 1];
+    match tokio::time::timeout(limits.keepalive_timeout, stream.peek(&mut byte)).await {
+        Ok(Ok(n)) if n > 0 => {}
+        _ => {
+            return None;
+        }
+    }
+    match read_request(stream, limits).await {
+        Ok(request) => Some(request),
+        Err(reason) => {
+            log_debug!("Failed to read the next request: {}", reason.as_str());
+            None
+        }
+    }
+}
+
+/// Separator line written before each response of a batch
+pub fn batch_separator(index: usize, total: usize, query: &str) -> String {
+    format!("% Batch query {}/{}: {}\r\n", index, total, query)
+}
+
+/// Run one query of a request and format its response
+///
+/// The query processor looks the query up and applies enrichment, colors,
+/// patches and output modifiers as for every other frontend; port 43 adds
+/// the provenance footer, banner and signature. Returns the framed response
+/// with the query type it was answered as.
+async fn respond(
+    query: &str,
+    request: &str,
+    addr: SocketAddr,
+    color_protocol: &ColorProtocol
+) -> (String, QueryType) {
+    // Analyze query type; output modifiers (-JSON, -MD, -P<n>) decide how the
+    // response is framed and signed
+    let query_type = analyze_query(query);
+    let resolved = resolve_aliases(query);
+    let (_, modifiers) = split_modifiers(&resolved);
+    let modifiers = modifiers.with_default_format(format_header(request));
+    let sign = modifiers.is_plain_text() && (modifiers.signed || signature_requested(request));
+
+    // Signed responses stay plain
+    let color = if color_protocol.should_colorize() && !sign { color_protocol.scheme.clone() } else { None };
+    let ctx = RequestContext::new(Frontend::Whois)
+        .with_client_addr(addr.ip())
+        .with_color(color)
+        .with_language(language_header(request))
+        .with_format(format_header(request));
+
+    log_debug!("[{}] Received query: {} (color: {:?})", ctx, query, ctx.color);
+
+    // Start timing the query
+    let start_time = ctx.started;
+    let show_provenance = footer_requested(request);
+
+    let (result, mut provenance) = collect(process_query(query, &query_type, &ctx)).await;
+    let query_time = start_time.elapsed();
+    provenance.add_stage("query", query_time);
+
+    let mut body = match result {
+        Ok(response) => response,
+        Err(e) if !modifiers.is_plain_text() => {
+            log_error!("WHOIS query error for {}: {}", query, e);
+            modifiers.format_error(query, &query_type, &e.to_string())
         }
         Err(e) => {
             log_error!("WHOIS query error for {}: {}", query, e);
 
-            let mut formatted = format!("{}\r\n", SERVER_BANNER);
-            formatted.push_str("% Please report any issues to noc@akae.re\r\n");
-            formatted.push_str("\r\n");
-
             let error_msg = format!("% Error: {}\r\n", e);
 
             // Apply colorization to error message if requested
@@ -579,78 +494,252 @@ pub async fn handle_connection(
                 error_msg
             };
 
-            formatted.push_str(&colored_error);
-            formatted.push_str("\r\n");
-            formatted
+            format!("{}\r\n", colored_error)
         }
     };
 
-    // Dump response if requested
-    if dump_traffic {
-        let timestamp = std::time::SystemTime
-            ::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        dump_to_file(&format!("{}/response_{}.txt", dump_dir, timestamp), &formatted_response);
-    }
+    provenance.add_stage("format", start_time.elapsed() - query_time);
+    provenance.add_stage("total", start_time.elapsed());
 
-    // Log the response size (helpful for debugging)
-    log_debug!("Sending response ({} bytes) for query: {}", formatted_response.len(), query);
+    // Append data sources and timings when requested
+    if show_provenance && modifiers.is_framed() {
+        if !body.ends_with("\r\n") {
+            body.push_str("\r\n");
+        }
+        body.push_str("\r\n");
+        body.push_str(&provenance.render());
+    }
 
-    // Send response - use write_all to ensure entire response is sent
-    match stream.write_all(formatted_response.as_bytes()).await {
-        Ok(_) => {
-            // Flush to ensure data is sent
-            if let Err(e) = stream.flush().await {
-                log_error!("Failed to flush response: {}", e);
-            }
-            log_debug!("Query response sent: {}", query);
+    // Wrap the response in the configured banner and footer
+    let mut context = ResponseContext::new(query, &query_type);
+    if !provenance.sources.is_empty() {
+        context.sources = provenance.source_names();
+    }
+    let mut formatted_response = if modifiers.is_framed() {
+        frame_response(&body, &context)
+    } else {
+        body
+    };
 
-            // Record statistics
-            crate::core::record_request(&stats, formatted_response.len()).await;
+    // Sign the framed response when requested
+    if sign {
+        formatted_response = sign_response(formatted_response, query);
+    }
 
-            // Send telemetry data
-            let response_time = start_time.elapsed().as_millis() as u64;
-            let client_ip = addr.ip().to_string();
-            let query_type_str = crate::core::telemetry::query_type_to_string(&query_type);
+    (formatted_response, query_type)
+}
 
-            let telemetry_data = crate::core::telemetry::TelemetryData::new(
-                query.clone(),
-                query_type_str,
-                client_ip,
-                response_time
-            );
+/// Answer a client over its rate limit with the time to retry
+///
+/// The request is read first (within the usual limits) so that closing the
+/// socket does not reset the connection before the client sees the message.
+pub async fn reject_rate_limited(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    limits: ConnectionLimits,
+    limited: RateLimited,
+    stats: StatsState
+) {
+    log_warn!("Rate limit exceeded by {}: {:?}", addr, limited);
+    record_dropped_connection(&stats, DropReason::RateLimited);
+
+    if read_request(&mut stream, &limits).await.is_err() {
+        return;
+    }
+    let message = rate_limit_message(limited);
+    if let Ok(Err(e)) = tokio::time::timeout(limits.write_timeout, stream.write_all(message.as_bytes())).await {
+        log_debug!("Failed to send rate limit response to {}: {}", addr, e);
+    }
+    let _ = stream.shutdown().await;
+}
 
-            crate::core::telemetry::send_telemetry(telemetry_data).await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::stats::{ TotalStats, stats_state_with };
+    use crate::server::rate_limit::RateLimitConfig;
+    use crate::storage::kv::MemoryStore;
+    use std::sync::Arc;
+    use tokio::io::duplex;
+
+    fn test_limits() -> ConnectionLimits {
+        ConnectionLimits {
+            read_timeout: Duration::from_millis(300),
+            idle_timeout: Duration::from_millis(100),
+            max_request_size: 64,
+            max_batch: 3,
+            write_timeout: Duration::from_millis(300),
+            keepalive_timeout: Duration::from_millis(300),
         }
-        Err(e) => {
-            log_error!("Failed to send response for {}: {}", query, e);
-            return Err(anyhow::anyhow!("Failed to send response: {}", e));
+    }
+
+    #[tokio::test]
+    async fn test_read_request_complete() {
+        let (mut client, mut server) = duplex(1024);
+        client.write_all(b"example.com\r\n").await.unwrap();
+
+        let request = read_request(&mut server, &test_limits()).await.unwrap();
+        assert_eq!(request, "example.com\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_read_request_batch() {
+        let (mut client, mut server) = duplex(1024);
+        client.write_all(b"X-WHOIS-BATCH: on\r\nexample.com\r\n").await.unwrap();
+        let reader = tokio::spawn(async move { read_request(&mut server, &test_limits()).await });
+
+        // The batch is only complete at the empty line
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.write_all(b"AS13335\r\n\r\n").await.unwrap();
+        let request = reader.await.unwrap().unwrap();
+        assert_eq!(request, "X-WHOIS-BATCH: on\r\nexample.com\r\nAS13335\r\n\r\n");
+        assert_eq!(request_queries(&request), vec!["example.com", "AS13335"]);
+    }
+
+    #[test]
+    fn test_request_queries() {
+        assert_eq!(request_queries("example.com\r\n"), vec!["example.com"]);
+        assert_eq!(request_queries("X-WHOIS-COLOR: ripe\r\n1.1.1.1\nAS13335\r\n"), vec!["1.1.1.1"]);
+        assert_eq!(
+            request_queries("X-WHOIS-BATCH: on\r\nX-WHOIS-COLOR: ripe\r\n1.1.1.1\n\n  AS13335  \r\n"),
+            vec!["1.1.1.1", "AS13335"]
+        );
+        assert!(request_queries("X-WHOIS-BATCH: on\r\n\r\n").is_empty());
+        assert!(request_queries("\r\nexample.com\r\n").is_empty());
+        assert!(batch_requested("x-whois-batch: 1\r\n"));
+        assert!(!batch_requested("X-WHOIS-COLOR: ripe\r\n"));
+        assert_eq!(batch_separator(2, 3, "AS13335"), "% Batch query 2/3: AS13335\r\n");
+    }
+
+    #[test]
+    fn test_split_pipelined() {
+        let mut request = "X-WHOIS-KEEPALIVE: 1\r\nHELP\r\nAS13335\r\n".to_string();
+        assert_eq!(split_pipelined(&mut request).as_deref(), Some("AS13335\r\n"));
+        assert_eq!(request, "X-WHOIS-KEEPALIVE: 1\r\nHELP\r\n");
+
+        let mut request = "example.com\r\n".to_string();
+        assert_eq!(split_pipelined(&mut request), None);
+
+        // A batch keeps all of its lines
+        let mut request = "X-WHOIS-BATCH: on\r\nexample.com\r\nAS13335\r\n\r\n".to_string();
+        assert_eq!(split_pipelined(&mut request), None);
+        assert_eq!(request_queries(&request), vec!["example.com", "AS13335"]);
+    }
+
+    #[test]
+    fn test_keepalive_requested() {
+        assert!(keepalive_requested("X-WHOIS-KEEPALIVE: 1\r\nAS13335\r\n"));
+        assert!(keepalive_requested("x-whois-keepalive:on\nAS13335\n"));
+        assert!(!keepalive_requested("X-WHOIS-KEEPALIVE: 0\r\nAS13335\r\n"));
+        assert!(!keepalive_requested("AS13335\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_next_request_on_kept_alive_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        client.write_all(b"AS13335\r\n").await.unwrap();
+        assert_eq!(next_request(&mut server, &test_limits()).await.as_deref(), Some("AS13335\r\n"));
+
+        // An idle client is let go after the keep-alive timeout
+        assert_eq!(next_request(&mut server, &test_limits()).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_respond_marks_special_purpose_addresses() {
+        let addr: SocketAddr = "127.0.0.1:4343".parse().unwrap();
+        let (response, query_type) = respond("192.0.2.1", "192.0.2.1\r\n", addr, &ColorProtocol::new()).await;
+        assert!(matches!(query_type, QueryType::IPv4(_)));
+        assert!(response.contains("% Special-purpose address: 192.0.2.1 is in 192.0.2.0/24\n"));
+    }
+
+    /// Read from a kept-alive connection up to the end of a response
+    async fn read_response(client: &mut TcpStream) -> String {
+        let mut response = Vec::new();
+        let mut buffer = [0u8; 4096];
+        while !response.ends_with(KEEPALIVE_TERMINATOR.as_bytes()) {
+            let n = client.read(&mut buffer).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            response.extend_from_slice(&buffer[..n]);
         }
+        String::from_utf8_lossy(&response).into_owned()
     }
 
-    // According to RFC 3912, the server MUST close the connection, not wait for client
-    log_debug!("Closing connection from server side (RFC 3912 requirement)");
+    #[tokio::test]
+    async fn test_kept_alive_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        // The first query was paid for when the connection was accepted,
+        // the bucket has room for one more
+        let limiter = RateLimiter::new(RateLimitConfig { per_minute: 1, burst: 1, ipv6_prefix: 64 });
+        let stats = stats_state_with(Arc::new(MemoryStore::new()), TotalStats::default());
+        let addr: SocketAddr = "192.0.2.9:4343".parse().unwrap();
+        let handler = tokio::spawn(async move {
+            handle_connection(server, addr, test_limits(), &limiter, None, stats, false).await
+        });
+
+        client.write_all(b"X-WHOIS-KEEPALIVE: 1\r\nHELP\r\n").await.unwrap();
+        assert!(read_response(&mut client).await.ends_with(KEEPALIVE_TERMINATOR));
+        client.write_all(b"HELP\r\n").await.unwrap();
+        assert!(read_response(&mut client).await.ends_with(KEEPALIVE_TERMINATOR));
+
+        // Blank requests are free and answered with the terminator alone
+        client.write_all(b"\r\n").await.unwrap();
+        assert_eq!(read_response(&mut client).await, KEEPALIVE_TERMINATOR);
+
+        // So are capability probes, which leave the connection open
+        client.write_all(b"X-WHOIS-COLOR-PROBE: v1.0\r\n").await.unwrap();
+        let capability = read_response(&mut client).await;
+        assert!(capability.starts_with("X-WHOIS-COLOR-SUPPORT:"));
+        assert!(capability.ends_with(KEEPALIVE_TERMINATOR));
+
+        client.write_all(b"HELP\r\n").await.unwrap();
+        assert!(read_response(&mut client).await.starts_with("% rate limit exceeded, retry after "));
+        handler.await.unwrap().unwrap();
+    }
 
-    // First shutdown write side to ensure all data is transmitted
-    if let Err(e) = stream.shutdown().await {
-        log_warn!("Error shutting down connection: {}", e);
+    #[tokio::test]
+    async fn test_read_request_too_large() {
+        let (mut client, mut server) = duplex(1024);
+        client.write_all(&[b'a'; 100]).await.unwrap();
+
+        let result = read_request(&mut server, &test_limits()).await;
+        assert_eq!(result, Err(DropReason::RequestTooLarge));
     }
 
-    // Drop the stream to forcibly close the connection
-    drop(stream);
+    #[tokio::test]
+    async fn test_read_request_idle_timeout() {
+        let (mut client, mut server) = duplex(1024);
+        client.write_all(b"exam").await.unwrap();
 
-    Ok(())
-}
+        let result = read_request(&mut server, &test_limits()).await;
+        assert_eq!(result, Err(DropReason::IdleTimeout));
+        drop(client);
+    }
 
-/// Process a WHOIS query and return the response (for use by SSH server and other modules)
-#[allow(dead_code)]
-pub async fn handle_query(
-    query: &str,
-    query_type: &QueryType,
-    color_scheme: Option<ColorScheme>,
-    client_ip: Option<String>
-) -> Result<String> {
-    crate::core::process_query(query, query_type, color_scheme, client_ip).await
+    #[tokio::test]
+    async fn test_read_request_trickle_hits_deadline() {
+        let (mut client, mut server) = duplex(1024);
+
+        // Send one byte every 50ms: never idle, but never finishes either
+        let writer = tokio::spawn(async move {
+            for _ in 0..20 {
+                if client.write_all(b"a").await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        });
+
+        let result = read_request(&mut server, &test_limits()).await;
+        assert_eq!(result, Err(DropReason::ReadTimeout));
+        writer.abort();
+    }
 }
@@ -1,6 +1,11 @@
 mod async_server;
 pub mod connection;
+pub mod rate_limit;
+pub mod shadow;
+pub mod tarpit;
 mod utils;
 
 pub use async_server::run_async_server;
+pub use connection::ConnectionLimits;
+pub use rate_limit::{RateLimitConfig, RateLimiter};
 pub use utils::create_dump_dir_if_needed;
@@ -0,0 +1,306 @@
+// WHOIS Server - Rate Limiting
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Per-client token-bucket rate limiting for the TCP server
+//!
+//! Every client has a bucket of `burst` tokens that refills at `per_minute`
+//! tokens a minute, and each connection takes one token, a batch one more
+//! for every query after the first. IPv6 clients are
+//! grouped by prefix (a /64 by default), as a single host can rotate through
+//! the addresses of its whole subnet. Clients over their limit get a
+//! `% rate limit exceeded` response telling them when to retry instead of a
+//! silently dropped connection. A batch costing more than the whole burst
+//! could never be paid for and is refused outright. Loopback clients are
+//! never limited.
+//!
+//! With a distributed cache backend, tokens are counted in the shared store
+//! instead so that a client is limited across all instances; the local
+//! buckets are only used while the store is unreachable.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::log_warn;
+use crate::storage::cache::{CacheStore, distributed_cache_store};
+
+/// Buckets are pruned once this many clients are tracked
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Rate limit settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// Connections per minute a client may sustain (0 disables rate limiting)
+    pub per_minute: u32,
+    /// Connections a client may open at once after being idle
+    pub burst: u32,
+    /// Prefix length IPv6 clients are aggregated to
+    pub ipv6_prefix: u8,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self { per_minute: 60, burst: 20, ipv6_prefix: 64 }
+    }
+}
+
+/// Why a client's request was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimited {
+    /// The bucket holds too few tokens; enough are back after this long
+    RetryAfter(Duration),
+    /// The request costs more tokens than the bucket can ever hold
+    OverBurst { cost: u32, burst: u32 },
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets of all recently seen clients
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self { config, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.per_minute > 0
+    }
+
+    /// Tokens added per second
+    fn refill_rate(&self) -> f64 {
+        (self.config.per_minute as f64) / 60.0
+    }
+
+    /// Bucket key of a client: IPv4 addresses as-is, IPv6 masked to the prefix
+    fn client_key(&self, ip: IpAddr) -> IpAddr {
+        match ip {
+            IpAddr::V4(_) => ip,
+            IpAddr::V6(v6) => {
+                if let Some(v4) = v6.to_ipv4_mapped() {
+                    return IpAddr::V4(v4);
+                }
+                let prefix = self.config.ipv6_prefix.min(128) as u32;
+                let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+                IpAddr::V6(Ipv6Addr::from(u128::from(v6) & mask))
+            }
+        }
+    }
+
+    /// Take a token for a new connection from `ip`
+    ///
+    /// Returns how long the client has to wait when its bucket is empty.
+    pub async fn check(&self, ip: IpAddr) -> Result<(), RateLimited> {
+        self.take(ip, 1).await
+    }
+
+    /// Take `cost` tokens at once for the queries of a request, or none if
+    /// the bucket holds fewer
+    ///
+    /// A request costing more than the burst is refused without taking any.
+    pub async fn take(&self, ip: IpAddr, cost: u32) -> Result<(), RateLimited> {
+        if let Some(decided) = self.precheck(ip, cost) {
+            return decided;
+        }
+        if let Some(store) = distributed_cache_store() {
+            match self.take_shared(store.as_ref(), ip, cost, SystemTime::now()).await {
+                Ok(taken) => return taken,
+                Err(e) => log_warn!("Shared rate limit counter unavailable, using local buckets: {}", e),
+            }
+        }
+        self.take_at(ip, cost, Instant::now())
+    }
+
+    /// The outcome of requests that need no bucket: unlimited clients,
+    /// free requests and requests larger than the burst
+    fn precheck(&self, ip: IpAddr, cost: u32) -> Option<Result<(), RateLimited>> {
+        if !self.is_enabled() || ip.is_loopback() || cost == 0 {
+            return Some(Ok(()));
+        }
+        let burst = self.config.burst.max(1);
+        (cost > burst).then_some(Err(RateLimited::OverBurst { cost, burst }))
+    }
+
+    /// Count `cost` tokens against the client's counter in the shared store
+    ///
+    /// Instances cannot share a token bucket through counters, so the shared
+    /// limit is a fixed window as long as a full burst takes to refill, in
+    /// which a client may spend `burst` tokens. Tokens counted by a refused
+    /// request stay spent until the window ends.
+    async fn take_shared(
+        &self,
+        store: &dyn CacheStore,
+        ip: IpAddr,
+        cost: u32,
+        now: SystemTime,
+    ) -> anyhow::Result<Result<(), RateLimited>> {
+        let burst = self.config.burst.max(1) as u64;
+        let window = (burst * 60).div_ceil(self.config.per_minute as u64).max(1);
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let start = now.as_secs() / window * window;
+        let remaining = Duration::from_secs(start + window).saturating_sub(now);
+
+        let key = format!("ratelimit:{}:{}", self.client_key(ip), start);
+        let mut count = 0;
+        for _ in 0..cost {
+            count = store.incr(&key, remaining.max(Duration::from_secs(1))).await?;
+        }
+        if count > burst {
+            Ok(Err(RateLimited::RetryAfter(remaining)))
+        } else {
+            Ok(Ok(()))
+        }
+    }
+
+    fn take_at(&self, ip: IpAddr, cost: u32, now: Instant) -> Result<(), RateLimited> {
+        if let Some(decided) = self.precheck(ip, cost) {
+            return decided;
+        }
+
+        let rate = self.refill_rate();
+        let burst = self.config.burst.max(1) as f64;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= PRUNE_THRESHOLD {
+            // Buckets that have refilled completely are the same as new ones
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate < burst
+            });
+        }
+
+        let bucket = buckets.entry(self.client_key(ip)).or_insert(Bucket { tokens: burst, updated: now });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+
+        let cost = cost as f64;
+        if bucket.tokens >= cost {
+            bucket.tokens -= cost;
+            Ok(())
+        } else {
+            Err(RateLimited::RetryAfter(Duration::from_secs_f64((cost - bucket.tokens) / rate)))
+        }
+    }
+}
+
+/// Response sent to a client over its limit
+pub fn rate_limit_message(limited: RateLimited) -> String {
+    match limited {
+        RateLimited::RetryAfter(retry_after) => {
+            let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            format!("% rate limit exceeded, retry after {} seconds\r\n", seconds)
+        }
+        RateLimited::OverBurst { cost, burst } => {
+            format!("% rate limit exceeded, a request may cost at most {} queries (this one costs {})\r\n", burst, cost)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_minute: u32, burst: u32) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig { per_minute, burst, ipv6_prefix: 64 })
+    }
+
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = limiter(60, 3);
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.take_at(ip, 1, start).is_ok());
+        }
+        let limited = limiter.take_at(ip, 1, start).unwrap_err();
+        assert_eq!(rate_limit_message(limited), "% rate limit exceeded, retry after 1 seconds\r\n");
+
+        // One token per second at 60/min; other clients are unaffected
+        assert!(limiter.take_at(ip, 1, start + Duration::from_secs(1)).is_ok());
+        assert!(limiter.take_at(ip, 1, start + Duration::from_secs(1)).is_err());
+        assert!(limiter.take_at("192.0.2.2".parse().unwrap(), 1, start).is_ok());
+    }
+
+    #[test]
+    fn test_batch_cost() {
+        let limiter = limiter(60, 5);
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let start = Instant::now();
+
+        // A batch takes all its tokens or none
+        assert!(limiter.take_at(ip, 3, start).is_ok());
+        assert_eq!(limiter.take_at(ip, 3, start).unwrap_err(), RateLimited::RetryAfter(Duration::from_secs(1)));
+        assert!(limiter.take_at(ip, 2, start).is_ok());
+        assert!(limiter.take_at(ip, 0, start).is_ok());
+        assert!(limiter.take_at(ip, 1, start).is_err());
+
+        // Larger than the burst, a batch can never be paid for and takes nothing
+        let other: IpAddr = "192.0.2.2".parse().unwrap();
+        let limited = limiter.take_at(other, 20, start).unwrap_err();
+        assert_eq!(limited, RateLimited::OverBurst { cost: 20, burst: 5 });
+        assert_eq!(
+            rate_limit_message(limited),
+            "% rate limit exceeded, a request may cost at most 5 queries (this one costs 20)\r\n"
+        );
+        assert!(limiter.take_at(other, 5, start).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_shared_counter() {
+        use crate::storage::cache::KvCacheStore;
+        use crate::storage::kv::MemoryStore;
+        use std::sync::Arc;
+
+        // Two instances counting in one store share the client's burst
+        let store = KvCacheStore::new(Arc::new(MemoryStore::new()));
+        let (first, second) = (limiter(60, 3), limiter(60, 3));
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_002);
+
+        assert_eq!(first.take_shared(&store, ip, 2, now).await.unwrap(), Ok(()));
+        assert_eq!(second.take_shared(&store, ip, 1, now).await.unwrap(), Ok(()));
+        // The 3s window started at 1_000_002 and ends a second after it
+        let refused = first.take_shared(&store, ip, 1, now + Duration::from_secs(2)).await.unwrap();
+        assert_eq!(refused, Err(RateLimited::RetryAfter(Duration::from_secs(1))));
+        assert_eq!(second.take_shared(&store, "192.0.2.2".parse().unwrap(), 3, now).await.unwrap(), Ok(()));
+
+        // The next window starts with a fresh counter
+        let later = now + Duration::from_secs(3);
+        assert_eq!(second.take_shared(&store, ip, 3, later).await.unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_ipv6_aggregation() {
+        let limiter = limiter(6, 1);
+        let start = Instant::now();
+
+        assert!(limiter.take_at("2001:db8:1:2::1".parse().unwrap(), 1, start).is_ok());
+        let limited = limiter.take_at("2001:db8:1:2:ffff::9".parse().unwrap(), 1, start).unwrap_err();
+        assert_eq!(limited, RateLimited::RetryAfter(Duration::from_secs(10)));
+        assert!(limiter.take_at("2001:db8:1:3::1".parse().unwrap(), 1, start).is_ok());
+
+        // IPv4-mapped addresses share the IPv4 bucket
+        assert!(limiter.take_at("198.51.100.7".parse().unwrap(), 1, start).is_ok());
+        assert!(limiter.take_at("::ffff:198.51.100.7".parse().unwrap(), 1, start).is_err());
+    }
+
+    #[test]
+    fn test_disabled_and_loopback() {
+        let start = Instant::now();
+        let disabled = limiter(0, 1);
+        let enabled = limiter(1, 1);
+        for _ in 0..5 {
+            assert!(disabled.take_at("192.0.2.1".parse().unwrap(), 1, start).is_ok());
+            assert!(enabled.take_at("127.0.0.1".parse().unwrap(), 1, start).is_ok());
+            assert!(enabled.take_at("::1".parse().unwrap(), 1, start).is_ok());
+        }
+    }
+}
@@ -0,0 +1,247 @@
+// WHOIS Server - Shadow Traffic
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Shadow traffic for validating a new release against real queries
+//!
+//! When a shadow server is configured, a sample of the queries answered by
+//! this instance is replayed against it after the client has received its
+//! response. The shadow response is compared with the one that was served and
+//! mismatches are logged, so a canary instance running a new version can be
+//! checked against production traffic without affecting clients.
+//!
+//! Comment lines (`%`) are ignored in the comparison since banners differ
+//! between versions, and queries whose answers change on every call (random,
+//! live measurement, side effects) are never mirrored.
+
+use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+
+use crate::core::{QueryType, is_cacheable_query};
+use crate::{log_debug, log_info, log_warn};
+
+/// Upper bound on shadow requests in flight; further samples are skipped
+const MAX_SHADOW_IN_FLIGHT: usize = 32;
+
+/// Longest line excerpt included in a mismatch log message
+const MAX_EXCERPT_LEN: usize = 120;
+
+/// Shadow traffic settings, set once at startup
+#[derive(Debug, Clone)]
+pub struct ShadowConfig {
+    /// Address of the instance receiving mirrored queries
+    pub server: String,
+    /// Percentage of queries to mirror (0-100)
+    pub percent: f64,
+    /// Time allowed for the shadow instance to answer
+    pub timeout: Duration,
+}
+
+static SHADOW_CONFIG: OnceLock<ShadowConfig> = OnceLock::new();
+
+static SHADOW_STATE: Lazy<ShadowState> = Lazy::new(|| ShadowState {
+    permits: Arc::new(Semaphore::new(MAX_SHADOW_IN_FLIGHT)),
+    mirrored: AtomicU64::new(0),
+    matched: AtomicU64::new(0),
+    mismatched: AtomicU64::new(0),
+    errors: AtomicU64::new(0),
+    skipped: AtomicU64::new(0),
+});
+
+struct ShadowState {
+    permits: Arc<Semaphore>,
+    mirrored: AtomicU64,
+    matched: AtomicU64,
+    mismatched: AtomicU64,
+    errors: AtomicU64,
+    skipped: AtomicU64,
+}
+
+/// Shadow traffic counters exposed through the stats API
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ShadowStatsEntry {
+    pub enabled: bool,
+    pub mirrored: u64,
+    pub matched: u64,
+    pub mismatched: u64,
+    pub errors: u64,
+    pub skipped: u64,
+}
+
+/// Enable shadow traffic with the given settings
+pub fn init_shadow(config: ShadowConfig) {
+    log_info!("Mirroring {}% of queries to shadow server {}", config.percent, config.server);
+    let _ = SHADOW_CONFIG.set(config);
+}
+
+fn should_sample(percent: f64) -> bool {
+    percent >= 100.0 || (percent > 0.0 && rand::random::<f64>() * 100.0 < percent)
+}
+
+/// Replay a served request against the shadow server in the background
+///
+/// `request` is the raw request as received (including WHOIS-COLOR headers)
+/// and `served` the response that was sent to the client.
+pub fn mirror(request: &str, query: &str, query_type: &QueryType, served: &str) {
+    let Some(config) = SHADOW_CONFIG.get() else {
+        return;
+    };
+    if !is_cacheable_query(query_type) || !should_sample(config.percent) {
+        return;
+    }
+
+    let Ok(permit) = SHADOW_STATE.permits.clone().try_acquire_owned() else {
+        SHADOW_STATE.skipped.fetch_add(1, Ordering::Relaxed);
+        return;
+    };
+
+    let request = request.to_string();
+    let query = query.to_string();
+    let served = served.to_string();
+    tokio::spawn(async move {
+        let _permit = permit;
+        SHADOW_STATE.mirrored.fetch_add(1, Ordering::Relaxed);
+
+        match fetch_shadow(config, &request).await {
+            Ok(shadow) => match diff_summary(&served, &shadow) {
+                None => {
+                    SHADOW_STATE.matched.fetch_add(1, Ordering::Relaxed);
+                }
+                Some(summary) => {
+                    SHADOW_STATE.mismatched.fetch_add(1, Ordering::Relaxed);
+                    log_warn!("Shadow mismatch for {}: {}", query, summary);
+                }
+            },
+            Err(e) => {
+                SHADOW_STATE.errors.fetch_add(1, Ordering::Relaxed);
+                log_debug!("Shadow request for {} failed: {}", query, e);
+            }
+        }
+    });
+}
+
+async fn fetch_shadow(config: &ShadowConfig, request: &str) -> Result<String> {
+    let exchange = async {
+        let mut stream = TcpStream::connect(&config.server).await?;
+        stream.write_all(request.as_bytes()).await?;
+        if !request.ends_with("\r\n") {
+            stream.write_all(b"\r\n").await?;
+        }
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok::<_, anyhow::Error>(String::from_utf8_lossy(&response).into_owned())
+    };
+
+    tokio::time::timeout(config.timeout, exchange)
+        .await
+        .map_err(|_| anyhow!("timed out after {}s", config.timeout.as_secs()))?
+}
+
+/// Response lines that take part in the comparison
+fn comparable_lines(response: &str) -> Vec<&str> {
+    response
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty() && !line.starts_with('%'))
+        .collect()
+}
+
+fn excerpt(line: Option<&&str>) -> String {
+    match line {
+        Some(line) => line.chars().take(MAX_EXCERPT_LEN).collect(),
+        None => "<missing>".to_string(),
+    }
+}
+
+/// Describe how the shadow response differs from the served one, if it does
+fn diff_summary(served: &str, shadow: &str) -> Option<String> {
+    let served = comparable_lines(served);
+    let shadow = comparable_lines(shadow);
+    if served == shadow {
+        return None;
+    }
+
+    let longest = served.len().max(shadow.len());
+    let differing = (0..longest).filter(|&i| served.get(i) != shadow.get(i)).count();
+    let first = (0..longest).find(|&i| served.get(i) != shadow.get(i)).unwrap_or(0);
+
+    Some(format!(
+        "{} of {} lines differ ({} served, {} shadow), first at line {}: served {:?}, shadow {:?}",
+        differing,
+        longest,
+        served.len(),
+        shadow.len(),
+        first + 1,
+        excerpt(served.get(first)),
+        excerpt(shadow.get(first))
+    ))
+}
+
+/// Snapshot of shadow traffic counters
+pub fn shadow_stats() -> ShadowStatsEntry {
+    ShadowStatsEntry {
+        enabled: SHADOW_CONFIG.get().is_some(),
+        mirrored: SHADOW_STATE.mirrored.load(Ordering::Relaxed),
+        matched: SHADOW_STATE.matched.load(Ordering::Relaxed),
+        mismatched: SHADOW_STATE.mismatched.load(Ordering::Relaxed),
+        errors: SHADOW_STATE.errors.load(Ordering::Relaxed),
+        skipped: SHADOW_STATE.skipped.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comment_lines_are_ignored() {
+        let served = "% Akaere NetWorks Whois Server\r\n\r\naut-num: AS13335\r\n";
+        let shadow = "% Akaere NetWorks Whois Server v2\r\naut-num: AS13335\r\n";
+        assert_eq!(diff_summary(served, shadow), None);
+    }
+
+    #[test]
+    fn test_diff_reports_first_difference() {
+        let served = "aut-num: AS13335\r\nas-name: CLOUDFLARENET\r\n";
+        let shadow = "aut-num: AS13335\r\nas-name: CLOUDFLARE\r\nsource: RIPE\r\n";
+        let summary = diff_summary(served, shadow).unwrap();
+        assert!(summary.starts_with("2 of 3 lines differ (2 served, 3 shadow), first at line 2"));
+        assert!(summary.contains("CLOUDFLARENET"));
+    }
+
+    #[test]
+    fn test_sampling_bounds() {
+        assert!(should_sample(100.0));
+        assert!(!should_sample(0.0));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_shadow_replays_request() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = ShadowConfig {
+            server: listener.local_addr().unwrap().to_string(),
+            percent: 100.0,
+            timeout: Duration::from_secs(5),
+        };
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = [0u8; 256];
+            let n = socket.read(&mut buffer).await.unwrap();
+            socket.write_all(b"aut-num: AS13335\r\n").await.unwrap();
+            String::from_utf8_lossy(&buffer[..n]).into_owned()
+        });
+
+        let response = fetch_shadow(&config, "X-WHOIS-COLOR: ripe\r\nAS13335\r\n").await.unwrap();
+        assert_eq!(response, "aut-num: AS13335\r\n");
+        assert_eq!(server.await.unwrap(), "X-WHOIS-COLOR: ripe\r\nAS13335\r\n");
+    }
+}
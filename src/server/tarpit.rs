@@ -0,0 +1,267 @@
+// WHOIS Server - Tarpit
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Tarpit for clients over their rate limit
+//!
+//! With `--tarpit`, a client the rate limiter turns away is not told so.
+//! Instead it gets a plausible but invented answer to its query, written one
+//! line at a time with a delay in between, so scrapers waste their time and
+//! connection slots. Every answer carries a random watermark in its handles,
+//! maintainer and registry IDs; the watermark is logged together with the
+//! client and query to a separate JSON-lines file (`--tarpit-log`), so data
+//! that resurfaces elsewhere can be traced back to the client that scraped it.
+//!
+//! At most `MAX_TARPITTED` connections are held at once; further limited
+//! clients get the usual rate limit response.
+
+use chrono::{Duration as ChronoDuration, NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::core::sanitize::sanitize_query;
+use crate::core::{DropReason, QueryType, StatsState, analyze_query, record_dropped_connection};
+use crate::server::connection::{ConnectionLimits, read_request};
+use crate::{log_debug, log_error, log_info, log_warn};
+
+/// Upper bound on connections held in the tarpit
+const MAX_TARPITTED: usize = 64;
+
+/// Tarpit settings, set once at startup
+#[derive(Debug, Clone)]
+pub struct TarpitConfig {
+    /// Delay before each line of a tarpit response
+    pub line_delay: Duration,
+    /// JSON-lines file tarpit sessions are appended to
+    pub log_file: PathBuf,
+}
+
+static TARPIT_CONFIG: OnceLock<TarpitConfig> = OnceLock::new();
+
+static TARPIT_PERMITS: Lazy<Arc<Semaphore>> = Lazy::new(|| Arc::new(Semaphore::new(MAX_TARPITTED)));
+
+/// One tarpit session in the tarpit log
+#[derive(Debug, Serialize)]
+struct TarpitLogEntry {
+    timestamp: String,
+    client: IpAddr,
+    query: String,
+    query_type: String,
+    watermark: String,
+    lines_sent: usize,
+    /// Whether the client stayed until the whole response was written
+    completed: bool,
+    duration_ms: u64,
+}
+
+/// Enable the tarpit with the given settings
+pub fn init_tarpit(config: TarpitConfig) {
+    log_info!(
+        "Tarpitting rate-limited clients ({} ms per line, log {})",
+        config.line_delay.as_millis(),
+        config.log_file.display()
+    );
+    let _ = TARPIT_CONFIG.set(config);
+}
+
+/// Reserve a tarpit slot for a rate-limited client
+///
+/// Returns `None` when the tarpit is disabled or full, in which case the
+/// client gets the usual rate limit response.
+pub fn admit() -> Option<OwnedSemaphorePermit> {
+    TARPIT_CONFIG.get()?;
+    TARPIT_PERMITS.clone().try_acquire_owned().ok()
+}
+
+/// Fixed-width watermark embedded in a decoy response
+fn new_watermark() -> u32 {
+    rand::random()
+}
+
+/// Date in 2000-2021 derived from the watermark, so decoy objects look aged
+fn decoy_date(watermark: u32, offset_days: i64) -> String {
+    let base = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap_or_default();
+    let date = base + ChronoDuration::days(((watermark % 8000) as i64) + offset_days);
+    format!("{}T{:02}:{:02}:{:02}Z", date, watermark % 24, (watermark >> 8) % 60, (watermark >> 16) % 60)
+}
+
+/// Plausible but invented answer to a query, carrying the watermark
+fn decoy_response(query: &str, query_type: &QueryType, watermark: u32) -> String {
+    let mark = format!("{:08X}", watermark);
+    let handle = format!("{}-NIC", mark);
+    let created = decoy_date(watermark, 0);
+    let changed = decoy_date(watermark, 900);
+
+    match query_type {
+        QueryType::Domain(domain) => {
+            let expires = decoy_date(watermark, 9000);
+            [
+                format!("Domain Name: {}", domain.to_uppercase()),
+                format!("Registry Domain ID: {}_DOMAIN-REG", watermark),
+                "Registrar WHOIS Server: whois.registrar-services.net".to_string(),
+                format!("Updated Date: {}", changed),
+                format!("Creation Date: {}", created),
+                format!("Registry Expiry Date: {}", expires),
+                "Registrar: Registrar Services LLC".to_string(),
+                format!("Registrar IANA ID: {}", 1000 + (watermark % 2000)),
+                format!("Registrant Contact ID: {}", handle),
+                "Domain Status: clientTransferProhibited https://icann.org/epp#clientTransferProhibited".to_string(),
+                format!("Name Server: NS1.{}.DNS-HOSTING.NET", mark),
+                format!("Name Server: NS2.{}.DNS-HOSTING.NET", mark),
+                "DNSSEC: unsigned".to_string(),
+            ].join("\n") + "\n"
+        }
+        QueryType::ASN(asn) => {
+            let asn = asn.to_uppercase();
+            [
+                format!("aut-num:        {}", asn),
+                format!("as-name:        NET-{}", mark),
+                "descr:          Regional Network Services".to_string(),
+                "status:         ASSIGNED".to_string(),
+                format!("admin-c:        {}", handle),
+                format!("tech-c:         {}", handle),
+                format!("mnt-by:         MNT-{}", mark),
+                format!("created:        {}", created),
+                format!("last-modified:  {}", changed),
+            ].join("\n") + "\n"
+        }
+        QueryType::IPv4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            [
+                format!("inetnum:        {}.{}.{}.0 - {}.{}.{}.255", a, b, c, a, b, c),
+                format!("netname:        NET-{}", mark),
+                "country:        ZZ".to_string(),
+                format!("admin-c:        {}", handle),
+                format!("tech-c:         {}", handle),
+                "status:         ASSIGNED PA".to_string(),
+                format!("mnt-by:         MNT-{}", mark),
+                format!("created:        {}", created),
+                format!("last-modified:  {}", changed),
+            ].join("\n") + "\n"
+        }
+        QueryType::IPv6(ip) => {
+            let network = Ipv6Addr::from(u128::from(*ip) & (u128::MAX << 80));
+            [
+                format!("inet6num:       {}/48", network),
+                format!("netname:        NET-{}", mark),
+                "country:        ZZ".to_string(),
+                format!("admin-c:        {}", handle),
+                format!("tech-c:         {}", handle),
+                "status:         ASSIGNED".to_string(),
+                format!("mnt-by:         MNT-{}", mark),
+                format!("created:        {}", created),
+                format!("last-modified:  {}", changed),
+            ].join("\n") + "\n"
+        }
+        _ => format!("% No entries found for {}\n% Reference: {}\n", query, handle),
+    }
+}
+
+fn append_log(path: &Path, entry: &TarpitLogEntry) {
+    let Ok(line) = serde_json::to_string(entry) else {
+        return;
+    };
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        log_error!("Failed to write tarpit log {}: {}", path.display(), e);
+    }
+}
+
+/// Answer a rate-limited client slowly with watermarked decoy data
+pub async fn tarpit_connection(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    limits: ConnectionLimits,
+    stats: StatsState,
+    _permit: OwnedSemaphorePermit
+) {
+    let Some(config) = TARPIT_CONFIG.get() else {
+        return;
+    };
+    record_dropped_connection(&stats, DropReason::RateLimited);
+
+    let request = match read_request(&mut stream, &limits).await {
+        Ok(request) => request,
+        Err(reason) => {
+            log_debug!("Tarpitted client {} sent no request: {}", addr, reason.as_str());
+            return;
+        }
+    };
+    let query_line = request
+        .trim()
+        .lines()
+        .find(|line| !line.trim().to_uppercase().starts_with("X-WHOIS-"))
+        .unwrap_or("");
+    let query = sanitize_query(query_line);
+    if query.is_empty() {
+        return;
+    }
+
+    let query_type = analyze_query(&query);
+    let watermark = new_watermark();
+    let response = decoy_response(&query, &query_type, watermark);
+    log_warn!("Tarpitting {} for query {} (watermark {:08X})", addr, query, watermark);
+
+    let start = Instant::now();
+    let mut lines_sent = 0;
+    for line in response.lines() {
+        tokio::time::sleep(config.line_delay).await;
+        let write = stream.write_all(format!("{}\n", line).as_bytes());
+        match tokio::time::timeout(limits.write_timeout, write).await {
+            Ok(Ok(())) => lines_sent += 1,
+            _ => break,
+        }
+    }
+    let completed = lines_sent == response.lines().count();
+    if completed {
+        let _ = stream.shutdown().await;
+    }
+
+    append_log(&config.log_file, &TarpitLogEntry {
+        timestamp: Utc::now().to_rfc3339(),
+        client: addr.ip(),
+        query,
+        query_type: crate::core::telemetry::query_type_to_string(&query_type),
+        watermark: format!("{:08X}", watermark),
+        lines_sent,
+        completed,
+        duration_ms: start.elapsed().as_millis() as u64,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoy_responses_carry_watermark() {
+        let watermark = 0x1a2b3c4d;
+        for query in ["example.com", "AS64500", "192.0.2.77", "2001:db8:1234:5678::1"] {
+            let response = decoy_response(query, &analyze_query(query), watermark);
+            assert!(response.contains("1A2B3C4D"), "{}", response);
+            assert!(!response.to_lowercase().contains("tarpit"));
+        }
+
+        let inetnum = decoy_response("192.0.2.77", &analyze_query("192.0.2.77"), watermark);
+        assert!(inetnum.starts_with("inetnum:        192.0.2.0 - 192.0.2.255\n"));
+        let inet6num = decoy_response("2001:db8:1234:5678::1", &analyze_query("2001:db8:1234:5678::1"), watermark);
+        assert!(inet6num.starts_with("inet6num:       2001:db8:1234::/48\n"));
+
+        // Dates are stable for a watermark and objects change after creation
+        assert_eq!(decoy_date(watermark, 0), decoy_date(watermark, 0));
+        assert!(decoy_date(watermark, 0) < decoy_date(watermark, 900));
+    }
+}
@@ -0,0 +1,323 @@
+// WHOIS Server - Abuse Contact Lookup
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Abuse contacts for an IP address or ASN (`-ABUSE`)
+//!
+//! The abuse mailbox comes from RIPEstat's abuse-contact-finder, which
+//! covers all five RIRs. The RDAP object of the resource (found through the
+//! rdap.org bootstrap redirector) adds the abuse role's handle and phone,
+//! the holding organisation, maintainers and the administrative, technical
+//! and NOC contacts to escalate to when the abuse desk does not answer.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::services::registrar::vcard_value;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use crate::log_debug;
+
+const RIPESTAT_DATA_BASE: &str = "https://stat.ripe.net/data/";
+const RDAP_BOOTSTRAP_BASE: &str = "https://rdap.org/";
+/// RDAP roles of contacts to escalate to
+const ESCALATION_ROLES: &[&str] = &["noc", "technical", "administrative"];
+
+/// Resource an abuse contact is looked up for
+#[derive(Debug, PartialEq, Eq)]
+enum Resource {
+    Ip(IpAddr),
+    Asn(u32),
+}
+
+impl Resource {
+    fn parse(query: &str) -> Result<Self> {
+        let query = query.trim();
+        if let Ok(ip) = query.parse::<IpAddr>() {
+            return Ok(Self::Ip(ip));
+        }
+        let digits = query.get(..2).filter(|p| p.eq_ignore_ascii_case("AS")).map_or(query, |_| &query[2..]);
+        digits
+            .parse()
+            .map(Self::Asn)
+            .map_err(|_| anyhow!("Usage: <IP address>-ABUSE or AS<number>-ABUSE"))
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Self::Ip(ip) => ip.to_string(),
+            Self::Asn(asn) => format!("AS{}", asn),
+        }
+    }
+
+    fn rdap_path(&self) -> String {
+        match self {
+            Self::Ip(ip) => format!("ip/{}", ip),
+            Self::Asn(asn) => format!("autnum/{}", asn),
+        }
+    }
+}
+
+/// Mailboxes reported by RIPEstat abuse-contact-finder
+#[derive(Debug, Default)]
+struct AbuseFinder {
+    contacts: Vec<String>,
+    rir: Option<String>,
+}
+
+/// An RDAP entity and the roles it holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Contact {
+    handle: Option<String>,
+    name: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+    roles: Vec<String>,
+}
+
+impl Contact {
+    fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    fn is_maintainer(&self) -> bool {
+        self.handle.as_deref().is_some_and(|h| h.to_ascii_uppercase().ends_with("-MNT"))
+    }
+}
+
+/// Summary of the resource's RDAP object
+#[derive(Debug, Default)]
+struct RdapSummary {
+    handle: Option<String>,
+    name: Option<String>,
+    contacts: Vec<Contact>,
+}
+
+/// Collect the entities of an RDAP object, including nested ones (ARIN
+/// lists the abuse role under the registrant organisation)
+fn collect_contacts(object: &Value, contacts: &mut Vec<Contact>) {
+    for entity in object["entities"].as_array().into_iter().flatten() {
+        let contact = Contact {
+            handle: entity["handle"].as_str().map(str::to_string),
+            name: vcard_value(entity, "fn").map(str::to_string),
+            email: vcard_value(entity, "email").map(str::to_string),
+            phone: vcard_value(entity, "tel").map(|tel| tel.trim_start_matches("tel:").to_string()),
+            roles: entity["roles"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|r| r.as_str().map(str::to_string))
+                .collect(),
+        };
+        if !contacts.contains(&contact) {
+            contacts.push(contact);
+        }
+        collect_contacts(entity, contacts);
+    }
+}
+
+fn parse_rdap(object: &Value) -> RdapSummary {
+    let mut contacts = Vec::new();
+    collect_contacts(object, &mut contacts);
+    RdapSummary {
+        handle: object["handle"].as_str().map(str::to_string),
+        name: object["name"].as_str().map(str::to_string),
+        contacts,
+    }
+}
+
+async fn fetch_abuse_finder(client: &reqwest::Client, resource: &Resource) -> Result<AbuseFinder> {
+    let url = format!("{}abuse-contact-finder/data.json", api_base(RIPESTAT_DATA_BASE));
+    let response = client.get(&url).query(&[("resource", resource.label())]).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("RIPEstat abuse-contact-finder failed: HTTP {}", response.status()));
+    }
+    let body: Value = response.limited_json().await?;
+    Ok(AbuseFinder {
+        contacts: body["data"]["abuse_contacts"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c.as_str().map(str::to_string))
+            .collect(),
+        rir: body["data"]["authoritative_rir"].as_str().map(str::to_ascii_uppercase),
+    })
+}
+
+async fn fetch_rdap(client: &reqwest::Client, resource: &Resource) -> Result<RdapSummary> {
+    let url = format!("{}{}", api_base(RDAP_BOOTSTRAP_BASE), resource.rdap_path());
+    let response = client.get(&url).header("Accept", "application/rdap+json").send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("RDAP lookup failed: HTTP {}", response.status()));
+    }
+    Ok(parse_rdap(&response.limited_json().await?))
+}
+
+fn format_abuse(resource: &Resource, finder: Option<&AbuseFinder>, rdap: Option<&RdapSummary>) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("% Abuse contact information for {}\n", resource.label()));
+    output.push_str("% Sources: RIPEstat abuse-contact-finder, RDAP\n\n");
+    output.push_str(&format!("resource:       {}\n", resource.label()));
+
+    let contacts = rdap.map(|r| r.contacts.as_slice()).unwrap_or_default();
+    if let Some(rdap) = rdap
+        && let Some(name) = rdap.name.as_ref().or(rdap.handle.as_ref())
+    {
+        output.push_str(&format!("netname:        {}\n", name));
+    }
+
+    // Abuse mailboxes from both sources, RIPEstat first
+    let mut mailboxes: Vec<&str> = Vec::new();
+    let rdap_mailboxes = contacts.iter().filter(|c| c.has_role("abuse")).filter_map(|c| c.email.as_deref());
+    for mailbox in finder.map(|f| f.contacts.iter().map(String::as_str)).into_iter().flatten().chain(rdap_mailboxes) {
+        if !mailboxes.iter().any(|m| m.eq_ignore_ascii_case(mailbox)) {
+            mailboxes.push(mailbox);
+        }
+    }
+    if mailboxes.is_empty() {
+        output.push_str("abuse-mailbox:  % none published\n");
+    }
+    for mailbox in &mailboxes {
+        output.push_str(&format!("abuse-mailbox:  {}\n", mailbox));
+    }
+
+    for abuse in contacts.iter().filter(|c| c.has_role("abuse")) {
+        if let Some(handle) = &abuse.handle {
+            output.push_str(&format!("abuse-c:        {}\n", handle));
+        }
+        if let Some(phone) = &abuse.phone {
+            output.push_str(&format!("abuse-phone:    {}\n", phone));
+        }
+    }
+
+    for org in contacts.iter().filter(|c| c.has_role("registrant") && !c.is_maintainer()) {
+        match (&org.handle, &org.name) {
+            (Some(handle), Some(name)) => output.push_str(&format!("org:            {} ({})\n", handle, name)),
+            (Some(value), None) | (None, Some(value)) => output.push_str(&format!("org:            {}\n", value)),
+            (None, None) => {}
+        }
+    }
+    for maintainer in contacts.iter().filter(|c| c.is_maintainer()) {
+        if let Some(handle) = &maintainer.handle {
+            output.push_str(&format!("mnt-by:         {}\n", handle));
+        }
+    }
+
+    for contact in contacts.iter().filter(|c| ESCALATION_ROLES.iter().any(|role| c.has_role(role))) {
+        let Some(id) = contact.handle.as_ref().or(contact.name.as_ref()) else {
+            continue;
+        };
+        let roles: Vec<&str> = contact.roles.iter().map(String::as_str).filter(|r| ESCALATION_ROLES.contains(r)).collect();
+        match &contact.email {
+            Some(email) => output.push_str(&format!("escalation:     {} <{}> ({})\n", id, email, roles.join(", "))),
+            None => output.push_str(&format!("escalation:     {} ({})\n", id, roles.join(", "))),
+        }
+    }
+
+    if let Some(rir) = finder.and_then(|f| f.rir.as_ref()) {
+        output.push_str(&format!("rir:            {}\n", rir));
+    }
+    let sources: Vec<&str> = [finder.map(|_| "RIPESTAT"), rdap.map(|_| "RDAP")].into_iter().flatten().collect();
+    output.push_str(&format!("source:         {}\n", sources.join(", ")));
+    output
+}
+
+/// Process an `-ABUSE` query
+pub async fn process_abuse_query(ctx: &RequestContext, query: &str) -> Result<String> {
+    let resource = Resource::parse(query)?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent("whois-server/1.0")
+        .build()?;
+
+    let (finder, rdap) = tokio::join!(fetch_abuse_finder(&client, &resource), fetch_rdap(&client, &resource));
+    let finder = match finder {
+        Ok(finder) => Some(finder),
+        Err(e) => {
+            log_debug!("[{}] abuse-contact-finder for {} failed: {}", ctx, resource.label(), e);
+            None
+        }
+    };
+    let rdap = match rdap {
+        Ok(rdap) => Some(rdap),
+        Err(e) => {
+            log_debug!("[{}] RDAP for {} failed: {}", ctx, resource.label(), e);
+            None
+        }
+    };
+    if finder.is_none() && rdap.is_none() {
+        return Err(anyhow!("No abuse contact source answered for {}", resource.label()));
+    }
+    Ok(format_abuse(&resource, finder.as_ref(), rdap.as_ref()))
+}
+
+pub struct AbuseService;
+
+#[async_trait]
+impl QueryService for AbuseService {
+    fn name(&self) -> &'static str {
+        "abuse"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-ABUSE"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "SECURITY INVESTIGATION",
+            summary: "Abuse mailbox, maintainer and escalation contacts of an IP or ASN",
+            example: "193.0.6.139-ABUSE",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "RIPEstat abuse-contact-finder, RDAP (rdap.org)"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(6 * 3600))
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_abuse_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::request_context::Frontend;
+    use crate::services::fixtures::FixtureServer;
+
+    #[test]
+    fn test_parse_resource() {
+        assert_eq!(Resource::parse("AS3333").unwrap(), Resource::Asn(3333));
+        assert_eq!(Resource::parse("as3333").unwrap(), Resource::Asn(3333));
+        assert_eq!(Resource::parse("3333").unwrap(), Resource::Asn(3333));
+        assert_eq!(Resource::parse("2001:67c:2e8::2").unwrap().rdap_path(), "ip/2001:67c:2e8::2");
+        assert!(Resource::parse("example.com").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_abuse_fixture() {
+        let mut fixtures = FixtureServer::start("abuse").await;
+        fixtures.serve(RIPESTAT_DATA_BASE, "abuse-contact-finder/data.json?resource=AS3333", "abuse_contact_finder.json").await;
+        fixtures.serve(RDAP_BOOTSTRAP_BASE, "autnum/3333", "autnum_3333.json").await;
+
+        let ctx = RequestContext::new(Frontend::Library);
+        let response = fixtures.run(process_abuse_query(&ctx, "AS3333")).await.unwrap();
+        assert!(response.contains("abuse-mailbox:  abuse@ripe.net\n"));
+        assert_eq!(response.matches("abuse-mailbox:").count(), 1);
+        assert!(response.contains("abuse-c:        AR40377-RIPE\n"));
+        assert!(response.contains("org:            ORG-RIEN1-RIPE (RIPE Network Coordination Centre)\n"));
+        assert!(response.contains("mnt-by:         RIPE-NCC-MNT\n"));
+        assert!(response.contains("escalation:     OPS4-RIPE <ops@ripe.net> (technical, administrative)\n"));
+        assert!(response.contains("rir:            RIPE\n"));
+    }
+}
@@ -17,10 +17,101 @@
  */
 
 use anyhow::Result;
-use regex::Regex;
 use serde::{ Deserialize, Serialize };
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
+use crate::core::regex_cache::CachedRegex;
 use crate::{ log_debug, log_error };
+
+/// Character template fields, each pattern capturing the field's value
+static TEMPLATE_PATTERNS: [(CachedRegex, &str); 47] = [
+    // 角色模板信息
+    (CachedRegex::new(r"角色\s*\|\s*[^=]*=\s*([^|\n\}]+)"), "character-template"),
+    (CachedRegex::new(r"作品\s*=\s*([^|\n\}]+)"), "source-work"),
+    (CachedRegex::new(r"系列\s*=\s*([^|\n\}]+)"), "series"),
+    // 声优/配音信息 (多种格式)
+    (CachedRegex::new(r"声优\s*[：=:|]\s*([^|\n\}]+)"), "voice-actor"),
+    (CachedRegex::new(r"配音\s*[：=:|]\s*([^|\n\}]+)"), "voice-actor"),
+    (CachedRegex::new(r"CV\s*[：=:|]\s*([^|\n\}]+)"), "voice-actor"),
+    (CachedRegex::new(r"日配\s*[：=:|]\s*([^|\n\}]+)"), "voice-actor-jp"),
+    (CachedRegex::new(r"中配\s*[：=:|]\s*([^|\n\}]+)"), "voice-actor-cn"),
+    // 基本信息
+    (CachedRegex::new(r"年龄\s*[：=:|]\s*([^|\n\}]+)"), "age"),
+    (CachedRegex::new(r"生日\s*[：=:|]\s*([^|\n\}]+)"), "birthday"),
+    (CachedRegex::new(r"身高\s*[：=:|]\s*([^|\n\}]+)"), "height"),
+    (CachedRegex::new(r"体重\s*[：=:|]\s*([^|\n\}]+)"), "weight"),
+    (CachedRegex::new(r"性别\s*[：=:|]\s*([^|\n\}]+)"), "gender"),
+    (CachedRegex::new(r"种族\s*[：=:|]\s*([^|\n\}]+)"), "species"),
+    (CachedRegex::new(r"血型\s*[：=:|]\s*([^|\n\}]+)"), "blood-type"),
+    // 外观特征
+    (CachedRegex::new(r"发色\s*[：=:|]\s*([^|\n\}]+)"), "hair-color"),
+    (CachedRegex::new(r"瞳色\s*[：=:|]\s*([^|\n\}]+)"), "eye-color"),
+    (CachedRegex::new(r"眼色\s*[：=:|]\s*([^|\n\}]+)"), "eye-color"),
+    (CachedRegex::new(r"头发颜色\s*[：=:|]\s*([^|\n\}]+)"), "hair-color"),
+    (CachedRegex::new(r"服装\s*[：=:|]\s*([^|\n\}]+)"), "clothing"),
+    (CachedRegex::new(r"装扮\s*[：=:|]\s*([^|\n\}]+)"), "appearance"),
+    // 身份和角色信息
+    (CachedRegex::new(r"出身\s*[：=:|]\s*([^|\n\}]+)"), "origin"),
+    (CachedRegex::new(r"职业\s*[：=:|]\s*([^|\n\}]+)"), "occupation"),
+    (CachedRegex::new(r"职务\s*[：=:|]\s*([^|\n\}]+)"), "position"),
+    (CachedRegex::new(r"身份\s*[：=:|]\s*([^|\n\}]+)"), "identity"),
+    (CachedRegex::new(r"等级\s*[：=:|]\s*([^|\n\}]+)"), "level"),
+    (CachedRegex::new(r"阶级\s*[：=:|]\s*([^|\n\}]+)"), "class"),
+    // 性格和特征
+    (CachedRegex::new(r"性格\s*[：=:|]\s*([^|\n\}]+)"), "personality"),
+    (CachedRegex::new(r"萌点\s*[：=:|]\s*([^|\n\}]+)"), "moe-points"),
+    (CachedRegex::new(r"属性\s*[：=:|]\s*([^|\n\}]+)"), "attributes"),
+    (CachedRegex::new(r"特征\s*[：=:|]\s*([^|\n\}]+)"), "traits"),
+    // 能力和技能
+    (CachedRegex::new(r"喜好\s*[：=:|]\s*([^|\n\}]+)"), "hobby"),
+    (CachedRegex::new(r"爱好\s*[：=:|]\s*([^|\n\}]+)"), "hobby"),
+    (CachedRegex::new(r"特技\s*[：=:|]\s*([^|\n\}]+)"), "special-skill"),
+    (CachedRegex::new(r"能力\s*[：=:|]\s*([^|\n\}]+)"), "ability"),
+    (CachedRegex::new(r"技能\s*[：=:|]\s*([^|\n\}]+)"), "skill"),
+    (CachedRegex::new(r"武器\s*[：=:|]\s*([^|\n\}]+)"), "weapon"),
+    (CachedRegex::new(r"装备\s*[：=:|]\s*([^|\n\}]+)"), "equipment"),
+    // 称号和别名
+    (CachedRegex::new(r"称号\s*[：=:|]\s*([^|\n\}]+)"), "title"),
+    (CachedRegex::new(r"别名\s*[：=:|]\s*([^|\n\}]+)"), "alias"),
+    (CachedRegex::new(r"外号\s*[：=:|]\s*([^|\n\}]+)"), "nickname"),
+    (CachedRegex::new(r"绰号\s*[：=:|]\s*([^|\n\}]+)"), "nickname"),
+    // 关系信息
+    (CachedRegex::new(r"亲属\s*[：=:|]\s*([^|\n\}]+)"), "family"),
+    (CachedRegex::new(r"朋友\s*[：=:|]\s*([^|\n\}]+)"), "friends"),
+    (CachedRegex::new(r"恋人\s*[：=:|]\s*([^|\n\}]+)"), "lover"),
+    (CachedRegex::new(r"主人\s*[：=:|]\s*([^|\n\}]+)"), "master"),
+    (CachedRegex::new(r"从属\s*[：=:|]\s*([^|\n\}]+)"), "subordinate"),
+];
+
+static CATEGORY_RE: CachedRegex = CachedRegex::new(r"\[\[Category:([^\]]+)\]\]");
+static TEMPLATE_RE: CachedRegex = CachedRegex::new(r"\{\{([^}]+)\}\}");
+static OPEN_TEMPLATE_RE: CachedRegex = CachedRegex::new(r"\{\{[^}]*$");
+static WIKI_LINK_RE: CachedRegex = CachedRegex::new(r"\[\[([^|\]]*\|)?([^\]]*)\]\]");
+static OPEN_WIKI_LINK_RE: CachedRegex = CachedRegex::new(r"\[\[[^\]]*$");
+static HTML_TAG_RE: CachedRegex = CachedRegex::new(r"<[^>]*>");
+static REF_RE: CachedRegex = CachedRegex::new(r"<ref[^>]*>.*?</ref>");
+static PIPES_RE: CachedRegex = CachedRegex::new(r"\|+");
+static WHITESPACE_RE: CachedRegex = CachedRegex::new(r"\s+");
+static TRAILING_MARKUP_RE: CachedRegex = CachedRegex::new(r"[{<[].*$");
+
+/// Patterns checked by `regex_cache::precompile_builtin_regexes` at startup
+pub(crate) fn builtin_regexes() -> Vec<&'static CachedRegex> {
+    let mut all: Vec<&'static CachedRegex> = TEMPLATE_PATTERNS.iter().map(|(pattern, _)| pattern).collect();
+    all.extend([
+        &CATEGORY_RE,
+        &TEMPLATE_RE,
+        &OPEN_TEMPLATE_RE,
+        &WIKI_LINK_RE,
+        &OPEN_WIKI_LINK_RE,
+        &HTML_TAG_RE,
+        &REF_RE,
+        &PIPES_RE,
+        &WHITESPACE_RE,
+        &TRAILING_MARKUP_RE,
+    ]);
+    all
+}
+
 /// MediaWiki API response structures for page information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MediaWikiResponse {
@@ -134,7 +225,7 @@ impl AcgcService {
             return Err(anyhow::anyhow!("Search request failed: {}", response.status()));
         }
 
-        let wiki_data: MediaWikiResponse = response.json().await?;
+        let wiki_data: MediaWikiResponse = response.limited_json().await?;
 
         if let Some(query_data) = wiki_data.query {
             if let Some(search_results) = query_data.search {
@@ -170,7 +261,7 @@ impl AcgcService {
             return Err(anyhow::anyhow!("Details request failed: {}", response.status()));
         }
 
-        let wiki_data: MediaWikiResponse = response.json().await?;
+        let wiki_data: MediaWikiResponse = response.limited_json().await?;
 
         if let Some(query_data) = wiki_data.query && let Some(pages) = query_data.pages {
             for (_, page) in pages {
@@ -227,74 +318,14 @@ impl AcgcService {
     fn extract_character_info(&self, content: &str) -> String {
         let mut info = String::new();
 
-        // Extract information from character template patterns
-        let template_patterns = [
-            // 角色模板信息
-            (r"角色\s*\|\s*[^=]*=\s*([^|\n\}]+)", "character-template"),
-            (r"作品\s*=\s*([^|\n\}]+)", "source-work"),
-            (r"系列\s*=\s*([^|\n\}]+)", "series"),
-            // 声优/配音信息 (多种格式)
-            (r"声优\s*[：=:|]\s*([^|\n\}]+)", "voice-actor"),
-            (r"配音\s*[：=:|]\s*([^|\n\}]+)", "voice-actor"),
-            (r"CV\s*[：=:|]\s*([^|\n\}]+)", "voice-actor"),
-            (r"日配\s*[：=:|]\s*([^|\n\}]+)", "voice-actor-jp"),
-            (r"中配\s*[：=:|]\s*([^|\n\}]+)", "voice-actor-cn"),
-            // 基本信息
-            (r"年龄\s*[：=:|]\s*([^|\n\}]+)", "age"),
-            (r"生日\s*[：=:|]\s*([^|\n\}]+)", "birthday"),
-            (r"身高\s*[：=:|]\s*([^|\n\}]+)", "height"),
-            (r"体重\s*[：=:|]\s*([^|\n\}]+)", "weight"),
-            (r"性别\s*[：=:|]\s*([^|\n\}]+)", "gender"),
-            (r"种族\s*[：=:|]\s*([^|\n\}]+)", "species"),
-            (r"血型\s*[：=:|]\s*([^|\n\}]+)", "blood-type"),
-            // 外观特征
-            (r"发色\s*[：=:|]\s*([^|\n\}]+)", "hair-color"),
-            (r"瞳色\s*[：=:|]\s*([^|\n\}]+)", "eye-color"),
-            (r"眼色\s*[：=:|]\s*([^|\n\}]+)", "eye-color"),
-            (r"头发颜色\s*[：=:|]\s*([^|\n\}]+)", "hair-color"),
-            (r"服装\s*[：=:|]\s*([^|\n\}]+)", "clothing"),
-            (r"装扮\s*[：=:|]\s*([^|\n\}]+)", "appearance"),
-            // 身份和角色信息
-            (r"出身\s*[：=:|]\s*([^|\n\}]+)", "origin"),
-            (r"职业\s*[：=:|]\s*([^|\n\}]+)", "occupation"),
-            (r"职务\s*[：=:|]\s*([^|\n\}]+)", "position"),
-            (r"身份\s*[：=:|]\s*([^|\n\}]+)", "identity"),
-            (r"等级\s*[：=:|]\s*([^|\n\}]+)", "level"),
-            (r"阶级\s*[：=:|]\s*([^|\n\}]+)", "class"),
-            // 性格和特征
-            (r"性格\s*[：=:|]\s*([^|\n\}]+)", "personality"),
-            (r"萌点\s*[：=:|]\s*([^|\n\}]+)", "moe-points"),
-            (r"属性\s*[：=:|]\s*([^|\n\}]+)", "attributes"),
-            (r"特征\s*[：=:|]\s*([^|\n\}]+)", "traits"),
-            // 能力和技能
-            (r"喜好\s*[：=:|]\s*([^|\n\}]+)", "hobby"),
-            (r"爱好\s*[：=:|]\s*([^|\n\}]+)", "hobby"),
-            (r"特技\s*[：=:|]\s*([^|\n\}]+)", "special-skill"),
-            (r"能力\s*[：=:|]\s*([^|\n\}]+)", "ability"),
-            (r"技能\s*[：=:|]\s*([^|\n\}]+)", "skill"),
-            (r"武器\s*[：=:|]\s*([^|\n\}]+)", "weapon"),
-            (r"装备\s*[：=:|]\s*([^|\n\}]+)", "equipment"),
-            // 称号和别名
-            (r"称号\s*[：=:|]\s*([^|\n\}]+)", "title"),
-            (r"别名\s*[：=:|]\s*([^|\n\}]+)", "alias"),
-            (r"外号\s*[：=:|]\s*([^|\n\}]+)", "nickname"),
-            (r"绰号\s*[：=:|]\s*([^|\n\}]+)", "nickname"),
-            // 关系信息
-            (r"亲属\s*[：=:|]\s*([^|\n\}]+)", "family"),
-            (r"朋友\s*[：=:|]\s*([^|\n\}]+)", "friends"),
-            (r"恋人\s*[：=:|]\s*([^|\n\}]+)", "lover"),
-            (r"主人\s*[：=:|]\s*([^|\n\}]+)", "master"),
-            (r"从属\s*[：=:|]\s*([^|\n\}]+)", "subordinate"),
-        ];
-
-        // Extract using enhanced patterns with deduplication
+        // Extract information from character template patterns, with deduplication
         let mut extracted_info: std::collections::HashMap<
             String,
             std::collections::HashSet<String>
         > = std::collections::HashMap::new();
 
-        for (pattern, field_name) in template_patterns {
-            if let Ok(re) = Regex::new(pattern) {
+        for (pattern, field_name) in &TEMPLATE_PATTERNS {
+            if let Ok(re) = pattern.get() {
                 for captures in re.captures_iter(content) {
                     if let Some(value) = captures.get(1) {
                         let cleaned_value = self.clean_wiki_text(value.as_str());
@@ -333,7 +364,7 @@ impl AcgcService {
         }
 
         // Extract萌娘百科分类信息
-        if let Ok(re) = Regex::new(r"\[\[Category:([^\]]+)\]\]") {
+        if let Ok(re) = CATEGORY_RE.get() {
             let mut categories = Vec::new();
             for captures in re.captures_iter(content) {
                 if let Some(category) = captures.get(1) {
@@ -370,22 +401,22 @@ impl AcgcService {
 
         // Extract content from MediaWiki templates {{...}} and remove template markers
         // Keep the content inside templates like {{角色|利姆鲁}} -> 角色|利姆鲁
-        if let Ok(re) = Regex::new(r"\{\{([^}]+)\}\}") {
+        if let Ok(re) = TEMPLATE_RE.get() {
             text = re.replace_all(&text, "$1").to_string();
         }
         // Remove incomplete templates that don't have closing braces
-        if let Ok(re) = Regex::new(r"\{\{[^}]*$") {
+        if let Ok(re) = OPEN_TEMPLATE_RE.get() {
             text = re.replace_all(&text, "").to_string();
         }
 
         // Remove wiki links and keep only the display text
         // Handle [[link|display]] -> display
-        if let Ok(re) = Regex::new(r"\[\[([^|\]]*\|)?([^\]]*)\]\]") {
+        if let Ok(re) = WIKI_LINK_RE.get() {
             text = re.replace_all(&text, "$2").to_string();
         }
 
         // Remove incomplete wiki links like [[text without closing
-        if let Ok(re) = Regex::new(r"\[\[[^\]]*$") {
+        if let Ok(re) = OPEN_WIKI_LINK_RE.get() {
             text = re.replace_all(&text, "").to_string();
         }
 
@@ -393,23 +424,23 @@ impl AcgcService {
         text = text.replace("'''", "").replace("''", "");
 
         // Remove HTML tags
-        if let Ok(re) = Regex::new(r"<[^>]*>") {
+        if let Ok(re) = HTML_TAG_RE.get() {
             text = re.replace_all(&text, "").to_string();
         }
 
         // Remove ref tags content
-        if let Ok(re) = Regex::new(r"<ref[^>]*>.*?</ref>") {
+        if let Ok(re) = REF_RE.get() {
             text = re.replace_all(&text, "").to_string();
         }
 
         // Remove wiki table markup and excess pipes
         text = text.replace("|-", "");
-        if let Ok(re) = Regex::new(r"\|+") {
+        if let Ok(re) = PIPES_RE.get() {
             text = re.replace_all(&text, " ").to_string();
         }
 
         // Clean up multiple spaces and newlines
-        if let Ok(re) = Regex::new(r"\s+") {
+        if let Ok(re) = WHITESPACE_RE.get() {
             text = re.replace_all(&text, " ").to_string();
         }
 
@@ -421,7 +452,7 @@ impl AcgcService {
 
         // Remove trailing incomplete content that might cause issues
         if
-            let Ok(re) = Regex::new(r"[{<[].*$") &&
+            let Ok(re) = TRAILING_MARKUP_RE.get() &&
             text.len() > 20 &&
             re.is_match(&text) &&
             let Some(pos) = text.find(['{', '<', '[']) &&
@@ -0,0 +1,372 @@
+// WHOIS Server - Local Annotations
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Operator notes and tags on resources (`--annotations-file`)
+//!
+//! Network teams often keep "who is this customer" and "never send abuse
+//! reports here" in spreadsheets. The annotations file brings that into the
+//! server:
+//!
+//! ```toml
+//! # Only these clients see annotations; everyone when omitted
+//! clients = ["10.0.0.0/8", "2001:db8::/32"]
+//!
+//! [[annotation]]
+//! resource = "AS64500"
+//! tags = ["customer", "do-not-abuse-report"]
+//! note = "customer: Foo Ltd, contract 1234"
+//!
+//! [[annotation]]
+//! resource = "192.0.2.0/24"
+//! tags = ["customer"]
+//! note = "Foo Ltd office uplink"
+//! ```
+//!
+//! Resources are ASNs, prefixes or addresses (matching every address inside)
+//! and domains (matching their subdomains too). Responses to ASN, IP and
+//! domain queries get a `% annotation:` line per matching entry, added after
+//! the response cache like the verification badge; `customer-TAGGED` lists
+//! every resource with a tag. The file is read once at startup.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use cidr::IpCidr;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::core::QueryType;
+use crate::core::query::parse_asn;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnnotationFile {
+    #[serde(default)]
+    clients: Vec<String>,
+    #[serde(default, rename = "annotation")]
+    annotations: Vec<AnnotationEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnnotationEntry {
+    resource: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+/// What an annotation is attached to
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotatedResource {
+    Asn(u32),
+    Prefix(IpCidr),
+    Domain(String),
+}
+
+impl AnnotatedResource {
+    fn parse(resource: &str) -> Option<Self> {
+        if let Some(asn) = parse_asn(resource) {
+            return Some(Self::Asn(asn));
+        }
+        if let Ok(prefix) = resource.parse::<IpCidr>() {
+            return Some(Self::Prefix(prefix));
+        }
+        let domain = resource.trim_end_matches('.').to_lowercase();
+        let valid = domain.contains('.')
+            && domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_');
+        valid.then_some(Self::Domain(domain))
+    }
+}
+
+impl std::fmt::Display for AnnotatedResource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Asn(asn) => write!(f, "AS{}", asn),
+            Self::Prefix(prefix) => write!(f, "{}", prefix),
+            Self::Domain(domain) => write!(f, "{}", domain),
+        }
+    }
+}
+
+/// A note and tags on one resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub resource: AnnotatedResource,
+    /// Lowercase tags
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+}
+
+impl Annotation {
+    fn line(&self) -> String {
+        let mut line = format!("% annotation: {}", self.resource);
+        if !self.tags.is_empty() {
+            line.push_str(&format!(" [{}]", self.tags.join(", ")));
+        }
+        if let Some(note) = &self.note {
+            line.push_str(&format!(" {}", note));
+        }
+        line.push('\n');
+        line
+    }
+}
+
+/// The loaded annotations file
+#[derive(Debug, Default)]
+pub struct Annotations {
+    /// Client networks allowed to see annotations, everyone when empty
+    clients: Vec<IpCidr>,
+    entries: Vec<Annotation>,
+}
+
+impl Annotations {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether a client may see annotations; clients of unknown address may
+    /// not when the file restricts them
+    fn visible_to(&self, client: Option<IpAddr>) -> bool {
+        self.clients.is_empty() || client.is_some_and(|ip| self.clients.iter().any(|net| net.contains(&ip)))
+    }
+
+    /// Annotations on the resource a query is about, most specific first
+    fn matching(&self, query_type: &QueryType) -> Vec<&Annotation> {
+        let ip = match query_type {
+            QueryType::IPv4(ip) => Some(IpAddr::V4(*ip)),
+            QueryType::IPv6(ip) => Some(IpAddr::V6(*ip)),
+            _ => None,
+        };
+        let asn = match query_type {
+            QueryType::ASN(asn) => parse_asn(asn),
+            _ => None,
+        };
+        let domain = match query_type {
+            QueryType::Domain(domain) => Some(domain.trim_end_matches('.').to_lowercase()),
+            _ => None,
+        };
+
+        let mut matching: Vec<(u8, &Annotation)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| match &entry.resource {
+                AnnotatedResource::Asn(number) => (asn == Some(*number)).then_some((0, entry)),
+                AnnotatedResource::Prefix(prefix) => ip
+                    .filter(|ip| prefix.contains(ip))
+                    .map(|_| (prefix.network_length(), entry)),
+                AnnotatedResource::Domain(name) => domain
+                    .as_deref()
+                    .filter(|queried| *queried == name.as_str() || queried.ends_with(&format!(".{}", name)))
+                    .map(|_| (name.matches('.').count() as u8, entry)),
+            })
+            .collect();
+        matching.sort_by_key(|(specificity, _)| std::cmp::Reverse(*specificity));
+        matching.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    fn tagged(&self, tag: &str) -> Vec<&Annotation> {
+        let tag = tag.to_lowercase();
+        self.entries.iter().filter(|entry| entry.tags.contains(&tag)).collect()
+    }
+}
+
+static ANNOTATIONS: OnceLock<Annotations> = OnceLock::new();
+
+/// Install the annotations used for all queries
+pub fn init_annotations(annotations: Annotations) {
+    let _ = ANNOTATIONS.set(annotations);
+}
+
+fn annotations() -> Option<&'static Annotations> {
+    ANNOTATIONS.get()
+}
+
+/// Parse an annotations file
+pub fn parse_annotations(content: &str) -> Result<Annotations> {
+    let file: AnnotationFile = toml::from_str(content)?;
+
+    let clients = file
+        .clients
+        .iter()
+        .map(|client| client.parse::<IpCidr>().map_err(|_| anyhow!("invalid client network '{}'", client)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut entries = Vec::with_capacity(file.annotations.len());
+    for entry in file.annotations {
+        let resource = AnnotatedResource::parse(entry.resource.trim())
+            .ok_or_else(|| anyhow!("'{}' is not an ASN, prefix, address or domain", entry.resource))?;
+        if entry.tags.is_empty() && entry.note.is_none() {
+            return Err(anyhow!("annotation of {} has neither tags nor a note", entry.resource));
+        }
+        let tags = entry.tags.iter().map(|tag| tag.trim().to_lowercase()).collect::<Vec<_>>();
+        if let Some(tag) = tags.iter().find(|tag| tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',')) {
+            return Err(anyhow!("tag '{}' of {} must be a single word", tag, entry.resource));
+        }
+        entries.push(Annotation { resource, tags, note: entry.note.map(|note| note.trim().to_string()) });
+    }
+    Ok(Annotations { clients, entries })
+}
+
+/// Load annotations from a TOML file
+pub fn load_annotation_file(path: &Path) -> Result<Annotations> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read annotations file {}: {}", path.display(), e))?;
+    parse_annotations(&content).map_err(|e| anyhow!("Invalid annotations file {}: {}", path.display(), e))
+}
+
+fn append_with(annotations: &Annotations, response: String, query_type: &QueryType, ctx: &RequestContext) -> String {
+    if !annotations.visible_to(ctx.client_addr) {
+        return response;
+    }
+    let matching = annotations.matching(query_type);
+    if matching.is_empty() {
+        return response;
+    }
+    let mut output = format!("{}\n", response.trim_end_matches('\n'));
+    for annotation in matching {
+        output.push_str(&annotation.line());
+    }
+    output
+}
+
+/// Append `% annotation:` lines for the resource a query is about
+pub fn append_annotations(response: String, query_type: &QueryType, ctx: &RequestContext) -> String {
+    match annotations() {
+        Some(annotations) => append_with(annotations, response, query_type, ctx),
+        None => response,
+    }
+}
+
+fn format_tagged(annotations: &Annotations, tag: &str) -> String {
+    let tagged = annotations.tagged(tag);
+    let mut output = format!("% Resources tagged '{}': {}\n", tag.to_lowercase(), tagged.len());
+    for annotation in tagged {
+        output.push('\n');
+        output.push_str(&format!("resource:       {}\n", annotation.resource));
+        output.push_str(&format!("tags:           {}\n", annotation.tags.join(", ")));
+        if let Some(note) = &annotation.note {
+            output.push_str(&format!("note:           {}\n", note));
+        }
+    }
+    output
+}
+
+pub struct TaggedService;
+
+#[async_trait]
+impl QueryService for TaggedService {
+    fn name(&self) -> &'static str {
+        "tagged"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-TAGGED"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "Resources carrying a tag in the local annotations file",
+            example: "customer-TAGGED",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "local annotations"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        // Only clients allowed to see annotations may see the response
+        None
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        let tag = target.trim();
+        if tag.is_empty() || tag.contains(char::is_whitespace) {
+            return Err(anyhow!("Usage: <tag>-TAGGED, got: {}", tag));
+        }
+        match annotations() {
+            Some(annotations) if annotations.visible_to(ctx.client_addr) => Ok(format_tagged(annotations, tag)),
+            Some(_) => Err(anyhow!("Annotations are not available to this client")),
+            None => Err(anyhow!("No annotations file is configured (--annotations-file)")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+        clients = ["10.0.0.0/8"]
+
+        [[annotation]]
+        resource = "AS64500"
+        tags = ["Customer", "do-not-abuse-report"]
+        note = "customer: Foo Ltd"
+
+        [[annotation]]
+        resource = "192.0.2.0/24"
+        tags = ["customer"]
+        note = "Foo Ltd office uplink"
+
+        [[annotation]]
+        resource = "192.0.0.0/16"
+        note = "lab aggregate"
+
+        [[annotation]]
+        resource = "example.com"
+        tags = ["internal"]
+    "#;
+
+    #[test]
+    fn test_annotations_match_and_append() {
+        let annotations = parse_annotations(FILE).unwrap();
+        assert_eq!(annotations.len(), 4);
+        let ctx = RequestContext::new(crate::core::request_context::Frontend::Whois).with_client_addr("10.1.2.3".parse().unwrap());
+
+        let response = append_with(&annotations, "inetnum: ...\n\n".to_string(), &QueryType::IPv4("192.0.2.7".parse().unwrap()), &ctx);
+        assert_eq!(
+            response,
+            "inetnum: ...\n% annotation: 192.0.2.0/24 [customer] Foo Ltd office uplink\n% annotation: 192.0.0.0/16 lab aggregate\n"
+        );
+        let response = append_with(&annotations, "aut-num: AS64500\n".to_string(), &QueryType::ASN("AS64500".to_string()), &ctx);
+        assert!(response.ends_with("% annotation: AS64500 [customer, do-not-abuse-report] customer: Foo Ltd\n"));
+        let response = append_with(&annotations, "Domain\n".to_string(), &QueryType::Domain("www.Example.com".to_string()), &ctx);
+        assert!(response.ends_with("% annotation: example.com [internal]\n"));
+
+        // Clients outside the allowed networks see nothing
+        let outside = RequestContext::new(crate::core::request_context::Frontend::Whois).with_client_addr("198.51.100.1".parse().unwrap());
+        let response = append_with(&annotations, "aut-num: AS64500\n".to_string(), &QueryType::ASN("AS64500".to_string()), &outside);
+        assert_eq!(response, "aut-num: AS64500\n");
+        assert!(!annotations.visible_to(None));
+
+        let tagged = format_tagged(&annotations, "CUSTOMER");
+        assert!(tagged.starts_with("% Resources tagged 'customer': 2\n\nresource:       AS64500\n"));
+        assert!(tagged.contains("resource:       192.0.2.0/24\ntags:           customer\nnote:           Foo Ltd office uplink\n"));
+
+        // Tag listings depend on the client, so they are neither cached nor shared
+        assert_eq!(TaggedService.cache_ttl(), None);
+        assert_eq!(crate::core::query::cache_ttl(&crate::core::analyze_query("customer-TAGGED")), None);
+    }
+
+    #[test]
+    fn test_invalid_annotations() {
+        assert!(parse_annotations("[[annotation]]\nresource = \"not a resource\"\nnote = \"x\"\n").is_err());
+        assert!(parse_annotations("[[annotation]]\nresource = \"AS1\"\n").is_err());
+        assert!(parse_annotations("[[annotation]]\nresource = \"AS1\"\ntags = [\"two words\"]\n").is_err());
+        assert!(parse_annotations("clients = [\"nonsense\"]\n").is_err());
+        assert!(parse_annotations("[[annotations]]\nresource = \"AS1\"\n").is_err());
+    }
+}
@@ -0,0 +1,246 @@
+// WHOIS Server - ASPA and BGPsec Status
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! ASPA provider authorizations and BGPsec router keys of an ASN (`-ASPA`)
+//!
+//! Reads the ASN's ASPA object and BGPsec router keys from the same validated
+//! RPKI export as `-ROA` (`services::roa`), then checks the upstreams RIS sees
+//! for the ASN (left neighbours in RIPEstat `asn-neighbours`) against it: an
+//! upstream listed as provider is `valid`, any other is `invalid`, and all are
+//! `unknown` when the ASN has no ASPA. The results are `validation:`
+//! attributes, so colored output shows them green, red and yellow.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::query::parse_asn;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::roa::{Aspa, BgpsecKey, RoaSnapshot, SNAPSHOT_TTL, format_unix_time, roa_snapshot};
+use crate::services::upstream::api_base;
+use crate::log_debug;
+
+const RIPESTAT_DATA_BASE: &str = "https://stat.ripe.net/data/";
+/// Neighbours seen by fewer RIS peers than this are ignored as noise
+const MIN_NEIGHBOUR_POWER: u64 = 2;
+
+/// ASPA verification state of one upstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HopState {
+    Valid,
+    Invalid,
+    Unknown,
+}
+
+impl HopState {
+    fn of(upstream: u32, aspa: Option<&Aspa>) -> Self {
+        match aspa {
+            None => HopState::Unknown,
+            Some(aspa) if aspa.providers.contains(&upstream) => HopState::Valid,
+            Some(_) => HopState::Invalid,
+        }
+    }
+
+    fn describe(self, asn: u32) -> String {
+        match self {
+            HopState::Valid => "valid (authorized provider)".to_string(),
+            HopState::Invalid => format!("invalid (not a provider in the ASPA of AS{})", asn),
+            HopState::Unknown => format!("unknown (AS{} has no ASPA)", asn),
+        }
+    }
+}
+
+/// Upstreams of an ASN as seen by RIS, by ASN
+async fn fetch_upstreams(asn: u32) -> Result<BTreeSet<u32>> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .user_agent("whois-server/1.0")
+        .build()?;
+    let url = format!("{}asn-neighbours/data.json", api_base(RIPESTAT_DATA_BASE));
+    let response = client.get(&url).query(&[("resource", format!("AS{}", asn))]).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("RIPEstat asn-neighbours failed: HTTP {}", response.status()));
+    }
+    let body: Value = response.limited_json().await?;
+    Ok(parse_upstreams(&body["data"]))
+}
+
+fn parse_upstreams(data: &Value) -> BTreeSet<u32> {
+    data["neighbours"]
+        .as_array()
+        .map(|neighbours| {
+            neighbours
+                .iter()
+                .filter(|n| n["type"].as_str() == Some("left"))
+                .filter(|n| n["power"].as_u64().unwrap_or(0) >= MIN_NEIGHBOUR_POWER)
+                .filter_map(|n| u32::try_from(n["asn"].as_u64()?).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn format_aspa(asn: u32, aspa: Option<Option<&Aspa>>, source: &str, output: &mut String) {
+    match aspa {
+        None => output.push_str(&format!("aspa:           unknown ({} publishes no ASPA objects)\n", source)),
+        Some(None) => output.push_str("aspa:           none\n"),
+        Some(Some(aspa)) => {
+            output.push_str(&format!("aspa:           AS{} authorizes {} providers\n", asn, aspa.providers.len()));
+            for provider in &aspa.providers {
+                if *provider == 0 {
+                    output.push_str("provider:       AS0 (no providers)\n");
+                } else {
+                    output.push_str(&format!("provider:       AS{}\n", provider));
+                }
+            }
+            if let Some(expires) = aspa.expires {
+                output.push_str(&format!("expires:        {}\n", format_unix_time(expires, "%Y-%m-%d")));
+            }
+        }
+    }
+}
+
+fn format_bgpsec(keys: Option<&[BgpsecKey]>, source: &str, output: &mut String) {
+    match keys {
+        None => output.push_str(&format!("bgpsec:         unknown ({} publishes no router keys)\n", source)),
+        Some([]) => output.push_str("bgpsec:         no router certificates\n"),
+        Some(keys) => {
+            output.push_str(&format!("bgpsec:         {} router certificates\n", keys.len()));
+            for key in keys {
+                let expires = key.expires.map(|expires| format!(", expires {}", format_unix_time(expires, "%Y-%m-%d")));
+                output.push_str(&format!("router-key:     {} ({}{})\n", key.ski, key.ta, expires.unwrap_or_default()));
+            }
+        }
+    }
+}
+
+fn format_status(asn: u32, snapshot: &RoaSnapshot, upstreams: Result<BTreeSet<u32>>) -> String {
+    let aspa = snapshot.aspa_for(asn);
+
+    let mut output = format!("% ASPA and BGPsec status for AS{}\n", asn);
+    output.push_str(&snapshot.source_line());
+    output.push('\n');
+
+    output.push_str(&format!("aut-num:        AS{}\n", asn));
+    format_aspa(asn, aspa, snapshot.source(), &mut output);
+    format_bgpsec(snapshot.bgpsec_keys_for(asn), snapshot.source(), &mut output);
+    output.push('\n');
+
+    let upstreams = match upstreams {
+        Ok(upstreams) => upstreams,
+        Err(e) => {
+            output.push_str(&format!("% Upstreams seen in RIS are unavailable: {}\n", e));
+            return output;
+        }
+    };
+    if upstreams.is_empty() {
+        output.push_str("% No upstreams of this AS seen in RIS\n");
+        return output;
+    }
+    if aspa.is_none() {
+        output.push_str(&format!("% Upstreams seen in RIS cannot be checked: {} has no ASPA data\n", snapshot.source()));
+        return output;
+    }
+
+    output.push_str("% Upstreams seen in RIS (RIPEstat asn-neighbours), checked against the ASPA\n");
+    for upstream in upstreams {
+        let state = HopState::of(upstream, aspa.flatten());
+        output.push_str(&format!("upstream:       AS{}\n", upstream));
+        output.push_str(&format!("validation:     {}\n", state.describe(asn)));
+    }
+    output
+}
+
+/// Process an `-ASPA` query
+pub async fn process_aspa_query(target: &str) -> Result<String> {
+    let asn = parse_asn(target.trim()).ok_or_else(|| anyhow!("Usage: AS<number>-ASPA, got: {}", target.trim()))?;
+    let snapshot = roa_snapshot().await?;
+    let upstreams = fetch_upstreams(asn).await;
+    log_debug!("-ASPA AS{}: {} RIS upstreams", asn, upstreams.as_ref().map_or(0, BTreeSet::len));
+    Ok(format_status(asn, &snapshot, upstreams))
+}
+
+pub struct AspaService;
+
+#[async_trait]
+impl QueryService for AspaService {
+    fn name(&self) -> &'static str {
+        "aspa"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-ASPA"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "ASPA providers and BGPsec router keys of an ASN, RIS upstreams checked against the ASPA",
+            example: "AS13335-ASPA",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "rpki.cloudflare.com, RIPEstat"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(SNAPSHOT_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_aspa_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"{"metadata": {"generated": 1735689600}, "roas": [],
+        "aspas": [
+            {"customer_asid": 64500, "expires": 1736294400, "providers": [174, 3356]},
+            {"customer_asid": 64501, "expires": 1736294400, "providers": [{"asid": 0, "afi_limit": "ipv4"}]}
+        ],
+        "bgpsec_keys": [{"asn": 64500, "ski": "510F485D29A29DB7B515F9C478F8ED1CE342A9C1", "pubkey": "", "ta": "ripe", "expires": 1736294400}]
+    }"#;
+
+    #[test]
+    fn test_upstreams_checked_against_aspa() {
+        let snapshot = RoaSnapshot::from_json("rpki.cloudflare.com", EXPORT);
+        let output = format_status(64500, &snapshot, Ok(BTreeSet::from([174, 6939])));
+
+        assert!(output.contains("aspa:           AS64500 authorizes 2 providers\nprovider:       AS174\nprovider:       AS3356\n"));
+        assert!(output.contains("router-key:     510F485D29A29DB7B515F9C478F8ED1CE342A9C1 (ripe, expires 2025-01-08)\n"));
+        assert!(output.contains("upstream:       AS174\nvalidation:     valid (authorized provider)\n"));
+        assert!(output.contains("upstream:       AS6939\nvalidation:     invalid (not a provider in the ASPA of AS64500)\n"));
+
+        let output = format_status(64501, &snapshot, Ok(BTreeSet::new()));
+        assert!(output.contains("provider:       AS0 (no providers)\n"));
+        assert!(output.contains("bgpsec:         no router certificates\n"));
+
+        let output = format_status(64502, &snapshot, Ok(BTreeSet::from([174])));
+        assert!(output.contains("aspa:           none\n"));
+        assert!(output.contains("validation:     unknown (AS64502 has no ASPA)\n"));
+
+        // Exports without ASPA support leave everything unknown
+        let ripe = RoaSnapshot::from_json("rpki-validator.ripe.net", r#"{"roas": []}"#);
+        let output = format_status(64500, &ripe, Ok(BTreeSet::from([174])));
+        assert!(output.contains("aspa:           unknown (rpki-validator.ripe.net publishes no ASPA objects)\n"));
+        assert!(!output.contains("validation:"));
+    }
+
+    #[test]
+    fn test_parse_upstreams() {
+        let data = serde_json::json!({"neighbours": [
+            {"asn": 174, "type": "left", "power": 40},
+            {"asn": 6939, "type": "left", "power": 1},
+            {"asn": 64511, "type": "right", "power": 12},
+        ]});
+        assert_eq!(parse_upstreams(&data), BTreeSet::from([174]));
+    }
+}
@@ -0,0 +1,299 @@
+// WHOIS Server - AS-SET Expansion
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Recursive as-set expansion (`AS-EXAMPLE-ASSET`)
+//!
+//! Expands an as-set into the ASNs it contains, as a prefix filter generator
+//! would, using IRRd's `!i` command on whois.radb.net. The sets are expanded
+//! one level at a time over a single persistent (`!!`) connection instead of
+//! with `!i<set>,1`, so the response can show which nested sets were pulled
+//! in, how deep, which ones do not exist, and where sets include themselves
+//! again. A loop does not stop the expansion: every set is expanded once and
+//! its ASNs counted once, as IRRd does.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::collections::{BTreeSet, HashSet};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::config::{RADB_WHOIS_PORT, RADB_WHOIS_SERVER, TIMEOUT_SECONDS};
+use crate::core::query::parse_asn;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::log_debug;
+
+/// Sets expanded before giving up on an as-set
+const MAX_SETS: usize = 5000;
+
+/// Members of one as-set as IRRd returns them, `None` if it does not exist
+#[async_trait]
+pub(crate) trait SetSource: Send {
+    async fn members(&mut self, set: &str) -> Result<Option<String>>;
+}
+
+/// An IRRd connection in multiple-command mode (`!!`)
+pub(crate) struct IrrdSession {
+    stream: BufReader<TcpStream>,
+}
+
+impl IrrdSession {
+    pub(crate) async fn connect(server: &str, port: u16) -> Result<Self> {
+        let address = format!("{}:{}", server, port);
+        let stream = tokio::time::timeout(Duration::from_secs(TIMEOUT_SECONDS), TcpStream::connect(&address))
+            .await
+            .map_err(|_| anyhow!("Connection to {} timed out", address))??;
+        let mut session = Self { stream: BufReader::new(stream) };
+        session.stream.get_mut().write_all(b"!!\n").await?;
+        Ok(session)
+    }
+
+    /// Run one command: its data, `None` when the key does not exist
+    pub(crate) async fn command(&mut self, command: &str) -> Result<Option<String>> {
+        self.stream.get_mut().write_all(format!("{}\n", command).as_bytes()).await?;
+
+        // `A<length>` + data + `C`, `C` (empty), `D` (not found) or `F <error>`
+        let status = self.read_line().await?;
+        match status.chars().next() {
+            Some('A') => {
+                let length: usize = status[1..].parse().map_err(|_| anyhow!("Malformed IRRd response: {}", status))?;
+                let mut data = vec![0u8; length];
+                tokio::time::timeout(Duration::from_secs(TIMEOUT_SECONDS), self.stream.read_exact(&mut data))
+                    .await
+                    .map_err(|_| anyhow!("IRRd response timed out"))??;
+                let end = self.read_line().await?;
+                let end = if end.is_empty() { self.read_line().await? } else { end };
+                if end != "C" {
+                    return Err(anyhow!("Malformed IRRd response end: {}", end));
+                }
+                Ok(Some(String::from_utf8_lossy(&data).into_owned()))
+            }
+            Some('C') => Ok(Some(String::new())),
+            Some('D') => Ok(None),
+            Some('F') => Err(anyhow!("IRRd error for {}: {}", command, status[1..].trim())),
+            _ => Err(anyhow!("Unexpected IRRd response: {}", status)),
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        let read = tokio::time::timeout(Duration::from_secs(TIMEOUT_SECONDS), self.stream.read_line(&mut line))
+            .await
+            .map_err(|_| anyhow!("IRRd response timed out"))??;
+        if read == 0 {
+            return Err(anyhow!("IRRd closed the connection"));
+        }
+        Ok(line.trim_end().to_string())
+    }
+}
+
+#[async_trait]
+impl SetSource for IrrdSession {
+    async fn members(&mut self, set: &str) -> Result<Option<String>> {
+        self.command(&format!("!i{}", set)).await
+    }
+}
+
+/// A nested set and how it was reached
+#[derive(Debug, PartialEq)]
+struct NestedSet {
+    name: String,
+    depth: usize,
+    /// Direct members, `None` when the set does not exist
+    members: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Expansion {
+    pub(crate) asns: BTreeSet<u32>,
+    nested: Vec<NestedSet>,
+    /// Paths from a set back to one of its ancestors
+    loops: Vec<Vec<String>>,
+    pub(crate) truncated: bool,
+}
+
+/// Expand `root` depth first, expanding every set once
+pub(crate) async fn expand(source: &mut dyn SetSource, root: &str) -> Result<Expansion> {
+    let mut expansion = Expansion::default();
+    let mut expanded: HashSet<String> = HashSet::new();
+    // (set, path from the root to it)
+    let mut stack = vec![(root.to_uppercase(), Vec::new())];
+
+    while let Some((set, path)) = stack.pop() {
+        if path.contains(&set) {
+            let mut cycle = path[path.iter().position(|s| *s == set).unwrap_or(0)..].to_vec();
+            cycle.push(set);
+            expansion.loops.push(cycle);
+            continue;
+        }
+        if !expanded.insert(set.clone()) {
+            continue;
+        }
+        if expanded.len() > MAX_SETS {
+            expansion.truncated = true;
+            break;
+        }
+
+        let members = source.members(&set).await?;
+        let members = members.map(|data| data.split_whitespace().map(str::to_uppercase).collect::<Vec<_>>());
+        if path.is_empty() {
+            if members.is_none() {
+                return Err(anyhow!("as-set {} not found in the IRR", set));
+            }
+        } else {
+            expansion.nested.push(NestedSet {
+                name: set.clone(),
+                depth: path.len(),
+                members: members.as_ref().map(Vec::len),
+            });
+        }
+
+        let mut child_path = path.clone();
+        child_path.push(set.clone());
+        // Pushed in reverse so nested sets are expanded in listed order
+        for member in members.unwrap_or_default().into_iter().rev() {
+            match parse_asn(&member) {
+                Some(asn) => {
+                    expansion.asns.insert(asn);
+                }
+                None => stack.push((member, child_path.clone())),
+            }
+        }
+    }
+    Ok(expansion)
+}
+
+fn format_expansion(root: &str, expansion: &Expansion) -> String {
+    let mut output = format!(
+        "% AS-SET expansion of {} via {} (IRRd !i)\n\n",
+        root.to_uppercase(),
+        RADB_WHOIS_SERVER
+    );
+    let max_depth = expansion.nested.iter().map(|set| set.depth).max().unwrap_or(0);
+    output.push_str(&format!("as-set:         {}\n", root.to_uppercase()));
+    output.push_str(&format!(
+        "members:        {} ASNs, {} nested sets (depth {})\n",
+        expansion.asns.len(),
+        expansion.nested.len(),
+        max_depth
+    ));
+    if expansion.truncated {
+        output.push_str(&format!("% Expansion stopped after {} sets; the counts are incomplete\n", MAX_SETS));
+    }
+
+    for set in &expansion.nested {
+        let members = match set.members {
+            Some(count) => format!("{} members", count),
+            None => "not found".to_string(),
+        };
+        output.push_str(&format!("nested-set:     {} (depth {}, {})\n", set.name, set.depth, members));
+    }
+    for cycle in &expansion.loops {
+        output.push_str(&format!("loop:           {}\n", cycle.join(" -> ")));
+    }
+
+    output.push('\n');
+    for asn in &expansion.asns {
+        output.push_str(&format!("member:         AS{}\n", asn));
+    }
+    output.push_str(&format!("total:          {} ASNs\n", expansion.asns.len()));
+    output
+}
+
+/// Process an `-ASSET` query
+pub async fn process_asset_query(target: &str) -> Result<String> {
+    let root = target.trim();
+    if root.is_empty() || root.contains(char::is_whitespace) || parse_asn(root).is_some() {
+        return Err(anyhow!("Usage: <as-set>-ASSET (e.g. AS-EXAMPLE-ASSET), got: {}", root));
+    }
+
+    let mut session = IrrdSession::connect(RADB_WHOIS_SERVER, RADB_WHOIS_PORT).await?;
+    let expansion = expand(&mut session, root).await?;
+    log_debug!(
+        "Expanded {}: {} ASNs, {} nested sets, {} loops",
+        root,
+        expansion.asns.len(),
+        expansion.nested.len(),
+        expansion.loops.len()
+    );
+    Ok(format_expansion(root, &expansion))
+}
+
+pub struct AsSetService;
+
+#[async_trait]
+impl QueryService for AsSetService {
+    fn name(&self) -> &'static str {
+        "asset"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-ASSET"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "Recursive as-set expansion with nested sets, loop detection and ASN count",
+            example: "AS-HURRICANE-ASSET",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        RADB_WHOIS_SERVER
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(3600))
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_asset_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct Sets(HashMap<&'static str, &'static str>);
+
+    #[async_trait]
+    impl SetSource for Sets {
+        async fn members(&mut self, set: &str) -> Result<Option<String>> {
+            Ok(self.0.get(set).map(|members| members.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expansion_with_nested_sets_and_loops() {
+        let mut sets = Sets(HashMap::from([
+            ("AS-ROOT", "AS64500 AS-CUSTOMERS AS-PEERS"),
+            ("AS-CUSTOMERS", "AS64501 AS64502 AS-DOWNSTREAM"),
+            ("AS-DOWNSTREAM", "AS64502 AS64503 AS-ROOT"),
+            ("AS-PEERS", "AS64504 AS-CUSTOMERS AS-MISSING"),
+        ]));
+        let expansion = expand(&mut sets, "as-root").await.unwrap();
+
+        assert_eq!(expansion.asns, BTreeSet::from([64500, 64501, 64502, 64503, 64504]));
+        let nested: Vec<_> = expansion.nested.iter().map(|set| (set.name.as_str(), set.depth, set.members)).collect();
+        assert_eq!(nested, [
+            ("AS-CUSTOMERS", 1, Some(3)),
+            ("AS-DOWNSTREAM", 2, Some(3)),
+            ("AS-PEERS", 1, Some(3)),
+            ("AS-MISSING", 2, None),
+        ]);
+        assert_eq!(expansion.loops, [vec!["AS-ROOT", "AS-CUSTOMERS", "AS-DOWNSTREAM", "AS-ROOT"]]);
+
+        let output = format_expansion("as-root", &expansion);
+        assert!(output.contains("members:        5 ASNs, 4 nested sets (depth 2)\n"));
+        assert!(output.contains("nested-set:     AS-MISSING (depth 2, not found)\n"));
+        assert!(output.contains("loop:           AS-ROOT -> AS-CUSTOMERS -> AS-DOWNSTREAM -> AS-ROOT\n"));
+        assert!(output.ends_with("member:         AS64504\ntotal:          5 ASNs\n"));
+
+        assert!(expand(&mut sets, "AS-NOPE").await.is_err());
+    }
+}
@@ -0,0 +1,255 @@
+// WHOIS Server - Bogon Check
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Team Cymru fullbogons check (`192.0.2.1-BOGON`, `100.64.0.0/10-BOGON`)
+//!
+//! The fullbogons lists cover the special-purpose ranges as well as address
+//! space IANA has not allocated to a RIR or the RIRs have not assigned yet,
+//! none of which should appear in the global routing table. An address is a
+//! bogon when a list entry covers it; a prefix is one when an entry covers
+//! it, and partially one when it only contains bogon entries. Matching IANA
+//! special-purpose entries (`core::special_purpose`) are listed alongside.
+//! Team Cymru refreshes the lists every four hours, so each is downloaded
+//! once per `LIST_TTL` and kept in memory.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use cidr::IpCidr;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::core::special_purpose::special_purpose_overlapping;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use crate::{log_debug, log_warn};
+
+const FULLBOGONS_IPV4: &str = "https://www.team-cymru.org/Services/Bogons/fullbogons-ipv4.txt";
+const FULLBOGONS_IPV6: &str = "https://www.team-cymru.org/Services/Bogons/fullbogons-ipv6.txt";
+
+/// How long a downloaded list is reused
+const LIST_TTL: Duration = Duration::from_secs(4 * 3600);
+
+/// More-specific bogons listed for a prefix before the rest are counted only
+const MAX_LISTED: usize = 50;
+
+static LISTS: Lazy<Mutex<HashMap<&'static str, Arc<BogonList>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// One downloaded fullbogons list
+struct BogonList {
+    /// The list's `# last updated` header
+    updated: Option<String>,
+    prefixes: Vec<IpCidr>,
+    fetched_at: Instant,
+}
+
+impl BogonList {
+    fn parse(text: &str) -> Self {
+        let mut updated = None;
+        let mut prefixes = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(date) = comment.trim().strip_prefix("last updated") {
+                    updated = Some(date.trim().to_string());
+                }
+            } else if let Ok(prefix) = line.parse::<IpCidr>() {
+                prefixes.push(prefix);
+            }
+        }
+        Self { updated, prefixes, fetched_at: Instant::now() }
+    }
+
+    /// Entries covering `target`, least specific first
+    fn covering(&self, target: &IpCidr) -> Vec<&IpCidr> {
+        let mut covering: Vec<&IpCidr> = self
+            .prefixes
+            .iter()
+            .filter(|prefix| prefix.network_length() <= target.network_length())
+            .filter(|prefix| prefix.contains(&target.first_address()))
+            .collect();
+        covering.sort_by_key(|prefix| prefix.network_length());
+        covering
+    }
+
+    /// Entries strictly inside `target`
+    fn more_specific(&self, target: &IpCidr) -> Vec<&IpCidr> {
+        self.prefixes
+            .iter()
+            .filter(|prefix| prefix.network_length() > target.network_length())
+            .filter(|prefix| target.contains(&prefix.first_address()))
+            .collect()
+    }
+}
+
+/// Current fullbogons list at `url`, downloaded when the cached one is stale
+async fn fullbogons(url: &'static str) -> Result<Arc<BogonList>> {
+    let mut lists = LISTS.lock().await;
+    if let Some(list) = lists.get(url)
+        && list.fetched_at.elapsed() < LIST_TTL
+    {
+        return Ok(list.clone());
+    }
+
+    match download(url).await {
+        Ok(list) => {
+            log_debug!("Loaded {} fullbogons from {}", list.prefixes.len(), url);
+            let list = Arc::new(list);
+            lists.insert(url, list.clone());
+            Ok(list)
+        }
+        // Bogon space changes slowly; an outdated list beats no answer
+        Err(e) => match lists.get(url) {
+            Some(list) => {
+                log_warn!("Fullbogons refresh from {} failed, using the cached list: {}", url, e);
+                Ok(list.clone())
+            }
+            None => Err(e),
+        },
+    }
+}
+
+async fn download(url: &'static str) -> Result<BogonList> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent("whois-server/1.0")
+        .build()?;
+    let response = client.get(api_base(url).as_ref()).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Team Cymru fullbogons download failed: HTTP {}", response.status()));
+    }
+    let list = BogonList::parse(&response.limited_text().await?);
+    if list.prefixes.is_empty() {
+        return Err(anyhow!("Team Cymru fullbogons list is empty"));
+    }
+    Ok(list)
+}
+
+/// An address or prefix to check
+fn parse_target(target: &str) -> Result<IpCidr> {
+    let usage = || anyhow!("Usage: <IP address or prefix>-BOGON, got: {}", target);
+    if target.contains('/') {
+        target.parse::<IpCidr>().map_err(|_| usage())
+    } else {
+        target.parse::<IpAddr>().map(IpCidr::new_host).map_err(|_| usage())
+    }
+}
+
+fn format_check(target: &IpCidr, list: &BogonList, source: &str) -> String {
+    let mut output = format!("% Team Cymru fullbogons check for {}\n", target);
+    match &list.updated {
+        Some(updated) => output.push_str(&format!("% Source: {} (last updated {})\n", source, updated)),
+        None => output.push_str(&format!("% Source: {}\n", source)),
+    }
+    output.push('\n');
+
+    let covering = list.covering(target);
+    let more_specific = if covering.is_empty() { list.more_specific(target) } else { Vec::new() };
+    let label = if target.is_host_address() { "address:" } else { "prefix:" };
+    output.push_str(&format!("{:<16}{}\n", label, target));
+    if !covering.is_empty() {
+        output.push_str("bogon:          yes\n");
+    } else if !more_specific.is_empty() {
+        output.push_str(&format!("bogon:          partially ({} bogon prefixes inside)\n", more_specific.len()));
+    } else {
+        output.push_str("bogon:          no\n");
+    }
+    for prefix in covering.iter().chain(more_specific.iter().take(MAX_LISTED)) {
+        output.push_str(&format!("fullbogon:      {}\n", prefix));
+    }
+    if more_specific.len() > MAX_LISTED {
+        output.push_str(&format!("% ... and {} more bogon prefixes\n", more_specific.len() - MAX_LISTED));
+    }
+
+    let special = special_purpose_overlapping(target);
+    for entry in &special {
+        output.push_str(&format!("iana-entry:     {} {} ({})\n", entry.prefix, entry.name, entry.rfc));
+    }
+    if special.is_empty() && !(covering.is_empty() && more_specific.is_empty()) {
+        output.push_str("% Not a special-purpose range: unallocated by IANA or not yet assigned by a RIR\n");
+    }
+    output
+}
+
+/// Process a `-BOGON` query
+pub async fn process_bogon_query(target: &str) -> Result<String> {
+    let target = parse_target(target.trim())?;
+    let url = if target.is_ipv4() { FULLBOGONS_IPV4 } else { FULLBOGONS_IPV6 };
+    let list = fullbogons(url).await?;
+    let source = url.rsplit('/').next().unwrap_or(url);
+    Ok(format_check(&target, &list, &format!("team-cymru.org {}", source)))
+}
+
+pub struct BogonService;
+
+#[async_trait]
+impl QueryService for BogonService {
+    fn name(&self) -> &'static str {
+        "bogon"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-BOGON"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "Check an address or prefix against the Team Cymru fullbogons and IANA special-purpose ranges",
+            example: "100.64.0.1-BOGON",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "team-cymru.org"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(LIST_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_bogon_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "# last updated 1735689601 (Wed Jan  1 00:00:01 2025 GMT)\n\
+        0.0.0.0/8\n10.0.0.0/8\n100.64.0.0/10\n192.0.2.0/24\n198.18.0.0/15\n\
+        203.0.113.0/24\n41.62.0.0/16\n";
+
+    #[test]
+    fn test_fullbogons_check() {
+        let list = BogonList::parse(LIST);
+        assert_eq!(list.prefixes.len(), 7);
+        assert_eq!(list.updated.as_deref(), Some("1735689601 (Wed Jan  1 00:00:01 2025 GMT)"));
+        let check = |target: &str| format_check(&parse_target(target).unwrap(), &list, "fullbogons-ipv4.txt");
+
+        let output = check("100.64.12.1");
+        assert!(output.contains("address:        100.64.12.1\nbogon:          yes\nfullbogon:      100.64.0.0/10\n"));
+        assert!(output.contains("iana-entry:     100.64.0.0/10 Shared Address Space (RFC 6598)\n"));
+
+        let output = check("41.62.128.0/17");
+        assert!(output.contains("bogon:          yes\nfullbogon:      41.62.0.0/16\n"));
+        assert!(output.contains("% Not a special-purpose range"));
+
+        let output = check("198.0.0.0/8");
+        assert!(output.contains("bogon:          partially (1 bogon prefixes inside)\nfullbogon:      198.18.0.0/15\n"));
+        assert!(output.contains("iana-entry:     198.18.0.0/15 Benchmarking (RFC 2544)\n"));
+
+        let output = check("1.1.1.1");
+        assert!(output.contains("bogon:          no\n"));
+        assert!(!output.contains("iana-entry:"));
+
+        assert!(parse_target("192.0.2.1/24").is_err());
+        assert!(parse_target("example.com").is_err());
+    }
+}
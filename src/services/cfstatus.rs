@@ -9,6 +9,7 @@
 
 use anyhow::{ Context, Result, anyhow };
 use serde::{ Deserialize, Serialize };
+use crate::core::body_limit::LimitedBody;
 use crate::log_debug;
 const CLOUDFLARE_STATUS_API: &str = "https://www.cloudflarestatus.com/api/v2";
 const REQUEST_TIMEOUT_SECS: u64 = 10;
@@ -135,7 +136,7 @@ async fn query_status() -> Result<String> {
     }
 
     let status_response: StatusResponse = response
-        .json().await
+        .limited_json().await
         .context("Failed to parse Cloudflare Status API response")?;
 
     Ok(format_status_response(&status_response))
@@ -162,7 +163,7 @@ async fn query_components() -> Result<String> {
     }
 
     let components_response: ComponentsResponse = response
-        .json().await
+        .limited_json().await
         .context("Failed to parse Cloudflare Status API response")?;
 
     Ok(format_components_response(&components_response))
@@ -189,7 +190,7 @@ async fn query_incidents() -> Result<String> {
     }
 
     let incidents_response: IncidentsResponse = response
-        .json().await
+        .limited_json().await
         .context("Failed to parse Cloudflare Status API response")?;
 
     Ok(format_incidents_response(&incidents_response))
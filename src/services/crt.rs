@@ -2,19 +2,21 @@ use anyhow::Result;
 use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
+use crate::services::utils::table::{Table, section};
 use crate::{log_debug, log_error, log_warn};
 /// Certificate entry from crt.sh API
 #[derive(Debug, Deserialize, Serialize)]
-struct CrtEntry {
+pub(crate) struct CrtEntry {
     issuer_ca_id: u64,
-    issuer_name: String,
-    common_name: Option<String>,
-    name_value: String,
-    id: u64,
-    entry_timestamp: String,
-    not_before: String,
-    not_after: String,
-    serial_number: String,
+    pub(crate) issuer_name: String,
+    pub(crate) common_name: Option<String>,
+    pub(crate) name_value: String,
+    pub(crate) id: u64,
+    pub(crate) entry_timestamp: String,
+    pub(crate) not_before: String,
+    pub(crate) not_after: String,
+    pub(crate) serial_number: String,
 }
 
 /// Processed certificate information for display
@@ -101,8 +103,18 @@ impl CrtService {
         }
     }
 
+    /// Issuers of the currently valid certificates logged for a domain
+    pub(crate) async fn valid_certificate_issuers(&self, domain: &str) -> Result<Vec<String>> {
+        let certificates = self.fetch_certificates(domain).await?;
+        Ok(self
+            .filter_valid_certificates(certificates)
+            .into_iter()
+            .map(|cert| cert.issuer)
+            .collect())
+    }
+
     /// Fetch certificates from crt.sh API
-    async fn fetch_certificates(&self, domain: &str) -> Result<Vec<CrtEntry>> {
+    pub(crate) async fn fetch_certificates(&self, domain: &str) -> Result<Vec<CrtEntry>> {
         let url = format!("https://crt.sh/json?q={}", urlencoding::encode(domain));
         log_debug!("Fetching certificates from URL: {}", url);
 
@@ -129,7 +141,7 @@ impl CrtService {
         }
 
         // Parse JSON response with timeout
-        let json_text = tokio::time::timeout(Duration::from_secs(10), response.text())
+        let json_text = tokio::time::timeout(Duration::from_secs(10), response.limited_text())
             .await
             .map_err(|_| {
                 anyhow::anyhow!("Response parsing timeout - crt.sh returned too much data")
@@ -211,16 +223,16 @@ impl CrtService {
                 serial_number: cert.serial_number,
                 not_before: self.format_date_display(&not_before),
                 not_after: self.format_date_display(&not_after),
-                entry_timestamp: cert.entry_timestamp,
+                entry_timestamp: self
+                    .parse_crt_date(&cert.entry_timestamp)
+                    .map(|date| self.format_date_display(&date))
+                    .unwrap_or(cert.entry_timestamp),
                 is_valid,
             };
 
             valid_certs.push(processed_cert);
         }
 
-        // Sort by not_after date (most recent expiration first)
-        valid_certs.sort_by(|a, b| b.not_after.cmp(&a.not_after));
-
         // Remove duplicates based on serial number and issuer
         let mut unique_certs = Vec::new();
         let mut seen = std::collections::HashSet::new();
@@ -259,14 +271,13 @@ impl CrtService {
 
     /// Format date for display
     fn format_date_display(&self, date: &DateTime<Utc>) -> String {
-        format!(
-            "{} ({})",
-            date.format("%Y-%m-%d %H:%M:%S UTC"),
-            date.timestamp()
-        )
+        date.format("%Y-%m-%d %H:%M:%S").to_string()
     }
 
-    /// Format certificates for display
+    /// Format certificates for display, newest expiry first
+    ///
+    /// The certificates are a table so that `-SORT` and `-COUNT` can reorder
+    /// or summarize them (e.g. `-COUNT=issuer`).
     fn format_certificates(&self, certificates: &[CertificateEntry], domain: &str) -> String {
         if certificates.is_empty() {
             return format!(
@@ -281,37 +292,33 @@ impl CrtService {
             domain
         ));
         output.push_str(&format!(
-            "Found {} valid (non-expired) certificates from CT logs\n",
+            "Found {} valid (non-expired) certificates from CT logs\n\n",
             certificates.len()
         ));
-        output.push_str("=".repeat(80).as_str());
-        output.push('\n');
-
-        for (index, cert) in certificates.iter().enumerate() {
-            output.push_str(&format!("\n[{}] Certificate #{}\n", index + 1, cert.id));
-            output.push_str(&format!("Common Name: {}\n", cert.common_name));
-
-            if cert.subject_alt_names.len() > 1
-                || (cert.subject_alt_names.len() == 1
-                    && cert.subject_alt_names[0] != cert.common_name)
-            {
-                output.push_str("Subject Alternative Names:\n");
-                for san in &cert.subject_alt_names {
-                    output.push_str(&format!("  - {}\n", san));
-                }
-            }
-
-            output.push_str(&format!("Issuer: {}\n", cert.issuer));
-            output.push_str(&format!("Serial Number: {}\n", cert.serial_number));
-            output.push_str(&format!("Valid From: {}\n", cert.not_before));
-            output.push_str(&format!("Valid Until: {}\n", cert.not_after));
-            output.push_str(&format!("CT Log Entry: {}\n", cert.entry_timestamp));
 
-            if index < certificates.len() - 1 {
-                output.push_str("-".repeat(40).as_str());
-                output.push('\n');
-            }
-        }
+        let rows = certificates
+            .iter()
+            .map(|cert| {
+                vec![
+                    cert.id.to_string(),
+                    cert.common_name.clone(),
+                    cert.not_before.clone(),
+                    cert.not_after.clone(),
+                    cert.entry_timestamp.clone(),
+                    cert.issuer.clone(),
+                    cert.serial_number.clone(),
+                    cert.subject_alt_names.join(", "),
+                ]
+            })
+            .collect();
+        let mut table = Table::new(
+            &["ID", "Common Name", "Valid From", "Valid Until", "Logged", "Issuer", "Serial", "Names"],
+            rows,
+        );
+        // Newest expiry first; -SORT picks another order
+        table.sort_by(3, true);
+        section(&mut output, "Certificates");
+        output.push_str(&table.render());
 
         output.push('\n');
         output.push_str("Note: Data sourced from Certificate Transparency logs via crt.sh\n");
@@ -355,6 +362,7 @@ pub async fn process_crt_query(query: &str) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::utils::table::map_tables;
 
     #[test]
     fn test_crt_query_detection() {
@@ -382,6 +390,39 @@ mod tests {
         assert_eq!(CrtService::parse_crt_query("example.com"), None);
     }
 
+    fn certificate(id: u64, issuer: &str, not_after: &str) -> CertificateEntry {
+        CertificateEntry {
+            id,
+            common_name: "example.com".to_string(),
+            subject_alt_names: vec!["example.com".to_string(), "www.example.com".to_string()],
+            issuer: issuer.to_string(),
+            serial_number: format!("0{}", id),
+            not_before: "2025-01-01 00:00:00".to_string(),
+            not_after: not_after.to_string(),
+            entry_timestamp: "2025-01-01 00:05:00".to_string(),
+            is_valid: true,
+        }
+    }
+
+    #[test]
+    fn test_certificates_table() {
+        let certificates = [
+            certificate(1, "C=US, O=Let's Encrypt, CN=R11", "2025-03-01 00:00:00"),
+            certificate(2, "C=US, O=Google Trust Services, CN=WE1", "2025-04-01 00:00:00"),
+        ];
+        let output = CrtService::new().format_certificates(&certificates, "example.com");
+
+        let table = output.lines().skip_while(|line| !line.starts_with("ID ")).collect::<Vec<_>>();
+        assert!(table[0].starts_with("ID | Common Name | Valid From          | Valid Until         | Logged "));
+        assert!(table[2].starts_with("2  | example.com "));
+        assert!(table[3].starts_with("1  | example.com "));
+        assert!(table[3].ends_with("| C=US, O=Let's Encrypt, CN=R11         | 01     | example.com, www.example.com"));
+
+        // The table is read back by -SORT and -COUNT
+        let issuers = map_tables(&output, |table| table.count_by(table.column("issuer").unwrap()).render()).unwrap();
+        assert!(issuers.contains("C=US, O=Google Trust Services, CN=WE1 | 1\n"));
+    }
+
     #[tokio::test]
     async fn test_crt_service_creation() {
         let service = CrtService::new();
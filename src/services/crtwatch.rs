@@ -0,0 +1,218 @@
+// WHOIS Server - Certificate Transparency Watch
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Lightweight Certificate Transparency monitoring (`-CRTWATCH`)
+//!
+//! The first `example.com-CRTWATCH` query stores the IDs of all certificates
+//! crt.sh knows for the domain as a baseline in LMDB. Every later query
+//! fetches the log entries again and reports only the certificates logged
+//! since the previous check, then adds them to the baseline, so a cron job
+//! running `whois -h ... example.com-CRTWATCH` gets a report of new
+//! certificates and nothing else. The baseline is per domain, shared by
+//! all clients.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::CRTWATCH_LMDB_PATH;
+use crate::core::request_context::RequestContext;
+use crate::log_debug;
+use crate::services::crt::{CrtEntry, CrtService};
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::storage::lmdb::LmdbStorage;
+
+/// Certificates known for a domain as of the last check
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+    created_at: u64,
+    checked_at: u64,
+    certificate_ids: BTreeSet<u64>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time should be after Unix epoch")
+        .as_secs()
+}
+
+fn format_date(secs: u64) -> String {
+    DateTime::from_timestamp(secs as i64, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_default()
+}
+
+/// Domain name to watch, lowercased; crt.sh wildcards are not accepted
+fn parse_domain(target: &str) -> Option<String> {
+    let domain = target.trim().trim_end_matches('.').to_lowercase();
+    let valid = domain.contains('.') &&
+        !domain.starts_with('.') &&
+        domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    valid.then_some(domain)
+}
+
+/// Entries not in the baseline, one per certificate
+///
+/// crt.sh lists a precertificate and the final certificate as two entries
+/// with the same serial number; only the first logged one is kept.
+fn new_entries<'a>(entries: &'a [CrtEntry], baseline: &Baseline) -> Vec<&'a CrtEntry> {
+    let mut seen = HashSet::new();
+    let mut new: Vec<&CrtEntry> = entries.iter().filter(|entry| !baseline.certificate_ids.contains(&entry.id)).collect();
+    new.sort_by(|a, b| a.entry_timestamp.cmp(&b.entry_timestamp));
+    new.retain(|entry| seen.insert((entry.serial_number.clone(), entry.issuer_name.clone())));
+    new
+}
+
+fn format_entry(entry: &CrtEntry) -> String {
+    let mut names: Vec<&str> = entry.name_value.lines().map(str::trim).filter(|name| !name.is_empty()).collect();
+    names.sort();
+    names.dedup();
+
+    let mut output = String::new();
+    output.push_str(&format!("crt-sh-id:      {}\n", entry.id));
+    output.push_str(&format!("common-name:    {}\n", entry.common_name.as_deref().unwrap_or("-")));
+    for name in names {
+        output.push_str(&format!("name:           {}\n", name));
+    }
+    output.push_str(&format!("issuer:         {}\n", entry.issuer_name));
+    output.push_str(&format!("serial:         {}\n", entry.serial_number));
+    output.push_str(&format!("valid:          {} - {}\n", entry.not_before, entry.not_after));
+    output.push_str(&format!("logged:         {}\n", entry.entry_timestamp));
+    output
+}
+
+/// Compare the current CT log entries of a domain with its baseline
+pub async fn process_crtwatch_query(target: &str) -> Result<String> {
+    let Some(domain) = parse_domain(target) else {
+        return Ok(format!("% Cannot watch '{}': expected a domain name such as example.com\n", target.trim()));
+    };
+
+    let entries = CrtService::new().fetch_certificates(&domain).await?;
+    let storage = LmdbStorage::new(CRTWATCH_LMDB_PATH)?;
+    let key = format!("crtwatch:{}", domain);
+    let previous: Option<Baseline> = storage.get_json(&key)?;
+    let now = now_secs();
+
+    let mut output = format!("% Certificate Transparency watch for {}\n%\n", domain);
+    let mut baseline = match previous {
+        None => {
+            let baseline = Baseline {
+                created_at: now,
+                checked_at: now,
+                certificate_ids: entries.iter().map(|entry| entry.id).collect(),
+            };
+            output.push_str(&format!(
+                "% Baseline created with {} logged certificates.\n% Query {}-CRTWATCH again to see certificates logged after now.\n",
+                baseline.certificate_ids.len(),
+                domain
+            ));
+            storage.put_json(&key, &baseline)?;
+            return Ok(output);
+        }
+        Some(baseline) => baseline,
+    };
+
+    let new = new_entries(&entries, &baseline);
+    log_debug!("CRTWATCH {}: {} new of {} logged certificates", domain, new.len(), entries.len());
+    output.push_str(&format!(
+        "% Baseline from {}, last checked {}\n",
+        format_date(baseline.created_at),
+        format_date(baseline.checked_at)
+    ));
+    if new.is_empty() {
+        output.push_str("% No new certificates logged since the last check\n");
+    } else {
+        output.push_str(&format!("% {} new certificates logged since the last check\n", new.len()));
+        for entry in &new {
+            output.push('\n');
+            output.push_str(&format_entry(entry));
+        }
+    }
+
+    baseline.certificate_ids.extend(entries.iter().map(|entry| entry.id));
+    baseline.checked_at = now;
+    storage.put_json(&key, &baseline)?;
+    Ok(output)
+}
+
+/// Certificate Transparency watch service
+pub struct CrtWatchService;
+
+#[async_trait]
+impl QueryService for CrtWatchService {
+    fn name(&self) -> &'static str {
+        "crtwatch"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-CRTWATCH"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "SECURITY INVESTIGATION",
+            summary: "Certificates logged in CT since the last check (the first query stores a baseline)",
+            example: "example.com-CRTWATCH",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "crt.sh, baseline in LMDB"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        // Every call advances the baseline
+        None
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_crtwatch_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, serial: &str, logged: &str) -> CrtEntry {
+        serde_json::from_value(serde_json::json!({
+            "issuer_ca_id": 1,
+            "issuer_name": "C=US, O=Let's Encrypt, CN=R3",
+            "common_name": "example.com",
+            "name_value": "example.com\nwww.example.com",
+            "id": id,
+            "entry_timestamp": logged,
+            "not_before": "2025-01-01T00:00:00",
+            "not_after": "2025-04-01T00:00:00",
+            "serial_number": serial,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_new_entries() {
+        let entries = [
+            entry(1, "aa", "2025-01-01T00:00:00"),
+            entry(3, "bb", "2025-02-01T00:00:05"),
+            // Precertificate of the same certificate, logged first
+            entry(2, "bb", "2025-02-01T00:00:00"),
+        ];
+        let baseline = Baseline { certificate_ids: BTreeSet::from([1]), ..Default::default() };
+
+        let new = new_entries(&entries, &baseline);
+        assert_eq!(new.iter().map(|entry| entry.id).collect::<Vec<_>>(), [2]);
+        assert!(format_entry(new[0]).contains("name:           www.example.com\n"));
+    }
+
+    #[test]
+    fn test_parse_domain() {
+        assert_eq!(parse_domain("Example.COM."), Some("example.com".to_string()));
+        assert_eq!(parse_domain("%.example.com"), None);
+        assert_eq!(parse_domain("localhost"), None);
+    }
+}
@@ -0,0 +1,468 @@
+// WHOIS Server - Scheduled Digests
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Daily or weekly digests delivered over the notification channels
+//!
+//! Digests are configured in a TOML file (`--digest-file`):
+//!
+//! ```toml
+//! [[digests]]
+//! name = "noc"
+//! schedule = "weekly"   # or "daily"
+//! weekday = "mon"       # weekly digests only
+//! hour = 8              # UTC
+//! top_queries = 10
+//! # Watched resources
+//! domains = ["example.com"]
+//! certificates = ["example.com", "mail.example.com:993"]
+//! dn42_objects = ["AS4242420000", "EXAMPLE-MNT"]
+//! expiry_days = 30
+//!
+//! [[digests.notify]]
+//! type = "email"
+//! smtp_host = "smtp.example.net"
+//! from = "whois@example.net"
+//! to = ["noc@example.net"]
+//! ```
+//!
+//! A digest lists the most popular queries (per query type until the
+//! popularity ranking has counted individual queries), the upstreams that
+//! failed since the previous digest, watched domains and certificates that
+//! expire within `expiry_days`, and changes to the watched DN42 registry
+//! objects. Registry objects are compared with the copy stored in LMDB when
+//! the previous digest was sent.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc, Weekday};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::config::DIGEST_LMDB_PATH;
+use crate::core::metrics::{query_totals, upstream_totals};
+use crate::core::scheduler::{CronSchedule, Job, Schedule as JobSchedule};
+use crate::core::stats::popular_queries;
+use crate::dn42::query_dn42_raw_managed;
+use crate::services::notify::{ChannelSpec, Notification, Notifier, validate_channels};
+use crate::services::report::registration_expiry;
+use crate::services::ssl::SslService;
+use crate::services::whois::query_with_iana_referral;
+use crate::storage::lmdb::LmdbStorage;
+use crate::{log_info, log_warn};
+
+/// Changed lines of a DN42 object listed in a digest
+const MAX_CHANGED_LINES: usize = 10;
+/// Upstreams listed in a digest
+const MAX_UPSTREAMS: usize = 10;
+
+fn default_hour() -> u32 {
+    8
+}
+
+fn default_weekday() -> String {
+    "mon".to_string()
+}
+
+fn default_top_queries() -> usize {
+    10
+}
+
+fn default_expiry_days() -> i64 {
+    30
+}
+
+#[derive(Deserialize)]
+struct DigestFile {
+    #[serde(default)]
+    digests: Vec<Digest>,
+}
+
+/// How often a digest is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Schedule {
+    Daily,
+    Weekly,
+}
+
+/// A configured digest
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Digest {
+    pub name: String,
+    pub schedule: Schedule,
+    /// Hour of the day (UTC) the digest is sent
+    #[serde(default = "default_hour")]
+    pub hour: u32,
+    /// Day of the week weekly digests are sent
+    #[serde(default = "default_weekday")]
+    pub weekday: String,
+    #[serde(default = "default_top_queries")]
+    pub top_queries: usize,
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Hosts whose TLS certificate is checked, as `host` or `host:port`
+    #[serde(default)]
+    pub certificates: Vec<String>,
+    #[serde(default)]
+    pub dn42_objects: Vec<String>,
+    /// Domains and certificates expiring within this many days are listed
+    #[serde(default = "default_expiry_days")]
+    pub expiry_days: i64,
+    #[serde(default)]
+    pub notify: Vec<ChannelSpec>,
+}
+
+impl Digest {
+    fn weekday(&self) -> Weekday {
+        self.weekday.parse().unwrap_or(Weekday::Mon)
+    }
+
+    /// When the digest is due, as a cron expression (UTC)
+    fn cron(&self) -> CronSchedule {
+        let weekday = match self.schedule {
+            Schedule::Daily => "*".to_string(),
+            Schedule::Weekly => self.weekday().num_days_from_sunday().to_string(),
+        };
+        format!("0 {} * * {}", self.hour, weekday)
+            .parse()
+            .expect("digest hour and weekday are validated")
+    }
+
+    fn title(&self) -> String {
+        let period = match self.schedule {
+            Schedule::Daily => "daily",
+            Schedule::Weekly => "weekly",
+        };
+        format!("{}: {} digest", self.name, period)
+    }
+}
+
+/// Parse digest configuration from TOML
+pub fn parse_digest_config(content: &str) -> Result<Vec<Digest>> {
+    let file: DigestFile = toml::from_str(content)?;
+    for digest in &file.digests {
+        if digest.name.is_empty() {
+            return Err(anyhow!("digest name must not be empty"));
+        }
+        if digest.hour > 23 {
+            return Err(anyhow!("{}: hour must be between 0 and 23", digest.name));
+        }
+        if digest.weekday.parse::<Weekday>().is_err() {
+            return Err(anyhow!("{}: unknown weekday '{}'", digest.name, digest.weekday));
+        }
+        if digest.notify.is_empty() {
+            return Err(anyhow!("{} needs a notify channel", digest.name));
+        }
+        validate_channels(&digest.notify).map_err(|e| anyhow!("{}: {}", digest.name, e))?;
+        if file.digests.iter().filter(|d| d.name == digest.name).count() > 1 {
+            return Err(anyhow!("{} is listed more than once", digest.name));
+        }
+    }
+    Ok(file.digests)
+}
+
+/// Load digest configuration from a TOML file
+pub fn load_digest_file(path: &Path) -> Result<Vec<Digest>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read digest file {}: {}", path.display(), e))?;
+    parse_digest_config(&content).map_err(|e| anyhow!("Invalid digest file {}: {}", path.display(), e))
+}
+
+/// Counters at the time the previous digest was sent
+#[derive(Debug, Clone, Default)]
+struct Totals {
+    queries: BTreeMap<String, u64>,
+    upstreams: BTreeMap<String, (u64, u64)>,
+}
+
+impl Totals {
+    fn now() -> Self {
+        Self { queries: query_totals(), upstreams: upstream_totals() }
+    }
+}
+
+/// A titled list of digest lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Section {
+    title: &'static str,
+    lines: Vec<String>,
+}
+
+fn top_queries_section(digest: &Digest, previous: &Totals, current: &Totals) -> Section {
+    let popular = popular_queries();
+    if !popular.is_empty() {
+        return Section {
+            title: "Top queries",
+            lines: popular
+                .iter()
+                .take(digest.top_queries)
+                .map(|ranked| format!("{:>8}  {}", ranked.count, ranked.query))
+                .collect(),
+        };
+    }
+
+    let mut by_type: Vec<(&String, u64)> = current
+        .queries
+        .iter()
+        .map(|(query_type, total)| (query_type, total.saturating_sub(previous.queries.get(query_type).copied().unwrap_or(0))))
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    by_type.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    Section {
+        title: "Top query types",
+        lines: by_type
+            .iter()
+            .take(digest.top_queries)
+            .map(|(query_type, count)| format!("{:>8}  {}", count, query_type))
+            .collect(),
+    }
+}
+
+/// Upstreams with failed requests since the previous digest, worst first
+fn upstream_section(previous: &Totals, current: &Totals) -> Section {
+    let mut failing: Vec<(&String, u64, u64)> = current
+        .upstreams
+        .iter()
+        .map(|(source, (requests, failed))| {
+            let (old_requests, old_failed) = previous.upstreams.get(source).copied().unwrap_or_default();
+            (source, requests.saturating_sub(old_requests), failed.saturating_sub(old_failed))
+        })
+        .filter(|(_, _, failed)| *failed > 0)
+        .collect();
+    let rate = |requests: u64, failed: u64| (failed as f64) / (requests.max(1) as f64);
+    failing.sort_by(|a, b| rate(b.1, b.2).total_cmp(&rate(a.1, a.2)).then_with(|| a.0.cmp(b.0)));
+    Section {
+        title: "Upstream errors",
+        lines: failing
+            .iter()
+            .take(MAX_UPSTREAMS)
+            .map(|(source, requests, failed)| {
+                format!("{}: {}/{} requests failed ({:.1}%)", source, failed, requests, rate(*requests, *failed) * 100.0)
+            })
+            .collect(),
+    }
+}
+
+/// Line for a resource expiring at `expiry`, if that is within `days`
+fn expiry_line(what: &str, expiry: DateTime<Utc>, now: DateTime<Utc>, days: i64) -> Option<String> {
+    let remaining = (expiry - now).num_days();
+    if remaining >= days {
+        return None;
+    }
+    Some(if expiry < now {
+        format!("{} expired on {}", what, expiry.format("%Y-%m-%d"))
+    } else {
+        format!("{} expires on {} ({} days)", what, expiry.format("%Y-%m-%d"), remaining)
+    })
+}
+
+async fn expiry_section(digest: &Digest, now: DateTime<Utc>) -> Section {
+    let mut lines = Vec::new();
+    for domain in &digest.domains {
+        match query_with_iana_referral(domain).await {
+            Ok(response) => match registration_expiry(&response) {
+                Some(expiry) => lines.extend(expiry_line(&format!("domain {}", domain), expiry, now, digest.expiry_days)),
+                None => lines.push(format!("domain {}: no expiry date published", domain)),
+            },
+            Err(e) => lines.push(format!("domain {}: lookup failed: {}", domain, e)),
+        }
+    }
+
+    for target in &digest.certificates {
+        let (host, port) = match target.rsplit_once(':') {
+            Some((host, port)) if port.parse::<u16>().is_ok() => (host.to_string(), port.parse().unwrap_or(443)),
+            _ => (target.clone(), 443),
+        };
+        // The TLS handshake blocks, so it runs on its own task
+        let result = tokio::spawn(async move { SslService::new().get_certificate_info(&host, port, None).await }).await;
+        match result {
+            Ok(Ok(cert)) => match DateTime::from_timestamp(cert.not_after_timestamp, 0) {
+                Some(expiry) => lines.extend(expiry_line(&format!("certificate {}", target), expiry, now, digest.expiry_days)),
+                None => lines.push(format!("certificate {}: invalid expiry date", target)),
+            },
+            Ok(Err(e)) => lines.push(format!("certificate {}: check failed: {}", target, e)),
+            Err(e) => lines.push(format!("certificate {}: check failed: {}", target, e)),
+        }
+    }
+    Section { title: "Expiring domains and certificates", lines }
+}
+
+/// Lines removed from and added to a registry object, as `-`/`+` lines
+fn object_changes(old: &str, new: &str) -> Vec<String> {
+    let old_lines: BTreeSet<&str> = old.lines().collect();
+    let new_lines: BTreeSet<&str> = new.lines().collect();
+    let removed = old.lines().filter(|line| !new_lines.contains(line)).map(|line| format!("  - {}", line));
+    let added = new.lines().filter(|line| !old_lines.contains(line)).map(|line| format!("  + {}", line));
+    removed.chain(added).collect()
+}
+
+async fn dn42_section(digest: &Digest, storage: Option<&LmdbStorage>) -> Section {
+    let mut lines = Vec::new();
+    for object in &digest.dn42_objects {
+        let current = match query_dn42_raw_managed(object).await {
+            Ok(current) => current,
+            Err(e) => {
+                lines.push(format!("{}: lookup failed: {}", object, e));
+                continue;
+            }
+        };
+        let Some(storage) = storage else {
+            continue;
+        };
+        let key = format!("dn42:{}:{}", digest.name, object.to_uppercase());
+        let previous: Option<String> = storage.get_json(&key).unwrap_or_default();
+        if let Some(previous) = previous
+            && previous != current
+        {
+            let changes = object_changes(&previous, &current);
+            lines.push(format!("{} changed ({} line(s))", object, changes.len()));
+            lines.extend(changes.into_iter().take(MAX_CHANGED_LINES));
+        }
+        if let Err(e) = storage.put_json(&key, &current) {
+            log_warn!("Digest: failed to store {}: {}", object, e);
+        }
+    }
+    Section { title: "DN42 registry changes", lines }
+}
+
+fn format_digest(sections: &[Section]) -> String {
+    let mut text = String::new();
+    for section in sections {
+        text.push_str(&format!("== {} ==\n", section.title));
+        if section.lines.is_empty() {
+            text.push_str("(none)\n");
+        }
+        for line in &section.lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.push('\n');
+    }
+    text.trim_end().to_string()
+}
+
+async fn build_digest(digest: &Digest, previous: &Totals, current: &Totals, storage: Option<&LmdbStorage>) -> Notification {
+    let mut sections = vec![top_queries_section(digest, previous, current), upstream_section(previous, current)];
+    if !digest.domains.is_empty() || !digest.certificates.is_empty() {
+        sections.push(expiry_section(digest, Utc::now()).await);
+    }
+    if !digest.dn42_objects.is_empty() {
+        sections.push(dn42_section(digest, storage).await);
+    }
+
+    let data: serde_json::Map<String, serde_json::Value> =
+        sections.iter().map(|section| (section.title.to_string(), json!(section.lines))).collect();
+    Notification::new(digest.title(), format_digest(&sections))
+        .with_data(json!({ "digest": digest.name, "sections": data }))
+}
+
+/// The `digest:<name>` job sending one digest
+fn digest_job(digest: Digest, notifier: Notifier) -> Job {
+    let name = format!("digest:{}", digest.name);
+    let schedule = JobSchedule::Cron(digest.cron());
+    let digest = Arc::new(digest);
+    let notifier = Arc::new(notifier);
+    let previous = Arc::new(Mutex::new(Totals::default()));
+    Job::new(name, schedule, move || {
+        let (digest, notifier, previous) = (digest.clone(), notifier.clone(), previous.clone());
+        async move {
+            let storage = match LmdbStorage::new(DIGEST_LMDB_PATH) {
+                Ok(storage) => Some(storage),
+                Err(e) => {
+                    log_warn!("Digest: failed to open state database: {}", e);
+                    None
+                }
+            };
+            let current = Totals::now();
+            let last = previous.lock().unwrap().clone();
+            let notification = build_digest(&digest, &last, &current, storage.as_ref()).await;
+            *previous.lock().unwrap() = current;
+            notifier
+                .deliver(&notification)
+                .await
+                .map_err(|e| anyhow!("delivery of digest {} failed: {}", digest.name, e))?;
+            log_info!("Digest: sent {}", digest.title());
+            Ok(())
+        }
+    })
+}
+
+/// One job per configured digest (call this from main.rs)
+pub fn digest_jobs(digests: Vec<Digest>) -> Vec<Job> {
+    log_info!("Starting {} scheduled digest(s)", digests.len());
+    let mut jobs = Vec::new();
+    for digest in digests {
+        match Notifier::new(&digest.notify) {
+            Ok(notifier) => jobs.push(digest_job(digest, notifier)),
+            Err(e) => log_warn!("Digest: notification channels of {} unusable: {}", digest.name, e),
+        }
+    }
+    jobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+
+    const CONFIG: &str = "[[digests]]\nname = \"noc\"\nschedule = \"weekly\"\nweekday = \"wed\"\nhour = 6\n\n[[digests.notify]]\ntype = \"webhook\"\nurl = \"https://hooks.example.net/digest\"\n";
+
+    #[test]
+    fn test_parse_digest_config() {
+        let digests = parse_digest_config(CONFIG).unwrap();
+        assert_eq!(digests.len(), 1);
+        assert_eq!(digests[0].schedule, Schedule::Weekly);
+        assert_eq!(digests[0].weekday(), Weekday::Wed);
+        assert_eq!(digests[0].expiry_days, 30);
+
+        assert!(parse_digest_config(&CONFIG.replace("hour = 6", "hour = 24")).is_err());
+        assert!(parse_digest_config(&CONFIG.replace("\"wed\"", "\"someday\"")).is_err());
+        assert!(parse_digest_config("[[digests]]\nname = \"noc\"\nschedule = \"daily\"\n").is_err());
+    }
+
+    #[test]
+    fn test_next_run() {
+        let mut digest = parse_digest_config(CONFIG).unwrap().remove(0);
+        // 2025-01-01 was a Wednesday
+        let now = DateTime::parse_from_rfc3339("2025-01-01T05:30:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(digest.cron().next_after(now).unwrap().to_rfc3339(), "2025-01-01T06:00:00+00:00");
+        let later = DateTime::parse_from_rfc3339("2025-01-01T06:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(digest.cron().next_after(later).unwrap().to_rfc3339(), "2025-01-08T06:00:00+00:00");
+
+        digest.schedule = Schedule::Daily;
+        assert_eq!(digest.cron().next_after(later).unwrap().to_rfc3339(), "2025-01-02T06:00:00+00:00");
+    }
+
+    #[test]
+    fn test_digest_sections() {
+        let previous = Totals {
+            queries: BTreeMap::new(),
+            upstreams: BTreeMap::from([("whois.ripe.net".to_string(), (100, 1))]),
+        };
+        let current = Totals {
+            queries: BTreeMap::new(),
+            upstreams: BTreeMap::from([
+                ("whois.ripe.net".to_string(), (150, 6)),
+                ("whois.arin.net".to_string(), (10, 0)),
+            ]),
+        };
+        let upstreams = upstream_section(&previous, &current);
+        assert_eq!(upstreams.lines, vec!["whois.ripe.net: 5/50 requests failed (10.0%)"]);
+
+        assert_eq!(
+            object_changes("aut-num: AS4242420000\nmnt-by: OLD-MNT\n", "aut-num: AS4242420000\nmnt-by: NEW-MNT\n"),
+            vec!["  - mnt-by: OLD-MNT", "  + mnt-by: NEW-MNT"]
+        );
+
+        let now = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let soon = now + ChronoDuration::days(10);
+        assert_eq!(expiry_line("domain example.com", soon, now, 30).unwrap(), "domain example.com expires on 2025-01-11 (10 days)");
+        assert_eq!(expiry_line("domain example.com", now + ChronoDuration::days(90), now, 30), None);
+
+        let text = format_digest(&[upstreams, Section { title: "DN42 registry changes", lines: Vec::new() }]);
+        assert_eq!(text, "== Upstream errors ==\nwhois.ripe.net: 5/50 requests failed (10.0%)\n\n== DN42 registry changes ==\n(none)");
+    }
+}
@@ -2,9 +2,17 @@
 //!
 //! This module provides DNS functionality using Cloudflare's DNS-over-HTTPS API
 //! with support for multiple record types: A, AAAA, CNAME, MX, TXT, NS, SOA, PTR
+//!
+//! Reverse lookups (`-RDNS`, or an address with `-DNS`) check that each PTR
+//! name resolves back to the address, and accept `in-addr.arpa`/`ip6.arpa`
+//! names as well as addresses.
 
 use anyhow::Result;
-use std::net::IpAddr;
+use async_trait::async_trait;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
 use crate::services::utils::doh::{DohClient, DnsRecordType, DnsAnswer};
 use crate::{log_debug, log_error};
 
@@ -59,7 +67,7 @@ impl DnsService {
         Ok(output)
     }
 
-    /// Reverse DNS lookup (PTR records)
+    /// Reverse DNS lookup (PTR records), forward-confirming each name
     pub async fn query_rdns(&self, ip: IpAddr) -> Result<String> {
         log_debug!("Querying reverse DNS for IP: {}", ip);
 
@@ -85,16 +93,42 @@ impl DnsService {
                         return Ok(format!("No reverse DNS record found for IP: {}\n", ip));
                     }
 
-                    for answer in answers {
-                        // Remove trailing dot if present
-                        let data = if answer.data.ends_with('.') {
-                            &answer.data[..answer.data.len() - 1]
-                        } else {
-                            &answer.data
+                    let ptr_answers: Vec<&DnsAnswer> = answers
+                        .iter()
+                        .filter(|answer| answer.record_type == DnsRecordType::PTR as u32)
+                        .collect();
+                    let forward = futures::future::join_all(
+                        ptr_answers.iter().map(|answer| self.forward_addresses(answer.data.trim_end_matches('.'), ip))
+                    ).await;
+
+                    let mut confirmed = 0;
+                    for (answer, addresses) in ptr_answers.iter().zip(forward) {
+                        let status = match addresses {
+                            Ok(addresses) if addresses.contains(&ip) => {
+                                confirmed += 1;
+                                "forward-confirmed".to_string()
+                            }
+                            Ok(addresses) if addresses.is_empty() => {
+                                format!("not forward-confirmed (no {} record)", if ip.is_ipv4() { "A" } else { "AAAA" })
+                            }
+                            Ok(addresses) => {
+                                let addresses: Vec<String> = addresses.iter().map(|a| a.to_string()).collect();
+                                format!("not forward-confirmed (resolves to {})", addresses.join(", "))
+                            }
+                            Err(e) => format!("forward lookup failed: {}", e),
                         };
-
-                        output.push_str(&format!("  {} (TTL: {})\n", data, answer.TTL));
+                        output.push_str(&format!(
+                            "  {} (TTL: {}) - {}\n",
+                            answer.data.trim_end_matches('.'),
+                            answer.TTL,
+                            status
+                        ));
                     }
+                    output.push_str(&format!(
+                        "\nForward-confirmed: {} of {} PTR records\n",
+                        confirmed,
+                        ptr_answers.len()
+                    ));
 
                     Ok(output)
                 } else {
@@ -108,6 +142,20 @@ impl DnsService {
         }
     }
 
+    /// Addresses of the same family as `ip` that a PTR hostname resolves to
+    async fn forward_addresses(&self, hostname: &str, ip: IpAddr) -> Result<Vec<IpAddr>> {
+        let record_type = if ip.is_ipv4() { DnsRecordType::A } else { DnsRecordType::AAAA };
+        let response = self.client.query(hostname, record_type.as_str()).await?;
+        Ok(
+            response.Answer
+                .unwrap_or_default()
+                .iter()
+                .filter(|answer| answer.record_type == (record_type as u32))
+                .filter_map(|answer| answer.data.parse().ok())
+                .collect()
+        )
+    }
+
     /// Format DOH answer records for display
     fn format_doh_answer(&self, answer: &DnsAnswer, record_type: &str) -> String {
         match record_type {
@@ -202,9 +250,86 @@ impl DnsService {
         true
     }
 
-    /// Parse IP address
+    /// Parse IP address, or the `in-addr.arpa`/`ip6.arpa` name of one
     pub fn parse_ip_address(query: &str) -> Option<IpAddr> {
-        query.parse::<IpAddr>().ok()
+        query.parse::<IpAddr>().ok().or_else(|| Self::parse_reverse_name(query))
+    }
+
+    /// Address of a full-length reverse DNS name (e.g., 1.1.1.1.in-addr.arpa -> 1.1.1.1)
+    fn parse_reverse_name(name: &str) -> Option<IpAddr> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+
+        if let Some(labels) = name.strip_suffix(".in-addr.arpa") {
+            let octets: Vec<u8> = labels
+                .split('.')
+                .rev()
+                .map(|label| label.parse().ok())
+                .collect::<Option<_>>()?;
+            let octets: [u8; 4] = octets.try_into().ok()?;
+            return Some(IpAddr::V4(Ipv4Addr::from(octets)));
+        }
+
+        let labels = name.strip_suffix(".ip6.arpa")?;
+        let nibbles: Vec<&str> = labels.split('.').collect();
+        if nibbles.len() != 32 {
+            return None;
+        }
+        let mut address = 0u128;
+        for nibble in nibbles.iter().rev() {
+            if nibble.len() != 1 {
+                return None;
+            }
+            address = (address << 4) | (u128::from_str_radix(nibble, 16).ok()?);
+        }
+        Some(IpAddr::V6(Ipv6Addr::from(address)))
+    }
+}
+
+/// Process a -RDNS query for an address or reverse DNS name
+pub async fn process_rdns_query(query: &str) -> Result<String> {
+    match DnsService::parse_ip_address(query.trim()) {
+        Some(ip) => DnsService::new().query_rdns(ip).await,
+        None =>
+            Ok(
+                format!(
+                    "Invalid reverse DNS query. Please provide an IP address or in-addr.arpa/ip6.arpa name.\nQuery: {}\n",
+                    query.trim()
+                )
+            ),
+    }
+}
+
+/// `-RDNS`: PTR records with forward confirmation
+pub struct RdnsService;
+
+#[async_trait]
+impl QueryService for RdnsService {
+    fn name(&self) -> &'static str {
+        "rdns"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-RDNS"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "Reverse DNS (PTR) names and whether each resolves back to the address",
+            example: "1.1.1.1-RDNS",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Cloudflare DNS over HTTPS"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(300))
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_rdns_query(target).await
     }
 }
 
@@ -260,4 +385,21 @@ mod tests {
         assert!(DnsService::parse_ip_address("2001:4860:4860::8888").is_some());
         assert!(DnsService::parse_ip_address("example.com").is_none());
     }
+
+    #[test]
+    fn test_reverse_name_parsing() {
+        assert_eq!(
+            DnsService::parse_ip_address("1.0.0.127.in-addr.arpa"),
+            Some("127.0.0.1".parse().unwrap())
+        );
+        assert_eq!(
+            DnsService::parse_ip_address(
+                "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.IP6.ARPA."
+            ),
+            Some("2001:db8::1".parse().unwrap())
+        );
+        // Zone names of whole networks are not addresses
+        assert!(DnsService::parse_ip_address("0.0.127.in-addr.arpa").is_none());
+        assert!(DnsService::parse_ip_address("8.b.d.0.1.0.0.2.ip6.arpa").is_none());
+    }
 }
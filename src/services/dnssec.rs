@@ -0,0 +1,603 @@
+// WHOIS Server - DNSSEC Chain Validation
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! DNSSEC chain of trust report (`-DNSSEC`)
+//!
+//! Walks from the root trust anchors down to the queried name over
+//! DNS-over-HTTPS. At every zone cut the DS RRset is validated with the
+//! parent's keys and the DNSKEY RRset with a key matching one of the DS
+//! records. Signatures are verified locally, so a zone that the resolver
+//! refuses (SERVFAIL) is still reported with the link that breaks. A
+//! delegation without DS records is reported as insecure; the NSEC/NSEC3
+//! proof of that absence is not checked.
+//!
+//! Supported algorithms: RSA/SHA-1 (5, 7), RSA/SHA-256 (8), RSA/SHA-512
+//! (10), ECDSA P-256 (13), ECDSA P-384 (14) and Ed25519 (15).
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use ring::{digest, signature};
+use std::time::Duration;
+
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::utils::doh::DohClient;
+use crate::log_debug;
+
+const TYPE_SOA: u32 = 6;
+const TYPE_DS: u32 = 43;
+const TYPE_RRSIG: u32 = 46;
+const TYPE_DNSKEY: u32 = 48;
+/// Labels checked below the root
+const MAX_LABELS: usize = 10;
+
+/// Root zone trust anchors (KSK-2017 and KSK-2024) from IANA root-anchors.xml
+const ROOT_ANCHORS: &[&str] = &[
+    "20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D",
+    "38696 8 2 683D2D0ACB8C9B712A1948B27F741219298D0A450D612C483AF444A4C0FB2B16",
+];
+
+fn algorithm_name(algorithm: u8) -> &'static str {
+    match algorithm {
+        5 => "RSASHA1",
+        7 => "RSASHA1-NSEC3-SHA1",
+        8 => "RSASHA256",
+        10 => "RSASHA512",
+        13 => "ECDSAP256SHA256",
+        14 => "ECDSAP384SHA384",
+        15 => "ED25519",
+        16 => "ED448",
+        _ => "unknown",
+    }
+}
+
+fn digest_name(digest_type: u8) -> &'static str {
+    match digest_type {
+        1 => "SHA-1",
+        2 => "SHA-256",
+        4 => "SHA-384",
+        _ => "unknown",
+    }
+}
+
+/// Type code of a record type in presentation format (`DNSKEY`, `TYPE48`)
+fn type_code(mnemonic: &str) -> Option<u16> {
+    let mnemonic = mnemonic.to_ascii_uppercase();
+    match mnemonic.as_str() {
+        "A" => Some(1),
+        "NS" => Some(2),
+        "CNAME" => Some(5),
+        "SOA" => Some(6),
+        "MX" => Some(15),
+        "TXT" => Some(16),
+        "AAAA" => Some(28),
+        "DS" => Some(TYPE_DS as u16),
+        "DNSKEY" => Some(TYPE_DNSKEY as u16),
+        _ => mnemonic.strip_prefix("TYPE")?.parse().ok(),
+    }
+}
+
+/// Lowercase name without the trailing dot, empty for the root
+fn normalize(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Fully qualified form of a normalized name
+fn fqdn(name: &str) -> String {
+    format!("{}.", name)
+}
+
+/// Canonical wire format of a name (RFC 4034 section 6.2)
+fn name_wire(name: &str) -> Vec<u8> {
+    let mut wire = Vec::new();
+    for label in normalize(name).split('.').filter(|label| !label.is_empty()) {
+        wire.push(label.len() as u8);
+        wire.extend_from_slice(label.as_bytes());
+    }
+    wire.push(0);
+    wire
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect()
+}
+
+/// RRSIG timestamps are `YYYYMMDDHHmmSS` or seconds since the epoch
+fn parse_time(field: &str) -> Option<u32> {
+    if field.len() == 14 {
+        let time = NaiveDateTime::parse_from_str(field, "%Y%m%d%H%M%S").ok()?;
+        return time.and_utc().timestamp().try_into().ok();
+    }
+    field.parse().ok()
+}
+
+fn format_time(timestamp: u32) -> String {
+    DateTime::from_timestamp(i64::from(timestamp), 0)
+        .map_or_else(|| timestamp.to_string(), |time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Dnskey {
+    flags: u16,
+    protocol: u8,
+    algorithm: u8,
+    public_key: Vec<u8>,
+}
+
+impl Dnskey {
+    fn parse(data: &str) -> Option<Self> {
+        let mut fields = data.split_whitespace();
+        let flags = fields.next()?.parse().ok()?;
+        let protocol = fields.next()?.parse().ok()?;
+        let algorithm = fields.next()?.parse().ok()?;
+        let public_key = base64::engine::general_purpose::STANDARD.decode(fields.collect::<String>()).ok()?;
+        Some(Self { flags, protocol, algorithm, public_key })
+    }
+
+    fn rdata(&self) -> Vec<u8> {
+        let mut rdata = self.flags.to_be_bytes().to_vec();
+        rdata.push(self.protocol);
+        rdata.push(self.algorithm);
+        rdata.extend_from_slice(&self.public_key);
+        rdata
+    }
+
+    /// Key tag (RFC 4034 appendix B)
+    fn key_tag(&self) -> u16 {
+        let mut sum: u32 = 0;
+        for (i, byte) in self.rdata().iter().enumerate() {
+            sum += if i.is_multiple_of(2) { u32::from(*byte) << 8 } else { u32::from(*byte) };
+        }
+        sum += (sum >> 16) & 0xffff;
+        (sum & 0xffff) as u16
+    }
+
+    /// Secure entry point flag, set on key-signing keys
+    fn is_ksk(&self) -> bool {
+        self.flags & 1 == 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Ds {
+    key_tag: u16,
+    algorithm: u8,
+    digest_type: u8,
+    digest: Vec<u8>,
+}
+
+impl Ds {
+    fn parse(data: &str) -> Option<Self> {
+        let mut fields = data.split_whitespace();
+        let key_tag = fields.next()?.parse().ok()?;
+        let algorithm = fields.next()?.parse().ok()?;
+        let digest_type = fields.next()?.parse().ok()?;
+        let digest = decode_hex(&fields.collect::<String>())?;
+        Some(Self { key_tag, algorithm, digest_type, digest })
+    }
+
+    fn rdata(&self) -> Vec<u8> {
+        let mut rdata = self.key_tag.to_be_bytes().to_vec();
+        rdata.push(self.algorithm);
+        rdata.push(self.digest_type);
+        rdata.extend_from_slice(&self.digest);
+        rdata
+    }
+
+    /// Whether this DS record is the digest of `key` at `owner`
+    fn matches(&self, owner: &str, key: &Dnskey) -> bool {
+        if self.key_tag != key.key_tag() || self.algorithm != key.algorithm {
+            return false;
+        }
+        let algorithm = match self.digest_type {
+            1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+            2 => &digest::SHA256,
+            4 => &digest::SHA384,
+            _ => return false,
+        };
+        let mut data = name_wire(owner);
+        data.extend(key.rdata());
+        digest::digest(algorithm, &data).as_ref() == self.digest.as_slice()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rrsig {
+    type_covered: u16,
+    algorithm: u8,
+    labels: u8,
+    original_ttl: u32,
+    expiration: u32,
+    inception: u32,
+    key_tag: u16,
+    signer: String,
+    signature: Vec<u8>,
+}
+
+impl Rrsig {
+    fn parse(data: &str) -> Option<Self> {
+        let mut fields = data.split_whitespace();
+        let type_covered = type_code(fields.next()?)?;
+        let algorithm = fields.next()?.parse().ok()?;
+        let labels = fields.next()?.parse().ok()?;
+        let original_ttl = fields.next()?.parse().ok()?;
+        let expiration = parse_time(fields.next()?)?;
+        let inception = parse_time(fields.next()?)?;
+        let key_tag = fields.next()?.parse().ok()?;
+        let signer = normalize(fields.next()?);
+        let signature = base64::engine::general_purpose::STANDARD.decode(fields.collect::<String>()).ok()?;
+        Some(Self { type_covered, algorithm, labels, original_ttl, expiration, inception, key_tag, signer, signature })
+    }
+
+    /// Data the signature covers: RRSIG fields and the canonical RRset (RFC 4034 section 3.1.8.1)
+    fn signed_data(&self, owner: &str, rdatas: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = self.type_covered.to_be_bytes().to_vec();
+        data.push(self.algorithm);
+        data.push(self.labels);
+        data.extend_from_slice(&self.original_ttl.to_be_bytes());
+        data.extend_from_slice(&self.expiration.to_be_bytes());
+        data.extend_from_slice(&self.inception.to_be_bytes());
+        data.extend_from_slice(&self.key_tag.to_be_bytes());
+        data.extend(name_wire(&self.signer));
+
+        let mut rdatas = rdatas.to_vec();
+        rdatas.sort();
+        rdatas.dedup();
+        let owner = name_wire(owner);
+        for rdata in rdatas {
+            data.extend_from_slice(&owner);
+            data.extend_from_slice(&self.type_covered.to_be_bytes());
+            // Class IN
+            data.extend_from_slice(&1u16.to_be_bytes());
+            data.extend_from_slice(&self.original_ttl.to_be_bytes());
+            data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            data.extend(rdata);
+        }
+        data
+    }
+}
+
+/// Verify a signature made with a DNSKEY
+fn verify_signature(key: &Dnskey, message: &[u8], sig: &[u8]) -> Result<()> {
+    let public_key = key.public_key.as_slice();
+    let result = match key.algorithm {
+        5 | 7 | 8 | 10 => {
+            // Exponent length, then exponent and modulus (RFC 3110 section 2)
+            let (exponent_len, rest) = match public_key.split_first() {
+                Some((0, rest)) if rest.len() >= 2 => (usize::from(u16::from_be_bytes([rest[0], rest[1]])), &rest[2..]),
+                Some((len, rest)) => (usize::from(*len), rest),
+                None => return Err(anyhow!("empty RSA key")),
+            };
+            if rest.len() <= exponent_len {
+                return Err(anyhow!("malformed RSA key"));
+            }
+            let (e, n) = rest.split_at(exponent_len);
+            let params = match key.algorithm {
+                8 => &signature::RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY,
+                10 => &signature::RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY,
+                _ => &signature::RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY,
+            };
+            signature::RsaPublicKeyComponents { n, e }.verify(params, message, sig)
+        }
+        13 | 14 => {
+            // Uncompressed point without its 0x04 prefix (RFC 6605 section 4)
+            let mut point = vec![0x04];
+            point.extend_from_slice(public_key);
+            let algorithm = if key.algorithm == 13 {
+                &signature::ECDSA_P256_SHA256_FIXED
+            } else {
+                &signature::ECDSA_P384_SHA384_FIXED
+            };
+            signature::UnparsedPublicKey::new(algorithm, point).verify(message, sig)
+        }
+        15 => signature::UnparsedPublicKey::new(&signature::ED25519, public_key).verify(message, sig),
+        other => return Err(anyhow!("algorithm {} ({}) is not supported", other, algorithm_name(other))),
+    };
+    result.map_err(|_| anyhow!("signature does not verify"))
+}
+
+/// Find a current signature over an RRset made by one of `keys`
+fn validate_rrset<'a>(
+    owner: &str,
+    type_covered: u16,
+    rdatas: &[Vec<u8>],
+    sigs: &'a [Rrsig],
+    keys: &[Dnskey],
+    now: u32,
+) -> Result<&'a Rrsig> {
+    let mut error = anyhow!("no RRSIG covers the RRset");
+    for sig in sigs.iter().filter(|sig| sig.type_covered == type_covered) {
+        if now > sig.expiration {
+            error = anyhow!("signature by key {} expired {}", sig.key_tag, format_time(sig.expiration));
+            continue;
+        }
+        if now < sig.inception {
+            error = anyhow!("signature by key {} is not valid before {}", sig.key_tag, format_time(sig.inception));
+            continue;
+        }
+        let signed = sig.signed_data(owner, rdatas);
+        let mut candidates = keys.iter().filter(|key| key.key_tag() == sig.key_tag && key.algorithm == sig.algorithm).peekable();
+        if candidates.peek().is_none() {
+            error = anyhow!("no trusted DNSKEY with key tag {}", sig.key_tag);
+        }
+        for key in candidates {
+            match verify_signature(key, &signed, &sig.signature) {
+                Ok(()) => return Ok(sig),
+                Err(e) => error = anyhow!("key {}: {}", sig.key_tag, e),
+            }
+        }
+    }
+    Err(error)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Status {
+    Secure,
+    /// The chain ends at an unsigned delegation
+    Insecure(String),
+    /// A signature or digest does not validate
+    Bogus(String),
+}
+
+/// One zone cut on the way from the root
+#[derive(Debug)]
+struct ZoneReport {
+    zone: String,
+    ds: Vec<Ds>,
+    ds_sig: Option<Rrsig>,
+    keys: Vec<Dnskey>,
+    key_sig: Option<Rrsig>,
+    status: Status,
+}
+
+impl ZoneReport {
+    fn new(zone: &str, status: Status) -> Self {
+        Self { zone: zone.to_string(), ds: Vec::new(), ds_sig: None, keys: Vec::new(), key_sig: None, status }
+    }
+}
+
+/// Records of one type owned by `name` and the signatures over them
+async fn fetch(doh: &DohClient, name: &str, record_type: &str, code: u32) -> Result<(Vec<String>, Vec<Rrsig>)> {
+    let response = doh.query_dnssec(&fqdn(name), record_type).await?;
+    // NOERROR and NXDOMAIN both mean the resolver answered
+    if response.Status != 0 && response.Status != 3 {
+        return Err(anyhow!("{} lookup for {} failed (rcode {})", record_type, fqdn(name), response.Status));
+    }
+    let answers = response.Answer.unwrap_or_default();
+    let owned: Vec<_> = answers.into_iter().filter(|answer| normalize(&answer.name) == name).collect();
+    let records = owned.iter().filter(|answer| answer.record_type == code).map(|answer| answer.data.clone()).collect();
+    let sigs = owned
+        .iter()
+        .filter(|answer| answer.record_type == TYPE_RRSIG)
+        .filter_map(|answer| Rrsig::parse(&answer.data))
+        .collect();
+    Ok((records, sigs))
+}
+
+/// Validate the chain of trust from the root to `name`
+async fn validate_chain(ctx: &RequestContext, doh: &DohClient, name: &str) -> Result<Vec<ZoneReport>> {
+    let now: u32 = Utc::now().timestamp().try_into().unwrap_or(u32::MAX);
+    let mut candidates = vec![String::new()];
+    if !name.is_empty() {
+        let labels: Vec<&str> = name.split('.').collect();
+        candidates.extend((0..labels.len()).rev().map(|i| labels[i..].join(".")));
+    }
+
+    let mut reports = Vec::new();
+    let mut parent_keys: Vec<Dnskey> = Vec::new();
+    for zone in candidates {
+        if !zone.is_empty() {
+            // Only zone apexes have their own keys
+            let (soa, _) = fetch(doh, &zone, "SOA", TYPE_SOA).await?;
+            if soa.is_empty() {
+                continue;
+            }
+        }
+        log_debug!("[{}] Validating DNSSEC for zone {}", ctx, fqdn(&zone));
+
+        let (ds, ds_sig) = if zone.is_empty() {
+            (ROOT_ANCHORS.iter().filter_map(|anchor| Ds::parse(anchor)).collect::<Vec<_>>(), None)
+        } else {
+            let (records, sigs) = fetch(doh, &zone, "DS", TYPE_DS).await?;
+            let ds: Vec<Ds> = records.iter().filter_map(|record| Ds::parse(record)).collect();
+            if ds.is_empty() {
+                let reason = "no DS record in the parent zone, so the zone is not signed or not linked".to_string();
+                reports.push(ZoneReport::new(&zone, Status::Insecure(reason)));
+                break;
+            }
+            let rdatas: Vec<Vec<u8>> = ds.iter().map(Ds::rdata).collect();
+            match validate_rrset(&zone, TYPE_DS as u16, &rdatas, &sigs, &parent_keys, now) {
+                Ok(sig) => (ds, Some(sig.clone())),
+                Err(e) => {
+                    reports.push(ZoneReport { ds, ..ZoneReport::new(&zone, Status::Bogus(format!("DS RRset: {}", e))) });
+                    break;
+                }
+            }
+        };
+
+        let (records, sigs) = fetch(doh, &zone, "DNSKEY", TYPE_DNSKEY).await?;
+        let keys: Vec<Dnskey> = records.iter().filter_map(|record| Dnskey::parse(record)).collect();
+        let anchored: Vec<Dnskey> = keys.iter().filter(|key| ds.iter().any(|ds| ds.matches(&zone, key))).cloned().collect();
+        let rdatas: Vec<Vec<u8>> = keys.iter().map(Dnskey::rdata).collect();
+
+        let (status, key_sig) = if keys.is_empty() {
+            (Status::Bogus("DS records are published but the zone has no DNSKEY".to_string()), None)
+        } else if anchored.is_empty() {
+            (Status::Bogus("no DNSKEY matches a DS record".to_string()), None)
+        } else {
+            match validate_rrset(&zone, TYPE_DNSKEY as u16, &rdatas, &sigs, &anchored, now) {
+                Ok(sig) => (Status::Secure, Some(sig.clone())),
+                Err(e) => (Status::Bogus(format!("DNSKEY RRset: {}", e)), None),
+            }
+        };
+
+        let secure = status == Status::Secure;
+        reports.push(ZoneReport { zone, ds, ds_sig, keys: keys.clone(), key_sig, status });
+        if !secure {
+            break;
+        }
+        parent_keys = keys;
+    }
+    Ok(reports)
+}
+
+fn format_chain(name: &str, reports: &[ZoneReport]) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("% DNSSEC chain of trust for {}\n", fqdn(name)));
+    output.push_str("% Signatures validated locally from the root trust anchors\n");
+    output.push('\n');
+
+    for report in reports {
+        output.push_str(&format!("zone:           {}\n", fqdn(&report.zone)));
+        for ds in &report.ds {
+            output.push_str(&format!(
+                "ds:             {} {} {}{}\n",
+                ds.key_tag,
+                algorithm_name(ds.algorithm),
+                digest_name(ds.digest_type),
+                if report.zone.is_empty() { " (trust anchor)" } else { "" }
+            ));
+        }
+        if let Some(sig) = &report.ds_sig {
+            output.push_str(&format!("ds-rrsig:       by key {}, expires {}\n", sig.key_tag, format_time(sig.expiration)));
+        }
+        for key in &report.keys {
+            output.push_str(&format!(
+                "dnskey:         {} {} {}\n",
+                key.key_tag(),
+                if key.is_ksk() { "KSK" } else { "ZSK" },
+                algorithm_name(key.algorithm)
+            ));
+        }
+        if let Some(sig) = &report.key_sig {
+            output.push_str(&format!("dnskey-rrsig:   by key {}, expires {}\n", sig.key_tag, format_time(sig.expiration)));
+        }
+        match &report.status {
+            Status::Secure => output.push_str("status:         secure\n"),
+            Status::Insecure(reason) => {
+                output.push_str("status:         insecure\n");
+                output.push_str(&format!("remarks:        {}\n", reason));
+            }
+            Status::Bogus(reason) => {
+                output.push_str("status:         bogus\n");
+                output.push_str(&format!("remarks:        {}\n", reason));
+            }
+        }
+        output.push('\n');
+    }
+
+    let validation = match reports.last().map(|report| &report.status) {
+        Some(Status::Secure) => "secure",
+        Some(Status::Insecure(_)) => "insecure",
+        Some(Status::Bogus(_)) | None => "bogus",
+    };
+    output.push_str(&format!("validation:     {}\n", validation));
+    if let Some(last) = reports.last().filter(|last| last.status == Status::Secure && last.zone != name) {
+        output.push_str(&format!(
+            "remarks:        {} is not a zone apex, its records are signed with the keys of {}\n",
+            fqdn(name),
+            fqdn(&last.zone)
+        ));
+    }
+    output.push_str("source:         Cloudflare DNS over HTTPS\n");
+    output
+}
+
+/// Process a `-DNSSEC` query
+pub async fn process_dnssec_query(ctx: &RequestContext, query: &str) -> Result<String> {
+    let name = normalize(query);
+    let valid = name.split('.').all(|label| !label.is_empty() && label.len() <= 63)
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_');
+    if !name.is_empty() && !valid {
+        return Err(anyhow!("Invalid domain name: {}", query.trim()));
+    }
+    if name.split('.').count() > MAX_LABELS {
+        return Err(anyhow!("Names with more than {} labels are not checked", MAX_LABELS));
+    }
+
+    let doh = DohClient::new();
+    let reports = validate_chain(ctx, &doh, &name).await?;
+    Ok(format_chain(&name, &reports))
+}
+
+pub struct DnssecService;
+
+#[async_trait]
+impl QueryService for DnssecService {
+    fn name(&self) -> &'static str {
+        "dnssec"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-DNSSEC"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "DOMAIN ANALYSIS",
+            summary: "DNSSEC chain of trust from the root: algorithms, key tags, signature expiry",
+            example: "example.com-DNSSEC",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Cloudflare DNS over HTTPS (signatures validated locally)"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(300))
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_dnssec_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_tag_and_ds_digest() {
+        // RFC 4034 section 5.4
+        let key = Dnskey::parse(
+            "256 3 5 AQOeiiR0GOMYkDshWoSKz9XzfwJr1AYtsmx3TGkJaNXVbfi/2pHm822aJ5iI9BMzNXxeYCmZDRD99WYwYqUSdjMmmAphXdvxegXd/M5+X7OrzKBaMbCVdFLUUh6DhweJBjEVv5f2wwjM9XzcnOf+EPbtG9DMBmADjFDc2w/rljwvFw==",
+        )
+        .unwrap();
+        assert_eq!(key.key_tag(), 60485);
+        assert!(!key.is_ksk());
+
+        let ds = Ds::parse("60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118").unwrap();
+        assert!(ds.matches("dskey.example.com.", &key));
+        assert!(!ds.matches("example.com", &key));
+        assert_eq!(ROOT_ANCHORS.iter().filter_map(|anchor| Ds::parse(anchor)).count(), 2);
+    }
+
+    #[test]
+    fn test_validate_rrset() {
+        let key = Dnskey::parse("257 3 15 A6EHv/POEL4dcN0Y50vAmWfk1jCbpQ1fHdyGZBJVMbg=").unwrap();
+        assert_eq!(key.key_tag(), 34259);
+        let sig = Rrsig::parse(
+            "DNSKEY 15 2 3600 20300101000000 20240101000000 34259 example.net. D19eDWvMZOqWOFxScajjYaKOoCgsfKeknHJPzNTpoRaMjAKIgQCYby4+5YaZpPiSEJe32ucGs+qUxudL8nFlAw==",
+        )
+        .unwrap();
+        assert_eq!((sig.type_covered, sig.expiration, sig.inception), (48, 1893456000, 1704067200));
+        assert_eq!(Rrsig::parse("dnskey 15 2 3600 1893456000 1704067200 34259 example.net. AA==").unwrap().expiration, 1893456000);
+
+        let sigs = [sig];
+        let rdatas = [key.rdata()];
+        let keys = [key];
+        assert!(validate_rrset("example.net", 48, &rdatas, &sigs, &keys, 1790000000).is_ok());
+        // Wrong owner, expired signature, untrusted key
+        assert!(validate_rrset("example.org", 48, &rdatas, &sigs, &keys, 1790000000).is_err());
+        let expired = validate_rrset("example.net", 48, &rdatas, &sigs, &keys, 1900000000).unwrap_err();
+        assert!(expired.to_string().contains("expired 2030-01-01 00:00 UTC"));
+        assert!(validate_rrset("example.net", 48, &rdatas, &sigs, &[], 1790000000).is_err());
+    }
+}
@@ -0,0 +1,333 @@
+// WHOIS Server - Dual-Stack Comparison
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! IPv4/IPv6 dual-stack comparison (`-DUALSTACK`)
+//!
+//! Resolves A and AAAA for `host[:port]` (port 443 by default), times a TCP
+//! connect and TLS handshake to the first address of each family, compares
+//! the certificates served over both, and says whether IPv6 is broken or
+//! slow enough that Happy Eyeballs clients (RFC 8305) fall back to IPv4.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use rustls::{ClientConfig, ClientConnection};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use x509_parser::prelude::*;
+
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::ssl::AcceptAllVerifier;
+use crate::services::utils::doh::DohClient;
+use crate::log_debug;
+
+const DEFAULT_TLS_PORT: u16 = 443;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Connection Attempt Delay recommended by RFC 8305
+const HAPPY_EYEBALLS_DELAY_MS: f64 = 250.0;
+/// Differences below this are reported as comparable
+const COMPARABLE_MS: f64 = 10.0;
+
+/// Outcome of connecting to one address
+#[derive(Debug, Clone, PartialEq)]
+struct Attempt {
+    address: IpAddr,
+    connect_ms: f64,
+    tls: Result<TlsIdentity, String>,
+}
+
+/// Leaf certificate served over a connection
+#[derive(Debug, Clone, PartialEq)]
+struct TlsIdentity {
+    handshake_ms: f64,
+    subject: String,
+    fingerprint_sha256: String,
+}
+
+/// How IPv6 compares with IPv4 for a host
+#[derive(Debug, PartialEq)]
+enum Verdict {
+    NoAddresses,
+    Ipv4Only,
+    Ipv6Only,
+    Ipv4Broken,
+    Ipv6Broken,
+    BothBroken,
+    Ipv6Faster(f64),
+    Comparable(f64),
+    Ipv6Slower(f64),
+    Ipv6FallsBack(f64),
+}
+
+impl Verdict {
+    fn describe(&self) -> String {
+        match self {
+            Verdict::NoAddresses => "no A or AAAA records".to_string(),
+            Verdict::Ipv4Only => "IPv4 only (no AAAA record)".to_string(),
+            Verdict::Ipv6Only => "IPv6 only (no A record)".to_string(),
+            Verdict::Ipv4Broken => "IPv4 BROKEN, IPv6 works".to_string(),
+            Verdict::Ipv6Broken => "IPv6 BROKEN, clients fall back to IPv4 after a delay".to_string(),
+            Verdict::BothBroken => "unreachable over IPv4 and IPv6".to_string(),
+            Verdict::Ipv6Faster(ms) => format!("IPv6 faster by {:.1} ms", ms),
+            Verdict::Comparable(ms) => format!("comparable ({:+.1} ms for IPv6)", ms),
+            Verdict::Ipv6Slower(ms) => format!("IPv6 slower by {:.1} ms", ms),
+            Verdict::Ipv6FallsBack(ms) =>
+                format!(
+                    "IPv6 slower by {:.1} ms, beyond the {:.0} ms Happy Eyeballs delay; clients will use IPv4",
+                    ms,
+                    HAPPY_EYEBALLS_DELAY_MS
+                ),
+        }
+    }
+}
+
+/// Split `host[:port]`
+fn parse_target(target: &str) -> Option<(String, u16)> {
+    let target = target.trim().trim_end_matches('.').to_ascii_lowercase();
+    let (host, port) = match target.rsplit_once(':') {
+        Some((host, port)) => (host.to_string(), port.parse().ok()?),
+        None => (target, DEFAULT_TLS_PORT),
+    };
+    let valid = host.contains('.') &&
+        host.parse::<IpAddr>().is_err() &&
+        host.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    valid.then_some((host, port))
+}
+
+/// A connection counts as working when both TCP and TLS succeeded
+fn working_time(attempt: &Option<Result<Attempt, String>>) -> Option<f64> {
+    match attempt {
+        Some(Ok(Attempt { connect_ms, tls: Ok(tls), .. })) => Some(connect_ms + tls.handshake_ms),
+        _ => None,
+    }
+}
+
+fn verdict(v4: &Option<Result<Attempt, String>>, v6: &Option<Result<Attempt, String>>) -> Verdict {
+    match (v4, v6) {
+        (None, None) => Verdict::NoAddresses,
+        (Some(_), None) => Verdict::Ipv4Only,
+        (None, Some(_)) => Verdict::Ipv6Only,
+        _ =>
+            match (working_time(v4), working_time(v6)) {
+                (None, None) => Verdict::BothBroken,
+                (None, Some(_)) => Verdict::Ipv4Broken,
+                (Some(_), None) => Verdict::Ipv6Broken,
+                (Some(v4_ms), Some(v6_ms)) => {
+                    let delta = v6_ms - v4_ms;
+                    if delta.abs() < COMPARABLE_MS {
+                        Verdict::Comparable(delta)
+                    } else if delta < 0.0 {
+                        Verdict::Ipv6Faster(-delta)
+                    } else if delta < HAPPY_EYEBALLS_DELAY_MS {
+                        Verdict::Ipv6Slower(delta)
+                    } else {
+                        Verdict::Ipv6FallsBack(delta)
+                    }
+                }
+            }
+    }
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Connect to one address and complete a TLS handshake (blocking)
+fn connect(host: &str, address: SocketAddr) -> Result<Attempt> {
+    let start = Instant::now();
+    let mut tcp = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+    let connect_ms = elapsed_ms(start);
+    tcp.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    tcp.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+
+    let tls = handshake(host, &mut tcp).map_err(|e| e.to_string());
+    Ok(Attempt { address: address.ip(), connect_ms, tls })
+}
+
+fn handshake(host: &str, tcp: &mut TcpStream) -> Result<TlsIdentity> {
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AcceptAllVerifier))
+        .with_no_client_auth();
+    let mut conn = ClientConnection::new(Arc::new(config), rustls::ServerName::try_from(host)?)?;
+
+    let start = Instant::now();
+    while conn.is_handshaking() {
+        conn.complete_io(tcp)?;
+    }
+    let handshake_ms = elapsed_ms(start);
+    conn.send_close_notify();
+    let _ = conn.complete_io(tcp);
+    let _ = tcp.flush();
+
+    let leaf = conn
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .ok_or_else(|| anyhow!("no certificate presented"))?;
+    let subject = X509Certificate::from_der(leaf.as_ref())
+        .map(|(_, cert)| cert.subject().to_string())
+        .unwrap_or_else(|_| "unparseable certificate".to_string());
+    let fingerprint_sha256 = Sha256::digest(leaf.as_ref())
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":");
+
+    Ok(TlsIdentity { handshake_ms, subject, fingerprint_sha256 })
+}
+
+/// First address of a record type, if any
+async fn first_address(doh: &DohClient, host: &str, record_type: &str, type_code: u32) -> Result<Option<IpAddr>> {
+    let response = doh.query(host, record_type).await?;
+    Ok(
+        response.Answer
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|answer| answer.record_type == type_code)
+            .find_map(|answer| answer.data.parse().ok())
+    )
+}
+
+/// Connect to the first address of a family, `None` when it has no address
+async fn attempt(host: &str, port: u16, address: Option<IpAddr>) -> Option<Result<Attempt, String>> {
+    let address = SocketAddr::new(address?, port);
+    let host = host.to_string();
+    let result = tokio::task::spawn_blocking(move || connect(&host, address)).await;
+    Some(match result {
+        Ok(attempt) => attempt.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    })
+}
+
+fn push_family(output: &mut String, family: &str, attempt: &Option<Result<Attempt, String>>) {
+    match attempt {
+        None => output.push_str(&format!("{}-address:   none\n", family)),
+        Some(Err(e)) => output.push_str(&format!("{}-connect:   FAILED ({})\n", family, e)),
+        Some(Ok(attempt)) => {
+            output.push_str(&format!("{}-address:   {}\n", family, attempt.address));
+            output.push_str(&format!("{}-connect:   {:.1} ms\n", family, attempt.connect_ms));
+            match &attempt.tls {
+                Ok(tls) => {
+                    output.push_str(&format!("{}-tls:       {:.1} ms\n", family, tls.handshake_ms));
+                    output.push_str(&format!("{}-subject:   {}\n", family, tls.subject));
+                    output.push_str(&format!("{}-sha256:    {}\n", family, tls.fingerprint_sha256));
+                }
+                Err(e) => output.push_str(&format!("{}-tls:       FAILED ({})\n", family, e)),
+            }
+        }
+    }
+}
+
+/// Compare IPv4 and IPv6 reachability of `target`
+pub async fn process_dualstack_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let Some((host, port)) = parse_target(target) else {
+        return Ok(format!("Invalid dual-stack target: {}\nUse a hostname, e.g. example.com-DUALSTACK\n", target));
+    };
+    log_debug!("[{}] Comparing IPv4 and IPv6 for {}:{}", ctx, host, port);
+
+    let doh = DohClient::new();
+    let (a, aaaa) = tokio::join!(first_address(&doh, &host, "A", 1), first_address(&doh, &host, "AAAA", 28));
+    let (v4, v6) = tokio::join!(attempt(&host, port, a?), attempt(&host, port, aaaa?));
+
+    let mut output = String::new();
+    output.push_str(&format!("% Dual-stack comparison for {}:{}\n", host, port));
+    output.push_str("%\n");
+    push_family(&mut output, "ipv4", &v4);
+    output.push_str("%\n");
+    push_family(&mut output, "ipv6", &v6);
+    output.push_str("%\n");
+
+    if let (Some(Ok(Attempt { tls: Ok(v4_tls), .. })), Some(Ok(Attempt { tls: Ok(v6_tls), .. }))) = (&v4, &v6) {
+        let identity = if v4_tls.fingerprint_sha256 == v6_tls.fingerprint_sha256 {
+            "same certificate over IPv4 and IPv6"
+        } else if v4_tls.subject == v6_tls.subject {
+            "different certificates with the same subject"
+        } else {
+            "DIFFERENT certificates over IPv4 and IPv6"
+        };
+        output.push_str(&format!("certificate:    {}\n", identity));
+    }
+    output.push_str(&format!("verdict:        {}\n", verdict(&v4, &v6).describe()));
+    output.push_str("% Times are TCP connect plus TLS handshake from this server\n");
+    Ok(output)
+}
+
+/// `-DUALSTACK`: IPv4 versus IPv6 reachability, latency and certificates
+pub struct DualStackService;
+
+#[async_trait]
+impl QueryService for DualStackService {
+    fn name(&self) -> &'static str {
+        "dualstack"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-DUALSTACK"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK MEASUREMENT",
+            summary: "Compare IPv4 and IPv6 connect/TLS timing and certificates",
+            example: "example.com-DUALSTACK",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "TCP/TLS connections from this server, Cloudflare DNS over HTTPS"
+    }
+
+    fn query_class(&self) -> &'static str {
+        "NETWORK"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        // Live measurement
+        None
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_dualstack_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn working(connect_ms: f64) -> Option<Result<Attempt, String>> {
+        Some(
+            Ok(Attempt {
+                address: IpAddr::from([192, 0, 2, 1]),
+                connect_ms,
+                tls: Ok(TlsIdentity {
+                    handshake_ms: 0.0,
+                    subject: "CN=example.com".to_string(),
+                    fingerprint_sha256: "AA".to_string(),
+                }),
+            })
+        )
+    }
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(parse_target("Example.com"), Some(("example.com".to_string(), 443)));
+        assert_eq!(parse_target("example.com:8443"), Some(("example.com".to_string(), 8443)));
+        assert_eq!(parse_target("192.0.2.1"), None);
+        assert_eq!(parse_target("example.com:https"), None);
+    }
+
+    #[test]
+    fn test_verdict() {
+        assert_eq!(verdict(&working(20.0), &None), Verdict::Ipv4Only);
+        assert_eq!(verdict(&working(20.0), &Some(Err("timed out".to_string()))), Verdict::Ipv6Broken);
+        assert_eq!(verdict(&working(20.0), &working(25.0)), Verdict::Comparable(5.0));
+        assert_eq!(verdict(&working(20.0), &working(120.0)), Verdict::Ipv6Slower(100.0));
+        assert_eq!(verdict(&working(20.0), &working(320.0)), Verdict::Ipv6FallsBack(300.0));
+        assert_eq!(verdict(&working(50.0), &working(20.0)), Verdict::Ipv6Faster(30.0));
+    }
+}
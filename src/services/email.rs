@@ -2,6 +2,7 @@ use anyhow::Result;
 use std::collections::HashSet;
 // Removed unused import
 use crate::dn42::query_dn42_raw_managed;
+use crate::services::utils::table::format_table;
 
 use crate::{log_debug};
 /// Process email search queries ending with -EMAIL
@@ -13,14 +14,14 @@ pub async fn process_email_search(base_query: &str) -> Result<String> {
     log_debug!("Base response length: {} chars", base_response.len());
 
     // Start with emails from the base object itself
-    let mut emails = HashSet::new();
+    let mut emails = Vec::new();
     let base_emails = extract_emails(&base_response);
     log_debug!(
         "Found {} emails in base object: {:?}",
         base_emails.len(),
         base_emails
     );
-    emails.extend(base_emails);
+    add_emails(&mut emails, base_emails, base_query);
 
     // Extract references from the base object
     let references = extract_references(&base_response);
@@ -51,7 +52,7 @@ pub async fn process_email_search(base_query: &str) -> Result<String> {
                         related_query,
                         related_emails
                     );
-                    emails.extend(related_emails);
+                    add_emails(&mut emails, related_emails, &related_query);
 
                     // Also extract references from related objects
                     let related_refs = extract_references(&related_response);
@@ -70,7 +71,7 @@ pub async fn process_email_search(base_query: &str) -> Result<String> {
                                         ref_name,
                                         ref_emails
                                     );
-                                    emails.extend(ref_emails);
+                                    add_emails(&mut emails, ref_emails, &ref_name);
                                 }
                                 Err(e) => {
                                     log_debug!(
@@ -101,7 +102,7 @@ pub async fn process_email_search(base_query: &str) -> Result<String> {
                     reference,
                     ref_emails
                 );
-                emails.extend(ref_emails);
+                add_emails(&mut emails, ref_emails, &reference);
             }
             Err(e) => {
                 log_debug!("Failed to query reference {}: {}", reference, e);
@@ -187,18 +188,31 @@ fn extract_field_value(line: &str, field_name: &str) -> Option<String> {
     None
 }
 
+/// Add the addresses found in an object, keeping the first object each was
+/// found in
+fn add_emails(emails: &mut Vec<(String, String)>, found: Vec<String>, object: &str) {
+    for email in found {
+        if !emails.iter().any(|(known, _)| *known == email) {
+            emails.push((email, object.to_string()));
+        }
+    }
+}
+
 /// Format email search response
-fn format_email_response(emails: &HashSet<String>) -> Result<String> {
+///
+/// The addresses are a table in the order they were found, which `-SORT`
+/// and `-COUNT` can reorder or summarize.
+fn format_email_response(emails: &[(String, String)]) -> Result<String> {
     if emails.is_empty() {
         return Ok("% Email Search\n% No email addresses found\n".to_string());
     }
 
-    let mut response = String::from("% Email Search\n");
-
-    // Add each unique email address
-    for email in emails {
-        response.push_str(&format!("e-mail:             {}\n", email));
-    }
+    let mut response = String::from("% Email Search\n\n");
+    let rows: Vec<Vec<String>> = emails
+        .iter()
+        .map(|(email, object)| vec![email.clone(), object.clone()])
+        .collect();
+    response.push_str(&format_table(&["E-mail", "Object"], &rows));
 
     Ok(response)
 }
@@ -226,6 +240,20 @@ admin-c:        TEST-DN42
         assert!(emails.contains(&"another@example.com".to_string()));
     }
 
+    #[test]
+    fn test_email_table() {
+        let mut emails = Vec::new();
+        add_emails(&mut emails, vec!["noc@example.com".to_string()], "AS4242420000");
+        add_emails(&mut emails, vec!["noc@example.com".to_string(), "me@example.com".to_string()], "EXAMPLE-MNT");
+
+        assert_eq!(
+            format_email_response(&emails).unwrap(),
+            "% Email Search\n\nE-mail          | Object\n----------------|-------------\n\
+             noc@example.com | AS4242420000\nme@example.com  | EXAMPLE-MNT\n"
+        );
+        assert_eq!(format_email_response(&[]).unwrap(), "% Email Search\n% No email addresses found\n");
+    }
+
     #[test]
     fn test_extract_references() {
         let whois_data = r#"
@@ -0,0 +1,338 @@
+// WHOIS Server - Domain Expiry
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Domain expiry summary (`example.com-EXPIRY`)
+//!
+//! Registries publish registration dates under many names and formats:
+//! `Registry Expiry Date: 2026-08-13T04:00:00Z` for gTLDs, `paid-till:` in
+//! .ru, `Expiry date: 13-Aug-2026` in .uk, `[有効期限] 2026/08/31` in .jp,
+//! `expire: 13.08.2026` in ccTLDs run with FRED and `expires: 20260813` in
+//! .br. The expiry, creation and last update dates are read from the
+//! registry WHOIS response, and any that are missing from RDAP, then shown
+//! as one normalized block with the days left and an urgency that the
+//! colorizer shows in red, yellow or green.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use std::time::Duration;
+
+use crate::core::public_suffix::registrable_domain;
+use crate::core::request_context::RequestContext;
+use crate::services::rdap::process_rdap_query;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::whois::query_with_iana_referral;
+use crate::log_debug;
+
+/// Registrations expiring within this many days are critical
+const CRITICAL_DAYS: i64 = 30;
+/// Registrations expiring within this many days should be renewed soon
+const RENEW_SOON_DAYS: i64 = 90;
+
+pub(crate) const EXPIRY_KEYS: &[&str] = &[
+    "Registry Expiry Date",
+    "Registrar Registration Expiration Date",
+    "Expiration Date",
+    "Expiration Time",
+    "Expiry Date",
+    "Expires",
+    "Expires On",
+    "Expire Date",
+    "expire",
+    "paid-till",
+    "Valid Until",
+    "Renewal Date",
+    "有効期限",
+    "expiration",
+];
+
+pub(crate) const CREATED_KEYS: &[&str] = &[
+    "Creation Date",
+    "Created",
+    "Created On",
+    "Registration Time",
+    "Registered On",
+    "Registered",
+    "Domain Registration Date",
+    "Record Created",
+    "登録年月日",
+    "registration",
+];
+
+pub(crate) const UPDATED_KEYS: &[&str] = &[
+    "Updated Date",
+    "Last Updated",
+    "Last Updated On",
+    "Last Modified",
+    "Last-Update",
+    "Modified",
+    "Changed",
+    "最終更新",
+    "last changed",
+];
+
+/// How soon a registration needs to be renewed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Expired,
+    Critical,
+    RenewSoon,
+    Ok,
+}
+
+impl Urgency {
+    pub fn from_days(days: i64) -> Self {
+        if days < 0 {
+            Self::Expired
+        } else if days < CRITICAL_DAYS {
+            Self::Critical
+        } else if days < RENEW_SOON_DAYS {
+            Self::RenewSoon
+        } else {
+            Self::Ok
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Expired => "expired",
+            Self::Critical => "critical",
+            Self::RenewSoon => "renew-soon",
+            Self::Ok => "ok",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [Self::Expired, Self::Critical, Self::RenewSoon, Self::Ok].into_iter().find(|u| u.label() == label)
+    }
+
+    fn description(self) -> String {
+        match self {
+            Self::Expired => "the registration has expired".to_string(),
+            Self::Critical => format!("expires within {} days", CRITICAL_DAYS),
+            Self::RenewSoon => format!("expires within {} days", RENEW_SOON_DAYS),
+            Self::Ok => format!("more than {} days left", RENEW_SOON_DAYS),
+        }
+    }
+}
+
+/// Registration dates found in a WHOIS or RDAP response
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct RegistrationDates {
+    pub(crate) created: Option<DateTime<Utc>>,
+    pub(crate) updated: Option<DateTime<Utc>>,
+    pub(crate) expires: Option<DateTime<Utc>>,
+}
+
+/// Parse a registry date in any of the formats seen in WHOIS responses
+pub(crate) fn parse_whois_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim().trim_end_matches('.');
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%z", "%Y-%m-%dT%H:%M:%S%.f%z", "%Y-%m-%d %H:%M:%S%z"] {
+        if let Ok(date) = DateTime::parse_from_str(value, format) {
+            return Some(date.with_timezone(&Utc));
+        }
+    }
+    // Date and time without a zone, read as UTC
+    let without_zone = value.trim_end_matches(" UTC").trim_end_matches(" GMT").trim_end_matches('Z');
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S", "%Y/%m/%d %H:%M:%S", "%d.%m.%Y %H:%M:%S"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(without_zone, format) {
+            return Some(date.and_utc());
+        }
+    }
+    // Whole-value date formats with spaces (.kr `2026. 08. 13`, `August 13 2026`)
+    for format in ["%Y. %m. %d", "%B %d %Y", "%B %d, %Y", "%d %B %Y", "%b %d %Y", "%d %b %Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+            return date.and_hms_opt(0, 0, 0).map(|date| date.and_utc());
+        }
+    }
+    // A date as the first word, possibly followed by a time or a note
+    let first = value.split_whitespace().next()?;
+    let first = first.split('T').next().unwrap_or(first);
+    for format in ["%Y-%m-%d", "%Y.%m.%d", "%Y/%m/%d", "%d-%b-%Y", "%d.%m.%Y", "%d/%m/%Y", "%d-%m-%Y", "%Y%m%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(first, format) {
+            return date.and_hms_opt(0, 0, 0).map(|date| date.and_utc());
+        }
+    }
+    None
+}
+
+/// Key and value of a `key: value` or JPRS-style `[key] value` line
+fn field(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    let (key, value) = match line.strip_prefix('[') {
+        Some(rest) => rest.split_once(']')?,
+        None => line.split_once(':')?,
+    };
+    let value = value.trim();
+    (!value.is_empty()).then_some((key.trim(), value))
+}
+
+/// First parseable date under one of `keys`
+fn date_field(response: &str, keys: &[&str]) -> Option<DateTime<Utc>> {
+    response
+        .lines()
+        .filter(|line| !line.starts_with('%') && !line.starts_with('#'))
+        .filter_map(field)
+        .filter(|(key, _)| keys.iter().any(|k| key.eq_ignore_ascii_case(k)))
+        .find_map(|(_, value)| parse_whois_date(value))
+}
+
+/// Expiry, creation and last update dates of a registration
+pub(crate) fn registration_dates(response: &str) -> RegistrationDates {
+    RegistrationDates {
+        created: date_field(response, CREATED_KEYS),
+        updated: date_field(response, UPDATED_KEYS),
+        expires: date_field(response, EXPIRY_KEYS),
+    }
+}
+
+fn registrar(response: &str) -> Option<&str> {
+    response
+        .lines()
+        .filter_map(field)
+        .find(|(key, _)| ["Registrar", "Sponsoring Registrar", "registrar"].iter().any(|k| key.eq_ignore_ascii_case(k)))
+        .map(|(_, value)| value)
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+fn format_expiry(domain: &str, registrar: Option<&str>, dates: &RegistrationDates, source: &str, now: DateTime<Utc>) -> String {
+    let mut output = format!("% Registration dates of {}\n% Source: {}\n\n", domain, source);
+    output.push_str(&format!("domain:         {}\n", domain));
+    if let Some(registrar) = registrar {
+        output.push_str(&format!("registrar:      {}\n", registrar));
+    }
+    for (label, date) in [("created:", dates.created), ("updated:", dates.updated), ("expires:", dates.expires)] {
+        if let Some(date) = date {
+            output.push_str(&format!("{:<16}{}\n", label, format_date(date)));
+        }
+    }
+    match dates.expires {
+        Some(expires) => {
+            let days = (expires - now).num_days();
+            let urgency = Urgency::from_days(days);
+            output.push_str(&format!("days-left:      {}\n", days));
+            output.push_str(&format!("urgency:        {} ({})\n", urgency.label(), urgency.description()));
+        }
+        None => output.push_str("% The registry publishes no expiry date for this domain\n"),
+    }
+    output
+}
+
+/// Process an `-EXPIRY` query
+pub async fn process_expiry_query(target: &str) -> Result<String> {
+    let name = target.trim().trim_end_matches('.').to_lowercase();
+    if !name.contains('.') || name.parse::<std::net::IpAddr>().is_ok() {
+        return Ok(format!("% Expected a domain name such as example.com-EXPIRY, got: {}\n", target.trim()));
+    }
+    let domain = registrable_domain(&name).unwrap_or(name);
+
+    let whois = query_with_iana_referral(&domain).await?;
+    let mut dates = registration_dates(&whois);
+    let mut source = "WHOIS".to_string();
+    if dates.expires.is_none() || dates.created.is_none() || dates.updated.is_none() {
+        match process_rdap_query(&domain).await {
+            Ok(rdap) => {
+                let from_rdap = registration_dates(&rdap);
+                if from_rdap != RegistrationDates::default() {
+                    source = "WHOIS, RDAP".to_string();
+                }
+                dates.created = dates.created.or(from_rdap.created);
+                dates.updated = dates.updated.or(from_rdap.updated);
+                dates.expires = dates.expires.or(from_rdap.expires);
+            }
+            Err(e) => log_debug!("RDAP lookup of {} for -EXPIRY failed: {}", domain, e),
+        }
+    }
+    Ok(format_expiry(&domain, registrar(&whois), &dates, &source, Utc::now()))
+}
+
+pub struct ExpiryService;
+
+#[async_trait]
+impl QueryService for ExpiryService {
+    fn name(&self) -> &'static str {
+        "expiry"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-EXPIRY"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "DOMAIN ANALYSIS",
+            summary: "Normalized expiry, creation and update dates with days until expiry",
+            example: "example.com-EXPIRY",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "registry WHOIS / RDAP"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(3600))
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_expiry_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(value: &str) -> String {
+        parse_whois_date(value).map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_date_formats() {
+        for value in [
+            "2026-08-13T04:00:00Z",
+            "2026-08-13T04:00:00.0Z",
+            "2026-08-13T04:00:00+0000",
+            "2026-08-13 04:00:00",
+            "2026-08-13",
+            "2026.08.13",
+            "2026/08/13",
+            "13-Aug-2026",
+            "13.08.2026",
+            "13.08.2026 12:00:00",
+            "20260813",
+            "2026. 08. 13.",
+            "August 13 2026",
+            "2026-08-13 (YYYY-MM-DD)",
+        ] {
+            assert_eq!(day(value), "2026-08-13", "{}", value);
+        }
+        assert_eq!(day("not a date"), "");
+    }
+
+    #[test]
+    fn test_registration_dates_and_summary() {
+        let jprs = "[ドメイン名]                EXAMPLE.JP\n[登録年月日]                2001/02/03\n[有効期限]                  2026/02/28\n[最終更新]                  2025/03/01 01:05:03 (JST)\n";
+        let dates = registration_dates(jprs);
+        assert_eq!(dates.created.map(|d| d.format("%F").to_string()).as_deref(), Some("2001-02-03"));
+        assert_eq!(dates.expires.map(|d| d.format("%F").to_string()).as_deref(), Some("2026-02-28"));
+        assert_eq!(dates.updated.map(|d| d.format("%F").to_string()).as_deref(), Some("2025-03-01"));
+
+        let nominet = "    Registrar:\n        Example Registrar Ltd\n    Relevant dates:\n        Registered on: 01-Jan-2000\n        Expiry date:  13-Aug-2026\n        Last updated:  01-Jul-2025\n";
+        let dates = registration_dates(nominet);
+        assert_eq!(dates.expires.map(|d| d.format("%F").to_string()).as_deref(), Some("2026-08-13"));
+
+        let now = parse_whois_date("2026-07-24T00:00:00Z").unwrap();
+        let output = format_expiry("example.uk", None, &dates, "WHOIS", now);
+        assert!(output.contains("expires:        2026-08-13 00:00:00 UTC\ndays-left:      20\nurgency:        critical"));
+        assert_eq!(Urgency::from_days(60), Urgency::RenewSoon);
+        assert_eq!(Urgency::from_label("ok"), Some(Urgency::Ok));
+    }
+}
@@ -0,0 +1,382 @@
+// WHOIS Server - Web Fingerprint
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Favicon hash and technology fingerprinting (`-FINGERPRINT`)
+//!
+//! Fetches a site's front page and favicon, computes the Shodan-compatible
+//! favicon hash (MurmurHash3 of the base64-encoded icon, usable as
+//! `http.favicon.hash:<n>`) and identifies common frameworks and
+//! technologies from response headers, cookies and HTML markers.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::header::HeaderMap;
+use std::time::Duration;
+
+use crate::core::regex_cache::CachedRegex;
+use crate::core::request_context::RequestContext;
+use crate::log_debug;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::utils::public_http::{is_public_url, public_addresses, public_only};
+
+/// Largest page or favicon body that is read
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+static TITLE_RE: CachedRegex = CachedRegex::new(r"(?is)<title[^>]*>(.*?)</title>");
+static LINK_RE: CachedRegex = CachedRegex::new(r"(?is)<link\b[^>]*>");
+static META_GENERATOR_RE: CachedRegex = CachedRegex::new(
+    r#"(?is)<meta\b[^>]*name\s*=\s*["']generator["'][^>]*content\s*=\s*["']([^"']+)["']"#
+);
+static REL_RE: CachedRegex = CachedRegex::new(r#"(?i)\brel\s*=\s*["']?([^"'>]+)"#);
+static HREF_RE: CachedRegex = CachedRegex::new(r#"(?i)\bhref\s*=\s*["']?([^"'\s>]+)"#);
+
+/// Patterns checked by `regex_cache::precompile_builtin_regexes` at startup
+pub(crate) fn builtin_regexes() -> Vec<&'static CachedRegex> {
+    vec![&TITLE_RE, &LINK_RE, &META_GENERATOR_RE, &REL_RE, &HREF_RE]
+}
+
+/// Evidence a technology is detected by
+enum Marker {
+    /// Response header is present
+    Header(&'static str),
+    /// Response header contains a value (case-insensitive)
+    HeaderValue(&'static str, &'static str),
+    /// A cookie with this name prefix is set
+    Cookie(&'static str),
+    /// HTML contains this text
+    Html(&'static str),
+}
+
+/// Technologies recognised from headers and markup
+const TECHNOLOGIES: &[(&str, Marker)] = &[
+    ("Cloudflare", Marker::Header("cf-ray")),
+    ("Amazon CloudFront", Marker::Header("x-amz-cf-id")),
+    ("Fastly", Marker::Header("x-fastly-request-id")),
+    ("Akamai", Marker::Header("x-akamai-transformed")),
+    ("Vercel", Marker::Header("x-vercel-id")),
+    ("Netlify", Marker::Header("x-nf-request-id")),
+    ("GitHub Pages", Marker::Header("x-github-request-id")),
+    ("Varnish", Marker::Header("x-varnish")),
+    ("nginx", Marker::HeaderValue("server", "nginx")),
+    ("Apache HTTP Server", Marker::HeaderValue("server", "apache")),
+    ("Microsoft IIS", Marker::HeaderValue("server", "microsoft-iis")),
+    ("LiteSpeed", Marker::HeaderValue("server", "litespeed")),
+    ("Caddy", Marker::HeaderValue("server", "caddy")),
+    ("OpenResty", Marker::HeaderValue("server", "openresty")),
+    ("PHP", Marker::HeaderValue("x-powered-by", "php")),
+    ("ASP.NET", Marker::HeaderValue("x-powered-by", "asp.net")),
+    ("Express", Marker::HeaderValue("x-powered-by", "express")),
+    ("Next.js", Marker::HeaderValue("x-powered-by", "next.js")),
+    ("Drupal", Marker::Header("x-drupal-cache")),
+    ("PHP", Marker::Cookie("PHPSESSID")),
+    ("Java", Marker::Cookie("JSESSIONID")),
+    ("ASP.NET", Marker::Cookie("ASP.NET_SessionId")),
+    ("Laravel", Marker::Cookie("laravel_session")),
+    ("Django", Marker::Cookie("csrftoken")),
+    ("WordPress", Marker::Html("/wp-content/")),
+    ("Next.js", Marker::Html("__NEXT_DATA__")),
+    ("Nuxt.js", Marker::Html("__NUXT__")),
+    ("Angular", Marker::Html("ng-version=")),
+    ("React", Marker::Html("data-reactroot")),
+    ("Gatsby", Marker::Html("___gatsby")),
+    ("Shopify", Marker::Html("cdn.shopify.com")),
+    ("jQuery", Marker::Html("jquery")),
+    ("Bootstrap", Marker::Html("bootstrap.min.css")),
+    ("Google Analytics", Marker::Html("googletagmanager.com/gtag")),
+    ("Google Tag Manager", Marker::Html("googletagmanager.com/gtm.js")),
+];
+
+/// MurmurHash3 (x86, 32-bit), as a signed value like Python's `mmh3.hash`
+fn murmur3_32(data: &[u8], seed: u32) -> i32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+
+    let mut hash = seed;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let mut k = tail.iter().enumerate().fold(0u32, |k, (i, b)| k | (u32::from(*b) << (8 * i)));
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85ebca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2ae35);
+    hash ^= hash >> 16;
+    hash as i32
+}
+
+/// Shodan favicon hash: MurmurHash3 of the base64 encoding with a newline
+/// after every 76 characters, as produced by Python's `base64.encodebytes`
+fn favicon_hash(icon: &[u8]) -> i32 {
+    let encoded = STANDARD.encode(icon);
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+    for line in encoded.as_bytes().chunks(76) {
+        wrapped.push_str(std::str::from_utf8(line).unwrap_or_default());
+        wrapped.push('\n');
+    }
+    murmur3_32(wrapped.as_bytes(), 0)
+}
+
+/// Favicon referenced by the page, if any
+fn favicon_href(html: &str) -> Option<String> {
+    let links = LINK_RE.get().ok()?;
+    links.find_iter(html).find_map(|link| {
+        let tag = link.as_str();
+        let rel = REL_RE.get().ok()?.captures(tag)?.get(1)?.as_str().to_ascii_lowercase();
+        if !rel.split_whitespace().any(|r| r == "icon") {
+            return None;
+        }
+        HREF_RE.get().ok()?.captures(tag)?.get(1).map(|href| href.as_str().replace("&amp;", "&"))
+    })
+}
+
+fn page_title(html: &str) -> Option<String> {
+    let title = TITLE_RE.get().ok()?.captures(html)?.get(1)?.as_str();
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+fn meta_generator(html: &str) -> Option<String> {
+    META_GENERATOR_RE.get().ok()?.captures(html)?.get(1).map(|g| g.as_str().trim().to_string())
+}
+
+/// Technologies detected from the response, with the evidence
+fn detect_technologies(headers: &HeaderMap, html: &str) -> Vec<(&'static str, String)> {
+    let html_lower = html.to_ascii_lowercase();
+    let cookies: Vec<&str> = headers
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect();
+
+    let mut found: Vec<(&'static str, String)> = Vec::new();
+    for (name, marker) in TECHNOLOGIES {
+        if found.iter().any(|(n, _)| n == name) {
+            continue;
+        }
+        let evidence = match marker {
+            Marker::Header(header) => headers.contains_key(*header).then(|| format!("header {}", header)),
+            Marker::HeaderValue(header, value) => headers
+                .get(*header)
+                .and_then(|v| v.to_str().ok())
+                .filter(|v| v.to_ascii_lowercase().contains(value))
+                .map(|v| format!("{}: {}", header, v)),
+            Marker::Cookie(prefix) => cookies
+                .iter()
+                .any(|c| c.starts_with(prefix))
+                .then(|| format!("cookie {}", prefix)),
+            Marker::Html(text) => html_lower.contains(&text.to_ascii_lowercase()).then(|| format!("html {}", text)),
+        };
+        if let Some(evidence) = evidence {
+            found.push((name, evidence));
+        }
+    }
+    found
+}
+
+/// Page URL for a domain or URL target
+fn target_url(target: &str) -> Option<reqwest::Url> {
+    let target = target.trim();
+    let url = if target.contains("://") { target.to_string() } else { format!("https://{}/", target) };
+    let parsed = reqwest::Url::parse(&url).ok()?;
+    (matches!(parsed.scheme(), "http" | "https") && parsed.host_str().is_some_and(|h| h.contains('.') || h.contains(':')))
+        .then_some(parsed)
+}
+
+/// Read a response body, truncated to `MAX_BODY_BYTES`
+async fn read_body(mut response: reqwest::Response) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_BODY_BYTES {
+            body.truncate(MAX_BODY_BYTES);
+            break;
+        }
+    }
+    Ok(body)
+}
+
+/// Fingerprint the site at `target`
+pub async fn process_fingerprint_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let Some(url) = target_url(target) else {
+        return Ok(format!("Invalid fingerprint target: {}\nUse a domain or URL, e.g. example.com-FINGERPRINT\n", target));
+    };
+    // Only public sites are fetched; the client also refuses internal
+    // addresses on redirects and for the favicon
+    if !is_public_url(&url) || public_addresses(&url).await.is_empty() {
+        return Ok(format!("Fingerprint refused for: {}
+The target does not resolve to a public address
+", target));
+    }
+    log_debug!("[{}] Fingerprinting {}", ctx, url);
+
+    let client = public_only(reqwest::Client::builder(), 5)
+        .timeout(Duration::from_secs(10))
+        .user_agent("Mozilla/5.0 (compatible; WhoisServer/1.0 Fingerprint)")
+        .build()?;
+
+    let response = match client.get(url.clone()).send().await {
+        Ok(response) => response,
+        Err(e) => return Ok(format!("Fingerprint Failed for: {}\nRequest failed: {}\n", target, e)),
+    };
+    let final_url = response.url().clone();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let html = String::from_utf8_lossy(&read_body(response).await?).into_owned();
+
+    let favicon_url = favicon_href(&html)
+        .and_then(|href| final_url.join(&href).ok())
+        .or_else(|| final_url.join("/favicon.ico").ok());
+    let mut favicon = None;
+    if let Some(favicon_url) = favicon_url.filter(is_public_url) {
+        match client.get(favicon_url.clone()).send().await {
+            Ok(response) if response.status().is_success() => {
+                let icon = read_body(response).await?;
+                if !icon.is_empty() {
+                    favicon = Some((favicon_url, icon));
+                }
+            }
+            Ok(response) => log_debug!("[{}] Favicon {} returned {}", ctx, favicon_url, response.status()),
+            Err(e) => log_debug!("[{}] Favicon {} failed: {}", ctx, favicon_url, e),
+        }
+    }
+
+    let mut output = String::new();
+    output.push_str(&format!("Fingerprint for: {}\n", target));
+    output.push_str("=".repeat(60).as_str());
+    output.push('\n');
+    output.push_str(&format!("url: {}\n", final_url));
+    output.push_str(&format!("status: {}\n", status.as_u16()));
+    if let Some(title) = page_title(&html) {
+        output.push_str(&format!("title: {}\n", title));
+    }
+    for header in ["server", "x-powered-by"] {
+        if let Some(value) = headers.get(header).and_then(|v| v.to_str().ok()) {
+            output.push_str(&format!("{}: {}\n", header, value));
+        }
+    }
+    if let Some(generator) = meta_generator(&html) {
+        output.push_str(&format!("generator: {}\n", generator));
+    }
+
+    match &favicon {
+        Some((favicon_url, icon)) => {
+            let hash = favicon_hash(icon);
+            output.push_str(&format!("favicon-url: {}\n", favicon_url));
+            output.push_str(&format!("favicon-size: {} bytes\n", icon.len()));
+            output.push_str(&format!("favicon-mmh3: {}\n", hash));
+            output.push_str(&format!("favicon-md5: {:x}\n", md5::compute(icon)));
+            output.push_str(&format!("shodan-query: http.favicon.hash:{}\n", hash));
+        }
+        None => output.push_str("favicon: not found\n"),
+    }
+
+    for (name, evidence) in detect_technologies(&headers, &html) {
+        output.push_str(&format!("technology: {} ({})\n", name, evidence));
+    }
+    Ok(output)
+}
+
+pub struct FingerprintService;
+
+#[async_trait]
+impl QueryService for FingerprintService {
+    fn name(&self) -> &'static str {
+        "fingerprint"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-FINGERPRINT"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "SECURITY INVESTIGATION",
+            summary: "Favicon hash (Shodan mmh3) and technology detection",
+            example: "example.com-FINGERPRINT",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "HTTP fetch of the target site"
+    }
+
+    fn query_class(&self) -> &'static str {
+        "NETWORK"
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_fingerprint_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_murmur3_matches_mmh3() {
+        assert_eq!(murmur3_32(b"", 0), 0);
+        assert_eq!(murmur3_32(b"foo", 0), -156908512);
+        assert_eq!(murmur3_32(b"hello", 0), 613153351);
+    }
+
+    #[test]
+    fn test_favicon_hash_wraps_base64() {
+        let icon: Vec<u8> = (0..=255u8).cycle().take(768).collect();
+        assert_eq!(favicon_hash(&icon), 1836528006);
+    }
+
+    #[test]
+    fn test_html_parsing() {
+        let html = r#"<html><head><title>
+            Example   Site</title>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="shortcut icon" href="/static/icon.png?v=1&amp;x=2">
+            <meta name="generator" content="WordPress 6.5.2">
+            </head><body><div id="___gatsby"></div></body></html>"#;
+
+        assert_eq!(page_title(html).as_deref(), Some("Example Site"));
+        assert_eq!(favicon_href(html).as_deref(), Some("/static/icon.png?v=1&x=2"));
+        assert_eq!(meta_generator(html).as_deref(), Some("WordPress 6.5.2"));
+        assert_eq!(favicon_href("<link rel=stylesheet href=/a.css>"), None);
+    }
+
+    #[test]
+    fn test_detect_technologies() {
+        let mut headers = HeaderMap::new();
+        headers.insert("server", HeaderValue::from_static("nginx/1.25.3"));
+        headers.insert("cf-ray", HeaderValue::from_static("8a1b2c3d4e5f-AMS"));
+        headers.append("set-cookie", HeaderValue::from_static("PHPSESSID=abc; path=/"));
+        headers.insert("x-powered-by", HeaderValue::from_static("PHP/8.3.0"));
+
+        let found = detect_technologies(&headers, "<script src=\"/wp-content/app.js\"></script>");
+        let names: Vec<&str> = found.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["Cloudflare", "nginx", "PHP", "WordPress"]);
+        assert_eq!(found[2].1, "x-powered-by: PHP/8.3.0");
+    }
+
+    #[tokio::test]
+    async fn test_internal_targets_are_refused() {
+        let ctx = RequestContext::default();
+        for target in ["http://169.254.169.254/latest/meta-data/", "http://10.0.0.1/", "127.0.0.1:8080"] {
+            let output = process_fingerprint_query(&ctx, target).await.unwrap();
+            assert!(output.starts_with("Fingerprint refused for:"), "{}", output);
+        }
+    }
+}
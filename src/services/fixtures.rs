@@ -0,0 +1,124 @@
+// WHOIS Server - HTTP Fixtures
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Recorded HTTP fixtures for offline service tests
+//!
+//! A `FixtureServer` serves recorded upstream responses from
+//! `tests/fixtures/<service>/` on a local mock server, and runs the service
+//! with its API base URL pointed at it:
+//!
+//! ```ignore
+//! let mut fixtures = FixtureServer::start("aur").await;
+//! fixtures.serve(AUR_API_BASE, "?arg=yay", "yay.json").await;
+//! let response = fixtures.run(process_aur_query("yay")).await?;
+//! ```
+//!
+//! With `RECORD=1` the live upstream is fetched first and the fixture file
+//! is refreshed, so `RECORD=1 cargo test aur` updates the AUR fixtures.
+//!
+//! Coverage is per upstream API, not yet per service. Services with
+//! fixtures: AUR, crates.io, PyPI, npm, GitHub, Wikipedia, the Steam store,
+//! the RPKI validity API, TMDB (`-IMDB`), urlscan, abuse contacts, registrar
+//! RDAP, PeeringDB through `-IX`, RIPEstat (`-MONITOR`, `-NEIGHBORS`) and
+//! the speedtest server lists. Not covered: port 43 lookups (WHOIS, IRR,
+//! bgp.tools), which are not HTTP; `-PEERINGDB`, which answers from its own
+//! LMDB cache first; Steam user profiles, which need an API key; and the
+//! remaining HTTP services, which still build their URLs without
+//! `api_base` and only have formatter tests.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use wiremock::matchers::{ method, path, query_param };
+use wiremock::{ Mock, MockServer, ResponseTemplate };
+
+use crate::services::upstream::with_upstreams;
+
+/// Whether fixtures should be refreshed from the live upstreams
+fn recording() -> bool {
+    std::env::var("RECORD").is_ok_and(|v| v == "1")
+}
+
+/// Path of a recorded fixture
+pub(crate) fn fixture_path(service: &str, fixture: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(service).join(fixture)
+}
+
+/// Local server answering a service's upstream requests from fixtures
+pub(crate) struct FixtureServer {
+    server: MockServer,
+    service: &'static str,
+    upstreams: HashMap<&'static str, String>,
+}
+
+impl FixtureServer {
+    pub(crate) async fn start(service: &'static str) -> Self {
+        Self { server: MockServer::start().await, service, upstreams: HashMap::new() }
+    }
+
+    /// Local replacement for an upstream base URL
+    fn local_base(&mut self, base: &'static str) -> String {
+        let local = format!(
+            "{}/{}/{}{}",
+            self.server.uri(),
+            self.service,
+            self.upstreams.len(),
+            if base.ends_with('/') { "/" } else { "" }
+        );
+        self.upstreams.entry(base).or_insert(local).clone()
+    }
+
+    /// Answer GET `base` + `request` with a recorded fixture
+    ///
+    /// `request` is the part of the URL the service appends to its base,
+    /// e.g. `?arg=yay` or `serde`.
+    pub(crate) async fn serve(&mut self, base: &'static str, request: &str, fixture: &str) {
+        let file = fixture_path(self.service, fixture);
+        if recording() {
+            record(&format!("{}{}", base, request), &file).await;
+        }
+        let body = std::fs::read(&file).unwrap_or_else(|e| {
+            panic!("missing fixture {} ({}), run the test with RECORD=1", file.display(), e)
+        });
+
+        self.mount(base, request, ResponseTemplate::new(200).set_body_bytes(body)).await;
+    }
+
+    /// Answer GET `base` + `request` with an empty response of `status`
+    pub(crate) async fn serve_status(&mut self, base: &'static str, request: &str, status: u16) {
+        self.mount(base, request, ResponseTemplate::new(status)).await;
+    }
+
+    async fn mount(&mut self, base: &'static str, request: &str, response: ResponseTemplate) {
+        let url = reqwest::Url::parse(&format!("{}{}", self.local_base(base), request))
+            .expect("fixture request must form a valid URL");
+
+        let mut mock = Mock::given(method("GET")).and(path(url.path()));
+        for (key, value) in url.query_pairs() {
+            mock = mock.and(query_param(key.as_ref(), value.as_ref()));
+        }
+        mock.respond_with(response).mount(&self.server).await;
+    }
+
+    /// Run a service call against the fixtures
+    pub(crate) async fn run<F: Future>(&self, fut: F) -> F::Output {
+        with_upstreams(self.upstreams.clone(), fut).await
+    }
+}
+
+/// Fetch a live upstream response into a fixture file
+async fn record(url: &str, file: &PathBuf) {
+    let response = reqwest::Client::new()
+        .get(url)
+        .header("User-Agent", "whois-server/1.0 (fixture recording)")
+        .send().await
+        .and_then(|r| r.error_for_status())
+        .unwrap_or_else(|e| panic!("failed to record {}: {}", url, e));
+    let body = response.bytes().await.unwrap_or_else(|e| panic!("failed to record {}: {}", url, e));
+
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).expect("create fixture directory");
+    }
+    std::fs::write(file, &body).expect("write fixture");
+}
@@ -0,0 +1,205 @@
+// WHOIS Server - Valve A2S Query
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Valve Server Query (A2S) protocol used by Source and GoldSrc games
+//!
+//! Sends `A2S_INFO` and `A2S_PLAYER` over UDP, answering the challenge the
+//! server may reply with first. Multi-packet (split) responses are not
+//! reassembled; servers with very long player lists report counts only.
+
+use anyhow::{Result, anyhow};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+use super::GameServerStatus;
+
+/// Header of a single-packet response
+const SINGLE_PACKET: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+const A2S_INFO: u8 = 0x54;
+const A2S_PLAYER: u8 = 0x55;
+const INFO_REPLY: u8 = 0x49;
+const PLAYER_REPLY: u8 = 0x44;
+const CHALLENGE_REPLY: u8 = 0x41;
+
+/// Reader over the little-endian fields of an A2S payload
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self.data
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| anyhow!("A2S response ended early"))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let rest = &self.data[self.offset.min(self.data.len())..];
+        let end = rest.iter().position(|b| *b == 0).ok_or_else(|| anyhow!("Unterminated string in A2S response"))?;
+        self.offset += end + 1;
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+}
+
+/// Fields of an `A2S_INFO` reply
+#[derive(Debug, PartialEq)]
+struct Info {
+    name: String,
+    map: String,
+    game: String,
+    players: u8,
+    max_players: u8,
+    bots: u8,
+    version: String,
+}
+
+fn parse_info(payload: &[u8]) -> Result<Info> {
+    let mut reader = Reader::new(payload);
+    if reader.u8()? != INFO_REPLY {
+        return Err(anyhow!("Unexpected A2S_INFO reply"));
+    }
+    let _protocol = reader.u8()?;
+    let name = reader.string()?;
+    let map = reader.string()?;
+    let _folder = reader.string()?;
+    let game = reader.string()?;
+    let _app_id = reader.u16()?;
+    let players = reader.u8()?;
+    let max_players = reader.u8()?;
+    let bots = reader.u8()?;
+    // Server type, environment, visibility, VAC
+    reader.bytes(4)?;
+    let version = reader.string()?;
+    Ok(Info { name, map, game, players, max_players, bots, version })
+}
+
+/// Names of connected players from an `A2S_PLAYER` reply
+fn parse_players(payload: &[u8]) -> Result<Vec<String>> {
+    let mut reader = Reader::new(payload);
+    if reader.u8()? != PLAYER_REPLY {
+        return Err(anyhow!("Unexpected A2S_PLAYER reply"));
+    }
+    let count = reader.u8()?;
+    let mut players = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let _index = reader.u8()?;
+        let name = reader.string()?;
+        // Score (i32) and connection time (f32)
+        reader.bytes(8)?;
+        // Players still connecting have no name yet
+        if !name.is_empty() {
+            players.push(name);
+        }
+    }
+    Ok(players)
+}
+
+/// Send a request, answering a challenge reply, and return the response payload
+async fn exchange(socket: &UdpSocket, request: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+    let mut packet = SINGLE_PACKET.to_vec();
+    packet.extend_from_slice(request);
+
+    let mut buffer = vec![0u8; 1400];
+    for _ in 0..2 {
+        socket.send(&packet).await?;
+        let len = tokio::time::timeout(timeout, socket.recv(&mut buffer))
+            .await
+            .map_err(|_| anyhow!("No A2S response after {} seconds", timeout.as_secs()))??;
+        let reply = &buffer[..len];
+        if reply.len() < 5 || reply[..4] != SINGLE_PACKET {
+            return Err(anyhow!("Split or malformed A2S response"));
+        }
+        if reply[4] != CHALLENGE_REPLY {
+            return Ok(reply[4..].to_vec());
+        }
+        // Repeat the request with the challenge appended (A2S_INFO) or in
+        // place of the placeholder challenge (A2S_PLAYER)
+        let challenge = reply.get(5..9).ok_or_else(|| anyhow!("Truncated A2S challenge"))?;
+        packet = SINGLE_PACKET.to_vec();
+        if request[0] == A2S_PLAYER {
+            packet.push(A2S_PLAYER);
+        } else {
+            packet.extend_from_slice(request);
+        }
+        packet.extend_from_slice(challenge);
+    }
+    Err(anyhow!("A2S server kept sending challenges"))
+}
+
+/// Query a Source/GoldSrc server
+pub async fn query(addr: SocketAddr, timeout: Duration) -> Result<GameServerStatus> {
+    let socket = UdpSocket::bind(if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).await?;
+    socket.connect(addr).await?;
+
+    let mut info_request = vec![A2S_INFO];
+    info_request.extend_from_slice(b"Source Engine Query\0");
+    let start = Instant::now();
+    let info = parse_info(&exchange(&socket, &info_request, timeout).await?)?;
+    let latency = start.elapsed().as_millis() as u64;
+
+    let player_request = [A2S_PLAYER, 0xff, 0xff, 0xff, 0xff];
+    let players = match exchange(&socket, &player_request, timeout).await {
+        Ok(payload) => parse_players(&payload).ok(),
+        Err(_) => None,
+    };
+
+    Ok(GameServerStatus {
+        protocol: "Valve A2S",
+        name: info.name,
+        game: Some(info.game).filter(|g| !g.is_empty()),
+        map: Some(info.map).filter(|m| !m.is_empty()),
+        version: Some(info.version).filter(|v| !v.is_empty()),
+        players_online: u32::from(info.players),
+        players_max: u32::from(info.max_players),
+        bots: Some(u32::from(info.bots)),
+        players,
+        latency,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_info_and_players() {
+        let mut info = vec![INFO_REPLY, 17];
+        info.extend_from_slice(b"Example CS2 Server\0de_dust2\0csgo\0Counter-Strike 2\0");
+        info.extend_from_slice(&730u16.to_le_bytes());
+        info.extend_from_slice(&[12, 32, 2, b'd', b'l', 0, 1]);
+        info.extend_from_slice(b"1.40.1.5\0");
+        let info = parse_info(&info).unwrap();
+        assert_eq!(info.map, "de_dust2");
+        assert_eq!(info.game, "Counter-Strike 2");
+        assert_eq!((info.players, info.max_players, info.bots), (12, 32, 2));
+        assert_eq!(info.version, "1.40.1.5");
+
+        let mut players = vec![PLAYER_REPLY, 2, 0];
+        players.extend_from_slice(b"gordon\0");
+        players.extend_from_slice(&[0; 8]);
+        players.push(1);
+        players.extend_from_slice(b"\0");
+        players.extend_from_slice(&[0; 8]);
+        assert_eq!(parse_players(&players).unwrap(), vec!["gordon"]);
+
+        assert!(parse_info(&[INFO_REPLY, 17, b'x']).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+// WHOIS Server - Game Server Query
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Game server status over each game's own query protocol (`-GAMESERVER`)
+//!
+//! The protocol is named after the suffix (`host:27015-GAMESERVER-A2S`) or
+//! as a prefix (`a2s:host:27015-GAMESERVER`). Without one it is inferred
+//! from well-known ports, and failing that every protocol is tried at once.
+//!
+//! - `A2S` (`SOURCE`, `VALVE`, `CS2`, `TF2`): Valve Server Query, see [`a2s`]
+//! - `MINECRAFT` (`MC`): Java Edition Server List Ping, see
+//!   [`crate::services::minecraft`]
+//! - `TERRARIA` (`TSHOCK`): TShock REST API, see [`terraria`]
+
+pub mod a2s;
+pub mod terraria;
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use futures::future::{BoxFuture, FutureExt};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use crate::core::is_internal;
+use crate::core::request_context::RequestContext;
+use crate::core::sanitize::strip_suffix_ignore_case;
+use crate::services::minecraft::MinecraftService;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::log_debug;
+
+const SUFFIX: &str = "-GAMESERVER";
+/// Time each protocol exchange may take
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Players listed before the rest are summarised
+const PLAYER_LIMIT: usize = 20;
+
+/// Status reported by any game server protocol
+#[derive(Debug, Clone)]
+pub struct GameServerStatus {
+    pub protocol: &'static str,
+    pub name: String,
+    pub game: Option<String>,
+    pub map: Option<String>,
+    pub version: Option<String>,
+    pub players_online: u32,
+    pub players_max: u32,
+    pub bots: Option<u32>,
+    /// Player names, when the server lists them
+    pub players: Option<Vec<String>>,
+    pub latency: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GameProtocol {
+    A2s,
+    Minecraft,
+    Terraria,
+}
+
+impl GameProtocol {
+    const ALL: [GameProtocol; 3] = [GameProtocol::A2s, GameProtocol::Minecraft, GameProtocol::Terraria];
+
+    fn parse(tag: &str) -> Option<Self> {
+        match tag.to_ascii_uppercase().as_str() {
+            "A2S" | "SOURCE" | "VALVE" | "CS2" | "TF2" => Some(Self::A2s),
+            "MINECRAFT" | "MC" => Some(Self::Minecraft),
+            "TERRARIA" | "TSHOCK" => Some(Self::Terraria),
+            _ => None,
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Self::A2s => "a2s",
+            Self::Minecraft => "minecraft",
+            Self::Terraria => "terraria",
+        }
+    }
+
+    fn default_port(&self) -> u16 {
+        match self {
+            Self::A2s => 27015,
+            Self::Minecraft => 25565,
+            Self::Terraria => 7878,
+        }
+    }
+
+    /// Protocol usually served on a port
+    fn for_port(port: u16) -> Option<Self> {
+        match port {
+            27015..=27020 | 27005 => Some(Self::A2s),
+            25565 => Some(Self::Minecraft),
+            7878 => Some(Self::Terraria),
+            _ => None,
+        }
+    }
+}
+
+/// Rewrite `target-GAMESERVER-<TYPE>` into the `type:target` form
+///
+/// Called before suffix matching, since type tags such as `-MC` would
+/// otherwise select other queries.
+pub fn split_protocol_suffix(query: &str) -> Option<String> {
+    let (rest, tag) = query.rsplit_once('-')?;
+    let protocol = GameProtocol::parse(tag)?;
+    let target = strip_suffix_ignore_case(rest, SUFFIX)?;
+    Some(format!("{}:{}", protocol.tag(), target))
+}
+
+/// Split a target into its protocol (if named), host and port (if given)
+fn parse_target(target: &str) -> Result<(Option<GameProtocol>, String, Option<u16>)> {
+    let target = target.trim();
+    let (protocol, target) = match target.split_once(':') {
+        Some((tag, rest)) if target.parse::<IpAddr>().is_err() => match GameProtocol::parse(tag) {
+            Some(protocol) => (Some(protocol), rest),
+            None => (None, target),
+        },
+        _ => (None, target),
+    };
+
+    let (host, port) = if let Ok(ip) = target.parse::<IpAddr>() {
+        (ip.to_string(), None)
+    } else if let Some(rest) = target.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(|| anyhow!("Unterminated IPv6 address: {}", target))?;
+        let port = match rest.strip_prefix(':') {
+            Some(port) => Some(port.parse().map_err(|_| anyhow!("Invalid port number: {}", port))?),
+            None => None,
+        };
+        (host.to_string(), port)
+    } else {
+        match target.rsplit_once(':') {
+            Some((host, port)) => (host.to_string(), Some(port.parse().map_err(|_| anyhow!("Invalid port number: {}", port))?)),
+            None => (target.to_string(), None),
+        }
+    };
+
+    if host.is_empty() || port == Some(0) {
+        return Err(anyhow!("Usage: host[:port]-GAMESERVER[-A2S|-MINECRAFT|-TERRARIA]"));
+    }
+    Ok((protocol, host, port))
+}
+
+/// First public address of a host; internal addresses are never queried
+async fn resolve(host: &str, port: u16) -> Result<SocketAddr> {
+    tokio::net::lookup_host((host, port))
+        .await?
+        .find(|address| !is_internal(address.ip()))
+        .ok_or_else(|| anyhow!("{} does not resolve to a public address", host))
+}
+
+fn query_protocol<'a>(protocol: GameProtocol, host: &'a str, port: Option<u16>) -> BoxFuture<'a, Result<GameServerStatus>> {
+    async move {
+        match protocol {
+            GameProtocol::A2s => a2s::query(resolve(host, port.unwrap_or(protocol.default_port())).await?, QUERY_TIMEOUT).await,
+            GameProtocol::Minecraft => MinecraftService::with_timeout(QUERY_TIMEOUT).game_server_status(host, port).await,
+            GameProtocol::Terraria => terraria::query(host, port.unwrap_or(protocol.default_port()), QUERY_TIMEOUT).await,
+        }
+    }
+    .boxed()
+}
+
+fn format_status(host: &str, port: Option<u16>, status: &GameServerStatus) -> String {
+    let mut output = String::new();
+    output.push_str("% Game server status\n");
+    output.push_str(&format!("% Protocol: {}\n\n", status.protocol));
+
+    match port {
+        Some(port) => output.push_str(&format!("server:         {}:{}\n", host, port)),
+        None => output.push_str(&format!("server:         {}\n", host)),
+    }
+    output.push_str(&format!("name:           {}\n", status.name));
+    if let Some(game) = &status.game {
+        output.push_str(&format!("game:           {}\n", game));
+    }
+    if let Some(map) = &status.map {
+        output.push_str(&format!("map:            {}\n", map));
+    }
+    if let Some(version) = &status.version {
+        output.push_str(&format!("version:        {}\n", version));
+    }
+    output.push_str(&format!("players:        {}/{}\n", status.players_online, status.players_max));
+    if let Some(bots) = status.bots.filter(|bots| *bots > 0) {
+        output.push_str(&format!("bots:           {}\n", bots));
+    }
+    output.push_str(&format!("latency:        {}ms\n", status.latency));
+
+    if let Some(players) = &status.players {
+        for player in players.iter().take(PLAYER_LIMIT) {
+            output.push_str(&format!("player:         {}\n", player));
+        }
+        if players.len() > PLAYER_LIMIT {
+            output.push_str(&format!("remarks:        ... and {} more players online\n", players.len() - PLAYER_LIMIT));
+        }
+    }
+    output.push_str("source:         AKAERE-NETWORKS-AGENT\n");
+    output
+}
+
+/// Process a `-GAMESERVER` query
+pub async fn process_gameserver_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let (protocol, host, port) = parse_target(target)?;
+
+    let status = match protocol.or_else(|| port.and_then(GameProtocol::for_port)) {
+        Some(protocol) => {
+            log_debug!("[{}] Querying {} game server {}:{:?}", ctx, protocol.tag(), host, port);
+            query_protocol(protocol, &host, port).await
+        }
+        None => {
+            log_debug!("[{}] Probing game server {}:{:?} with every protocol", ctx, host, port);
+            let attempts = GameProtocol::ALL.iter().map(|protocol| query_protocol(*protocol, &host, port));
+            futures::future::select_ok(attempts)
+                .await
+                .map(|(status, _)| status)
+                .map_err(|_| anyhow!("No supported game server protocol answered"))
+        }
+    };
+
+    match status {
+        Ok(status) => Ok(format_status(&host, port, &status)),
+        Err(e) => Ok(format!(
+            "Game server query failed for {}\nError: {}\n\nName the protocol with -GAMESERVER-A2S, -GAMESERVER-MINECRAFT or -GAMESERVER-TERRARIA if the port is not the game's default\n",
+            target.trim(),
+            e
+        )),
+    }
+}
+
+pub struct GameServerService;
+
+#[async_trait]
+impl QueryService for GameServerService {
+    fn name(&self) -> &'static str {
+        "gameserver"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &[SUFFIX]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "GAMING SERVICES",
+            summary: "Game server map, players and latency (Valve A2S, Minecraft, Terraria)",
+            example: "203.0.113.7:27015-GAMESERVER-A2S",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Game server query protocols (A2S, Server List Ping, TShock REST)"
+    }
+
+    fn query_class(&self) -> &'static str {
+        "NETWORK"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(60))
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_gameserver_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_selection() {
+        assert_eq!(split_protocol_suffix("203.0.113.7:27015-GAMESERVER-CS2").as_deref(), Some("a2s:203.0.113.7:27015"));
+        assert_eq!(split_protocol_suffix("mc.example.net-gameserver-mc").as_deref(), Some("minecraft:mc.example.net"));
+        assert_eq!(split_protocol_suffix("mc.example.net-GAMESERVER"), None);
+        assert_eq!(split_protocol_suffix("example.com-MC"), None);
+
+        let (protocol, host, port) = parse_target("a2s:203.0.113.7:27016").unwrap();
+        assert_eq!((protocol, host.as_str(), port), (Some(GameProtocol::A2s), "203.0.113.7", Some(27016)));
+        let (protocol, host, port) = parse_target("[2001:db8::7]:7777").unwrap();
+        assert_eq!((protocol, host.as_str(), port), (None, "2001:db8::7", Some(7777)));
+        let (protocol, host, port) = parse_target("2001:db8::7").unwrap();
+        assert_eq!((protocol, host.as_str(), port), (None, "2001:db8::7", None));
+        assert!(parse_target("play.example.net:abc").is_err());
+
+        assert_eq!(GameProtocol::for_port(27015), Some(GameProtocol::A2s));
+        assert_eq!(GameProtocol::for_port(25565), Some(GameProtocol::Minecraft));
+        assert_eq!(GameProtocol::for_port(30000), None);
+    }
+
+    #[test]
+    fn test_format_status() {
+        let status = GameServerStatus {
+            protocol: "Valve A2S",
+            name: "Example CS2 Server".to_string(),
+            game: Some("Counter-Strike 2".to_string()),
+            map: Some("de_dust2".to_string()),
+            version: None,
+            players_online: 12,
+            players_max: 32,
+            bots: Some(2),
+            players: Some(vec!["gordon".to_string()]),
+            latency: 23,
+        };
+        let output = format_status("203.0.113.7", Some(27015), &status);
+        assert!(output.contains("server:         203.0.113.7:27015\n"));
+        assert!(output.contains("map:            de_dust2\n"));
+        assert!(output.contains("players:        12/32\n"));
+        assert!(output.contains("bots:           2\n"));
+        assert!(output.contains("player:         gordon\n"));
+        assert!(!output.contains("version:"));
+    }
+
+    #[tokio::test]
+    async fn test_internal_servers_are_refused() {
+        let ctx = RequestContext::default();
+        for target in ["a2s:127.0.0.1:27015", "minecraft:[::1]:25565", "terraria:10.0.0.1:7878"] {
+            let output = process_gameserver_query(&ctx, target).await.unwrap();
+            assert!(output.contains("does not resolve to a public address"), "{}", output);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// WHOIS Server - Terraria Query
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Terraria server status from the TShock REST API
+//!
+//! Vanilla Terraria servers have no status query, so this asks the
+//! unauthenticated `/v2/server/status` endpoint of TShock (port 7878 unless
+//! the server moved it).
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+use super::GameServerStatus;
+use crate::core::body_limit::LimitedBody;
+
+#[derive(Debug, Deserialize)]
+struct TShockStatus {
+    name: String,
+    #[serde(default)]
+    serverversion: Option<String>,
+    #[serde(default)]
+    tshockversion: Option<String>,
+    #[serde(default)]
+    world: Option<String>,
+    playercount: u32,
+    maxplayers: u32,
+    #[serde(default)]
+    players: Option<Vec<TShockPlayer>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TShockPlayer {
+    nickname: String,
+}
+
+fn status_from(status: TShockStatus, latency: u64) -> GameServerStatus {
+    let version = match (status.serverversion, status.tshockversion) {
+        (Some(server), Some(tshock)) => Some(format!("{} (TShock {})", server, tshock)),
+        (server, tshock) => server.or(tshock.map(|v| format!("TShock {}", v))),
+    };
+    GameServerStatus {
+        protocol: "TShock REST",
+        name: status.name,
+        game: Some("Terraria".to_string()),
+        map: status.world,
+        version,
+        players_online: status.playercount,
+        players_max: status.maxplayers,
+        bots: None,
+        players: status.players.map(|players| players.into_iter().map(|p| p.nickname).collect()),
+        latency,
+    }
+}
+
+/// Query a TShock server's REST API
+pub async fn query(host: &str, port: u16, timeout: Duration) -> Result<GameServerStatus> {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .user_agent("whois-server/1.0")
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let url = format!("http://{}/v2/server/status?players=true", super::resolve(host, port).await?);
+
+    let start = Instant::now();
+    let response = client.get(&url).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("TShock status request failed: HTTP {}", response.status()));
+    }
+    let status: TShockStatus = response.limited_json().await?;
+    Ok(status_from(status, start.elapsed().as_millis() as u64))
+}
@@ -2,6 +2,7 @@ use anyhow::Result;
 use reqwest::Client;
 use super::types::BilibiliIpResponse;
 
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_warn};
 /// Query BiliBili API for geo-location information (async version)
 pub async fn query_bilibili(client: &Client, ip: &str) -> Result<BilibiliIpResponse> {
@@ -30,7 +31,7 @@ pub async fn query_bilibili(client: &Client, ip: &str) -> Result<BilibiliIpRespo
         ));
     }
 
-    let body = response.text().await?;
+    let body = response.limited_text().await?;
     log_debug!("BiliBili API response body: {}", body);
 
     let api_response: BilibiliIpResponse = serde_json::from_str(&body)
@@ -8,6 +8,7 @@ use super::types::{
     RirGeoResponse,
 };
 use super::utils::{extract_ip_from_prefix, truncate_string};
+use crate::services::utils::table::{format_table, section};
 
 use crate::{log_debug};
 /// Format RIR geo location response
@@ -825,71 +826,12 @@ pub async fn format_prefixes_response(
                 prefix_data.len()
             );
 
-            // Calculate adaptive column widths
-            let prefix_width = std::cmp::max(
-                6, // Minimum width for "Prefix"
-                prefix_data
-                    .iter()
-                    .map(|(p, _, _)| p.len())
-                    .max()
-                    .unwrap_or(6),
-            );
-
-            let country_width = std::cmp::max(
-                7, // Minimum width for "Country"
-                prefix_data
-                    .iter()
-                    .map(|(_, c, _)| c.len())
-                    .max()
-                    .unwrap_or(7),
-            );
-
-            let as_name_width = std::cmp::max(
-                7, // Minimum width for "AS Name"
-                prefix_data
-                    .iter()
-                    .map(|(_, _, a)| a.len())
-                    .max()
-                    .unwrap_or(7),
-            );
-
-            formatted.push_str("Currently Announced Prefixes\n");
-            formatted.push_str("============================\n\n");
-
-            // Dynamic header
-            formatted.push_str(&format!(
-                "{:<width1$} | {:<width2$} | {:<width3$}\n",
-                "Prefix",
-                "Country",
-                "AS Name",
-                width1 = prefix_width,
-                width2 = country_width,
-                width3 = as_name_width
-            ));
-
-            // Dynamic separator
-            formatted.push_str(&format!(
-                "{:-<width1$}-|-{:-<width2$}-|-{:-<width3$}\n",
-                "",
-                "",
-                "",
-                width1 = prefix_width,
-                width2 = country_width,
-                width3 = as_name_width
-            ));
-
-            // Data rows
-            for (prefix, country, as_name) in prefix_data {
-                formatted.push_str(&format!(
-                    "{:<width1$} | {:<width2$} | {:<width3$}\n",
-                    truncate_string(&prefix, prefix_width),
-                    truncate_string(&country, country_width),
-                    truncate_string(&as_name, as_name_width),
-                    width1 = prefix_width,
-                    width2 = country_width,
-                    width3 = as_name_width
-                ));
-            }
+            section(&mut formatted, "Currently Announced Prefixes");
+            let rows: Vec<Vec<String>> = prefix_data
+                .into_iter()
+                .map(|(prefix, country, as_name)| vec![prefix, country, as_name])
+                .collect();
+            formatted.push_str(&format_table(&["Prefix", "Country", "AS Name"], &rows));
 
             formatted.push_str(&format!(
                 "\n% Total announced prefixes: {}\n",
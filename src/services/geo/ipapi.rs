@@ -2,6 +2,7 @@ use anyhow::Result;
 use reqwest::Client;
 use super::types::IpApiResponse;
 
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_warn};
 /// Query IP-API for geo-location information (async version)
 pub async fn query_ipapi(client: &Client, ip: &str) -> Result<IpApiResponse> {
@@ -26,7 +27,7 @@ pub async fn query_ipapi(client: &Client, ip: &str) -> Result<IpApiResponse> {
         ));
     }
 
-    let body = response.text().await?;
+    let body = response.limited_text().await?;
     log_debug!("IP-API response body: {}", body);
 
     let api_response: IpApiResponse = serde_json::from_str(&body)
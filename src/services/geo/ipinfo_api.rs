@@ -2,6 +2,7 @@ use anyhow::{Result, anyhow};
 use super::constants::{IPINFO_API_BASE, IPINFO_TOKEN};
 use super::types::IpinfoResponse;
 
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug};
 /// Query IPinfo API
 pub async fn query_ipinfo_api(client: &reqwest::Client, resource: &str) -> Result<IpinfoResponse> {
@@ -18,6 +19,6 @@ pub async fn query_ipinfo_api(client: &reqwest::Client, resource: &str) -> Resul
         return Err(anyhow!("IPinfo API HTTP error: {}", response.status()));
     }
 
-    let json_response: IpinfoResponse = response.json().await?;
+    let json_response: IpinfoResponse = response.limited_json().await?;
     Ok(json_response)
 }
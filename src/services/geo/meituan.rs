@@ -2,6 +2,7 @@ use anyhow::Result;
 use reqwest::Client;
 use super::types::{MeituanCityData, MeituanCityResponse, MeituanIpResponse};
 
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_warn};
 /// Combined Meituan response containing both IP location and city details
 #[derive(Debug, Clone)]
@@ -46,7 +47,7 @@ pub async fn query_meituan(client: &Client, ip: &str) -> Result<MeituanCombinedR
         ));
     }
 
-    let ip_body = ip_response.text().await?;
+    let ip_body = ip_response.limited_text().await?;
     log_debug!("Meituan IP API response body: {}", ip_body);
 
     let ip_api_response: MeituanIpResponse = serde_json::from_str(&ip_body)
@@ -70,7 +71,7 @@ pub async fn query_meituan(client: &Client, ip: &str) -> Result<MeituanCombinedR
         .await?;
 
     let city_details = if city_response.status().is_success() {
-        let city_body = city_response.text().await?;
+        let city_body = city_response.limited_text().await?;
         log_debug!("Meituan City API response body: {}", city_body);
 
         match serde_json::from_str::<MeituanCityResponse>(&city_body) {
@@ -2,6 +2,7 @@ use anyhow::{Result, anyhow};
 use super::constants::{RIPE_PREFIXES_API_BASE, RIPE_RIR_GEO_API_BASE, RIPE_STAT_API_BASE};
 use super::types::{PrefixesResponse, RipeStatResponse, RirGeoResponse};
 
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug};
 /// Query RIPE NCC STAT API
 pub async fn query_ripe_api(client: &reqwest::Client, resource: &str) -> Result<RipeStatResponse> {
@@ -22,7 +23,7 @@ pub async fn query_ripe_api(client: &reqwest::Client, resource: &str) -> Result<
         return Err(anyhow!("RIPE API HTTP error: {}", response.status()));
     }
 
-    let json_response: RipeStatResponse = response.json().await?;
+    let json_response: RipeStatResponse = response.limited_json().await?;
 
     if json_response.status != "ok" {
         return Err(anyhow!("RIPE API error: status={}", json_response.status));
@@ -53,7 +54,7 @@ pub async fn query_rir_geo_api(client: &reqwest::Client, resource: &str) -> Resu
         ));
     }
 
-    let json_response: RirGeoResponse = response.json().await?;
+    let json_response: RirGeoResponse = response.limited_json().await?;
 
     if json_response.status != "ok" {
         return Err(anyhow!(
@@ -87,7 +88,7 @@ pub async fn query_prefixes_api(client: &reqwest::Client, asn: &str) -> Result<P
         ));
     }
 
-    let json_response: PrefixesResponse = response.json().await?;
+    let json_response: PrefixesResponse = response.limited_json().await?;
 
     if json_response.status != "ok" {
         return Err(anyhow!(
@@ -19,6 +19,8 @@
 use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
+use crate::core::body_limit::LimitedBody;
+use crate::services::upstream::api_base;
 use crate::{log_debug, log_error};
 const GITHUB_API_URL: &str = "https://api.github.com";
 
@@ -165,7 +167,7 @@ async fn query_github_user(username: &str) -> Result<GitHubUser> {
         .build()
         .context("Failed to create HTTP client")?;
 
-    let user_url = format!("{}/users/{}", GITHUB_API_URL, urlencoding::encode(username));
+    let user_url = format!("{}/users/{}", api_base(GITHUB_API_URL), urlencoding::encode(username));
 
     log_debug!("Querying GitHub API: {}", user_url);
 
@@ -187,7 +189,7 @@ async fn query_github_user(username: &str) -> Result<GitHubUser> {
     }
 
     let user_data: GitHubUser = response
-        .json()
+        .limited_json()
         .await
         .context("Failed to parse GitHub user data")?;
 
@@ -203,7 +205,7 @@ async fn query_github_repository(owner: &str, repo: &str) -> Result<GitHubReposi
 
     let repo_url = format!(
         "{}/repos/{}/{}",
-        GITHUB_API_URL,
+        api_base(GITHUB_API_URL),
         urlencoding::encode(owner),
         urlencoding::encode(repo)
     );
@@ -228,7 +230,7 @@ async fn query_github_repository(owner: &str, repo: &str) -> Result<GitHubReposi
     }
 
     let repo_data: GitHubRepository = response
-        .json()
+        .limited_json()
         .await
         .context("Failed to parse GitHub repository data")?;
 
@@ -440,6 +442,7 @@ fn format_github_not_found(query: &str, resource_type: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::fixtures::FixtureServer;
 
     #[test]
     fn test_github_name_validation() {
@@ -462,4 +465,28 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().contains("GitHub"));
     }
+
+    #[tokio::test]
+    async fn test_github_fixtures() {
+        let mut fixtures = FixtureServer::start("github").await;
+        fixtures.serve(GITHUB_API_URL, "/users/torvalds", "user_torvalds.json").await;
+        fixtures.serve(GITHUB_API_URL, "/repos/rust-lang/rust", "repo_rust-lang_rust.json").await;
+        fixtures.serve_status(GITHUB_API_URL, "/users/no-such-user", 404).await;
+
+        let response = fixtures.run(process_github_query("torvalds")).await.unwrap();
+        assert!(response.contains("username: torvalds\nuser-id: 1024025\nuser-type: User\n"));
+        assert!(response.contains("display-name: Linus Torvalds\n"));
+        assert!(response.contains("location: Portland, OR\n"));
+        assert!(!response.contains("website:"));
+
+        let response = fixtures.run(process_github_query("rust-lang/rust")).await.unwrap();
+        assert!(response.contains("full-name: rust-lang/rust\n"));
+        assert!(response.contains("owner-type: Organization\n"));
+        assert!(response.contains("license: Other\nlicense-spdx: NOASSERTION\n"));
+        assert!(response.contains("features: issues, projects, downloads\n"));
+        assert!(response.contains("topics: compiler, hacktoberfest, language, rust\n"));
+
+        let response = fixtures.run(process_github_query("no-such-user")).await.unwrap();
+        assert!(response.starts_with("GitHub USER Not Found: no-such-user\n"));
+    }
 }
@@ -16,6 +16,32 @@
  * along with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
+use crate::services::registry::service_registry;
+
+/// List the registered services, grouped by their HELP category
+fn push_registered_services(output: &mut String) {
+    let registry = service_registry();
+    let mut categories: Vec<&str> = Vec::new();
+    for service in registry.services() {
+        if !categories.contains(&service.help().category) {
+            categories.push(service.help().category);
+        }
+    }
+
+    for category in categories {
+        output.push_str(&format!("{}:\n", category));
+        output.push_str("-".repeat(40).as_str());
+        output.push('\n');
+        for service in registry.services().filter(|s| s.help().category == category) {
+            let help = service.help();
+            let sample = help.example.split(' ').next().unwrap_or(help.example);
+            output.push_str(&format!("{:<19} - {}\n", sample, help.summary));
+            output.push_str(&format!("example: {}\n", help.example));
+            output.push('\n');
+        }
+    }
+}
+
 pub fn generate_help_response() -> String {
     let mut output = String::new();
 
@@ -75,6 +101,8 @@ pub fn generate_help_response() -> String {
     output.push('\n');
     output.push_str("8.8.8.8-LG          - RIPE RIS Looking Glass query\n");
     output.push_str("example: 8.8.8.8-LG\n");
+    output.push_str("8.8.8.0/24-LG@DE    - Looking Glass limited to collectors or peer ASes\n");
+    output.push_str("example: 8.8.8.0/24-LG@rrc00,AS6939\n");
     output.push('\n');
     output.push_str("AS15169-RADB        - Routing Assets Database query\n");
     output.push_str("example: AS15169-RADB\n");
@@ -88,6 +116,7 @@ pub fn generate_help_response() -> String {
     output.push('\n');
     output.push_str("730-STEAM           - Steam game or user information\n");
     output.push_str("example: 730-STEAM (Counter-Strike 2)\n");
+    output.push_str("example: 730-STEAM-CC=JP (prices from the Japanese store)\n");
     output.push('\n');
     output.push_str("Inception-STEAMSEARCH - Search Steam games by title\n");
     output.push_str("example: Inception-STEAMSEARCH\n");
@@ -160,12 +189,24 @@ pub fn generate_help_response() -> String {
     output.push_str("google.com-TRACEROUTE - Alternative traceroute format\n");
     output.push_str("example: google.com-TRACE\n");
     output.push('\n');
+    output.push_str("8.8.8.8-TRACE@de    - Traceroute from a chosen vantage point\n");
+    output.push_str("1.1.1.1-PING@as13335 - Ping from a country, city, ASN or network\n");
+    output.push_str("8.8.8.8-TRACE@local - Traceroute from this server (if enabled)\n");
+    output.push_str("example: 8.8.8.8-TRACE@de,us\n");
+    output.push('\n');
+    output.push_str("PROBES              - Online Globalping probes per region\n");
+    output.push_str("AS13335-PROBES      - Probes matching a location filter\n");
+    output.push_str("example: DE-PROBES\n");
+    output.push('\n');
 
     output.push_str("SECURITY & CERTIFICATES:\n");
     output.push_str("-".repeat(40).as_str());
     output.push('\n');
-    output.push_str("google.com-SSL      - SSL/TLS certificate analysis\n");
+    output.push_str("google.com-SSL      - SSL/TLS certificate analysis with full chain and trust path\n");
     output.push_str("example: google.com-SSL\n");
+    output.push_str("host:port-SSL       - Certificate on another port (e.g. example.com:8443-SSL)\n");
+    output.push_str("host:port-SSL-STARTTLS - Certificate after SMTP/IMAP/POP3 STARTTLS\n");
+    output.push_str("example: mail.example.com:587-SSL-STARTTLS\n");
     output.push('\n');
     output.push_str("google.com-CRT      - Certificate Transparency logs\n");
     output.push_str("example: google.com-CRT\n");
@@ -185,6 +226,7 @@ pub fn generate_help_response() -> String {
     output.push('\n');
     output.push_str("mc.hypixel.net-MINECRAFT - Minecraft server status\n");
     output.push_str("mc.hypixel.net-MC   - Minecraft server status (short)\n");
+    output.push_str("bedrock:play.example.net-MC - Minecraft Bedrock server status\n");
     output.push_str("example: mc.hypixel.net-MINECRAFT\n");
     output.push('\n');
     output.push_str("730-STEAM           - Steam game/user information\n");
@@ -217,42 +259,7 @@ pub fn generate_help_response() -> String {
     output.push_str("example: 今天吃什么中国 or -MEAL-CN\n");
     output.push('\n');
 
-    output.push_str("PACKAGE REPOSITORIES:\n");
-    output.push_str("-".repeat(40).as_str());
-    output.push('\n');
-    output.push_str("serde-CARGO         - Rust crates.io package information\n");
-    output.push_str("example: serde-CARGO\n");
-    output.push('\n');
-    output.push_str("requests-PYPI       - Python PyPI package information\n");
-    output.push_str("example: requests-PYPI\n");
-    output.push('\n');
-    output.push_str("react-NPM           - Node.js NPM package information\n");
-    output.push_str("example: react-NPM\n");
-    output.push('\n');
-    output.push_str("yay-AUR             - Arch User Repository packages\n");
-    output.push_str("example: yay-AUR\n");
-    output.push('\n');
-    output.push_str("curl-DEBIAN         - Debian package information\n");
-    output.push_str("example: curl-DEBIAN\n");
-    output.push('\n');
-    output.push_str("firefox-UBUNTU      - Ubuntu package information\n");
-    output.push_str("example: firefox-UBUNTU\n");
-    output.push('\n');
-    output.push_str("nixpkgs-NIXOS       - NixOS package information\n");
-    output.push_str("example: nixpkgs-NIXOS\n");
-    output.push('\n');
-    output.push_str("zypper-OPENSUSE     - OpenSUSE package information\n");
-    output.push_str("example: zypper-OPENSUSE\n");
-    output.push('\n');
-    output.push_str("htop-AOSC           - AOSC OS package information\n");
-    output.push_str("example: htop-AOSC\n");
-    output.push('\n');
-    output.push_str("sodium-MODRINTH     - Modrinth mod/resource pack information\n");
-    output.push_str("example: sodium-MODRINTH\n");
-    output.push('\n');
-    output.push_str("jei-CURSEFORGE      - CurseForge mod information (requires API key)\n");
-    output.push_str("example: jei-CURSEFORGE or 238222-CURSEFORGE\n");
-    output.push('\n');
+    push_registered_services(&mut output);
 
     output.push_str("DEVELOPMENT SERVICES:\n");
     output.push_str("-".repeat(40).as_str());
@@ -275,7 +282,48 @@ pub fn generate_help_response() -> String {
     output.push_str("-".repeat(40).as_str());
     output.push('\n');
     output.push_str("HELP                - Show this help message\n");
+    output.push_str("example.com-EXPLAIN - Show how a query would be processed, without running it\n");
+    output.push('\n');
+
+    output.push_str("OUTPUT MODIFIERS (chain after any query):\n");
+    output.push_str("-".repeat(40).as_str());
+    output.push('\n');
+    output.push_str("-JSON               - JSON output (e.g. 1.1.1.1-GEO-JSON)\n");
+    output.push_str("-MD                 - Markdown output (e.g. example.com-SSL-MD)\n");
+    output.push_str("-P<n>               - Page n of a long response (e.g. AS13335-PREFIXES-P2)\n");
+    output.push_str("-SIGNED             - Append a minisign signature of the response (e.g. AS13335-SIGNED)\n");
+    output.push_str("-NOCACHE            - Fetch a fresh response instead of a cached one (e.g. 8.8.8.8-GEO-NOCACHE)\n");
+    output.push_str("-NOFOLLOW           - Registry answer only, without the registrar referral (e.g. example.com-NOFOLLOW)\n");
+    output.push_str("-RAW                - Unmodified upstream response, no patches or colors (e.g. example.com-RAW)\n");
+    output.push('\n');
+
+    output.push_str("REGISTRY FLAGS (words after an IP, ASN or object):\n");
+    output.push_str("-".repeat(40).as_str());
+    output.push('\n');
+    output.push_str("RIPE, APNIC, AFRINIC - -B -r -G -b -x -l -L -m -M -c -d, -T <types> -i <attrs> -s <sources>\n");
+    output.push_str("example: 193.0.0.0/21 -B -T inetnum\n");
+    output.push_str("ARIN                - n a o p c (record type), + = < > ! (e.g. 8.8.8.8 n +)\n");
+    output.push_str("LACNIC and others   - No flags; the bare target is sent\n");
+    output.push_str("Flags are sent in the syntax of the server the query is referred to; others are listed as not sent\n");
+    output.push('\n');
+
+    output.push_str("MACROS:\n");
+    output.push_str("-".repeat(40).as_str());
     output.push('\n');
+    output.push_str("AUDIT <domain>      - Run WHOIS, DNS, SSL and CRT queries in sequence\n");
+    output.push_str("More macros can be defined by the operator (see --macro-file)\n");
+    output.push('\n');
+
+    let aliases = crate::core::alias::aliases();
+    if !aliases.is_empty() {
+        output.push_str("SUFFIX ALIASES:\n");
+        output.push_str("-".repeat(40).as_str());
+        output.push('\n');
+        for entry in aliases {
+            output.push_str(&format!("{:<19} - Same as {}\n", entry.alias, entry.target));
+        }
+        output.push('\n');
+    }
 
     output.push_str("WHOIS-COLOR PROTOCOL:\n");
     output.push_str("-".repeat(40).as_str());
@@ -283,6 +331,8 @@ pub fn generate_help_response() -> String {
     output.push_str("This server supports WHOIS-COLOR protocol v1.0 for enhanced output.\n");
     output.push_str("Send 'X-WHOIS-COLOR-PROBE: 1' to detect color support.\n");
     output.push_str("Use 'X-WHOIS-COLOR: ripe' or 'X-WHOIS-COLOR: bgptools' for colored output.\n");
+    output.push_str("Send 'X-WHOIS-DEBUG: 1' to list data sources and timings below the response.\n");
+    output.push_str("Send 'X-WHOIS-SIGNATURE: 1' to have the response signed (same as -SIGNED).\n");
     output.push('\n');
 
     output.push_str("EXAMPLES:\n");
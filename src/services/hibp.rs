@@ -0,0 +1,354 @@
+// WHOIS Server - Have I Been Pwned
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Have I Been Pwned breach lookups (`-HIBP`)
+//!
+//! Disabled unless the operator sets `HIBP_API_KEY`. For an email address the
+//! breaches it appears in are listed; for a domain (which must be verified
+//! on the operator's HIBP subscription) only the number of breached accounts
+//! per breach is shown, never the addresses themselves.
+//!
+//! The API key is rate limited per minute, so requests are spaced out to
+//! `HIBP_RATE_LIMIT` per minute (default 10) across all clients. A request
+//! that would have to wait past its deadline is answered with a retry hint
+//! instead of being queued.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use crate::{log_debug, log_warn};
+
+const HIBP_API_BASE: &str = "https://haveibeenpwned.com/api/v3/";
+const DEFAULT_RATE_LIMIT: u32 = 10;
+
+/// Earliest time the next HIBP request may be sent
+static NEXT_SLOT: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Breach {
+    name: String,
+    title: Option<String>,
+    domain: Option<String>,
+    breach_date: Option<String>,
+    added_date: Option<String>,
+    pwn_count: Option<u64>,
+    #[serde(default)]
+    data_classes: Vec<String>,
+    #[serde(default)]
+    is_verified: bool,
+}
+
+/// What a `-HIBP` query refers to
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    Email(String),
+    Domain(String),
+}
+
+fn parse_target(target: &str) -> Option<Target> {
+    let target = target.trim().to_ascii_lowercase();
+    let is_domain = |d: &str| {
+        d.contains('.') &&
+            !d.starts_with('.') &&
+            !d.ends_with('.') &&
+            d.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+    };
+
+    match target.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && is_domain(domain) => Some(Target::Email(target)),
+        Some(_) => None,
+        None if is_domain(&target) => Some(Target::Domain(target)),
+        None => None,
+    }
+}
+
+/// Reserve the next request slot, returning how long to wait for it
+fn reserve_slot(next: &mut Option<Instant>, now: Instant, interval: Duration) -> Duration {
+    let start = next.map_or(now, |slot| slot.max(now));
+    *next = Some(start + interval);
+    start - now
+}
+
+/// Requests per minute allowed by the operator's key
+fn rate_limit() -> u32 {
+    std::env::var("HIBP_RATE_LIMIT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&v| v > 0)
+        .unwrap_or(DEFAULT_RATE_LIMIT)
+}
+
+/// Wait for a request slot, or return the wait when it exceeds the deadline
+async fn acquire_slot(ctx: &RequestContext) -> Result<(), Duration> {
+    let interval = Duration::from_secs(60) / rate_limit();
+    let wait = {
+        let mut next = NEXT_SLOT.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let pending = next.map_or(Duration::ZERO, |slot| slot.saturating_duration_since(now));
+        if let Some(left) = ctx.remaining() && pending >= left {
+            return Err(pending);
+        }
+        reserve_slot(&mut next, now, interval)
+    };
+    if !wait.is_zero() {
+        log_debug!("[{}] Waiting {:?} for an HIBP request slot", ctx, wait);
+        tokio::time::sleep(wait).await;
+    }
+    Ok(())
+}
+
+/// Outcome of an HIBP API request
+enum Reply<T> {
+    Found(T),
+    NotFound,
+    Refused(String),
+}
+
+struct HibpClient {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+impl HibpClient {
+    fn from_env() -> Option<Self> {
+        let _ = dotenv::dotenv();
+        let api_key = std::env::var("HIBP_API_KEY").ok().filter(|k| !k.trim().is_empty())?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .user_agent("WhoisServer/1.0 HIBP Client")
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        Some(Self { client, api_key: api_key.trim().to_string() })
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<Reply<T>> {
+        let response = self.client
+            .get(format!("{}{}", api_base(HIBP_API_BASE), path))
+            .header("hibp-api-key", &self.api_key)
+            .send().await?;
+
+        let status = response.status();
+        match status.as_u16() {
+            200 => Ok(Reply::Found(response.limited_json().await?)),
+            404 => Ok(Reply::NotFound),
+            401 => Ok(Reply::Refused("The server's HIBP API key was rejected".to_string())),
+            403 => Ok(Reply::Refused("Not permitted for this server's HIBP subscription".to_string())),
+            429 => {
+                let retry = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("a few");
+                Ok(Reply::Refused(format!("HIBP rate limit reached, try again in {} seconds", retry)))
+            }
+            _ => Err(anyhow!("HIBP request failed: HTTP {}", status)),
+        }
+    }
+}
+
+/// Look up breaches for an email address or verified domain
+pub async fn process_hibp_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let Some(client) = HibpClient::from_env() else {
+        return Ok(
+            "Have I Been Pwned lookups are not enabled on this server.\n\
+             The operator must set HIBP_API_KEY to enable -HIBP queries.\n".to_string()
+        );
+    };
+    let Some(parsed) = parse_target(target) else {
+        return Ok(format!(
+            "Invalid HIBP query: {}\nUse an email address or a domain, e.g. user@example.com-HIBP\n",
+            target
+        ));
+    };
+
+    if let Err(wait) = acquire_slot(ctx).await {
+        log_warn!("[{}] HIBP request slot not available for {:?}", ctx, wait);
+        return Ok(format!(
+            "HIBP lookups are rate limited on this server, try again in {} seconds\n",
+            wait.as_secs().max(1)
+        ));
+    }
+
+    match parsed {
+        Target::Email(email) => {
+            let path = format!("breachedaccount/{}?truncateResponse=false", urlencoding::encode(&email));
+            Ok(match client.get::<Vec<Breach>>(&path).await? {
+                Reply::Found(breaches) => format_account(&email, &breaches),
+                Reply::NotFound => format_account(&email, &[]),
+                Reply::Refused(reason) => format!("HIBP Query Failed for: {}\n{}\n", email, reason),
+            })
+        }
+        Target::Domain(domain) => {
+            let path = format!("breacheddomain/{}", urlencoding::encode(&domain));
+            Ok(match client.get::<BTreeMap<String, Vec<String>>>(&path).await? {
+                Reply::Found(aliases) => format_domain(&domain, &aliases),
+                Reply::NotFound => format_domain(&domain, &BTreeMap::new()),
+                Reply::Refused(reason) => format!(
+                    "HIBP Query Failed for: {}\n{}\n\
+                     % Domain searches only work for domains verified on the operator's HIBP account\n",
+                    domain,
+                    reason
+                ),
+            })
+        }
+    }
+}
+
+fn format_account(email: &str, breaches: &[Breach]) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("Have I Been Pwned Report for: {}\n", email));
+    output.push_str("=".repeat(60).as_str());
+    output.push('\n');
+    output.push_str(&format!("account: {}\n", email));
+    output.push_str(&format!("breach-count: {}\n", breaches.len()));
+
+    if breaches.is_empty() {
+        output.push_str("% Good news, no breached accounts found\n");
+        return output;
+    }
+
+    let mut breaches: Vec<&Breach> = breaches.iter().collect();
+    breaches.sort_by(|a, b| b.breach_date.cmp(&a.breach_date));
+    for breach in breaches {
+        output.push('\n');
+        output.push_str(&format!("breach: {}\n", breach.name));
+        if let Some(title) = breach.title.as_ref().filter(|t| **t != breach.name) {
+            output.push_str(&format!("breach-title: {}\n", title));
+        }
+        if let Some(domain) = breach.domain.as_ref().filter(|d| !d.is_empty()) {
+            output.push_str(&format!("breach-domain: {}\n", domain));
+        }
+        if let Some(date) = &breach.breach_date {
+            output.push_str(&format!("breach-date: {}\n", date));
+        }
+        if let Some(added) = &breach.added_date {
+            output.push_str(&format!("breach-added: {}\n", added));
+        }
+        if let Some(count) = breach.pwn_count {
+            output.push_str(&format!("breach-accounts: {}\n", count));
+        }
+        if !breach.data_classes.is_empty() {
+            output.push_str(&format!("breach-data: {}\n", breach.data_classes.join(", ")));
+        }
+        if !breach.is_verified {
+            output.push_str("breach-verified: no\n");
+        }
+    }
+    output
+}
+
+fn format_domain(domain: &str, aliases: &BTreeMap<String, Vec<String>>) -> String {
+    let mut per_breach: BTreeMap<&str, usize> = BTreeMap::new();
+    for breach in aliases.values().flatten() {
+        *per_breach.entry(breach.as_str()).or_default() += 1;
+    }
+    let mut per_breach: Vec<(&str, usize)> = per_breach.into_iter().collect();
+    per_breach.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut output = String::new();
+    output.push_str(&format!("Have I Been Pwned Domain Report for: {}\n", domain));
+    output.push_str("=".repeat(60).as_str());
+    output.push('\n');
+    output.push_str(&format!("domain: {}\n", domain));
+    output.push_str(&format!("breached-accounts: {}\n", aliases.len()));
+    output.push_str(&format!("breaches: {}\n", per_breach.len()));
+    for (breach, count) in per_breach {
+        output.push_str(&format!("breach: {} ({} accounts)\n", breach, count));
+    }
+    output.push_str("% Individual addresses are not disclosed\n");
+    output
+}
+
+pub struct HibpService;
+
+#[async_trait]
+impl QueryService for HibpService {
+    fn name(&self) -> &'static str {
+        "hibp"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-HIBP"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "SECURITY INVESTIGATION",
+            summary: "Have I Been Pwned breaches (when enabled)",
+            example: "user@example.com-HIBP",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Have I Been Pwned API v3"
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_hibp_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(parse_target("User@Example.com"), Some(Target::Email("user@example.com".to_string())));
+        assert_eq!(parse_target("example.com"), Some(Target::Domain("example.com".to_string())));
+        assert_eq!(parse_target("@example.com"), None);
+        assert_eq!(parse_target("user@localhost"), None);
+        assert_eq!(parse_target("not a domain"), None);
+    }
+
+    #[test]
+    fn test_reserve_slot_spaces_requests() {
+        let now = Instant::now();
+        let interval = Duration::from_secs(6);
+        let mut next = None;
+
+        assert_eq!(reserve_slot(&mut next, now, interval), Duration::ZERO);
+        assert_eq!(reserve_slot(&mut next, now, interval), interval);
+        assert_eq!(reserve_slot(&mut next, now + Duration::from_secs(2), interval), Duration::from_secs(10));
+        assert_eq!(reserve_slot(&mut next, now + Duration::from_secs(60), interval), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_format_domain_hides_addresses() {
+        let aliases = BTreeMap::from([
+            ("alice".to_string(), vec!["Adobe".to_string(), "LinkedIn".to_string()]),
+            ("bob".to_string(), vec!["Adobe".to_string()]),
+        ]);
+        let output = format_domain("example.com", &aliases);
+
+        assert!(output.contains("breached-accounts: 2\n"));
+        assert!(output.contains("breach: Adobe (2 accounts)\nbreach: LinkedIn (1 accounts)\n"));
+        assert!(!output.contains("alice"));
+    }
+
+    #[test]
+    fn test_format_account() {
+        let breaches: Vec<Breach> = serde_json::from_str(
+            r#"[{"Name":"Adobe","Title":"Adobe","Domain":"adobe.com","BreachDate":"2013-10-04",
+                "AddedDate":"2013-12-04T00:00:00Z","PwnCount":152445165,
+                "DataClasses":["Email addresses","Password hints","Passwords","Usernames"],"IsVerified":true}]"#,
+        )
+        .unwrap();
+        let output = format_account("user@example.com", &breaches);
+
+        assert!(output.contains("breach-count: 1\n"));
+        assert!(output.contains("breach: Adobe\nbreach-domain: adobe.com\nbreach-date: 2013-10-04\n"));
+        assert!(output.contains("breach-data: Email addresses, Password hints, Passwords, Usernames\n"));
+        assert!(format_account("user@example.com", &[]).contains("no breached accounts found"));
+    }
+}
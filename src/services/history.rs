@@ -0,0 +1,410 @@
+// WHOIS Server - WHOIS History
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Historical WHOIS snapshots (`example.com-HISTORY`)
+//!
+//! Every domain, IP and ASN response served is compared with the last
+//! snapshot of the object in LMDB. Comment lines and registry timestamps
+//! that change on every query are left out, and when the SHA-256 hash of
+//! what remains differs, the body is stored as a new snapshot. The history
+//! query lists the dates on which the record changed and a unified diff of
+//! the two most recent snapshots. Only objects queried through this server
+//! have a history, starting with their first query.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::HISTORY_LMDB_PATH;
+use crate::core::public_suffix::registrable_domain;
+use crate::core::query::parse_asn;
+use crate::core::request_context::RequestContext;
+use crate::core::{QueryType, analyze_query};
+use crate::log_debug;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::storage::kv::KvStore;
+use crate::storage::lmdb::LmdbStorage;
+
+/// Snapshots kept per object, oldest dropped first
+const MAX_SNAPSHOTS: usize = 50;
+/// Larger responses are not recorded
+const MAX_BODY_BYTES: usize = 256 * 1024;
+/// Lines of unchanged context around each change in the diff
+const DIFF_CONTEXT: usize = 3;
+/// Line pairs compared before the diff falls back to replacing the whole middle
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// The record as it was from `taken_at` on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    taken_at: u64,
+    hash: String,
+    body: String,
+}
+
+/// Snapshots of one object, oldest first
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ObjectHistory {
+    snapshots: Vec<Snapshot>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time should be after Unix epoch")
+        .as_secs()
+}
+
+fn format_date(secs: u64) -> String {
+    DateTime::from_timestamp(secs as i64, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_default()
+}
+
+/// Object a response is about, as stored; other query types have no history
+fn object_key(query_type: &QueryType) -> Option<String> {
+    match query_type {
+        QueryType::Domain(domain) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            Some(registrable_domain(&domain).unwrap_or(domain))
+        }
+        QueryType::IPv4(ip) => Some(ip.to_string()),
+        QueryType::IPv6(ip) => Some(ip.to_string()),
+        QueryType::ASN(asn) => parse_asn(asn).map(|asn| format!("AS{}", asn)),
+        _ => None,
+    }
+}
+
+/// The record without comments and per-query timestamps
+fn normalize(response: &str) -> String {
+    response
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with('%') && !line.starts_with('#'))
+        .filter(|line| !line.to_lowercase().contains("last update of whois database"))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+fn hash(body: &str) -> String {
+    Sha256::digest(body.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Add `body` as a snapshot taken at `now` unless it equals the latest one
+fn add_snapshot(history: &mut ObjectHistory, body: String, now: u64) -> bool {
+    let hash = hash(&body);
+    if history.snapshots.last().is_some_and(|latest| latest.hash == hash) {
+        return false;
+    }
+    history.snapshots.push(Snapshot { taken_at: now, hash, body });
+    if history.snapshots.len() > MAX_SNAPSHOTS {
+        history.snapshots.remove(0);
+    }
+    true
+}
+
+/// Store a served response as a new snapshot if the record changed
+pub fn record_snapshot(query_type: &QueryType, response: &str) {
+    let Some(key) = object_key(query_type) else {
+        return;
+    };
+    let body = normalize(response);
+    if body.is_empty() || body.len() > MAX_BODY_BYTES {
+        return;
+    }
+    let result = (|| -> Result<()> {
+        let storage = LmdbStorage::new(HISTORY_LMDB_PATH)?;
+        let key = format!("history:{}", key);
+        let mut history: ObjectHistory = storage.get_json(&key)?.unwrap_or_default();
+        if add_snapshot(&mut history, body, now_secs()) {
+            storage.put_json(&key, &history)?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        log_debug!("Recording WHOIS history of {} failed: {}", key, e);
+    }
+}
+
+/// Drop the oldest snapshots of all objects until at least `bytes` of
+/// bodies are gone, returning the bytes removed
+fn evict_oldest_in(store: &dyn KvStore, bytes: u64) -> Result<u64> {
+    let mut snapshots = Vec::new();
+    for key in store.scan("history:")? {
+        let Some(history) = store.get_json::<ObjectHistory>(&key)? else {
+            continue;
+        };
+        snapshots.extend(
+            history.snapshots.iter().map(|snapshot| (snapshot.taken_at, snapshot.body.len() as u64, key.clone()))
+        );
+    }
+    snapshots.sort();
+
+    // Snapshots are stored oldest first, so dropping the oldest ones of an
+    // object removes a prefix of its list
+    let mut dropped: BTreeMap<String, usize> = BTreeMap::new();
+    let mut evicted = 0;
+    for (_, size, key) in snapshots {
+        if evicted >= bytes {
+            break;
+        }
+        *dropped.entry(key).or_default() += 1;
+        evicted += size;
+    }
+
+    for (key, count) in dropped {
+        let Some(mut history) = store.get_json::<ObjectHistory>(&key)? else {
+            continue;
+        };
+        history.snapshots.drain(..count.min(history.snapshots.len()));
+        if history.snapshots.is_empty() {
+            store.delete(&key)?;
+        } else {
+            store.put_json(&key, &history)?;
+        }
+    }
+    Ok(evicted)
+}
+
+/// Drop the oldest snapshots until at least `bytes` are gone
+///
+/// Used by the disk janitor when the LMDB databases are over their cap.
+pub fn evict_oldest_snapshots(bytes: u64) -> Result<u64> {
+    let storage = LmdbStorage::new(HISTORY_LMDB_PATH)?;
+    evict_oldest_in(&storage, bytes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Line edits turning `old` into `new` (longest common subsequence)
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Change, &'a str)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old_mid, new_mid) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut edits: Vec<(Change, &str)> = old[..prefix].iter().map(|line| (Change::Equal, *line)).collect();
+    if old_mid.len() * new_mid.len() > MAX_DIFF_CELLS {
+        edits.extend(old_mid.iter().map(|line| (Change::Delete, *line)));
+        edits.extend(new_mid.iter().map(|line| (Change::Insert, *line)));
+    } else {
+        // lcs[i][j]: common subsequence length of old_mid[i..] and new_mid[j..]
+        let width = new_mid.len() + 1;
+        let mut lcs = vec![0u32; (old_mid.len() + 1) * width];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lcs[i * width + j] = if old_mid[i] == new_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() || j < new_mid.len() {
+            if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+                edits.push((Change::Equal, old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if i < old_mid.len() && (j == new_mid.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]) {
+                edits.push((Change::Delete, old_mid[i]));
+                i += 1;
+            } else {
+                edits.push((Change::Insert, new_mid[j]));
+                j += 1;
+            }
+        }
+    }
+    edits.extend(old[old.len() - suffix..].iter().map(|line| (Change::Equal, *line)));
+    edits
+}
+
+/// Unified diff of two bodies, empty when they are equal
+fn unified_diff(old_label: &str, new_label: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+    let changed: Vec<usize> = (0..edits.len()).filter(|&i| edits[i].0 != Change::Equal).collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // Edit ranges with context, merged when their context overlaps
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let start = i.saturating_sub(DIFF_CONTEXT);
+        let end = (i + DIFF_CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for (start, end) in hunks {
+        let before = &edits[..start];
+        let old_before = before.iter().filter(|(change, _)| *change != Change::Insert).count();
+        let new_before = before.iter().filter(|(change, _)| *change != Change::Delete).count();
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|(change, _)| *change != Change::Insert).count();
+        let new_count = hunk.iter().filter(|(change, _)| *change != Change::Delete).count();
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_before + usize::from(old_count > 0),
+            old_count,
+            new_before + usize::from(new_count > 0),
+            new_count
+        ));
+        for (change, line) in hunk {
+            let marker = match change {
+                Change::Equal => ' ',
+                Change::Delete => '-',
+                Change::Insert => '+',
+            };
+            output.push_str(&format!("{}{}\n", marker, line));
+        }
+    }
+    output
+}
+
+fn format_history(key: &str, history: &ObjectHistory) -> String {
+    let mut output = format!("% WHOIS history of {}\n", key);
+    let Some(first) = history.snapshots.first() else {
+        output.push_str(&format!("% No snapshots yet; query {} to record its current record\n", key));
+        return output;
+    };
+    output.push_str(&format!(
+        "% {} snapshots since {}, taken when the record changed between queries\n\n",
+        history.snapshots.len(),
+        format_date(first.taken_at)
+    ));
+    for (i, snapshot) in history.snapshots.iter().enumerate().rev() {
+        let label = if i == 0 { "first-seen:" } else { "changed:" };
+        output.push_str(&format!("{:<16}{}  sha256:{}\n", label, format_date(snapshot.taken_at), &snapshot.hash[..16]));
+    }
+
+    if let [.., previous, latest] = history.snapshots.as_slice() {
+        output.push_str(&format!(
+            "\n% Changes between {} and {}\n",
+            format_date(previous.taken_at),
+            format_date(latest.taken_at)
+        ));
+        output.push_str(&unified_diff(
+            &format!("{} {}", key, format_date(previous.taken_at)),
+            &format!("{} {}", key, format_date(latest.taken_at)),
+            &previous.body,
+            &latest.body,
+        ));
+    } else {
+        output.push_str("\n% The record has not changed since it was first seen\n");
+    }
+    output
+}
+
+/// Process a `-HISTORY` query
+pub async fn process_history_query(target: &str) -> Result<String> {
+    let target = target.trim();
+    let Some(key) = object_key(&analyze_query(target)) else {
+        return Ok(format!(
+            "% No history for '{}': expected a domain, IP address or ASN such as example.com-HISTORY\n",
+            target
+        ));
+    };
+    let storage = LmdbStorage::new(HISTORY_LMDB_PATH)?;
+    let history: ObjectHistory = storage.get_json(&format!("history:{}", key))?.unwrap_or_default();
+    Ok(format_history(&key, &history))
+}
+
+/// WHOIS history service
+pub struct HistoryService;
+
+#[async_trait]
+impl QueryService for HistoryService {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-HISTORY"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "DOMAIN ANALYSIS",
+            summary: "Dates a domain, IP or ASN record changed and a diff of its last two snapshots",
+            example: "example.com-HISTORY",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "local snapshots"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        None
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_history_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshots_and_diff() {
+        let mut history = ObjectHistory::default();
+        let v1 = "% This query was served by the RIPE Database\ndomain: example.com\nstatus: active\nnserver: a.ns\nnserver: b.ns\n>>> Last update of WHOIS database: 2025-01-01T00:00:00Z <<<\n";
+        let v1_again = "% This query was served by another node\ndomain: example.com\nstatus: active\nnserver: a.ns\nnserver: b.ns\n>>> Last update of WHOIS database: 2025-01-02T00:00:00Z <<<\n";
+        let v2 = "domain: example.com\nstatus: active\nnserver: a.ns\nnserver: c.ns\n";
+        assert!(add_snapshot(&mut history, normalize(v1), 1_700_000_000));
+        assert!(!add_snapshot(&mut history, normalize(v1_again), 1_700_000_100));
+        assert!(add_snapshot(&mut history, normalize(v2), 1_700_086_400));
+        assert_eq!(history.snapshots.len(), 2);
+
+        let output = format_history("example.com", &history);
+        assert!(output.contains("changed:        2023-11-15 22:13:20 UTC"));
+        assert!(output.contains("first-seen:     2023-11-14 22:13:20 UTC"));
+        assert!(output.ends_with("@@ -1,4 +1,4 @@\n domain: example.com\n status: active\n nserver: a.ns\n-nserver: b.ns\n+nserver: c.ns\n"));
+
+        assert_eq!(unified_diff("a", "b", "x\n", "x\n"), "");
+        assert_eq!(unified_diff("a", "b", "", "x\n"), "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+x\n");
+        assert_eq!(object_key(&analyze_query("WWW.Example.COM")), Some("example.com".to_string()));
+        assert_eq!(object_key(&analyze_query("as213605")), Some("AS213605".to_string()));
+    }
+
+    #[test]
+    fn test_evict_oldest_snapshots() {
+        let store = crate::storage::kv::MemoryStore::new();
+        let store: &dyn KvStore = &store;
+        let mut first = ObjectHistory::default();
+        add_snapshot(&mut first, "status: old\n".to_string(), 100);
+        add_snapshot(&mut first, "status: new\n".to_string(), 300);
+        let mut second = ObjectHistory::default();
+        add_snapshot(&mut second, "status: only\n".to_string(), 200);
+        store.put_json("history:example.com", &first).unwrap();
+        store.put_json("history:AS13335", &second).unwrap();
+
+        // The oldest snapshot overall goes first, then the next oldest
+        assert!(evict_oldest_in(store, 1).unwrap() > 0);
+        let first: ObjectHistory = store.get_json("history:example.com").unwrap().unwrap();
+        assert_eq!(first.snapshots.len(), 1);
+        assert_eq!(first.snapshots[0].taken_at, 300);
+        assert!(store.exists("history:AS13335").unwrap());
+
+        evict_oldest_in(store, 1).unwrap();
+        assert!(!store.exists("history:AS13335").unwrap());
+        assert!(store.exists("history:example.com").unwrap());
+    }
+}
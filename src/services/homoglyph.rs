@@ -0,0 +1,409 @@
+// WHOIS Server - Homoglyph Analysis
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! IDN homograph detection (`-HOMOGLYPH`)
+//!
+//! Decodes punycode labels, reports the scripts used by each label, and
+//! flags labels that mix scripts the way spoofed names do (Latin with
+//! Cyrillic or Greek). The registrable label is reduced to a confusable
+//! "skeleton" (in the spirit of UTS #39) and compared with the skeletons
+//! of frequently impersonated domains, so `аpple.com` (Cyrillic `а`) and
+//! `rnicrosoft.com` are both reported as lookalikes.
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::core::public_suffix::{public_suffix_list, to_ascii};
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+
+/// Frequently impersonated domains
+const POPULAR_DOMAINS: &[&str] = &[
+    "google.com",
+    "youtube.com",
+    "gmail.com",
+    "facebook.com",
+    "instagram.com",
+    "whatsapp.com",
+    "apple.com",
+    "icloud.com",
+    "microsoft.com",
+    "office.com",
+    "outlook.com",
+    "live.com",
+    "amazon.com",
+    "paypal.com",
+    "netflix.com",
+    "github.com",
+    "twitter.com",
+    "linkedin.com",
+    "wikipedia.org",
+    "yahoo.com",
+    "dropbox.com",
+    "adobe.com",
+    "binance.com",
+    "coinbase.com",
+    "ebay.com",
+    "cloudflare.com",
+    "steampowered.com",
+    "baidu.com",
+    "taobao.com",
+    "alipay.com",
+    "chase.com",
+    "wellsfargo.com",
+    "bankofamerica.com",
+];
+
+/// Characters commonly used in place of Latin letters
+const CONFUSABLES: &[(char, &str)] = &[
+    // Cyrillic
+    ('а', "a"),
+    ('в', "b"),
+    ('с', "c"),
+    ('ԁ', "d"),
+    ('е', "e"),
+    ('ё', "e"),
+    ('һ', "h"),
+    ('і', "i"),
+    ('ї', "i"),
+    ('ј', "j"),
+    ('к', "k"),
+    ('ӏ', "l"),
+    ('м', "m"),
+    ('н', "h"),
+    ('о', "o"),
+    ('р', "p"),
+    ('ԛ', "q"),
+    ('ѕ', "s"),
+    ('т', "t"),
+    ('у', "y"),
+    ('ԝ', "w"),
+    ('х', "x"),
+    ('ь', "b"),
+    // Greek
+    ('α', "a"),
+    ('β', "b"),
+    ('ϲ', "c"),
+    ('ε', "e"),
+    ('η', "n"),
+    ('ι', "i"),
+    ('κ', "k"),
+    ('ν', "v"),
+    ('ο', "o"),
+    ('ρ', "p"),
+    ('τ', "t"),
+    ('υ', "u"),
+    ('χ', "x"),
+    ('ω', "w"),
+    // Latin lookalikes
+    ('ı', "i"),
+    ('ɡ', "g"),
+    ('ɩ', "i"),
+    ('ʟ', "l"),
+    ('ℓ', "l"),
+    ('ß', "ss"),
+    ('æ', "ae"),
+    ('ø', "o"),
+    ('đ', "d"),
+    ('ł', "l"),
+    // Armenian
+    ('օ', "o"),
+    ('ս', "u"),
+    ('հ', "h"),
+    ('ո', "n"),
+];
+
+/// ASCII sequences that render like a single letter
+const ASCII_CONFUSABLES: &[(&str, &str)] = &[("rn", "m"), ("vv", "w"), ("cl", "d"), ("0", "o"), ("1", "l")];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Thai,
+    Hangul,
+    Hiragana,
+    Katakana,
+    Han,
+    Other,
+}
+
+impl Script {
+    fn of(c: char) -> Option<Self> {
+        let script = match c as u32 {
+            0x30..=0x39 | 0x2D => return None,
+            0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+            0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x400..=0x52F => Script::Cyrillic,
+            0x530..=0x58F => Script::Armenian,
+            0x590..=0x5FF => Script::Hebrew,
+            0x600..=0x6FF => Script::Arabic,
+            0xE00..=0xE7F => Script::Thai,
+            0x1100..=0x11FF | 0xAC00..=0xD7AF => Script::Hangul,
+            0x3040..=0x309F => Script::Hiragana,
+            0x30A0..=0x30FF => Script::Katakana,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF => Script::Han,
+            _ => Script::Other,
+        };
+        Some(script)
+    }
+}
+
+/// Whether a set of scripts is one a legitimate name would use together
+/// (UTS #39 "highly restrictive": Latin may mix with CJK scripts only)
+fn is_allowed_mix(scripts: &[Script]) -> bool {
+    const JAPANESE: &[Script] = &[Script::Latin, Script::Han, Script::Hiragana, Script::Katakana];
+    const KOREAN: &[Script] = &[Script::Latin, Script::Han, Script::Hangul];
+    scripts.len() <= 1 ||
+        scripts.iter().all(|s| JAPANESE.contains(s)) ||
+        scripts.iter().all(|s| KOREAN.contains(s))
+}
+
+fn scripts_of(label: &str) -> Vec<Script> {
+    let mut scripts: Vec<Script> = label.chars().filter_map(Script::of).collect();
+    scripts.sort();
+    scripts.dedup();
+    scripts
+}
+
+const BASE: u32 = 36;
+const TMIN: u32 = 1;
+const TMAX: u32 = 26;
+
+fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+    let mut delta = if first { delta / 700 } else { delta / 2 };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > ((BASE - TMIN) * TMAX) / 2 {
+        delta /= BASE - TMIN;
+        k += BASE;
+    }
+    k + ((BASE - TMIN + 1) * delta) / (delta + 38)
+}
+
+/// Decode a punycode string (RFC 3492), without the `xn--` prefix
+fn punycode_decode(input: &str) -> Option<String> {
+    let (basic, extended) = match input.rfind('-') {
+        Some(pos) => (&input[..pos], &input[pos + 1..]),
+        None => ("", input),
+    };
+    if !input.is_ascii() {
+        return None;
+    }
+    let mut output: Vec<char> = basic.chars().collect();
+    let (mut n, mut i, mut bias) = (128u32, 0u32, 72u32);
+    let mut digits = extended.bytes().peekable();
+
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut w = 1u32;
+        let mut k = BASE;
+        loop {
+            let digit = match digits.next()? {
+                b @ b'a'..=b'z' => b - b'a',
+                b @ b'A'..=b'Z' => b - b'A',
+                b @ b'0'..=b'9' => b - b'0' + 26,
+                _ => return None,
+            } as u32;
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = if k <= bias { TMIN } else if k >= bias + TMAX { TMAX } else { k - bias };
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let len = (output.len() as u32) + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n.checked_add(i / len)?;
+        i %= len;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+    Some(output.into_iter().collect())
+}
+
+/// Unicode form of an ASCII label
+fn label_to_unicode(label: &str) -> String {
+    label
+        .strip_prefix("xn--")
+        .and_then(punycode_decode)
+        .unwrap_or_else(|| label.to_string())
+}
+
+/// Unicode form (U-labels) of an ASCII domain name
+pub(crate) fn to_unicode(ascii: &str) -> String {
+    ascii.split('.').map(label_to_unicode).collect::<Vec<_>>().join(".")
+}
+
+/// Reduce a label to the Latin letters it looks like
+fn skeleton(label: &str) -> String {
+    let mut mapped = String::new();
+    for c in label.chars().flat_map(char::to_lowercase) {
+        match CONFUSABLES.iter().find(|(from, _)| *from == c) {
+            Some((_, to)) => mapped.push_str(to),
+            None => mapped.push(c),
+        }
+    }
+    ASCII_CONFUSABLES.iter().fold(mapped, |s, (from, to)| s.replace(from, to))
+}
+
+/// Popular domain whose registrable label looks like `label`, other than the domain itself
+fn lookalike_of(label: &str, domain: &str) -> Option<&'static str> {
+    let label_skeleton = skeleton(label);
+    POPULAR_DOMAINS.iter()
+        .copied()
+        .filter(|popular| *popular != domain)
+        .find(|popular| {
+            let popular_label = popular.split('.').next().unwrap_or(popular);
+            popular_label != label && skeleton(popular_label) == label_skeleton
+        })
+}
+
+fn format_analysis(ascii: &str) -> String {
+    let labels: Vec<&str> = ascii.split('.').collect();
+    let unicode: Vec<String> = labels.iter().map(|l| label_to_unicode(l)).collect();
+    let unicode_domain = unicode.join(".");
+    let parts = public_suffix_list().lookup(ascii, false);
+    let registrable_label = parts
+        .as_ref()
+        .and_then(|p| p.registrable.as_deref())
+        .and_then(|r| r.split('.').next())
+        .map(label_to_unicode);
+
+    let mut output = String::new();
+    output.push_str(&format!("% Homoglyph analysis for {}\n", ascii));
+    output.push_str("%\n");
+    output.push_str(&format!("domain-ascii:    {}\n", ascii));
+    output.push_str(&format!("domain-unicode:  {}\n", unicode_domain));
+
+    let mut mixed = false;
+    for label in unicode.iter().filter(|l| !l.is_ascii()) {
+        let scripts = scripts_of(label);
+        let allowed = is_allowed_mix(&scripts);
+        mixed |= !allowed;
+        let names: Vec<String> = scripts.iter().map(|s| format!("{:?}", s)).collect();
+        output.push_str(&format!(
+            "label:           {} ({}{})\n",
+            label,
+            names.join(", "),
+            if allowed { "" } else { ", MIXED SCRIPTS" }
+        ));
+        for c in label.chars().filter(|c| !c.is_ascii()) {
+            let script = Script::of(c).map_or("Common".to_string(), |s| format!("{:?}", s));
+            match CONFUSABLES.iter().find(|(from, _)| *from == c) {
+                Some((_, to)) =>
+                    output.push_str(
+                        &format!("character:       U+{:04X} {} {}, looks like '{}'\n", c as u32, c, script, to)
+                    ),
+                None => output.push_str(&format!("character:       U+{:04X} {} {}\n", c as u32, c, script)),
+            }
+        }
+    }
+
+    let mut lookalike = None;
+    if let Some(label) = registrable_label.as_deref() {
+        output.push_str(&format!("skeleton:        {}\n", skeleton(label)));
+        lookalike = lookalike_of(label, ascii);
+    }
+    if let Some(popular) = lookalike {
+        output.push_str(&format!("lookalike-of:    {}\n", popular));
+    }
+
+    let risk = if lookalike.is_some() {
+        "HIGH (imitates a frequently impersonated domain)"
+    } else if mixed {
+        "MEDIUM (label mixes scripts)"
+    } else if !unicode_domain.is_ascii() {
+        "LOW (internationalized name, single script per label)"
+    } else {
+        "NONE (plain ASCII, no lookalike found)"
+    };
+    output.push_str(&format!("risk:            {}\n", risk));
+    if lookalike.is_some() || mixed {
+        output.push_str("% WARNING: this name is likely intended to spoof another domain\n");
+    }
+    output
+}
+
+/// Analyze a domain for homograph spoofing
+pub async fn process_homoglyph_query(target: &str) -> Result<String> {
+    let ascii = match to_ascii(target) {
+        Some(ascii) if ascii.contains('.') && !ascii.split('.').any(str::is_empty) => ascii,
+        _ => {
+            return Ok(format!("Invalid domain name: {}\nUse a domain, e.g. xn--pple-43d.com-HOMOGLYPH\n", target));
+        }
+    };
+    Ok(format_analysis(&ascii))
+}
+
+/// `-HOMOGLYPH`: mixed-script and lookalike detection for a domain
+pub struct HomoglyphService;
+
+#[async_trait]
+impl QueryService for HomoglyphService {
+    fn name(&self) -> &'static str {
+        "homoglyph"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-HOMOGLYPH"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "SECURITY INVESTIGATION",
+            summary: "IDN homograph check: scripts, confusable skeleton, lookalike domains",
+            example: "xn--pple-43d.com-HOMOGLYPH",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "built-in confusable table"
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_homoglyph_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_punycode_decode() {
+        assert_eq!(punycode_decode("pple-43d").as_deref(), Some("аpple"));
+        assert_eq!(punycode_decode("bcher-kva").as_deref(), Some("bücher"));
+        assert_eq!(punycode_decode("55qx5d").as_deref(), Some("公司"));
+        assert_eq!(punycode_decode("l-7sba6dbr").as_deref(), Some("раураl"));
+        assert_eq!(punycode_decode("a!b"), None);
+    }
+
+    #[test]
+    fn test_skeleton_and_scripts() {
+        assert_eq!(skeleton("раураl"), "paypal");
+        assert_eq!(skeleton("rnicrosoft"), "microsoft");
+        assert!(!is_allowed_mix(&scripts_of("аpple")));
+        assert!(is_allowed_mix(&scripts_of("bücher")));
+        assert!(is_allowed_mix(&scripts_of("東京タワー")));
+    }
+
+    #[tokio::test]
+    async fn test_homoglyph_output() {
+        let response = process_homoglyph_query("xn--pple-43d.com").await.unwrap();
+        assert!(response.contains("domain-unicode:  аpple.com\n"));
+        assert!(response.contains("MIXED SCRIPTS"));
+        assert!(response.contains("lookalike-of:    apple.com\n"));
+        assert!(response.contains("risk:            HIGH"));
+
+        let response = process_homoglyph_query("apple.com").await.unwrap();
+        assert!(response.contains("risk:            NONE"));
+        assert!(process_homoglyph_query("paypa1.com").await.unwrap().contains("lookalike-of:    paypal.com\n"));
+    }
+}
@@ -1,9 +1,14 @@
+use crate::config::IANA_LMDB_PATH;
+use crate::core::provenance::record_source;
 use crate::storage::lmdb::LmdbStorage;
 use crate::{log_debug, log_error, log_warn};
 use anyhow::Result;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const IANA_WHOIS_SERVER: &str = "whois.iana.org";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IanaReferral {
     pub whois_server: String,
@@ -147,15 +152,38 @@ pub struct IanaCache {
 
 impl IanaCache {
     pub fn new() -> Result<Self> {
-        let storage = LmdbStorage::new("./cache/iana_cache")?;
+        let storage = LmdbStorage::new(IANA_LMDB_PATH)?;
         Ok(Self { storage })
     }
 
+    /// Cached WHOIS server for a query, without contacting IANA
+    pub fn cached_whois_server(&self, query: &str) -> Option<String> {
+        if let Some(asn) = self.extract_asn(query)
+            && let Some(server) = self.find_server_for_asn(asn)
+        {
+            return Some(server);
+        }
+
+        if let Some(ip) = self.extract_ip(query)
+            && let Some(server) = self.find_server_for_ip(&ip)
+        {
+            return Some(server);
+        }
+
+        match self.storage.get_json::<IanaReferral>(&self.get_cache_key(query)) {
+            Ok(Some(referral)) if !referral.is_expired() => Some(referral.whois_server),
+            _ => None,
+        }
+    }
+
     pub async fn get_whois_server(&self, query: &str) -> Option<String> {
+        let start = Instant::now();
+
         // For ASN queries, check if any existing block contains this ASN
         if let Some(asn) = self.extract_asn(query)
             && let Some(server) = self.find_server_for_asn(asn)
         {
+            record_source("referral", IANA_WHOIS_SERVER, start.elapsed(), "cache hit");
             return Some(server);
         }
 
@@ -163,6 +191,7 @@ impl IanaCache {
         if let Some(ip) = self.extract_ip(query)
             && let Some(server) = self.find_server_for_ip(&ip)
         {
+            record_source("referral", IANA_WHOIS_SERVER, start.elapsed(), "cache hit");
             return Some(server);
         }
 
@@ -173,6 +202,7 @@ impl IanaCache {
             Ok(Some(referral)) => {
                 if !referral.is_expired() {
                     log_debug!("IANA cache hit for {}: {}", query, referral.whois_server);
+                    record_source("referral", IANA_WHOIS_SERVER, start.elapsed(), "cache hit");
                     return Some(referral.whois_server);
                 } else {
                     log_debug!("IANA cache entry expired for {}", query);
@@ -188,7 +218,10 @@ impl IanaCache {
         }
 
         // Cache miss or expired, query IANA
-        match self.query_iana(query).await {
+        let result = self.query_iana(query).await;
+        let status = if result.is_ok() { "cache miss" } else { "failed" };
+        record_source("referral", IANA_WHOIS_SERVER, start.elapsed(), status);
+        match result {
             Ok(Some(referral)) => {
                 let cache_key = if let (Some(start), Some(end)) = (referral.as_block_start, referral.as_block_end) {
                     // Use block range as cache key for ASN blocks
@@ -382,7 +415,7 @@ impl IanaCache {
 
         let mut stream = timeout(
             Duration::from_secs(10),
-            TcpStream::connect("whois.iana.org:43"),
+            TcpStream::connect(format!("{}:43", IANA_WHOIS_SERVER)),
         )
         .await??;
 
@@ -508,8 +541,9 @@ impl IanaCache {
         for pattern in &patterns {
             if let Ok(regex) = Regex::new(pattern)
                 && let Some(caps) = regex.captures(response)
+                && let Some(value) = caps.get(1)
             {
-                return caps.get(1).unwrap().as_str().trim().to_string();
+                return value.as_str().trim().to_string();
             }
         }
 
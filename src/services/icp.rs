@@ -6,15 +6,23 @@
 //! Queries multiple external providers for ICP registration information
 
 use crate::config::{ICP_CACHE_TTL, ICP_LMDB_PATH};
+use crate::core::body_limit::LimitedBody;
+use crate::core::regex_cache::CachedRegex;
 use crate::storage::lmdb::LmdbStorage;
 use crate::{log_debug, log_error};
 use anyhow::Result;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 const MAX_RETRIES: u32 = 3;
 
+static DOMAIN_RE: CachedRegex = CachedRegex::new(r"^[\w\-]+(\.[\w\-]+)+[\w\-]*$");
+
+/// Patterns checked by `regex_cache::precompile_builtin_regexes` at startup
+pub(crate) fn builtin_regexes() -> Vec<&'static CachedRegex> {
+    vec![&DOMAIN_RE]
+}
+
 /// ICP cache entry with TTL
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ICPCacheEntry {
@@ -160,8 +168,7 @@ fn clean_domain(domain: &str) -> Result<String> {
     let domain = domain.split(':').next().unwrap_or(&domain);
 
     // Validate domain format
-    let domain_regex = Regex::new(r"^[\w\-]+(\.[\w\-]+)+[\w\-]*$").unwrap();
-    if !domain_regex.is_match(domain) {
+    if !DOMAIN_RE.get()?.is_match(domain) {
         return Err(anyhow::anyhow!("Invalid domain format: {}", domain));
     }
 
@@ -202,7 +209,7 @@ async fn query_baidu_icp(domain: &str) -> Result<String> {
         match response {
             Ok(resp) => {
                 if resp.status().is_success() {
-                    let text = resp.text().await?;
+                    let text = resp.limited_text().await?;
                     let baidu_resp: BaiduICPResponse = serde_json::from_str(&text)
                         .map_err(|e| anyhow::anyhow!("Failed to parse Baidu response: {}", e))?;
 
@@ -281,7 +288,7 @@ async fn query_dnspod_icp(domain: &str) -> Result<String> {
         return Err(anyhow::anyhow!("DNSPod HTTP error: status={}", response.status()));
     }
 
-    let text = response.text().await?;
+    let text = response.limited_text().await?;
     let dnspod_resp: DNSPodResponse = serde_json::from_str(&text)
         .map_err(|e| anyhow::anyhow!("Failed to parse DNSPod response: {}", e))?;
 
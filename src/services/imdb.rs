@@ -19,9 +19,11 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
+use crate::services::tmdb::{TmdbClient, WatchProviders};
 use crate::{log_debug, log_error, log_warn};
 /// IMDb API response structures for movie/TV show information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ImdbResponse {
     #[serde(rename = "Response")]
     pub response: String,
@@ -77,6 +79,9 @@ pub struct ImdbResponse {
     pub error: Option<String>,
     #[serde(rename = "totalSeasons")]
     pub total_seasons: Option<String>,
+    /// Streaming availability from TMDB, not part of the OMDb response
+    #[serde(skip)]
+    pub watch_providers: Option<WatchProviders>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -122,9 +127,14 @@ pub struct ImdbSearchResult {
 /// OMDB_API_KEY=your_omdb_api_key_here
 /// ```
 /// You can get a free API key from: http://www.omdbapi.com/apikey.aspx
+///
+/// With `TMDB_API_KEY` set as well, TMDB answers when OMDb is not
+/// configured, unavailable or rate-limited, and adds watch providers for
+/// `TMDB_WATCH_REGION` (default `US`) to OMDb results.
 pub struct ImdbService {
     client: reqwest::Client,
     api_key: Option<String>,
+    tmdb: Option<TmdbClient>,
 }
 
 impl Default for ImdbService {
@@ -147,20 +157,81 @@ impl ImdbService {
 
         // Try to get API key from environment variable (including from .env file)
         let api_key = std::env::var("OMDB_API_KEY").ok();
-        if api_key.is_none() {
+        let tmdb = TmdbClient::from_env(client.clone());
+        if api_key.is_none() && tmdb.is_none() {
             log_warn!(
-                "OMDB_API_KEY not found in environment variables or .env file - IMDb queries will be limited"
+                "Neither OMDB_API_KEY nor TMDB_API_KEY found in environment variables or .env file - IMDb queries will be limited"
             );
+        } else if api_key.is_none() {
+            log_debug!("OMDB_API_KEY not configured, IMDb queries will use TMDB");
+        }
+
+        Self {
+            client,
+            api_key,
+            tmdb,
         }
+    }
+
+    /// Whether an OMDb error means the API cannot be used right now
+    /// (rate limit or key problem) rather than that the title was not found
+    fn is_omdb_unavailable(error: &str) -> bool {
+        let error = error.to_lowercase();
+        error.contains("limit reached") || error.contains("api key")
+    }
 
-        Self { client, api_key }
+    /// Add TMDB watch providers to an OMDb result, when TMDB is configured
+    async fn add_watch_providers(&self, imdb_data: &mut ImdbResponse) {
+        let (Some(tmdb), Some(imdb_id)) = (&self.tmdb, &imdb_data.imdb_id) else {
+            return;
+        };
+        match tmdb.watch_providers(imdb_id).await {
+            Ok(providers) => imdb_data.watch_providers = providers,
+            Err(e) => log_debug!("TMDB watch providers unavailable for {}: {}", imdb_id, e),
+        }
     }
 
     /// Query IMDb information by title or IMDb ID
-    /// If the query is not an IMDb ID and title search fails, attempts a search
+    /// If the query is not an IMDb ID and title search fails, attempts a search.
+    /// Falls back to TMDB when OMDb is not configured or unavailable.
     pub async fn query_imdb_info(&self, query: &str) -> Result<String> {
         log_debug!("Querying IMDb info for: {}", query);
 
+        if self.api_key.is_some() {
+            match self.query_omdb_info(query).await {
+                Ok(result) => return Ok(result),
+                Err(e) if self.tmdb.is_some() => {
+                    log_warn!("OMDb unavailable for '{}', falling back to TMDB: {}", query, e);
+                }
+                Err(e) => return Ok(format!("IMDb Query Failed for: {}\n{}\n", query, e)),
+            }
+        }
+
+        if let Some(tmdb) = &self.tmdb {
+            return Ok(match tmdb.lookup(query).await {
+                Ok(Some(imdb_data)) => format!(
+                    "{}% Data provided by TMDB (themoviedb.org)\n",
+                    self.format_imdb_info(&imdb_data)
+                ),
+                Ok(None) => format!("IMDb Information Not Found for: {}\nMovie not found!\n", query),
+                Err(e) => format!("IMDb Query Failed for: {}\n{}\n", query, e),
+            });
+        }
+
+        Ok(format!(
+            "IMDb Query Failed for: {}\nOMDB API key not configured.\n\
+             To enable IMDb queries, set the OMDB_API_KEY (or TMDB_API_KEY) environment variable\n\
+             or add it to a .env file in the project root.\n\
+             You can get a free API key from: http://www.omdbapi.com/apikey.aspx\n",
+            query
+        ))
+    }
+
+    /// Query OMDb by title or IMDb ID
+    ///
+    /// Errors mean OMDb is unavailable (HTTP failure, rate limit, bad key);
+    /// a title that does not exist is an `Ok` not-found message.
+    async fn query_omdb_info(&self, query: &str) -> Result<String> {
         if let Some(api_key) = &self.api_key {
             // First, try direct lookup (by IMDb ID or exact title)
             let search_param = if query.starts_with("tt") && query.len() >= 9 {
@@ -177,17 +248,18 @@ impl ImdbService {
             let response = self.client.get(&url).send().await?;
 
             if !response.status().is_success() {
-                return Ok(format!(
-                    "IMDb Query Failed for: {}\nHTTP Status: {}\n",
-                    query,
-                    response.status()
-                ));
+                return Err(anyhow::anyhow!("HTTP Status: {}", response.status()));
             }
 
-            let imdb_data: ImdbResponse = response.json().await?;
+            let mut imdb_data: ImdbResponse = response.limited_json().await?;
 
             if imdb_data.response == "True" {
+                self.add_watch_providers(&mut imdb_data).await;
                 Ok(self.format_imdb_info(&imdb_data))
+            } else if let Some(error) = &imdb_data.error
+                && Self::is_omdb_unavailable(error)
+            {
+                Err(anyhow::anyhow!("{}", error))
             } else {
                 // If direct lookup failed and it's not an IMDb ID, try search
                 if !query.starts_with("tt") {
@@ -225,13 +297,7 @@ impl ImdbService {
                 }
             }
         } else {
-            Ok(format!(
-                "IMDb Query Failed for: {}\nOMDB API key not configured.\n\
-                 To enable IMDb queries, set the OMDB_API_KEY environment variable\n\
-                 or add it to a .env file in the project root.\n\
-                 You can get a free API key from: http://www.omdbapi.com/apikey.aspx\n",
-                query
-            ))
+            Err(anyhow::anyhow!("No API key configured"))
         }
     }
 
@@ -255,7 +321,7 @@ impl ImdbService {
                 ));
             }
 
-            let search_data: ImdbSearchResponse = response.json().await?;
+            let search_data: ImdbSearchResponse = response.limited_json().await?;
 
             if search_data.response == "True"
                 && let Some(results) = search_data.search
@@ -291,9 +357,10 @@ impl ImdbService {
                 return Err(anyhow::anyhow!("Request failed: {}", response.status()));
             }
 
-            let imdb_data: ImdbResponse = response.json().await?;
+            let mut imdb_data: ImdbResponse = response.limited_json().await?;
 
             if imdb_data.response == "True" {
+                self.add_watch_providers(&mut imdb_data).await;
                 Ok(self.format_imdb_info(&imdb_data))
             } else {
                 Err(anyhow::anyhow!(
@@ -309,9 +376,45 @@ impl ImdbService {
     }
 
     /// Search IMDb for movies/TV shows by title
+    /// Falls back to TMDB when OMDb is not configured or unavailable.
     pub async fn search_imdb(&self, query: &str, limit: usize) -> Result<String> {
         log_debug!("Searching IMDb for: {}", query);
 
+        if self.api_key.is_some() {
+            match self.search_omdb(query, limit).await {
+                Ok(result) => return Ok(result),
+                Err(e) if self.tmdb.is_some() => {
+                    log_warn!("OMDb unavailable for search '{}', falling back to TMDB: {}", query, e);
+                }
+                Err(e) => return Ok(format!("IMDb Search Failed for: {}\n{}\n", query, e)),
+            }
+        }
+
+        if let Some(tmdb) = &self.tmdb {
+            return Ok(match tmdb.search_titles(query, limit).await {
+                Ok(results) if !results.is_empty() => {
+                    let results: Vec<&ImdbSearchResult> = results.iter().collect();
+                    format!(
+                        "{}% Data provided by TMDB (themoviedb.org)\n",
+                        self.format_search_results(query, &results)
+                    )
+                }
+                Ok(_) => format!("No IMDb search results found for: {}\n", query),
+                Err(e) => format!("IMDb Search Failed for: {}\n{}\n", query, e),
+            });
+        }
+
+        Ok(format!(
+            "IMDb Search Failed for: {}\nOMDB API key not configured.\n\
+             To enable IMDb searches, set the OMDB_API_KEY (or TMDB_API_KEY) environment variable\n\
+             or add it to a .env file in the project root.\n\
+             You can get a free API key from: http://www.omdbapi.com/apikey.aspx\n",
+            query
+        ))
+    }
+
+    /// Search OMDb, erroring when OMDb is unavailable
+    async fn search_omdb(&self, query: &str, limit: usize) -> Result<String> {
         if let Some(api_key) = &self.api_key {
             let url = format!(
                 "http://www.omdbapi.com/?s={}&apikey={}",
@@ -322,14 +425,10 @@ impl ImdbService {
             let response = self.client.get(&url).send().await?;
 
             if !response.status().is_success() {
-                return Ok(format!(
-                    "IMDb Search Failed for: {}\nHTTP Status: {}\n",
-                    query,
-                    response.status()
-                ));
+                return Err(anyhow::anyhow!("HTTP Status: {}", response.status()));
             }
 
-            let search_data: ImdbSearchResponse = response.json().await?;
+            let search_data: ImdbSearchResponse = response.limited_json().await?;
 
             if search_data.response == "True" {
                 if let Some(results) = search_data.search {
@@ -339,6 +438,10 @@ impl ImdbService {
                 } else {
                     Ok(format!("No IMDb search results found for: {}\n", query))
                 }
+            } else if let Some(error) = &search_data.error
+                && Self::is_omdb_unavailable(error)
+            {
+                Err(anyhow::anyhow!("{}", error))
             } else {
                 Ok(format!(
                     "IMDb Search Failed for: {}\n{}\n",
@@ -349,13 +452,7 @@ impl ImdbService {
                 ))
             }
         } else {
-            Ok(format!(
-                "IMDb Search Failed for: {}\nOMDB API key not configured.\n\
-                 To enable IMDb searches, set the OMDB_API_KEY environment variable\n\
-                 or add it to a .env file in the project root.\n\
-                 You can get a free API key from: http://www.omdbapi.com/apikey.aspx\n",
-                query
-            ))
+            Err(anyhow::anyhow!("No API key configured"))
         }
     }
 
@@ -480,6 +577,28 @@ impl ImdbService {
             ));
         }
 
+        if let Some(poster) = &imdb.poster
+            && poster != "N/A"
+        {
+            output.push_str(&format!("poster: {}\n", poster));
+        }
+
+        if let Some(providers) = &imdb.watch_providers {
+            output.push_str(&format!("watch-region: {}\n", providers.region));
+            if !providers.stream.is_empty() {
+                output.push_str(&format!("watch-stream: {}\n", providers.stream.join(", ")));
+            }
+            if !providers.rent.is_empty() {
+                output.push_str(&format!("watch-rent: {}\n", providers.rent.join(", ")));
+            }
+            if !providers.buy.is_empty() {
+                output.push_str(&format!("watch-buy: {}\n", providers.buy.join(", ")));
+            }
+            if let Some(link) = &providers.link {
+                output.push_str(&format!("watch-url: {}\n", link));
+            }
+        }
+
         if let Some(imdb_id) = &imdb.imdb_id {
             output.push_str(&format!(
                 "imdb-url: https://www.imdb.com/title/{}/\n",
@@ -658,4 +777,38 @@ mod tests {
         // The client is properly configured with timeout during creation
         assert!(service.api_key.is_none() || service.api_key.is_some());
     }
+
+    #[test]
+    fn test_omdb_unavailable_errors() {
+        assert!(ImdbService::is_omdb_unavailable("Request limit reached!"));
+        assert!(ImdbService::is_omdb_unavailable("Invalid API key!"));
+        assert!(!ImdbService::is_omdb_unavailable("Movie not found!"));
+        assert!(!ImdbService::is_omdb_unavailable("Too many results."));
+    }
+
+    #[test]
+    fn test_format_poster_and_watch_providers() {
+        let service = ImdbService::new();
+        let imdb = ImdbResponse {
+            response: "True".to_string(),
+            title: Some("Inception".to_string()),
+            runtime: Some("148 min".to_string()),
+            poster: Some("https://image.tmdb.org/t/p/w500/inception.jpg".to_string()),
+            imdb_id: Some("tt1375666".to_string()),
+            watch_providers: Some(WatchProviders {
+                region: "US".to_string(),
+                stream: vec!["Netflix".to_string()],
+                rent: vec!["Apple TV".to_string(), "Amazon Video".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let output = service.format_imdb_info(&imdb);
+        assert!(output.contains("runtime: 148 min\n"));
+        assert!(output.contains("poster: https://image.tmdb.org/t/p/w500/inception.jpg\n"));
+        assert!(output.contains("watch-region: US\nwatch-stream: Netflix\nwatch-rent: Apple TV, Amazon Video\n"));
+        assert!(!output.contains("watch-buy:"));
+        assert!(output.ends_with("imdb-url: https://www.imdb.com/title/tt1375666/\n"));
+    }
 }
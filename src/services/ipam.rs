@@ -0,0 +1,441 @@
+// WHOIS Server - IPAM Integration
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Internal resource data from NetBox (`10.1.2.0/24-IPAM`, `AS64500-IPAM`)
+//!
+//! NOC teams look up the same prefix in the RIR database and in their IPAM.
+//! With a NetBox instance (or another IPAM serving the NetBox REST API)
+//! configured, `-IPAM` shows its prefixes, IP addresses and ASNs with their
+//! description, status, tenant, site, VLAN, VRF and role, and plain IP and
+//! ASN queries get the same data as `% ipam:` lines after the public answer.
+//!
+//! Internal data is only shown to clients inside `IPAM_CLIENTS`; without it
+//! nobody sees any. Like annotations it is added after the response cache
+//! and `-IPAM` responses are never cached, so it cannot reach other clients.
+//!
+//! Environment variables:
+//! - `NETBOX_URL`: base URL of the NetBox instance (the integration is off when unset)
+//! - `NETBOX_TOKEN`: API token with read access
+//! - `IPAM_CLIENTS`: comma-separated client networks allowed to see IPAM data
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use cidr::IpCidr;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::core::QueryType;
+use crate::core::body_limit::LimitedBody;
+use crate::core::query::parse_asn;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::{log_debug, log_warn};
+
+/// Time an enrichment lookup may add to a plain IP or ASN query
+const ENRICH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Objects requested per NetBox list call
+const PAGE_SIZE: usize = 50;
+
+static IPAM: OnceLock<Option<Ipam>> = OnceLock::new();
+
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    results: Vec<T>,
+}
+
+/// A related object as NetBox nests it
+#[derive(Debug, Deserialize)]
+struct Related {
+    display: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Status {
+    label: String,
+}
+
+/// A NetBox prefix, IP address or ASN
+#[derive(Debug, Deserialize)]
+struct IpamObject {
+    #[serde(default)]
+    prefix: Option<String>,
+    #[serde(default)]
+    address: Option<String>,
+    #[serde(default)]
+    asn: Option<u32>,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    status: Option<Status>,
+    #[serde(default)]
+    tenant: Option<Related>,
+    /// The site before NetBox 4.2
+    #[serde(default)]
+    site: Option<Related>,
+    /// Site, location or region since NetBox 4.2
+    #[serde(default)]
+    scope: Option<Related>,
+    #[serde(default)]
+    vlan: Option<Related>,
+    #[serde(default)]
+    vrf: Option<Related>,
+    #[serde(default)]
+    role: Option<Related>,
+    #[serde(default)]
+    rir: Option<Related>,
+}
+
+impl IpamObject {
+    /// (attribute, value) pairs in display order, empty ones left out
+    fn attributes(&self) -> Vec<(&'static str, String)> {
+        let mut attributes = Vec::new();
+        if let Some(prefix) = &self.prefix {
+            attributes.push(("prefix", prefix.clone()));
+        }
+        if let Some(address) = &self.address {
+            attributes.push(("address", address.clone()));
+        }
+        if let Some(asn) = self.asn {
+            attributes.push(("asn", format!("AS{}", asn)));
+        }
+        if !self.description.trim().is_empty() {
+            attributes.push(("description", self.description.trim().to_string()));
+        }
+        let related = [
+            ("status", self.status.as_ref().map(|status| status.label.clone())),
+            ("tenant", self.tenant.as_ref().map(|r| r.display.clone())),
+            ("site", self.site.as_ref().or(self.scope.as_ref()).map(|r| r.display.clone())),
+            ("vlan", self.vlan.as_ref().map(|r| r.display.clone())),
+            ("vrf", self.vrf.as_ref().map(|r| r.display.clone())),
+            ("role", self.role.as_ref().map(|r| r.display.clone())),
+            ("rir", self.rir.as_ref().map(|r| r.display.clone())),
+        ];
+        attributes.extend(related.into_iter().filter_map(|(name, value)| value.map(|value| (name, value))));
+        attributes
+    }
+
+    /// Prefix length for sorting, most specific first
+    fn length(&self) -> u8 {
+        self.prefix
+            .as_deref()
+            .and_then(|prefix| prefix.parse::<IpCidr>().ok())
+            .map_or(0, |prefix| prefix.network_length())
+    }
+}
+
+/// What an `-IPAM` query or an enriched query asks about
+#[derive(Debug, Clone, PartialEq)]
+enum IpamTarget {
+    Address(IpAddr),
+    Prefix(IpCidr),
+    Asn(u32),
+}
+
+impl IpamTarget {
+    fn parse(target: &str) -> Option<Self> {
+        if let Ok(ip) = target.parse::<IpAddr>() {
+            return Some(Self::Address(ip));
+        }
+        if let Ok(prefix) = target.parse::<IpCidr>() {
+            return Some(Self::Prefix(prefix));
+        }
+        parse_asn(target).map(Self::Asn)
+    }
+
+    fn from_query_type(query_type: &QueryType) -> Option<Self> {
+        match query_type {
+            QueryType::IPv4(ip) => Some(Self::Address(IpAddr::V4(*ip))),
+            QueryType::IPv6(ip) => Some(Self::Address(IpAddr::V6(*ip))),
+            QueryType::ASN(asn) => parse_asn(asn).map(Self::Asn),
+            _ => None,
+        }
+    }
+}
+
+/// NetBox connection settings loaded from environment variables
+struct Ipam {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    /// Client networks allowed to see IPAM data
+    clients: Vec<IpCidr>,
+}
+
+impl Ipam {
+    fn from_env() -> Option<Self> {
+        let _ = dotenv::dotenv();
+        let url = std::env::var("NETBOX_URL").ok().filter(|u| !u.trim().is_empty())?;
+        let token = std::env::var("NETBOX_TOKEN").ok().filter(|t| !t.trim().is_empty());
+        let clients = std::env::var("IPAM_CLIENTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|client| !client.is_empty())
+            .filter_map(|client| match client.parse::<IpCidr>() {
+                Ok(network) => Some(network),
+                Err(_) => {
+                    log_warn!("Ignoring invalid IPAM_CLIENTS network: {}", client);
+                    None
+                }
+            })
+            .collect();
+        Some(Self::new(&url, token, clients))
+    }
+
+    fn new(url: &str, token: Option<String>, clients: Vec<IpCidr>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .user_agent("whois-server/1.0")
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        Self {
+            client,
+            url: url.trim().trim_end_matches('/').to_string(),
+            token: token.map(|t| t.trim().to_string()),
+            clients,
+        }
+    }
+
+    fn allows(&self, client: Option<IpAddr>) -> bool {
+        client.is_some_and(|ip| self.clients.iter().any(|network| network.contains(&ip)))
+    }
+
+    fn host(&self) -> &str {
+        let host = self.url.split_once("://").map_or(self.url.as_str(), |(_, rest)| rest);
+        host.split('/').next().unwrap_or(host)
+    }
+
+    async fn list<T: DeserializeOwned>(&self, endpoint: &str, filter: (&str, String)) -> Result<Vec<T>> {
+        let mut request = self
+            .client
+            .get(format!("{}/api/{}/", self.url, endpoint))
+            .query(&[filter, ("limit", PAGE_SIZE.to_string())])
+            .header("Accept", "application/json");
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Token {}", token));
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("NetBox {} returned HTTP {}", endpoint, response.status()));
+        }
+        let page: Page<T> = response.limited_json().await?;
+        Ok(page.results)
+    }
+
+    /// Matching objects: covering prefixes (most specific first) and the IP
+    /// address for an address, covering prefixes for a prefix, the ASN for an ASN
+    async fn lookup(&self, target: &IpamTarget) -> Result<Vec<IpamObject>> {
+        let mut objects = match target {
+            IpamTarget::Address(ip) => self.list::<IpamObject>("ipam/prefixes", ("contains", ip.to_string())).await?,
+            IpamTarget::Prefix(prefix) => self.list::<IpamObject>("ipam/prefixes", ("contains", prefix.to_string())).await?,
+            IpamTarget::Asn(asn) => return self.list("ipam/asns", ("asn", asn.to_string())).await,
+        };
+        objects.sort_by_key(|object| std::cmp::Reverse(object.length()));
+        if let IpamTarget::Address(ip) = target {
+            objects.extend(self.list::<IpamObject>("ipam/ip-addresses", ("address", ip.to_string())).await?);
+        }
+        Ok(objects)
+    }
+}
+
+fn ipam() -> Option<&'static Ipam> {
+    IPAM.get_or_init(Ipam::from_env).as_ref()
+}
+
+fn format_objects(host: &str, target: &str, objects: &[IpamObject]) -> String {
+    let mut output = format!("% IPAM data for {} from {}\n", target, host);
+    if objects.is_empty() {
+        output.push_str("% No matching prefixes, addresses or ASNs\n");
+    }
+    for object in objects {
+        output.push('\n');
+        for (name, value) in object.attributes() {
+            output.push_str(&format!("{:<16}{}\n", format!("{}:", name), value));
+        }
+    }
+    output
+}
+
+/// One `% ipam:` line per object, for appending to public responses
+fn format_enrichment(objects: &[IpamObject]) -> String {
+    let mut output = String::new();
+    for object in objects {
+        let mut attributes = object.attributes().into_iter();
+        let Some((_, resource)) = attributes.next() else {
+            continue;
+        };
+        let details: Vec<String> = attributes.map(|(name, value)| format!("{}={}", name, value)).collect();
+        if details.is_empty() {
+            output.push_str(&format!("% ipam: {}\n", resource));
+        } else {
+            output.push_str(&format!("% ipam: {} {}\n", resource, details.join(", ")));
+        }
+    }
+    output
+}
+
+/// Append internal IPAM data to IP and ASN responses for allowed clients
+pub async fn append_ipam(response: String, query_type: &QueryType, ctx: &RequestContext) -> String {
+    let Some(target) = IpamTarget::from_query_type(query_type) else {
+        return response;
+    };
+    let Some(ipam) = ipam().filter(|ipam| ipam.allows(ctx.client_addr)) else {
+        return response;
+    };
+    match tokio::time::timeout(ENRICH_TIMEOUT, ipam.lookup(&target)).await {
+        Ok(Ok(objects)) if !objects.is_empty() => {
+            format!("{}\n{}", response.trim_end_matches('\n'), format_enrichment(&objects))
+        }
+        Ok(Ok(_)) => response,
+        Ok(Err(e)) => {
+            log_debug!("[{}] IPAM enrichment failed: {}", ctx, e);
+            response
+        }
+        Err(_) => {
+            log_debug!("[{}] IPAM enrichment timed out", ctx);
+            response
+        }
+    }
+}
+
+pub struct IpamService;
+
+#[async_trait]
+impl QueryService for IpamService {
+    fn name(&self) -> &'static str {
+        "ipam"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-IPAM"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "Internal prefix, address and ASN data from NetBox (allowlisted clients only)",
+            example: "10.1.2.0/24-IPAM",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "netbox"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        // Only allowlisted clients may see the response
+        None
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        let target = target.trim();
+        let parsed = IpamTarget::parse(target)
+            .ok_or_else(|| anyhow!("Usage: <IP address, prefix or ASN>-IPAM, got: {}", target))?;
+        let ipam = ipam().ok_or_else(|| anyhow!("The IPAM integration is not configured (NETBOX_URL is not set)"))?;
+        if !ipam.allows(ctx.client_addr) {
+            return Err(anyhow!("IPAM data is not available to this client"));
+        }
+        let objects = ipam.lookup(&parsed).await?;
+        Ok(format_objects(ipam.host(), target, &objects))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_netbox_lookup() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/ipam/prefixes/"))
+            .and(query_param("contains", "10.1.2.3"))
+            .and(header("Authorization", "Token secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "count": 2,
+                "results": [
+                    { "prefix": "10.0.0.0/8", "description": "", "status": { "value": "container", "label": "Container" } },
+                    {
+                        "prefix": "10.1.2.0/24",
+                        "description": "Office LAN",
+                        "status": { "value": "active", "label": "Active" },
+                        "tenant": { "id": 1, "display": "Foo Ltd" },
+                        "scope": { "id": 4, "display": "AMS1" },
+                        "vlan": { "id": 7, "display": "Users (100)" },
+                        "vrf": null
+                    }
+                ]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/ipam/ip-addresses/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "count": 1,
+                "results": [{ "address": "10.1.2.3/24", "description": "printer", "tenant": null }]
+            })))
+            .mount(&server)
+            .await;
+
+        let ipam = Ipam::new(&format!("{}/", server.uri()), Some("secret".to_string()), vec!["10.0.0.0/8".parse().unwrap()]);
+        assert!(ipam.allows(Some("10.9.9.9".parse().unwrap())));
+        assert!(!ipam.allows(Some("192.0.2.1".parse().unwrap())));
+        assert!(!ipam.allows(None));
+
+        let objects = ipam.lookup(&IpamTarget::parse("10.1.2.3").unwrap()).await.unwrap();
+        let output = format_objects("netbox.example.net", "10.1.2.3", &objects);
+        assert!(output.starts_with(
+            "% IPAM data for 10.1.2.3 from netbox.example.net\n\nprefix:         10.1.2.0/24\n\
+             description:    Office LAN\nstatus:         Active\ntenant:         Foo Ltd\n\
+             site:           AMS1\nvlan:           Users (100)\n\nprefix:         10.0.0.0/8\n"
+        ));
+        assert!(output.ends_with("address:        10.1.2.3/24\ndescription:    printer\n"));
+
+        assert_eq!(
+            format_enrichment(&objects),
+            "% ipam: 10.1.2.0/24 description=Office LAN, status=Active, tenant=Foo Ltd, site=AMS1, vlan=Users (100)\n\
+             % ipam: 10.0.0.0/8 status=Container\n\
+             % ipam: 10.1.2.3/24 description=printer\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_service_response_is_not_shared_across_clients() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "count": 1,
+                "results": [{ "prefix": "10.1.2.0/24", "description": "Office LAN" }]
+            })))
+            .mount(&server)
+            .await;
+        let _ = IPAM.set(Some(Ipam::new(&format!("{}/", server.uri()), None, vec!["10.0.0.0/8".parse().unwrap()])));
+
+        let query = "10.1.2.0/24-IPAM";
+        let query_type = crate::core::analyze_query(query);
+        assert_eq!(crate::core::query::cache_ttl(&query_type), None);
+
+        let allowed = RequestContext::default().with_client_addr("10.9.9.9".parse().unwrap());
+        let response = crate::core::process_query(query, &query_type, &allowed).await.unwrap();
+        assert!(response.contains("Office LAN"));
+
+        let other = RequestContext::default().with_client_addr("192.0.2.1".parse().unwrap());
+        let refused = crate::core::process_query(query, &query_type, &other).await.unwrap_err();
+        assert_eq!(refused.to_string(), "IPAM data is not available to this client");
+    }
+
+    #[test]
+    fn test_ipam_targets() {
+        assert_eq!(IpamTarget::parse("AS64500"), Some(IpamTarget::Asn(64500)));
+        assert_eq!(IpamTarget::parse("10.1.2.0/24"), Some(IpamTarget::Prefix("10.1.2.0/24".parse().unwrap())));
+        assert_eq!(IpamTarget::parse("2001:db8::1"), Some(IpamTarget::Address("2001:db8::1".parse().unwrap())));
+        assert_eq!(IpamTarget::parse("example.com"), None);
+    }
+}
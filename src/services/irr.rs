@@ -1,6 +1,7 @@
 use anyhow::Result;
 use serde::Deserialize;
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug};
 /// IRR Explorer API response structures
 #[derive(Debug, Deserialize)]
@@ -118,7 +119,7 @@ async fn query_irr_explorer_api(
         ));
     }
 
-    let json_response: Vec<IrrResponse> = response.json().await?;
+    let json_response: Vec<IrrResponse> = response.limited_json().await?;
     Ok(json_response)
 }
 
@@ -0,0 +1,350 @@
+// WHOIS Server - Internet Exchange Lookup
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Internet Exchange peering LANs and members from PeeringDB (`-IX`)
+//!
+//! An exchange is selected by name (`DE-CIX Frankfurt-IX`) or PeeringDB ID
+//! (`ix:31-IX`). The response lists its peering LAN prefixes, the route
+//! server ASNs (networks of type "Route Server" connected to it) and a table
+//! of member connections. Names matching several exchanges list the
+//! candidates with their IDs instead.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use crate::config::PEERINGDB_CACHE_TTL;
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use crate::services::utils::table::{format_table, section};
+use crate::log_debug;
+
+const PEERINGDB_API_BASE: &str = "https://www.peeringdb.com/api/";
+/// ASNs per network lookup, keeping the request URL short
+const ASN_CHUNK: usize = 100;
+/// Exchanges listed when a name is ambiguous
+const MAX_CANDIDATES: usize = 20;
+const ROUTE_SERVER_TYPE: &str = "Route Server";
+
+#[derive(Debug, Deserialize)]
+struct PeeringDbList<T> {
+    data: Vec<T>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Exchange {
+    id: u32,
+    name: String,
+    #[serde(default)]
+    aka: Option<String>,
+    #[serde(default)]
+    city: String,
+    #[serde(default)]
+    country: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IxLan {
+    id: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct IxPrefix {
+    prefix: String,
+    #[serde(default)]
+    protocol: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Connection {
+    asn: u32,
+    #[serde(default)]
+    ipaddr4: Option<String>,
+    #[serde(default)]
+    ipaddr6: Option<String>,
+    #[serde(default)]
+    speed: u64,
+    #[serde(default)]
+    is_rs_peer: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Network {
+    asn: u32,
+    name: String,
+    #[serde(default)]
+    info_type: Option<String>,
+    #[serde(default)]
+    info_types: Option<Vec<String>>,
+}
+
+impl Network {
+    fn is_route_server(&self) -> bool {
+        self.info_type.as_deref() == Some(ROUTE_SERVER_TYPE) ||
+            self.info_types.as_ref().is_some_and(|types| types.iter().any(|t| t == ROUTE_SERVER_TYPE))
+    }
+}
+
+/// How the exchange was asked for
+#[derive(Debug, PartialEq, Eq)]
+enum Selector {
+    Id(u32),
+    Name(String),
+}
+
+fn parse_selector(query: &str) -> Result<Selector> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(anyhow!("Usage: <exchange name>-IX or ix:<PeeringDB ID>-IX"));
+    }
+    match query.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("ix:") => query[3..]
+            .trim()
+            .parse()
+            .map(Selector::Id)
+            .map_err(|_| anyhow!("Invalid PeeringDB IX ID: {}", &query[3..])),
+        _ => Ok(Selector::Name(query.to_string())),
+    }
+}
+
+async fn fetch<T: DeserializeOwned>(client: &reqwest::Client, endpoint: &str, params: &[(&str, String)]) -> Result<Vec<T>> {
+    let url = format!("{}{}", api_base(PEERINGDB_API_BASE), endpoint);
+    let response = client.get(&url).query(params).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("PeeringDB {} request failed: HTTP {}", endpoint, response.status()));
+    }
+    let list: PeeringDbList<T> = response.limited_json().await?;
+    Ok(list.data)
+}
+
+/// The exchange a name refers to, or the candidates when it is ambiguous
+fn choose_exchange(name: &str, mut exchanges: Vec<Exchange>) -> std::result::Result<Exchange, Vec<Exchange>> {
+    let exact = exchanges.iter().position(|ix| {
+        ix.name.eq_ignore_ascii_case(name) || ix.aka.as_deref().is_some_and(|aka| aka.eq_ignore_ascii_case(name))
+    });
+    match exact {
+        Some(index) => Ok(exchanges.swap_remove(index)),
+        None if exchanges.len() == 1 => Ok(exchanges.remove(0)),
+        None => Err(exchanges),
+    }
+}
+
+fn speed_label(mbps: u64) -> String {
+    match mbps {
+        0 => "-".to_string(),
+        m if m >= 1000 && m % 1000 == 0 => format!("{}G", m / 1000),
+        m if m >= 1000 => format!("{:.1}G", (m as f64) / 1000.0),
+        m => format!("{}M", m),
+    }
+}
+
+
+fn format_exchange(
+    ix: &Exchange,
+    prefixes: &[IxPrefix],
+    connections: &[Connection],
+    networks: &BTreeMap<u32, Network>,
+) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("ix:             {}\n", ix.name));
+    output.push_str(&format!("peeringdb-id:   {}\n", ix.id));
+    output.push_str(&format!("location:       {}, {}\n", ix.city, ix.country));
+    let members: BTreeSet<u32> = connections.iter().map(|c| c.asn).collect();
+    output.push_str(&format!("members:        {}\n", members.len()));
+    output.push_str(&format!("connections:    {}\n\n", connections.len()));
+
+    section(&mut output, "Peering LAN Prefixes");
+    if prefixes.is_empty() {
+        output.push_str("% No peering LAN prefixes published\n");
+    }
+    for prefix in prefixes {
+        output.push_str(&format!("{:<24} {}\n", prefix.prefix, prefix.protocol));
+    }
+    output.push('\n');
+
+    section(&mut output, "Route Servers");
+    let route_servers: Vec<&Network> = networks.values().filter(|net| net.is_route_server()).collect();
+    if route_servers.is_empty() {
+        output.push_str("% No route server networks listed\n");
+    }
+    for net in route_servers {
+        output.push_str(&format!("AS{:<10} {}\n", net.asn, net.name));
+    }
+    output.push('\n');
+
+    section(&mut output, "Members");
+    let rows: Vec<Vec<String>> = connections
+        .iter()
+        .map(|c| {
+            vec![
+                format!("AS{}", c.asn),
+                networks.get(&c.asn).map(|net| net.name.clone()).unwrap_or_else(|| "N/A".to_string()),
+                c.ipaddr4.clone().unwrap_or_else(|| "-".to_string()),
+                c.ipaddr6.clone().unwrap_or_else(|| "-".to_string()),
+                speed_label(c.speed),
+                if c.is_rs_peer { "yes" } else { "no" }.to_string(),
+            ]
+        })
+        .collect();
+    output.push_str(&format_table(&["ASN", "Name", "IPv4", "IPv6", "Speed", "RS Peer"], &rows));
+    output
+}
+
+async fn resolve_exchange(client: &reqwest::Client, selector: &Selector) -> Result<std::result::Result<Exchange, Vec<Exchange>>> {
+    match selector {
+        Selector::Id(id) => {
+            let exchanges: Vec<Exchange> = fetch(client, "ix", &[("id", id.to_string())]).await?;
+            exchanges.into_iter().next().map(Ok).ok_or_else(|| anyhow!("No exchange with PeeringDB ID {}", id))
+        }
+        Selector::Name(name) => {
+            let exchanges: Vec<Exchange> = fetch(client, "ix", &[("name__contains", name.clone())]).await?;
+            if exchanges.is_empty() {
+                return Err(anyhow!("No exchange found matching: {}", name));
+            }
+            Ok(choose_exchange(name, exchanges))
+        }
+    }
+}
+
+/// Process an `-IX` query
+pub async fn process_ix_query(ctx: &RequestContext, query: &str) -> Result<String> {
+    let selector = parse_selector(query)?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .user_agent("whois-server/1.0")
+        .build()?;
+
+    let mut output = String::new();
+    output.push_str("% Internet Exchange Information\n");
+    output.push_str("% Source: https://www.peeringdb.com/\n");
+    output.push_str(&format!("% Query: {}\n\n", query.trim()));
+
+    let ix = match resolve_exchange(&client, &selector).await? {
+        Ok(ix) => ix,
+        Err(candidates) => {
+            output.push_str(&format!("% {} exchanges match, query one by ID (ix:<id>-IX):\n\n", candidates.len()));
+            let rows: Vec<Vec<String>> = candidates
+                .iter()
+                .take(MAX_CANDIDATES)
+                .map(|ix| vec![format!("ix:{}", ix.id), ix.name.clone(), format!("{}, {}", ix.city, ix.country)])
+                .collect();
+            output.push_str(&format_table(&["ID", "Name", "Location"], &rows));
+            return Ok(output);
+        }
+    };
+    log_debug!("[{}] -IX {} resolved to PeeringDB IX {}", ctx, query, ix.id);
+
+    let ixlans: Vec<IxLan> = fetch(&client, "ixlan", &[("ix_id", ix.id.to_string())]).await?;
+    let ixlan_ids = ixlans.iter().map(|lan| lan.id.to_string()).collect::<Vec<_>>().join(",");
+    let mut prefixes: Vec<IxPrefix> = if ixlan_ids.is_empty() {
+        Vec::new()
+    } else {
+        fetch(&client, "ixpfx", &[("ixlan_id__in", ixlan_ids)]).await?
+    };
+    prefixes.sort_by(|a, b| a.protocol.cmp(&b.protocol).then_with(|| a.prefix.cmp(&b.prefix)));
+
+    let mut connections: Vec<Connection> = fetch(&client, "netixlan", &[("ix_id", ix.id.to_string())]).await?;
+    connections.sort_by(|a, b| a.asn.cmp(&b.asn).then_with(|| a.ipaddr4.cmp(&b.ipaddr4)));
+
+    let asns: Vec<String> = connections.iter().map(|c| c.asn).collect::<BTreeSet<_>>().iter().map(u32::to_string).collect();
+    let mut networks = BTreeMap::new();
+    for chunk in asns.chunks(ASN_CHUNK) {
+        let params = [("asn__in", chunk.join(",")), ("fields", "asn,name,info_type,info_types".to_string())];
+        for net in fetch::<Network>(&client, "net", &params).await? {
+            networks.insert(net.asn, net);
+        }
+    }
+
+    output.push_str(&format_exchange(&ix, &prefixes, &connections, &networks));
+    Ok(output)
+}
+
+pub struct IxService;
+
+#[async_trait]
+impl QueryService for IxService {
+    fn name(&self) -> &'static str {
+        "ix"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-IX"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "Internet Exchange peering LAN prefixes, members and route servers (PeeringDB)",
+            example: "ix:31-IX",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "PeeringDB API"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(PEERINGDB_CACHE_TTL))
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_ix_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::request_context::Frontend;
+    use crate::services::fixtures::FixtureServer;
+
+    fn exchange(id: u32, name: &str) -> Exchange {
+        Exchange { id, name: name.to_string(), aka: None, city: "Frankfurt".to_string(), country: "DE".to_string() }
+    }
+
+    #[test]
+    fn test_selector_and_choice() {
+        assert_eq!(parse_selector("ix:31").unwrap(), Selector::Id(31));
+        assert_eq!(parse_selector("IX:31").unwrap(), Selector::Id(31));
+        assert_eq!(parse_selector("DE-CIX").unwrap(), Selector::Name("DE-CIX".to_string()));
+        assert!(parse_selector("ix:abc").is_err());
+
+        let candidates = vec![exchange(31, "DE-CIX Frankfurt"), exchange(62, "DE-CIX Munich")];
+        assert_eq!(choose_exchange("de-cix frankfurt", candidates.clone()).unwrap().id, 31);
+        assert_eq!(choose_exchange("DE-CIX", candidates).unwrap_err().len(), 2);
+        assert_eq!(choose_exchange("AMS-IX", vec![exchange(26, "AMS-IX")]).unwrap().id, 26);
+    }
+
+    #[test]
+    fn test_table_and_speed() {
+        let table = format_table(&["ASN", "Name"], &[vec!["AS6695".to_string(), "DE-CIX".to_string()]]);
+        assert_eq!(table, "ASN    | Name\n-------|-------\nAS6695 | DE-CIX\n");
+        assert_eq!(speed_label(100000), "100G");
+        assert_eq!(speed_label(2500), "2.5G");
+        assert_eq!(speed_label(0), "-");
+    }
+
+    #[tokio::test]
+    async fn test_exchange_fixture() {
+        let mut fixtures = FixtureServer::start("ix").await;
+        fixtures.serve(PEERINGDB_API_BASE, "ix?id=31", "ix.json").await;
+        fixtures.serve(PEERINGDB_API_BASE, "ixlan?ix_id=31", "ixlan.json").await;
+        fixtures.serve(PEERINGDB_API_BASE, "ixpfx?ixlan_id__in=31", "ixpfx.json").await;
+        fixtures.serve(PEERINGDB_API_BASE, "netixlan?ix_id=31", "netixlan.json").await;
+        fixtures.serve(PEERINGDB_API_BASE, "net?asn__in=6695,13335", "net.json").await;
+
+        let ctx = RequestContext::new(Frontend::Library);
+        let response = fixtures.run(process_ix_query(&ctx, "ix:31")).await.unwrap();
+        assert!(response.contains("ix:             DE-CIX Frankfurt\n"));
+        assert!(response.contains("80.81.192.0/21"));
+        assert!(response.contains("AS6695       DE-CIX Route Servers\n"));
+        assert!(response.contains("AS13335 | Cloudflare, Inc."));
+        assert!(response.contains("members:        2\n"));
+    }
+}
@@ -0,0 +1,274 @@
+// WHOIS Server - Latency Matrix
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Per-continent latency summary (`-LATMATRIX`)
+//!
+//! Runs one Globalping ping measurement with a few probes on every continent
+//! and reports min/avg latency and loss per continent, answering "how far is
+//! this host from my users" in one query.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::utils::{
+    GlobalpingClient,
+    GlobalpingRequest,
+    GlobalpingResult,
+    MeasurementLocation,
+    MeasurementOptions,
+    PingOptions,
+};
+use crate::services::utils::globalping::Timings;
+use crate::{log_debug, log_error};
+
+/// Continents probed, in output order
+const CONTINENTS: [(&str, &str); 6] = [
+    ("AF", "Africa"),
+    ("AS", "Asia"),
+    ("EU", "Europe"),
+    ("NA", "North America"),
+    ("OC", "Oceania"),
+    ("SA", "South America"),
+];
+const PROBES_PER_CONTINENT: u32 = 3;
+const PACKETS_PER_PROBE: u32 = 3;
+const MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// Latency summary of one continent
+#[derive(Debug, PartialEq)]
+struct RegionLatency {
+    code: &'static str,
+    name: &'static str,
+    probes: usize,
+    sent: usize,
+    received: usize,
+    min: Option<f64>,
+    avg: Option<f64>,
+}
+
+impl RegionLatency {
+    fn loss_percent(&self) -> Option<f64> {
+        (self.sent > 0).then(|| ((self.sent - self.received) as f64) * 100.0 / (self.sent as f64))
+    }
+}
+
+/// Group probe results by continent
+///
+/// Latency comes from the individual replies rather than the probe's own
+/// stats, so probes that lost every packet still count towards loss.
+fn summarize(results: &GlobalpingResult) -> Vec<RegionLatency> {
+    CONTINENTS.iter()
+        .map(|&(code, name)| {
+            let probes: Vec<_> = results.results
+                .iter()
+                .filter(|r| r.probe.continent.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(code)))
+                .collect();
+            let rtts: Vec<f64> = probes
+                .iter()
+                .flat_map(|r| r.result.timings.iter().flat_map(Timings::replies).map(|t| t.rtt))
+                .collect();
+            RegionLatency {
+                code,
+                name,
+                probes: probes.len(),
+                sent: probes.len() * (PACKETS_PER_PROBE as usize),
+                received: rtts.len(),
+                min: rtts.iter().copied().reduce(f64::min),
+                avg: (!rtts.is_empty()).then(|| rtts.iter().sum::<f64>() / (rtts.len() as f64)),
+            }
+        })
+        .collect()
+}
+
+fn format_ms(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |ms| format!("{:.1}", ms))
+}
+
+fn format_matrix(target: &str, results: &GlobalpingResult) -> String {
+    let regions = summarize(results);
+    let mut output = String::new();
+
+    output.push_str(&format!("% Latency matrix for {}\n", target));
+    if let Some(address) = results.results.iter().find_map(|r| r.result.resolved_address.as_deref()) {
+        output.push_str(&format!("% Resolved to: {}\n", address));
+    }
+    output.push_str(&format!(
+        "% ICMP ping, {} packets from up to {} probes per continent\n",
+        PACKETS_PER_PROBE,
+        PROBES_PER_CONTINENT
+    ));
+    output.push_str("%\n");
+    output.push_str(&format!("{:<20} {:>6} {:>9} {:>9} {:>6}\n", "continent", "probes", "min-ms", "avg-ms", "loss"));
+    for region in &regions {
+        let loss = region.loss_percent().map_or_else(|| "-".to_string(), |loss| format!("{:.0}%", loss));
+        output.push_str(&format!(
+            "{:<20} {:>6} {:>9} {:>9} {:>6}\n",
+            format!("{} ({})", region.name, region.code),
+            region.probes,
+            format_ms(region.min),
+            format_ms(region.avg),
+            loss
+        ));
+    }
+
+    let reachable = || regions.iter().filter_map(|r| r.avg.map(|avg| (r, avg)));
+    if let Some((nearest, avg)) = reachable().min_by(|a, b| a.1.total_cmp(&b.1)) {
+        output.push_str("%\n");
+        output.push_str(&format!("nearest-region:  {} ({:.1} ms avg)\n", nearest.name, avg));
+    }
+    if let Some((farthest, avg)) = reachable().max_by(|a, b| a.1.total_cmp(&b.1)) {
+        output.push_str(&format!("farthest-region: {} ({:.1} ms avg)\n", farthest.name, avg));
+    }
+    let missing: Vec<&str> = regions.iter().filter(|r| r.probes == 0).map(|r| r.code).collect();
+    if !missing.is_empty() {
+        output.push_str(&format!("% No probes were available in: {}\n", missing.join(", ")));
+    }
+
+    output.push_str("% Data provided by Globalping (globalping.io)\n");
+    output
+}
+
+/// Ping `target` from every continent and summarize latency per continent
+pub async fn process_latmatrix_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let target = target.trim();
+    if target.is_empty() || target.contains(char::is_whitespace) {
+        return Ok(format!("Invalid latency matrix target: {}\nUse a host or IP, e.g. 1.1.1.1-LATMATRIX\n", target));
+    }
+
+    let globalping = match GlobalpingClient::new() {
+        Ok(client) => client,
+        Err(e) => {
+            log_error!("Failed to initialize Globalping client: {}", e);
+            return Ok(format!("Latency matrix service error: {}\n", e));
+        }
+    };
+
+    let locations: Vec<MeasurementLocation> = CONTINENTS.iter()
+        .map(|(code, _)| MeasurementLocation {
+            continent: Some(code.to_string()),
+            limit: Some(PROBES_PER_CONTINENT),
+            ..Default::default()
+        })
+        .collect();
+    let request = GlobalpingRequest {
+        measurement_type: "ping".to_string(),
+        target: target.to_string(),
+        limit: None,
+        measurement_options: Some(
+            MeasurementOptions::Ping(PingOptions {
+                packets: Some(PACKETS_PER_PROBE),
+                protocol: Some("ICMP".to_string()),
+                port: None,
+            })
+        ),
+        locations: Some(locations),
+        in_progress_updates: Some(false),
+    };
+
+    let measurement_id = match globalping.submit_measurement(&request).await {
+        Ok(id) => id,
+        Err(e) => {
+            log_error!("Failed to submit latency matrix measurement: {}", e);
+            return Ok(format!("Latency matrix failed: {}\n", e));
+        }
+    };
+    log_debug!("[{}] Latency matrix measurement ID: {}", ctx, measurement_id);
+
+    let budget = ctx.remaining().map_or(MAX_WAIT, |left| left.min(MAX_WAIT));
+    let results = match globalping.wait_for_results(&measurement_id, budget.as_secs().max(1)).await {
+        Ok(results) => results,
+        Err(e) => {
+            log_error!("Failed to get latency matrix results: {}", e);
+            return Ok(format!("Latency matrix measurement timed out or failed: {}\n", e));
+        }
+    };
+
+    Ok(format_matrix(target, &results))
+}
+
+/// `-LATMATRIX`: per-continent latency to a host
+pub struct LatMatrixService;
+
+#[async_trait]
+impl QueryService for LatMatrixService {
+    fn name(&self) -> &'static str {
+        "latmatrix"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-LATMATRIX"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK MEASUREMENT",
+            summary: "Min/avg ping latency and loss from every continent",
+            example: "1.1.1.1-LATMATRIX",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Globalping (live)"
+    }
+
+    fn query_class(&self) -> &'static str {
+        "TRACE"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        // Live measurement
+        None
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_latmatrix_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe_result(continent: &str, rtts: &[f64]) -> serde_json::Value {
+        serde_json::json!({
+            "probe": { "continent": continent, "country": "XX", "asn": 64496, "network": "Example" },
+            "result": {
+                "status": "finished",
+                "resolvedAddress": "192.0.2.1",
+                "timings": rtts.iter().map(|rtt| serde_json::json!({ "rtt": rtt })).collect::<Vec<_>>()
+            }
+        })
+    }
+
+    #[test]
+    fn test_summarize_by_continent() {
+        let results: GlobalpingResult = serde_json::from_value(
+            serde_json::json!({
+                "status": "finished",
+                "results": [
+                    probe_result("EU", &[10.0, 12.0, 14.0]),
+                    probe_result("EU", &[8.0, 9.0, 10.0]),
+                    probe_result("OC", &[])
+                ]
+            })
+        ).unwrap();
+
+        let regions = summarize(&results);
+        let europe = regions.iter().find(|r| r.code == "EU").unwrap();
+        assert_eq!((europe.probes, europe.received, europe.min), (2, 6, Some(8.0)));
+        assert_eq!(europe.avg, Some(10.5));
+        assert_eq!(europe.loss_percent(), Some(0.0));
+
+        let oceania = regions.iter().find(|r| r.code == "OC").unwrap();
+        assert_eq!((oceania.avg, oceania.loss_percent()), (None, Some(100.0)));
+        assert_eq!(regions.iter().find(|r| r.code == "AF").unwrap().loss_percent(), None);
+
+        let output = format_matrix("192.0.2.1", &results);
+        assert!(output.contains("nearest-region:  Europe (10.5 ms avg)\n"));
+        assert!(output.contains("% No probes were available in: AF, AS, NA, SA\n"));
+    }
+}
@@ -1,10 +1,39 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
+use crate::services::utils::{MeasurementLocation, parse_vantage_points};
 use crate::{log_debug};
 // RIPE STAT Looking Glass API
 const RIPE_STAT_API_BASE: &str = "https://stat.ripe.net";
 
+/// Country and continent of the RIS route collectors, for `-LG@<location>`
+const COLLECTOR_LOCATIONS: &[(&str, &str, &str)] = &[
+    ("rrc00", "NL", "EU"),
+    ("rrc01", "GB", "EU"),
+    ("rrc03", "NL", "EU"),
+    ("rrc04", "CH", "EU"),
+    ("rrc05", "AT", "EU"),
+    ("rrc06", "JP", "AS"),
+    ("rrc07", "SE", "EU"),
+    ("rrc10", "IT", "EU"),
+    ("rrc11", "US", "NA"),
+    ("rrc12", "DE", "EU"),
+    ("rrc13", "RU", "EU"),
+    ("rrc14", "US", "NA"),
+    ("rrc15", "BR", "SA"),
+    ("rrc16", "US", "NA"),
+    ("rrc18", "ES", "EU"),
+    ("rrc19", "ZA", "AF"),
+    ("rrc20", "CH", "EU"),
+    ("rrc21", "FR", "EU"),
+    ("rrc22", "RO", "EU"),
+    ("rrc23", "SG", "AS"),
+    ("rrc24", "UY", "SA"),
+    ("rrc25", "NL", "EU"),
+    ("rrc26", "AE", "AS"),
+];
+
 #[derive(Debug, Deserialize, Serialize)]
 struct LookingGlassResponse {
     data: LookingGlassData,
@@ -40,8 +69,57 @@ struct PeerData {
     latest_time: String,
 }
 
+/// Whether a collector matches a location: its name (`rrc12`), country or
+/// continent code, or part of its location (`Frankfurt`)
+fn collector_matches(rrc: &RrcData, location: &str) -> bool {
+    let name = rrc.rrc.to_lowercase();
+    let location = location.to_lowercase();
+    let codes = COLLECTOR_LOCATIONS.iter().find(|(collector, _, _)| *collector == name);
+    name == location ||
+        codes.is_some_and(|(_, country, continent)| {
+            country.eq_ignore_ascii_case(&location) || continent.eq_ignore_ascii_case(&location)
+        }) ||
+        (location.len() > 2 && rrc.location.to_lowercase().contains(&location))
+}
+
+/// Peer AS of a route, the first ASN of its path
+fn peer_asn(peer: &PeerData) -> Option<u32> {
+    peer.as_path.split_whitespace().next()?.parse().ok()
+}
+
+/// Keep the collectors matching a location and the peers matching an `AS<n>`
+/// selector; selectors of either kind that are absent do not filter
+fn select_collectors(data: LookingGlassData, locations: &[MeasurementLocation]) -> LookingGlassData {
+    let (mut peer_asns, mut places) = (Vec::new(), Vec::new());
+    for magic in locations.iter().filter_map(|location| location.magic.as_deref()) {
+        let asn = magic.get(..2)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("as"))
+            .and_then(|_| magic[2..].parse::<u32>().ok());
+        match asn {
+            Some(asn) => peer_asns.push(asn),
+            None => places.push(magic),
+        }
+    }
+
+    let rrcs = data.rrcs
+        .into_iter()
+        .filter(|rrc| places.is_empty() || places.iter().any(|place| collector_matches(rrc, place)))
+        .map(|mut rrc| {
+            if !peer_asns.is_empty() {
+                rrc.peers.retain(|peer| peer_asn(peer).is_some_and(|asn| peer_asns.contains(&asn)));
+            }
+            rrc
+        })
+        .filter(|rrc| !rrc.peers.is_empty())
+        .collect();
+    LookingGlassData { rrcs }
+}
+
 /// Process Looking Glass queries ending with -LG (async version)
-pub async fn process_looking_glass_query(resource: &str) -> Result<String> {
+///
+/// `collectors` is an optional `@` selector (`DE`, `rrc00`, `AS13335`, ...)
+/// limiting the output to matching route collectors and peers.
+pub async fn process_looking_glass_query(resource: &str, collectors: Option<&str>) -> Result<String> {
     log_debug!("Processing Looking Glass query for: {}", resource);
 
     let url = format!(
@@ -63,13 +141,25 @@ pub async fn process_looking_glass_query(resource: &str) -> Result<String> {
         ));
     }
 
-    let lg_response: LookingGlassResponse = response.json().await?;
+    let lg_response: LookingGlassResponse = response.limited_json().await?;
 
     if lg_response.data_call_status != "supported" {
         return Err(anyhow!("Looking Glass data call not supported"));
     }
 
-    format_bird_output(&lg_response.data, resource)
+    let Some(spec) = collectors else {
+        return format_bird_output(&lg_response.data, resource);
+    };
+    let locations = parse_vantage_points(spec)
+        .ok_or_else(|| anyhow!("Invalid collector selector: {}", spec))?;
+    let selected = select_collectors(lg_response.data, &locations);
+    let mut output = format!("% Routes seen by collectors and peers matching @{}\n", spec);
+    if selected.rrcs.is_empty() {
+        output.push_str("% No collector or peer matches the selection\n");
+        return Ok(output);
+    }
+    output.push_str(&format_bird_output(&selected, resource)?);
+    Ok(output)
 }
 
 /// Format Looking Glass response in BIRD-style format
@@ -222,4 +312,51 @@ mod tests {
         assert!(result.contains("bgp_origin = IGP"));
         assert!(result.contains("bgp_community.add((34854,1000))"));
     }
+
+    fn peer(as_path: &str) -> PeerData {
+        PeerData {
+            asn_origin: "13335".to_string(),
+            as_path: as_path.to_string(),
+            community: String::new(),
+            large_community: String::new(),
+            extended_community: String::new(),
+            last_updated: String::new(),
+            prefix: "1.1.1.0/24".to_string(),
+            peer: "192.0.2.1".to_string(),
+            origin: "IGP".to_string(),
+            next_hop: "192.0.2.1".to_string(),
+            latest_time: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_select_collectors() {
+        let data = || LookingGlassData {
+            rrcs: vec![
+                RrcData {
+                    rrc: "RRC12".to_string(),
+                    location: "Frankfurt, Germany".to_string(),
+                    peers: vec![peer("6939 13335"), peer("3356 13335")],
+                },
+                RrcData {
+                    rrc: "RRC06".to_string(),
+                    location: "Otemachi, Japan".to_string(),
+                    peers: vec![peer("6939 13335")],
+                },
+            ],
+        };
+        let select = |spec: &str| select_collectors(data(), &parse_vantage_points(spec).unwrap());
+
+        let germany = select("de");
+        assert_eq!(germany.rrcs.len(), 1);
+        assert_eq!(germany.rrcs[0].peers.len(), 2);
+        assert_eq!(select("rrc06").rrcs[0].rrc, "RRC06");
+        assert_eq!(select("otemachi").rrcs[0].rrc, "RRC06");
+        assert_eq!(select("EU,AS").rrcs.len(), 2);
+
+        let he = select("AS6939");
+        assert_eq!(he.rrcs.iter().map(|rrc| rrc.peers.len()).sum::<usize>(), 2);
+        let level3_in_japan = select("jp,as3356");
+        assert!(level3_in_japan.rrcs.is_empty());
+    }
 }
@@ -19,6 +19,7 @@
 use anyhow::Result;
 use serde::{ Deserialize, Serialize };
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
 /// Luotianyi lyric API response structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,13 +72,13 @@ impl LyricService {
 
         if !status.is_success() {
             let error_text = response
-                .text().await
+                .limited_text().await
                 .unwrap_or_else(|_| "Unable to read error response".to_string());
             log_debug!("Lyric API error response: {}", error_text);
             return Err(anyhow::anyhow!("Lyric request failed: {} - {}", status, error_text));
         }
 
-        let response_text = response.text().await?;
+        let response_text = response.limited_text().await?;
         log_debug!(
             "Lyric API response body: {}",
             &response_text[..std::cmp::min(200, response_text.len())]
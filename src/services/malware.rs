@@ -0,0 +1,389 @@
+// WHOIS Server - Malware Hash Lookup
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Threat intelligence lookups for file hashes (`-MALWARE`)
+//!
+//! Accepts an MD5, SHA-1 or SHA-256 hash and reports detection names,
+//! first-seen dates and tags from MalwareBazaar (`MALWAREBAZAAR_API_KEY`)
+//! and VirusTotal (`VIRUSTOTAL_API_KEY`). Each source is queried only when
+//! its key is configured; both are queried concurrently.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::log_debug;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+
+const MALWAREBAZAAR_API_BASE: &str = "https://mb-api.abuse.ch/api/v1/";
+const VIRUSTOTAL_API_BASE: &str = "https://www.virustotal.com/api/v3/";
+/// Engine detections listed before the rest are summarized
+const DETECTION_LIMIT: usize = 10;
+
+/// Kind of hash, by length
+fn hash_type(hash: &str) -> Option<&'static str> {
+    if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hash.len() {
+        32 => Some("MD5"),
+        40 => Some("SHA-1"),
+        64 => Some("SHA-256"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BazaarResponse {
+    query_status: String,
+    #[serde(default)]
+    data: Vec<BazaarSample>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BazaarSample {
+    sha256_hash: Option<String>,
+    sha1_hash: Option<String>,
+    md5_hash: Option<String>,
+    first_seen: Option<String>,
+    last_seen: Option<String>,
+    file_name: Option<String>,
+    file_size: Option<u64>,
+    file_type: Option<String>,
+    signature: Option<String>,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    reporter: Option<String>,
+    origin_country: Option<String>,
+    #[serde(default)]
+    intelligence: BazaarIntelligence,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BazaarIntelligence {
+    #[serde(default)]
+    clamav: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VirusTotalResponse {
+    data: VirusTotalFile,
+}
+
+#[derive(Debug, Deserialize)]
+struct VirusTotalFile {
+    id: String,
+    attributes: VirusTotalAttributes,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct VirusTotalAttributes {
+    meaningful_name: Option<String>,
+    type_description: Option<String>,
+    size: Option<u64>,
+    first_submission_date: Option<i64>,
+    last_analysis_date: Option<i64>,
+    #[serde(default)]
+    last_analysis_stats: HashMap<String, u32>,
+    #[serde(default)]
+    last_analysis_results: HashMap<String, EngineResult>,
+    popular_threat_classification: Option<ThreatClassification>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EngineResult {
+    category: Option<String>,
+    result: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThreatClassification {
+    suggested_threat_label: Option<String>,
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent("WhoisServer/1.0 Malware Hash Client")
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+fn env_key(name: &str) -> Option<String> {
+    std::env::var(name).ok().map(|k| k.trim().to_string()).filter(|k| !k.is_empty())
+}
+
+/// Sample from MalwareBazaar, `None` when the hash is unknown
+async fn query_malwarebazaar(client: &reqwest::Client, api_key: &str, hash: &str) -> Result<Option<BazaarSample>> {
+    let response = client
+        .post(api_base(MALWAREBAZAAR_API_BASE).as_ref())
+        .header("Auth-Key", api_key)
+        .form(&[("query", "get_info"), ("hash", hash)])
+        .send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("MalwareBazaar request failed: HTTP {}", response.status()));
+    }
+
+    let found: BazaarResponse = response.limited_json().await?;
+    match found.query_status.as_str() {
+        "ok" => Ok(found.data.into_iter().next()),
+        "hash_not_found" | "no_results" => Ok(None),
+        status => Err(anyhow!("MalwareBazaar query failed: {}", status)),
+    }
+}
+
+/// File report from VirusTotal, `None` when the hash is unknown
+async fn query_virustotal(client: &reqwest::Client, api_key: &str, hash: &str) -> Result<Option<VirusTotalFile>> {
+    let response = client
+        .get(format!("{}files/{}", api_base(VIRUSTOTAL_API_BASE), hash))
+        .header("x-apikey", api_key)
+        .send().await?;
+    match response.status().as_u16() {
+        200 => Ok(Some(response.limited_json::<VirusTotalResponse>().await?.data)),
+        404 => Ok(None),
+        429 => Err(anyhow!("VirusTotal quota exceeded, try again later")),
+        status => Err(anyhow!("VirusTotal request failed: HTTP {}", status)),
+    }
+}
+
+/// Look up a file hash in the configured threat intelligence sources
+pub async fn process_malware_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let hash = target.trim().to_ascii_lowercase();
+    let Some(kind) = hash_type(&hash) else {
+        return Ok(format!(
+            "Invalid hash: {}\nUse an MD5, SHA-1 or SHA-256 hash, e.g. <sha256>-MALWARE\n",
+            target
+        ));
+    };
+
+    let _ = dotenv::dotenv();
+    let bazaar_key = env_key("MALWAREBAZAAR_API_KEY");
+    let virustotal_key = env_key("VIRUSTOTAL_API_KEY");
+    if bazaar_key.is_none() && virustotal_key.is_none() {
+        return Ok(
+            "Malware hash lookups are not enabled on this server.\n\
+             The operator must set MALWAREBAZAAR_API_KEY and/or VIRUSTOTAL_API_KEY.\n".to_string()
+        );
+    }
+
+    log_debug!("[{}] Looking up {} hash {}", ctx, kind, hash);
+    let client = client();
+    let (bazaar, virustotal) = tokio::join!(
+        async {
+            match &bazaar_key {
+                Some(key) => Some(query_malwarebazaar(&client, key, &hash).await),
+                None => None,
+            }
+        },
+        async {
+            match &virustotal_key {
+                Some(key) => Some(query_virustotal(&client, key, &hash).await),
+                None => None,
+            }
+        }
+    );
+
+    let mut output = String::new();
+    output.push_str(&format!("Malware Hash Report for: {}\n", hash));
+    output.push_str("=".repeat(60).as_str());
+    output.push('\n');
+    output.push_str(&format!("hash: {}\n", hash));
+    output.push_str(&format!("hash-type: {}\n", kind));
+
+    if let Some(result) = bazaar {
+        output.push('\n');
+        output.push_str("% Source: MalwareBazaar (abuse.ch)\n");
+        match result {
+            Ok(Some(sample)) => push_bazaar(&mut output, &sample),
+            Ok(None) => output.push_str("mb-status: not found\n"),
+            Err(e) => output.push_str(&format!("mb-status: error ({})\n", e)),
+        }
+    }
+
+    if let Some(result) = virustotal {
+        output.push('\n');
+        output.push_str("% Source: VirusTotal\n");
+        match result {
+            Ok(Some(file)) => push_virustotal(&mut output, &file),
+            Ok(None) => output.push_str("vt-status: not found\n"),
+            Err(e) => output.push_str(&format!("vt-status: error ({})\n", e)),
+        }
+    }
+
+    Ok(output)
+}
+
+fn push_field(output: &mut String, key: &str, value: Option<&str>) {
+    if let Some(value) = value.filter(|v| !v.is_empty()) {
+        output.push_str(&format!("{}: {}\n", key, value));
+    }
+}
+
+fn push_bazaar(output: &mut String, sample: &BazaarSample) {
+    output.push_str("mb-status: found\n");
+    push_field(output, "mb-signature", sample.signature.as_deref());
+    push_field(output, "mb-file-name", sample.file_name.as_deref());
+    push_field(output, "mb-file-type", sample.file_type.as_deref());
+    if let Some(size) = sample.file_size {
+        output.push_str(&format!("mb-file-size: {} bytes\n", size));
+    }
+    push_field(output, "mb-first-seen", sample.first_seen.as_deref());
+    push_field(output, "mb-last-seen", sample.last_seen.as_deref());
+    if let Some(tags) = sample.tags.as_ref().filter(|t| !t.is_empty()) {
+        output.push_str(&format!("mb-tags: {}\n", tags.join(", ")));
+    }
+    if let Some(clamav) = sample.intelligence.clamav.as_ref().filter(|c| !c.is_empty()) {
+        output.push_str(&format!("mb-clamav: {}\n", clamav.join(", ")));
+    }
+    push_field(output, "mb-reporter", sample.reporter.as_deref());
+    push_field(output, "mb-origin-country", sample.origin_country.as_deref());
+    push_field(output, "md5", sample.md5_hash.as_deref());
+    push_field(output, "sha1", sample.sha1_hash.as_deref());
+    push_field(output, "sha256", sample.sha256_hash.as_deref());
+    if let Some(sha256) = &sample.sha256_hash {
+        output.push_str(&format!("mb-url: https://bazaar.abuse.ch/sample/{}/\n", sha256));
+    }
+}
+
+fn format_timestamp(timestamp: i64) -> Option<String> {
+    DateTime::from_timestamp(timestamp, 0).map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+}
+
+fn push_virustotal(output: &mut String, file: &VirusTotalFile) {
+    let attributes = &file.attributes;
+    let stat = |key: &str| attributes.last_analysis_stats.get(key).copied().unwrap_or(0);
+    let flagged = stat("malicious") + stat("suspicious");
+    let engines = stat("malicious") + stat("suspicious") + stat("undetected") + stat("harmless");
+
+    output.push_str(&format!("vt-detections: {}/{}\n", flagged, engines));
+    push_field(
+        output,
+        "vt-label",
+        attributes.popular_threat_classification
+            .as_ref()
+            .and_then(|c| c.suggested_threat_label.as_deref())
+    );
+    push_field(output, "vt-name", attributes.meaningful_name.as_deref());
+    push_field(output, "vt-file-type", attributes.type_description.as_deref());
+    if let Some(size) = attributes.size {
+        output.push_str(&format!("vt-file-size: {} bytes\n", size));
+    }
+    push_field(output, "vt-first-seen", attributes.first_submission_date.and_then(format_timestamp).as_deref());
+    push_field(output, "vt-last-analysis", attributes.last_analysis_date.and_then(format_timestamp).as_deref());
+    if !attributes.tags.is_empty() {
+        output.push_str(&format!("vt-tags: {}\n", attributes.tags.join(", ")));
+    }
+
+    let mut detections: Vec<(&String, &str)> = attributes.last_analysis_results
+        .iter()
+        .filter(|(_, r)| matches!(r.category.as_deref(), Some("malicious" | "suspicious")))
+        .filter_map(|(engine, r)| r.result.as_deref().map(|name| (engine, name)))
+        .collect();
+    detections.sort();
+    for (engine, name) in detections.iter().take(DETECTION_LIMIT) {
+        output.push_str(&format!("detection: {}: {}\n", engine, name));
+    }
+    if detections.len() > DETECTION_LIMIT {
+        output.push_str(&format!("% {} more detections omitted\n", detections.len() - DETECTION_LIMIT));
+    }
+    output.push_str(&format!("vt-url: https://www.virustotal.com/gui/file/{}\n", file.id));
+}
+
+pub struct MalwareService;
+
+#[async_trait]
+impl QueryService for MalwareService {
+    fn name(&self) -> &'static str {
+        "malware"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-MALWARE"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "SECURITY INVESTIGATION",
+            summary: "MalwareBazaar/VirusTotal file hash lookup (when enabled)",
+            example: "44d88612fea8a8f36de82e1278abb02f-MALWARE",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "MalwareBazaar API, VirusTotal API v3"
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_malware_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_type() {
+        assert_eq!(hash_type("44d88612fea8a8f36de82e1278abb02f"), Some("MD5"));
+        assert_eq!(hash_type("3395856ce81f2b7382dee72602f798b642f14140"), Some("SHA-1"));
+        assert_eq!(
+            hash_type("275a021bbfb6489e54d471899f7db9d1663fc695ec2fe2a2c4538aabf651fd0f"),
+            Some("SHA-256")
+        );
+        assert_eq!(hash_type("44d88612fea8a8f36de82e1278abb02g"), None);
+        assert_eq!(hash_type("example.com"), None);
+    }
+
+    #[test]
+    fn test_virustotal_summary() {
+        let response: VirusTotalResponse = serde_json::from_str(
+            r#"{"data":{"id":"275a021bbfb6489e54d471899f7db9d1663fc695ec2fe2a2c4538aabf651fd0f","attributes":{
+                "meaningful_name":"eicar.com","type_description":"Text","size":68,
+                "first_submission_date":1148301722,
+                "last_analysis_stats":{"malicious":2,"suspicious":0,"undetected":1,"harmless":0,"timeout":0},
+                "last_analysis_results":{
+                    "Kaspersky":{"category":"malicious","result":"EICAR-Test-File"},
+                    "ClamAV":{"category":"malicious","result":"Eicar-Signature"},
+                    "Other":{"category":"undetected","result":null}},
+                "popular_threat_classification":{"suggested_threat_label":"virus.eicar/test"},
+                "tags":["text"]}}}"#,
+        )
+        .unwrap();
+
+        let mut output = String::new();
+        push_virustotal(&mut output, &response.data);
+        assert!(output.contains("vt-detections: 2/3\nvt-label: virus.eicar/test\n"));
+        assert!(output.contains("vt-first-seen: 2006-05-22 12:42:02 UTC\n"));
+        assert!(output.contains("detection: ClamAV: Eicar-Signature\ndetection: Kaspersky: EICAR-Test-File\n"));
+        assert!(!output.contains("Other"));
+    }
+
+    #[test]
+    fn test_malwarebazaar_summary() {
+        let response: BazaarResponse = serde_json::from_str(
+            r#"{"query_status":"ok","data":[{"sha256_hash":"094fd325049b8a9cf6d3e5ef2a6d4cc6a567d7d49c35f8bb8dd9e3c6acf3d78d",
+                "md5_hash":"e3e4e6e1ee5d5b3c8f7a1e48d6d1d0a4","first_seen":"2024-05-14 08:12:33","last_seen":null,
+                "file_name":"invoice.exe","file_size":421888,"file_type":"exe","signature":"AgentTesla",
+                "tags":["AgentTesla","exe"],"reporter":"abuse_ch","origin_country":"NL",
+                "intelligence":{"clamav":null,"downloads":"12","uploads":"1"}}]}"#,
+        )
+        .unwrap();
+
+        let mut output = String::new();
+        push_bazaar(&mut output, &response.data[0]);
+        assert!(output.contains("mb-status: found\nmb-signature: AgentTesla\nmb-file-name: invoice.exe\n"));
+        assert!(output.contains("mb-first-seen: 2024-05-14 08:12:33\n"));
+        assert!(output.contains("mb-tags: AgentTesla, exe\n"));
+        assert!(!output.contains("mb-last-seen"));
+        assert!(!output.contains("mb-clamav"));
+    }
+}
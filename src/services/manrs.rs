@@ -9,6 +9,8 @@
  */
 #![allow(non_snake_case)]
 
+use crate::config::MANRS_LMDB_PATH;
+use crate::core::body_limit::LimitedBody;
 use crate::storage::{SharedLmdbStorage, create_shared_storage};
 use crate::{log_debug, log_error, log_info, log_warn};
 use anyhow::Result;
@@ -18,7 +20,6 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 const MANRS_API_URL: &str = "https://api.manrs.org/asns";
-const MANRS_LMDB_PATH: &str = "./cache/manrs_lmdb";
 const CACHE_KEY: &str = "manrs_asns";
 const CACHE_TIMESTAMP_KEY: &str = "manrs_last_updated";
 const CACHE_DURATION_DAYS: u64 = 14;
@@ -104,7 +105,7 @@ impl ManrsChecker {
             ));
         }
 
-        let api_response: ManrsApiResponse = response.json().await?;
+        let api_response: ManrsApiResponse = response.limited_json().await?;
 
         log_info!(
             "Retrieved {} MANRS member ASNs from API",
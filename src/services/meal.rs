@@ -20,6 +20,7 @@ use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Write;
+use crate::core::body_limit::LimitedBody;
 
 #[derive(Debug, Deserialize, Serialize)]
 struct MealResponse {
@@ -136,7 +137,7 @@ pub async fn query_random_meal() -> Result<String> {
         ));
     }
 
-    let meal_response: MealResponse = response.json().await?;
+    let meal_response: MealResponse = response.limited_json().await?;
 
     if let Some(meals) = meal_response.meals
         && let Some(meal) = meals.first()
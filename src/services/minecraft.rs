@@ -1,10 +1,93 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
+use tokio::net::{TcpStream, UdpSocket};
+use crate::core::body_limit::LimitedBody;
+use crate::core::is_internal;
+use crate::services::gameserver::GameServerStatus;
+use crate::services::utils::doh::DohClient;
 use crate::{log_debug, log_error};
+
+/// Default Java Edition port
+const JAVA_PORT: u16 = 25565;
+/// Default Bedrock Edition port
+const BEDROCK_PORT: u16 = 19132;
+/// How long the optional Query protocol lookup may take
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+/// RakNet offline message magic
+const RAKNET_MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+/// Players, mods and plugins listed before the rest are summarised
+const LIST_LIMIT: usize = 10;
+
+/// How sampled player names are shown in -MC responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerPrivacy {
+    Show,
+    Mask,
+    Hide,
+}
+
+static PLAYER_PRIVACY: OnceLock<PlayerPrivacy> = OnceLock::new();
+
+/// Set how player names are shown (show, mask or hide)
+pub fn init_player_privacy(mode: &str) -> Result<()> {
+    let privacy = match mode.to_ascii_lowercase().as_str() {
+        "show" => PlayerPrivacy::Show,
+        "mask" => PlayerPrivacy::Mask,
+        "hide" => PlayerPrivacy::Hide,
+        _ => return Err(anyhow::anyhow!("Invalid Minecraft player privacy '{}', expected show, mask or hide", mode)),
+    };
+    let _ = PLAYER_PRIVACY.set(privacy);
+    Ok(())
+}
+
+fn player_privacy() -> PlayerPrivacy {
+    PLAYER_PRIVACY.get().copied().unwrap_or(PlayerPrivacy::Show)
+}
+
+/// Mask a player name, keeping its first and last character
+fn mask_player_name(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    if chars.len() <= 2 {
+        return "*".repeat(chars.len());
+    }
+    format!("{}{}{}", chars[0], "*".repeat(chars.len() - 2), chars[chars.len() - 1])
+}
+
+/// Player names as they may be shown, or `None` when hidden
+fn visible_players(names: &[String]) -> Option<Vec<String>> {
+    match player_privacy() {
+        PlayerPrivacy::Show => Some(names.to_vec()),
+        PlayerPrivacy::Mask => Some(names.iter().map(|name| mask_player_name(name)).collect()),
+        PlayerPrivacy::Hide => None,
+    }
+}
+
+/// Minecraft edition selected by the query (`bedrock:` prefix for Bedrock)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edition {
+    Java,
+    Bedrock,
+}
+
+/// Split an optional `bedrock:` / `be:` edition prefix off a target
+fn split_edition(target: &str) -> (Edition, &str) {
+    for prefix in ["bedrock:", "be:"] {
+        if let Some(head) = target.get(..prefix.len())
+            && head.eq_ignore_ascii_case(prefix)
+        {
+            return (Edition::Bedrock, &target[prefix.len()..]);
+        }
+    }
+    (Edition::Java, target)
+}
+
 /// Minecraft server status response structure
 #[derive(Debug, Deserialize, Serialize)]
 struct MinecraftStatus {
@@ -16,6 +99,68 @@ struct MinecraftStatus {
     enforces_secure_chat: Option<bool>,
     #[serde(rename = "previewsChat")]
     previews_chat: Option<bool>,
+    /// Mod list of Forge/NeoForge 1.13+ servers
+    #[serde(rename = "forgeData")]
+    forge_data: Option<ForgeData>,
+    /// Mod list of FML servers before 1.13
+    modinfo: Option<ModInfo>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ForgeData {
+    #[serde(default)]
+    mods: Vec<ForgeMod>,
+    /// Binary-encoded mod list sent by Forge 1.18+ instead of `mods`
+    #[serde(default)]
+    d: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ForgeMod {
+    #[serde(rename = "modId")]
+    mod_id: String,
+    #[serde(default)]
+    modmarker: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ModInfo {
+    #[serde(rename = "type", default)]
+    loader: String,
+    #[serde(rename = "modList", default)]
+    mod_list: Vec<FmlMod>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct FmlMod {
+    modid: String,
+    #[serde(default)]
+    version: String,
+}
+
+impl MinecraftStatus {
+    /// Mod loader and mods advertised in the status response
+    fn mods(&self) -> Option<(String, Vec<String>)> {
+        if let Some(forge) = &self.forge_data {
+            let mods = forge.mods
+                .iter()
+                .map(|m| match &m.modmarker {
+                    Some(marker) => format!("{} {}", m.mod_id, marker),
+                    None => m.mod_id.clone(),
+                })
+                .collect();
+            return Some(("Forge".to_string(), mods));
+        }
+        self.modinfo.as_ref().map(|info| {
+            let mods = info.mod_list.iter().map(|m| format!("{} {}", m.modid, m.version).trim_end().to_string()).collect();
+            (info.loader.clone(), mods)
+        })
+    }
+
+    /// Whether the mod list is only available in Forge's binary encoding
+    fn mods_encoded(&self) -> bool {
+        self.forge_data.as_ref().is_some_and(|forge| forge.mods.is_empty() && forge.d.is_some())
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -42,6 +187,7 @@ struct MinecraftPlayer {
 struct MinecraftServerInfo {
     address: String,
     port: u16,
+    edition: Edition,
     online: bool,
     version: String,
     protocol: i32,
@@ -52,6 +198,105 @@ struct MinecraftServerInfo {
     latency: u64,
     enforces_secure_chat: Option<bool>,
     previews_chat: Option<bool>,
+    /// SRV record the connection address came from
+    srv_record: Option<String>,
+    game_mode: Option<String>,
+    map: Option<String>,
+    /// Mod loader and mods (Forge/FML status data)
+    mods: Option<(String, Vec<String>)>,
+    mods_encoded: bool,
+    /// Server software and plugins (Query protocol)
+    software: Option<String>,
+    plugins: Option<Vec<String>>,
+}
+
+/// Full stat response of the GameSpy4 Query protocol (`enable-query`)
+#[derive(Debug, Default, PartialEq)]
+struct QueryStat {
+    values: BTreeMap<String, String>,
+    players: Vec<String>,
+}
+
+impl QueryStat {
+    /// Server software and plugins from the `plugins` value
+    /// ("Paper on 1.20.4: WorldEdit 7.2.15; Vault 1.7.3")
+    fn plugins(&self) -> (Option<String>, Vec<String>) {
+        let Some(value) = self.values.get("plugins").filter(|v| !v.trim().is_empty()) else {
+            return (None, Vec::new());
+        };
+        match value.split_once(':') {
+            Some((software, list)) => (
+                Some(software.trim().to_string()),
+                list.split(';').map(str::trim).filter(|p| !p.is_empty()).map(str::to_string).collect(),
+            ),
+            None => (Some(value.trim().to_string()), Vec::new()),
+        }
+    }
+}
+
+/// Parse a Query protocol full stat response
+fn parse_query_stat(data: &[u8]) -> Result<QueryStat> {
+    // Type (1) + session ID (4) + "splitnum\0\x80\0" padding (11)
+    if data.len() < 16 || data[0] != 0x00 {
+        return Err(anyhow::anyhow!("Invalid query full stat response"));
+    }
+    let mut fields = data[16..].split(|b| *b == 0).map(|f| String::from_utf8_lossy(f).into_owned());
+
+    let mut stat = QueryStat::default();
+    while let Some(key) = fields.next() {
+        if key.is_empty() {
+            break;
+        }
+        stat.values.insert(key, fields.next().unwrap_or_default());
+    }
+    // "\x01player_\0\0" introduces the player list
+    if fields.next().as_deref() == Some("\u{1}player_") {
+        fields.next();
+        stat.players = fields.take_while(|name| !name.is_empty()).collect();
+    }
+    Ok(stat)
+}
+
+/// Parse the `MCPE;motd;protocol;version;online;max;...` string of a Bedrock pong
+fn parse_bedrock_status(address: &str, port: u16, status: &str, latency: u64) -> Result<MinecraftServerInfo> {
+    let fields: Vec<&str> = status.split(';').collect();
+    if fields.len() < 6 || !matches!(fields[0], "MCPE" | "MCEE") {
+        return Err(anyhow::anyhow!("Invalid Bedrock status response"));
+    }
+    let field = |index: usize| fields.get(index).map(|f| f.trim()).filter(|f| !f.is_empty()).map(str::to_string);
+
+    Ok(MinecraftServerInfo {
+        address: address.to_string(),
+        port,
+        edition: Edition::Bedrock,
+        online: true,
+        version: fields[3].to_string(),
+        protocol: fields[2].parse().unwrap_or_default(),
+        players_online: fields[4].parse().unwrap_or_default(),
+        players_max: fields[5].parse().unwrap_or_default(),
+        player_list: Vec::new(),
+        description: fields[1].to_string(),
+        latency,
+        enforces_secure_chat: None,
+        previews_chat: None,
+        srv_record: None,
+        game_mode: field(8),
+        map: field(7),
+        mods: None,
+        mods_encoded: false,
+        software: None,
+        plugins: None,
+    })
+}
+
+/// Parse SRV record data ("priority weight port target.")
+fn parse_srv_data(data: &str) -> Option<(u16, u16, String)> {
+    let mut parts = data.split_whitespace();
+    let priority = parts.next()?.parse().ok()?;
+    let _weight: u16 = parts.next()?.parse().ok()?;
+    let port = parts.next()?.parse().ok()?;
+    let target = parts.next()?.trim_end_matches('.');
+    (!target.is_empty()).then(|| (priority, port, target.to_string()))
 }
 
 /// Minecraft server query service
@@ -74,18 +319,31 @@ impl MinecraftService {
     }
 
     /// Create Minecraft service with custom timeout
-    #[allow(dead_code)]
     pub fn with_timeout(timeout: Duration) -> Self {
         Self { timeout }
     }
 
     /// Query Minecraft server status
+    ///
+    /// Java Edition targets without a port follow the `_minecraft._tcp` SRV
+    /// record; `bedrock:host[:port]` pings a Bedrock Edition server instead.
     pub async fn query_minecraft(&self, target: &str) -> Result<String> {
         log_debug!("Querying Minecraft server: {}", target);
 
+        let (edition, target) = split_edition(target);
         let (host, port) = self.parse_minecraft_target(target)?;
+        let status = match edition {
+            Edition::Java => {
+                let srv = if target.contains(':') { None } else { self.resolve_srv(&host).await };
+                self.get_server_status(&host, port, srv).await
+            }
+            Edition::Bedrock => {
+                let port = if target.contains(':') { port } else { BEDROCK_PORT };
+                self.get_bedrock_status(&host, port).await
+            }
+        };
 
-        match self.get_server_status(&host, port).await {
+        match status {
             Ok(server_info) => {
                 let output = self.format_server_info(&server_info);
                 log_debug!(
@@ -121,17 +379,68 @@ impl MinecraftService {
             Ok((host, port))
         } else {
             // Default Minecraft port
-            Ok((target.to_string(), 25565))
+            Ok((target.to_string(), JAVA_PORT))
+        }
+    }
+
+    /// Java Edition status as a `-GAMESERVER` summary
+    pub(crate) async fn game_server_status(&self, host: &str, port: Option<u16>) -> Result<GameServerStatus> {
+        let srv = if port.is_some() { None } else { self.resolve_srv(host).await };
+        let info = self.get_server_status(host, port.unwrap_or(JAVA_PORT), srv).await?;
+        Ok(GameServerStatus {
+            protocol: "Minecraft Server List Ping",
+            name: info.description,
+            game: Some(match info.software {
+                Some(software) => format!("Minecraft ({})", software),
+                None => "Minecraft".to_string(),
+            }),
+            map: info.map,
+            version: Some(info.version),
+            players_online: info.players_online.max(0) as u32,
+            players_max: info.players_max.max(0) as u32,
+            bots: None,
+            players: visible_players(&info.player_list),
+            latency: info.latency,
+        })
+    }
+
+    /// Look up the `_minecraft._tcp` SRV record of a hostname
+    async fn resolve_srv(&self, host: &str) -> Option<(String, u16)> {
+        if host.parse::<IpAddr>().is_ok() {
+            return None;
         }
+        let name = format!("_minecraft._tcp.{}", host);
+        let response = match DohClient::new().query(&name, "SRV").await {
+            Ok(response) => response,
+            Err(e) => {
+                log_debug!("SRV lookup for {} failed: {}", name, e);
+                return None;
+            }
+        };
+        response.Answer
+            .unwrap_or_default()
+            .iter()
+            .filter(|answer| answer.record_type == 33)
+            .filter_map(|answer| parse_srv_data(&answer.data))
+            .min_by_key(|(priority, _, _)| *priority)
+            .map(|(_, port, target)| (target, port))
     }
 
     /// Get server status using Minecraft Server List Ping protocol
-    async fn get_server_status(&self, host: &str, port: u16) -> Result<MinecraftServerInfo> {
+    ///
+    /// `srv` redirects the connection; the handshake still names `host`.
+    async fn get_server_status(&self, host: &str, port: u16, srv: Option<(String, u16)>) -> Result<MinecraftServerInfo> {
         let start_time = Instant::now();
 
+        let srv_record = srv.as_ref().map(|(target, srv_port)| format!("_minecraft._tcp.{} -> {}:{}", host, target, srv_port));
+        let (connect_host, port) = match &srv {
+            Some((target, srv_port)) => (target.as_str(), *srv_port),
+            None => (host, port),
+        };
+
         // Resolve hostname to IP address
-        let socket_addr = self.resolve_address(host, port).await?;
-        log_debug!("Resolved {}:{} to {}", host, port, socket_addr);
+        let socket_addr = self.resolve_address(connect_host, port).await?;
+        log_debug!("Resolved {}:{} to {}", connect_host, port, socket_addr);
 
         // Connect to server with timeout
         let mut stream = tokio::time::timeout(self.timeout, TcpStream::connect(socket_addr))
@@ -157,29 +466,54 @@ impl MinecraftService {
         let status: MinecraftStatus = serde_json::from_str(&status_json)
             .map_err(|e| anyhow::anyhow!("Failed to parse server response: {}", e))?;
 
-        // Send ping request for latency measurement
-        let ping_start = Instant::now();
-        self.send_ping(&mut stream).await?;
-        self.read_ping_response(&mut stream).await?;
-        let ping_latency = ping_start.elapsed().as_millis() as u64;
+        // Send ping request for latency measurement, asking for the
+        // Query protocol's plugin list at the same time
+        let ping = async {
+            let ping_start = Instant::now();
+            self.send_ping(&mut stream).await?;
+            self.read_ping_response(&mut stream).await?;
+            Ok::<u64, anyhow::Error>(ping_start.elapsed().as_millis() as u64)
+        };
+        let (ping_latency, query_stat) = tokio::join!(ping, self.query_full_stat(socket_addr));
+        let ping_latency = ping_latency?;
+        let query_stat = match query_stat {
+            Ok(stat) => Some(stat),
+            Err(e) => {
+                log_debug!("Query protocol unavailable on {}: {}", socket_addr, e);
+                None
+            }
+        };
 
         let total_latency = start_time.elapsed().as_millis() as u64;
 
-        // Extract player list
-        let player_list = status
+        // Format description (can be string or object)
+        let description = self.format_description(&status.description);
+        let mods = status.mods();
+        let mods_encoded = status.mods_encoded();
+
+        // Extract player list, preferring the complete Query protocol list
+        let mut player_list: Vec<String> = status
             .players
             .sample
             .unwrap_or_default()
             .into_iter()
             .map(|p| p.name)
             .collect();
-
-        // Format description (can be string or object)
-        let description = self.format_description(&status.description);
+        let (software, plugins) = match &query_stat {
+            Some(stat) => {
+                if !stat.players.is_empty() {
+                    player_list = stat.players.clone();
+                }
+                let (software, plugins) = stat.plugins();
+                (software, Some(plugins))
+            }
+            None => (None, None),
+        };
 
         Ok(MinecraftServerInfo {
             address: host.to_string(),
             port,
+            edition: Edition::Java,
             online: true,
             version: status.version.name,
             protocol: status.version.protocol,
@@ -190,9 +524,89 @@ impl MinecraftService {
             latency: std::cmp::min(total_latency, ping_latency),
             enforces_secure_chat: status.enforces_secure_chat,
             previews_chat: status.previews_chat,
+            srv_record,
+            game_mode: query_stat.as_ref().and_then(|stat| stat.values.get("gametype").cloned()),
+            map: query_stat.as_ref().and_then(|stat| stat.values.get("map").cloned()),
+            mods,
+            mods_encoded,
+            software,
+            plugins,
         })
     }
 
+    /// Bind a UDP socket connected to `addr`
+    async fn udp_socket(&self, addr: SocketAddr) -> Result<UdpSocket> {
+        let bind = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(bind).await?;
+        socket.connect(addr).await?;
+        Ok(socket)
+    }
+
+    /// Send a datagram and wait for the reply
+    async fn udp_exchange(&self, socket: &UdpSocket, request: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+        socket.send(request).await?;
+        let mut buffer = vec![0u8; 65535];
+        let len = tokio::time::timeout(timeout, socket.recv(&mut buffer))
+            .await
+            .map_err(|_| anyhow::anyhow!("No response after {} seconds", timeout.as_secs()))??;
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+
+    /// Request the full stat of the GameSpy4 Query protocol (`enable-query`)
+    async fn query_full_stat(&self, addr: SocketAddr) -> Result<QueryStat> {
+        let socket = self.udp_socket(addr).await?;
+        let session = (rand::random::<u32>() & 0x0f0f_0f0f).to_be_bytes();
+
+        // Handshake: the reply carries a challenge token as an ASCII number
+        let mut handshake = vec![0xfe, 0xfd, 0x09];
+        handshake.extend_from_slice(&session);
+        let reply = self.udp_exchange(&socket, &handshake, QUERY_TIMEOUT).await?;
+        if reply.len() < 6 || reply[0] != 0x09 {
+            return Err(anyhow::anyhow!("Invalid query handshake response"));
+        }
+        let token: i32 = String::from_utf8_lossy(&reply[5..])
+            .trim_end_matches('\0')
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid query challenge token"))?;
+
+        let mut request = vec![0xfe, 0xfd, 0x00];
+        request.extend_from_slice(&session);
+        request.extend_from_slice(&token.to_be_bytes());
+        request.extend_from_slice(&[0, 0, 0, 0]);
+        let reply = self.udp_exchange(&socket, &request, QUERY_TIMEOUT).await?;
+        parse_query_stat(&reply)
+    }
+
+    /// Get Bedrock Edition server status using a RakNet unconnected ping
+    async fn get_bedrock_status(&self, host: &str, port: u16) -> Result<MinecraftServerInfo> {
+        let socket_addr = self.resolve_address(host, port).await?;
+        log_debug!("Resolved {}:{} to {}", host, port, socket_addr);
+        let socket = self.udp_socket(socket_addr).await?;
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut ping = vec![0x01];
+        ping.extend_from_slice(&timestamp.to_be_bytes());
+        ping.extend_from_slice(&RAKNET_MAGIC);
+        ping.extend_from_slice(&rand::random::<u64>().to_be_bytes());
+        let start_time = Instant::now();
+        let pong = self.udp_exchange(&socket, &ping, self.timeout).await?;
+        let latency = start_time.elapsed().as_millis() as u64;
+
+        // Unconnected pong: ID (1), time (8), server GUID (8), magic (16), string length (2)
+        if pong.len() < 35 || pong[0] != 0x1c || pong[17..33] != RAKNET_MAGIC {
+            return Err(anyhow::anyhow!("Invalid Bedrock pong packet"));
+        }
+        let length = u16::from_be_bytes([pong[33], pong[34]]) as usize;
+        let status = pong
+            .get(35..35 + length)
+            .ok_or_else(|| anyhow::anyhow!("Bedrock status exceeds packet length"))?;
+        parse_bedrock_status(host, port, &String::from_utf8_lossy(status), latency)
+    }
+
     /// Resolve hostname to socket address
     async fn resolve_address(&self, host: &str, port: u16) -> Result<SocketAddr> {
         let addr_str = format!("{}:{}", host, port);
@@ -203,9 +617,10 @@ impl MinecraftService {
             .map_err(|e| anyhow::anyhow!("DNS resolution task failed: {}", e))?
             .map_err(|e| anyhow::anyhow!("Failed to resolve hostname '{}': {}", host, e))?;
 
+        // Never probe this server's own network on a client's behalf
         addrs
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("No addresses found for hostname: {}", host))
+            .find(|addr| !is_internal(addr.ip()))
+            .ok_or_else(|| anyhow::anyhow!("{} does not resolve to a public address", host))
     }
 
     /// Send handshake packet (Protocol state: Status)
@@ -445,6 +860,17 @@ impl MinecraftService {
         output.push_str("server:         ");
         output.push_str(&format!("{}:{}\n", info.address, info.port));
 
+        if let Some(srv_record) = &info.srv_record {
+            output.push_str("srv-record:     ");
+            output.push_str(&format!("{}\n", srv_record));
+        }
+
+        output.push_str("edition:        ");
+        output.push_str(match info.edition {
+            Edition::Java => "Java\n",
+            Edition::Bedrock => "Bedrock\n",
+        });
+
         output.push_str("status:         ");
         output.push_str(&format!(
             "{}\n",
@@ -469,6 +895,16 @@ impl MinecraftService {
         output.push_str("latency:        ");
         output.push_str(&format!("{}ms\n", info.latency));
 
+        if let Some(game_mode) = &info.game_mode {
+            output.push_str("game-mode:      ");
+            output.push_str(&format!("{}\n", game_mode));
+        }
+
+        if let Some(map) = &info.map {
+            output.push_str("map:            ");
+            output.push_str(&format!("{}\n", map));
+        }
+
         if let Some(secure_chat) = info.enforces_secure_chat {
             output.push_str("secure-chat:    ");
             output.push_str(&format!(
@@ -485,21 +921,41 @@ impl MinecraftService {
             ));
         }
 
+        // Mods (Forge/FML) and plugins (Query protocol) when exposed
+        if let Some((loader, mods)) = &info.mods {
+            output.push_str("mod-loader:     ");
+            output.push_str(&format!("{}\n", loader));
+            if info.mods_encoded {
+                output.push_str("remarks:        ");
+                output.push_str("Mod list is sent in Forge's encoded format\n");
+            } else {
+                output.push_str("mods:           ");
+                output.push_str(&format!("{}\n", mods.len()));
+                self.format_list(&mut output, "mod:            ", mods, "mods");
+            }
+        }
+
+        if let Some(software) = &info.software {
+            output.push_str("software:       ");
+            output.push_str(&format!("{}\n", software));
+        }
+
+        if let Some(plugins) = &info.plugins {
+            output.push_str("plugins:        ");
+            output.push_str(&format!("{}\n", plugins.len()));
+            self.format_list(&mut output, "plugin:         ", plugins, "plugins");
+        }
+
         // Player list in RIPE-style (if available)
         if !info.player_list.is_empty() {
-            for (i, player) in info.player_list.iter().enumerate() {
-                if i >= 10 {
+            match visible_players(&info.player_list) {
+                Some(players) => self.format_list(&mut output, "player:         ", &players, "players online"),
+                None => {
                     output.push_str("remarks:        ");
-                    output.push_str(&format!(
-                        "... and {} more players online\n",
-                        info.player_list.len() - 10
-                    ));
-                    break;
+                    output.push_str("Player names hidden by WHOIS server policy\n");
                 }
-                output.push_str("player:         ");
-                output.push_str(&format!("{}\n", player));
             }
-        } else if info.players_online > 0 {
+        } else if info.players_online > 0 && info.edition == Edition::Java {
             output.push_str("remarks:        ");
             output.push_str("Player list hidden by server configuration\n");
         }
@@ -508,12 +964,27 @@ impl MinecraftService {
         output.push_str("source:         AKAERE-NETWORKS-AGENT\n");
 
         output.push('\n');
-        output.push_str("% Information retrieved using Minecraft Server List Ping protocol\n");
+        match info.edition {
+            Edition::Java => output.push_str("% Information retrieved using Minecraft Server List Ping protocol\n"),
+            Edition::Bedrock => output.push_str("% Information retrieved using RakNet unconnected ping\n"),
+        }
         output.push_str("% Query processed by WHOIS server\n");
 
         output
     }
 
+    /// Append up to ten list entries under `attribute`, summarising the rest
+    fn format_list(&self, output: &mut String, attribute: &str, entries: &[String], noun: &str) {
+        for entry in entries.iter().take(LIST_LIMIT) {
+            output.push_str(attribute);
+            output.push_str(&format!("{}\n", entry));
+        }
+        if entries.len() > LIST_LIMIT {
+            output.push_str("remarks:        ");
+            output.push_str(&format!("... and {} more {}\n", entries.len() - LIST_LIMIT, noun));
+        }
+    }
+
     /// Check if a query string is a Minecraft query
     #[allow(dead_code)]
     pub fn is_minecraft_query(query: &str) -> bool {
@@ -640,7 +1111,7 @@ impl MinecraftUserService {
             return Err(anyhow::anyhow!("API request failed: {}", response.status()));
         }
 
-        let uuid_response: MinecraftUuidResponse = response.json().await?;
+        let uuid_response: MinecraftUuidResponse = response.limited_json().await?;
         Ok(uuid_response)
     }
 
@@ -660,7 +1131,7 @@ impl MinecraftUserService {
             ));
         }
 
-        let profile: MinecraftUserProfile = response.json().await?;
+        let profile: MinecraftUserProfile = response.limited_json().await?;
         Ok(profile)
     }
 
@@ -905,4 +1376,56 @@ mod tests {
         let custom_service = MinecraftService::with_timeout(Duration::from_secs(5));
         assert_eq!(custom_service.timeout, Duration::from_secs(5));
     }
+
+    #[test]
+    fn test_edition_srv_and_masking() {
+        assert_eq!(split_edition("bedrock:play.example.net"), (Edition::Bedrock, "play.example.net"));
+        assert_eq!(split_edition("BE:play.example.net:19133"), (Edition::Bedrock, "play.example.net:19133"));
+        assert_eq!(split_edition("mc.hypixel.net"), (Edition::Java, "mc.hypixel.net"));
+
+        assert_eq!(parse_srv_data("0 5 25566 mc.example.net."), Some((0, 25566, "mc.example.net".to_string())));
+        assert_eq!(parse_srv_data("0 5 25566 ."), None);
+
+        assert_eq!(mask_player_name("Notch"), "N***h");
+        assert_eq!(mask_player_name("ab"), "**");
+        assert!(init_player_privacy("blur").is_err());
+    }
+
+    #[test]
+    fn test_bedrock_and_query_parsing() {
+        let status = "MCPE;Dedicated Server;589;1.20.0;3;10;13253860892328930865;Bedrock level;Survival;1;19132;19133;";
+        let info = parse_bedrock_status("play.example.net", 19132, status, 12).unwrap();
+        assert_eq!(info.version, "1.20.0");
+        assert_eq!((info.players_online, info.players_max), (3, 10));
+        assert_eq!(info.map.as_deref(), Some("Bedrock level"));
+        assert_eq!(info.game_mode.as_deref(), Some("Survival"));
+        assert!(parse_bedrock_status("play.example.net", 19132, "MCPE;short", 0).is_err());
+
+        let mut data = vec![0x00, 0, 0, 0, 1];
+        data.extend_from_slice(b"splitnum\0\x80\0");
+        data.extend_from_slice(b"hostname\0A Server\0plugins\0Paper on 1.20.4: WorldEdit 7.2.15; Vault 1.7.3\0map\0world\0\0");
+        data.extend_from_slice(b"\x01player_\0\0Notch\0jeb_\0\0");
+        let stat = parse_query_stat(&data).unwrap();
+        assert_eq!(stat.values.get("map").map(String::as_str), Some("world"));
+        assert_eq!(stat.players, vec!["Notch", "jeb_"]);
+        let (software, plugins) = stat.plugins();
+        assert_eq!(software.as_deref(), Some("Paper on 1.20.4"));
+        assert_eq!(plugins, vec!["WorldEdit 7.2.15", "Vault 1.7.3"]);
+    }
+
+    #[test]
+    fn test_status_mods() {
+        let forge: MinecraftStatus = serde_json::from_str(
+            r#"{"version":{"name":"1.16.5","protocol":754},"players":{"max":20,"online":0},"description":"A Forge server",
+                "forgeData":{"channels":[],"mods":[{"modId":"forge","modmarker":"36.2.0"},{"modId":"jei","modmarker":"7.7.1"}],"fmlNetworkVersion":2}}"#,
+        ).unwrap();
+        assert_eq!(forge.mods(), Some(("Forge".to_string(), vec!["forge 36.2.0".to_string(), "jei 7.7.1".to_string()])));
+        assert!(!forge.mods_encoded());
+
+        let fml: MinecraftStatus = serde_json::from_str(
+            r#"{"version":{"name":"1.12.2","protocol":340},"players":{"max":20,"online":0},"description":{"text":"FML"},
+                "modinfo":{"type":"FML","modList":[{"modid":"minecraft","version":"1.12.2"}]}}"#,
+        ).unwrap();
+        assert_eq!(fml.mods(), Some(("FML".to_string(), vec!["minecraft 1.12.2".to_string()])));
+    }
 }
@@ -1,34 +1,77 @@
+pub mod abuse;
 pub mod acgc;
+pub mod annotations;
+pub mod aspa;
+pub mod asset;
 pub mod bgptool;
+pub mod bogon;
 pub mod utils;
 pub mod cfstatus;
 pub mod crt;
+pub mod crtwatch;
 pub mod desc;
+pub mod digest;
 pub mod dns;
+pub mod dnssec;
+pub mod dualstack;
 pub mod email;
+pub mod expiry;
+pub mod fingerprint;
+pub mod gameserver;
+#[cfg(test)]
+pub(crate) mod fixtures;
 pub mod geo;
 pub mod github;
 pub mod help;
+pub mod hibp;
+pub mod history;
+pub mod homoglyph;
 pub mod icp;
 pub mod iana_cache;
 pub mod imdb;
+pub mod ipam;
 pub mod irr;
+pub mod ix;
+pub mod latmatrix;
 pub mod looking_glass;
 pub mod lyric;
+pub mod malware;
 pub mod manrs;
 pub mod meal;
 pub mod minecraft;
+pub mod monitor;
+pub mod mtr;
+pub mod neighbors;
+pub mod notify;
 pub mod ntp;
 pub mod packages;
+pub mod peerconf;
 pub mod ping;
+pub mod port;
+pub mod prefixlist;
+pub mod probes;
+pub mod psl;
 pub mod peeringdb;
 pub mod pen;
 pub mod pixiv;
 pub mod rdap;
+pub mod registrar;
+pub mod registry;
+pub mod report;
+pub mod roa;
 pub mod rpki;
+pub mod setop;
+pub mod speedtest;
 pub mod ssl;
+pub mod sshkeys;
 pub mod steam;
+pub mod tld_bootstrap;
+pub mod tmdb;
 pub mod traceroute;
+pub mod translate;
+pub mod upstream;
+pub mod urlscan;
+pub mod verify;
 pub mod whois;
 pub mod wikipedia;
 
@@ -55,6 +98,7 @@ pub use minecraft::*;
 pub use ntp::*;
 pub use packages::*;
 pub use ping::*;
+pub use probes::*;
 pub use peeringdb::*;
 pub use pen::*;
 // pub use pixiv::*; // Pixiv implementation used via explicit imports
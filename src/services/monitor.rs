@@ -0,0 +1,394 @@
+// WHOIS Server - ASN Monitoring
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! BGP adjacency and origin monitoring for operators' own networks
+//!
+//! Networks are registered in a TOML file (`--monitor-file`):
+//!
+//! ```toml
+//! interval_minutes = 15
+//!
+//! [[networks]]
+//! asn = 213605
+//! webhook = "https://hooks.example.net/bgp"
+//! # Prefixes to watch; defaults to those the ASN announces
+//! prefixes = ["2a0e:b107:1170::/48"]
+//! # Other ASNs allowed to originate them (e.g. a DDoS scrubbing provider)
+//! allowed_origins = [19551]
+//!
+//! # Further channels, see `services::notify`
+//! [[networks.notify]]
+//! type = "telegram"
+//! bot_token = "123456:ABC..."
+//! chat_id = "-1001234567890"
+//! ```
+//!
+//! Every interval the RIPEstat neighbours of each ASN and the origins of its
+//! prefixes are compared with the previous snapshot, kept in LMDB so a
+//! restart does not re-announce everything. New or vanished upstreams,
+//! downstreams and peers, and prefixes originated by an ASN that is neither
+//! the network nor an allowed origin (possible hijacks), are POSTed as JSON
+//! to the network's webhook and sent to its other notification channels.
+//! The first snapshot of a network only reports unexpected origins.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::MONITOR_LMDB_PATH;
+use crate::core::body_limit::LimitedBody;
+use crate::core::scheduler::{Job, Schedule};
+use crate::services::notify::{ChannelSpec, Notification, Notifier, validate_channels};
+use crate::services::upstream::api_base;
+use crate::storage::lmdb::LmdbStorage;
+use crate::{log_debug, log_info, log_warn};
+
+const RIPESTAT_DATA_BASE: &str = "https://stat.ripe.net/data/";
+/// Neighbours seen by fewer RIS peers than this are ignored as noise
+const MIN_NEIGHBOUR_POWER: u64 = 2;
+/// Announced prefixes checked per network when none are configured
+const MAX_AUTO_PREFIXES: usize = 50;
+const MIN_INTERVAL_MINUTES: u64 = 5;
+
+fn default_interval() -> u64 {
+    15
+}
+
+#[derive(Deserialize)]
+struct MonitorFile {
+    #[serde(default = "default_interval")]
+    interval_minutes: u64,
+    #[serde(default)]
+    networks: Vec<MonitoredNetwork>,
+}
+
+/// A network registered for monitoring
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MonitoredNetwork {
+    pub asn: u32,
+    #[serde(default)]
+    pub webhook: String,
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    #[serde(default)]
+    pub allowed_origins: Vec<u32>,
+    #[serde(default)]
+    pub notify: Vec<ChannelSpec>,
+}
+
+impl MonitoredNetwork {
+    /// The webhook followed by the other notification channels
+    fn channels(&self) -> Vec<ChannelSpec> {
+        let webhook = (!self.webhook.is_empty()).then(|| ChannelSpec::webhook(&self.webhook));
+        webhook.into_iter().chain(self.notify.iter().cloned()).collect()
+    }
+}
+
+/// Monitoring configuration from `--monitor-file`
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    pub interval: Duration,
+    pub networks: Vec<MonitoredNetwork>,
+}
+
+/// Parse monitoring configuration from TOML
+pub fn parse_monitor_config(content: &str) -> Result<MonitorConfig> {
+    let file: MonitorFile = toml::from_str(content)?;
+    if file.interval_minutes < MIN_INTERVAL_MINUTES {
+        return Err(anyhow!("interval_minutes must be at least {}", MIN_INTERVAL_MINUTES));
+    }
+    for network in &file.networks {
+        if network.asn == 0 {
+            return Err(anyhow!("AS0 cannot be monitored"));
+        }
+        if network.webhook.is_empty() && network.notify.is_empty() {
+            return Err(anyhow!("AS{} needs a webhook or notify channel", network.asn));
+        }
+        validate_channels(&network.channels()).map_err(|e| anyhow!("AS{}: {}", network.asn, e))?;
+        if file.networks.iter().filter(|n| n.asn == network.asn).count() > 1 {
+            return Err(anyhow!("AS{} is listed more than once", network.asn));
+        }
+    }
+    Ok(MonitorConfig {
+        interval: Duration::from_secs(file.interval_minutes * 60),
+        networks: file.networks,
+    })
+}
+
+/// Load monitoring configuration from a TOML file
+pub fn load_monitor_file(path: &Path) -> Result<MonitorConfig> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read monitor file {}: {}", path.display(), e))?;
+    parse_monitor_config(&content).map_err(|e| anyhow!("Invalid monitor file {}: {}", path.display(), e))
+}
+
+/// Public BGP view of a network at one point in time
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Snapshot {
+    /// Neighbour ASN -> relation (upstream, downstream or peer)
+    neighbours: BTreeMap<u32, String>,
+    /// Prefix -> origin ASNs
+    origins: BTreeMap<String, BTreeSet<u32>>,
+}
+
+/// A change worth telling the operator about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum MonitorAlert {
+    NeighbourAdded { neighbour: u32, relation: String },
+    NeighbourRemoved { neighbour: u32, relation: String },
+    UnexpectedOrigin { prefix: String, origin: u32 },
+}
+
+impl MonitorAlert {
+    fn message(&self, asn: u32) -> String {
+        match self {
+            MonitorAlert::NeighbourAdded { neighbour, relation } => {
+                format!("AS{}: new {} AS{}", asn, relation, neighbour)
+            }
+            MonitorAlert::NeighbourRemoved { neighbour, relation } => {
+                format!("AS{}: {} AS{} is no longer seen", asn, relation, neighbour)
+            }
+            MonitorAlert::UnexpectedOrigin { prefix, origin } => {
+                format!("AS{}: {} is originated by AS{} (possible hijack)", asn, prefix, origin)
+            }
+        }
+    }
+}
+
+/// RIPEstat neighbour type to relation
+fn relation(kind: &str) -> &'static str {
+    match kind {
+        "left" => "upstream",
+        "right" => "downstream",
+        _ => "peer",
+    }
+}
+
+/// Alerts for the change from `old` to `new`
+fn diff_snapshots(network: &MonitoredNetwork, old: Option<&Snapshot>, new: &Snapshot) -> Vec<MonitorAlert> {
+    let mut alerts = Vec::new();
+    if let Some(old) = old {
+        for (neighbour, relation) in &new.neighbours {
+            if !old.neighbours.contains_key(neighbour) {
+                alerts.push(MonitorAlert::NeighbourAdded { neighbour: *neighbour, relation: relation.clone() });
+            }
+        }
+        for (neighbour, relation) in &old.neighbours {
+            if !new.neighbours.contains_key(neighbour) {
+                alerts.push(MonitorAlert::NeighbourRemoved { neighbour: *neighbour, relation: relation.clone() });
+            }
+        }
+    }
+
+    let expected = |origin: &u32| *origin == network.asn || network.allowed_origins.contains(origin);
+    for (prefix, origins) in &new.origins {
+        let known = old.and_then(|old| old.origins.get(prefix));
+        for origin in origins.iter().filter(|o| !expected(o)) {
+            if !known.is_some_and(|known| known.contains(origin)) {
+                alerts.push(MonitorAlert::UnexpectedOrigin { prefix: prefix.clone(), origin: *origin });
+            }
+        }
+    }
+    alerts
+}
+
+async fn ripestat(client: &reqwest::Client, endpoint: &str, resource: &str) -> Result<Value> {
+    let url = format!("{}{}/data.json", api_base(RIPESTAT_DATA_BASE), endpoint);
+    let response = client.get(&url).query(&[("resource", resource)]).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("RIPEstat {} failed: HTTP {}", endpoint, response.status()));
+    }
+    let body: Value = response.limited_json().await?;
+    Ok(body["data"].clone())
+}
+
+async fn fetch_neighbours(client: &reqwest::Client, asn: u32) -> Result<BTreeMap<u32, String>> {
+    let data = ripestat(client, "asn-neighbours", &format!("AS{}", asn)).await?;
+    let neighbours = data["neighbours"].as_array().cloned().unwrap_or_default();
+    Ok(neighbours
+        .iter()
+        .filter(|n| n["power"].as_u64().unwrap_or(0) >= MIN_NEIGHBOUR_POWER)
+        .filter_map(|n| {
+            let asn = u32::try_from(n["asn"].as_u64()?).ok()?;
+            Some((asn, relation(n["type"].as_str().unwrap_or_default()).to_string()))
+        })
+        .collect())
+}
+
+async fn fetch_announced_prefixes(client: &reqwest::Client, asn: u32) -> Result<Vec<String>> {
+    let data = ripestat(client, "announced-prefixes", &format!("AS{}", asn)).await?;
+    Ok(data["prefixes"]
+        .as_array()
+        .map(|prefixes| {
+            prefixes.iter().filter_map(|p| p["prefix"].as_str().map(str::to_string)).take(MAX_AUTO_PREFIXES).collect()
+        })
+        .unwrap_or_default())
+}
+
+async fn fetch_origins(client: &reqwest::Client, prefix: &str) -> Result<BTreeSet<u32>> {
+    let data = ripestat(client, "prefix-overview", prefix).await?;
+    Ok(data["asns"]
+        .as_array()
+        .map(|asns| asns.iter().filter_map(|a| u32::try_from(a["asn"].as_u64()?).ok()).collect())
+        .unwrap_or_default())
+}
+
+async fn take_snapshot(client: &reqwest::Client, network: &MonitoredNetwork) -> Result<Snapshot> {
+    let neighbours = fetch_neighbours(client, network.asn).await?;
+    let prefixes = if network.prefixes.is_empty() {
+        fetch_announced_prefixes(client, network.asn).await?
+    } else {
+        network.prefixes.clone()
+    };
+
+    let mut origins = BTreeMap::new();
+    for prefix in prefixes {
+        match fetch_origins(client, &prefix).await {
+            Ok(asns) => {
+                origins.insert(prefix, asns);
+            }
+            Err(e) => log_warn!("Monitor: origin lookup for {} failed: {}", prefix, e),
+        }
+    }
+    Ok(Snapshot { neighbours, origins })
+}
+
+fn notification(network: &MonitoredNetwork, alerts: &[MonitorAlert]) -> Notification {
+    let text = alerts.iter().map(|a| a.message(network.asn)).collect::<Vec<_>>().join("\n");
+    Notification::new(format!("AS{}: {} BGP change(s)", network.asn, alerts.len()), text)
+        .with_data(json!({ "asn": network.asn, "alerts": alerts }))
+}
+
+async fn check_network(
+    client: &reqwest::Client,
+    storage: &LmdbStorage,
+    network: &MonitoredNetwork,
+    notifier: &Notifier,
+) -> Result<usize> {
+    let key = format!("snapshot:AS{}", network.asn);
+    let old: Option<Snapshot> = storage.get_json(&key)?;
+    let new = take_snapshot(client, network).await?;
+
+    let alerts = diff_snapshots(network, old.as_ref(), &new);
+    if !alerts.is_empty() {
+        for alert in &alerts {
+            log_info!("Monitor: {}", alert.message(network.asn));
+        }
+        // Keep the old snapshot if a channel fails so the alerts are sent again next time
+        notifier.deliver(&notification(network, &alerts)).await?;
+    }
+    storage.put_json(&key, &new)?;
+    Ok(alerts.len())
+}
+
+/// The `asn-monitor` job checking the configured networks
+pub fn monitor_job(config: MonitorConfig) -> Result<Job> {
+    log_info!(
+        "Starting ASN monitoring for {} network(s) (every {} minutes)",
+        config.networks.len(),
+        config.interval.as_secs() / 60
+    );
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent("whois-server/1.0")
+        .build()?;
+    let mut networks = Vec::new();
+    for network in config.networks {
+        match Notifier::new(&network.channels()) {
+            Ok(notifier) => networks.push((network, notifier)),
+            Err(e) => log_warn!("Monitor: notification channels of AS{} unusable: {}", network.asn, e),
+        }
+    }
+    let networks = Arc::new(networks);
+
+    let job = Job::new("asn-monitor", Schedule::Every(config.interval), move || {
+        let client = client.clone();
+        let networks = networks.clone();
+        async move {
+            let storage = LmdbStorage::new(MONITOR_LMDB_PATH)?;
+            for (network, notifier) in networks.iter() {
+                match check_network(&client, &storage, network, notifier).await {
+                    Ok(alerts) => log_debug!("Monitor: AS{} checked, {} alert(s)", network.asn, alerts),
+                    Err(e) => log_warn!("Monitor: check of AS{} failed: {}", network.asn, e),
+                }
+            }
+            Ok(())
+        }
+    });
+    Ok(job.run_at_start())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::fixtures::FixtureServer;
+
+    fn network() -> MonitoredNetwork {
+        MonitoredNetwork {
+            asn: 213605,
+            webhook: "https://hooks.example.net/bgp".to_string(),
+            prefixes: Vec::new(),
+            allowed_origins: vec![19551],
+            notify: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_monitor_config() {
+        let config = parse_monitor_config(
+            "interval_minutes = 30\n\n[[networks]]\nasn = 213605\nwebhook = \"https://hooks.example.net/bgp\"\nallowed_origins = [19551]\n"
+        ).unwrap();
+        assert_eq!(config.interval, Duration::from_secs(1800));
+        assert_eq!(config.networks, vec![network()]);
+
+        assert!(parse_monitor_config("interval_minutes = 1").is_err());
+        assert!(parse_monitor_config("[[networks]]\nasn = 213605\nwebhook = \"ftp://example.net\"\n").is_err());
+        assert!(parse_monitor_config("[[networks]]\nasn = 213605\n").is_err());
+
+        let config = parse_monitor_config(
+            "[[networks]]\nasn = 213605\n\n[[networks.notify]]\ntype = \"discord\"\nwebhook_url = \"https://discord.com/api/webhooks/1/x\"\n"
+        ).unwrap();
+        assert_eq!(config.networks[0].channels().len(), 1);
+    }
+
+    #[test]
+    fn test_diff_snapshots() {
+        let old = Snapshot {
+            neighbours: BTreeMap::from([(6939, "upstream".to_string()), (1299, "upstream".to_string())]),
+            origins: BTreeMap::from([("2a0e:b107:1170::/48".to_string(), BTreeSet::from([213605]))]),
+        };
+        let new = Snapshot {
+            neighbours: BTreeMap::from([(6939, "upstream".to_string()), (34927, "peer".to_string())]),
+            origins: BTreeMap::from([("2a0e:b107:1170::/48".to_string(), BTreeSet::from([213605, 19551, 64512]))]),
+        };
+
+        assert_eq!(diff_snapshots(&network(), Some(&old), &new), vec![
+            MonitorAlert::NeighbourAdded { neighbour: 34927, relation: "peer".to_string() },
+            MonitorAlert::NeighbourRemoved { neighbour: 1299, relation: "upstream".to_string() },
+            MonitorAlert::UnexpectedOrigin { prefix: "2a0e:b107:1170::/48".to_string(), origin: 64512 },
+        ]);
+
+        // The first snapshot is a baseline for neighbours, but hijacks are reported
+        assert_eq!(diff_snapshots(&network(), None, &new).len(), 1);
+        // An unexpected origin is reported once
+        assert!(diff_snapshots(&network(), Some(&new), &new).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_neighbours_fixture() {
+        let mut fixtures = FixtureServer::start("monitor").await;
+        fixtures.serve(RIPESTAT_DATA_BASE, "asn-neighbours/data.json?resource=AS213605", "asn_neighbours.json").await;
+
+        let client = reqwest::Client::new();
+        let neighbours = fixtures.run(fetch_neighbours(&client, 213605)).await.unwrap();
+        assert_eq!(neighbours.get(&6939).map(String::as_str), Some("upstream"));
+        assert_eq!(neighbours.get(&34927).map(String::as_str), Some("peer"));
+        assert!(!neighbours.contains_key(&64500));
+    }
+}
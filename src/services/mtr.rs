@@ -0,0 +1,333 @@
+// WHOIS Server - MTR Report
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! mtr-style path report (`-MTR`)
+//!
+//! Runs several Globalping traceroute rounds from the same probe and
+//! aggregates them per hop into packet loss, best/avg/worst RTT and jitter,
+//! which shows where along the path loss and latency variation start. The
+//! first round picks the probe; the remaining rounds are pinned to its
+//! country, city and ASN and run concurrently.
+//!
+//! Like `-TRACE`, a location code picks the probe: `1.1.1.1-de-MTR`.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::traceroute::parse_location_query;
+use crate::services::utils::globalping::{ProbeInfo, TestResult};
+use crate::services::utils::table::format_table;
+use crate::services::utils::{
+    GlobalpingClient,
+    GlobalpingRequest,
+    GlobalpingResult,
+    MeasurementLocation,
+    MeasurementOptions,
+    TracerouteOptions,
+};
+use crate::{log_debug, log_error, log_warn};
+
+/// Traceroute rounds aggregated into one report
+const ROUNDS: usize = 5;
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// Aggregated statistics of one hop over all rounds
+#[derive(Debug, Default, PartialEq)]
+struct HopStats {
+    /// Distinct responding hosts, in order of appearance
+    hosts: Vec<String>,
+    sent: usize,
+    /// Replies in round order
+    rtts: Vec<f64>,
+}
+
+impl HopStats {
+    fn loss_percent(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        ((self.sent - self.rtts.len().min(self.sent)) as f64) * 100.0 / (self.sent as f64)
+    }
+
+    fn best(&self) -> Option<f64> {
+        self.rtts.iter().copied().reduce(f64::min)
+    }
+
+    fn worst(&self) -> Option<f64> {
+        self.rtts.iter().copied().reduce(f64::max)
+    }
+
+    fn avg(&self) -> Option<f64> {
+        (!self.rtts.is_empty()).then(|| self.rtts.iter().sum::<f64>() / (self.rtts.len() as f64))
+    }
+
+    /// Mean difference between consecutive replies, as mtr's `Javg`
+    fn jitter(&self) -> Option<f64> {
+        (self.rtts.len() >= 2).then(|| {
+            let total: f64 = self.rtts.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum();
+            total / ((self.rtts.len() - 1) as f64)
+        })
+    }
+}
+
+/// Combine traceroute rounds hop by hop
+///
+/// A round sends as many packets per hop as its best-answering hop received,
+/// so silent hops (`* * *`) count as fully lost.
+fn aggregate(rounds: &[&TestResult]) -> Vec<HopStats> {
+    let mut hops: Vec<HopStats> = Vec::new();
+    for round in rounds {
+        let Some(round_hops) = &round.hops else {
+            continue;
+        };
+        let per_hop = round_hops
+            .iter()
+            .map(|hop| hop.timings.as_ref().map_or(0, Vec::len))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        if hops.len() < round_hops.len() {
+            hops.resize_with(round_hops.len(), HopStats::default);
+        }
+        for (stats, hop) in hops.iter_mut().zip(round_hops) {
+            stats.sent += per_hop;
+            stats.rtts.extend(hop.timings.iter().flatten().map(|t| t.rtt));
+            if let Some(address) = hop.resolved_address.as_deref() {
+                let host = match hop.resolved_hostname.as_deref() {
+                    Some(hostname) if hostname != address => format!("{} ({})", hostname, address),
+                    _ => address.to_string(),
+                };
+                if !stats.hosts.contains(&host) {
+                    stats.hosts.push(host);
+                }
+            }
+        }
+    }
+    hops
+}
+
+fn format_ms(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |ms| format!("{:.1}", ms))
+}
+
+fn format_report(target: &str, probe: &ProbeInfo, rounds: &[&TestResult]) -> String {
+    let hops = aggregate(rounds);
+    let mut output = String::new();
+
+    output.push_str(&format!("% MTR report for {}\n", target));
+    if let Some(address) = rounds.iter().find_map(|r| r.resolved_address.as_deref()) {
+        output.push_str(&format!("% Resolved to: {}\n", address));
+    }
+    output.push_str(&format!(
+        "% Probe: {} - {}, {} (AS{})\n",
+        probe.network,
+        probe.city.as_deref().unwrap_or("Unknown"),
+        probe.country,
+        probe.asn
+    ));
+    output.push_str(&format!("% {} of {} ICMP traceroute rounds completed\n", rounds.len(), ROUNDS));
+    output.push('\n');
+
+    if hops.is_empty() {
+        output.push_str("% No hops data available in traceroute results\n");
+    } else {
+        let rows: Vec<Vec<String>> = hops
+            .iter()
+            .enumerate()
+            .map(|(i, hop)| {
+                let host = match hop.hosts.as_slice() {
+                    [] => "???".to_string(),
+                    [host] => host.clone(),
+                    [host, others @ ..] => format!("{} (+{} more)", host, others.len()),
+                };
+                vec![
+                    (i + 1).to_string(),
+                    host,
+                    format!("{:.1}%", hop.loss_percent()),
+                    hop.sent.to_string(),
+                    format_ms(hop.best()),
+                    format_ms(hop.avg()),
+                    format_ms(hop.worst()),
+                    format_ms(hop.jitter()),
+                ]
+            })
+            .collect();
+        output.push_str(&format_table(&["hop", "host", "loss", "snt", "best", "avg", "worst", "jitter"], &rows));
+    }
+
+    output.push('\n');
+    output.push_str("% RTTs in ms; jitter is the mean difference between consecutive replies\n");
+    output.push_str("% Loss at an intermediate hop that does not continue to later hops is usually ICMP rate limiting\n");
+    output.push_str("% Data provided by Globalping (globalping.io)\n");
+    output
+}
+
+fn traceroute_request(target: &str, location: Option<MeasurementLocation>) -> GlobalpingRequest {
+    GlobalpingRequest {
+        measurement_type: "traceroute".to_string(),
+        target: target.to_string(),
+        limit: Some(1),
+        measurement_options: Some(MeasurementOptions::Traceroute(TracerouteOptions::default())),
+        locations: location.map(|location| vec![location]),
+        in_progress_updates: Some(false),
+    }
+}
+
+/// Run one traceroute round and wait for it to finish
+async fn run_round(globalping: &GlobalpingClient, request: &GlobalpingRequest, budget: Duration) -> Result<GlobalpingResult> {
+    let measurement_id = globalping.submit_measurement(request).await?;
+    globalping.wait_for_results(&measurement_id, budget.as_secs().max(1)).await
+}
+
+/// Trace `query` (`target` or `target-location`) several times and report per-hop statistics
+pub async fn process_mtr_query(ctx: &RequestContext, query: &str) -> Result<String> {
+    let (target, location) = parse_location_query(query.trim())?;
+    if target.is_empty() || target.contains(char::is_whitespace) {
+        return Ok(format!("Invalid MTR target: {}\nUse a host or IP, e.g. 1.1.1.1-MTR or 1.1.1.1-de-MTR\n", target));
+    }
+
+    let globalping = match GlobalpingClient::new() {
+        Ok(client) => client,
+        Err(e) => {
+            log_error!("Failed to initialize Globalping client: {}", e);
+            return Ok(format!("MTR service error: {}\n", e));
+        }
+    };
+
+    let budget = || ctx.remaining().map_or(MAX_WAIT, |left| left.min(MAX_WAIT));
+    let first_location = location.as_deref().map(MeasurementLocation::magic);
+    let first = match run_round(&globalping, &traceroute_request(target, first_location), budget()).await {
+        Ok(results) => results,
+        Err(e) => {
+            log_error!("MTR first round to {} failed: {}", target, e);
+            return Ok(format!("MTR measurement timed out or failed: {}\n", e));
+        }
+    };
+    let Some(first) = first.results.into_iter().next() else {
+        return Ok(format!("No results received for MTR to {}\n", target));
+    };
+    log_debug!("[{}] MTR to {} uses probe {} ({}, AS{})", ctx, target, first.probe.network, first.probe.country, first.probe.asn);
+
+    // Later rounds pinned to the first round's probe
+    let pinned = MeasurementLocation {
+        country: Some(first.probe.country.clone()),
+        city: first.probe.city.clone(),
+        asn: Some(first.probe.asn),
+        ..Default::default()
+    };
+    let request = traceroute_request(target, Some(pinned));
+    let later = futures::future::join_all((1..ROUNDS).map(|_| run_round(&globalping, &request, budget()))).await;
+
+    let mut rounds = vec![&first.result];
+    let later_results: Vec<GlobalpingResult> = later
+        .into_iter()
+        .filter_map(|round| match round {
+            Ok(results) => Some(results),
+            Err(e) => {
+                log_warn!("[{}] MTR round to {} failed: {}", ctx, target, e);
+                None
+            }
+        })
+        .collect();
+    rounds.extend(later_results.iter().flat_map(|results| results.results.first()).map(|r| &r.result));
+
+    Ok(format_report(target, &first.probe, &rounds))
+}
+
+/// `-MTR`: per-hop loss and latency over several traceroute rounds
+pub struct MtrService;
+
+#[async_trait]
+impl QueryService for MtrService {
+    fn name(&self) -> &'static str {
+        "mtr"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-MTR"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK MEASUREMENT",
+            summary: "Per-hop loss, best/avg/worst RTT and jitter over several traceroute rounds",
+            example: "8.8.8.8-MTR",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Globalping (live)"
+    }
+
+    fn query_class(&self) -> &'static str {
+        "TRACE"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        // Live measurement
+        None
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_mtr_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(hops: serde_json::Value) -> TestResult {
+        serde_json::from_value(serde_json::json!({
+            "status": "finished",
+            "resolvedAddress": "192.0.2.1",
+            "hops": hops
+        }))
+        .unwrap()
+    }
+
+    fn hop(address: Option<&str>, rtts: &[f64]) -> serde_json::Value {
+        serde_json::json!({
+            "resolvedAddress": address,
+            "resolvedHostname": address,
+            "timings": rtts.iter().map(|rtt| serde_json::json!({ "rtt": rtt })).collect::<Vec<_>>()
+        })
+    }
+
+    #[test]
+    fn test_aggregate_rounds() {
+        let first = round(serde_json::json!([
+            hop(Some("198.51.100.1"), &[1.0, 3.0]),
+            hop(None, &[]),
+            hop(Some("192.0.2.1"), &[10.0, 12.0])
+        ]));
+        let second = round(serde_json::json!([
+            hop(Some("198.51.100.1"), &[2.0, 2.0]),
+            hop(Some("203.0.113.7"), &[5.0]),
+            hop(Some("192.0.2.1"), &[11.0, 13.0])
+        ]));
+
+        let hops = aggregate(&[&first, &second]);
+        assert_eq!(hops.len(), 3);
+        assert_eq!((hops[0].sent, hops[0].best(), hops[0].worst(), hops[0].avg()), (4, Some(1.0), Some(3.0), Some(2.0)));
+        assert_eq!(hops[0].jitter(), Some(1.0));
+        assert_eq!(hops[0].loss_percent(), 0.0);
+        assert_eq!(hops[1].hosts, vec!["203.0.113.7"]);
+        assert_eq!(hops[1].loss_percent(), 75.0);
+        assert_eq!(hops[1].jitter(), None);
+        assert_eq!(hops[2].avg(), Some(11.5));
+
+        let probe: ProbeInfo = serde_json::from_value(serde_json::json!({
+            "country": "DE", "city": "Frankfurt", "asn": 64496, "network": "Example"
+        }))
+        .unwrap();
+        let output = format_report("192.0.2.1", &probe, &[&first, &second]);
+        assert!(output.contains("% 2 of 5 ICMP traceroute rounds completed\n"));
+        assert!(output.contains("% Probe: Example - Frankfurt, DE (AS64496)\n"));
+        assert!(output.contains("2   | 203.0.113.7  | 75.0% | 4   | 5.0  | 5.0  | 5.0   | -\n"));
+    }
+}
@@ -0,0 +1,260 @@
+// WHOIS Server - AS Neighbours
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Upstreams, downstreams and peers of an ASN (`AS13335-NEIGHBORS`)
+//!
+//! RIPEstat `asn-neighbours` lists every AS seen next to the ASN in the AS
+//! paths RIPE RIS collects. A neighbour always seen left of it (closer to the
+//! collector) is an upstream, one always seen right of it a downstream, and
+//! one seen on both sides a peer, as `-MONITOR` classifies them. The table
+//! shows how many paths and RIS peers saw each adjacency, and for the
+//! neighbours in the most paths the prefixes they originate (RIPEstat
+//! `routing-status`). `-NEIGHBOURS` and `-ASPATHS` are the same query.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::query::parse_asn;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use crate::services::utils::table::format_table;
+use crate::log_debug;
+
+const RIPESTAT_DATA_BASE: &str = "https://stat.ripe.net/data/";
+/// Neighbours seen by fewer RIS peers than this are ignored as noise
+const MIN_NEIGHBOUR_POWER: u64 = 2;
+/// Neighbours whose originated prefixes are looked up, by paths seen
+const MAX_PREFIX_LOOKUPS: usize = 25;
+/// Table rows before the remaining neighbours are only counted
+const MAX_ROWS: usize = 500;
+
+/// How a neighbour relates to the queried ASN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Relationship {
+    Upstream,
+    Peer,
+    Downstream,
+}
+
+impl Relationship {
+    fn from_type(kind: &str) -> Self {
+        match kind {
+            "left" => Self::Upstream,
+            "right" => Self::Downstream,
+            _ => Self::Peer,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Upstream => "upstream",
+            Self::Peer => "peer",
+            Self::Downstream => "downstream",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Neighbour {
+    asn: u32,
+    relationship: Relationship,
+    /// AS paths the adjacency was seen in
+    paths: u64,
+    v4_peers: u64,
+    v6_peers: u64,
+    /// Originated (IPv4, IPv6) prefixes, when looked up
+    prefixes: Option<(u64, u64)>,
+}
+
+async fn ripestat(client: &reqwest::Client, endpoint: &str, resource: &str) -> Result<Value> {
+    let url = format!("{}{}/data.json", api_base(RIPESTAT_DATA_BASE), endpoint);
+    let response = client.get(&url).query(&[("resource", resource)]).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("RIPEstat {} failed: HTTP {}", endpoint, response.status()));
+    }
+    let body: Value = response.limited_json().await?;
+    Ok(body["data"].clone())
+}
+
+/// Neighbours by relationship, then by paths seen
+fn parse_neighbours(data: &Value) -> Vec<Neighbour> {
+    let mut neighbours: Vec<Neighbour> = data["neighbours"]
+        .as_array()
+        .map(|neighbours| {
+            neighbours
+                .iter()
+                .filter(|n| n["power"].as_u64().unwrap_or(0) >= MIN_NEIGHBOUR_POWER)
+                .filter_map(|n| {
+                    Some(Neighbour {
+                        asn: u32::try_from(n["asn"].as_u64()?).ok()?,
+                        relationship: Relationship::from_type(n["type"].as_str().unwrap_or_default()),
+                        paths: n["power"].as_u64().unwrap_or(0),
+                        v4_peers: n["v4_peers"].as_u64().unwrap_or(0),
+                        v6_peers: n["v6_peers"].as_u64().unwrap_or(0),
+                        prefixes: None,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    neighbours.sort_by_key(|n| (n.relationship, std::cmp::Reverse(n.paths), n.asn));
+    neighbours
+}
+
+/// Originated (IPv4, IPv6) prefix counts from `routing-status`
+fn parse_announced(data: &Value) -> Option<(u64, u64)> {
+    let space = &data["announced_space"];
+    Some((space["v4"]["prefixes"].as_u64()?, space["v6"]["prefixes"].as_u64()?))
+}
+
+async fn fetch_neighbours(asn: u32) -> Result<Vec<Neighbour>> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(20))
+        .user_agent("whois-server/1.0")
+        .build()?;
+    let mut neighbours = parse_neighbours(&ripestat(&client, "asn-neighbours", &format!("AS{}", asn)).await?);
+
+    // Prefix counts for the neighbours in the most paths, whatever their relationship
+    let mut busiest: Vec<usize> = (0..neighbours.len()).collect();
+    busiest.sort_by_key(|&i| std::cmp::Reverse(neighbours[i].paths));
+    busiest.truncate(MAX_PREFIX_LOOKUPS);
+    let lookups = busiest.iter().map(|&i| {
+        let resource = format!("AS{}", neighbours[i].asn);
+        let client = &client;
+        async move { ripestat(client, "routing-status", &resource).await }
+    });
+    let results = futures::future::join_all(lookups).await;
+    for (i, result) in busiest.into_iter().zip(results) {
+        match result {
+            Ok(data) => neighbours[i].prefixes = parse_announced(&data),
+            Err(e) => log_debug!("routing-status of AS{} failed: {}", neighbours[i].asn, e),
+        }
+    }
+    Ok(neighbours)
+}
+
+fn format_neighbours(asn: u32, neighbours: &[Neighbour]) -> String {
+    let mut output = format!("% AS neighbours of AS{} seen in RIPE RIS AS paths\n", asn);
+    output.push_str(&format!(
+        "% upstream: seen left of AS{} (towards the collectors), downstream: seen right of it, peer: both\n",
+        asn
+    ));
+    output.push_str("% Source: stat.ripe.net asn-neighbours, routing-status\n\n");
+
+    if neighbours.is_empty() {
+        output.push_str(&format!("% No neighbours of AS{} are visible in RIS\n", asn));
+        return output;
+    }
+    for relationship in [Relationship::Upstream, Relationship::Peer, Relationship::Downstream] {
+        let count = neighbours.iter().filter(|n| n.relationship == relationship).count();
+        output.push_str(&format!("{:<16}{}\n", format!("{}s:", relationship.label()), count));
+    }
+    output.push('\n');
+
+    let rows: Vec<Vec<String>> = neighbours
+        .iter()
+        .take(MAX_ROWS)
+        .map(|n| {
+            vec![
+                format!("AS{}", n.asn),
+                n.relationship.label().to_string(),
+                n.paths.to_string(),
+                n.v4_peers.to_string(),
+                n.v6_peers.to_string(),
+                n.prefixes.map_or("-".to_string(), |(v4, v6)| format!("{} / {}", v4, v6)),
+            ]
+        })
+        .collect();
+    output.push_str(&format_table(
+        &["ASN", "Relationship", "Paths", "IPv4 peers", "IPv6 peers", "Prefixes v4 / v6"],
+        &rows,
+    ));
+    if neighbours.len() > MAX_ROWS {
+        output.push_str(&format!("% ... and {} more neighbours\n", neighbours.len() - MAX_ROWS));
+    }
+    if neighbours.len() > MAX_PREFIX_LOOKUPS {
+        output.push_str(&format!(
+            "% Originated prefixes are shown for the {} neighbours seen in the most paths\n",
+            MAX_PREFIX_LOOKUPS
+        ));
+    }
+    output
+}
+
+/// Process a `-NEIGHBORS` query
+pub async fn process_neighbors_query(target: &str) -> Result<String> {
+    let asn = parse_asn(target.trim())
+        .ok_or_else(|| anyhow!("Usage: <ASN>-NEIGHBORS (e.g. AS13335-NEIGHBORS), got: {}", target.trim()))?;
+    let neighbours = fetch_neighbours(asn).await?;
+    Ok(format_neighbours(asn, &neighbours))
+}
+
+pub struct NeighborsService;
+
+#[async_trait]
+impl QueryService for NeighborsService {
+    fn name(&self) -> &'static str {
+        "neighbors"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-NEIGHBORS", "-NEIGHBOURS", "-ASPATHS"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "Upstreams, downstreams and peers from RIS AS paths with path, peer and prefix counts",
+            example: "AS13335-NEIGHBORS",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "stat.ripe.net"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(3600))
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_neighbors_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::fixtures::FixtureServer;
+
+    #[tokio::test]
+    async fn test_neighbors_fixture() {
+        let mut fixtures = FixtureServer::start("neighbors").await;
+        fixtures.serve(RIPESTAT_DATA_BASE, "asn-neighbours/data.json?resource=AS213605", "asn_neighbours.json").await;
+        for asn in [6939, 34927, 202409] {
+            let request = format!("routing-status/data.json?resource=AS{}", asn);
+            fixtures.serve(RIPESTAT_DATA_BASE, &request, &format!("routing_status_{}.json", asn)).await;
+        }
+
+        let output = fixtures.run(process_neighbors_query("AS213605")).await.unwrap();
+        assert!(output.contains("upstreams:      1\npeers:          1\ndownstreams:    1\n"));
+        let rows: Vec<&str> = output.lines().filter(|line| line.contains(" | ")).skip(1).collect();
+        assert_eq!(
+            rows,
+            [
+                "AS6939   | upstream     | 142   | 0          | 142        | 3512 / 2105",
+                "AS34927  | peer         | 12    | 0          | 12         | 14 / 3",
+                "AS202409 | downstream   | 4     | 0          | 4          | 0 / 1",
+            ]
+        );
+        // AS64500 is seen by a single RIS peer only
+        assert!(!output.contains("AS64500"));
+
+        assert!(process_neighbors_query("example.com").await.is_err());
+    }
+}
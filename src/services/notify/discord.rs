@@ -0,0 +1,33 @@
+// WHOIS Server - Discord Notifications
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Discord channel webhook
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{Notification, NotificationChannel, check_status};
+
+/// Longest message content Discord accepts
+const MAX_CONTENT_CHARS: usize = 2000;
+
+pub(super) struct DiscordChannel {
+    pub(super) webhook_url: String,
+}
+
+#[async_trait]
+impl NotificationChannel for DiscordChannel {
+    fn kind(&self) -> &'static str {
+        "discord"
+    }
+
+    async fn send(&self, client: &reqwest::Client, _notification: &Notification, text: &str) -> Result<()> {
+        let content: String = text.chars().take(MAX_CONTENT_CHARS).collect();
+        // Alerts may quote user-controlled text; never ping anyone
+        let body = json!({ "content": content, "allowed_mentions": { "parse": [] } });
+        let response = client.post(&self.webhook_url).json(&body).send().await?;
+        check_status(response, "Discord").await
+    }
+}
@@ -0,0 +1,62 @@
+// WHOIS Server - Email Notifications
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Email over SMTP (STARTTLS) with `lettre`
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::{Notification, NotificationChannel};
+
+pub(super) fn parse_mailbox(address: &str) -> Result<Mailbox> {
+    address.parse().map_err(|e| anyhow!("invalid email address '{}': {}", address, e))
+}
+
+pub(super) struct EmailChannel {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailChannel {
+    pub(super) fn new(
+        smtp_host: &str,
+        smtp_port: u16,
+        username: Option<String>,
+        password: Option<String>,
+        from: &str,
+        to: &[String],
+    ) -> Result<Self> {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host)?.port(smtp_port);
+        if let Some(username) = username {
+            builder = builder.credentials(Credentials::new(username, password.unwrap_or_default()));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from: parse_mailbox(from)?,
+            to: to.iter().map(|address| parse_mailbox(address)).collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn kind(&self) -> &'static str {
+        "email"
+    }
+
+    async fn send(&self, _client: &reqwest::Client, notification: &Notification, text: &str) -> Result<()> {
+        let mut message = Message::builder().from(self.from.clone()).subject(&notification.title);
+        for recipient in &self.to {
+            message = message.to(recipient.clone());
+        }
+        let message = message.header(ContentType::TEXT_PLAIN).body(text.to_string())?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
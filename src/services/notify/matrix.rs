@@ -0,0 +1,38 @@
+// WHOIS Server - Matrix Notifications
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Matrix `m.room.message` events via the client-server API
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{Notification, NotificationChannel, check_status};
+
+pub(super) struct MatrixChannel {
+    pub(super) homeserver: String,
+    pub(super) room_id: String,
+    pub(super) access_token: String,
+}
+
+#[async_trait]
+impl NotificationChannel for MatrixChannel {
+    fn kind(&self) -> &'static str {
+        "matrix"
+    }
+
+    async fn send(&self, client: &reqwest::Client, _notification: &Notification, text: &str) -> Result<()> {
+        // A fresh transaction ID per attempt; retries after a lost response may duplicate
+        let txn_id = uuid::Uuid::new_v4().simple().to_string();
+        let url = format!(
+            "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+            self.homeserver.trim_end_matches('/'),
+            urlencoding::encode(&self.room_id),
+            txn_id
+        );
+        let body = json!({ "msgtype": "m.notice", "body": text });
+        let response = client.put(&url).bearer_auth(&self.access_token).json(&body).send().await?;
+        check_status(response, "Matrix").await
+    }
+}
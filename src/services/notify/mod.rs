@@ -0,0 +1,351 @@
+// WHOIS Server - Notification Channels
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Notifications to operators over pluggable channels
+//!
+//! Subsystems that need to tell someone about an event (monitoring alerts,
+//! admin events) describe it as a [`Notification`] and hand it to a
+//! [`Notifier`] built from channel definitions in their TOML configuration:
+//!
+//! ```toml
+//! [[notify]]
+//! type = "telegram"
+//! bot_token = "123456:ABC..."
+//! chat_id = "-1001234567890"
+//! template = "[{asn}] {title}\n{text}"
+//!
+//! [[notify]]
+//! type = "email"
+//! smtp_host = "smtp.example.net"
+//! username = "noc@example.net"
+//! password = "..."
+//! from = "whois@example.net"
+//! to = ["noc@example.net"]
+//! ```
+//!
+//! Supported channels are generic JSON webhooks, Telegram, Discord, Matrix
+//! and email over SMTP. Each channel renders the message from its template
+//! (`{title}`, `{text}` and any top-level field of the notification data),
+//! and failed deliveries are retried with backoff.
+
+mod discord;
+mod email;
+mod matrix;
+mod telegram;
+mod webhook;
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::core::body_limit::LimitedBody;
+use crate::{log_debug, log_warn};
+
+/// Delivery attempts per channel before giving up
+const MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, doubled for each further one
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+/// Used when a channel has no template of its own
+const DEFAULT_TEMPLATE: &str = "{title}\n{text}";
+
+/// An event to tell operators about
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// One-line summary, also used as the email subject
+    pub title: String,
+    /// Human-readable details
+    pub text: String,
+    /// Structured data, sent as-is by webhooks and available to templates
+    pub data: Value,
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>, text: impl Into<String>) -> Self {
+        Self { title: title.into(), text: text.into(), data: Value::Null }
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = data;
+        self
+    }
+}
+
+/// Fill `{title}`, `{text}` and `{<field>}` placeholders of a template
+pub fn render_template(template: &str, notification: &Notification) -> String {
+    let mut output = template.replace("{title}", &notification.title).replace("{text}", &notification.text);
+    if let Some(fields) = notification.data.as_object() {
+        for (key, value) in fields {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Number(_) | Value::Bool(_) => value.to_string(),
+                _ => continue,
+            };
+            output = output.replace(&format!("{{{}}}", key), &value);
+        }
+    }
+    output
+}
+
+/// A way of delivering notifications
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    /// Channel type, as used in the configuration
+    fn kind(&self) -> &'static str;
+
+    /// Deliver one notification, `text` being the rendered template
+    async fn send(&self, client: &reqwest::Client, notification: &Notification, text: &str) -> Result<()>;
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+/// Channel definition from a configuration file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ChannelConfig {
+    /// POST the notification as JSON
+    Webhook { url: String },
+    Telegram { bot_token: String, chat_id: String },
+    Discord { webhook_url: String },
+    Matrix { homeserver: String, room_id: String, access_token: String },
+    Email {
+        smtp_host: String,
+        #[serde(default = "default_smtp_port")]
+        smtp_port: u16,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+}
+
+fn require_http_url(field: &str, url: &str) -> Result<()> {
+    if url.starts_with("https://") || url.starts_with("http://") {
+        Ok(())
+    } else {
+        Err(anyhow!("{} must be an http(s) URL", field))
+    }
+}
+
+impl ChannelConfig {
+    fn validate(&self) -> Result<()> {
+        match self {
+            ChannelConfig::Webhook { url } => require_http_url("webhook url", url),
+            ChannelConfig::Discord { webhook_url } => require_http_url("discord webhook_url", webhook_url),
+            ChannelConfig::Matrix { homeserver, room_id, .. } => {
+                require_http_url("matrix homeserver", homeserver)?;
+                if !room_id.starts_with('!') {
+                    return Err(anyhow!("matrix room_id must be a room ID such as !abc:example.org"));
+                }
+                Ok(())
+            }
+            ChannelConfig::Telegram { bot_token, chat_id } => {
+                if bot_token.is_empty() || chat_id.is_empty() {
+                    return Err(anyhow!("telegram needs bot_token and chat_id"));
+                }
+                Ok(())
+            }
+            ChannelConfig::Email { from, to, .. } => {
+                if to.is_empty() {
+                    return Err(anyhow!("email needs at least one recipient"));
+                }
+                email::parse_mailbox(from)?;
+                for recipient in to {
+                    email::parse_mailbox(recipient)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn build(&self) -> Result<Box<dyn NotificationChannel>> {
+        Ok(match self.clone() {
+            ChannelConfig::Webhook { url } => Box::new(webhook::WebhookChannel { url }),
+            ChannelConfig::Telegram { bot_token, chat_id } => Box::new(telegram::TelegramChannel { bot_token, chat_id }),
+            ChannelConfig::Discord { webhook_url } => Box::new(discord::DiscordChannel { webhook_url }),
+            ChannelConfig::Matrix { homeserver, room_id, access_token } => {
+                Box::new(matrix::MatrixChannel { homeserver, room_id, access_token })
+            }
+            ChannelConfig::Email { smtp_host, smtp_port, username, password, from, to } => {
+                Box::new(email::EmailChannel::new(&smtp_host, smtp_port, username, password, &from, &to)?)
+            }
+        })
+    }
+}
+
+/// A channel with its message template
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ChannelSpec {
+    #[serde(flatten)]
+    pub channel: ChannelConfig,
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+impl ChannelSpec {
+    /// A webhook whose `text` is the notification text alone
+    pub fn webhook(url: &str) -> Self {
+        Self { channel: ChannelConfig::Webhook { url: url.to_string() }, template: Some("{text}".to_string()) }
+    }
+}
+
+/// Check channel definitions loaded from a configuration file
+pub fn validate_channels(specs: &[ChannelSpec]) -> Result<()> {
+    specs.iter().try_for_each(|spec| spec.channel.validate())
+}
+
+struct Channel {
+    channel: Box<dyn NotificationChannel>,
+    template: String,
+}
+
+/// Delivers notifications to a set of channels
+pub struct Notifier {
+    channels: Vec<Channel>,
+    client: reqwest::Client,
+    retry_delay: Duration,
+}
+
+impl Notifier {
+    pub fn new(specs: &[ChannelSpec]) -> Result<Self> {
+        validate_channels(specs)?;
+        let channels = specs
+            .iter()
+            .map(|spec| {
+                Ok(Channel {
+                    channel: spec.channel.build()?,
+                    template: spec.template.clone().unwrap_or_else(|| DEFAULT_TEMPLATE.to_string()),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .user_agent("whois-server/1.0")
+            .build()?;
+        Ok(Self { channels, client, retry_delay: RETRY_DELAY })
+    }
+
+    /// Send to every channel, retrying failures; fails if any channel failed
+    pub async fn deliver(&self, notification: &Notification) -> Result<()> {
+        deliver_all(&self.channels, &self.client, self.retry_delay, notification).await
+    }
+}
+
+async fn deliver_all(
+    channels: &[Channel],
+    client: &reqwest::Client,
+    retry_delay: Duration,
+    notification: &Notification,
+) -> Result<()> {
+    let mut failures = Vec::new();
+    for channel in channels {
+        let text = render_template(&channel.template, notification);
+        if let Err(e) = deliver_with_retry(channel.channel.as_ref(), client, retry_delay, notification, &text).await {
+            failures.push(format!("{}: {}", channel.channel.kind(), e));
+        }
+    }
+    if failures.is_empty() { Ok(()) } else { Err(anyhow!(failures.join("; "))) }
+}
+
+async fn deliver_with_retry(
+    channel: &dyn NotificationChannel,
+    client: &reqwest::Client,
+    retry_delay: Duration,
+    notification: &Notification,
+    text: &str,
+) -> Result<()> {
+    let mut delay = retry_delay;
+    let mut attempt = 1;
+    loop {
+        match channel.send(client, notification, text).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                log_debug!("{} notification attempt {} failed: {}", channel.kind(), attempt, e);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Fail on a non-success HTTP status
+async fn check_status(response: reqwest::Response, channel: &str) -> Result<()> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.limited_text().await.unwrap_or_default();
+    Err(anyhow!("{} returned HTTP {}: {}", channel, status, body.chars().take(200).collect::<String>()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[derive(Deserialize)]
+    struct File {
+        notify: Vec<ChannelSpec>,
+    }
+
+    #[test]
+    fn test_parse_channels() {
+        let file: File = toml::from_str(
+            "[[notify]]\ntype = \"discord\"\nwebhook_url = \"https://discord.com/api/webhooks/1/x\"\n\n\
+             [[notify]]\ntype = \"email\"\nsmtp_host = \"smtp.example.net\"\nfrom = \"whois@example.net\"\n\
+             to = [\"noc@example.net\"]\ntemplate = \"{text}\"\n",
+        )
+        .unwrap();
+        assert!(validate_channels(&file.notify).is_ok());
+        assert_eq!(file.notify[1].template.as_deref(), Some("{text}"));
+        assert!(matches!(file.notify[1].channel, ChannelConfig::Email { smtp_port: 587, .. }));
+
+        let invalid: File = toml::from_str("[[notify]]\ntype = \"matrix\"\nhomeserver = \"https://matrix.org\"\nroom_id = \"#room:matrix.org\"\naccess_token = \"x\"\n").unwrap();
+        assert!(validate_channels(&invalid.notify).is_err());
+        assert!(toml::from_str::<File>("[[notify]]\ntype = \"pager\"\n").is_err());
+    }
+
+    #[test]
+    fn test_render_template() {
+        let notification = Notification::new("Route leak", "AS64500 announced 192.0.2.0/24")
+            .with_data(json!({ "asn": 213605, "severity": "high", "alerts": [1, 2] }));
+        assert_eq!(
+            render_template("[AS{asn}/{severity}] {title}: {text} {alerts}", &notification),
+            "[AS213605/high] Route leak: AS64500 announced 192.0.2.0/24 {alerts}"
+        );
+        assert_eq!(render_template(DEFAULT_TEMPLATE, &notification), "Route leak\nAS64500 announced 192.0.2.0/24");
+    }
+
+    #[tokio::test]
+    async fn test_webhook_is_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(body_partial_json(json!({ "title": "test", "asn": 213605 })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut notifier = Notifier::new(&[ChannelSpec::webhook(&format!("{}/hook", server.uri()))]).unwrap();
+        notifier.retry_delay = Duration::from_millis(10);
+        let notification = Notification::new("test", "hello").with_data(json!({ "asn": 213605 }));
+        notifier.deliver(&notification).await.unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+// WHOIS Server - Telegram Notifications
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Telegram Bot API `sendMessage`
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{Notification, NotificationChannel, check_status};
+use crate::services::upstream::api_base;
+
+const TELEGRAM_API_BASE: &str = "https://api.telegram.org/";
+/// Longest message Telegram accepts
+const MAX_MESSAGE_CHARS: usize = 4096;
+
+pub(super) struct TelegramChannel {
+    pub(super) bot_token: String,
+    pub(super) chat_id: String,
+}
+
+#[async_trait]
+impl NotificationChannel for TelegramChannel {
+    fn kind(&self) -> &'static str {
+        "telegram"
+    }
+
+    async fn send(&self, client: &reqwest::Client, _notification: &Notification, text: &str) -> Result<()> {
+        let url = format!("{}bot{}/sendMessage", api_base(TELEGRAM_API_BASE), self.bot_token);
+        let text: String = text.chars().take(MAX_MESSAGE_CHARS).collect();
+        let body = json!({ "chat_id": self.chat_id, "text": text, "disable_web_page_preview": true });
+        let response = client.post(&url).json(&body).send().await?;
+        check_status(response, "Telegram").await
+    }
+}
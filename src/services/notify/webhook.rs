@@ -0,0 +1,42 @@
+// WHOIS Server - Webhook Notifications
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Generic JSON webhook
+//!
+//! The notification data is POSTed with `title` and the rendered `text`
+//! added, so receivers get both the structured event and a readable line.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+use super::{Notification, NotificationChannel, check_status};
+
+pub(super) struct WebhookChannel {
+    pub(super) url: String,
+}
+
+/// JSON body for a notification
+fn payload(notification: &Notification, text: &str) -> Value {
+    let mut body = match &notification.data {
+        Value::Object(fields) => Value::Object(fields.clone()),
+        Value::Null => json!({}),
+        data => json!({ "data": data }),
+    };
+    body["title"] = json!(notification.title);
+    body["text"] = json!(text);
+    body
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn kind(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn send(&self, client: &reqwest::Client, notification: &Notification, text: &str) -> Result<()> {
+        let response = client.post(&self.url).json(&payload(notification, text)).send().await?;
+        check_status(response, "webhook").await
+    }
+}
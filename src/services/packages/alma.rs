@@ -20,6 +20,11 @@ use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 const ALMA_REPO_BASE: &str = "https://repo.almalinux.org/almalinux/9/BaseOS/x86_64/os";
 const ALMA_APPSTREAM_BASE: &str = "https://repo.almalinux.org/almalinux/9/AppStream/x86_64/os";
 const ALMA_EXTRAS_BASE: &str = "https://repo.almalinux.org/almalinux/9/extras/x86_64/os";
@@ -268,6 +273,40 @@ fn format_alma_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-ALMA` query service
+pub struct AlmaService;
+
+#[async_trait]
+impl QueryService for AlmaService {
+    fn name(&self) -> &'static str {
+        "alma"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-ALMA"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "AlmaLinux package information",
+            example: "nginx-ALMA",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "AlmaLinux repositories"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_alma_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -17,13 +17,62 @@
  */
 
 use anyhow::{Context, Result};
-use regex::Regex;
 use reqwest;
 use serde::{Deserialize, Serialize};
+use crate::core::body_limit::LimitedBody;
+use crate::core::regex_cache::CachedRegex;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 const AOSC_PACKAGES_URL: &str = "https://packages.aosc.io/packages/";
 const AOSC_SEARCH_URL: &str = "https://packages.aosc.io/search?q=";
 
+static VERSION_RE: CachedRegex = CachedRegex::new(r#"<span class="pkg-version">([^<]+)</span>"#);
+static DESC_RE: CachedRegex = CachedRegex::new(
+    r#"<p class="description pkg-description">([^<]+)</p>"#
+);
+static SECTION_RE: CachedRegex = CachedRegex::new(r#"<b class="pkg-field">Section</b>:\s*([^<]+)"#);
+static DEPENDS_RE: CachedRegex = CachedRegex::new(
+    r#"<b class="pkg-field pkg-dep-rel">Depends</b>\s*:\s*\n\s*((?:<span class="pkg-dep"><a href="[^"]+">([^<]+)</a></span>,?\s*)+)"#
+);
+static DEP_NAME_RE: CachedRegex = CachedRegex::new(r#"<a href="([^"]+)">([^<]+)</a>"#);
+static UPSTREAM_SOURCE_RE: CachedRegex = CachedRegex::new(
+    r#"<b class="pkg-field"[^>]*>Upstream</b>:\s*<a href="([^"]+)">source</a>"#
+);
+static UPSTREAM_TARBALL_RE: CachedRegex = CachedRegex::new(
+    r#"<a href="([^"]+)"\s*>\(tarball\)[^<]*</a>"#
+);
+static UPSTREAM_VER_RE: CachedRegex = CachedRegex::new(
+    r#"<a href="[^"]+"\s*\(git\)\s*([^<]+)</a>"#
+);
+static ARCH_SIZE_RE: CachedRegex = CachedRegex::new(r#"([a-z0-9]+):\s+(\d+\.\d+\s+[KMGT]?iB)"#);
+static DOWNLOAD_RE: CachedRegex = CachedRegex::new(
+    r#"<a[^>]+href="/files/([a-z0-9]+)/[^"]*"[^>]*>\s*([0-9.]+\s*[KMGT]?iB)\s*</a>"#
+);
+static SIZE_CONTEXT_RE: CachedRegex = CachedRegex::new(
+    r#"(amd64|arm64|loongarch64|loongson3|mips64r6el|ppc64el|riscv64)[^0-9]*?(\d+\.\d+\s*[KMGT]?iB)"#
+);
+
+/// Patterns checked by `regex_cache::precompile_builtin_regexes` at startup
+pub(crate) fn builtin_regexes() -> Vec<&'static CachedRegex> {
+    vec![
+        &VERSION_RE,
+        &DESC_RE,
+        &SECTION_RE,
+        &DEPENDS_RE,
+        &DEP_NAME_RE,
+        &UPSTREAM_SOURCE_RE,
+        &UPSTREAM_TARBALL_RE,
+        &UPSTREAM_VER_RE,
+        &ARCH_SIZE_RE,
+        &DOWNLOAD_RE,
+        &SIZE_CONTEXT_RE,
+    ]
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct AOSCPackage {
     name: String,
@@ -114,7 +163,7 @@ async fn query_aosc_packages(package_name: &str) -> Result<AOSCSearchResponse> {
     }
 
     let html_content = response
-        .text()
+        .limited_text()
         .await
         .context("Failed to get HTML content from AOSC packages page")?;
 
@@ -126,14 +175,14 @@ fn parse_aosc_html(html: &str, query: &str) -> Result<AOSCSearchResponse> {
     let mut packages = Vec::new();
 
     // Extract package version from header
-    let version_regex = Regex::new(r#"<span class="pkg-version">([^<]+)</span>"#).expect("Invalid AOSC regex pattern");
+    let version_regex = VERSION_RE.get()?;
     let version = version_regex
         .captures(html)
         .and_then(|cap| cap.get(1))
         .map_or("unknown".to_string(), |m| m.as_str().to_string());
 
     // Extract description
-    let desc_regex = Regex::new(r#"<p class="description pkg-description">([^<]+)</p>"#).expect("Invalid AOSC regex pattern");
+    let desc_regex = DESC_RE.get()?;
     let description = desc_regex
         .captures(html)
         .and_then(|cap| cap.get(1))
@@ -142,20 +191,18 @@ fn parse_aosc_html(html: &str, query: &str) -> Result<AOSCSearchResponse> {
         });
 
     // Extract section
-    let section_regex = Regex::new(r#"<b class="pkg-field">Section</b>:\s*([^<]+)"#).expect("Invalid AOSC regex pattern");
+    let section_regex = SECTION_RE.get()?;
     let section = section_regex
         .captures(html)
         .and_then(|cap| cap.get(1))
         .map(|m| m.as_str().trim().to_string());
 
     // Extract dependencies - only runtime dependencies, not build or library
-    let depends_regex = Regex::new(
-        r#"<b class="pkg-field pkg-dep-rel">Depends</b>\s*:\s*\n\s*((?:<span class="pkg-dep"><a href="[^"]+">([^<]+)</a></span>,?\s*)+)"#
-    ).expect("Invalid AOSC regex pattern");
+    let depends_regex = DEPENDS_RE.get()?;
     let mut depends = Vec::new();
     if let Some(cap) = depends_regex.captures(html) {
         let deps_html = cap.get(1).map_or("", |m| m.as_str());
-        let dep_name_regex = Regex::new(r#"<a href="([^"]+)">([^<]+)</a>"#).expect("Invalid AOSC regex pattern");
+        let dep_name_regex = DEP_NAME_RE.get()?;
         for dep_cap in dep_name_regex.captures_iter(deps_html) {
             if let Some(dep_name) = dep_cap.get(1) {
                 // Only include actual package names, skip URLs and paths
@@ -173,9 +220,7 @@ fn parse_aosc_html(html: &str, query: &str) -> Result<AOSCSearchResponse> {
     // Extract upstream URL - try multiple patterns
     let upstream_url = {
         // Try the source link first
-        let upstream_regex1 =
-            Regex::new(r#"<b class="pkg-field"[^>]*>Upstream</b>:\s*<a href="([^"]+)">source</a>"#)
-                .expect("Invalid AOSC regex pattern");
+        let upstream_regex1 = UPSTREAM_SOURCE_RE.get()?;
         let url1 = upstream_regex1
             .captures(html)
             .and_then(|cap| cap.get(1))
@@ -186,8 +231,7 @@ fn parse_aosc_html(html: &str, query: &str) -> Result<AOSCSearchResponse> {
             url1
         } else {
             // Try the tarball link as fallback
-            let upstream_regex2 =
-                Regex::new(r#"<a href="([^"]+)"\s*>\(tarball\)[^<]*</a>"#).expect("Invalid AOSC regex pattern");
+            let upstream_regex2 = UPSTREAM_TARBALL_RE.get()?;
             upstream_regex2
                 .captures(html)
                 .and_then(|cap| cap.get(1))
@@ -197,7 +241,7 @@ fn parse_aosc_html(html: &str, query: &str) -> Result<AOSCSearchResponse> {
     };
 
     // Extract upstream version
-    let upstream_ver_regex = Regex::new(r#"<a href="[^"]+"\s*\(git\)\s*([^<]+)</a>"#).expect("Invalid AOSC regex pattern");
+    let upstream_ver_regex = UPSTREAM_VER_RE.get()?;
     let upstream_version = upstream_ver_regex
         .captures(html)
         .and_then(|cap| cap.get(1))
@@ -207,7 +251,7 @@ fn parse_aosc_html(html: &str, query: &str) -> Result<AOSCSearchResponse> {
     let mut architectures = Vec::new();
 
     // Pattern 1: Extract architecture and size from text patterns like "amd64: 19.8 MiB"
-    let arch_size_regex = Regex::new(r#"([a-z0-9]+):\s+(\d+\.\d+\s+[KMGT]?iB)"#).expect("Invalid AOSC regex pattern");
+    let arch_size_regex = ARCH_SIZE_RE.get()?;
     for cap in arch_size_regex.captures_iter(html) {
         if let (Some(arch_name), Some(size)) = (cap.get(1), cap.get(2)) {
             let arch = arch_name.as_str().trim();
@@ -239,10 +283,7 @@ fn parse_aosc_html(html: &str, query: &str) -> Result<AOSCSearchResponse> {
 
     // Pattern 2: Extract from download links with size information
     if architectures.is_empty() {
-        let download_regex = Regex::new(
-            r#"<a[^>]+href="/files/([a-z0-9]+)/[^"]*"[^>]*>\s*([0-9.]+\s*[KMGT]?iB)\s*</a>"#,
-        )
-        .expect("Invalid AOSC regex pattern");
+        let download_regex = DOWNLOAD_RE.get()?;
         for cap in download_regex.captures_iter(html) {
             if let (Some(arch_name), Some(size)) = (cap.get(1), cap.get(2)) {
                 let arch = arch_name.as_str().trim();
@@ -286,9 +327,7 @@ fn parse_aosc_html(html: &str, query: &str) -> Result<AOSCSearchResponse> {
         let mut size_map = std::collections::HashMap::new();
 
         // Look for size patterns in the HTML text
-        let size_context_regex = Regex::new(
-            r#"(amd64|arm64|loongarch64|loongson3|mips64r6el|ppc64el|riscv64)[^0-9]*?(\d+\.\d+\s*[KMGT]?iB)"#
-        ).expect("Invalid AOSC regex pattern");
+        let size_context_regex = SIZE_CONTEXT_RE.get()?;
         for cap in size_context_regex.captures_iter(html) {
             if let (Some(arch_name), Some(size)) = (cap.get(1), cap.get(2)) {
                 let arch = arch_name.as_str().trim();
@@ -419,6 +458,40 @@ fn format_aosc_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-AOSC` query service
+pub struct AoscService;
+
+#[async_trait]
+impl QueryService for AoscService {
+    fn name(&self) -> &'static str {
+        "aosc"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-AOSC"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "AOSC OS package information",
+            example: "htop-AOSC",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "AOSC packages API"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_aosc_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -11,7 +11,14 @@
 use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
+use crate::services::upstream::api_base;
 const AUR_API_BASE: &str = "https://aur.archlinux.org/rpc/v5/info";
 const AUR_PACKAGE_BASE: &str = "https://aur.archlinux.org/packages";
 
@@ -104,7 +111,7 @@ async fn query_aur_api(package_name: &str) -> Result<AurPackage> {
         .build()
         .context("Failed to create HTTP client")?;
 
-    let url = format!("{}?arg={}", AUR_API_BASE, package_name);
+    let url = format!("{}?arg={}", api_base(AUR_API_BASE), package_name);
     log_debug!("Querying AUR API: {}", url);
 
     let response = client
@@ -121,7 +128,7 @@ async fn query_aur_api(package_name: &str) -> Result<AurPackage> {
     }
 
     let aur_response: AurResponse = response
-        .json()
+        .limited_json()
         .await
         .context("Failed to parse AUR API response")?;
 
@@ -324,9 +331,44 @@ fn format_timestamp(timestamp: u64) -> String {
     }
 }
 
+/// `-AUR` query service
+pub struct AurService;
+
+#[async_trait]
+impl QueryService for AurService {
+    fn name(&self) -> &'static str {
+        "aur"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-AUR"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "Arch User Repository packages",
+            example: "yay-AUR",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "AUR RPC"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_aur_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::fixtures::FixtureServer;
 
     #[test]
     fn test_format_timestamp() {
@@ -343,4 +385,21 @@ mod tests {
         assert!(result.contains("nonexistent-package"));
         assert!(result.contains("AUR URL"));
     }
+
+    #[tokio::test]
+    async fn test_aur_fixture() {
+        let mut fixtures = FixtureServer::start("aur").await;
+        fixtures.serve(AUR_API_BASE, "?arg=yay", "yay.json").await;
+        fixtures.serve(AUR_API_BASE, "?arg=missing-package", "not_found.json").await;
+
+        let response = fixtures.run(process_aur_query("yay")).await.unwrap();
+        assert!(response.contains("package: yay\n"));
+        assert!(response.contains("version: 12.4.2-1\n"));
+        assert!(response.contains("maintainer: Jguer\n"));
+        assert!(response.contains("depends: pacman>5, git\n"));
+        assert!(response.contains("out-of-date: no\n"));
+
+        let response = fixtures.run(process_aur_query("missing-package")).await.unwrap();
+        assert!(response.contains("not found"));
+    }
 }
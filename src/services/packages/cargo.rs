@@ -20,7 +20,14 @@ use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
+use crate::services::upstream::api_base;
 const CRATES_IO_API_URL: &str = "https://crates.io/api/v1/crates/";
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -132,7 +139,7 @@ async fn query_crates_io_crate(crate_name: &str) -> Result<CratesResponse> {
         .build()
         .context("Failed to create HTTP client")?;
 
-    let crate_url = format!("{}{}", CRATES_IO_API_URL, urlencoding::encode(crate_name));
+    let crate_url = format!("{}{}", api_base(CRATES_IO_API_URL), urlencoding::encode(crate_name));
 
     log_debug!("Querying crates.io API: {}", crate_url);
 
@@ -154,7 +161,7 @@ async fn query_crates_io_crate(crate_name: &str) -> Result<CratesResponse> {
     }
 
     let crate_data: CratesResponse = response
-        .json()
+        .limited_json()
         .await
         .context("Failed to parse crates.io response")?;
 
@@ -374,9 +381,44 @@ fn format_timestamp(timestamp: &str) -> String {
     }
 }
 
+/// `-CARGO` query service
+pub struct CargoService;
+
+#[async_trait]
+impl QueryService for CargoService {
+    fn name(&self) -> &'static str {
+        "cargo"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-CARGO"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "Rust crates.io package information",
+            example: "serde-CARGO",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "crates.io API"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_cargo_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::fixtures::FixtureServer;
 
     #[tokio::test]
     async fn test_cargo_crate_name_validation() {
@@ -406,4 +448,22 @@ mod tests {
         assert_eq!(format_number(1500), "1.5K");
         assert_eq!(format_number(1500000), "1.5M");
     }
+
+    #[tokio::test]
+    async fn test_cargo_fixture() {
+        let mut fixtures = FixtureServer::start("cargo").await;
+        fixtures.serve(CRATES_IO_API_URL, "serde", "serde.json").await;
+        fixtures.serve_status(CRATES_IO_API_URL, "no-such-crate", 404).await;
+
+        let response = fixtures.run(process_cargo_query("serde")).await.unwrap();
+        assert!(response.contains("crate-name: serde\n"));
+        assert!(response.contains("version: 1.0.210\n"));
+        assert!(response.contains("license: MIT OR Apache-2.0\n"));
+        assert!(response.contains("published-by: David Tolnay (dtolnay)\n"));
+        assert!(response.contains("repository: https://github.com/serde-rs/serde\n"));
+        assert!(response.contains("categories: Encoding, No standard library\n"));
+
+        let response = fixtures.run(process_cargo_query("no-such-crate")).await.unwrap();
+        assert!(response.contains("Rust Crate"));
+    }
 }
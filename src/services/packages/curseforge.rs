@@ -21,7 +21,13 @@ use reqwest::Client;
 use serde::Deserialize;
 use std::env;
 
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct CurseForgeProject {
@@ -186,7 +192,7 @@ async fn get_project_by_id(client: &Client, api_key: &str, project_id: u64) -> R
     if !response.status().is_success() {
         let status = response.status();
         let error_body = response
-            .text()
+            .limited_text()
             .await
             .unwrap_or_else(|_| "Unable to read error body".to_string());
         log_error!(
@@ -200,7 +206,7 @@ async fn get_project_by_id(client: &Client, api_key: &str, project_id: u64) -> R
         ));
     }
 
-    let curse_response: CurseForgeResponse = response.json().await?;
+    let curse_response: CurseForgeResponse = response.limited_json().await?;
     Ok(format_project_info(&curse_response.data))
 }
 
@@ -226,7 +232,7 @@ async fn search_curseforge(client: &Client, api_key: &str, query: &str) -> Resul
     if !response.status().is_success() {
         let status = response.status();
         let error_body = response
-            .text()
+            .limited_text()
             .await
             .unwrap_or_else(|_| "Unable to read error body".to_string());
         log_error!(
@@ -240,7 +246,7 @@ async fn search_curseforge(client: &Client, api_key: &str, query: &str) -> Resul
         ));
     }
 
-    let search_response: SearchResponse = response.json().await?;
+    let search_response: SearchResponse = response.limited_json().await?;
 
     if search_response.data.is_empty() {
         return Ok(format!("% No CurseForge mods found for: {}", query));
@@ -478,3 +484,37 @@ fn format_date(date_str: &str) -> String {
         date_str.to_string()
     }
 }
+
+/// `-CURSEFORGE` query service
+pub struct CurseForgeService;
+
+#[async_trait]
+impl QueryService for CurseForgeService {
+    fn name(&self) -> &'static str {
+        "curseforge"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-CURSEFORGE"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "CurseForge mod information (requires API key)",
+            example: "jei-CURSEFORGE or 238222-CURSEFORGE",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "CurseForge API"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        query_curseforge(target).await
+    }
+}
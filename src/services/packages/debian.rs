@@ -11,7 +11,13 @@
 use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 const DEBIAN_API_BASE: &str = "https://sources.debian.org/api/src";
 const DEBIAN_PACKAGES_BASE: &str = "https://packages.debian.org";
 const UBUNTU_PACKAGES_BASE: &str = "https://packages.ubuntu.com";
@@ -104,7 +110,7 @@ async fn query_debian_api(package_name: &str) -> Result<DebianPackageResponse> {
     }
 
     let package_response: DebianPackageResponse = response
-        .json()
+        .limited_json()
         .await
         .context("Failed to parse Debian API response")?;
 
@@ -236,6 +242,40 @@ fn format_debian_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-DEBIAN` query service
+pub struct DebianService;
+
+#[async_trait]
+impl QueryService for DebianService {
+    fn name(&self) -> &'static str {
+        "debian"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-DEBIAN"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "Debian package information",
+            example: "curl-DEBIAN",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Debian sources API"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_debian_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
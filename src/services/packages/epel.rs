@@ -20,6 +20,11 @@ use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 // EPEL repository URLs for different versions
 const EPEL_10_REPO: &str = "https://dl.fedoraproject.org/pub/epel/10/Everything/x86_64";
 const EPEL_9_REPO: &str = "https://dl.fedoraproject.org/pub/epel/9/Everything/x86_64";
@@ -268,6 +273,40 @@ fn format_epel_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-EPEL` query service
+pub struct EpelService;
+
+#[async_trait]
+impl QueryService for EpelService {
+    fn name(&self) -> &'static str {
+        "epel"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-EPEL"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "EPEL package information",
+            example: "htop-EPEL",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "EPEL repositories"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_epel_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -8,6 +8,8 @@
 // the Free Software Foundation, either version 3 of the License, or
 // (at your option) any later version.
 
+use std::time::Duration;
+
 pub mod alma;
 pub mod aosc;
 pub mod aur;
@@ -38,3 +40,6 @@ pub use opensuse::process_opensuse_query;
 pub use openwrt::process_openwrt_query;
 pub use pypi::process_pypi_query;
 pub use ubuntu::process_ubuntu_query;
+
+/// Response cache TTL of package metadata, which changes with releases only
+pub const PACKAGE_CACHE_TTL: Duration = Duration::from_secs(24 * 3600);
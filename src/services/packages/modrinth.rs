@@ -19,6 +19,12 @@
 use anyhow::Result;
 use reqwest::Client;
 use serde::Deserialize;
+use async_trait::async_trait;
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
@@ -135,7 +141,7 @@ pub async fn query_modrinth(package_name: &str) -> Result<String> {
 
     let result = if let Ok(response) = project_result {
         if response.status().is_success() {
-            let project: ModrinthProject = response.json().await?;
+            let project: ModrinthProject = response.limited_json().await?;
             format_project_info(&project)
         } else {
             // 如果直接查询失败，尝试搜索
@@ -161,7 +167,7 @@ async fn search_modrinth(client: &Client, query: &str) -> Result<String> {
         return Ok(format!("% Modrinth query failed: {}", response.status()));
     }
 
-    let search_result: SearchResponse = response.json().await?;
+    let search_result: SearchResponse = response.limited_json().await?;
 
     if search_result.hits.is_empty() {
         return Ok(format!("% No Modrinth projects found for: {}", query));
@@ -450,3 +456,37 @@ fn format_date(date_str: &str) -> String {
         date_str.to_string()
     }
 }
+
+/// `-MODRINTH` query service
+pub struct ModrinthService;
+
+#[async_trait]
+impl QueryService for ModrinthService {
+    fn name(&self) -> &'static str {
+        "modrinth"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-MODRINTH"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "Modrinth mod/resource pack information",
+            example: "sodium-MODRINTH",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Modrinth API"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        query_modrinth(target).await
+    }
+}
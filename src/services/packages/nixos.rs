@@ -19,7 +19,13 @@
 use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 const NIXOS_SEARCH_API: &str = "https://search.nixos.org/packages";
 const NIXOS_SEARCH_URL: &str = "https://search.nixos.org/packages?query=";
 
@@ -118,7 +124,7 @@ async fn query_nixos_packages(package_name: &str) -> Result<NixOSSearchResponse>
     }
 
     let html_content = response
-        .text()
+        .limited_text()
         .await
         .context("Failed to get HTML content from NixOS search page")?;
 
@@ -340,6 +346,40 @@ fn format_nixos_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-NIXOS` query service
+pub struct NixOsService;
+
+#[async_trait]
+impl QueryService for NixOsService {
+    fn name(&self) -> &'static str {
+        "nixos"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-NIXOS"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "NixOS package information",
+            example: "nixpkgs-NIXOS",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "NixOS search"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_nixos_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -20,7 +20,14 @@ use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 const NPM_REGISTRY_URL: &str = "https://registry.npmjs.org/";
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -179,7 +186,7 @@ async fn query_npm_package(package_name: &str) -> Result<NPMPackage> {
     } else {
         urlencoding::encode(package_name).to_string()
     };
-    let package_url = format!("{}{}", NPM_REGISTRY_URL, encoded_name);
+    let package_url = format!("{}{}", api_base(NPM_REGISTRY_URL), encoded_name);
 
     log_debug!("Querying NPM registry: {}", package_url);
 
@@ -201,7 +208,7 @@ async fn query_npm_package(package_name: &str) -> Result<NPMPackage> {
     }
 
     let package_response: NPMPackageResponse = response
-        .json()
+        .limited_json()
         .await
         .context("Failed to parse NPM package data")?;
 
@@ -414,9 +421,44 @@ fn format_npm_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-NPM` query service
+pub struct NpmService;
+
+#[async_trait]
+impl QueryService for NpmService {
+    fn name(&self) -> &'static str {
+        "npm"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-NPM"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "Node.js NPM package information",
+            example: "react-NPM",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "npm registry"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_npm_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::fixtures::FixtureServer;
 
     #[tokio::test]
     async fn test_npm_package_name_validation() {
@@ -444,4 +486,23 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().contains("NPM Package"));
     }
+
+    #[tokio::test]
+    async fn test_npm_fixture() {
+        let mut fixtures = FixtureServer::start("npm").await;
+        fixtures.serve(NPM_REGISTRY_URL, "left-pad", "left-pad.json").await;
+        fixtures.serve_status(NPM_REGISTRY_URL, "no-such-package", 404).await;
+
+        let response = fixtures.run(process_npm_query("left-pad")).await.unwrap();
+        assert!(response.contains("package-name: left-pad\n"));
+        assert!(response.contains("version: 1.3.0\n"));
+        assert!(response.contains("author: azer\n"));
+        assert!(response.contains("license: WTFPL\n"));
+        assert!(response.contains("repository: git+ssh://git@github.com/stevemao/left-pad.git\n"));
+        assert!(response.contains("maintainers: stevemao, azer\n"));
+        assert!(response.contains("file-count: 10\n"));
+
+        let response = fixtures.run(process_npm_query("no-such-package")).await.unwrap();
+        assert!(response.starts_with("NPM Package Not Found: no-such-package\n"));
+    }
 }
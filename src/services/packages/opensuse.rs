@@ -19,7 +19,13 @@
 use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 const OPENSUSE_SEARCH_URL: &str = "https://software.opensuse.org/search";
 const OPENSUSE_PACKAGES_URL: &str = "https://software.opensuse.org/package/";
 
@@ -120,7 +126,7 @@ async fn query_opensuse_packages(package_name: &str) -> Result<OpenSUSESearchRes
     }
 
     let html_content = response
-        .text()
+        .limited_text()
         .await
         .context("Failed to get HTML content from OpenSUSE search page")?;
 
@@ -317,6 +323,40 @@ fn format_opensuse_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-OPENSUSE` query service
+pub struct OpenSuseService;
+
+#[async_trait]
+impl QueryService for OpenSuseService {
+    fn name(&self) -> &'static str {
+        "opensuse"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-OPENSUSE"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "OpenSUSE package information",
+            example: "zypper-OPENSUSE",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "openSUSE repositories"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_opensuse_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -20,6 +20,11 @@ use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 #[allow(dead_code)]
 const OPENWRT_PACKAGES_API: &str = "https://downloads.openwrt.org/releases";
 const OPENWRT_PACKAGES_SEARCH: &str = "https://openwrt.org/packages";
@@ -266,6 +271,40 @@ fn format_openwrt_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-OPENWRT` query service
+pub struct OpenWrtService;
+
+#[async_trait]
+impl QueryService for OpenWrtService {
+    fn name(&self) -> &'static str {
+        "openwrt"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-OPENWRT"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "OpenWrt package information",
+            example: "luci-OPENWRT",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "OpenWrt package index"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_openwrt_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -20,7 +20,14 @@ use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 const PYPI_API_URL: &str = "https://pypi.org/pypi/";
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -116,7 +123,7 @@ async fn query_pypi_package(package_name: &str) -> Result<PyPIResponse> {
         .build()
         .context("Failed to create HTTP client")?;
 
-    let package_url = format!("{}{}/json", PYPI_API_URL, urlencoding::encode(package_name));
+    let package_url = format!("{}{}/json", api_base(PYPI_API_URL), urlencoding::encode(package_name));
 
     log_debug!("Querying PyPI API: {}", package_url);
 
@@ -138,7 +145,7 @@ async fn query_pypi_package(package_name: &str) -> Result<PyPIResponse> {
     }
 
     let package_data: PyPIResponse = response
-        .json()
+        .limited_json()
         .await
         .context("Failed to parse PyPI package data")?;
 
@@ -333,9 +340,44 @@ fn format_pypi_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-PYPI` query service
+pub struct PypiService;
+
+#[async_trait]
+impl QueryService for PypiService {
+    fn name(&self) -> &'static str {
+        "pypi"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-PYPI"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "Python PyPI package information",
+            example: "requests-PYPI",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "PyPI JSON API"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_pypi_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::fixtures::FixtureServer;
 
     #[tokio::test]
     async fn test_pypi_package_name_validation() {
@@ -355,4 +397,23 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().contains("PyPI Package"));
     }
+
+    #[tokio::test]
+    async fn test_pypi_fixture() {
+        let mut fixtures = FixtureServer::start("pypi").await;
+        fixtures.serve(PYPI_API_URL, "requests/json", "requests.json").await;
+        fixtures.serve_status(PYPI_API_URL, "no-such-package/json", 404).await;
+
+        let response = fixtures.run(process_pypi_query("requests")).await.unwrap();
+        assert!(response.contains("package-name: requests\n"));
+        assert!(response.contains("version: 2.32.3\n"));
+        assert!(response.contains("author: Kenneth Reitz <me@kennethreitz.org>\n"));
+        assert!(response.contains("requires-python: >=3.8\n"));
+        assert!(response.contains("dependencies: charset-normalizer<4,>=2, idna<4,>=2.5, urllib3<3,>=1.21.1"));
+        assert!(response.contains("development-status: 5 - Production/Stable\n"));
+        assert!(response.contains("wheel-files: 1\nsource-files: 1\n"));
+
+        let response = fixtures.run(process_pypi_query("no-such-package")).await.unwrap();
+        assert!(response.starts_with("PyPI Package Not Found: no-such-package\n"));
+    }
 }
@@ -19,7 +19,13 @@
 use anyhow::{Context, Result};
 use reqwest;
 use serde::{Deserialize, Serialize};
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
+use async_trait::async_trait;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use super::PACKAGE_CACHE_TTL;
+use std::time::Duration;
 const UBUNTU_PACKAGES_API: &str = "https://api.launchpad.net/1.0/ubuntu/+archive/primary";
 const UBUNTU_PACKAGES_SEARCH: &str = "https://packages.ubuntu.com";
 
@@ -112,7 +118,7 @@ async fn query_ubuntu_packages(package_name: &str) -> Result<UbuntuSearchResult>
     }
 
     let search_result: UbuntuSearchResult = response
-        .json()
+        .limited_json()
         .await
         .context("Failed to parse Ubuntu packages API response")?;
 
@@ -200,6 +206,40 @@ fn format_ubuntu_not_found(package_name: &str) -> String {
     )
 }
 
+/// `-UBUNTU` query service
+pub struct UbuntuService;
+
+#[async_trait]
+impl QueryService for UbuntuService {
+    fn name(&self) -> &'static str {
+        "ubuntu"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-UBUNTU"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "PACKAGE REPOSITORIES",
+            summary: "Ubuntu package information",
+            example: "firefox-UBUNTU",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Launchpad API"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(PACKAGE_CACHE_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_ubuntu_query(target).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,291 @@
+// WHOIS Server - DN42 Peering Config Templates
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! DN42 peering config templates (`-PEERCONF`)
+//!
+//! `<your-asn>,<peer-asn>[,<endpoint>]-PEERCONF` looks both networks up in
+//! the DN42 registry and prints a WireGuard interface and a BIRD2 `protocol
+//! bgp` stanza for the session. WireGuard keys, endpoints and link-local
+//! addresses are taken from the `remarks:` of the aut-num and its mntner
+//! when published there; everything else follows the usual DN42 conventions
+//! (listen port `2` + last four digits of the peer ASN, `fe80::<last four>`
+//! link-local addresses) and is left as a placeholder to fill in.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::net::Ipv6Addr;
+
+use crate::core::request_context::RequestContext;
+use crate::dn42::query::parse_asn;
+use crate::dn42::query_dn42_raw_managed;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+
+/// Address ranges routed inside DN42 (and the overlay networks it peers with)
+const DN42_ALLOWED_IPS: &str = "172.20.0.0/14, 172.31.0.0/16, 10.0.0.0/8, fd00::/8, fe80::/64";
+
+/// A network as described by the registry
+#[derive(Debug, Default, Clone, PartialEq)]
+struct PeerInfo {
+    asn: u32,
+    as_name: Option<String>,
+    mnt_by: Option<String>,
+    wireguard_key: Option<String>,
+    endpoint: Option<String>,
+    link_local: Option<Ipv6Addr>,
+}
+
+impl PeerInfo {
+    /// Last four digits of the ASN, used for ports, link-local addresses and names
+    fn short(&self) -> String {
+        let digits = self.asn.to_string();
+        digits[digits.len().saturating_sub(4)..].to_string()
+    }
+
+    /// Published link-local address, or `fe80::<last four digits>`
+    fn link_local(&self) -> String {
+        self.link_local.map_or_else(|| format!("fe80::{}", self.short()), |addr| addr.to_string())
+    }
+
+    fn label(&self) -> String {
+        match &self.as_name {
+            Some(name) => format!("AS{} ({})", self.asn, name),
+            None => format!("AS{}", self.asn),
+        }
+    }
+}
+
+/// Parse `<your-asn>,<peer-asn>[,<endpoint>]` (`:` also separates the ASNs)
+fn parse_peerconf_query(query: &str) -> Option<(u32, u32, Option<String>)> {
+    let mut parts = query.split(',').map(str::trim);
+    let first = parts.next()?;
+    let (local, peer) = match first.split_once(':') {
+        Some((local, peer)) => (local, peer),
+        None => (first, parts.next()?),
+    };
+    let endpoint = parts.next().filter(|e| !e.is_empty()).map(str::to_string);
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let asn = |s: &str| parse_asn(s)?.strip_prefix("AS")?.parse::<u32>().ok();
+    Some((asn(local)?, asn(peer)?, endpoint))
+}
+
+/// WireGuard public key (32 bytes of base64)
+fn is_wireguard_key(token: &str) -> bool {
+    token.len() == 44 &&
+        token.ends_with('=') &&
+        token[..43].bytes().all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+}
+
+/// Fill in the registry attributes of an aut-num or mntner object
+fn read_object(info: &mut PeerInfo, object: &str) {
+    for line in object.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "as-name" if info.as_name.is_none() => info.as_name = Some(value.to_string()),
+            "mnt-by" if info.mnt_by.is_none() => info.mnt_by = Some(value.to_string()),
+            "remarks" => read_remarks(info, value),
+            _ => {}
+        }
+    }
+}
+
+/// Look for a WireGuard key, endpoint and link-local address in a `remarks:` line
+fn read_remarks(info: &mut PeerInfo, remarks: &str) {
+    let tokens: Vec<&str> = remarks
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .map(|t| t.trim_matches(|c| c == '"' || c == '\'' || c == '(' || c == ')'))
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if info.wireguard_key.is_none() && is_wireguard_key(token) {
+            info.wireguard_key = Some(token.to_string());
+        }
+        if info.link_local.is_none()
+            && let Ok(addr) = token.split('/').next().unwrap_or(token).parse::<Ipv6Addr>()
+            && addr.segments()[0] == 0xfe80
+        {
+            info.link_local = Some(addr);
+        }
+        if info.endpoint.is_none() && i > 0 && tokens[i - 1].trim_end_matches(':').eq_ignore_ascii_case("endpoint") {
+            info.endpoint = Some(token.to_string());
+        }
+    }
+}
+
+async fn lookup_peer(asn: u32) -> Result<Option<PeerInfo>> {
+    let aut_num = query_dn42_raw_managed(&format!("AS{}", asn)).await?;
+    if !aut_num.contains("aut-num:") {
+        return Ok(None);
+    }
+    let mut info = PeerInfo { asn, ..Default::default() };
+    read_object(&mut info, &aut_num);
+
+    if let Some(mntner) = info.mnt_by.clone() {
+        let object = query_dn42_raw_managed(&mntner).await.unwrap_or_default();
+        read_object(&mut info, &object);
+    }
+    Ok(Some(info))
+}
+
+fn format_peerconf(local: &PeerInfo, peer: &PeerInfo, endpoint: Option<&str>) -> String {
+    let interface = format!("dn42_{}", peer.short());
+    let protocol = match &peer.as_name {
+        Some(name) => format!("dn42_{}", name.to_lowercase().replace(|c: char| !c.is_ascii_alphanumeric(), "_")),
+        None => interface.clone(),
+    };
+    let endpoint = endpoint
+        .map(str::to_string)
+        .or_else(|| peer.endpoint.clone())
+        .unwrap_or_else(|| format!("<PEER_HOST>:2{}", local.short()));
+
+    let mut output = String::new();
+    output.push_str(&format!("% DN42 peering template: {} <-> {}\n", local.label(), peer.label()));
+    output.push_str("% TEMPLATE ONLY - review every value and replace the <PLACEHOLDERS> before use.\n");
+    output.push_str("% Values marked 'registry' come from the DN42 registry, the rest are conventions.\n");
+    output.push_str("%\n");
+    output.push_str(&format!("% peer-wireguard-key: {}\n", if peer.wireguard_key.is_some() { "registry" } else { "not published" }));
+    output.push_str(&format!("% peer-endpoint:      {}\n", if peer.endpoint.is_some() { "registry" } else { "not published" }));
+    output.push_str(&format!("% peer-link-local:    {}\n", if peer.link_local.is_some() { "registry" } else { "convention" }));
+    output.push('\n');
+
+    output.push_str(&format!("# /etc/wireguard/{}.conf\n", interface));
+    output.push_str("[Interface]\n");
+    output.push_str("PrivateKey = <YOUR_PRIVATE_KEY>\n");
+    output.push_str(&format!("ListenPort = 2{}\n", peer.short()));
+    output.push_str("Table = off\n");
+    output.push_str(&format!("PostUp = ip addr add {}/64 dev %i\n", local.link_local()));
+    output.push_str("PostUp = ip addr add <YOUR_DN42_IPV4>/32 peer <PEER_DN42_IPV4>/32 dev %i\n");
+    output.push_str("PostUp = sysctl -w net.ipv6.conf.%i.autoconf=0\n");
+    output.push('\n');
+    output.push_str("[Peer]\n");
+    output.push_str(&format!(
+        "PublicKey = {}\n",
+        peer.wireguard_key.as_deref().unwrap_or("<PEER_PUBLIC_KEY>")
+    ));
+    output.push_str(&format!("Endpoint = {}\n", endpoint));
+    output.push_str(&format!("AllowedIPs = {}\n", DN42_ALLOWED_IPS));
+    output.push('\n');
+
+    output.push_str(&format!("# /etc/bird/peers/{}.conf (uses the dnpeers template from the DN42 wiki)\n", interface));
+    output.push_str(&format!("protocol bgp {} from dnpeers {{\n", protocol));
+    output.push_str(&format!("    neighbor {}%{} as {};\n", peer.link_local(), interface, peer.asn));
+    output.push_str("    # local as is set in the dnpeers template:\n");
+    output.push_str(&format!("    # local as {};\n", local.asn));
+    output.push_str("}\n");
+    output
+}
+
+/// Build a WireGuard + BIRD2 template for a DN42 session
+pub async fn process_peerconf_query(query: &str) -> Result<String> {
+    let Some((local_asn, peer_asn, endpoint)) = parse_peerconf_query(query) else {
+        return Ok(format!(
+            "Invalid peering query: {}\nUse <your-asn>,<peer-asn>[,<endpoint>], e.g. 4242421080,4242420253-PEERCONF\n",
+            query
+        ));
+    };
+    if local_asn == peer_asn {
+        return Ok(format!("Both ASNs are AS{}; give your ASN and the peer's ASN\n", local_asn));
+    }
+
+    let Some(local) = lookup_peer(local_asn).await? else {
+        return Ok(format!("AS{} was not found in the DN42 registry\n", local_asn));
+    };
+    let Some(peer) = lookup_peer(peer_asn).await? else {
+        return Ok(format!("AS{} was not found in the DN42 registry\n", peer_asn));
+    };
+    Ok(format_peerconf(&local, &peer, endpoint.as_deref()))
+}
+
+/// `-PEERCONF`: WireGuard + BIRD2 session template for two DN42 networks
+pub struct PeerConfService;
+
+#[async_trait]
+impl QueryService for PeerConfService {
+    fn name(&self) -> &'static str {
+        "peerconf"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-PEERCONF"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "DN42 NETWORK",
+            summary: "WireGuard + BIRD2 peering template from DN42 registry data",
+            example: "4242421080,4242420253-PEERCONF",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "DN42 registry (aut-num and mntner objects)"
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_peerconf_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUT_NUM: &str = "aut-num:            AS4242420253
+as-name:            MOE-DN42
+mnt-by:             MOE-MNT
+remarks:            WireGuard: Endpoint: dn42.example.net:21080 key: Hn1vKS5lT4Gp6lJt0o6fGVJmcD0d/Pp3ZbBqT+3pUwE=
+remarks:            link-local fe80::253/64
+source:             DN42
+";
+
+    #[test]
+    fn test_parse_peerconf_query() {
+        assert_eq!(parse_peerconf_query("4242421080,4242420253"), Some((4242421080, 4242420253, None)));
+        assert_eq!(parse_peerconf_query("1080:253"), Some((4242421080, 4242420253, None)));
+        assert_eq!(
+            parse_peerconf_query("AS4242421080, AS4242420253, [2001:db8::1]:21080"),
+            Some((4242421080, 4242420253, Some("[2001:db8::1]:21080".to_string())))
+        );
+        assert_eq!(parse_peerconf_query("4242421080"), None);
+        assert_eq!(parse_peerconf_query("foo,bar"), None);
+    }
+
+    #[test]
+    fn test_read_registry_object() {
+        let mut info = PeerInfo { asn: 4242420253, ..Default::default() };
+        read_object(&mut info, AUT_NUM);
+        assert_eq!(info.as_name.as_deref(), Some("MOE-DN42"));
+        assert_eq!(info.mnt_by.as_deref(), Some("MOE-MNT"));
+        assert_eq!(info.endpoint.as_deref(), Some("dn42.example.net:21080"));
+        assert_eq!(info.wireguard_key.as_deref(), Some("Hn1vKS5lT4Gp6lJt0o6fGVJmcD0d/Pp3ZbBqT+3pUwE="));
+        assert_eq!(info.link_local, Some("fe80::253".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_format_peerconf() {
+        let local = PeerInfo { asn: 4242421080, ..Default::default() };
+        let mut peer = PeerInfo { asn: 4242420253, ..Default::default() };
+        read_object(&mut peer, AUT_NUM);
+
+        let output = format_peerconf(&local, &peer, None);
+        assert!(output.contains("TEMPLATE ONLY"));
+        assert!(output.contains("ListenPort = 20253\n"));
+        assert!(output.contains("PostUp = ip addr add fe80::1080/64 dev %i\n"));
+        assert!(output.contains("Endpoint = dn42.example.net:21080\n"));
+        assert!(output.contains("PublicKey = Hn1vKS5lT4Gp6lJt0o6fGVJmcD0d/Pp3ZbBqT+3pUwE=\n"));
+        assert!(output.contains("protocol bgp dn42_moe_dn42 from dnpeers {\n"));
+        assert!(output.contains("    neighbor fe80::253%dn42_0253 as 4242420253;\n"));
+
+        let output = format_peerconf(&local, &PeerInfo { asn: 4242420253, ..Default::default() }, None);
+        assert!(output.contains("PublicKey = <PEER_PUBLIC_KEY>\n"));
+        assert!(output.contains("Endpoint = <PEER_HOST>:21080\n"));
+        assert!(output.contains("    neighbor fe80::0253%dn42_0253 as 4242420253;\n"));
+    }
+}
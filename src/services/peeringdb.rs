@@ -1,4 +1,5 @@
 use crate::config::{PEERINGDB_CACHE_TTL, PEERINGDB_LMDB_PATH};
+use crate::core::body_limit::LimitedBody;
 use crate::storage::lmdb::LmdbStorage;
 use crate::{log_debug};
 use anyhow::Result;
@@ -218,7 +219,7 @@ pub async fn query_peeringdb_asn(asn: &str) -> Result<String> {
     if !response.status().is_success() {
         let status = response.status();
         let error_body = response
-            .text()
+            .limited_text()
             .await
             .unwrap_or_else(|_| "Failed to read error response".to_string());
         log_debug!("PeeringDB API error response: {}", error_body);
@@ -229,7 +230,7 @@ pub async fn query_peeringdb_asn(asn: &str) -> Result<String> {
         ));
     }
 
-    let body = response.text().await?;
+    let body = response.limited_text().await?;
     let pdb_response: PeeringDBResponse<NetworkInfo> = serde_json::from_str(&body)
         .map_err(|e| anyhow::anyhow!("Failed to parse PeeringDB response: {}", e))?;
 
@@ -302,7 +303,7 @@ pub async fn query_peeringdb_ix(ix_id: &str) -> Result<String> {
     if !response.status().is_success() {
         let status = response.status();
         let error_body = response
-            .text()
+            .limited_text()
             .await
             .unwrap_or_else(|_| "Failed to read error response".to_string());
         log_debug!("PeeringDB API error response: {}", error_body);
@@ -313,7 +314,7 @@ pub async fn query_peeringdb_ix(ix_id: &str) -> Result<String> {
         ));
     }
 
-    let body = response.text().await?;
+    let body = response.limited_text().await?;
     let pdb_response: PeeringDBResponse<InternetExchangeInfo> = serde_json::from_str(&body)
         .map_err(|e| anyhow::anyhow!("Failed to parse PeeringDB response: {}", e))?;
 
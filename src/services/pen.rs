@@ -1,8 +1,12 @@
+use crate::config::PEN_LMDB_PATH;
+use crate::core::body_limit::LimitedBody;
+use crate::core::scheduler::{Job, Schedule};
+use crate::core::warmup::{Subsystem, ensure_ready};
 use crate::storage::lmdb::LmdbStorage;
 use crate::{log_debug, log_info, log_warn};
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 /// IANA Private Enterprise Number entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PenEntry {
@@ -90,17 +94,20 @@ static PEN_UPDATE_RUNNING: std::sync::atomic::AtomicBool =
 
 impl PenService {
     pub fn new() -> Result<Self> {
-        let storage = LmdbStorage::new("./cache/pen_cache")?;
+        let storage = LmdbStorage::new(PEN_LMDB_PATH)?;
         let data_url = "https://www.iana.org/assignments/enterprise-numbers.txt".to_string();
 
         Ok(Self { storage, data_url })
     }
 
     /// Check if cache needs update (older than 1 day)
-    pub fn needs_update(&self) -> Result<bool> {
-        let last_update_key = "pen_last_update";
+    /// Unix time of the last download, if there was one
+    pub fn last_update(&self) -> Result<Option<u64>> {
+        self.storage.get_json::<u64>("pen_last_update")
+    }
 
-        match self.storage.get_json::<u64>(&last_update_key) {
+    pub fn needs_update(&self) -> Result<bool> {
+        match self.last_update() {
             Ok(Some(last_update)) => {
                 let now = SystemTime::now()
                     .duration_since(UNIX_EPOCH)
@@ -317,7 +324,7 @@ impl PenService {
             ));
         }
 
-        let content = response.text().await?;
+        let content = response.limited_text().await?;
         log_info!("Downloaded {} bytes of PEN data", content.len());
 
         Ok(content)
@@ -453,6 +460,7 @@ impl PenService {
 
 /// Process PEN query (public function for use in query_processor)
 pub async fn process_pen_query(query: &str) -> Result<String> {
+    ensure_ready(Subsystem::Pen)?;
     let service = PenService::new()?;
     service.handle_query(query).await
 }
@@ -491,54 +499,43 @@ pub async fn pen_update_cache() -> Result<()> {
     result
 }
 
-/// Start periodic PEN cache update task (call this from main.rs)
-pub async fn start_pen_periodic_update() {
-    use tokio::time::{Duration, interval};
-
-    log_info!("Starting PEN periodic update task (checking every hour)");
-
-    // Immediately check and update on startup
-    log_info!("PEN: Performing initial cache check on startup");
-    match pen_needs_update().await {
-        Ok(true) => {
-            log_info!("PEN cache needs initial update, starting download...");
+/// Startup check of the PEN cache (a step of the startup graph)
+///
+/// Only a server without any copy of the list waits for the download; a
+/// stale copy keeps answering queries while the new one is fetched.
+pub async fn pen_initial_update() -> Result<String> {
+    let service = PenService::new()?;
+    if !service.needs_update()? {
+        return Ok("cache is up to date".to_string());
+    }
+    if service.last_update()?.is_some() {
+        tokio::spawn(async {
             if let Err(e) = pen_update_cache().await {
-                log_warn!("Failed to perform initial PEN cache update: {}", e);
-            } else {
-                log_info!("PEN cache initial update completed successfully");
+                log_warn!("Failed to refresh stale PEN cache: {}", e);
             }
-        }
-        Ok(false) => {
-            log_info!("PEN cache is up to date on startup");
-        }
-        Err(e) => {
-            log_warn!("Failed to check PEN update status on startup: {}", e);
-        }
+        });
+        return Ok("refreshing stale cache in the background".to_string());
     }
+    pen_update_cache().await?;
+    Ok("downloaded".to_string())
+}
 
-    let mut check_interval = interval(Duration::from_secs(3600)); // Check every hour
-    check_interval.tick().await; // Skip the first tick
-
-    loop {
-        check_interval.tick().await;
+/// Hourly check of the PEN cache; the startup check is done by [`pen_initial_update`]
+pub fn pen_update_job() -> Job {
+    Job::new("pen-update", Schedule::Every(Duration::from_secs(3600)), pen_periodic_update)
+        .with_jitter(Duration::from_secs(300))
+}
 
-        match pen_needs_update().await {
-            Ok(true) => {
-                log_info!("PEN cache needs update, starting update...");
-                if let Err(e) = pen_update_cache().await {
-                    log_warn!("Failed to update PEN cache: {}", e);
-                } else {
-                    log_info!("PEN cache updated successfully");
-                }
-            }
-            Ok(false) => {
-                log_debug!("PEN cache is up to date");
-            }
-            Err(e) => {
-                log_warn!("Failed to check PEN update status: {}", e);
-            }
-        }
+/// Update the PEN cache when it is older than a day
+async fn pen_periodic_update() -> Result<()> {
+    if !pen_needs_update().await? {
+        log_debug!("PEN cache is up to date");
+        return Ok(());
     }
+    log_info!("PEN cache needs update, starting update...");
+    pen_update_cache().await?;
+    log_info!("PEN cache updated successfully");
+    Ok(())
 }
 
 #[cfg(test)]
@@ -4,6 +4,7 @@
 //! detailed information including ASN, geolocation, and PTR records.
 //!
 //! Supports location-based queries: target-location-PING (e.g., 1.1.1.1-tw-PING)
+//! and vantage point selectors: target-PING@location (e.g., 1.1.1.1-PING@as13335)
 
 use anyhow::Result;
 use crate::services::utils::{
@@ -14,7 +15,9 @@ use crate::services::utils::{
     PingOptions,
     MeasurementOptions,
     MeasurementLocation,
+    parse_vantage_points,
 };
+use crate::services::utils::globalping::Timings;
 use crate::{log_debug, log_error};
 
 /// Parse a query with optional location code
@@ -51,8 +54,9 @@ fn parse_location_query<'a>(query: &'a str) -> Result<(&'a str, Option<String>)>
 
 /// Process a ping query with -PING suffix
 /// Supports optional location code: target-location-PING (e.g., 1.1.1.1-tw-PING)
-pub async fn process_ping_query(query: &str) -> Result<String> {
-    log_debug!("Processing ping query: {}", query);
+/// A vantage point selector (`-PING@de,us`) takes precedence over the location code
+pub async fn process_ping_query(query: &str, vantage: Option<&str>) -> Result<String> {
+    log_debug!("Processing ping query: {} (vantage: {:?})", query, vantage);
 
     // Parse target and location
     // The suffix has already been removed by query.rs
@@ -88,19 +92,10 @@ pub async fn process_ping_query(query: &str) -> Result<String> {
     };
 
     // Add location if specified
-    if let Some(loc) = location {
-        request.locations = Some(vec![MeasurementLocation {
-            magic: Some(loc),
-            limit: None,
-            continent: None,
-            region: None,
-            country: None,
-            state: None,
-            city: None,
-            asn: None,
-            network: None,
-            tags: None,
-        }]);
+    if let Some(spec) = vantage {
+        request.locations = parse_vantage_points(spec);
+    } else if let Some(loc) = location {
+        request.locations = Some(vec![MeasurementLocation::magic(&loc)]);
     }
 
     let measurement_id = match globalping.submit_measurement(&request).await {
@@ -224,7 +219,7 @@ async fn format_ping_output(
         }
 
         // Individual packet times
-        if let Some(timings) = &test_result.timings {
+        if let Some(timings) = test_result.timings.as_ref().map(Timings::replies) {
             output.push_str("  Times: ");
             for (i, timing) in timings.iter().enumerate() {
                 if i > 0 {
@@ -249,7 +244,7 @@ mod tests {
     #[ignore] // Requires network and API tokens
     async fn test_ping_query_formatting() {
         // This test requires actual API calls
-        let result = process_ping_query("1.1.1.1-PING").await;
+        let result = process_ping_query("1.1.1.1", None).await;
         assert!(result.is_ok());
     }
 }
@@ -5,6 +5,7 @@
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use super::error::{PixivError, PixivResult};
+use crate::core::body_limit::LimitedBody;
 
 /// Pixiv API authentication constants
 pub mod constants {
@@ -74,7 +75,10 @@ impl AuthToken {
             .await?;
 
         if response.status().is_success() {
-            let token_response: AuthResponse = response.json().await?;
+            let token_response: AuthResponse = response
+                .limited_json()
+                .await
+                .map_err(|e| PixivError::InvalidResponse(e.to_string()))?;
             *self = token_response.into();
             Ok(())
         } else {
@@ -186,11 +190,14 @@ impl AuthManager {
             .await?;
 
         if response.status().is_success() {
-            let token_response: AuthResponse = response.json().await?;
+            let token_response: AuthResponse = response
+                .limited_json()
+                .await
+                .map_err(|e| PixivError::InvalidResponse(e.to_string()))?;
             self.token = Some(token_response.into());
             Ok(())
         } else {
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = response.limited_text().await.unwrap_or_default();
             Err(PixivError::Authentication(format!(
                 "Authentication failed: {}",
                 error_text
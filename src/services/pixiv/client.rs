@@ -8,6 +8,7 @@ use reqwest::{Method, StatusCode};
 use std::collections::HashMap;
 use std::env;
 
+use crate::core::body_limit::LimitedBody;
 use crate::{log_info};
 /// Main Pixiv API client
 pub struct PixivClient {
@@ -86,7 +87,10 @@ impl PixivClient {
         let status = response.status();
 
         if status.is_success() {
-            let result: T = response.json().await?;
+            let result: T = response
+                .limited_json()
+                .await
+                .map_err(|e| PixivError::InvalidResponse(e.to_string()))?;
             Ok(result)
         } else {
             match status {
@@ -97,7 +101,7 @@ impl PixivClient {
                     Err(PixivError::RateLimit)
                 }
                 _ => {
-                    let error_text = response.text().await.unwrap_or_default();
+                    let error_text = response.limited_text().await.unwrap_or_default();
                     Err(PixivError::api_error(
                         status.to_string(),
                         error_text,
@@ -0,0 +1,435 @@
+// WHOIS Server - TCP Port Check
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! TCP port reachability (`-PORT`)
+//!
+//! `host:port-PORT` connects from this server to the addresses of the host
+//! and reports each as open, closed (refused) or filtered (no answer), with
+//! the connect time. For a few well-known ports the service banner is read
+//! too: the greeting of server-first protocols such as SSH, SMTP and FTP, or
+//! the status line and `Server` header of HTTP.
+//!
+//! `host:port-<location>-PORT` (e.g. `example.com:443-de-PORT`) checks from a
+//! Globalping probe in that location instead. Globalping only offers HTTP
+//! measurements, so a remote check tells whether a web server answers on the
+//! port, not the state of other services.
+//!
+//! Private, loopback and other internal addresses are never connected to.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::core::request_context::RequestContext;
+use crate::core::is_internal;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::utils::doh::DohClient;
+use crate::services::utils::globalping::Timings;
+use crate::services::utils::{
+    GlobalpingClient,
+    GlobalpingRequest,
+    HttpOptions,
+    HttpRequestOptions,
+    MeasurementLocation,
+    MeasurementOptions,
+};
+use crate::{log_debug, log_error};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const BANNER_TIMEOUT: Duration = Duration::from_secs(3);
+/// Addresses of a host that are checked
+const MAX_ADDRESSES: usize = 4;
+const MAX_BANNER_LEN: usize = 200;
+const MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// How the banner of a port is obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BannerProbe {
+    /// The server speaks first
+    Greeting,
+    /// Answer to a `HEAD /` request
+    HttpHead,
+}
+
+/// Ports whose service banner is read
+const BANNER_PORTS: &[(u16, &str, BannerProbe)] = &[
+    (21, "ftp", BannerProbe::Greeting),
+    (22, "ssh", BannerProbe::Greeting),
+    (25, "smtp", BannerProbe::Greeting),
+    (80, "http", BannerProbe::HttpHead),
+    (110, "pop3", BannerProbe::Greeting),
+    (143, "imap", BannerProbe::Greeting),
+    (587, "submission", BannerProbe::Greeting),
+    (8080, "http-alt", BannerProbe::HttpHead),
+];
+
+/// Outcome of connecting to one address
+#[derive(Debug, PartialEq)]
+enum PortState {
+    Open,
+    /// Connection refused
+    Closed,
+    /// No answer within the connect timeout
+    Filtered,
+    /// Any other error, e.g. no route to the host
+    Unreachable(String),
+}
+
+impl PortState {
+    fn from_error(error: &std::io::Error) -> Self {
+        match error.kind() {
+            ErrorKind::ConnectionRefused => PortState::Closed,
+            ErrorKind::TimedOut => PortState::Filtered,
+            _ => PortState::Unreachable(error.to_string()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            PortState::Open => "open".to_string(),
+            PortState::Closed => "closed (connection refused)".to_string(),
+            PortState::Filtered => format!("filtered (no answer within {} s)", CONNECT_TIMEOUT.as_secs()),
+            PortState::Unreachable(e) => format!("unreachable ({})", e),
+        }
+    }
+}
+
+/// Target of a port check
+#[derive(Debug, PartialEq)]
+struct PortTarget {
+    host: String,
+    port: u16,
+    /// Globalping location to check from, `None` for this server
+    location: Option<String>,
+}
+
+/// Parse `host:port`, `[v6]:port`, optionally followed by `-<location>`
+fn parse_target(target: &str) -> Option<PortTarget> {
+    let target = target.trim();
+    let (target, location) = match target.rsplit_once('-') {
+        Some((rest, location))
+            if (2..=5).contains(&location.len()) &&
+                location.chars().all(|c| c.is_ascii_alphanumeric()) &&
+                rest.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) => {
+            (rest, Some(location.to_ascii_lowercase()))
+        }
+        _ => (target, None),
+    };
+
+    let (host, port) = match target.strip_prefix('[') {
+        Some(rest) => {
+            let (host, port) = rest.split_once("]:")?;
+            host.parse::<std::net::Ipv6Addr>().ok()?;
+            (host, port)
+        }
+        None => target.rsplit_once(':')?,
+    };
+    let port: u16 = port.parse().ok()?;
+
+    let valid_host = host.parse::<IpAddr>().is_ok() ||
+        (!host.is_empty() &&
+            host.contains('.') &&
+            host.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.'));
+    (valid_host && port != 0).then(|| PortTarget { host: host.to_ascii_lowercase(), port, location })
+}
+
+/// Printable first line of a greeting, or status line and `Server` header of an HTTP response
+fn clean_banner(raw: &[u8], probe: BannerProbe) -> Option<String> {
+    let text = String::from_utf8_lossy(raw);
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let banner = match probe {
+        BannerProbe::Greeting => lines.next()?.to_string(),
+        BannerProbe::HttpHead => {
+            let status = lines.next()?.to_string();
+            match lines.find(|line| line.to_ascii_lowercase().starts_with("server:")) {
+                Some(server) => format!("{}, {}", status, server),
+                None => status,
+            }
+        }
+    };
+    let printable: String = banner
+        .chars()
+        .map(|c| if c.is_control() { '?' } else { c })
+        .take(MAX_BANNER_LEN)
+        .collect();
+    (!printable.is_empty()).then_some(printable)
+}
+
+async fn read_banner(stream: &mut TcpStream, host: &str, probe: BannerProbe) -> Option<String> {
+    if probe == BannerProbe::HttpHead {
+        let request = format!("HEAD / HTTP/1.0\r\nHost: {}\r\nUser-Agent: whois-server/1.0\r\n\r\n", host);
+        stream.write_all(request.as_bytes()).await.ok()?;
+    }
+    let mut buffer = [0u8; 1024];
+    let read = tokio::time::timeout(BANNER_TIMEOUT, stream.read(&mut buffer)).await.ok()?.ok()?;
+    clean_banner(&buffer[..read], probe)
+}
+
+/// Connect to one address, reading the banner of whitelisted ports
+async fn check_address(host: &str, address: SocketAddr, probe: Option<BannerProbe>) -> (PortState, Option<f64>, Option<String>) {
+    let start = Instant::now();
+    match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(address)).await {
+        Err(_) => (PortState::Filtered, None, None),
+        Ok(Err(e)) => (PortState::from_error(&e), None, None),
+        Ok(Ok(mut stream)) => {
+            let connect_ms = start.elapsed().as_secs_f64() * 1000.0;
+            let banner = match probe {
+                Some(probe) => read_banner(&mut stream, host, probe).await,
+                None => None,
+            };
+            (PortState::Open, Some(connect_ms), banner)
+        }
+    }
+}
+
+/// A and AAAA addresses of a host, or the host itself if it is an address
+async fn resolve(host: &str) -> Result<Vec<IpAddr>> {
+    if let Ok(address) = host.parse::<IpAddr>() {
+        return Ok(vec![address]);
+    }
+    let doh = DohClient::new();
+    let (a, aaaa) = tokio::join!(doh.query(host, "A"), doh.query(host, "AAAA"));
+    let mut addresses = Vec::new();
+    for (response, type_code) in [(a?, 1), (aaaa?, 28)] {
+        addresses.extend(
+            response.Answer
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|answer| answer.record_type == type_code)
+                .filter_map(|answer| answer.data.parse::<IpAddr>().ok())
+        );
+    }
+    Ok(addresses)
+}
+
+async fn check_locally(ctx: &RequestContext, target: &PortTarget) -> Result<String> {
+    let service = BANNER_PORTS.iter().find(|(port, _, _)| *port == target.port);
+    let mut output = String::new();
+    output.push_str(&format!("% TCP port check for {}:{} from this server\n", target.host, target.port));
+    if let Some((_, name, _)) = service {
+        output.push_str(&format!("% Well-known service: {}\n", name));
+    }
+
+    let addresses = resolve(&target.host).await?;
+    if addresses.is_empty() {
+        output.push_str("\n% The host has no A or AAAA records\n");
+        return Ok(output);
+    }
+
+    let (internal, public): (Vec<IpAddr>, Vec<IpAddr>) = addresses.into_iter().partition(|address| is_internal(*address));
+    let probe = service.map(|(_, _, probe)| *probe);
+    let checks = public
+        .iter()
+        .take(MAX_ADDRESSES)
+        .map(|address| check_address(&target.host, SocketAddr::new(*address, target.port), probe));
+    let results = futures::future::join_all(checks).await;
+    log_debug!("[{}] Checked {} addresses of {}:{}", ctx, results.len(), target.host, target.port);
+
+    for (address, (state, connect_ms, banner)) in public.iter().zip(results) {
+        output.push('\n');
+        output.push_str(&format!("address:        {}\n", address));
+        output.push_str(&format!("state:          {}\n", state.describe()));
+        if let Some(connect_ms) = connect_ms {
+            output.push_str(&format!("connect-time:   {:.1} ms\n", connect_ms));
+        }
+        if let Some(banner) = banner {
+            output.push_str(&format!("banner:         {}\n", banner));
+        }
+    }
+    if public.len() > MAX_ADDRESSES {
+        output.push_str(&format!("\n% Only the first {} of {} addresses were checked\n", MAX_ADDRESSES, public.len()));
+    }
+    for address in internal {
+        output.push_str(&format!("\naddress:        {}\nstate:          not checked (private or reserved address)\n", address));
+    }
+    Ok(output)
+}
+
+async fn check_remotely(ctx: &RequestContext, target: &PortTarget, location: &str) -> Result<String> {
+    let globalping = match GlobalpingClient::new() {
+        Ok(client) => client,
+        Err(e) => {
+            log_error!("Failed to initialize Globalping client: {}", e);
+            return Ok(format!("Port check service error: {}\n", e));
+        }
+    };
+
+    let protocol = if matches!(target.port, 443 | 8443) { "HTTPS" } else { "HTTP" };
+    let request = GlobalpingRequest {
+        measurement_type: "http".to_string(),
+        target: target.host.clone(),
+        limit: Some(1),
+        measurement_options: Some(
+            MeasurementOptions::Http(HttpOptions {
+                request: HttpRequestOptions { method: "HEAD".to_string(), path: "/".to_string() },
+                protocol: protocol.to_string(),
+                port: Some(target.port),
+            })
+        ),
+        locations: Some(vec![MeasurementLocation::magic(location)]),
+        in_progress_updates: Some(false),
+    };
+
+    let measurement_id = match globalping.submit_measurement(&request).await {
+        Ok(id) => id,
+        Err(e) => {
+            log_error!("Failed to submit port check measurement: {}", e);
+            return Ok(format!("Port check failed: {}\n", e));
+        }
+    };
+    log_debug!("[{}] Port check measurement ID: {}", ctx, measurement_id);
+
+    let budget = ctx.remaining().map_or(MAX_WAIT, |left| left.min(MAX_WAIT));
+    let results = match globalping.wait_for_results(&measurement_id, budget.as_secs().max(1)).await {
+        Ok(results) => results,
+        Err(e) => {
+            log_error!("Failed to get port check results: {}", e);
+            return Ok(format!("Port check measurement timed out or failed: {}\n", e));
+        }
+    };
+
+    let mut output = String::new();
+    output.push_str(&format!("% {} check of {}:{} from Globalping probes in {}\n", protocol, target.host, target.port, location));
+    if results.results.is_empty() {
+        output.push_str("\n% No probe matched the location\n");
+    }
+    for probe_result in &results.results {
+        let probe = &probe_result.probe;
+        let result = &probe_result.result;
+        output.push('\n');
+        output.push_str(&format!(
+            "probe:          {} - {}, {} (AS{})\n",
+            probe.network,
+            probe.city.as_deref().unwrap_or("Unknown"),
+            probe.country,
+            probe.asn
+        ));
+        if let Some(address) = &result.resolved_address {
+            output.push_str(&format!("address:        {}\n", address));
+        }
+        match result.status_code {
+            Some(status) => output.push_str(&format!("state:          open (HTTP {})\n", status)),
+            None => {
+                let reason = result.raw_output.as_deref().and_then(|raw| raw.lines().next()).unwrap_or("no answer");
+                output.push_str(&format!("state:          no HTTP answer ({})\n", reason.trim()));
+            }
+        }
+        if let Some(Timings::Http(timings)) = &result.timings {
+            let phases = [
+                ("connect-time:  ", timings.tcp),
+                ("tls-time:      ", timings.tls),
+                ("first-byte:    ", timings.first_byte),
+                ("total-time:    ", timings.total),
+            ];
+            for (label, value) in phases {
+                if let Some(ms) = value {
+                    output.push_str(&format!("{} {:.1} ms\n", label, ms));
+                }
+            }
+        }
+    }
+    output.push_str("\n% Remote checks make an HTTP(S) request; closed, filtered and non-HTTP ports all show no HTTP answer\n");
+    output.push_str("% Data provided by Globalping (globalping.io)\n");
+    Ok(output)
+}
+
+/// Check whether a TCP port is reachable
+pub async fn process_port_query(ctx: &RequestContext, query: &str) -> Result<String> {
+    let Some(target) = parse_target(query) else {
+        return Ok(format!(
+            "Invalid port check target: {}\nUse host:port, e.g. example.com:443-PORT or example.com:443-de-PORT\n",
+            query
+        ));
+    };
+    match &target.location {
+        Some(location) => check_remotely(ctx, &target, location).await,
+        None => check_locally(ctx, &target).await,
+    }
+}
+
+/// `-PORT`: TCP port state, connect time and banner
+pub struct PortService;
+
+#[async_trait]
+impl QueryService for PortService {
+    fn name(&self) -> &'static str {
+        "port"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-PORT"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK MEASUREMENT",
+            summary: "TCP port open/closed/filtered, connect time and service banner; host:port-<location>-PORT checks HTTP from Globalping",
+            example: "example.com:443-PORT",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "direct TCP connect, Globalping for remote locations (live)"
+    }
+
+    fn query_class(&self) -> &'static str {
+        "NETWORK"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        // Live measurement
+        None
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_port_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        let target = |host: &str, port, location: Option<&str>| PortTarget {
+            host: host.to_string(),
+            port,
+            location: location.map(str::to_string),
+        };
+        assert_eq!(parse_target("Example.com:443"), Some(target("example.com", 443, None)));
+        assert_eq!(parse_target("my-host.example.com:22-DE"), Some(target("my-host.example.com", 22, Some("de"))));
+        assert_eq!(parse_target("[2001:4860:4860::8888]:53"), Some(target("2001:4860:4860::8888", 53, None)));
+        assert_eq!(parse_target("8.8.8.8:53-as15169"), Some(target("8.8.8.8", 53, Some("as15169"))));
+        assert_eq!(parse_target("example.com"), None);
+        assert_eq!(parse_target("example.com:0"), None);
+        assert_eq!(parse_target("example.com:70000"), None);
+        assert_eq!(parse_target("exa mple.com:80"), None);
+    }
+
+    #[test]
+    fn test_port_state() {
+        let refused = std::io::Error::from(ErrorKind::ConnectionRefused);
+        assert_eq!(PortState::from_error(&refused), PortState::Closed);
+        assert_eq!(PortState::from_error(&std::io::Error::from(ErrorKind::TimedOut)), PortState::Filtered);
+    }
+
+    #[test]
+    fn test_clean_banner() {
+        assert_eq!(
+            clean_banner(b"SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13\r\n", BannerProbe::Greeting).as_deref(),
+            Some("SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13")
+        );
+        assert_eq!(
+            clean_banner(b"HTTP/1.1 301 Moved Permanently\r\nLocation: /\r\nServer: nginx\r\n\r\n", BannerProbe::HttpHead).as_deref(),
+            Some("HTTP/1.1 301 Moved Permanently, Server: nginx")
+        );
+        assert_eq!(clean_banner(b"220 mail\x1b[31m ready\r\n", BannerProbe::Greeting).as_deref(), Some("220 mail?[31m ready"));
+        assert_eq!(clean_banner(b"\r\n", BannerProbe::Greeting), None);
+    }
+}
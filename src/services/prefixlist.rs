@@ -0,0 +1,338 @@
+// WHOIS Server - Prefix List Generation
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Router prefix lists from IRR data (`AS13335-PREFIXLIST-BIRD`)
+//!
+//! Builds the prefix filter for a peer the way bgpq4 does: an ASN, or the
+//! ASNs an as-set expands to (`services::asset`), and the route/route6
+//! objects with those origins, fetched with IRRd's `!g` and `!6` commands
+//! over one RADB connection. Routes that RPKI origin validation finds
+//! invalid against the ROA snapshot of `-ROA` are left out and listed in the
+//! header. The configuration is Cisco IOS (`-PREFIXLIST`, the bgpq4 default,
+//! or `-PREFIXLIST-CISCO`), Junos (`-PREFIXLIST-JUNIPER`) or BIRD 2
+//! (`-PREFIXLIST-BIRD`), with one list per address family.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use cidr::IpCidr;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use crate::config::{RADB_WHOIS_PORT, RADB_WHOIS_SERVER};
+use crate::core::query::parse_asn;
+use crate::core::request_context::RequestContext;
+use crate::services::asset::{IrrdSession, expand};
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::roa::{RoaSnapshot, RouteValidity, roa_snapshot};
+use crate::{log_debug, log_warn};
+
+/// ASNs a prefix list is generated for; bigger sets are a job for bgpq4
+const MAX_ASNS: usize = 2000;
+
+/// Router configuration syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouterFormat {
+    Cisco,
+    Juniper,
+    Bird,
+}
+
+impl RouterFormat {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Cisco => "Cisco IOS",
+            Self::Juniper => "Junos",
+            Self::Bird => "BIRD 2",
+        }
+    }
+}
+
+/// Route objects registered with an origin ASN
+#[async_trait]
+trait RouteSource: Send {
+    async fn routes(&mut self, asn: u32, ipv6: bool) -> Result<Vec<String>>;
+}
+
+#[async_trait]
+impl RouteSource for IrrdSession {
+    async fn routes(&mut self, asn: u32, ipv6: bool) -> Result<Vec<String>> {
+        let command = if ipv6 { format!("!6AS{}", asn) } else { format!("!gAS{}", asn) };
+        let data = self.command(&command).await?.unwrap_or_default();
+        Ok(data.split_whitespace().map(str::to_string).collect())
+    }
+}
+
+#[derive(Debug, Default)]
+struct PrefixList {
+    ipv4: BTreeSet<IpCidr>,
+    ipv6: BTreeSet<IpCidr>,
+    /// Route objects found, before de-duplication and filtering
+    routes: usize,
+    /// RPKI-invalid (prefix, origin) routes left out
+    invalid: Vec<(IpCidr, u32)>,
+}
+
+/// Routes of every ASN, without the RPKI-invalid ones when a snapshot is given
+async fn collect(source: &mut dyn RouteSource, asns: &BTreeSet<u32>, snapshot: Option<&RoaSnapshot>) -> Result<PrefixList> {
+    let mut list = PrefixList::default();
+    for &asn in asns {
+        for ipv6 in [false, true] {
+            for route in source.routes(asn, ipv6).await? {
+                let Ok(prefix) = route.parse::<IpCidr>() else {
+                    continue;
+                };
+                list.routes += 1;
+                if snapshot.is_some_and(|snapshot| snapshot.validate(&prefix, asn) == RouteValidity::Invalid) {
+                    list.invalid.push((prefix, asn));
+                    continue;
+                }
+                if prefix.is_ipv4() {
+                    list.ipv4.insert(prefix);
+                } else {
+                    list.ipv6.insert(prefix);
+                }
+            }
+        }
+    }
+    Ok(list)
+}
+
+/// Prefix list name for a family, in the characters the format accepts
+fn list_name(name: &str, ipv6: bool, format: RouterFormat) -> String {
+    let name = format!("{}-{}", name.to_uppercase(), if ipv6 { "V6" } else { "V4" });
+    match format {
+        RouterFormat::Bird => name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect(),
+        RouterFormat::Cisco | RouterFormat::Juniper => name.replace(':', "_"),
+    }
+}
+
+fn render(name: &str, list: &PrefixList, format: RouterFormat) -> String {
+    let families = [(false, &list.ipv4), (true, &list.ipv6)];
+    let families = families.iter().filter(|(_, prefixes)| !prefixes.is_empty());
+    let mut output = String::new();
+    match format {
+        RouterFormat::Cisco => {
+            for (ipv6, prefixes) in families {
+                let name = list_name(name, *ipv6, format);
+                let keyword = if *ipv6 { "ipv6" } else { "ip" };
+                output.push_str(&format!("no {} prefix-list {}\n", keyword, name));
+                for prefix in prefixes.iter() {
+                    output.push_str(&format!("{} prefix-list {} permit {}\n", keyword, name, prefix));
+                }
+            }
+        }
+        RouterFormat::Juniper => {
+            output.push_str("policy-options {\n");
+            for (ipv6, prefixes) in families {
+                output.push_str(&format!("replace:\n prefix-list {} {{\n", list_name(name, *ipv6, format)));
+                for prefix in prefixes.iter() {
+                    output.push_str(&format!("    {};\n", prefix));
+                }
+                output.push_str(" }\n");
+            }
+            output.push_str("}\n");
+        }
+        RouterFormat::Bird => {
+            for (ipv6, prefixes) in families {
+                let entries: Vec<String> = prefixes.iter().map(|prefix| format!("    {}", prefix)).collect();
+                output.push_str(&format!("define {} = [\n{}\n];\n", list_name(name, *ipv6, format), entries.join(",\n")));
+            }
+        }
+    }
+    output
+}
+
+fn format_prefix_list(
+    name: &str,
+    asns: usize,
+    list: &PrefixList,
+    format: RouterFormat,
+    rpki: std::result::Result<&RoaSnapshot, String>
+) -> String {
+    let mut output = format!(
+        "% {} prefix list for {} from IRR data on {}\n",
+        format.label(),
+        name.to_uppercase(),
+        RADB_WHOIS_SERVER
+    );
+    match &rpki {
+        Ok(snapshot) => output.push_str(&format!("% RPKI-invalid routes removed using {}\n", snapshot.source())),
+        Err(e) => output.push_str(&format!("% RPKI filtering unavailable ({}), the list is unfiltered IRR data\n", e)),
+    }
+    output.push_str(&format!(
+        "% {} ASNs, {} route objects: {} IPv4 and {} IPv6 prefixes, {} RPKI-invalid routes removed\n",
+        asns,
+        list.routes,
+        list.ipv4.len(),
+        list.ipv6.len(),
+        list.invalid.len()
+    ));
+    for (prefix, origin) in &list.invalid {
+        output.push_str(&format!("% removed: {} AS{} (RPKI invalid)\n", prefix, origin));
+    }
+    if list.ipv4.is_empty() && list.ipv6.is_empty() {
+        output.push_str("% No routes registered for these ASNs, no prefix list generated\n");
+        return output;
+    }
+    output.push('\n');
+    output.push_str(&render(name, list, format));
+    output
+}
+
+/// Process a `-PREFIXLIST` query for an ASN or as-set
+pub async fn process_prefixlist_query(target: &str, format: RouterFormat) -> Result<String> {
+    let target = target.trim();
+    if target.is_empty() || target.contains(char::is_whitespace) {
+        return Err(anyhow!("Usage: <ASN or as-set>-PREFIXLIST[-BIRD|-JUNIPER|-CISCO], got: {}", target));
+    }
+
+    let mut session = IrrdSession::connect(RADB_WHOIS_SERVER, RADB_WHOIS_PORT).await?;
+    let (name, asns) = match parse_asn(target) {
+        Some(asn) => (format!("AS{}", asn), BTreeSet::from([asn])),
+        None => {
+            let expansion = expand(&mut session, target).await?;
+            if expansion.truncated || expansion.asns.len() > MAX_ASNS {
+                return Err(anyhow!(
+                    "{} expands to more than {} ASNs; generate its prefix list with bgpq4",
+                    target.to_uppercase(),
+                    MAX_ASNS
+                ));
+            }
+            (target.to_uppercase(), expansion.asns)
+        }
+    };
+
+    let snapshot = roa_snapshot().await;
+    if let Err(e) = &snapshot {
+        log_warn!("Generating the {} prefix list without RPKI filtering: {}", name, e);
+    }
+    let list = collect(&mut session, &asns, snapshot.as_deref().ok()).await?;
+    log_debug!(
+        "Prefix list for {}: {} routes, {} IPv4, {} IPv6, {} invalid",
+        name,
+        list.routes,
+        list.ipv4.len(),
+        list.ipv6.len(),
+        list.invalid.len()
+    );
+    let rpki = snapshot.as_deref().map_err(|e| e.to_string());
+    Ok(format_prefix_list(&name, asns.len(), &list, format, rpki))
+}
+
+/// `-PREFIXLIST` in one router format
+pub struct PrefixListService(pub RouterFormat);
+
+#[async_trait]
+impl QueryService for PrefixListService {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            RouterFormat::Cisco => "prefixlist",
+            RouterFormat::Juniper => "prefixlist-juniper",
+            RouterFormat::Bird => "prefixlist-bird",
+        }
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        match self.0 {
+            RouterFormat::Cisco => &["-PREFIXLIST", "-PREFIXLIST-CISCO"],
+            RouterFormat::Juniper => &["-PREFIXLIST-JUNIPER"],
+            RouterFormat::Bird => &["-PREFIXLIST-BIRD"],
+        }
+    }
+
+    fn help(&self) -> ServiceHelp {
+        let (summary, example) = match self.0 {
+            RouterFormat::Cisco => (
+                "RPKI-filtered Cisco IOS prefix lists from IRR data for an ASN or as-set",
+                "AS13335-PREFIXLIST",
+            ),
+            RouterFormat::Juniper => (
+                "RPKI-filtered Junos prefix lists from IRR data for an ASN or as-set",
+                "AS13335-PREFIXLIST-JUNIPER",
+            ),
+            RouterFormat::Bird => (
+                "RPKI-filtered BIRD 2 prefix sets from IRR data for an ASN or as-set",
+                "AS-EXAMPLE-PREFIXLIST-BIRD",
+            ),
+        };
+        ServiceHelp { category: "NETWORK INFORMATION", summary, example }
+    }
+
+    fn upstream(&self) -> &'static str {
+        RADB_WHOIS_SERVER
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(3600))
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_prefixlist_query(target, self.0).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct Routes(HashMap<(u32, bool), &'static str>);
+
+    #[async_trait]
+    impl RouteSource for Routes {
+        async fn routes(&mut self, asn: u32, ipv6: bool) -> Result<Vec<String>> {
+            let routes = self.0.get(&(asn, ipv6)).copied().unwrap_or_default();
+            Ok(routes.split_whitespace().map(str::to_string).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpki_filtered_prefix_lists() {
+        let snapshot = RoaSnapshot::from_json(
+            "rpki.cloudflare.com",
+            r#"{"roas": [
+                {"prefix": "192.0.2.0/24", "maxLength": 24, "asn": 64500, "ta": "ripe"},
+                {"prefix": "198.51.100.0/22", "maxLength": 23, "asn": 64501, "ta": "ripe"},
+                {"prefix": "203.0.113.0/24", "maxLength": 24, "asn": 0, "ta": "apnic"}
+            ]}"#,
+        );
+        let mut routes = Routes(HashMap::from([
+            ((64500, false), "192.0.2.0/24 198.51.100.0/23 10.0.0.0/8"),
+            ((64500, true), "2001:db8::/32"),
+            ((64501, false), "198.51.100.0/23 198.51.100.0/24 203.0.113.0/24 not-a-prefix"),
+        ]));
+        let list = collect(&mut routes, &BTreeSet::from([64500, 64501]), Some(&snapshot)).await.unwrap();
+
+        // Wrong origin, too specific for the max length, covered by an AS0 ROA
+        assert_eq!(list.routes, 7);
+        assert_eq!(
+            list.invalid,
+            [
+                ("198.51.100.0/23".parse().unwrap(), 64500),
+                ("198.51.100.0/24".parse().unwrap(), 64501),
+                ("203.0.113.0/24".parse().unwrap(), 64501),
+            ]
+        );
+
+        let output = format_prefix_list("as-example", 2, &list, RouterFormat::Cisco, Ok(&snapshot));
+        assert!(output.contains("% 2 ASNs, 7 route objects: 3 IPv4 and 1 IPv6 prefixes, 3 RPKI-invalid routes removed\n"));
+        assert!(output.contains("% removed: 203.0.113.0/24 AS64501 (RPKI invalid)\n"));
+        assert!(output.ends_with(
+            "\nno ip prefix-list AS-EXAMPLE-V4\nip prefix-list AS-EXAMPLE-V4 permit 10.0.0.0/8\n\
+             ip prefix-list AS-EXAMPLE-V4 permit 192.0.2.0/24\nip prefix-list AS-EXAMPLE-V4 permit 198.51.100.0/23\n\
+             no ipv6 prefix-list AS-EXAMPLE-V6\nipv6 prefix-list AS-EXAMPLE-V6 permit 2001:db8::/32\n"
+        ));
+
+        let juniper = render("AS64500", &list, RouterFormat::Juniper);
+        assert!(juniper.starts_with("policy-options {\nreplace:\n prefix-list AS64500-V4 {\n    10.0.0.0/8;\n"));
+        assert!(juniper.ends_with("replace:\n prefix-list AS64500-V6 {\n    2001:db8::/32;\n }\n}\n"));
+        let bird = render("AS-EXAMPLE", &list, RouterFormat::Bird);
+        assert!(bird.starts_with("define AS_EXAMPLE_V4 = [\n    10.0.0.0/8,\n    192.0.2.0/24,\n    198.51.100.0/23\n];\n"));
+
+        let unfiltered = collect(&mut routes, &BTreeSet::from([64501]), None).await.unwrap();
+        assert!(unfiltered.invalid.is_empty());
+        let output = format_prefix_list("AS64501", 1, &unfiltered, RouterFormat::Bird, Err("timeout".to_string()));
+        assert!(output.contains("% RPKI filtering unavailable (timeout), the list is unfiltered IRR data\n"));
+    }
+}
@@ -0,0 +1,168 @@
+// WHOIS Server - Globalping Probe Listing
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Globalping probe availability
+//!
+//! Lists where online Globalping probes are, so users can pick a vantage
+//! point for `-PING@<location>` and `-TRACE@<location>` queries.
+//! `PROBES` summarizes continents and countries; `<filter>-PROBES` narrows
+//! to a continent, country, ASN (`AS13335`), city or network.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use crate::services::utils::{ GlobalpingClient, Probe };
+use crate::{ log_debug, log_error };
+
+/// Countries, cities and networks shown per section
+const TOP_ENTRIES: usize = 20;
+
+/// Process a PROBES query with an optional location filter
+pub async fn process_probes_query(filter: Option<&str>) -> Result<String> {
+    log_debug!("Processing probes query (filter: {:?})", filter);
+
+    let globalping = match GlobalpingClient::new() {
+        Ok(client) => client,
+        Err(e) => {
+            log_error!("Failed to initialize Globalping client: {}", e);
+            return Ok(format!("Probe listing error: {}\n", e));
+        }
+    };
+
+    let probes = match globalping.list_probes().await {
+        Ok(probes) => probes,
+        Err(e) => {
+            log_error!("Failed to list Globalping probes: {}", e);
+            return Ok(format!("Probe listing failed: {}\n", e));
+        }
+    };
+
+    Ok(format_probes(&probes, filter))
+}
+
+/// Whether a probe matches a continent, country, ASN, city, region or network filter
+fn probe_matches(probe: &Probe, filter: &str) -> bool {
+    let location = &probe.location;
+    let filter = filter.trim();
+
+    let upper = filter.to_uppercase();
+    if let Some(asn) = upper.strip_prefix("AS") && let Ok(asn) = asn.parse::<u32>() {
+        return location.asn == asn;
+    }
+
+    // Two-letter filters are continent or country codes, never name fragments
+    if filter.len() == 2 {
+        return location.continent.eq_ignore_ascii_case(filter) || location.country.eq_ignore_ascii_case(filter);
+    }
+
+    let needle = filter.to_lowercase();
+    [location.city.as_deref(), location.region.as_deref(), Some(location.network.as_str())]
+        .into_iter()
+        .flatten()
+        .any(|value| value.to_lowercase().contains(&needle))
+}
+
+/// Count values and return them most common first, ties broken by name
+fn ranked<'a>(values: impl Iterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut ranked: Vec<(&str, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    ranked
+}
+
+fn push_section(output: &mut String, title: &str, key: &str, entries: &[(&str, usize)], limit: usize) {
+    output.push_str("%\n");
+    output.push_str(&format!("% {}:\n", title));
+    for (name, count) in entries.iter().take(limit) {
+        output.push_str(&format!("{:<16}{:<32} {}\n", format!("{}:", key), name, count));
+    }
+    if entries.len() > limit {
+        output.push_str(&format!("% ... and {} more\n", entries.len() - limit));
+    }
+}
+
+/// Format probe counts, grouped by continent and country, or by city and network when filtered
+fn format_probes(probes: &[Probe], filter: Option<&str>) -> String {
+    let selected: Vec<&Probe> = probes
+        .iter()
+        .filter(|probe| filter.is_none_or(|f| probe_matches(probe, f)))
+        .collect();
+
+    let mut output = String::new();
+    output.push_str("% Globalping Probe Availability\n");
+    if let Some(filter) = filter {
+        output.push_str(&format!("% Filter: {}\n", filter));
+    }
+    output.push_str("%\n");
+
+    if selected.is_empty() {
+        output.push_str("% No online probes match this location\n");
+        return output;
+    }
+
+    let countries = ranked(selected.iter().map(|p| p.location.country.as_str()));
+    output.push_str(&format!("probes-online:  {}\n", selected.len()));
+    output.push_str(&format!("countries:      {}\n", countries.len()));
+
+    if filter.is_none() {
+        let continents = ranked(selected.iter().map(|p| p.location.continent.as_str()));
+        push_section(&mut output, "Probes per continent", "continent", &continents, continents.len());
+        push_section(&mut output, "Probes per country", "country", &countries, TOP_ENTRIES);
+    } else {
+        let cities = ranked(selected.iter().filter_map(|p| p.location.city.as_deref()));
+        let networks = ranked(selected.iter().map(|p| p.location.network.as_str()));
+        push_section(&mut output, "Probes per country", "country", &countries, TOP_ENTRIES);
+        push_section(&mut output, "Probes per city", "city", &cities, TOP_ENTRIES);
+        push_section(&mut output, "Probes per network", "network", &networks, TOP_ENTRIES);
+    }
+
+    output.push_str("%\n");
+    output.push_str("% Select vantage points with <target>-PING@<location> or <target>-TRACE@<location>\n");
+    output.push_str("% Data provided by Globalping (globalping.io)\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::utils::globalping::ProbeLocation;
+
+    fn probe(continent: &str, country: &str, city: &str, asn: u32, network: &str) -> Probe {
+        Probe {
+            location: ProbeLocation {
+                continent: continent.to_string(),
+                region: None,
+                country: country.to_string(),
+                city: Some(city.to_string()),
+                asn,
+                network: network.to_string(),
+            },
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_probe_filters() {
+        let probes = vec![
+            probe("EU", "DE", "Frankfurt", 24940, "Hetzner Online GmbH"),
+            probe("EU", "DE", "Berlin", 13335, "Cloudflare, Inc."),
+            probe("NA", "US", "Ashburn", 16509, "Amazon.com, Inc.")
+        ];
+
+        let summary = format_probes(&probes, None);
+        assert!(summary.contains("probes-online:  3\n"));
+        assert!(summary.contains("continent:      EU"));
+
+        let by_asn = format_probes(&probes, Some("as13335"));
+        assert!(by_asn.contains("probes-online:  1\n"));
+        assert!(by_asn.contains("city:           Berlin"));
+
+        assert!(format_probes(&probes, Some("de")).contains("probes-online:  2\n"));
+        assert!(format_probes(&probes, Some("ashburn")).contains("probes-online:  1\n"));
+        assert!(format_probes(&probes, Some("AS64512")).contains("No online probes match"));
+    }
+}
@@ -0,0 +1,114 @@
+// WHOIS Server - Public Suffix Analysis
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Domain structure analysis (`-PSL`)
+//!
+//! Shows the public suffix, matching rule, list section and registrable
+//! domain of a name. For names under a private-section suffix such as
+//! `github.io` the ICANN registrable domain is shown as well, since that is
+//! what domain WHOIS is answered for.
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::core::public_suffix::{DomainParts, PublicSuffixList, public_suffix_list};
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+
+fn format_parts(name: &str, parts: &DomainParts, icann: Option<&DomainParts>, list: &PublicSuffixList) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("% Public Suffix List analysis for {}\n", name));
+    output.push_str("%\n");
+    output.push_str(&format!("domain:              {}\n", parts.domain));
+    output.push_str(&format!("public-suffix:       {}\n", parts.suffix));
+    output.push_str(&format!("suffix-rule:         {}\n", parts.rule));
+    output.push_str(&format!(
+        "suffix-section:      {}\n",
+        parts.section.map_or("not listed (default rule)", |s| s.as_str())
+    ));
+    match &parts.registrable {
+        Some(registrable) => {
+            output.push_str(&format!("registrable-domain:  {}\n", registrable));
+            if let Some(subdomain) = parts.domain.strip_suffix(registrable.as_str()).and_then(|s| s.strip_suffix('.')) {
+                output.push_str(&format!("subdomain:           {}\n", subdomain));
+            }
+        }
+        None => output.push_str("registrable-domain:  none (the name is itself a public suffix)\n"),
+    }
+
+    if let Some(icann) = icann.filter(|icann| icann.suffix != parts.suffix) {
+        output.push_str(&format!("icann-suffix:        {}\n", icann.suffix));
+        if let Some(registrable) = &icann.registrable {
+            output.push_str(&format!("icann-registrable:   {}\n", registrable));
+        }
+    }
+
+    output.push_str("%\n");
+    match list.fetched_at {
+        Some(fetched_at) => output.push_str(&format!(
+            "% Public Suffix List fetched {} ({} rules)\n",
+            fetched_at.format("%Y-%m-%d %H:%M UTC"),
+            list.len()
+        )),
+        None => output.push_str(&format!("% Bundled Public Suffix List ({} rules)\n", list.len())),
+    }
+    output
+}
+
+/// Analyze the structure of a domain name
+pub async fn process_psl_query(target: &str) -> Result<String> {
+    let list = public_suffix_list();
+    let Some(parts) = list.lookup(target, true) else {
+        return Ok(format!("Invalid domain name: {}\nUse a domain, e.g. www.example.co.uk-PSL\n", target));
+    };
+    let icann = list.lookup(target, false);
+    Ok(format_parts(target.trim(), &parts, icann.as_ref(), &list))
+}
+
+/// `-PSL`: public suffix and registrable domain of a name
+pub struct PslService;
+
+#[async_trait]
+impl QueryService for PslService {
+    fn name(&self) -> &'static str {
+        "psl"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-PSL"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "DOMAIN ANALYSIS",
+            summary: "Public suffix, registrable domain and PSL section of a name",
+            example: "www.example.co.uk-PSL",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "Public Suffix List (bundled, refreshed daily)"
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_psl_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_psl_output() {
+        let response = process_psl_query("www.example.co.uk").await.unwrap();
+        assert!(response.contains("public-suffix:       co.uk\n"));
+        assert!(response.contains("registrable-domain:  example.co.uk\n"));
+        assert!(response.contains("subdomain:           www\n"));
+
+        let response = process_psl_query("user.github.io").await.unwrap();
+        assert!(response.contains("suffix-section:      PRIVATE\n"));
+        assert!(response.contains("icann-registrable:   github.io\n"));
+    }
+}
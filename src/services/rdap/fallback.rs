@@ -0,0 +1,89 @@
+// WHOIS Server - RDAP Fallback
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! RDAP fallback for standard WHOIS queries
+//!
+//! Used by the IANA referral path when the referred port 43 server cannot
+//! be reached or answers with a rate-limit notice. Errors are returned
+//! rather than rendered, so the caller can carry on with its other
+//! fallbacks when RDAP has no answer either.
+
+use anyhow::{Result, anyhow};
+use rdap::{RdapClient, RdapObject, RdapRequest};
+use std::time::Instant;
+
+use super::format::format_rdap_output;
+use crate::core::provenance::record_source;
+use crate::log_debug;
+
+/// Phrases WHOIS servers use when refusing queries for exceeding a limit
+const RATE_LIMIT_INDICATORS: &[&str] = &[
+    "query rate limit exceeded",
+    "rate limit exceeded",
+    "too many queries",
+    "too many requests",
+    "query limit exceeded",
+    "exceeded the maximum allowable number",
+    "exceeded the query limit",
+    "maximum daily connection limit reached",
+    "access denied: query limit",
+    "please try again later",
+    "temporarily blocked",
+];
+
+/// Whether a WHOIS response is a rate-limit refusal rather than data
+pub fn is_rate_limited(response: &str) -> bool {
+    let lower = response.to_lowercase();
+    // Long responses carrying one of the phrases in a footer still have data
+    let meaningful_lines = response
+        .lines()
+        .filter(|line| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('%') && !line.starts_with('#')
+        })
+        .count();
+    meaningful_lines < 10 && RATE_LIMIT_INDICATORS.iter().any(|indicator| lower.contains(indicator))
+}
+
+/// Answer `query` from RDAP because `server` failed for `reason`
+pub async fn rdap_fallback(query: &str, server: &str, reason: &str) -> Result<String> {
+    let query_type = RdapRequest::detect_type(query).map_err(|e| anyhow!("not an RDAP query: {}", e))?;
+    log_debug!("Falling back to RDAP for {} ({:?}) after {} failed: {}", query, query_type, server, reason);
+
+    let client = RdapClient::new().map_err(|e| anyhow!("failed to create RDAP client: {}", e))?;
+    let start = Instant::now();
+    let result = client.query(&RdapRequest::new(query_type, query)).await;
+    let status = if result.is_ok() { "cache miss" } else { "failed" };
+    record_source("upstream", "RDAP", start.elapsed(), status);
+
+    let object = result.map_err(|e| anyhow!("RDAP query failed: {}", e))?;
+    if let RdapObject::Error(err) = &object {
+        return Err(anyhow!(
+            "RDAP error {}",
+            err.error_code.map_or_else(|| "response".to_string(), |code| code.to_string())
+        ));
+    }
+
+    Ok(format!(
+        "% WHOIS server {} unavailable ({}), answered from RDAP\n%\n{}",
+        server,
+        reason,
+        format_rdap_output(&object)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_rate_limited() {
+        assert!(is_rate_limited("%ERROR:201: access denied\n% Query rate limit exceeded\n"));
+        assert!(is_rate_limited("WHOIS LIMIT EXCEEDED - too many queries, please try again later\n"));
+        assert!(!is_rate_limited("% No entries found for the selected source(s).\n"));
+
+        let data: String = (0..20).map(|i| format!("remarks: line {}\n", i)).collect();
+        assert!(!is_rate_limited(&format!("{}% Too many queries will get you blocked\n", data)));
+    }
+}
@@ -1,79 +1,11 @@
-use anyhow::Result;
-use rdap::{RdapClient, RdapRequest};
-use crate::{log_debug, log_warn};
-/// Process RDAP query
-pub async fn process_rdap_query(query: &str) -> Result<String> {
-    log_debug!("Processing RDAP query: {}", query);
-
-    // Create RDAP client
-    let client = match RdapClient::new() {
-        Ok(c) => c,
-        Err(e) => {
-            return Ok(format!(
-                "% RDAP Client Error\n\
-                 % Failed to create RDAP client: {}\n",
-                e
-            ));
-        }
-    };
-
-    // Auto-detect query type
-    let query_type = match RdapRequest::detect_type(query) {
-        Ok(qt) => qt,
-        Err(e) => {
-            return Ok(format!(
-                "% RDAP Query Error\n\
-                 % Unable to detect query type for: {}\n\
-                 % Error: {}\n\
-                 % \n\
-                 % Supported query types:\n\
-                 %   - Domain names (e.g., example.com)\n\
-                 %   - IP addresses (e.g., 8.8.8.8, 2001:4860:4860::8888)\n\
-                 %   - AS numbers (e.g., AS15169 or 15169)\n",
-                query, e
-            ));
-        }
-    };
-
-    log_debug!("Detected RDAP query type: {:?}", query_type);
-
-    // Create request
-    let request = RdapRequest::new(query_type, query);
-
-    // Execute query
-    match client.query(&request).await {
-        Ok(result) => {
-            // Format the RDAP response in WHOIS-like style
-            let output = format!(
-                "% RDAP (Registration Data Access Protocol) Response\n\
-                 % Query: {}\n\
-                 % Query Type: {:?}\n\
-                 % \n",
-                query, query_type
-            );
-
-            // Format the result manually
-            Ok(output + &format_rdap_output(&result))
-        }
-        Err(e) => {
-            log_warn!("RDAP query failed for {}: {}", query, e);
-            Ok(format!(
-                "% RDAP Query Failed\n\
-                 % Query: {}\n\
-                 % Error: {}\n\
-                 % \n\
-                 % This may be due to:\n\
-                 %   - Network connectivity issues\n\
-                 %   - RDAP service unavailable\n\
-                 %   - Invalid or non-existent resource\n",
-                query, e
-            ))
-        }
-    }
-}
+// WHOIS Server - RDAP Formatting
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Conversion of RDAP objects to RPSL-style key/value output
 
 /// Format RDAP output in WHOIS-like style
-fn format_rdap_output(result: &rdap::RdapObject) -> String {
+pub(super) fn format_rdap_output(result: &rdap::RdapObject) -> String {
     use rdap::RdapObject;
 
     match result {
@@ -319,15 +251,3 @@ fn format_entity(output: &mut String, entity: &rdap::Entity) {
 
     output.push_str("\n");
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[tokio::test]
-    async fn test_rdap_query_format() {
-        // Basic test to ensure the function signature works
-        let result = process_rdap_query("example.com").await;
-        assert!(result.is_ok());
-    }
-}
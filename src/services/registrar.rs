@@ -0,0 +1,373 @@
+// WHOIS Server - Registrar Lookup
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! ICANN registrar lookup by IANA ID or name (`-REGISTRAR`)
+//!
+//! The IANA Registrar IDs registry (ID, name, status, RDAP base URL) is
+//! cached in LMDB and refreshed daily by [`registrar_update_job`].
+//! Abuse contacts and the registrar WHOIS server come from the registrar
+//! object in the `.com` registry's RDAP service, which every accredited
+//! registrar has.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::REGISTRAR_LMDB_PATH;
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::core::scheduler::{Job, Schedule};
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use crate::storage::lmdb::LmdbStorage;
+use crate::{log_debug, log_info, log_warn};
+
+const REGISTRAR_IDS_URL: &str = "https://www.iana.org/assignments/registrar-ids/registrar-ids-1.csv";
+const REGISTRAR_RDAP_BASE: &str = "https://rdap.verisign.com/com/v1/";
+const CSV_KEY: &str = "registrar_ids_csv";
+const LAST_UPDATE_KEY: &str = "registrar_last_update";
+/// Refresh the registrar list when older than a day
+const MAX_AGE_SECS: u64 = 86400;
+const MAX_RESULTS: usize = 20;
+
+/// A row of the IANA Registrar IDs registry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistrarEntry {
+    pub id: u32,
+    pub name: String,
+    pub status: String,
+    pub rdap_base: Option<String>,
+}
+
+/// Contact details from the registrar's RDAP object
+#[derive(Debug, Default, PartialEq, Eq)]
+struct RegistrarContacts {
+    whois_server: Option<String>,
+    url: Option<String>,
+    abuse_email: Option<String>,
+    abuse_phone: Option<String>,
+}
+
+/// Split a CSV line, honouring double-quoted fields
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Parse the registrar IDs CSV (`ID,Registrar Name,Status,RDAP Base URL`)
+fn parse_registrar_csv(content: &str) -> Vec<RegistrarEntry> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = split_csv_line(line.trim_end_matches('\r'));
+            let id = fields.first()?.trim().parse().ok()?;
+            let field = |i: usize| fields.get(i).map(|f| f.trim().to_string()).unwrap_or_default();
+            Some(RegistrarEntry {
+                id,
+                name: field(1),
+                status: field(2),
+                rdap_base: Some(field(3)).filter(|url| !url.is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// Find registrars by IANA ID, or by case-insensitive name fragment
+///
+/// An exact name match is returned on its own so `MarkMonitor Inc.` is not
+/// buried among other registrars containing the same words.
+fn find_registrars<'a>(entries: &'a [RegistrarEntry], query: &str) -> Vec<&'a RegistrarEntry> {
+    let query = query.trim();
+    if let Ok(id) = query.parse::<u32>() {
+        return entries.iter().filter(|e| e.id == id).collect();
+    }
+    if let Some(exact) = entries.iter().find(|e| e.name.eq_ignore_ascii_case(query)) {
+        return vec![exact];
+    }
+    let needle = query.to_lowercase();
+    entries.iter().filter(|e| e.name.to_lowercase().contains(&needle)).collect()
+}
+
+/// Value of the first vCard property `name` of an RDAP entity
+pub(crate) fn vcard_value<'a>(entity: &'a Value, name: &str) -> Option<&'a str> {
+    entity["vcardArray"][1]
+        .as_array()?
+        .iter()
+        .find(|property| property[0] == name)
+        .and_then(|property| property[3].as_str())
+        .filter(|value| !value.is_empty())
+}
+
+fn parse_contacts(entity: &Value) -> RegistrarContacts {
+    let abuse = entity["entities"]
+        .as_array()
+        .and_then(|entities| {
+            entities.iter().find(|e| e["roles"].as_array().is_some_and(|roles| roles.iter().any(|r| r == "abuse")))
+        });
+    let url = entity["links"]
+        .as_array()
+        .and_then(|links| links.iter().find(|l| l["rel"] == "about"))
+        .and_then(|l| l["href"].as_str())
+        .or_else(|| vcard_value(entity, "url"));
+
+    RegistrarContacts {
+        whois_server: entity["port43"].as_str().filter(|s| !s.is_empty()).map(str::to_string),
+        url: url.map(str::to_string),
+        abuse_email: abuse.and_then(|a| vcard_value(a, "email")).map(str::to_string),
+        abuse_phone: abuse
+            .and_then(|a| vcard_value(a, "tel"))
+            .map(|tel| tel.trim_start_matches("tel:").to_string()),
+    }
+}
+
+/// Look up a registrar's RDAP object by IANA ID
+async fn fetch_contacts(id: u32) -> Result<Option<RegistrarContacts>> {
+    let url = format!("{}entity/{}", api_base(REGISTRAR_RDAP_BASE), id);
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .user_agent("whois-server/1.0")
+        .build()?;
+    let response = client.get(&url).header("Accept", "application/rdap+json").send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!("registrar RDAP lookup failed: HTTP {}", response.status()));
+    }
+    Ok(Some(parse_contacts(&response.limited_json().await?)))
+}
+
+fn format_registrar(entry: &RegistrarEntry, contacts: Option<&RegistrarContacts>) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("registrar-id:    {}\n", entry.id));
+    output.push_str(&format!("registrar-name:  {}\n", entry.name));
+    output.push_str(&format!("status:          {}\n", entry.status));
+    if let Some(rdap_base) = &entry.rdap_base {
+        output.push_str(&format!("rdap-base-url:   {}\n", rdap_base));
+    }
+    if let Some(contacts) = contacts {
+        let lines = [
+            ("whois-server:    ", &contacts.whois_server),
+            ("url:             ", &contacts.url),
+            ("abuse-email:     ", &contacts.abuse_email),
+            ("abuse-phone:     ", &contacts.abuse_phone),
+        ];
+        for (key, value) in lines {
+            if let Some(value) = value {
+                output.push_str(&format!("{}{}\n", key, value));
+            }
+        }
+    }
+    output
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time should be after Unix epoch")
+        .as_secs()
+}
+
+/// Cached copy of the IANA Registrar IDs registry
+pub struct RegistrarDirectory {
+    storage: LmdbStorage,
+}
+
+impl RegistrarDirectory {
+    pub fn new() -> Result<Self> {
+        Ok(Self { storage: LmdbStorage::new(REGISTRAR_LMDB_PATH)? })
+    }
+
+    /// Check if the cached list is missing or older than a day
+    pub fn needs_update(&self) -> bool {
+        match self.storage.get_json::<u64>(LAST_UPDATE_KEY) {
+            Ok(Some(last_update)) => now_secs().saturating_sub(last_update) > MAX_AGE_SECS,
+            _ => true,
+        }
+    }
+
+    /// Download the registry from IANA and cache it
+    pub async fn force_update(&self) -> Result<usize> {
+        log_info!("Downloading IANA registrar IDs from {}", REGISTRAR_IDS_URL);
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .user_agent("whois-server/1.0")
+            .build()?;
+        let response = client.get(REGISTRAR_IDS_URL).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Failed to download registrar IDs: HTTP {}", response.status()));
+        }
+        let content = response.limited_text().await?;
+
+        let count = parse_registrar_csv(&content).len();
+        if count == 0 {
+            return Err(anyhow!("Registrar IDs download contained no registrars"));
+        }
+        self.storage.put(CSV_KEY, &content)?;
+        self.storage.put_json(LAST_UPDATE_KEY, &now_secs())?;
+        Ok(count)
+    }
+
+    /// All registrars, downloading the registry if it was never cached
+    pub async fn entries(&self) -> Result<Vec<RegistrarEntry>> {
+        if let Ok(Some(content)) = self.storage.get(CSV_KEY) {
+            return Ok(parse_registrar_csv(&content));
+        }
+        log_warn!("No registrar cache found, triggering initial download");
+        self.force_update().await?;
+        let content = self.storage.get(CSV_KEY)?.unwrap_or_default();
+        Ok(parse_registrar_csv(&content))
+    }
+}
+
+/// Resolve a registrar IANA ID or name
+pub async fn process_registrar_query(ctx: &RequestContext, query: &str) -> Result<String> {
+    let entries = RegistrarDirectory::new()?.entries().await?;
+    let matches = find_registrars(&entries, query);
+    log_debug!("[{}] {} registrars match {}", ctx, matches.len(), query);
+
+    let mut output = String::new();
+    output.push_str("% ICANN Registrar Information\n");
+    output.push_str("% https://www.iana.org/assignments/registrar-ids\n");
+    output.push_str("%\n");
+
+    match matches.as_slice() {
+        [] => {
+            output.push_str(&format!("% No registrar found matching: {}\n", query));
+            return Ok(output);
+        }
+        [entry] => {
+            let contacts = match fetch_contacts(entry.id).await {
+                Ok(contacts) => contacts,
+                Err(e) => {
+                    log_warn!("Registrar contact lookup failed for {}: {}", entry.id, e);
+                    None
+                }
+            };
+            output.push_str(&format_registrar(entry, contacts.as_ref()));
+            if contacts.is_none() {
+                output.push_str("% Contact details unavailable from RDAP\n");
+            }
+        }
+        entries => {
+            for entry in entries.iter().take(MAX_RESULTS) {
+                output.push_str(&format!("registrar:       {:<6} {} ({})\n", entry.id, entry.name, entry.status));
+            }
+            if entries.len() > MAX_RESULTS {
+                output.push_str(&format!("% ... and {} more, refine the name\n", entries.len() - MAX_RESULTS));
+            }
+            output.push_str("% Query an ID, e.g. 292-REGISTRAR, for contacts and WHOIS server\n");
+        }
+    }
+
+    output.push_str("%\n");
+    output.push_str("% Data sources: IANA Registrar IDs registry, Verisign RDAP\n");
+    Ok(output)
+}
+
+/// Hourly check of the registrar list
+pub fn registrar_update_job() -> Job {
+    Job::new("registrar-update", Schedule::Every(Duration::from_secs(3600)), registrar_periodic_update)
+        .with_jitter(Duration::from_secs(300))
+}
+
+/// Refresh the registrar list when it is stale
+async fn registrar_periodic_update() -> Result<()> {
+    let directory = RegistrarDirectory::new()?;
+    if !directory.needs_update() {
+        log_debug!("Registrar list is up to date");
+        return Ok(());
+    }
+    let count = directory.force_update().await?;
+    log_info!("Registrar list updated ({} registrars)", count);
+    Ok(())
+}
+
+/// `-REGISTRAR`: ICANN registrar by IANA ID or name
+pub struct RegistrarService;
+
+#[async_trait]
+impl QueryService for RegistrarService {
+    fn name(&self) -> &'static str {
+        "registrar"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-REGISTRAR"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "DOMAIN ANALYSIS",
+            summary: "ICANN registrar by IANA ID or name: abuse contact, WHOIS/RDAP server",
+            example: "292-REGISTRAR",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "IANA Registrar IDs (cached in LMDB), Verisign RDAP"
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_registrar_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::fixtures::FixtureServer;
+
+    const CSV: &str = "ID,Registrar Name,Status,RDAP Base URL\r\n\
+        292,MarkMonitor Inc.,Accredited,https://rdap.markmonitor.com/rdap/\r\n\
+        2,\"Network Solutions, LLC\",Accredited,https://rdap.networksolutions.com/rdap/\r\n\
+        9999,Reserved for non-ICANN registrars,Reserved,\r\n\
+        1068,\"NameCheap, Inc.\",Accredited,https://rdap.namecheap.com/\r\n";
+
+    #[test]
+    fn test_parse_and_find() {
+        let entries = parse_registrar_csv(CSV);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[1].name, "Network Solutions, LLC");
+        assert_eq!(entries[2].rdap_base, None);
+
+        assert_eq!(find_registrars(&entries, "292")[0].name, "MarkMonitor Inc.");
+        assert_eq!(find_registrars(&entries, "markmonitor")[0].id, 292);
+        assert_eq!(find_registrars(&entries, "namecheap, inc.").len(), 1);
+        assert_eq!(find_registrars(&entries, "inc").len(), 2);
+        assert!(find_registrars(&entries, "12345").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_registrar_contacts_fixture() {
+        let mut fixtures = FixtureServer::start("registrar").await;
+        fixtures.serve(REGISTRAR_RDAP_BASE, "entity/292", "entity_292.json").await;
+
+        let contacts = fixtures.run(fetch_contacts(292)).await.unwrap().unwrap();
+        assert_eq!(contacts, RegistrarContacts {
+            whois_server: Some("whois.markmonitor.com".to_string()),
+            url: Some("http://www.markmonitor.com".to_string()),
+            abuse_email: Some("abusecomplaints@markmonitor.com".to_string()),
+            abuse_phone: Some("+1.2086851750".to_string()),
+        });
+    }
+}
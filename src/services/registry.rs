@@ -0,0 +1,367 @@
+// WHOIS Server - Service Registry
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Registry of suffix-selected query services
+//!
+//! A service implements [`QueryService`] next to its lookup code and is
+//! registered once in [`builtin_registry`]. Query detection, dispatch,
+//! `-EXPLAIN`, `HELP` and telemetry all read the registry, so adding a new
+//! suffix does not require a new `QueryType` variant or another arm in each
+//! dispatcher: registered services are detected as
+//! `QueryType::Service(name, target)`.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::core::request_context::RequestContext;
+use crate::core::sanitize::strip_suffix_ignore_case;
+use crate::log_debug;
+
+/// Result of a service lookup
+pub type ServiceResult = Result<String>;
+
+/// Response cache TTL of services that do not declare one
+pub const DEFAULT_SERVICE_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// HELP entry of a service
+#[derive(Debug, Clone, Copy)]
+pub struct ServiceHelp {
+    /// HELP section the service is listed under
+    pub category: &'static str,
+    /// One-line description
+    pub summary: &'static str,
+    /// Example query, including the suffix
+    pub example: &'static str,
+}
+
+/// A query service selected by a suffix such as `-CARGO`
+#[async_trait]
+pub trait QueryService: Send + Sync {
+    /// Stable lowercase name, used for telemetry and metrics
+    fn name(&self) -> &'static str;
+
+    /// Suffixes selecting the service, including the leading dash
+    fn suffixes(&self) -> &'static [&'static str];
+
+    /// Description for HELP
+    fn help(&self) -> ServiceHelp;
+
+    /// Upstream data source, shown by `-EXPLAIN`
+    fn upstream(&self) -> &'static str;
+
+    /// Class the service is limited with in `--query-limits-file`, as
+    /// listed in [`crate::core::query_limits`]
+    fn query_class(&self) -> &'static str {
+        "SERVICE"
+    }
+
+    /// How long responses may be served from the response cache
+    ///
+    /// `None` for live measurements, which are never cached.
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(DEFAULT_SERVICE_CACHE_TTL)
+    }
+
+    /// Answer a query for `target` (the query without the suffix)
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult;
+}
+
+/// Registered query services
+#[derive(Default)]
+pub struct ServiceRegistry {
+    services: Vec<Box<dyn QueryService>>,
+    /// Services switched off with `--disable-services`
+    disabled: Vec<&'static str>,
+}
+
+impl ServiceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a service, rejecting names and suffixes that are already taken
+    pub fn register(&mut self, service: Box<dyn QueryService>) -> Result<()> {
+        if self.get(service.name()).is_some() {
+            return Err(anyhow!("service '{}' is already registered", service.name()));
+        }
+        for suffix in service.suffixes() {
+            if !suffix.starts_with('-') || suffix.len() < 2 {
+                return Err(anyhow!("service '{}' has an invalid suffix '{}'", service.name(), suffix));
+            }
+            if let Some(owner) = self.services.iter().find(|s| s.suffixes().iter().any(|x| x.eq_ignore_ascii_case(suffix))) {
+                return Err(anyhow!("suffix '{}' of service '{}' is already used by '{}'", suffix, service.name(), owner.name()));
+            }
+        }
+        self.services.push(service);
+        Ok(())
+    }
+
+    /// Look up a service by name
+    pub fn get(&self, name: &str) -> Option<&dyn QueryService> {
+        self.services.iter().find(|s| s.name() == name).map(|s| s.as_ref())
+    }
+
+    /// Find the service selected by the suffix of `query`
+    ///
+    /// Returns the service and the query without its suffix. The longest
+    /// matching suffix wins, so `-FOOBAR` is never shadowed by `-BAR`.
+    pub fn match_suffix<'q>(&self, query: &'q str) -> Option<(&dyn QueryService, &'q str)> {
+        self.services
+            .iter()
+            .flat_map(|service| service.suffixes().iter().map(move |suffix| (service, suffix)))
+            .filter_map(|(service, suffix)| {
+                strip_suffix_ignore_case(query, suffix).map(|target| (service.as_ref(), target, suffix.len()))
+            })
+            .filter(|(_, target, _)| !target.is_empty())
+            .max_by_key(|(_, _, len)| *len)
+            .map(|(service, target, _)| (service, target))
+    }
+
+    /// Switch a service off; its suffix still selects it, but queries are refused
+    pub fn disable(&mut self, name: &str) -> Result<()> {
+        let service = self.get(name).ok_or_else(|| anyhow!("unknown service '{}'", name))?;
+        self.disabled.push(service.name());
+        Ok(())
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.contains(&name)
+    }
+
+    /// All enabled services, in registration order
+    pub fn services(&self) -> impl Iterator<Item = &dyn QueryService> {
+        self.services.iter().map(|s| s.as_ref()).filter(|s| self.is_enabled(s.name()))
+    }
+
+    /// Names of all registered services, including disabled ones
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.services.iter().map(|s| s.name())
+    }
+
+    pub fn len(&self) -> usize {
+        self.services.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    /// Run the service `name` for `target`
+    pub async fn handle(&self, name: &str, ctx: &RequestContext, target: &str) -> ServiceResult {
+        let service = self.get(name).ok_or_else(|| anyhow!("Service not registered: {}", name))?;
+        if !self.is_enabled(name) {
+            return Err(anyhow!("{} queries are disabled on this server", name));
+        }
+        log_debug!("[{}] Processing {} query: {}", ctx, name, target);
+        service.handle(ctx, target).await
+    }
+}
+
+/// Registry with all built-in services
+pub fn builtin_registry() -> Result<ServiceRegistry> {
+    use crate::services::packages::{
+        alma::AlmaService,
+        aosc::AoscService,
+        aur::AurService,
+        cargo::CargoService,
+        curseforge::CurseForgeService,
+        debian::DebianService,
+        epel::EpelService,
+        modrinth::ModrinthService,
+        nixos::NixOsService,
+        npm::NpmService,
+        opensuse::OpenSuseService,
+        openwrt::OpenWrtService,
+        pypi::PypiService,
+        ubuntu::UbuntuService,
+    };
+    use crate::dn42::registries::{CrxnRegistry, IcvpnRegistry};
+    use crate::services::abuse::AbuseService;
+    use crate::services::aspa::AspaService;
+    use crate::services::annotations::TaggedService;
+    use crate::services::asset::AsSetService;
+    use crate::services::ipam::IpamService;
+    use crate::services::neighbors::NeighborsService;
+    use crate::services::prefixlist::{PrefixListService, RouterFormat};
+    use crate::services::bogon::BogonService;
+    use crate::services::crtwatch::CrtWatchService;
+    use crate::services::dns::RdnsService;
+    use crate::services::dnssec::DnssecService;
+    use crate::services::dualstack::DualStackService;
+    use crate::services::expiry::ExpiryService;
+    use crate::services::fingerprint::FingerprintService;
+    use crate::services::gameserver::GameServerService;
+    use crate::services::hibp::HibpService;
+    use crate::services::history::HistoryService;
+    use crate::services::homoglyph::HomoglyphService;
+    use crate::services::ix::IxService;
+    use crate::services::peerconf::PeerConfService;
+    use crate::services::latmatrix::LatMatrixService;
+    use crate::services::malware::MalwareService;
+    use crate::services::mtr::MtrService;
+    use crate::services::port::PortService;
+    use crate::services::psl::PslService;
+    use crate::services::registrar::RegistrarService;
+    use crate::services::report::ReportService;
+    use crate::services::roa::RoaService;
+    use crate::services::setop::SetOpService;
+    use crate::services::speedtest::SpeedtestService;
+    use crate::services::sshkeys::SshKeysService;
+    use crate::services::urlscan::UrlscanService;
+    use crate::services::verify::VerifyService;
+
+    let mut registry = ServiceRegistry::new();
+    registry.register(Box::new(CargoService))?;
+    registry.register(Box::new(PypiService))?;
+    registry.register(Box::new(NpmService))?;
+    registry.register(Box::new(AurService))?;
+    registry.register(Box::new(DebianService))?;
+    registry.register(Box::new(UbuntuService))?;
+    registry.register(Box::new(NixOsService))?;
+    registry.register(Box::new(OpenSuseService))?;
+    registry.register(Box::new(AoscService))?;
+    registry.register(Box::new(AlmaService))?;
+    registry.register(Box::new(EpelService))?;
+    registry.register(Box::new(OpenWrtService))?;
+    registry.register(Box::new(ModrinthService))?;
+    registry.register(Box::new(CurseForgeService))?;
+    registry.register(Box::new(UrlscanService))?;
+    registry.register(Box::new(HibpService))?;
+    registry.register(Box::new(MalwareService))?;
+    registry.register(Box::new(SshKeysService))?;
+    registry.register(Box::new(FingerprintService))?;
+    registry.register(Box::new(LatMatrixService))?;
+    registry.register(Box::new(MtrService))?;
+    registry.register(Box::new(PortService))?;
+    registry.register(Box::new(RdnsService))?;
+    registry.register(Box::new(DualStackService))?;
+    registry.register(Box::new(SpeedtestService))?;
+    registry.register(Box::new(PslService))?;
+    registry.register(Box::new(RegistrarService))?;
+    registry.register(Box::new(DnssecService))?;
+    registry.register(Box::new(HomoglyphService))?;
+    registry.register(Box::new(PeerConfService))?;
+    registry.register(Box::new(IxService))?;
+    registry.register(Box::new(SetOpService))?;
+    registry.register(Box::new(GameServerService))?;
+    registry.register(Box::new(AbuseService))?;
+    registry.register(Box::new(IcvpnRegistry))?;
+    registry.register(Box::new(CrxnRegistry))?;
+    registry.register(Box::new(ReportService))?;
+    registry.register(Box::new(VerifyService))?;
+    registry.register(Box::new(CrtWatchService))?;
+    registry.register(Box::new(RoaService))?;
+    registry.register(Box::new(AspaService))?;
+    registry.register(Box::new(BogonService))?;
+    registry.register(Box::new(AsSetService))?;
+    registry.register(Box::new(TaggedService))?;
+    registry.register(Box::new(IpamService))?;
+    registry.register(Box::new(PrefixListService(RouterFormat::Cisco)))?;
+    registry.register(Box::new(PrefixListService(RouterFormat::Juniper)))?;
+    registry.register(Box::new(PrefixListService(RouterFormat::Bird)))?;
+    registry.register(Box::new(NeighborsService))?;
+    registry.register(Box::new(HistoryService))?;
+    registry.register(Box::new(ExpiryService))?;
+    Ok(registry)
+}
+
+static REGISTRY: OnceLock<ServiceRegistry> = OnceLock::new();
+
+/// Install the registry used for all queries
+pub fn init_service_registry(registry: ServiceRegistry) {
+    let _ = REGISTRY.set(registry);
+}
+
+/// The service registry, falling back to the built-in services when it was
+/// not initialized (library use)
+pub fn service_registry() -> &'static ServiceRegistry {
+    REGISTRY.get_or_init(|| builtin_registry().expect("built-in services have unique names and suffixes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, &'static [&'static str]);
+
+    #[async_trait]
+    impl QueryService for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn suffixes(&self) -> &'static [&'static str] {
+            self.1
+        }
+
+        fn help(&self) -> ServiceHelp {
+            ServiceHelp { category: "TEST", summary: "test service", example: "x-TEST" }
+        }
+
+        fn upstream(&self) -> &'static str {
+            "none"
+        }
+
+        async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+            Ok(format!("{}:{}", self.0, target))
+        }
+    }
+
+    #[test]
+    fn test_builtin_registry_is_valid() {
+        let registry = builtin_registry().unwrap();
+        assert_eq!(registry.len(), 51);
+        let (service, target) = registry.match_suffix("serde-cargo").unwrap();
+        assert_eq!((service.name(), target), ("cargo", "serde"));
+    }
+
+    #[test]
+    fn test_longest_suffix_wins() {
+        let mut registry = ServiceRegistry::new();
+        registry.register(Box::new(Fixed("bar", &["-BAR"]))).unwrap();
+        registry.register(Box::new(Fixed("foobar", &["-FOOBAR"]))).unwrap();
+
+        assert_eq!(registry.match_suffix("x-FOOBAR").unwrap().0.name(), "foobar");
+        assert_eq!(registry.match_suffix("x-foo-bar").unwrap().1, "x-foo");
+        assert!(registry.match_suffix("-BAR").is_none());
+        assert!(registry.match_suffix("example.com").is_none());
+    }
+
+    #[test]
+    fn test_duplicates_are_rejected() {
+        let mut registry = ServiceRegistry::new();
+        registry.register(Box::new(Fixed("a", &["-A"]))).unwrap();
+        assert!(registry.register(Box::new(Fixed("a", &["-B"]))).is_err());
+        assert!(registry.register(Box::new(Fixed("b", &["-a"]))).is_err());
+        assert!(registry.register(Box::new(Fixed("c", &["C"]))).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handle_dispatches_by_name() {
+        let mut registry = ServiceRegistry::new();
+        registry.register(Box::new(Fixed("a", &["-A"]))).unwrap();
+        let ctx = RequestContext::default();
+        assert_eq!(registry.handle("a", &ctx, "x").await.unwrap(), "a:x");
+        assert!(registry.handle("missing", &ctx, "x").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_services_are_refused() {
+        let mut registry = ServiceRegistry::new();
+        registry.register(Box::new(Fixed("a", &["-A"]))).unwrap();
+        registry.register(Box::new(Fixed("b", &["-B"]))).unwrap();
+        registry.disable("a").unwrap();
+        assert!(registry.disable("c").is_err());
+
+        let ctx = RequestContext::new(crate::core::request_context::Frontend::Whois);
+        assert!(registry.handle("a", &ctx, "x").await.is_err());
+        assert_eq!(registry.handle("b", &ctx, "x").await.unwrap(), "b:x");
+        assert_eq!(registry.services().map(|s| s.name()).collect::<Vec<_>>(), ["b"]);
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["a", "b"]);
+        // The suffix still selects the service, so the query is not misread as something else
+        assert_eq!(registry.match_suffix("x-A").unwrap().0.name(), "a");
+    }
+}
@@ -0,0 +1,633 @@
+// WHOIS Server - Domain Report
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Consolidated domain report (`-REPORT`)
+//!
+//! Runs a fixed battery of checks concurrently: registration (WHOIS), DNS,
+//! DNSSEC, the TLS certificate, mail authentication (MX, SPF, DMARC),
+//! Certificate Transparency and urlscan.io reputation. The report opens
+//! with a scorecard of one line per check and a grade, followed by the
+//! findings of each check. Every check has its own time limit, so a slow
+//! upstream only marks its own line as unknown.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+use crate::core::public_suffix::registrable_domain;
+use crate::core::request_context::RequestContext;
+use crate::services::crt::CrtService;
+use crate::services::expiry::registration_dates;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::ssl::{CertificateInfo, SslService};
+use crate::services::urlscan::{HostVerdict, latest_verdict};
+use crate::services::utils::doh::{DnsResponse, DohClient};
+use crate::services::whois::query_with_iana_referral;
+use crate::log_debug;
+
+/// Longest time a single check may take
+const CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+/// Reports include live checks, so they are cached for less than an hour
+const REPORT_CACHE_TTL: Duration = Duration::from_secs(15 * 60);
+/// Domains expiring sooner than this are flagged
+const DOMAIN_EXPIRY_WARN_DAYS: i64 = 30;
+/// Certificates expiring sooner than this are flagged
+const CERT_EXPIRY_WARN_DAYS: i64 = 14;
+
+const TYPE_A: u32 = 1;
+const TYPE_NS: u32 = 2;
+const TYPE_MX: u32 = 15;
+const TYPE_TXT: u32 = 16;
+const TYPE_AAAA: u32 = 28;
+const TYPE_DS: u32 = 43;
+
+/// DNS response codes
+const RCODE_SERVFAIL: u32 = 2;
+const RCODE_NXDOMAIN: u32 = 3;
+
+/// Outcome of one check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+    /// The check could not be completed
+    Unknown,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Pass => "PASS",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+            Status::Unknown => "N/A",
+        }
+    }
+
+    /// Score points, `None` for checks left out of the score
+    fn points(self) -> Option<u32> {
+        match self {
+            Status::Pass => Some(2),
+            Status::Warn => Some(1),
+            Status::Fail => Some(0),
+            Status::Unknown => None,
+        }
+    }
+}
+
+/// Result of one check of the report
+#[derive(Debug, Clone, PartialEq)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    summary: String,
+    details: Vec<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, summary: impl Into<String>) -> Self {
+        Self { name, status, summary: summary.into(), details: Vec::new() }
+    }
+
+    fn detail(mut self, key: &str, value: impl std::fmt::Display) -> Self {
+        self.details.push(format!("{}: {}", key, value));
+        self
+    }
+
+    fn unknown(name: &'static str, error: anyhow::Error) -> Self {
+        Self::new(name, Status::Unknown, format!("check failed: {}", error))
+    }
+}
+
+/// Normalize the report target to a lowercase host name
+fn parse_target(target: &str) -> Option<String> {
+    let host = target.trim().trim_end_matches('.').to_ascii_lowercase();
+    let valid = host.contains('.') &&
+        host.parse::<std::net::IpAddr>().is_err() &&
+        host.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        });
+    valid.then_some(host)
+}
+
+/// Answers of one record type, TXT strings unquoted
+fn answers(response: &DnsResponse, type_code: u32) -> Vec<String> {
+    response.Answer
+        .iter()
+        .flatten()
+        .filter(|answer| answer.record_type == type_code)
+        .map(|answer| {
+            if type_code == TYPE_TXT { unquote_txt(&answer.data) } else { answer.data.trim_end_matches('.').to_string() }
+        })
+        .collect()
+}
+
+/// Join the quoted character-strings of a TXT record
+fn unquote_txt(data: &str) -> String {
+    if !data.contains('"') {
+        return data.trim().to_string();
+    }
+    data.split('"')
+        .skip(1)
+        .step_by(2)
+        .collect::<String>()
+}
+
+/// Days from `now` until `date`
+fn days_until(date: DateTime<Utc>, now: DateTime<Utc>) -> i64 {
+    (date - now).num_days()
+}
+
+/// Value of the first `key: value` line whose key is one of `keys`
+fn whois_field<'a>(response: &'a str, keys: &[&str]) -> Option<&'a str> {
+    response.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once(':')?;
+        let value = value.trim();
+        (keys.iter().any(|k| key.trim().eq_ignore_ascii_case(k)) && !value.is_empty()).then_some(value)
+    })
+}
+
+/// Expiry date of a domain registration from its WHOIS response
+pub(crate) fn registration_expiry(response: &str) -> Option<DateTime<Utc>> {
+    registration_dates(response).expires
+}
+
+fn evaluate_whois(domain: &str, response: &str, now: DateTime<Utc>) -> Check {
+    let lower = response.to_lowercase();
+    let unregistered = ["no match for", "not found", "no data found", "no entries found", "status: free"];
+    if unregistered.iter().any(|marker| lower.contains(marker)) {
+        return Check::new("WHOIS", Status::Fail, format!("{} is not registered", domain));
+    }
+
+    let registrar = whois_field(response, &["Registrar", "Sponsoring Registrar"]);
+    let expiry = registration_expiry(response);
+
+    let mut check = match expiry {
+        Some(expiry) => {
+            let days = days_until(expiry, now);
+            let status = if days < 0 {
+                Status::Fail
+            } else if days < DOMAIN_EXPIRY_WARN_DAYS {
+                Status::Warn
+            } else {
+                Status::Pass
+            };
+            let summary = if days < 0 {
+                format!("registration expired on {}", expiry.format("%Y-%m-%d"))
+            } else {
+                format!("registered until {} ({} days)", expiry.format("%Y-%m-%d"), days)
+            };
+            Check::new("WHOIS", status, summary).detail("expires", expiry.format("%Y-%m-%d"))
+        }
+        None => Check::new("WHOIS", Status::Pass, "registered (no expiry date published)"),
+    };
+    check = check.detail("domain", domain);
+    if let Some(registrar) = registrar {
+        check = check.detail("registrar", registrar);
+    }
+    let statuses: Vec<&str> = response
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .filter(|(key, _)| key.trim().eq_ignore_ascii_case("Domain Status"))
+        .filter_map(|(_, value)| value.split_whitespace().next())
+        .collect();
+    for status in statuses {
+        check = check.detail("status", status);
+    }
+    check
+}
+
+fn evaluate_dns(rcode: u32, a: &[String], aaaa: &[String], ns: &[String]) -> Check {
+    if rcode == RCODE_NXDOMAIN {
+        return Check::new("DNS", Status::Fail, "name does not exist (NXDOMAIN)");
+    }
+    if a.is_empty() && aaaa.is_empty() {
+        return Check::new("DNS", Status::Fail, "no A or AAAA records");
+    }
+
+    let families = match (a.is_empty(), aaaa.is_empty()) {
+        (false, false) => "IPv4 and IPv6",
+        (false, true) => "IPv4 only",
+        _ => "IPv6 only",
+    };
+    let status = if ns.len() == 1 || aaaa.is_empty() { Status::Warn } else { Status::Pass };
+    let summary = match ns.len() {
+        0 => format!("{} (nameservers delegated at the parent)", families),
+        1 => format!("{}, single nameserver", families),
+        n => format!("{}, {} nameservers", families, n),
+    };
+
+    let mut check = Check::new("DNS", status, summary);
+    for address in a.iter().chain(aaaa) {
+        check = check.detail("address", address);
+    }
+    for server in ns {
+        check = check.detail("nameserver", server);
+    }
+    check
+}
+
+fn evaluate_dnssec(zone: &str, rcode: u32, validated: bool, ds: &[String]) -> Check {
+    if rcode == RCODE_SERVFAIL {
+        return Check::new("DNSSEC", Status::Fail, "validation fails (SERVFAIL), resolvers cannot resolve the zone");
+    }
+    let check = if ds.is_empty() {
+        Check::new("DNSSEC", Status::Warn, format!("{} is not signed (no DS record)", zone))
+    } else if validated {
+        Check::new("DNSSEC", Status::Pass, format!("{} is signed and validates", zone))
+    } else {
+        Check::new("DNSSEC", Status::Warn, format!("{} has a DS record but the answer was not validated", zone))
+    };
+    ds.iter().fold(check.detail("zone", zone), |check, record| check.detail("ds", record))
+}
+
+/// Whether a certificate name (possibly a wildcard) covers `host`
+fn name_covers(name: &str, host: &str) -> bool {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    match name.strip_prefix("*.") {
+        Some(parent) => host.split_once('.').is_some_and(|(_, rest)| rest == parent),
+        None => name == host,
+    }
+}
+
+fn evaluate_certificate(host: &str, cert: &CertificateInfo, now: DateTime<Utc>) -> Check {
+    let names: Vec<&str> = cert.subject_alternative_names
+        .iter()
+        .filter_map(|san| san.strip_prefix("DNS: "))
+        .collect();
+    let covered = names.iter().any(|name| name_covers(name, host));
+    let remaining_secs = cert.not_after_timestamp - now.timestamp();
+    let days = remaining_secs / 86400;
+
+    let (status, summary) = if remaining_secs < 0 {
+        (Status::Fail, "certificate has expired".to_string())
+    } else if !covered {
+        (Status::Fail, format!("certificate does not cover {}", host))
+    } else if cert.is_self_signed {
+        (Status::Fail, "certificate is self-signed".to_string())
+    } else if days < CERT_EXPIRY_WARN_DAYS {
+        (Status::Warn, format!("certificate expires in {} days", days))
+    } else {
+        (Status::Pass, format!("valid for {} more days", days))
+    };
+
+    Check::new("SSL", status, summary)
+        .detail("issuer", &cert.issuer)
+        .detail("expires", &cert.not_after)
+        .detail("names", names.join(", "))
+}
+
+fn evaluate_mail(mx: &[String], txt: &[String], dmarc: &[String]) -> Check {
+    let spf = txt.iter().find(|record| record.to_ascii_lowercase().starts_with("v=spf1"));
+    let dmarc = dmarc.iter().find(|record| record.to_ascii_uppercase().starts_with("V=DMARC1"));
+    let policy = dmarc.and_then(|record| {
+        record
+            .split(';')
+            .filter_map(|tag| tag.trim().split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("p"))
+            .map(|(_, value)| value.trim().to_ascii_lowercase())
+    });
+    // A null MX (RFC 7505, `0 .`) declares that the domain receives no mail
+    let null_mx = mx.len() == 1 && mx[0].split_whitespace().nth(1).is_none();
+
+    let enforced = matches!(policy.as_deref(), Some("quarantine" | "reject"));
+    let (status, summary) = match (spf, dmarc) {
+        (Some(_), Some(_)) if enforced => {
+            (Status::Pass, format!("SPF and DMARC (p={})", policy.as_deref().unwrap_or_default()))
+        }
+        (Some(_), Some(_)) => (Status::Warn, "SPF and DMARC, but DMARC does not enforce (p=none)".to_string()),
+        (Some(_), None) => (Status::Warn, "SPF only, no DMARC policy".to_string()),
+        (None, Some(_)) => (Status::Warn, "DMARC only, no SPF record".to_string()),
+        (None, None) => (Status::Fail, "no SPF or DMARC records, the domain can be spoofed".to_string()),
+    };
+
+    let mut check = Check::new("MAIL", status, summary);
+    if null_mx {
+        check = check.detail("mx", "null MX, no mail accepted");
+    } else if mx.is_empty() {
+        check = check.detail("mx", "none");
+    } else {
+        for record in mx {
+            check = check.detail("mx", record);
+        }
+    }
+    check
+        .detail("spf", spf.map_or("none", String::as_str))
+        .detail("dmarc", dmarc.map_or("none", String::as_str))
+}
+
+/// Organization of an issuer DN such as `C=US, O=Let's Encrypt, CN=R11`
+fn issuer_organization(issuer: &str) -> &str {
+    issuer
+        .split(", ")
+        .find_map(|part| part.strip_prefix("O="))
+        .unwrap_or(issuer)
+        .trim_matches('"')
+}
+
+fn evaluate_ct(domain: &str, issuers: &[String]) -> Check {
+    if issuers.is_empty() {
+        return Check::new("CT", Status::Warn, format!("no currently valid certificates logged for {}", domain));
+    }
+    let mut organizations: Vec<&str> = issuers.iter().map(|issuer| issuer_organization(issuer)).collect();
+    organizations.sort_unstable();
+    organizations.dedup();
+
+    let summary = format!(
+        "{} valid certificate{} from {} issuer{}",
+        issuers.len(),
+        if issuers.len() == 1 { "" } else { "s" },
+        organizations.len(),
+        if organizations.len() == 1 { "" } else { "s" }
+    );
+    organizations
+        .into_iter()
+        .fold(Check::new("CT", Status::Pass, summary), |check, organization| check.detail("issuer", organization))
+}
+
+fn evaluate_reputation(verdict: Option<&HostVerdict>) -> Check {
+    let Some(verdict) = verdict else {
+        return Check::new("REPUTATION", Status::Unknown, "never scanned by urlscan.io");
+    };
+
+    let check = if verdict.malicious {
+        let categories = if verdict.categories.is_empty() { "malicious".to_string() } else { verdict.categories.join(", ") };
+        Check::new("REPUTATION", Status::Fail, format!("flagged by urlscan.io ({})", categories))
+    } else if verdict.score > 0 {
+        Check::new("REPUTATION", Status::Warn, format!("suspicious urlscan.io score {}", verdict.score))
+    } else {
+        Check::new("REPUTATION", Status::Pass, "no malicious verdicts on urlscan.io")
+    };
+    let check = check.detail("score", verdict.score);
+    let check = match &verdict.time {
+        Some(time) => check.detail("last-scan", time),
+        None => check,
+    };
+    match &verdict.report_url {
+        Some(url) => check.detail("report-url", url),
+        None => check,
+    }
+}
+
+async fn check_whois(domain: &str) -> Check {
+    match query_with_iana_referral(domain).await {
+        Ok(response) => evaluate_whois(domain, &response, Utc::now()),
+        Err(e) => Check::unknown("WHOIS", e),
+    }
+}
+
+async fn check_dns(doh: &DohClient, host: &str) -> Check {
+    let (a, aaaa, ns) = tokio::join!(doh.query(host, "A"), doh.query(host, "AAAA"), doh.query(host, "NS"));
+    match (a, aaaa, ns) {
+        (Ok(a), Ok(aaaa), Ok(ns)) =>
+            evaluate_dns(a.Status, &answers(&a, TYPE_A), &answers(&aaaa, TYPE_AAAA), &answers(&ns, TYPE_NS)),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Check::unknown("DNS", e),
+    }
+}
+
+async fn check_dnssec(doh: &DohClient, zone: &str) -> Check {
+    match doh.query(zone, "DS").await {
+        Ok(response) => evaluate_dnssec(zone, response.Status, response.AD, &answers(&response, TYPE_DS)),
+        Err(e) => Check::unknown("DNSSEC", e),
+    }
+}
+
+async fn check_ssl(host: &str) -> Check {
+    // The TLS handshake blocks, so it runs on its own task
+    let owned = host.to_string();
+    let result = tokio::spawn(async move { SslService::new().get_certificate_info(&owned, 443, None).await }).await;
+    match result {
+        Ok(Ok(cert)) => evaluate_certificate(host, &cert, Utc::now()),
+        Ok(Err(e)) => Check::new("SSL", Status::Fail, format!("no TLS on port 443: {}", e)),
+        Err(e) => Check::unknown("SSL", anyhow!(e)),
+    }
+}
+
+async fn check_mail(doh: &DohClient, host: &str) -> Check {
+    let dmarc_name = format!("_dmarc.{}", host);
+    let (mx, txt, dmarc) = tokio::join!(doh.query(host, "MX"), doh.query(host, "TXT"), doh.query(&dmarc_name, "TXT"));
+    match (mx, txt, dmarc) {
+        (Ok(mx), Ok(txt), Ok(dmarc)) =>
+            evaluate_mail(&answers(&mx, TYPE_MX), &answers(&txt, TYPE_TXT), &answers(&dmarc, TYPE_TXT)),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Check::unknown("MAIL", e),
+    }
+}
+
+async fn check_ct(host: &str) -> Check {
+    match CrtService::new().valid_certificate_issuers(host).await {
+        Ok(issuers) => evaluate_ct(host, &issuers),
+        Err(e) => Check::unknown("CT", e),
+    }
+}
+
+async fn check_reputation(host: &str) -> Check {
+    match latest_verdict(host).await {
+        Ok(verdict) => evaluate_reputation(verdict.as_ref()),
+        Err(e) => Check::unknown("REPUTATION", e),
+    }
+}
+
+/// Run a check, marking it unknown when it exceeds `limit`
+async fn limited(name: &'static str, limit: Duration, check: impl Future<Output = Check>) -> Check {
+    tokio::time::timeout(limit, check)
+        .await
+        .unwrap_or_else(|_| Check::new(name, Status::Unknown, format!("timed out after {}s", limit.as_secs())))
+}
+
+/// Letter grade for a score percentage
+fn grade(percent: u32) -> &'static str {
+    match percent {
+        90.. => "A",
+        75..=89 => "B",
+        60..=74 => "C",
+        40..=59 => "D",
+        _ => "F",
+    }
+}
+
+fn format_report(host: &str, checks: &[Check]) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("Domain Report for: {}\n", host));
+    output.push_str("=".repeat(60).as_str());
+    output.push('\n');
+
+    for check in checks {
+        output.push_str(&format!("{:<12} {:<4}  {}\n", check.name, check.status.label(), check.summary));
+    }
+    output.push_str("-".repeat(60).as_str());
+    output.push('\n');
+
+    let scored: Vec<u32> = checks.iter().filter_map(|check| check.status.points()).collect();
+    let count = |status| checks.iter().filter(|check| check.status == status).count();
+    if scored.is_empty() {
+        output.push_str("score: n/a (no check completed)\n");
+    } else {
+        let points: u32 = scored.iter().sum();
+        let possible = (scored.len() as u32) * 2;
+        let percent = (points * 100) / possible;
+        output.push_str(&format!(
+            "score: {}/{} ({}%, grade {})\n",
+            points,
+            possible,
+            percent,
+            grade(percent)
+        ));
+    }
+    output.push_str(&format!(
+        "checks: {} pass, {} warn, {} fail, {} unknown\n",
+        count(Status::Pass),
+        count(Status::Warn),
+        count(Status::Fail),
+        count(Status::Unknown)
+    ));
+
+    for check in checks.iter().filter(|check| !check.details.is_empty()) {
+        output.push('\n');
+        output.push_str(&format!("{}\n", check.name));
+        output.push_str("-".repeat(40).as_str());
+        output.push('\n');
+        for line in &check.details {
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Run all checks for `target` and render the report
+pub async fn process_report_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let Some(host) = parse_target(target) else {
+        return Ok(format!("Invalid domain: {}\nUse a domain name, e.g. example.com-REPORT\n", target));
+    };
+    let zone = registrable_domain(&host).unwrap_or_else(|| host.clone());
+    let limit = ctx.remaining().map_or(CHECK_TIMEOUT, |left| left.min(CHECK_TIMEOUT));
+    log_debug!("[{}] Generating report for {} (zone {})", ctx, host, zone);
+
+    let doh = DohClient::new();
+    let (whois, dns, dnssec, ssl, mail, ct, reputation) = tokio::join!(
+        limited("WHOIS", limit, check_whois(&zone)),
+        limited("DNS", limit, check_dns(&doh, &host)),
+        limited("DNSSEC", limit, check_dnssec(&doh, &zone)),
+        limited("SSL", limit, check_ssl(&host)),
+        limited("MAIL", limit, check_mail(&doh, &host)),
+        limited("CT", limit, check_ct(&host)),
+        limited("REPUTATION", limit, check_reputation(&host))
+    );
+
+    Ok(format_report(&host, &[whois, dns, dnssec, ssl, mail, ct, reputation]))
+}
+
+/// Consolidated domain report
+pub struct ReportService;
+
+#[async_trait]
+impl QueryService for ReportService {
+    fn name(&self) -> &'static str {
+        "report"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-REPORT"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "DOMAIN ANALYSIS",
+            summary: "Scorecard of WHOIS, DNS, DNSSEC, SSL, mail auth, CT and reputation checks",
+            example: "example.com-REPORT",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "WHOIS, Cloudflare DoH, TLS handshake, crt.sh, urlscan.io"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(REPORT_CACHE_TTL)
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_report_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::expiry::parse_whois_date;
+
+    fn now() -> DateTime<Utc> {
+        parse_whois_date("2025-06-01T00:00:00Z").unwrap()
+    }
+
+    #[test]
+    fn test_evaluate_whois() {
+        let response = "Domain Name: EXAMPLE.COM\nRegistrar: RESERVED-Internet Assigned Numbers Authority\n\
+                        Registry Expiry Date: 2025-08-13T04:00:00Z\n\
+                        Domain Status: clientDeleteProhibited https://icann.org/epp#clientDeleteProhibited\n";
+        let check = evaluate_whois("example.com", response, now());
+        assert_eq!(check.status, Status::Pass);
+        assert_eq!(check.summary, "registered until 2025-08-13 (73 days)");
+        assert!(check.details.contains(&"status: clientDeleteProhibited".to_string()));
+
+        let soon = response.replace("2025-08-13", "2025-06-20");
+        assert_eq!(evaluate_whois("example.com", &soon, now()).status, Status::Warn);
+        let unregistered = evaluate_whois("example.invalid", "No match for \"EXAMPLE.INVALID\".\n", now());
+        assert_eq!(unregistered.status, Status::Fail);
+    }
+
+    #[test]
+    fn test_evaluate_mail() {
+        let mx = vec!["10 mail.example.com".to_string()];
+        let spf = vec!["v=spf1 mx -all".to_string()];
+        let dmarc = |policy: &str| vec![format!("v=DMARC1; p={}; rua=mailto:d@example.com", policy)];
+
+        assert_eq!(evaluate_mail(&mx, &spf, &dmarc("reject")).status, Status::Pass);
+        assert_eq!(evaluate_mail(&mx, &spf, &dmarc("none")).status, Status::Warn);
+        assert_eq!(evaluate_mail(&mx, &spf, &[]).status, Status::Warn);
+        assert_eq!(evaluate_mail(&[], &[], &[]).status, Status::Fail);
+        assert_eq!(unquote_txt("\"v=spf1 include:_spf.example.com \" \"-all\""), "v=spf1 include:_spf.example.com -all");
+    }
+
+    #[test]
+    fn test_dns_and_dnssec() {
+        let a = vec!["93.184.215.14".to_string()];
+        let aaaa = vec!["2606:2800:21f:cb07:6820:80da:af6b:8b2c".to_string()];
+        let ns = vec!["a.iana-servers.net".to_string(), "b.iana-servers.net".to_string()];
+        assert_eq!(evaluate_dns(0, &a, &aaaa, &ns).status, Status::Pass);
+        assert_eq!(evaluate_dns(0, &a, &[], &ns).status, Status::Warn);
+        assert_eq!(evaluate_dns(RCODE_NXDOMAIN, &[], &[], &[]).status, Status::Fail);
+
+        let ds = vec!["370 13 2 BE74...".to_string()];
+        assert_eq!(evaluate_dnssec("example.com", 0, true, &ds).status, Status::Pass);
+        assert_eq!(evaluate_dnssec("example.com", 0, false, &[]).status, Status::Warn);
+        assert_eq!(evaluate_dnssec("example.com", RCODE_SERVFAIL, false, &[]).status, Status::Fail);
+    }
+
+    #[test]
+    fn test_name_covers_and_target() {
+        assert!(name_covers("*.example.com", "www.example.com"));
+        assert!(!name_covers("*.example.com", "example.com"));
+        assert!(name_covers("example.com", "example.com"));
+        assert_eq!(parse_target("Example.COM."), Some("example.com".to_string()));
+        assert_eq!(parse_target("1.1.1.1"), None);
+        assert_eq!(parse_target("localhost"), None);
+    }
+
+    #[test]
+    fn test_format_report_scorecard() {
+        let checks = [
+            Check::new("WHOIS", Status::Pass, "registered").detail("registrar", "Example Registrar"),
+            Check::new("DNS", Status::Warn, "IPv4 only"),
+            Check::new("REPUTATION", Status::Unknown, "never scanned by urlscan.io"),
+        ];
+        let report = format_report("example.com", &checks);
+        assert!(report.contains("WHOIS        PASS  registered\n"));
+        assert!(report.contains("score: 3/4 (75%, grade B)\n"));
+        assert!(report.contains("checks: 1 pass, 1 warn, 0 fail, 1 unknown\n"));
+        assert!(report.contains("registrar: Example Registrar\n"));
+    }
+}
@@ -0,0 +1,386 @@
+// WHOIS Server - RPKI ROA Listing
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! ROAs originated by an ASN (`AS13335-ROA`)
+//!
+//! Complements the single-route `-RPKI` validation with the full list of
+//! Route Origin Authorizations naming an ASN as origin: prefix, max length,
+//! trust anchor and expiry. The data comes from the validated ROA export of
+//! rpki.cloudflare.com, or RIPE NCC's validator when Cloudflare is down (its
+//! export has no expiry dates). Both exports cover every ASN, so one snapshot
+//! is downloaded and kept in memory for `SNAPSHOT_TTL`, grouped by origin.
+//! The snapshot also holds the export's ASPA objects and BGPsec router keys
+//! when it has them, for `-ASPA` (`services::aspa`).
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::DateTime;
+use cidr::{IpCidr, IpInet};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::core::body_limit::read_body;
+use crate::core::query::parse_asn;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use crate::services::utils::table::format_table;
+use crate::{log_debug, log_warn};
+
+const CLOUDFLARE_EXPORT: &str = "https://rpki.cloudflare.com/rpki.json";
+const RIPE_EXPORT: &str = "https://rpki-validator.ripe.net/api/export.json";
+
+/// The exports list every ROA in the world, far more than other upstreams send
+const MAX_EXPORT_BODY: usize = 512 * 1024 * 1024;
+
+/// How long a downloaded export is reused for all ASNs
+pub(crate) const SNAPSHOT_TTL: Duration = Duration::from_secs(15 * 60);
+
+static SNAPSHOT: Lazy<Mutex<Option<Arc<RoaSnapshot>>>> = Lazy::new(|| Mutex::new(None));
+
+/// ASN as a number (`13335`) or text (`"AS13335"`), depending on the export
+fn deserialize_asn<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Asn {
+        Number(u32),
+        Text(String),
+    }
+
+    match Asn::deserialize(deserializer)? {
+        Asn::Number(asn) => Ok(asn),
+        Asn::Text(text) => parse_asn(&text).ok_or_else(|| serde::de::Error::custom(format!("invalid ASN: {}", text))),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ExportMetadata {
+    /// Unix time the export was generated (Cloudflare only)
+    #[serde(default)]
+    generated: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoaExport {
+    #[serde(default)]
+    metadata: ExportMetadata,
+    roas: Vec<Roa>,
+    /// Missing from exports without ASPA support
+    #[serde(default)]
+    aspas: Option<Vec<Aspa>>,
+    #[serde(default)]
+    bgpsec_keys: Option<Vec<BgpsecKey>>,
+}
+
+/// RPKI origin validation state of a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RouteValidity {
+    Valid,
+    Invalid,
+    NotFound,
+}
+
+/// One validated ROA payload
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Roa {
+    #[serde(deserialize_with = "deserialize_asn")]
+    asn: u32,
+    prefix: String,
+    #[serde(rename = "maxLength")]
+    max_length: u8,
+    ta: String,
+    /// Unix time the ROA expires (Cloudflare only)
+    #[serde(default)]
+    expires: Option<i64>,
+}
+
+/// Provider ASNs, as numbers or `{"asid": 174, "afi_limit": "ipv4"}` objects
+/// in older rpki-client versions
+fn deserialize_providers<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<u32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Provider {
+        Number(u32),
+        Object { asid: u32 },
+    }
+
+    let providers = Vec::<Provider>::deserialize(deserializer)?;
+    Ok(providers
+        .into_iter()
+        .map(|provider| match provider {
+            Provider::Number(asn) | Provider::Object { asid: asn } => asn,
+        })
+        .collect())
+}
+
+/// A validated ASPA object: the providers a customer AS authorizes
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Aspa {
+    customer_asid: u32,
+    #[serde(deserialize_with = "deserialize_providers")]
+    pub(crate) providers: Vec<u32>,
+    #[serde(default)]
+    pub(crate) expires: Option<i64>,
+}
+
+/// A BGPsec router key certified for an ASN
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct BgpsecKey {
+    #[serde(deserialize_with = "deserialize_asn")]
+    asn: u32,
+    pub(crate) ski: String,
+    pub(crate) ta: String,
+    #[serde(default)]
+    pub(crate) expires: Option<i64>,
+}
+
+/// A validated ROA export, grouped by origin ASN
+pub(crate) struct RoaSnapshot {
+    source: &'static str,
+    generated: Option<i64>,
+    fetched_at: Instant,
+    roas: HashMap<u32, Vec<Roa>>,
+    /// (origin, max length) of the ROAs for each prefix, for route origin validation
+    by_prefix: HashMap<IpCidr, Vec<(u32, u8)>>,
+    /// ASPA objects by customer ASN, `None` when the export has none
+    aspas: Option<HashMap<u32, Aspa>>,
+    /// BGPsec router keys by ASN, `None` when the export has none
+    bgpsec_keys: Option<HashMap<u32, Vec<BgpsecKey>>>,
+}
+
+impl RoaSnapshot {
+    fn new(source: &'static str, export: RoaExport) -> Self {
+        let mut roas: HashMap<u32, Vec<Roa>> = HashMap::new();
+        let mut by_prefix: HashMap<IpCidr, Vec<(u32, u8)>> = HashMap::new();
+        for roa in export.roas {
+            if let Ok(prefix) = roa.prefix.parse::<IpCidr>() {
+                by_prefix.entry(prefix).or_default().push((roa.asn, roa.max_length));
+            }
+            roas.entry(roa.asn).or_default().push(roa);
+        }
+        let aspas = export.aspas.map(|aspas| aspas.into_iter().map(|aspa| (aspa.customer_asid, aspa)).collect());
+        let bgpsec_keys = export.bgpsec_keys.map(|keys| {
+            let mut by_asn: HashMap<u32, Vec<BgpsecKey>> = HashMap::new();
+            for key in keys {
+                by_asn.entry(key.asn).or_default().push(key);
+            }
+            by_asn
+        });
+        Self { source, generated: export.metadata.generated, fetched_at: Instant::now(), roas, by_prefix, aspas, bgpsec_keys }
+    }
+
+    /// Snapshot of an export given as JSON
+    #[cfg(test)]
+    pub(crate) fn from_json(source: &'static str, json: &str) -> Self {
+        Self::new(source, serde_json::from_str(json).unwrap())
+    }
+
+    /// Name of the export this snapshot comes from
+    pub(crate) fn source(&self) -> &'static str {
+        self.source
+    }
+
+    /// `% Source:` header line naming the export and when it was generated
+    pub(crate) fn source_line(&self) -> String {
+        match self.generated {
+            Some(generated) => format!(
+                "% Source: {} (generated {})\n",
+                self.source,
+                format_unix_time(generated, "%Y-%m-%d %H:%M:%S UTC")
+            ),
+            None => format!("% Source: {}\n", self.source),
+        }
+    }
+
+    /// The ASPA of a customer ASN: `None` when the export carries no ASPA
+    /// objects at all, `Some(None)` when the ASN has published none
+    pub(crate) fn aspa_for(&self, asn: u32) -> Option<Option<&Aspa>> {
+        self.aspas.as_ref().map(|aspas| aspas.get(&asn))
+    }
+
+    /// BGPsec router keys of an ASN, `None` when the export carries none
+    pub(crate) fn bgpsec_keys_for(&self, asn: u32) -> Option<&[BgpsecKey]> {
+        self.bgpsec_keys.as_ref().map(|keys| keys.get(&asn).map(Vec::as_slice).unwrap_or_default())
+    }
+
+    /// Route origin validation of `prefix` announced by `origin` (RFC 6811)
+    pub(crate) fn validate(&self, prefix: &IpCidr, origin: u32) -> RouteValidity {
+        let mut covered = false;
+        for length in 0..=prefix.network_length() {
+            let Ok(covering) = IpInet::new(prefix.first_address(), length) else {
+                continue;
+            };
+            if let Some(roas) = self.by_prefix.get(&covering.network()) {
+                covered = true;
+                // AS0 ROAs never make a route valid (RFC 6483)
+                if roas.iter().any(|&(asn, max_length)| asn == origin && asn != 0 && prefix.network_length() <= max_length) {
+                    return RouteValidity::Valid;
+                }
+            }
+        }
+        if covered { RouteValidity::Invalid } else { RouteValidity::NotFound }
+    }
+
+    /// ROAs with `asn` as origin, IPv4 before IPv6, in address order
+    fn roas_for(&self, asn: u32) -> Vec<&Roa> {
+        let mut roas: Vec<&Roa> = self.roas.get(&asn).map(|roas| roas.iter().collect()).unwrap_or_default();
+        roas.sort_by_key(|roa| {
+            let prefix = roa.prefix.parse::<IpCidr>().ok();
+            (prefix.map(|p| (p.first_address(), p.network_length())), roa.max_length)
+        });
+        roas
+    }
+}
+
+async fn download(client: &reqwest::Client, source: &'static str, url: &str) -> Result<RoaSnapshot> {
+    let response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{} ROA export request failed: HTTP {}", source, response.status()));
+    }
+    let export: RoaExport = serde_json::from_slice(&read_body(response, MAX_EXPORT_BODY).await?)?;
+    log_debug!("Downloaded {} ROAs from {}", export.roas.len(), source);
+    Ok(RoaSnapshot::new(source, export))
+}
+
+/// The current ROA export, downloaded again once `SNAPSHOT_TTL` has passed
+pub(crate) async fn roa_snapshot() -> Result<Arc<RoaSnapshot>> {
+    // Holding the lock while downloading lets concurrent queries share one download
+    let mut cached = SNAPSHOT.lock().await;
+    if let Some(snapshot) = cached.as_ref()
+        && snapshot.fetched_at.elapsed() < SNAPSHOT_TTL
+    {
+        return Ok(snapshot.clone());
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .user_agent("whois-server/1.0")
+        .build()?;
+    let snapshot = match download(&client, "rpki.cloudflare.com", &api_base(CLOUDFLARE_EXPORT)).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            log_warn!("Cloudflare ROA export unavailable, using RIPE NCC's validator: {}", e);
+            download(&client, "rpki-validator.ripe.net", &api_base(RIPE_EXPORT)).await?
+        }
+    };
+
+    let snapshot = Arc::new(snapshot);
+    *cached = Some(snapshot.clone());
+    Ok(snapshot)
+}
+
+pub(crate) fn format_unix_time(secs: i64, format: &str) -> String {
+    DateTime::from_timestamp(secs, 0)
+        .map(|date| date.format(format).to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn format_roas(asn: u32, snapshot: &RoaSnapshot) -> String {
+    let roas = snapshot.roas_for(asn);
+    let ipv4 = roas.iter().filter(|roa| !roa.prefix.contains(':')).count();
+
+    let mut output = format!("% RPKI ROAs originated by AS{}\n", asn);
+    output.push_str(&snapshot.source_line());
+    output.push_str("% Validate a single route with <prefix>-<asn>-RPKI\n\n");
+
+    if roas.is_empty() {
+        output.push_str(&format!("% No ROAs found for AS{}\n", asn));
+        return output;
+    }
+
+    output.push_str(&format!("% {} ROAs ({} IPv4, {} IPv6)\n\n", roas.len(), ipv4, roas.len() - ipv4));
+    let rows: Vec<Vec<String>> = roas
+        .iter()
+        .map(|roa| {
+            vec![
+                roa.prefix.clone(),
+                roa.max_length.to_string(),
+                roa.ta.clone(),
+                roa.expires.map(|expires| format_unix_time(expires, "%Y-%m-%d")).unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+    output.push_str(&format_table(&["Prefix", "Max Length", "Trust Anchor", "Expires"], &rows));
+    output
+}
+
+/// Process an `-ROA` query
+pub async fn process_roa_query(target: &str) -> Result<String> {
+    let asn = parse_asn(target.trim()).ok_or_else(|| anyhow!("Usage: AS<number>-ROA, got: {}", target.trim()))?;
+    let snapshot = roa_snapshot().await?;
+    Ok(format_roas(asn, &snapshot))
+}
+
+pub struct RoaService;
+
+#[async_trait]
+impl QueryService for RoaService {
+    fn name(&self) -> &'static str {
+        "roa"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-ROA"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "RPKI ROAs originated by an ASN: prefix, max length, trust anchor and expiry",
+            example: "AS13335-ROA",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "rpki.cloudflare.com (RIPE NCC validator as fallback)"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(SNAPSHOT_TTL)
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_roa_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exports_parse_and_format() {
+        let snapshot = RoaSnapshot::from_json(
+            "rpki.cloudflare.com",
+            r#"{"metadata": {"generated": 1735689600}, "roas": [
+                {"prefix": "2606:4700::/32", "maxLength": 48, "asn": 13335, "ta": "arin", "expires": 1736294400},
+                {"prefix": "104.16.0.0/13", "maxLength": 24, "asn": 13335, "ta": "arin", "expires": 1736294400},
+                {"prefix": "1.1.1.0/24", "maxLength": 24, "asn": 13335, "ta": "apnic", "expires": 1736294400},
+                {"prefix": "193.0.0.0/21", "maxLength": 21, "asn": 3333, "ta": "ripe", "expires": 1736294400}
+            ]}"#
+        );
+
+        let output = format_roas(13335, &snapshot);
+        assert!(output.contains("% Source: rpki.cloudflare.com (generated 2025-01-01 00:00:00 UTC)\n"));
+        assert!(output.contains("% 3 ROAs (2 IPv4, 1 IPv6)\n"));
+        assert!(output.contains("1.1.1.0/24     | 24         | apnic        | 2025-01-08\n"));
+        assert!(output.find("1.1.1.0/24") < output.find("104.16.0.0/13"));
+        assert!(output.find("104.16.0.0/13") < output.find("2606:4700::/32"));
+        assert!(!output.contains("193.0.0.0/21"));
+        assert!(format_roas(64500, &snapshot).contains("% No ROAs found for AS64500\n"));
+
+        // RIPE's export names the ASN as text and has no expiry
+        let ripe = RoaSnapshot::from_json(
+            "rpki-validator.ripe.net",
+            r#"{"roas": [{"asn": "AS3333", "prefix": "193.0.0.0/21", "maxLength": 21, "ta": "RIPE NCC RPKI Root"}]}"#
+        );
+        let output = format_roas(3333, &ripe);
+        assert!(output.contains("% Source: rpki-validator.ripe.net\n"));
+        assert!(output.contains("193.0.0.0/21 | 21         | RIPE NCC RPKI Root | -\n"));
+    }
+}
@@ -1,6 +1,8 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
+use crate::services::upstream::api_base;
 use crate::{log_debug};
 // RPKI API
 const RPKI_API_BASE: &str = "https://rpki.akae.re/api/v1/validity";
@@ -51,7 +53,7 @@ pub struct Vrp {
 pub async fn process_rpki_query(prefix: &str, asn: &str) -> Result<String> {
     log_debug!("Processing RPKI query for prefix: {}, ASN: {}", prefix, asn);
 
-    let url = format!("{}/{}/{}", RPKI_API_BASE, asn, prefix);
+    let url = format!("{}/{}/{}", api_base(RPKI_API_BASE), asn, prefix);
     log_debug!("Requesting RPKI API URL: {}", url);
 
     let client = reqwest::Client::builder()
@@ -71,7 +73,7 @@ pub async fn process_rpki_query(prefix: &str, asn: &str) -> Result<String> {
         ));
     }
 
-    let rpki_response: RpkiResponse = response.json().await?;
+    let rpki_response: RpkiResponse = response.limited_json().await?;
     format_rpki_response(prefix, asn, &rpki_response)
 }
 
@@ -178,6 +180,7 @@ fn format_rpki_response(prefix: &str, asn: &str, response: &RpkiResponse) -> Res
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::fixtures::FixtureServer;
 
     #[test]
     fn test_format_rpki_response() {
@@ -214,4 +217,20 @@ mod tests {
         assert!(formatted.contains("origin-asn:     AS13335"));
         assert!(formatted.contains("prefix:         1.1.1.0/24"));
     }
+
+    #[tokio::test]
+    async fn test_rpki_fixture() {
+        let mut fixtures = FixtureServer::start("rpki").await;
+        fixtures.serve(RPKI_API_BASE, "/64496/1.1.1.0/24", "invalid_as.json").await;
+        fixtures.serve_status(RPKI_API_BASE, "/64496/192.0.2.0/24", 503).await;
+
+        let response = fixtures.run(process_rpki_query("1.1.1.0/24", "64496")).await.unwrap();
+        assert!(response.contains("% Generated Time: 2024-09-06T21:10:03Z\n"));
+        assert!(response.contains("  state:          invalid\n  description:"));
+        assert!(response.contains("  reason:         as\n"));
+        assert!(response.contains("  matched:        none\n  unmatched-as:\n    asn:          AS13335\n"));
+
+        let error = fixtures.run(process_rpki_query("192.0.2.0/24", "64496")).await.unwrap_err();
+        assert!(error.to_string().contains("503"));
+    }
 }
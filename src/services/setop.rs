@@ -0,0 +1,313 @@
+// WHOIS Server - Prefix Set Operations
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Prefix list calculator (`-SETOP`)
+//!
+//! Combines comma-separated prefix lists with `UNION`, `INTERSECT` and
+//! `EXCLUDE`, applied left to right, and prints the result as the smallest
+//! list of aggregated prefixes:
+//!
+//! ```text
+//! 192.0.2.0/24,10.0.0.0/8 EXCLUDE RFC1918 EXCLUDE 192.0.2.128/27-SETOP
+//! ```
+//!
+//! A single list without an operator is just aggregated. Everything is
+//! computed locally; no upstream is queried.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use cidr::IpCidr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+
+const USAGE: &str = "Usage: <prefix>,<prefix>... [UNION|INTERSECT|EXCLUDE <prefix>,<prefix>...]...-SETOP";
+
+/// Named prefix lists usable in place of a prefix
+const NAMED_SETS: &[(&str, &[&str])] = &[
+    ("RFC1918", &["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"]),
+    ("RFC6598", &["100.64.0.0/10"]),
+    ("RFC4193", &["fc00::/7"]),
+];
+
+/// Inclusive address range, IPv4 addresses widened to `u128`
+type Range = (u128, u128);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetOp {
+    Union,
+    Intersect,
+    Exclude,
+}
+
+impl SetOp {
+    fn parse(word: &str) -> Option<Self> {
+        match word.to_ascii_uppercase().as_str() {
+            "UNION" | "OR" => Some(Self::Union),
+            "INTERSECT" | "AND" => Some(Self::Intersect),
+            "EXCLUDE" | "MINUS" | "EXCEPT" => Some(Self::Exclude),
+            _ => None,
+        }
+    }
+}
+
+/// Sorted, non-overlapping, non-adjacent ranges of one address family
+#[derive(Debug, Clone, Default, PartialEq)]
+struct RangeSet(Vec<Range>);
+
+impl RangeSet {
+    fn from_ranges(mut ranges: Vec<Range>) -> Self {
+        ranges.sort_unstable();
+        let mut merged: Vec<Range> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if last.1 == u128::MAX || start <= last.1 + 1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        Self(merged)
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        Self::from_ranges(self.0.iter().chain(&other.0).copied().collect())
+    }
+
+    fn intersect(&self, other: &Self) -> Self {
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.0.len() && j < other.0.len() {
+            let (a, b) = (self.0[i], other.0[j]);
+            let (start, end) = (a.0.max(b.0), a.1.min(b.1));
+            if start <= end {
+                result.push((start, end));
+            }
+            if a.1 < b.1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self(result)
+    }
+
+    fn exclude(&self, other: &Self) -> Self {
+        let mut result = Vec::new();
+        for &(start, end) in &self.0 {
+            // Start of the part not yet cut, None once the range is used up
+            let mut from = Some(start);
+            for &(cut_start, cut_end) in &other.0 {
+                let Some(rest) = from else { break };
+                if cut_end < rest {
+                    continue;
+                }
+                if cut_start > end {
+                    break;
+                }
+                if cut_start > rest {
+                    result.push((rest, cut_start - 1));
+                }
+                from = cut_end.checked_add(1).filter(|next| *next <= end);
+            }
+            if let Some(rest) = from {
+                result.push((rest, end));
+            }
+        }
+        Self(result)
+    }
+
+    fn apply(&self, op: SetOp, other: &Self) -> Self {
+        match op {
+            SetOp::Union => self.union(other),
+            SetOp::Intersect => self.intersect(other),
+            SetOp::Exclude => self.exclude(other),
+        }
+    }
+
+    /// Smallest list of `(network, length)` prefixes covering the set
+    fn prefixes(&self, bits: u32) -> Vec<(u128, u32)> {
+        let mut prefixes = Vec::new();
+        for &(mut start, end) in &self.0 {
+            loop {
+                let span = end - start;
+                let fits = if span == u128::MAX { 128 } else { 127 - (span + 1).leading_zeros() };
+                let size = start.trailing_zeros().min(fits).min(bits);
+                prefixes.push((start, bits - size));
+                let last = start + if size == 128 { u128::MAX } else { (1u128 << size) - 1 };
+                if last >= end {
+                    break;
+                }
+                start = last + 1;
+            }
+        }
+        prefixes
+    }
+
+    /// Number of addresses, in units of `2^shift`
+    fn size(&self, shift: u32) -> u128 {
+        self.0
+            .iter()
+            .map(|(start, end)| ((end - start) >> shift).saturating_add(1))
+            .fold(0u128, u128::saturating_add)
+    }
+}
+
+/// IPv4 and IPv6 prefixes of one list
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PrefixSet {
+    v4: RangeSet,
+    v6: RangeSet,
+}
+
+impl PrefixSet {
+    fn parse(items: &[&str]) -> Result<Self> {
+        let (mut v4, mut v6) = (Vec::new(), Vec::new());
+        for item in items {
+            let named = NAMED_SETS.iter().find(|(name, _)| name.eq_ignore_ascii_case(item));
+            let prefixes = named.map_or_else(|| vec![*item], |(_, prefixes)| prefixes.to_vec());
+            for prefix in prefixes {
+                let cidr: IpCidr = prefix
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid prefix (host bits set or malformed): {}", prefix))?;
+                match (cidr.first_address(), cidr.last_address()) {
+                    (IpAddr::V4(first), IpAddr::V4(last)) => v4.push((u32::from(first).into(), u32::from(last).into())),
+                    (IpAddr::V6(first), IpAddr::V6(last)) => v6.push((u128::from(first), u128::from(last))),
+                    _ => unreachable!("prefix bounds share an address family"),
+                }
+            }
+        }
+        Ok(Self { v4: RangeSet::from_ranges(v4), v6: RangeSet::from_ranges(v6) })
+    }
+
+    fn apply(&self, op: SetOp, other: &Self) -> Self {
+        Self { v4: self.v4.apply(op, &other.v4), v6: self.v6.apply(op, &other.v6) }
+    }
+
+    fn cidrs(&self) -> Vec<String> {
+        let v4 = self.v4.prefixes(32).into_iter().map(|(network, len)| format!("{}/{}", Ipv4Addr::from(network as u32), len));
+        let v6 = self.v6.prefixes(128).into_iter().map(|(network, len)| format!("{}/{}", Ipv6Addr::from(network), len));
+        v4.chain(v6).collect()
+    }
+}
+
+/// Evaluate `list [OP list]...` left to right
+fn evaluate(expression: &str) -> Result<PrefixSet> {
+    let mut result: Option<PrefixSet> = None;
+    let mut pending = None;
+    let mut items: Vec<&str> = Vec::new();
+    for word in expression.split_whitespace() {
+        if let Some(op) = SetOp::parse(word) {
+            if items.is_empty() {
+                return Err(anyhow!("{} needs a prefix list on both sides\n{}", word.to_ascii_uppercase(), USAGE));
+            }
+            let list = PrefixSet::parse(&items)?;
+            result = Some(match (result, pending) {
+                (Some(left), Some(pending)) => left.apply(pending, &list),
+                _ => list,
+            });
+            pending = Some(op);
+            items.clear();
+        } else {
+            items.extend(word.split(',').map(str::trim).filter(|item| !item.is_empty()));
+        }
+    }
+
+    if items.is_empty() {
+        return Err(match pending {
+            Some(_) => anyhow!("Operators need a prefix list on both sides\n{}", USAGE),
+            None => anyhow!(USAGE),
+        });
+    }
+    let list = PrefixSet::parse(&items)?;
+    Ok(match (result, pending) {
+        (Some(left), Some(pending)) => left.apply(pending, &list),
+        _ => list,
+    })
+}
+
+/// Process a `-SETOP` query
+pub async fn process_setop_query(expression: &str) -> Result<String> {
+    let result = evaluate(expression)?;
+    let prefixes = result.cidrs();
+
+    let mut output = String::new();
+    output.push_str(&format!("% Prefix set operation: {}\n", expression.split_whitespace().collect::<Vec<_>>().join(" ")));
+    output.push_str("% Operations apply left to right; the result is aggregated\n");
+    output.push('\n');
+    if prefixes.is_empty() {
+        output.push_str("% The result is empty\n");
+    }
+    for prefix in &prefixes {
+        output.push_str(&format!("prefix:         {}\n", prefix));
+    }
+    output.push('\n');
+    output.push_str(&format!(
+        "% {} prefixes, {} IPv4 addresses, {} IPv6 /64s\n",
+        prefixes.len(),
+        result.v4.size(0),
+        result.v6.size(64)
+    ));
+    Ok(output)
+}
+
+pub struct SetOpService;
+
+#[async_trait]
+impl QueryService for SetOpService {
+    fn name(&self) -> &'static str {
+        "setop"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-SETOP"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK INFORMATION",
+            summary: "Union, intersection or exclusion of prefix lists, aggregated (RFC1918, RFC6598, RFC4193 usable as lists)",
+            example: "192.0.2.0/24,10.0.0.0/8 EXCLUDE RFC1918 EXCLUDE 192.0.2.128/27-SETOP",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "computed locally"
+    }
+
+    async fn handle(&self, _ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_setop_query(target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_operations() {
+        let result = evaluate("192.0.2.0/24,10.0.0.0/8 EXCLUDE RFC1918 EXCLUDE 192.0.2.128/27").unwrap();
+        assert_eq!(result.cidrs(), vec!["192.0.2.0/25", "192.0.2.160/27", "192.0.2.192/26"]);
+
+        let result = evaluate("198.51.100.0/25, 198.51.100.128/25,2001:db8::/33 union 2001:db8:8000::/33").unwrap();
+        assert_eq!(result.cidrs(), vec!["198.51.100.0/24", "2001:db8::/32"]);
+
+        let result = evaluate("10.0.0.0/8 INTERSECT 10.1.0.0/16,172.16.0.0/12").unwrap();
+        assert_eq!(result.cidrs(), vec!["10.1.0.0/16"]);
+
+        let result = evaluate("0.0.0.0/0,::/0 EXCLUDE 0.0.0.0/1").unwrap();
+        assert_eq!(result.cidrs(), vec!["128.0.0.0/1", "::/0"]);
+        assert_eq!(result.v4.size(0), 1 << 31);
+
+        assert!(evaluate("10.0.0.0/8 EXCLUDE").is_err());
+        assert!(evaluate("EXCLUDE 10.0.0.0/8").is_err());
+        assert!(evaluate("10.0.0.1/8").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_setop_output() {
+        let output = process_setop_query("203.0.113.0/24 EXCLUDE 203.0.113.0/24").await.unwrap();
+        assert!(output.contains("% The result is empty\n"));
+        assert!(output.contains("% 0 prefixes, 0 IPv4 addresses, 0 IPv6 /64s\n"));
+    }
+}
@@ -0,0 +1,370 @@
+// WHOIS Server - Speedtest Server Finder
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Speedtest servers near an IP address or place (`-SPEEDTEST`)
+//!
+//! IP addresses are geolocated with ip-api.com; Ookla servers in the same
+//! city and country are then sorted by great-circle distance. Place names
+//! are searched as given. LibreSpeed servers publish no coordinates, so they
+//! are matched by name. With an `rtt:` prefix (`rtt:1.1.1.1-SPEEDTEST`) the
+//! closest candidates are timed with a TCP connect from this server.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::services::geo::ipapi::query_ipapi;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use crate::services::utils::table::{format_table, section};
+use crate::log_debug;
+
+const OOKLA_API_BASE: &str = "https://www.speedtest.net/api/js/servers";
+const LIBRESPEED_SERVERS_URL: &str = "https://librespeed.org/backend-servers/servers.php";
+/// Servers listed per provider
+const MAX_SERVERS: usize = 10;
+/// Closest servers per provider timed by `rtt:` queries
+const RTT_CANDIDATES: usize = 3;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(Debug, Clone, Deserialize)]
+struct OoklaServer {
+    id: String,
+    name: String,
+    country: String,
+    sponsor: String,
+    host: String,
+    lat: String,
+    lon: String,
+}
+
+impl OoklaServer {
+    fn coords(&self) -> Option<(f64, f64)> {
+        Some((self.lat.parse().ok()?, self.lon.parse().ok()?))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LibreSpeedServer {
+    name: String,
+    server: String,
+    #[serde(rename = "sponsorName", default)]
+    sponsor: String,
+}
+
+/// Where to look for servers
+#[derive(Debug)]
+struct Origin {
+    label: String,
+    /// Search terms, most specific first
+    terms: Vec<String>,
+    coords: Option<(f64, f64)>,
+}
+
+/// Great-circle distance in kilometres
+fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Split the optional `rtt:` prefix off a query
+fn split_rtt(query: &str) -> (bool, &str) {
+    let query = query.trim();
+    match query.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("rtt:") => (true, query[4..].trim()),
+        _ => (false, query),
+    }
+}
+
+/// Host and port of a LibreSpeed backend URL (`//host/path/` or `https://host/`)
+fn librespeed_host(server: &str) -> Option<(String, u16)> {
+    let absolute = if server.starts_with("//") { format!("https:{}", server) } else { server.to_string() };
+    let url = url::Url::parse(&absolute).ok()?;
+    Some((url.host_str()?.to_string(), url.port_or_known_default()?))
+}
+
+/// Host and port of an Ookla server (`host:8080`)
+fn ookla_host(server: &OoklaServer) -> Option<(String, u16)> {
+    let (host, port) = server.host.rsplit_once(':')?;
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+/// Order servers by distance from the origin, keeping search order without coordinates
+fn rank_ookla(servers: Vec<OoklaServer>, origin: &Origin) -> Vec<(OoklaServer, Option<f64>)> {
+    // City and country searches overlap
+    let mut seen = HashSet::new();
+    let mut ranked: Vec<(OoklaServer, Option<f64>)> = servers
+        .into_iter()
+        .filter(|server| seen.insert(server.id.clone()))
+        .map(|server| {
+            let distance = origin.coords.zip(server.coords()).map(|(from, to)| distance_km(from, to));
+            (server, distance)
+        })
+        .collect();
+    if origin.coords.is_some() {
+        ranked.sort_by(|a, b| a.1.unwrap_or(f64::MAX).total_cmp(&b.1.unwrap_or(f64::MAX)));
+    }
+    ranked.truncate(MAX_SERVERS);
+    ranked
+}
+
+fn match_librespeed(servers: Vec<LibreSpeedServer>, origin: &Origin) -> Vec<LibreSpeedServer> {
+    let terms: Vec<String> = origin.terms.iter().map(|t| t.to_lowercase()).collect();
+    let mut matched: Vec<LibreSpeedServer> = servers
+        .into_iter()
+        .filter(|server| terms.iter().any(|term| server.name.to_lowercase().contains(term)))
+        .collect();
+    // Servers in the city before those only in the country
+    if let Some(first) = terms.first() {
+        matched.sort_by_key(|server| !server.name.to_lowercase().contains(first));
+    }
+    matched.truncate(MAX_SERVERS);
+    matched
+}
+
+async fn locate(client: &reqwest::Client, target: &str) -> Result<Origin> {
+    let Ok(ip) = target.parse::<IpAddr>() else {
+        return Ok(Origin { label: target.to_string(), terms: vec![target.to_string()], coords: None });
+    };
+    let geo = query_ipapi(client, &ip.to_string()).await?;
+    let place: Vec<String> = [geo.city, geo.country].into_iter().flatten().filter(|p| !p.is_empty()).collect();
+    if place.is_empty() {
+        return Err(anyhow!("No location known for {}", ip));
+    }
+    let coords = geo.lat.zip(geo.lon);
+    let label = match coords {
+        Some((lat, lon)) => format!("{} ({}, {:.2}, {:.2})", ip, place.join(", "), lat, lon),
+        None => format!("{} ({})", ip, place.join(", ")),
+    };
+    Ok(Origin { label, terms: place, coords })
+}
+
+async fn fetch_ookla(client: &reqwest::Client, term: &str) -> Result<Vec<OoklaServer>> {
+    let response = client
+        .get(api_base(OOKLA_API_BASE).as_ref())
+        .query(&[("engine", "js"), ("search", term), ("limit", "50")])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Ookla server list request failed: HTTP {}", response.status()));
+    }
+    Ok(response.limited_json().await?)
+}
+
+async fn fetch_librespeed(client: &reqwest::Client) -> Result<Vec<LibreSpeedServer>> {
+    let response = client.get(api_base(LIBRESPEED_SERVERS_URL).as_ref()).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("LibreSpeed server list request failed: HTTP {}", response.status()));
+    }
+    Ok(response.limited_json().await?)
+}
+
+/// TCP connect time to a server in milliseconds
+async fn connect_rtt(host: String, port: u16) -> Option<f64> {
+    let addr = tokio::net::lookup_host((host.as_str(), port)).await.ok()?.next()?;
+    let start = Instant::now();
+    tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await.ok()?.ok()?;
+    Some(start.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Time the first candidates, leaving the rest unmeasured
+async fn measure(hosts: Vec<Option<(String, u16)>>) -> Vec<Option<f64>> {
+    let checks = hosts.into_iter().enumerate().map(|(i, host)| async move {
+        match host {
+            Some((host, port)) if i < RTT_CANDIDATES => connect_rtt(host, port).await,
+            _ => None,
+        }
+    });
+    futures::future::join_all(checks).await
+}
+
+fn rtt_label(rtt: Option<f64>) -> String {
+    rtt.map_or_else(|| "-".to_string(), |ms| format!("{:.1} ms", ms))
+}
+
+fn format_servers(
+    origin: &Origin,
+    ookla: &[(OoklaServer, Option<f64>)],
+    librespeed: &[LibreSpeedServer],
+    rtts: Option<(Vec<Option<f64>>, Vec<Option<f64>>)>,
+) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("% Speedtest servers near {}\n", origin.label));
+    output.push_str("% Sources: speedtest.net (Ookla), librespeed.org\n");
+    if rtts.is_some() {
+        output.push_str(&format!("% RTT: TCP connect time from this server to the {} closest servers of each provider\n", RTT_CANDIDATES));
+    }
+    output.push('\n');
+    let (ookla_rtts, librespeed_rtts) = rtts.unwrap_or_default();
+
+    section(&mut output, "Ookla Servers");
+    if ookla.is_empty() {
+        output.push_str("% No Ookla servers found\n");
+    } else {
+        let mut header = vec!["ID", "Sponsor", "Location", "Distance", "Host"];
+        if !ookla_rtts.is_empty() {
+            header.push("RTT");
+        }
+        let rows: Vec<Vec<String>> = ookla
+            .iter()
+            .enumerate()
+            .map(|(i, (server, distance))| {
+                let mut row = vec![
+                    server.id.clone(),
+                    server.sponsor.clone(),
+                    format!("{}, {}", server.name, server.country),
+                    distance.map_or_else(|| "-".to_string(), |km| format!("{:.0} km", km)),
+                    server.host.clone(),
+                ];
+                if !ookla_rtts.is_empty() {
+                    row.push(rtt_label(ookla_rtts.get(i).copied().flatten()));
+                }
+                row
+            })
+            .collect();
+        output.push_str(&format_table(&header, &rows));
+    }
+    output.push('\n');
+
+    section(&mut output, "LibreSpeed Servers");
+    if librespeed.is_empty() {
+        output.push_str("% No LibreSpeed servers found\n");
+    } else {
+        let mut header = vec!["Name", "Sponsor", "Server"];
+        if !librespeed_rtts.is_empty() {
+            header.push("RTT");
+        }
+        let rows: Vec<Vec<String>> = librespeed
+            .iter()
+            .enumerate()
+            .map(|(i, server)| {
+                let mut row = vec![server.name.clone(), server.sponsor.clone(), server.server.clone()];
+                if !librespeed_rtts.is_empty() {
+                    row.push(rtt_label(librespeed_rtts.get(i).copied().flatten()));
+                }
+                row
+            })
+            .collect();
+        output.push_str(&format_table(&header, &rows));
+    }
+    output
+}
+
+/// Process a `-SPEEDTEST` query
+pub async fn process_speedtest_query(ctx: &RequestContext, query: &str) -> Result<String> {
+    let (with_rtt, target) = split_rtt(query);
+    if target.is_empty() {
+        return Err(anyhow!("Usage: <IP address or place>-SPEEDTEST, or rtt:<IP address or place>-SPEEDTEST"));
+    }
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent("whois-server/1.0")
+        .build()?;
+
+    let origin = locate(&client, target).await?;
+    log_debug!("[{}] Searching speedtest servers for {:?}", ctx, origin.terms);
+
+    let mut ookla = Vec::new();
+    for term in &origin.terms {
+        ookla.extend(fetch_ookla(&client, term).await?);
+        // A city with enough servers does not need the country searched
+        if ookla.len() >= MAX_SERVERS {
+            break;
+        }
+    }
+    let ookla = rank_ookla(ookla, &origin);
+    let librespeed = match fetch_librespeed(&client).await {
+        Ok(servers) => match_librespeed(servers, &origin),
+        Err(e) => {
+            log_debug!("[{}] LibreSpeed server list unavailable: {}", ctx, e);
+            Vec::new()
+        }
+    };
+
+    let rtts = if with_rtt {
+        let ookla_hosts = ookla.iter().map(|(server, _)| ookla_host(server)).collect();
+        let librespeed_hosts = librespeed.iter().map(|server| librespeed_host(&server.server)).collect();
+        Some(tokio::join!(measure(ookla_hosts), measure(librespeed_hosts)))
+    } else {
+        None
+    };
+
+    Ok(format_servers(&origin, &ookla, &librespeed, rtts))
+}
+
+pub struct SpeedtestService;
+
+#[async_trait]
+impl QueryService for SpeedtestService {
+    fn name(&self) -> &'static str {
+        "speedtest"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-SPEEDTEST"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "NETWORK MEASUREMENT",
+            summary: "Nearby Ookla and LibreSpeed servers (rtt: prefix times the closest)",
+            example: "rtt:Amsterdam-SPEEDTEST",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "speedtest.net and librespeed.org server lists, ip-api.com"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(3600))
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_speedtest_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::request_context::Frontend;
+    use crate::services::fixtures::FixtureServer;
+
+    #[test]
+    fn test_distance_and_hosts() {
+        // Amsterdam to Frankfurt
+        let km = distance_km((52.37, 4.89), (50.11, 8.68));
+        assert!((km - 365.0).abs() < 5.0, "{}", km);
+
+        assert_eq!(split_rtt("rtt:1.1.1.1"), (true, "1.1.1.1"));
+        assert_eq!(split_rtt("Amsterdam"), (false, "Amsterdam"));
+        assert_eq!(librespeed_host("//ams.speedtest.example.net/backend/"), Some(("ams.speedtest.example.net".to_string(), 443)));
+        assert_eq!(librespeed_host("http://fra.example.net:8080/"), Some(("fra.example.net".to_string(), 8080)));
+    }
+
+    #[tokio::test]
+    async fn test_speedtest_fixture() {
+        let mut fixtures = FixtureServer::start("speedtest").await;
+        fixtures.serve(OOKLA_API_BASE, "?engine=js&search=Amsterdam&limit=50", "ookla_amsterdam.json").await;
+        fixtures.serve(LIBRESPEED_SERVERS_URL, "", "librespeed.json").await;
+
+        let ctx = RequestContext::new(Frontend::Library);
+        let response = fixtures.run(process_speedtest_query(&ctx, "Amsterdam")).await.unwrap();
+        assert!(response.contains("% Speedtest servers near Amsterdam\n"));
+        assert!(response.contains("13883 | KPN"));
+        assert!(response.contains("Amsterdam, Netherlands (Clouvider)"));
+        assert!(!response.contains("Frankfurt, Germany"));
+        assert!(!response.contains("RTT"));
+    }
+}
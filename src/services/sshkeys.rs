@@ -0,0 +1,425 @@
+// WHOIS Server - SSH Host Keys
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! SSH host key scanning (`-SSHKEYS`)
+//!
+//! Connects to `host[:port]` (port 22 by default) once per host key
+//! algorithm, stopping after the key exchange, and reports each key's SHA256
+//! and MD5 fingerprints. For hostnames the keys are compared with the
+//! published SSHFP records and mismatches are flagged.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use russh::client;
+use russh_keys::PublicKeyBase64;
+use russh_keys::key;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::core::is_internal;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::utils::doh::DohClient;
+use crate::log_debug;
+
+const DEFAULT_SSH_PORT: u16 = 22;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Host key algorithms that are probed, one connection each
+const PROBED_ALGORITHMS: [key::Name; 5] = [
+    key::ED25519,
+    key::ECDSA_SHA2_NISTP256,
+    key::ECDSA_SHA2_NISTP384,
+    key::ECDSA_SHA2_NISTP521,
+    key::RSA_SHA2_512,
+];
+
+/// A scanned host key
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HostKey {
+    /// Key type from the wire encoding, e.g. `ssh-ed25519`
+    key_type: String,
+    /// SSH wire encoding of the public key
+    blob: Vec<u8>,
+}
+
+impl HostKey {
+    fn from_blob(blob: Vec<u8>) -> Option<Self> {
+        let len = u32::from_be_bytes(blob.get(..4)?.try_into().ok()?) as usize;
+        let key_type = std::str::from_utf8(blob.get(4..4 + len)?).ok()?.to_string();
+        Some(Self { key_type, blob })
+    }
+
+    fn sha256_fingerprint(&self) -> String {
+        format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(&self.blob)))
+    }
+
+    fn md5_fingerprint(&self) -> String {
+        let digest = md5::compute(&self.blob);
+        let hex: Vec<String> = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("MD5:{}", hex.join(":"))
+    }
+
+    /// SSHFP algorithm number (RFC 4255, 6594, 7479)
+    fn sshfp_algorithm(&self) -> Option<u8> {
+        match self.key_type.as_str() {
+            "ssh-rsa" => Some(1),
+            "ssh-dss" => Some(2),
+            t if t.starts_with("ecdsa-sha2-") => Some(3),
+            "ssh-ed25519" => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Hex digest for an SSHFP fingerprint type
+    fn sshfp_digest(&self, fingerprint_type: u8) -> Option<String> {
+        let digest: Vec<u8> = match fingerprint_type {
+            1 => Sha1::digest(&self.blob).to_vec(),
+            2 => Sha256::digest(&self.blob).to_vec(),
+            _ => return None,
+        };
+        Some(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+/// A published SSHFP record
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SshfpRecord {
+    algorithm: u8,
+    fingerprint_type: u8,
+    fingerprint: String,
+}
+
+impl SshfpRecord {
+    /// Parse DoH answer data, in presentation (`4 2 abcd...`) or RFC 3597
+    /// generic (`\# 34 0402abcd...`) format
+    fn parse(data: &str) -> Option<Self> {
+        let fields: Vec<&str> = data.split_whitespace().collect();
+        if fields.first() == Some(&"\\#") {
+            let hex: String = fields.get(2..)?.concat().to_ascii_lowercase();
+            if hex.len() < 6 || !hex.is_ascii() {
+                return None;
+            }
+            return Some(Self {
+                algorithm: u8::from_str_radix(&hex[0..2], 16).ok()?,
+                fingerprint_type: u8::from_str_radix(&hex[2..4], 16).ok()?,
+                fingerprint: hex[4..].to_string(),
+            });
+        }
+
+        let [algorithm, fingerprint_type, fingerprint @ ..] = fields.as_slice() else {
+            return None;
+        };
+        Some(Self {
+            algorithm: algorithm.parse().ok()?,
+            fingerprint_type: fingerprint_type.parse().ok()?,
+            fingerprint: fingerprint.concat().to_ascii_lowercase(),
+        })
+    }
+
+    fn matches(&self, key: &HostKey) -> bool {
+        key.sshfp_algorithm() == Some(self.algorithm) &&
+            key.sshfp_digest(self.fingerprint_type).is_some_and(|d| d == self.fingerprint)
+    }
+}
+
+/// SSHFP verdict for one host key
+#[derive(Debug, PartialEq, Eq)]
+enum SshfpStatus {
+    Match,
+    Mismatch,
+    NoRecord,
+}
+
+fn sshfp_status(key: &HostKey, records: &[SshfpRecord]) -> SshfpStatus {
+    let relevant: Vec<&SshfpRecord> = records
+        .iter()
+        .filter(|r| Some(r.algorithm) == key.sshfp_algorithm())
+        .collect();
+    if relevant.is_empty() {
+        SshfpStatus::NoRecord
+    } else if relevant.iter().any(|r| r.matches(key)) {
+        SshfpStatus::Match
+    } else {
+        SshfpStatus::Mismatch
+    }
+}
+
+/// Split `host`, `host:port`, `[v6]:port` or a bare IPv6 address
+fn parse_target(target: &str) -> Option<(String, u16)> {
+    let target = target.trim();
+    if let Ok(ip) = target.parse::<IpAddr>() {
+        return Some((ip.to_string(), DEFAULT_SSH_PORT));
+    }
+
+    let (host, port) = if let Some(rest) = target.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']')?;
+        host.parse::<std::net::Ipv6Addr>().ok()?;
+        match rest.strip_prefix(':') {
+            Some(port) => (host, port.parse().ok()?),
+            None if rest.is_empty() => (host, DEFAULT_SSH_PORT),
+            None => return None,
+        }
+    } else {
+        match target.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (target, DEFAULT_SSH_PORT),
+        }
+    };
+
+    let valid_host = host.parse::<IpAddr>().is_ok() ||
+        (!host.is_empty() &&
+            host.contains('.') &&
+            host.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.'));
+    (valid_host && port != 0).then(|| (host.to_ascii_lowercase(), port))
+}
+
+/// Client handler that records the server key and ends the connection
+struct KeyCollector(Arc<Mutex<Option<Vec<u8>>>>);
+
+#[async_trait]
+impl client::Handler for KeyCollector {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, server_public_key: &key::PublicKey) -> Result<bool, Self::Error> {
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = Some(server_public_key.public_key_bytes());
+        }
+        Ok(false)
+    }
+}
+
+/// First public address of a host, `None` if it only has internal ones
+async fn public_address(host: &str, port: u16) -> Option<SocketAddr> {
+    let addresses = tokio::time::timeout(CONNECT_TIMEOUT, tokio::net::lookup_host((host, port))).await.ok()?.ok()?;
+    addresses.into_iter().find(|address| !is_internal(address.ip()))
+}
+
+/// Host key offered for one algorithm, if the server supports it
+async fn probe(address: SocketAddr, algorithm: key::Name) -> Option<HostKey> {
+    let config = client::Config {
+        preferred: russh::Preferred {
+            key: Cow::Owned(vec![algorithm]),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let captured = Arc::new(Mutex::new(None));
+    let handler = KeyCollector(captured.clone());
+
+    // Rejecting the key aborts the connection, so the result is always an error
+    let _ = tokio::time::timeout(
+        CONNECT_TIMEOUT,
+        client::connect(Arc::new(config), address, handler)
+    ).await;
+
+    let blob = captured.lock().ok()?.take()?;
+    HostKey::from_blob(blob)
+}
+
+/// Published SSHFP records and whether the answer was DNSSEC-validated
+async fn lookup_sshfp(host: &str) -> Result<(Vec<SshfpRecord>, bool)> {
+    let response = DohClient::new().query(host, "SSHFP").await?;
+    let records = response.Answer
+        .unwrap_or_default()
+        .iter()
+        .filter(|answer| answer.record_type == 44)
+        .filter_map(|answer| SshfpRecord::parse(&answer.data))
+        .collect();
+    Ok((records, response.AD))
+}
+
+/// Scan the SSH host keys of `target`
+pub async fn process_sshkeys_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let Some((host, port)) = parse_target(target) else {
+        return Ok(format!(
+            "Invalid SSH host: {}\nUse host, host:port or [IPv6]:port, e.g. github.com-SSHKEYS\n",
+            target
+        ));
+    };
+    // Like -PORT, never connect to this server's own network
+    let Some(address) = public_address(&host, port).await else {
+        return Ok(format!(
+            "SSH host key scan refused for: {}\nThe host does not resolve to a public address\n",
+            target
+        ));
+    };
+    log_debug!("[{}] Scanning SSH host keys of {}:{} ({})", ctx, host, port, address);
+
+    let probes = PROBED_ALGORITHMS.iter().map(|algorithm| probe(address, *algorithm));
+    let mut keys: Vec<HostKey> = Vec::new();
+    for key in futures::future::join_all(probes).await.into_iter().flatten() {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    let sshfp = if host.parse::<IpAddr>().is_err() {
+        match lookup_sshfp(&host).await {
+            Ok(found) => Some(found),
+            Err(e) => {
+                log_debug!("[{}] SSHFP lookup for {} failed: {}", ctx, host, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok(format_report(&host, port, &keys, sshfp.as_ref()))
+}
+
+fn format_report(host: &str, port: u16, keys: &[HostKey], sshfp: Option<&(Vec<SshfpRecord>, bool)>) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("SSH Host Keys for: {}\n", host));
+    output.push_str("=".repeat(60).as_str());
+    output.push('\n');
+    output.push_str(&format!("host: {}\n", host));
+    output.push_str(&format!("port: {}\n", port));
+
+    if keys.is_empty() {
+        output.push_str("% No SSH host keys received (connection refused, filtered or timed out)\n");
+        return output;
+    }
+    output.push_str(&format!("host-keys: {}\n", keys.len()));
+
+    let mut mismatches = 0;
+    for key in keys {
+        output.push('\n');
+        output.push_str(&format!("key-algorithm: {}\n", key.key_type));
+        output.push_str(&format!("fingerprint-sha256: {}\n", key.sha256_fingerprint()));
+        output.push_str(&format!("fingerprint-md5: {}\n", key.md5_fingerprint()));
+        if let Some((records, _)) = sshfp {
+            let status = sshfp_status(key, records);
+            if status == SshfpStatus::Mismatch {
+                mismatches += 1;
+            }
+            output.push_str(match status {
+                SshfpStatus::Match => "sshfp: match\n",
+                SshfpStatus::Mismatch => "sshfp: MISMATCH\n",
+                SshfpStatus::NoRecord => "sshfp: no record\n",
+            });
+        }
+    }
+
+    output.push('\n');
+    match sshfp {
+        Some((records, validated)) if !records.is_empty() => {
+            output.push_str(&format!("sshfp-records: {}\n", records.len()));
+            output.push_str(&format!("sshfp-dnssec: {}\n", if *validated { "validated" } else { "not validated" }));
+            let unmatched = records.iter().filter(|r| !keys.iter().any(|k| r.matches(k))).count();
+            if unmatched > 0 {
+                output.push_str(&format!("sshfp-unmatched: {}\n", unmatched));
+            }
+            if mismatches > 0 {
+                output.push_str(&format!("% WARNING: {} host key(s) do not match the published SSHFP records\n", mismatches));
+            } else {
+                output.push_str("% All host keys with SSHFP records match\n");
+            }
+        }
+        Some(_) => output.push_str("% No SSHFP records published\n"),
+        None if host.parse::<IpAddr>().is_ok() => output.push_str("% SSHFP check skipped for IP address targets\n"),
+        None => output.push_str("% SSHFP lookup failed\n"),
+    }
+    output
+}
+
+pub struct SshKeysService;
+
+#[async_trait]
+impl QueryService for SshKeysService {
+    fn name(&self) -> &'static str {
+        "sshkeys"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-SSHKEYS"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "SECURITY INVESTIGATION",
+            summary: "SSH host key fingerprints checked against SSHFP",
+            example: "github.com-SSHKEYS (or host:2222-SSHKEYS)",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "SSH key exchange with the target, SSHFP via Cloudflare DoH"
+    }
+
+    fn query_class(&self) -> &'static str {
+        "NETWORK"
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_sshkeys_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ed25519_key() -> HostKey {
+        let blob = base64::engine::general_purpose::STANDARD
+            .decode("AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl")
+            .unwrap();
+        HostKey::from_blob(blob).unwrap()
+    }
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(parse_target("github.com"), Some(("github.com".to_string(), 22)));
+        assert_eq!(parse_target("Example.com:2222"), Some(("example.com".to_string(), 2222)));
+        assert_eq!(parse_target("2001:db8::1"), Some(("2001:db8::1".to_string(), 22)));
+        assert_eq!(parse_target("[2001:db8::1]:2222"), Some(("2001:db8::1".to_string(), 2222)));
+        assert_eq!(parse_target("192.0.2.1:22"), Some(("192.0.2.1".to_string(), 22)));
+        assert_eq!(parse_target("example.com:0"), None);
+        assert_eq!(parse_target("localhost"), None);
+    }
+
+    #[test]
+    fn test_fingerprints() {
+        let key = ed25519_key();
+        assert_eq!(key.key_type, "ssh-ed25519");
+        assert_eq!(key.sha256_fingerprint(), "SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU");
+        assert_eq!(key.md5_fingerprint(), "MD5:65:96:2d:fc:e8:d5:a9:11:64:0c:0f:ea:00:6e:5b:bd");
+    }
+
+    #[test]
+    fn test_sshfp_comparison() {
+        let key = ed25519_key();
+        let sha256 = SshfpRecord::parse(
+            "4 2 F83898DF0BEF57A4EE24985BA598AC17FCCB0C0D333CC4AF1DD92BE14BC23AA5"
+        ).unwrap();
+        let sha1 = SshfpRecord::parse("\\# 22 0401e9619e2ed56c2f2a71729db80bacc2ce9ccce8d4").unwrap();
+        let wrong = SshfpRecord::parse("4 2 0000").unwrap();
+        let rsa = SshfpRecord::parse("1 2 0000").unwrap();
+
+        assert_eq!(sshfp_status(&key, &[sha256.clone(), rsa.clone()]), SshfpStatus::Match);
+        assert_eq!(sshfp_status(&key, &[sha1]), SshfpStatus::Match);
+        assert_eq!(sshfp_status(&key, &[wrong.clone()]), SshfpStatus::Mismatch);
+        assert_eq!(sshfp_status(&key, &[rsa]), SshfpStatus::NoRecord);
+
+        let report = format_report("example.com", 22, &[key], Some(&(vec![wrong], true)));
+        assert!(report.contains("sshfp: MISMATCH\n"));
+        assert!(report.contains("% WARNING: 1 host key(s) do not match"));
+    }
+
+    #[tokio::test]
+    async fn test_internal_hosts_are_refused() {
+        let ctx = RequestContext::default();
+        for target in ["127.0.0.1", "10.0.0.1:2222", "[::1]:22"] {
+            let output = process_sshkeys_query(&ctx, target).await.unwrap();
+            assert!(output.starts_with("SSH host key scan refused for:"), "{}", output);
+        }
+    }
+}
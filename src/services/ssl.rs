@@ -1,14 +1,15 @@
 use anyhow::Result;
 use chrono::DateTime;
-use rustls::{ClientConfig, ClientConnection, StreamOwned};
+use rustls::{ClientConfig, ClientConnection};
 use sha1::{Digest, Sha1};
 use sha2::Sha256;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{Read, Write};
 use std::net::{TcpStream, ToSocketAddrs};
 use std::sync::Arc;
 use std::time::Duration;
 use x509_parser::prelude::*;
 
+use crate::core::sanitize::strip_suffix_ignore_case;
 use crate::{log_debug, log_error};
 /// SSL certificate information structure
 #[derive(Debug, Clone)]
@@ -19,6 +20,8 @@ pub struct CertificateInfo {
     pub version: u32,
     pub not_before: String,
     pub not_after: String,
+    /// `not_after` as a Unix timestamp
+    pub not_after_timestamp: i64,
     pub signature_algorithm: String,
     pub public_key_algorithm: String,
     pub subject_alternative_names: Vec<String>,
@@ -30,6 +33,227 @@ pub struct CertificateInfo {
     pub is_ca: bool,
     pub is_self_signed: bool,
     pub chain_length: usize,
+    /// Negotiated TLS version, e.g. `TLSv1.3`
+    pub tls_version: Option<String>,
+    pub cipher_suite: Option<String>,
+    /// Plaintext protocol upgraded with STARTTLS before the handshake
+    pub starttls: Option<StartTls>,
+    /// Presented chain and trust path, when the whole chain was examined
+    pub chain: Option<ChainReport>,
+}
+
+/// Plaintext protocols that can be upgraded to TLS with STARTTLS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartTls {
+    Smtp,
+    Imap,
+    Pop3,
+}
+
+impl StartTls {
+    /// Protocol spoken on a mail port
+    pub fn for_port(port: u16) -> Option<Self> {
+        match port {
+            25 | 587 | 2525 => Some(StartTls::Smtp),
+            143 => Some(StartTls::Imap),
+            110 => Some(StartTls::Pop3),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StartTls::Smtp => "SMTP",
+            StartTls::Imap => "IMAP",
+            StartTls::Pop3 => "POP3",
+        }
+    }
+}
+
+/// Read one CRLF-terminated line byte by byte, so nothing of the TLS
+/// handshake that follows is consumed
+fn read_line<S: Read>(stream: &mut S) -> Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+    while line.len() < 1024 {
+        if stream.read(&mut byte)? == 0 {
+            return Err(anyhow::anyhow!("connection closed during STARTTLS negotiation"));
+        }
+        if byte[0] == b'\n' {
+            break;
+        }
+        line.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&line).trim_end().to_string())
+}
+
+/// Read a (possibly multi-line) SMTP reply, returning all of its lines
+fn read_smtp_reply<S: Read>(stream: &mut S) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    loop {
+        let line = read_line(stream)?;
+        let last = line.as_bytes().get(3) != Some(&b'-');
+        lines.push(line);
+        if last {
+            return Ok(lines);
+        }
+    }
+}
+
+fn expect_reply(reply: &str, prefix: &str, step: &str) -> Result<()> {
+    if reply.starts_with(prefix) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("STARTTLS failed at {}: {}", step, reply))
+    }
+}
+
+/// Upgrade a plaintext mail connection so the TLS handshake can start
+fn negotiate_starttls<S: Read + Write>(stream: &mut S, protocol: StartTls) -> Result<()> {
+    match protocol {
+        StartTls::Smtp => {
+            let greeting = read_smtp_reply(stream)?;
+            expect_reply(greeting.last().map_or("", String::as_str), "220", "greeting")?;
+            stream.write_all(b"EHLO whois-server\r\n")?;
+            let ehlo = read_smtp_reply(stream)?;
+            expect_reply(ehlo.last().map_or("", String::as_str), "250", "EHLO")?;
+            if !ehlo.iter().any(|line| line.get(4..).is_some_and(|ext| ext.eq_ignore_ascii_case("STARTTLS"))) {
+                return Err(anyhow::anyhow!("server does not offer STARTTLS"));
+            }
+            stream.write_all(b"STARTTLS\r\n")?;
+            let reply = read_smtp_reply(stream)?;
+            expect_reply(reply.last().map_or("", String::as_str), "220", "STARTTLS")
+        }
+        StartTls::Imap => {
+            expect_reply(&read_line(stream)?, "* OK", "greeting")?;
+            stream.write_all(b"a1 STARTTLS\r\n")?;
+            loop {
+                let line = read_line(stream)?;
+                if line.starts_with("a1 ") {
+                    return expect_reply(&line, "a1 OK", "STARTTLS");
+                }
+            }
+        }
+        StartTls::Pop3 => {
+            expect_reply(&read_line(stream)?, "+OK", "greeting")?;
+            stream.write_all(b"STLS\r\n")?;
+            expect_reply(&read_line(stream)?, "+OK", "STLS")
+        }
+    }
+}
+
+fn tls_version_name(version: rustls::ProtocolVersion) -> String {
+    match version {
+        rustls::ProtocolVersion::TLSv1_3 => "TLSv1.3".to_string(),
+        rustls::ProtocolVersion::TLSv1_2 => "TLSv1.2".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// One certificate of the chain presented by the server
+#[derive(Debug, Clone)]
+pub struct ChainCertificate {
+    pub subject: String,
+    pub issuer: String,
+    pub not_after: String,
+    pub fingerprint_sha256: String,
+    pub is_self_signed: bool,
+}
+
+/// Presented chain and the trust path built from it
+#[derive(Debug, Clone, Default)]
+pub struct ChainReport {
+    /// Certificates in the order the server sent them, leaf first
+    pub presented: Vec<ChainCertificate>,
+    /// Subjects from the leaf up to the root the path ends at
+    pub path: Vec<String>,
+    /// Whether the path ends at a root of the Mozilla root store
+    pub anchored: bool,
+    /// Issuer the path stops at because the server did not send it
+    pub missing_issuer: Option<String>,
+    /// Presented certificates for a Mozilla root that are signed by another CA
+    pub cross_signed: Vec<ChainCertificate>,
+    /// Why the chain fails verification, `None` when it verifies
+    pub verification_error: Option<String>,
+}
+
+/// Presented certificate with the raw names used to link the chain
+struct LinkedCertificate {
+    info: ChainCertificate,
+    subject_der: Vec<u8>,
+    issuer_der: Vec<u8>,
+}
+
+/// Contents of a DER-encoded value, without its tag and length
+fn der_contents(der: &[u8]) -> Option<&[u8]> {
+    let length = *der.get(1)?;
+    let header = if length < 0x80 { 2 } else { 2 + (length & 0x7f) as usize };
+    der.get(header..)
+}
+
+/// Whether a DER-encoded name is the subject of a Mozilla root
+fn is_mozilla_root(name_der: &[u8]) -> bool {
+    // Trust anchors store the name without its SEQUENCE header
+    der_contents(name_der)
+        .is_some_and(|name| webpki_roots::TLS_SERVER_ROOTS.iter().any(|root| root.subject == name))
+}
+
+/// Follow issuers from the leaf through the presented certificates
+///
+/// Returns the subjects along the path, whether it ends at a trusted root
+/// and the issuer it stops at if the server left out an intermediate. A root
+/// in `is_root` ends the path even if the server also sent a cross-signed
+/// copy of it, as browsers do.
+fn trust_path(presented: &[LinkedCertificate], is_root: impl Fn(&[u8]) -> bool) -> (Vec<String>, bool, Option<String>) {
+    let Some(leaf) = presented.first() else {
+        return (Vec::new(), false, None);
+    };
+    let mut path = vec![leaf.info.subject.clone()];
+    let mut used = vec![0];
+    let mut current = leaf;
+
+    loop {
+        if current.info.is_self_signed {
+            return (path, is_root(&current.subject_der), None);
+        }
+        if is_root(&current.issuer_der) {
+            path.push(current.info.issuer.clone());
+            return (path, true, None);
+        }
+        let next = presented
+            .iter()
+            .enumerate()
+            .find(|(index, cert)| !used.contains(index) && cert.subject_der == current.issuer_der);
+        match next {
+            Some((index, cert)) => {
+                used.push(index);
+                path.push(cert.info.subject.clone());
+                current = cert;
+            }
+            None => return (path, false, Some(current.info.issuer.clone())),
+        }
+    }
+}
+
+/// Verify the chain for `domain` against the Mozilla root store
+fn verify_chain(certs: &[rustls::Certificate], domain: &str) -> Option<String> {
+    use rustls::client::ServerCertVerifier;
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|root| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(root.subject, root.spki, root.name_constraints)
+    }));
+    let verifier = rustls::client::WebPkiVerifier::new(roots, None);
+
+    let (leaf, intermediates) = certs.split_first()?;
+    let server_name = match rustls::ServerName::try_from(domain) {
+        Ok(name) => name,
+        Err(e) => return Some(e.to_string()),
+    };
+    verifier
+        .verify_server_cert(leaf, intermediates, &server_name, &mut std::iter::empty(), &[], std::time::SystemTime::now())
+        .err()
+        .map(|e| e.to_string())
 }
 
 /// SSL service for certificate retrieval and analysis
@@ -58,11 +282,25 @@ impl SslService {
     }
 
     /// Query SSL certificate information for a domain
-    pub async fn query_ssl_certificate(&self, domain: &str, port: Option<u16>) -> Result<String> {
-        let port = port.unwrap_or(443);
+    ///
+    /// With `starttls` the port (25 by default) must be a mail port whose
+    /// protocol is upgraded with STARTTLS before the handshake.
+    pub async fn query_ssl_certificate(&self, domain: &str, port: Option<u16>, starttls: bool) -> Result<String> {
+        let port = port.unwrap_or(if starttls { 25 } else { 443 });
         log_debug!("Querying SSL certificate for {}:{}", domain, port);
 
-        match self.get_certificate_info(domain, port).await {
+        let protocol = match (starttls, StartTls::for_port(port)) {
+            (false, _) => None,
+            (true, Some(protocol)) => Some(protocol),
+            (true, None) => {
+                return Ok(format!(
+                    "STARTTLS is supported on SMTP (25, 587, 2525), IMAP (143) and POP3 (110), not port {}\n",
+                    port
+                ));
+            }
+        };
+
+        match self.get_certificate_info(domain, port, protocol).await {
             Ok(cert_info) => {
                 let output = self.format_certificate_info(&cert_info, domain, port);
                 log_debug!("SSL certificate query completed for {}", domain);
@@ -79,7 +317,7 @@ impl SslService {
     }
 
     /// Retrieve certificate information from domain
-    async fn get_certificate_info(&self, domain: &str, port: u16) -> Result<CertificateInfo> {
+    pub(crate) async fn get_certificate_info(&self, domain: &str, port: u16, starttls: Option<StartTls>) -> Result<CertificateInfo> {
         // Create SSL client configuration with custom verifier
         let config = ClientConfig::builder()
             .with_safe_defaults()
@@ -87,11 +325,11 @@ impl SslService {
             .with_no_client_auth();
 
         let server_name = rustls::ServerName::try_from(domain)?;
-        let conn = ClientConnection::new(Arc::new(config), server_name)?;
+        let mut conn = ClientConnection::new(Arc::new(config), server_name)?;
 
         // Connect to the server
         let addr = format!("{}:{}", domain, port);
-        let tcp_stream = TcpStream::connect_timeout(
+        let mut tcp_stream = TcpStream::connect_timeout(
             &addr
                 .to_socket_addrs()?
                 .next()
@@ -102,23 +340,18 @@ impl SslService {
         tcp_stream.set_read_timeout(Some(self.timeout))?;
         tcp_stream.set_write_timeout(Some(self.timeout))?;
 
-        let mut tls_stream = StreamOwned::new(conn, tcp_stream);
-
-        // Perform TLS handshake by sending a basic HTTP request
-        let request = format!(
-            "HEAD / HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
-            domain
-        );
-        tls_stream.write_all(request.as_bytes())?;
+        if let Some(protocol) = starttls {
+            negotiate_starttls(&mut tcp_stream, protocol)?;
+        }
 
-        // Read response to ensure handshake completion
-        let mut reader = BufReader::new(&mut tls_stream);
-        let mut response = String::new();
-        reader.read_line(&mut response)?;
+        // Complete the handshake; no application data is exchanged, so
+        // this works for any protocol spoken over TLS
+        while conn.is_handshaking() {
+            conn.complete_io(&mut tcp_stream)?;
+        }
 
         // Get peer certificates
-        let peer_certs = tls_stream
-            .conn
+        let peer_certs = conn
             .peer_certificates()
             .ok_or_else(|| anyhow::anyhow!("No peer certificates available"))?;
 
@@ -128,11 +361,50 @@ impl SslService {
 
         // Parse the first certificate (leaf certificate)
         let cert_der = &peer_certs[0];
-        let cert_info = self.parse_certificate(cert_der.as_ref(), peer_certs.len())?;
+        let mut cert_info = self.parse_certificate(cert_der.as_ref(), peer_certs.len())?;
+        cert_info.tls_version = conn.protocol_version().map(tls_version_name);
+        cert_info.cipher_suite = conn.negotiated_cipher_suite().map(|suite| format!("{:?}", suite.suite()));
+        cert_info.starttls = starttls;
+        cert_info.chain = Some(self.analyze_chain(peer_certs, domain)?);
 
         Ok(cert_info)
     }
 
+    /// Examine the whole presented chain and its trust path
+    fn analyze_chain(&self, certs: &[rustls::Certificate], domain: &str) -> Result<ChainReport> {
+        let mut presented = Vec::new();
+        for cert_der in certs {
+            let (_, cert) = X509Certificate::from_der(cert_der.as_ref())?;
+            presented.push(LinkedCertificate {
+                info: ChainCertificate {
+                    subject: cert.subject().to_string(),
+                    issuer: cert.issuer().to_string(),
+                    not_after: self.format_asn1_time(&cert.validity().not_after)?,
+                    fingerprint_sha256: self.generate_fingerprint(cert_der.as_ref(), "SHA256")?,
+                    is_self_signed: cert.subject() == cert.issuer(),
+                },
+                subject_der: cert.subject().as_raw().to_vec(),
+                issuer_der: cert.issuer().as_raw().to_vec(),
+            });
+        }
+
+        let (path, anchored, missing_issuer) = trust_path(&presented, is_mozilla_root);
+        let cross_signed = presented
+            .iter()
+            .filter(|cert| !cert.info.is_self_signed && is_mozilla_root(&cert.subject_der))
+            .map(|cert| cert.info.clone())
+            .collect();
+
+        Ok(ChainReport {
+            presented: presented.into_iter().map(|cert| cert.info).collect(),
+            path,
+            anchored,
+            missing_issuer,
+            cross_signed,
+            verification_error: verify_chain(certs, domain),
+        })
+    }
+
     /// Parse DER-encoded certificate
     fn parse_certificate(&self, cert_der: &[u8], chain_length: usize) -> Result<CertificateInfo> {
         let (_, cert) = X509Certificate::from_der(cert_der)?;
@@ -248,6 +520,7 @@ impl SslService {
             version,
             not_before,
             not_after,
+            not_after_timestamp: cert.validity().not_after.timestamp(),
             signature_algorithm,
             public_key_algorithm,
             subject_alternative_names: san_list,
@@ -258,6 +531,10 @@ impl SslService {
             is_ca,
             is_self_signed,
             chain_length,
+            tls_version: None,
+            cipher_suite: None,
+            starttls: None,
+            chain: None,
         })
     }
 
@@ -315,6 +592,17 @@ impl SslService {
         output.push_str("=".repeat(60).as_str());
         output.push('\n');
 
+        if let Some(version) = &cert.tls_version {
+            let via = cert.starttls.map(|p| format!(" via {} STARTTLS", p.as_str())).unwrap_or_default();
+            output.push_str(&format!("Protocol: {}{}\n", version, via));
+        }
+        if let Some(suite) = &cert.cipher_suite {
+            output.push_str(&format!("Cipher Suite: {}\n", suite));
+        }
+        if cert.tls_version.is_some() || cert.cipher_suite.is_some() {
+            output.push('\n');
+        }
+
         output.push_str(&format!("Subject: {}\n", cert.subject));
         output.push_str(&format!("Issuer: {}\n", cert.issuer));
         output.push_str(&format!("Serial Number: {}\n", cert.serial_number));
@@ -366,37 +654,89 @@ impl SslService {
         output.push_str(&format!("  SHA1: {}\n", cert.fingerprint_sha1));
         output.push_str(&format!("  SHA256: {}\n", cert.fingerprint_sha256));
 
+        if let Some(chain) = &cert.chain {
+            output.push('\n');
+            output.push_str(&Self::format_chain_report(chain));
+        }
+
+        output
+    }
+
+    /// Format the presented chain and trust path for display
+    fn format_chain_report(chain: &ChainReport) -> String {
+        let mut output = String::new();
+
+        output.push_str("Presented Chain:\n");
+        for (index, cert) in chain.presented.iter().enumerate() {
+            let role = match index {
+                0 => "leaf",
+                _ if cert.is_self_signed => "root",
+                _ => "intermediate",
+            };
+            output.push_str(&format!("  [{}] {} ({})\n", index, cert.subject, role));
+            output.push_str(&format!("      Issuer: {}\n", cert.issuer));
+            output.push_str(&format!("      Not After: {}\n", cert.not_after));
+            output.push_str(&format!("      SHA256: {}\n", cert.fingerprint_sha256));
+        }
+        output.push('\n');
+
+        output.push_str("Trust Path (Mozilla root store):\n");
+        for (index, subject) in chain.path.iter().enumerate() {
+            let arrow = if index == 0 { "" } else { "-> " };
+            let anchor = if chain.anchored && index + 1 == chain.path.len() { " (trusted root)" } else { "" };
+            output.push_str(&format!("  {}{}{}\n", arrow, subject, anchor));
+        }
+        if let Some(issuer) = &chain.missing_issuer {
+            output.push_str(&format!("  Missing Intermediate: {} was not sent by the server\n", issuer));
+        } else if !chain.anchored {
+            output.push_str("  Path ends at a root that is not in the Mozilla root store\n");
+        }
+        for cert in &chain.cross_signed {
+            output.push_str(&format!("  Cross-Signed: {} (signed by {})\n", cert.subject, cert.issuer));
+        }
+        match &chain.verification_error {
+            None => output.push_str("  Verification: OK\n"),
+            Some(error) => output.push_str(&format!("  Verification: Failed ({})\n", error)),
+        }
+
         output
     }
 
     /// Check if a query string is a valid domain for SSL lookup
     pub fn is_ssl_query(query: &str) -> bool {
-        query.to_uppercase().ends_with("-SSL")
+        Self::parse_ssl_query(query).is_some()
     }
 
-    /// Parse SSL query to extract domain and optional port
-    pub fn parse_ssl_query(query: &str) -> Option<(String, Option<u16>)> {
-        if !Self::is_ssl_query(query) {
-            return None;
-        }
-
-        let clean_query = &query[..query.len() - 4]; // Remove "-SSL"
+    /// Parse SSL query to extract domain, optional port and whether to use STARTTLS
+    ///
+    /// STARTTLS is requested with `-SSL-STARTTLS`; the dispatchers pass it
+    /// on as `host:port-STARTTLS-SSL`.
+    pub fn parse_ssl_query(query: &str) -> Option<(String, Option<u16>, bool)> {
+        let (clean_query, starttls) = if let Some(base) = strip_suffix_ignore_case(query, "-SSL-STARTTLS") {
+            (base, true)
+        } else {
+            let base = strip_suffix_ignore_case(query, "-SSL")?;
+            match strip_suffix_ignore_case(base, "-STARTTLS") {
+                Some(base) => (base, true),
+                None => (base, false),
+            }
+        };
 
         // Check for port specification
         if let Some(colon_pos) = clean_query.rfind(':') {
             let domain = clean_query[..colon_pos].to_string();
             if let Ok(port) = clean_query[colon_pos + 1..].parse::<u16>() {
-                return Some((domain, Some(port)));
+                return Some((domain, Some(port), starttls));
             }
         }
 
-        Some((clean_query.to_string(), None))
+        Some((clean_query.to_string(), None, starttls))
     }
 }
 
 /// Custom certificate verifier that accepts all certificates
 /// This is needed to analyze certificates that might be invalid/expired
-struct AcceptAllVerifier;
+pub(crate) struct AcceptAllVerifier;
 
 impl rustls::client::ServerCertVerifier for AcceptAllVerifier {
     fn verify_server_cert(
@@ -416,17 +756,17 @@ impl rustls::client::ServerCertVerifier for AcceptAllVerifier {
 pub async fn process_ssl_query(query: &str) -> Result<String> {
     let ssl_service = SslService::new();
 
-    if let Some((domain, port)) = SslService::parse_ssl_query(query) {
+    if let Some((domain, port, starttls)) = SslService::parse_ssl_query(query) {
         log_debug!(
-            "Processing SSL query for domain: {}, port: {:?}",
-            domain, port
+            "Processing SSL query for domain: {}, port: {:?}, STARTTLS: {}",
+            domain, port, starttls
         );
-        return ssl_service.query_ssl_certificate(&domain, port).await;
+        return ssl_service.query_ssl_certificate(&domain, port, starttls).await;
     }
 
     log_error!("Invalid SSL query format: {}", query);
     Ok(format!(
-        "Invalid SSL query format. Use: domain-SSL or domain:port-SSL\nQuery: {}\n",
+        "Invalid SSL query format. Use: domain-SSL, domain:port-SSL or domain:port-SSL-STARTTLS\nQuery: {}\n",
         query
     ))
 }
@@ -450,22 +790,121 @@ mod tests {
     fn test_ssl_query_parsing() {
         assert_eq!(
             SslService::parse_ssl_query("example.com-SSL"),
-            Some(("example.com".to_string(), None))
+            Some(("example.com".to_string(), None, false))
         );
 
         assert_eq!(
             SslService::parse_ssl_query("example.com:8443-SSL"),
-            Some(("example.com".to_string(), Some(8443)))
+            Some(("example.com".to_string(), Some(8443), false))
         );
 
         assert_eq!(
             SslService::parse_ssl_query("sub.domain.com:443-SSL"),
-            Some(("sub.domain.com".to_string(), Some(443)))
+            Some(("sub.domain.com".to_string(), Some(443), false))
+        );
+
+        assert_eq!(
+            SslService::parse_ssl_query("mail.example.com:587-SSL-STARTTLS"),
+            Some(("mail.example.com".to_string(), Some(587), true))
+        );
+        assert_eq!(
+            SslService::parse_ssl_query("mail.example.com:587-STARTTLS-SSL"),
+            Some(("mail.example.com".to_string(), Some(587), true))
         );
 
         assert_eq!(SslService::parse_ssl_query("example.com"), None);
     }
 
+    /// Server replies played back to the client, recording what it sends
+    struct ScriptedServer {
+        replies: std::io::Cursor<Vec<u8>>,
+        sent: Vec<u8>,
+    }
+
+    impl ScriptedServer {
+        fn new(replies: &str) -> Self {
+            Self { replies: std::io::Cursor::new(replies.as_bytes().to_vec()), sent: Vec::new() }
+        }
+    }
+
+    impl Read for ScriptedServer {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for ScriptedServer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.sent.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn linked(subject: &str, issuer: &str) -> LinkedCertificate {
+        LinkedCertificate {
+            info: ChainCertificate {
+                subject: subject.to_string(),
+                issuer: issuer.to_string(),
+                not_after: String::new(),
+                fingerprint_sha256: String::new(),
+                is_self_signed: subject == issuer,
+            },
+            subject_der: subject.as_bytes().to_vec(),
+            issuer_der: issuer.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_trust_path() {
+        let is_root = |name: &[u8]| name == b"ISRG Root X1";
+
+        // Intermediates may be sent in any order
+        let chain = [linked("example.com", "R3"), linked("ISRG Root X1", "DST Root CA X3"), linked("R3", "ISRG Root X1")];
+        let (path, anchored, missing) = trust_path(&chain, is_root);
+        assert_eq!(path, ["example.com", "R3", "ISRG Root X1"]);
+        assert!(anchored);
+        assert_eq!(missing, None);
+
+        let (path, anchored, missing) = trust_path(&[linked("example.com", "R3")], is_root);
+        assert_eq!(path, ["example.com"]);
+        assert!(!anchored);
+        assert_eq!(missing.as_deref(), Some("R3"));
+
+        let (_, anchored, missing) = trust_path(&[linked("router.local", "router.local")], is_root);
+        assert!(!anchored);
+        assert_eq!(missing, None);
+
+        assert_eq!(der_contents(&[0x30, 0x03, 1, 2, 3]), Some(&[1u8, 2, 3][..]));
+        assert_eq!(der_contents(&[0x30, 0x81, 0x02, 1, 2]), Some(&[1u8, 2][..]));
+    }
+
+    #[test]
+    fn test_starttls_negotiation() {
+        let mut smtp = ScriptedServer::new(
+            "220-mail.example.com ESMTP\r\n220 ready\r\n250-mail.example.com\r\n250-SIZE 52428800\r\n250 STARTTLS\r\n220 2.0.0 Ready to start TLS\r\n\x16\x03"
+        );
+        negotiate_starttls(&mut smtp, StartTls::Smtp).unwrap();
+        assert_eq!(smtp.sent, b"EHLO whois-server\r\nSTARTTLS\r\n");
+        // The first bytes after the reply are left for the TLS layer
+        assert_eq!(smtp.replies.position(), smtp.replies.get_ref().len() as u64 - 2);
+
+        let mut no_tls = ScriptedServer::new("220 mail ESMTP\r\n250-mail\r\n250 8BITMIME\r\n");
+        assert!(negotiate_starttls(&mut no_tls, StartTls::Smtp).unwrap_err().to_string().contains("does not offer STARTTLS"));
+
+        let mut imap = ScriptedServer::new("* OK IMAP4rev1 ready\r\n* CAPABILITY IMAP4rev1\r\na1 OK Begin TLS now\r\n");
+        negotiate_starttls(&mut imap, StartTls::Imap).unwrap();
+        assert_eq!(imap.sent, b"a1 STARTTLS\r\n");
+
+        let mut pop3 = ScriptedServer::new("+OK POP3 ready\r\n-ERR TLS not available\r\n");
+        assert!(negotiate_starttls(&mut pop3, StartTls::Pop3).is_err());
+
+        assert_eq!(StartTls::for_port(587), Some(StartTls::Smtp));
+        assert_eq!(StartTls::for_port(443), None);
+    }
+
     #[tokio::test]
     async fn test_ssl_service_creation() {
         let service = SslService::new();
@@ -18,8 +18,51 @@
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
+use crate::services::upstream::api_base;
 use crate::{log_debug, log_error, log_warn};
+
+const STEAM_STORE_API_URL: &str = "https://store.steampowered.com/api/";
+
+/// Store region used when a query does not select one
+const DEFAULT_STEAM_REGION: &str = "US";
+
+static STEAM_REGION: OnceLock<String> = OnceLock::new();
+
+/// Validate a Steam store region (ISO 3166 country code), returning it uppercased
+pub fn normalize_steam_region(region: &str) -> Option<String> {
+    (region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic())).then(|| region.to_ascii_uppercase())
+}
+
+/// Set the store region used when a query does not select one
+pub fn init_steam_region(region: &str) -> Result<()> {
+    let region = normalize_steam_region(region)
+        .ok_or_else(|| anyhow::anyhow!("Invalid Steam region '{}', expected a two-letter country code", region))?;
+    let _ = STEAM_REGION.set(region);
+    Ok(())
+}
+
+fn default_steam_region() -> &'static str {
+    STEAM_REGION.get().map(String::as_str).unwrap_or(DEFAULT_STEAM_REGION)
+}
+
+/// Split a trailing `-CC=<country>` region selector off a Steam query
+///
+/// `730-STEAM-CC=JP` becomes (`730-STEAM`, `Some("JP")`); queries without a
+/// valid selector are returned unchanged.
+pub fn split_steam_region(query: &str) -> (&str, Option<String>) {
+    if let Some((rest, selector)) = query.rsplit_once('-')
+        && selector.len() == 5
+        && selector.is_char_boundary(3)
+        && selector[..3].eq_ignore_ascii_case("CC=")
+        && let Some(region) = normalize_steam_region(&selector[3..])
+    {
+        return (rest, Some(region));
+    }
+    (query, None)
+}
 /// Steam API response structures
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SteamAppDetails {
@@ -211,6 +254,8 @@ pub struct SteamUserProfile {
 pub struct SteamService {
     client: reqwest::Client,
     api_key: Option<String>,
+    /// Store region, selecting the currency of prices
+    region: String,
 }
 
 impl Default for SteamService {
@@ -239,7 +284,15 @@ impl SteamService {
             );
         }
 
-        Self { client, api_key }
+        Self { client, api_key, region: default_steam_region().to_string() }
+    }
+
+    /// Use the store of `region` (e.g. `JP`) for prices
+    pub fn with_region(mut self, region: Option<&str>) -> Self {
+        if let Some(region) = region.and_then(normalize_steam_region) {
+            self.region = region;
+        }
+        self
     }
 
     /// Query Steam application information
@@ -247,8 +300,8 @@ impl SteamService {
         log_debug!("Querying Steam app info for ID: {}", app_id);
 
         let url = format!(
-            "https://store.steampowered.com/api/appdetails?appids={}&l=english",
-            app_id
+            "{}appdetails?appids={}&l=english&cc={}",
+            api_base(STEAM_STORE_API_URL), app_id, self.region
         );
 
         let response = self.client.get(&url).send().await?;
@@ -261,7 +314,7 @@ impl SteamService {
             ));
         }
 
-        let text = response.text().await?;
+        let text = response.limited_text().await?;
 
         // Steam API returns a nested JSON structure with app ID as key
         let parsed: Result<serde_json::Value, _> = serde_json::from_str(&text);
@@ -337,7 +390,7 @@ impl SteamService {
                 ));
             }
 
-            let user_response: Result<SteamUserResponse, _> = response.json().await;
+            let user_response: Result<SteamUserResponse, _> = response.limited_json().await;
 
             match user_response {
                 Ok(response) => {
@@ -391,8 +444,10 @@ impl SteamService {
     async fn search_games_via_store_api(&self, query: &str, limit: usize) -> Result<String> {
         // Use Steam store search endpoint
         let url = format!(
-            "https://store.steampowered.com/api/storesearch/?term={}&l=english&cc=US",
-            urlencoding::encode(query)
+            "{}storesearch/?term={}&l=english&cc={}",
+            api_base(STEAM_STORE_API_URL),
+            urlencoding::encode(query),
+            self.region
         );
 
         let response = self.client.get(&url).send().await?;
@@ -404,7 +459,7 @@ impl SteamService {
             ));
         }
 
-        let search_data: serde_json::Value = response.json().await?;
+        let search_data: serde_json::Value = response.limited_json().await?;
 
         // Parse the search results
         if let Some(items) = search_data.get("items").and_then(|v| v.as_array()) {
@@ -460,7 +515,7 @@ impl SteamService {
             ));
         }
 
-        let app_list_response: SteamAppListResponse = response.json().await?;
+        let app_list_response: SteamAppListResponse = response.limited_json().await?;
 
         // Perform fuzzy search on app names
         let query_lower = query.to_lowercase();
@@ -507,10 +562,8 @@ impl SteamService {
     /// Extract price information from search result item
     fn extract_price_info_from_search(&self, item: &serde_json::Value) -> Option<String> {
         if let Some(price_obj) = item.get("price") {
-            // Handle free games
-            if let Some(currency) = price_obj.get("currency").and_then(|v| v.as_str())
-                && currency == "USD"
-                && let Some(final_price) = price_obj.get("final").and_then(|v| v.as_u64())
+            // Handle free games (in any currency)
+            if let Some(final_price) = price_obj.get("final").and_then(|v| v.as_u64())
                 && final_price == 0
             {
                 return Some("Free".to_string());
@@ -691,6 +744,7 @@ impl SteamService {
                 output.push_str(&format!("price: {}\n", price.final_formatted));
             }
             output.push_str(&format!("currency: {}\n", price.currency));
+            output.push_str(&format!("store-region: {}\n", self.region));
         }
 
         if let Some(metacritic) = &app.metacritic {
@@ -870,28 +924,12 @@ impl SteamService {
     }
 }
 
-/// Process Steam query with -STEAM suffix
+/// Process Steam query with -STEAM suffix, optionally followed by -CC=<region>
 pub async fn process_steam_query(query: &str) -> Result<String> {
-    let steam_service = SteamService::new();
+    let (query, region) = split_steam_region(query);
 
     if let Some(steam_query) = SteamService::parse_steam_query(query) {
-        log_debug!("Processing Steam query for: {}", steam_query);
-
-        // Try to determine if this is an app ID or user ID
-        if SteamService::is_likely_app_id(&steam_query) {
-            // Try parsing as app ID first
-            if let Ok(app_id) = steam_query.parse::<u32>() {
-                log_debug!("Treating as Steam App ID: {}", app_id);
-                return steam_service.query_app_info(app_id).await;
-            }
-        }
-
-        // If not clearly an app ID, treat as user ID/username
-        log_debug!("Treating as Steam User ID: {}", steam_query);
-
-        // For custom URLs, we'd need to resolve them to Steam IDs first
-        // For now, assume it's already a Steam ID
-        steam_service.query_user_info(&steam_query).await
+        query_steam(&steam_query, region.as_deref()).await
     } else {
         log_error!("Invalid Steam query format: {}", query);
         Ok(format!(
@@ -901,21 +939,34 @@ pub async fn process_steam_query(query: &str) -> Result<String> {
     }
 }
 
-/// Process Steam search query with -STEAMSEARCH suffix
-pub async fn process_steam_search_query(query: &str) -> Result<String> {
-    let steam_service = SteamService::new();
+/// Look up a Steam app or user, with prices from the store of `region`
+pub async fn query_steam(steam_query: &str, region: Option<&str>) -> Result<String> {
+    let steam_service = SteamService::new().with_region(region);
+    log_debug!("Processing Steam query for: {} (region: {})", steam_query, steam_service.region);
+
+    // Try to determine if this is an app ID or user ID
+    if SteamService::is_likely_app_id(steam_query) {
+        // Try parsing as app ID first
+        if let Ok(app_id) = steam_query.parse::<u32>() {
+            log_debug!("Treating as Steam App ID: {}", app_id);
+            return steam_service.query_app_info(app_id).await;
+        }
+    }
 
-    if let Some(search_query) = SteamService::parse_steam_search_query(query) {
-        log_debug!("Processing Steam search query for: {}", search_query);
+    // If not clearly an app ID, treat as user ID/username
+    log_debug!("Treating as Steam User ID: {}", steam_query);
 
-        if search_query.is_empty() {
-            return Ok(
-                "Invalid Steam search query. Please provide a search term.\nExample: Counter-Strike-STEAMSEARCH\n".to_string()
-            );
-        }
+    // For custom URLs, we'd need to resolve them to Steam IDs first
+    // For now, assume it's already a Steam ID
+    steam_service.query_user_info(steam_query).await
+}
 
-        // Search for games with a limit of 10 results
-        steam_service.search_games(&search_query, 10).await
+/// Process Steam search query with -STEAMSEARCH suffix, optionally followed by -CC=<region>
+pub async fn process_steam_search_query(query: &str) -> Result<String> {
+    let (query, region) = split_steam_region(query);
+
+    if let Some(search_query) = SteamService::parse_steam_search_query(query) {
+        search_steam(&search_query, region.as_deref()).await
     } else {
         log_error!("Invalid Steam search query format: {}", query);
         Ok(format!(
@@ -925,9 +976,24 @@ pub async fn process_steam_search_query(query: &str) -> Result<String> {
     }
 }
 
+/// Search Steam games, with prices from the store of `region`
+pub async fn search_steam(search_query: &str, region: Option<&str>) -> Result<String> {
+    log_debug!("Processing Steam search query for: {}", search_query);
+
+    if search_query.is_empty() {
+        return Ok(
+            "Invalid Steam search query. Please provide a search term.\nExample: Counter-Strike-STEAMSEARCH\n".to_string()
+        );
+    }
+
+    // Search for games with a limit of 10 results
+    SteamService::new().with_region(region).search_games(search_query, 10).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::fixtures::FixtureServer;
 
     #[test]
     fn test_steam_query_detection() {
@@ -1002,6 +1068,16 @@ mod tests {
         assert!(!SteamService::is_likely_app_id("username"));
     }
 
+    #[test]
+    fn test_region_selector() {
+        assert_eq!(split_steam_region("730-STEAM-CC=JP"), ("730-STEAM", Some("JP".to_string())));
+        assert_eq!(split_steam_region("portal-STEAMSEARCH-cc=de"), ("portal-STEAMSEARCH", Some("DE".to_string())));
+        assert_eq!(split_steam_region("730-STEAM"), ("730-STEAM", None));
+        assert_eq!(split_steam_region("730-STEAM-CC=JPN"), ("730-STEAM-CC=JPN", None));
+        assert_eq!(split_steam_region("730-STEAM-CC=1A"), ("730-STEAM-CC=1A", None));
+        assert_eq!(SteamService::new().with_region(Some("gb")).region, "GB");
+    }
+
     #[tokio::test]
     async fn test_steam_service_creation() {
         let service = SteamService::new();
@@ -1009,4 +1085,18 @@ mod tests {
         // The client is properly configured with timeout during creation
         assert!(service.api_key.is_none() || service.api_key.is_some());
     }
+
+    #[tokio::test]
+    async fn test_steam_app_fixture() {
+        let mut fixtures = FixtureServer::start("steam").await;
+        let request = "appdetails?appids=1245620&l=english&cc=JP";
+        fixtures.serve(STEAM_STORE_API_URL, request, "appdetails_1245620_jp.json").await;
+
+        let response = fixtures.run(query_steam("1245620", Some("jp"))).await.unwrap();
+        assert!(response.contains("name: ELDEN RING\ntype: game\nis-free: false\n"));
+        assert!(response.contains("publishers: FromSoftware, Inc., Bandai Namco Entertainment\n"));
+        assert!(response.contains("price: ¥ 4,752 (40%↓)\noriginal-price: ¥ 7,920\ncurrency: JPY\nstore-region: JP\n"));
+        assert!(response.contains("platforms: Windows\n"));
+        assert!(response.contains("genres: Action, RPG\n"));
+    }
 }
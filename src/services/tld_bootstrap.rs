@@ -0,0 +1,156 @@
+// WHOIS Server - TLD Bootstrap
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Discovery of the authoritative server for TLDs IANA lists no WHOIS for
+//!
+//! Domain queries are referred by whois.iana.org, but many new gTLDs no
+//! longer run port 43 WHOIS and IANA lists none for them; unknown TLDs used
+//! to end up at the default (RIPE) server. This module keeps the IANA TLD
+//! list cached in LMDB, refreshed daily by [`tld_bootstrap_job`], and tells
+//! the referral path where to go instead: the `whois.nic.<tld>` server gTLD
+//! registries run, after RDAP (whose client finds the registry's service in
+//! the RFC 9224 bootstrap file itself), and an error for names under a TLD
+//! that is not delegated at all.
+
+use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::TLD_BOOTSTRAP_LMDB_PATH;
+use crate::core::body_limit::LimitedBody;
+use crate::core::scheduler::{Job, Schedule};
+use crate::services::upstream::api_base;
+use crate::storage::lmdb::LmdbStorage;
+use crate::{log_debug, log_info};
+
+const TLD_LIST_URL: &str = "https://data.iana.org/TLD/tlds-alpha-by-domain.txt";
+const BOOTSTRAP_KEY: &str = "tld_bootstrap";
+/// Download again when the cached copy is older than a day
+const MAX_AGE_SECS: u64 = 86400;
+
+/// Delegated TLDs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TldBootstrap {
+    pub fetched_at: u64,
+    /// Delegated TLDs in lower case, IDN TLDs as A-labels
+    pub tlds: BTreeSet<String>,
+}
+
+/// Where to look up a domain IANA has no WHOIS server for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TldRoute {
+    /// The TLD is not in the root zone
+    NotDelegated(String),
+    /// RDAP, then the conventional `whois.nic.<tld>` server
+    Whois(String),
+}
+
+static BOOTSTRAP: Lazy<RwLock<Option<Arc<TldBootstrap>>>> = Lazy::new(|| RwLock::new(None));
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Parse `tlds-alpha-by-domain.txt` (one TLD per line, `#` comments)
+pub fn parse_tld_list(text: &str) -> BTreeSet<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+impl TldBootstrap {
+    /// Route for a domain under one of the known TLDs
+    pub fn route(&self, domain: &str) -> Option<TldRoute> {
+        let tld = domain.trim_end_matches('.').rsplit('.').next()?.to_ascii_lowercase();
+        // TLDs start with a letter, which rules out IP addresses and prefixes
+        if !domain.contains('.') || !tld.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        if !self.tlds.contains(&tld) {
+            return Some(TldRoute::NotDelegated(tld));
+        }
+        Some(TldRoute::Whois(format!("whois.nic.{}", tld)))
+    }
+}
+
+/// Route for `domain`, `None` until the bootstrap data is loaded
+pub fn tld_route(domain: &str) -> Option<TldRoute> {
+    BOOTSTRAP.read().unwrap_or_else(|e| e.into_inner()).as_ref()?.route(domain)
+}
+
+fn install(bootstrap: TldBootstrap) {
+    *BOOTSTRAP.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(bootstrap));
+}
+
+/// Download the TLD list
+async fn download() -> Result<TldBootstrap> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent("whois-server/1.0")
+        .build()?;
+
+    let response = client.get(api_base(TLD_LIST_URL).as_ref()).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Failed to download the TLD list: HTTP {}", response.status()));
+    }
+    let tlds = parse_tld_list(&response.limited_text().await?);
+    if tlds.is_empty() {
+        return Err(anyhow!("TLD list download contained no TLDs"));
+    }
+
+    Ok(TldBootstrap { fetched_at: now_secs(), tlds })
+}
+
+/// Load the cached bootstrap data, downloading it when missing or stale
+async fn refresh_tld_bootstrap() -> Result<()> {
+    let storage = LmdbStorage::new(TLD_BOOTSTRAP_LMDB_PATH)?;
+    if let Some(cached) = storage.get_json::<TldBootstrap>(BOOTSTRAP_KEY)? {
+        let fresh = now_secs().saturating_sub(cached.fetched_at) <= MAX_AGE_SECS;
+        install(cached);
+        if fresh {
+            log_debug!("TLD bootstrap data is up to date");
+            return Ok(());
+        }
+    }
+
+    let bootstrap = download().await?;
+    log_info!("TLD bootstrap data updated ({} TLDs)", bootstrap.tlds.len());
+    storage.put_json(BOOTSTRAP_KEY, &bootstrap)?;
+    install(bootstrap);
+    Ok(())
+}
+
+/// Hourly check of the TLD list, loaded at startup
+pub fn tld_bootstrap_job() -> Job {
+    Job::new("tld-bootstrap", Schedule::Every(Duration::from_secs(3600)), refresh_tld_bootstrap)
+        .with_jitter(Duration::from_secs(300))
+        .run_at_start()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bootstrap() -> TldBootstrap {
+        let tlds = parse_tld_list("# Version 2025010100, Last Updated Wed Jan  1 07:07:01 2025 UTC\nAPP\nCOM\nXN--P1AI\nZW\n");
+        TldBootstrap { fetched_at: 0, tlds }
+    }
+
+    #[test]
+    fn test_tld_routes() {
+        let bootstrap = bootstrap();
+        assert_eq!(bootstrap.tlds.len(), 4);
+        assert_eq!(bootstrap.route("example.app"), Some(TldRoute::Whois("whois.nic.app".to_string())));
+        assert_eq!(bootstrap.route("Example.ZW."), Some(TldRoute::Whois("whois.nic.zw".to_string())));
+        assert_eq!(bootstrap.route("xn--e1afmkfd.xn--p1ai"), Some(TldRoute::Whois("whois.nic.xn--p1ai".to_string())));
+        assert_eq!(bootstrap.route("example.invalid"), Some(TldRoute::NotDelegated("invalid".to_string())));
+        assert_eq!(bootstrap.route("AS13335"), None);
+        assert_eq!(bootstrap.route("192.0.2.0/24"), None);
+    }
+}
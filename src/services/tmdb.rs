@@ -0,0 +1,544 @@
+// WHOIS Server - TMDB Client
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! The Movie Database (TMDB) client used by the IMDb service
+//!
+//! TMDB is an alternate movie-data provider: `-IMDB` and `-IMDBSEARCH` fall
+//! back to it when OMDb is not configured, unavailable or rate-limited, and
+//! OMDb results are enriched with TMDB watch-provider availability. It is
+//! enabled by `TMDB_API_KEY`; providers are reported for `TMDB_WATCH_REGION`
+//! (ISO 3166-1 code, default `US`).
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+use crate::core::body_limit::LimitedBody;
+use crate::log_debug;
+use crate::services::imdb::{ImdbRating, ImdbResponse, ImdbSearchResult};
+use crate::services::upstream::api_base;
+
+pub const TMDB_API_BASE: &str = "https://api.themoviedb.org/3";
+const TMDB_IMAGE_BASE: &str = "https://image.tmdb.org/t/p/w500";
+const DEFAULT_WATCH_REGION: &str = "US";
+
+/// Kind of TMDB title
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TmdbMediaType {
+    Movie,
+    Tv,
+}
+
+impl TmdbMediaType {
+    fn from_name(media_type: &str) -> Option<Self> {
+        match media_type {
+            "movie" => Some(Self::Movie),
+            "tv" => Some(Self::Tv),
+            _ => None,
+        }
+    }
+
+    /// Path segment of the TMDB API
+    fn path(&self) -> &'static str {
+        match self {
+            Self::Movie => "movie",
+            Self::Tv => "tv",
+        }
+    }
+
+    /// Type name as used by OMDb
+    fn omdb_type(&self) -> &'static str {
+        match self {
+            Self::Movie => "movie",
+            Self::Tv => "series",
+        }
+    }
+}
+
+/// Where a title can be watched in one region
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WatchProviders {
+    pub region: String,
+    /// Subscription, free and ad-supported streaming
+    pub stream: Vec<String>,
+    pub rent: Vec<String>,
+    pub buy: Vec<String>,
+    /// TMDB page listing the offers
+    pub link: Option<String>,
+}
+
+impl WatchProviders {
+    pub fn is_empty(&self) -> bool {
+        self.stream.is_empty() && self.rent.is_empty() && self.buy.is_empty()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FindResponse {
+    #[serde(default)]
+    movie_results: Vec<IdOnly>,
+    #[serde(default)]
+    tv_results: Vec<IdOnly>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdOnly {
+    id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    results: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    id: u64,
+    media_type: Option<String>,
+    title: Option<String>,
+    name: Option<String>,
+    release_date: Option<String>,
+    first_air_date: Option<String>,
+    poster_path: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SpokenLanguage {
+    english_name: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Credits {
+    #[serde(default)]
+    cast: Vec<Named>,
+    #[serde(default)]
+    crew: Vec<CrewMember>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CrewMember {
+    name: String,
+    job: Option<String>,
+    department: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ExternalIds {
+    imdb_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WatchProviderResults {
+    #[serde(default)]
+    results: HashMap<String, RegionOffers>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RegionOffers {
+    link: Option<String>,
+    #[serde(default)]
+    flatrate: Vec<Provider>,
+    #[serde(default)]
+    free: Vec<Provider>,
+    #[serde(default)]
+    ads: Vec<Provider>,
+    #[serde(default)]
+    rent: Vec<Provider>,
+    #[serde(default)]
+    buy: Vec<Provider>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Provider {
+    provider_name: String,
+}
+
+/// Movie or TV details with credits, external ids and watch providers
+#[derive(Debug, Default, Deserialize)]
+struct TmdbDetails {
+    title: Option<String>,
+    name: Option<String>,
+    release_date: Option<String>,
+    first_air_date: Option<String>,
+    runtime: Option<u32>,
+    #[serde(default)]
+    episode_run_time: Vec<u32>,
+    #[serde(default)]
+    genres: Vec<Named>,
+    overview: Option<String>,
+    #[serde(default)]
+    spoken_languages: Vec<SpokenLanguage>,
+    #[serde(default)]
+    production_countries: Vec<Named>,
+    #[serde(default)]
+    production_companies: Vec<Named>,
+    #[serde(default)]
+    created_by: Vec<Named>,
+    poster_path: Option<String>,
+    imdb_id: Option<String>,
+    vote_average: Option<f64>,
+    vote_count: Option<u64>,
+    homepage: Option<String>,
+    number_of_seasons: Option<u32>,
+    #[serde(default)]
+    credits: Credits,
+    #[serde(default)]
+    external_ids: ExternalIds,
+    #[serde(rename = "watch/providers", default)]
+    watch_providers: WatchProviderResults,
+}
+
+/// Non-empty names without duplicates, in order
+fn unique_names<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for name in names.filter(|n| !n.is_empty()) {
+        if !unique.iter().any(|u| u == name) {
+            unique.push(name.to_string());
+        }
+    }
+    unique
+}
+
+/// Join non-empty names, `None` when there are none
+fn join_names<'a>(names: impl Iterator<Item = &'a str>) -> Option<String> {
+    let unique = unique_names(names);
+    (!unique.is_empty()).then(|| unique.join(", "))
+}
+
+fn poster_url(path: Option<&str>) -> Option<String> {
+    path.filter(|p| !p.is_empty())
+        .map(|p| format!("{}{}", TMDB_IMAGE_BASE, p))
+}
+
+impl WatchProviderResults {
+    fn for_region(&self, region: &str) -> Option<WatchProviders> {
+        let offers = self.results.get(region)?;
+        let names = |lists: &[&Vec<Provider>]| {
+            unique_names(lists.iter().flat_map(|list| list.iter().map(|p| p.provider_name.as_str())))
+        };
+
+        let providers = WatchProviders {
+            region: region.to_string(),
+            stream: names(&[&offers.flatrate, &offers.free, &offers.ads]),
+            rent: names(&[&offers.rent]),
+            buy: names(&[&offers.buy]),
+            link: offers.link.clone(),
+        };
+        (!providers.is_empty()).then_some(providers)
+    }
+}
+
+impl TmdbDetails {
+    /// Map TMDB details onto the OMDb response shape, so the IMDb output
+    /// format stays the same whichever provider answered
+    fn into_imdb_response(self, media_type: TmdbMediaType, region: &str) -> ImdbResponse {
+        let date = self
+            .release_date
+            .clone()
+            .or_else(|| self.first_air_date.clone())
+            .filter(|d| !d.is_empty());
+        let runtime = self
+            .runtime
+            .or_else(|| self.episode_run_time.first().copied())
+            .filter(|minutes| *minutes > 0);
+        let directors = match media_type {
+            TmdbMediaType::Movie => join_names(
+                self.credits
+                    .crew
+                    .iter()
+                    .filter(|c| c.job.as_deref() == Some("Director"))
+                    .map(|c| c.name.as_str()),
+            ),
+            TmdbMediaType::Tv => join_names(self.created_by.iter().map(|c| c.name.as_str())),
+        };
+        let ratings = self
+            .vote_average
+            .filter(|_| self.vote_count.unwrap_or(0) > 0)
+            .map(|average| {
+                vec![ImdbRating {
+                    source: "TMDB".to_string(),
+                    value: format!("{:.1}/10", average),
+                }]
+            });
+
+        ImdbResponse {
+            response: "True".to_string(),
+            title: self.title.or(self.name),
+            year: date.as_ref().map(|d| d.chars().take(4).collect()),
+            released: date,
+            runtime: runtime.map(|minutes| format!("{} min", minutes)),
+            genre: join_names(self.genres.iter().map(|g| g.name.as_str())),
+            director: directors,
+            writer: join_names(
+                self.credits
+                    .crew
+                    .iter()
+                    .filter(|c| c.department.as_deref() == Some("Writing"))
+                    .map(|c| c.name.as_str()),
+            ),
+            actors: join_names(self.credits.cast.iter().take(4).map(|c| c.name.as_str())),
+            plot: self.overview.filter(|o| !o.is_empty()),
+            language: join_names(
+                self.spoken_languages
+                    .iter()
+                    .filter_map(|l| l.english_name.as_deref().or(l.name.as_deref())),
+            ),
+            country: join_names(self.production_countries.iter().map(|c| c.name.as_str())),
+            poster: poster_url(self.poster_path.as_deref()),
+            ratings,
+            imdb_id: self
+                .imdb_id
+                .or(self.external_ids.imdb_id)
+                .filter(|id| !id.is_empty()),
+            content_type: Some(media_type.omdb_type().to_string()),
+            production: join_names(self.production_companies.iter().map(|c| c.name.as_str())),
+            website: self.homepage.filter(|h| !h.is_empty()),
+            total_seasons: self.number_of_seasons.map(|n| n.to_string()),
+            watch_providers: self.watch_providers.for_region(region),
+            ..Default::default()
+        }
+    }
+}
+
+/// TMDB API client
+pub struct TmdbClient {
+    client: reqwest::Client,
+    api_key: String,
+    region: String,
+}
+
+impl TmdbClient {
+    pub fn new(client: reqwest::Client, api_key: String, region: &str) -> Self {
+        Self {
+            client,
+            api_key,
+            region: region.to_ascii_uppercase(),
+        }
+    }
+
+    /// Client configured from `TMDB_API_KEY` and `TMDB_WATCH_REGION`, `None`
+    /// when no API key is set
+    pub fn from_env(client: reqwest::Client) -> Option<Self> {
+        let api_key = std::env::var("TMDB_API_KEY")
+            .ok()
+            .filter(|k| !k.trim().is_empty())?;
+        let region = std::env::var("TMDB_WATCH_REGION")
+            .ok()
+            .filter(|r| r.len() == 2 && r.chars().all(|c| c.is_ascii_alphabetic()))
+            .unwrap_or_else(|| DEFAULT_WATCH_REGION.to_string());
+        Some(Self::new(client, api_key.trim().to_string(), &region))
+    }
+
+    /// Region watch providers are reported for
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, params: &[(&str, &str)]) -> Result<T> {
+        let url = format!("{}/{}", api_base(TMDB_API_BASE), path);
+        let response = self
+            .client
+            .get(&url)
+            .query(&[("api_key", self.api_key.as_str())])
+            .query(params)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("TMDB request failed: HTTP {}", response.status()));
+        }
+        Ok(response.limited_json().await?)
+    }
+
+    /// Resolve an IMDb id (`tt...`) to a TMDB title
+    async fn find_imdb_id(&self, imdb_id: &str) -> Result<Option<(TmdbMediaType, u64)>> {
+        let found: FindResponse = self
+            .get(
+                &format!("find/{}", urlencoding::encode(imdb_id)),
+                &[("external_source", "imdb_id")],
+            )
+            .await?;
+
+        Ok(found
+            .movie_results
+            .first()
+            .map(|m| (TmdbMediaType::Movie, m.id))
+            .or_else(|| found.tv_results.first().map(|t| (TmdbMediaType::Tv, t.id))))
+    }
+
+    /// Movies and TV shows matching a title, best match first
+    async fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
+        let found: SearchResponse = self
+            .get("search/multi", &[("query", query), ("include_adult", "false")])
+            .await?;
+
+        Ok(found
+            .results
+            .into_iter()
+            .filter(|r| {
+                r.media_type
+                    .as_deref()
+                    .and_then(TmdbMediaType::from_name)
+                    .is_some()
+            })
+            .collect())
+    }
+
+    async fn details(&self, media_type: TmdbMediaType, id: u64) -> Result<TmdbDetails> {
+        self.get(
+            &format!("{}/{}", media_type.path(), id),
+            &[("append_to_response", "credits,external_ids,watch/providers")],
+        )
+        .await
+    }
+
+    /// Look up a title by IMDb id or name, in the OMDb response shape
+    pub async fn lookup(&self, query: &str) -> Result<Option<ImdbResponse>> {
+        log_debug!("Querying TMDB for: {}", query);
+
+        let target = if query.starts_with("tt") && query.len() >= 9 {
+            self.find_imdb_id(query).await?
+        } else {
+            self.search(query).await?.into_iter().find_map(|r| {
+                r.media_type
+                    .as_deref()
+                    .and_then(TmdbMediaType::from_name)
+                    .map(|media_type| (media_type, r.id))
+            })
+        };
+
+        let Some((media_type, id)) = target else {
+            return Ok(None);
+        };
+        let details = self.details(media_type, id).await?;
+        Ok(Some(details.into_imdb_response(media_type, &self.region)))
+    }
+
+    /// Search titles, keeping only those with an IMDb id
+    pub async fn search_titles(&self, query: &str, limit: usize) -> Result<Vec<ImdbSearchResult>> {
+        log_debug!("Searching TMDB for: {}", query);
+
+        let results = self.search(query).await?;
+        let lookups = results.into_iter().take(limit).map(|result| async move {
+            let media_type = result
+                .media_type
+                .as_deref()
+                .and_then(TmdbMediaType::from_name)?;
+            let ids: ExternalIds = self
+                .get(&format!("{}/{}/external_ids", media_type.path(), result.id), &[])
+                .await
+                .ok()?;
+            let date = result.release_date.or(result.first_air_date).unwrap_or_default();
+
+            Some(ImdbSearchResult {
+                title: result.title.or(result.name).unwrap_or_default(),
+                year: date.chars().take(4).collect(),
+                imdb_id: ids.imdb_id.filter(|id| !id.is_empty())?,
+                content_type: media_type.omdb_type().to_string(),
+                poster: poster_url(result.poster_path.as_deref()).unwrap_or_else(|| "N/A".to_string()),
+            })
+        });
+
+        Ok(futures::future::join_all(lookups)
+            .await
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Watch providers of the title with an IMDb id, in the configured region
+    pub async fn watch_providers(&self, imdb_id: &str) -> Result<Option<WatchProviders>> {
+        let Some((media_type, id)) = self.find_imdb_id(imdb_id).await? else {
+            return Ok(None);
+        };
+        let providers: WatchProviderResults = self
+            .get(&format!("{}/{}/watch/providers", media_type.path(), id), &[])
+            .await?;
+        Ok(providers.for_region(&self.region))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::fixtures::FixtureServer;
+
+    fn test_client() -> TmdbClient {
+        TmdbClient::new(reqwest::Client::new(), "test".to_string(), "us")
+    }
+
+    #[test]
+    fn test_watch_providers_for_region() {
+        let results: WatchProviderResults = serde_json::from_str(
+            r#"{"results":{"US":{"link":"https://www.themoviedb.org/movie/27205/watch?locale=US",
+                "flatrate":[{"provider_name":"Netflix"}],"ads":[{"provider_name":"Tubi"}],
+                "rent":[{"provider_name":"Apple TV"}],"buy":[{"provider_name":"Apple TV"}]},
+                "DE":{"link":"https://www.themoviedb.org/movie/27205/watch?locale=DE"}}}"#,
+        )
+        .unwrap();
+
+        let us = results.for_region("US").unwrap();
+        assert_eq!(us.stream, vec!["Netflix", "Tubi"]);
+        assert_eq!(us.rent, vec!["Apple TV"]);
+        assert!(us.link.unwrap().ends_with("locale=US"));
+        assert!(results.for_region("DE").is_none());
+        assert!(results.for_region("JP").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_lookup_by_imdb_id() {
+        let mut fixtures = FixtureServer::start("tmdb").await;
+        fixtures
+            .serve(
+                TMDB_API_BASE,
+                "/find/tt1375666?api_key=test&external_source=imdb_id",
+                "find_tt1375666.json",
+            )
+            .await;
+        fixtures
+            .serve(
+                TMDB_API_BASE,
+                "/movie/27205?api_key=test&append_to_response=credits,external_ids,watch/providers",
+                "movie_27205.json",
+            )
+            .await;
+
+        let movie = fixtures
+            .run(test_client().lookup("tt1375666"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(movie.title.as_deref(), Some("Inception"));
+        assert_eq!(movie.year.as_deref(), Some("2010"));
+        assert_eq!(movie.runtime.as_deref(), Some("148 min"));
+        assert_eq!(movie.director.as_deref(), Some("Christopher Nolan"));
+        assert_eq!(movie.imdb_id.as_deref(), Some("tt1375666"));
+        assert_eq!(movie.content_type.as_deref(), Some("movie"));
+        assert!(movie.poster.unwrap().starts_with(TMDB_IMAGE_BASE));
+        assert_eq!(movie.watch_providers.unwrap().region, "US");
+    }
+
+    #[tokio::test]
+    async fn test_lookup_unavailable() {
+        let mut fixtures = FixtureServer::start("tmdb").await;
+        fixtures
+            .serve_status(
+                TMDB_API_BASE,
+                "/find/tt0000000?api_key=test&external_source=imdb_id",
+                401,
+            )
+            .await;
+
+        let result = fixtures.run(test_client().lookup("tt0000000")).await;
+        assert!(result.unwrap_err().to_string().contains("401"));
+    }
+}
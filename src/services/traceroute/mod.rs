@@ -0,0 +1,358 @@
+//! Traceroute query handler using Globalping API
+//!
+//! This module provides traceroute functionality using the Globalping API with
+//! detailed information including ASN, geolocation, PTR records, and hop-by-hop analysis.
+//!
+//! Supports location-based queries: target-location-TRACE (e.g., 1.1.1.1-us-TRACE)
+//! and vantage point selectors: target-TRACE@location (e.g., 8.8.8.8-TRACE@de).
+//! With `--native-traceroute`, `target-TRACE@local` and unselected traces run
+//! from the server itself with the native engine in [`native`]. Native traces
+//! never go to private or reserved addresses, which would reveal the
+//! server's own network.
+
+pub mod native;
+
+use anyhow::Result;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use crate::core::is_internal;
+use crate::services::utils::{GlobalpingClient, GlobalpingRequest, IpInfoClient, DohClient, TracerouteOptions, MeasurementOptions, MeasurementLocation, parse_vantage_points};
+use crate::{log_debug, log_error};
+use native::{TraceHop, TraceOptions};
+
+/// Vantage point selecting the native engine (`-TRACE@local`)
+const LOCAL_VANTAGE: &str = "local";
+
+static NATIVE_ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Allow traces from this server (`-TRACE@local`) and make them the default
+/// when no vantage point is selected
+pub fn init_native_traceroute(enabled: bool) {
+    let _ = NATIVE_ENABLED.set(enabled);
+}
+
+fn native_enabled() -> bool {
+    NATIVE_ENABLED.get().copied().unwrap_or(false)
+}
+
+/// Parse a query with optional location code
+/// Returns (target, location) where location is None if not specified
+///
+/// The suffix has already been removed by query.rs, so we just need to parse
+/// the remaining string which may be in format "target" or "target-location"
+/// Examples:
+///   "1.1.1.1" -> ("1.1.1.1", None)
+///   "1.1.1.1-TW" -> ("1.1.1.1", Some("TW"))
+///   "example.com-us" -> ("example.com", Some("us"))
+pub(crate) fn parse_location_query<'a>(query: &'a str) -> Result<(&'a str, Option<String>)> {
+    // Check if there's a location code (format: target-location)
+    // Location code is typically 2-5 characters (country codes, region codes)
+    // We need to be careful: target can be IP (1.1.1.1) or domain (example.com)
+    if let Some(last_dash_pos) = query.rfind('-') {
+        let potential_location = &query[last_dash_pos + 1..];
+        let potential_target = &query[..last_dash_pos];
+
+        // Validate: target must contain a dot (domain or IP) or be parseable as IP
+        // Location codes are short strings without dots
+        let is_valid_target = potential_target.contains('.') ||
+                             potential_target.parse::<std::net::Ipv4Addr>().is_ok() ||
+                             potential_target.parse::<std::net::Ipv6Addr>().is_ok();
+
+        if is_valid_target && potential_location.len() <= 5 && !potential_location.contains('.') {
+            return Ok((potential_target, Some(potential_location.to_string())));
+        }
+    }
+
+    // No location code found, return entire query as target
+    Ok((query, None))
+}
+
+/// Process a traceroute query with -TRACE suffix
+/// Supports optional location code: target-location-TRACE (e.g., 1.1.1.1-us-TRACE)
+/// A vantage point selector (`-TRACE@de,us`) takes precedence over the location code
+pub async fn process_traceroute_query(query: &str, vantage: Option<&str>) -> Result<String> {
+    log_debug!("Processing traceroute query: {} (vantage: {:?})", query, vantage);
+
+    // Parse target and location
+    // The suffix has already been removed by query.rs
+    // Format: target-location or target
+    let (target, location) = parse_location_query(query)?;
+
+    if vantage.is_some_and(|v| v.eq_ignore_ascii_case(LOCAL_VANTAGE)) && !native_enabled() {
+        return Ok("% Traceroute from this server is not enabled (--native-traceroute)\n".to_string());
+    }
+    let local = vantage.is_some_and(|v| v.eq_ignore_ascii_case(LOCAL_VANTAGE)) ||
+        (vantage.is_none() && location.is_none() && native_enabled());
+    if local {
+        return process_native_traceroute(target).await;
+    }
+
+    log_debug!("Starting traceroute to {} (location: {:?})", target, location);
+
+    // Initialize clients
+    let globalping = match GlobalpingClient::new() {
+        Ok(client) => client,
+        Err(e) => {
+            log_error!("Failed to initialize Globalping client: {}", e);
+            return Ok(format!("Traceroute service error: {}\n", e));
+        }
+    };
+
+    let ip_info_client = IpInfoClient::new(); // May fail if token not set
+    let doh_client = DohClient::new();
+
+    // Submit traceroute measurement to Globalping
+    let measurement_opts: MeasurementOptions = MeasurementOptions::Traceroute(TracerouteOptions {
+        protocol: Some("ICMP".to_string()),
+        port: None,
+    });
+
+    log_debug!("Parsed target: '{}', location: {:?}", target, location);
+
+    let mut request: GlobalpingRequest = GlobalpingRequest {
+        measurement_type: "traceroute".to_string(),
+        target: target.to_string(),
+        limit: Some(1), // Use 1 probe
+        measurement_options: Some(measurement_opts),
+        locations: None,
+        in_progress_updates: Some(false),
+    };
+
+    // Add location if specified
+    if let Some(locations) = vantage.and_then(parse_vantage_points) {
+        // One probe per selected vantage point
+        request.limit = Some(locations.len() as u32);
+        request.locations = Some(locations);
+    } else if let Some(loc) = location {
+        request.locations = Some(vec![MeasurementLocation::magic(&loc)]);
+    }
+
+    let measurement_id = match globalping.submit_measurement(&request).await {
+        Ok(id) => id,
+        Err(e) => {
+            log_error!("Failed to submit traceroute measurement: {}", e);
+            return Ok(format!("Traceroute failed: {}\n", e));
+        }
+    };
+
+    log_debug!("Traceroute measurement ID: {}", measurement_id);
+
+    // Wait for results (60 second timeout for traceroute)
+    let results = match globalping.wait_for_results(&measurement_id, 60).await {
+        Ok(results) => results,
+        Err(e) => {
+            log_error!("Failed to get traceroute results: {}", e);
+            return Ok(format!("Traceroute measurement timed out or failed: {}\n", e));
+        }
+    };
+
+    // Format and return output
+    format_traceroute_output(&results, &ip_info_client, &doh_client, target).await
+}
+
+/// Format traceroute results with detailed hop information
+async fn format_traceroute_output(
+    results: &crate::services::utils::GlobalpingResult,
+    ip_info_client: &Result<IpInfoClient>,
+    doh_client: &DohClient,
+    target: &str,
+) -> Result<String> {
+    let mut output = String::new();
+
+    if results.results.is_empty() {
+        output.push_str(&format!("No results received for traceroute to {}\n", target));
+        return Ok(output);
+    }
+
+    // Process results from each probe
+    for probe_result in &results.results {
+        let test_result = &probe_result.result;
+        let probe_info = &probe_result.probe;
+
+        // Get resolved address
+        let target_ip = test_result.resolved_address.as_deref().unwrap_or(target);
+
+        // Header line
+        output.push_str(&format!(
+            "traceroute to {}, 30 hops max, 52 bytes payload, ICMP mode\n",
+            target_ip
+        ));
+
+        output.push_str(&format!("Probe: {} - {}, {}\n\n",
+            probe_info.network,
+            probe_info.city.as_deref().unwrap_or("Unknown"),
+            probe_info.country
+        ));
+
+        // Process hops
+        // Globalping API returns hops with resolvedAddress, resolvedHostname, and timings
+        if let Some(hops) = &test_result.hops {
+            for (hop_num, hop) in hops.iter().enumerate() {
+                let rtts: Vec<f64> = hop.timings.iter().flatten().map(|t| t.rtt).collect();
+                format_hop(&mut output, hop_num + 1, hop.resolved_address.as_deref(), &rtts, ip_info_client, doh_client).await;
+            }
+        } else {
+            output.push_str("No hops data available in traceroute results\n");
+        }
+
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+/// Format one hop: address with ASN and location, PTR record and RTTs
+async fn format_hop(
+    output: &mut String,
+    hop_num: usize,
+    address: Option<&str>,
+    rtts: &[f64],
+    ip_info_client: &Result<IpInfoClient>,
+    doh_client: &DohClient,
+) {
+    // Check if hop has resolved address
+    let Some(resolved_address) = address else {
+        // Hop timed out - no IP response
+        output.push_str(&format!("{:3}   *\n", hop_num));
+        return;
+    };
+
+    // Get IP info and PTR records
+    let ip_info = if let Ok(client) = ip_info_client {
+        client.get_ip_info(resolved_address).await.ok()
+    } else {
+        None
+    };
+
+    let ptr_records = doh_client.query_ptr(resolved_address).await.ok();
+
+    // Format hop information - first line with IP
+    output.push_str(&format!("{:3}   {:15}", hop_num, resolved_address));
+
+    // ASN and location info on same line
+    if let Some(info) = &ip_info {
+        output.push_str(&format!(
+            "   {:15}  {:20}  {:6}  {:10}  {}\n",
+            info.asn, info.as_name, info.country_code,
+            info.continent_code, info.as_domain
+        ));
+    } else {
+        // No IP info available
+        output.push_str("   *             *                      *           *\n");
+    }
+
+    // PTR records on next line (indented)
+    if let Some(ptrs) = &ptr_records {
+        if !ptrs.is_empty() {
+            // Take first PTR record
+            output.push_str(&format!("      {:15}\n", ptrs[0]));
+        }
+    }
+
+    // RTT times on next line (indented)
+    if rtts.is_empty() {
+        output.push_str("                                                *\n");
+    } else {
+        let times: Vec<String> = rtts.iter()
+            .map(|rtt| format!("{:.2} ms", rtt))
+            .collect();
+        output.push_str(&format!(
+            "                                                {}\n",
+            times.join(" / ")
+        ));
+    }
+}
+
+/// Resolve a traceroute target to an address, preferring the literal form
+async fn resolve_target(target: &str) -> Result<IpAddr> {
+    if let Ok(ip) = target.parse::<IpAddr>() {
+        return Ok(ip);
+    }
+    tokio::net::lookup_host((target, 0))
+        .await?
+        .next()
+        .map(|addr| addr.ip())
+        .ok_or_else(|| anyhow::anyhow!("{} does not resolve to an address", target))
+}
+
+/// Trace from this server with the native engine
+async fn process_native_traceroute(target: &str) -> Result<String> {
+    let address = match resolve_target(target).await {
+        Ok(address) => address,
+        Err(e) => return Ok(format!("Traceroute failed: {}\n", e)),
+    };
+    if is_internal(address) {
+        return Ok(format!("% Traceroute to {} refused: private or reserved address\n", address));
+    }
+
+    let options = TraceOptions::default();
+    let trace = match native::trace(address, &options).await {
+        Ok(trace) => trace,
+        Err(e) => {
+            log_error!("Native traceroute to {} failed: {}", address, e);
+            return Ok(format!("Traceroute failed: {}\n", e));
+        }
+    };
+
+    Ok(format_native_trace(&trace, &options, &IpInfoClient::new(), &DohClient::new()).await)
+}
+
+/// Format a native trace like the Globalping output
+async fn format_native_trace(
+    trace: &native::Trace,
+    options: &TraceOptions,
+    ip_info_client: &Result<IpInfoClient>,
+    doh_client: &DohClient,
+) -> String {
+    let mut output = format!(
+        "traceroute to {}, {} hops max, {} mode\n",
+        trace.target, options.max_hops, trace.mode.as_str()
+    );
+    output.push_str("Probe: this server (native)\n\n");
+
+    for TraceHop { ttl, address, rtts } in &trace.hops {
+        let address = address.map(|ip| ip.to_string());
+        format_hop(&mut output, *ttl as usize, address.as_deref(), rtts, ip_info_client, doh_client).await;
+    }
+    if !trace.reached {
+        output.push_str(&format!("% {} not reached within {} hops\n", trace.target, options.max_hops));
+    }
+    output.push('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore] // Requires network and API tokens
+    async fn test_traceroute_query_formatting() {
+        // This test requires actual API calls
+        let result = process_traceroute_query("1.1.1.1", None).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires network and API tokens
+    async fn test_traceroute_long_form() {
+        // Test vantage point selection
+        let result = process_traceroute_query("1.1.1.1", Some("de")).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_local_trace_needs_opt_in() {
+        // --native-traceroute is not set in tests
+        let output = process_traceroute_query("1.1.1.1", Some("local")).await.unwrap();
+        assert!(output.contains("not enabled"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn test_native_trace_refuses_internal_targets() {
+        for target in ["127.0.0.1", "10.0.0.1", "fe80::1", "169.254.169.254"] {
+            let output = process_native_traceroute(target).await.unwrap();
+            assert!(output.contains("refused"), "{}", output);
+        }
+    }
+}
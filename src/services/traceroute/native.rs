@@ -0,0 +1,527 @@
+//! Native traceroute from the server itself
+//!
+//! Probes for all TTLs are sent at once instead of one hop after another, so
+//! a trace takes about as long as its slowest hop rather than the sum of all
+//! hops, and no `traceroute` binary is needed. The probe type depends on what
+//! the process is allowed to do:
+//! - ICMP echo over a raw socket, with `CAP_NET_RAW` (root, or the default
+//!   capabilities of most container runtimes)
+//! - Otherwise on Linux, UDP datagrams to ports 33434 and up, reading the ICMP
+//!   errors they cause from the socket error queue (`IP_RECVERR`), which needs
+//!   no privileges
+//!
+//! [`trace`] only returns the hops, so callers can annotate them (ASN, PTR)
+//! or repeat traces for per-hop statistics.
+
+use anyhow::{ Result, anyhow };
+use std::collections::{ BTreeMap, HashMap };
+use std::net::{ IpAddr, SocketAddr };
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+
+use crate::log_debug;
+
+/// First destination port of UDP probes, as used by classic traceroute
+const BASE_PORT: u16 = 33434;
+/// Pause between probe rounds, which keeps routers from rate limiting replies
+const ROUND_GAP: Duration = Duration::from_millis(20);
+const ECHO_PAYLOAD: &[u8] = b"whois-server traceroute";
+
+/// How probes are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeMode {
+    Icmp,
+    Udp,
+}
+
+impl ProbeMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProbeMode::Icmp => "ICMP",
+            ProbeMode::Udp => "UDP",
+        }
+    }
+}
+
+/// Limits of a trace
+#[derive(Debug, Clone)]
+pub struct TraceOptions {
+    pub max_hops: u8,
+    /// Probes per hop
+    pub queries: u8,
+    /// Time to wait for answers after the first probe
+    pub timeout: Duration,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        Self { max_hops: 30, queries: 3, timeout: Duration::from_secs(3) }
+    }
+}
+
+/// One hop of a trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceHop {
+    pub ttl: u8,
+    /// First address that answered, None if the hop stayed silent
+    pub address: Option<IpAddr>,
+    /// Round-trip times in milliseconds of the answered probes
+    pub rtts: Vec<f64>,
+}
+
+/// Result of a trace
+#[derive(Debug)]
+pub struct Trace {
+    pub target: IpAddr,
+    pub mode: ProbeMode,
+    pub hops: Vec<TraceHop>,
+    pub reached: bool,
+}
+
+/// Trace the route to `target` from this server
+pub async fn trace(target: IpAddr, options: &TraceOptions) -> Result<Trace> {
+    if options.max_hops == 0 || options.queries == 0 {
+        return Err(anyhow!("max_hops and queries must be at least 1"));
+    }
+
+    let (mode, collector) = match icmp_socket(target) {
+        Ok(socket) => {
+            log_debug!("Native traceroute to {} using ICMP", target);
+            (ProbeMode::Icmp, trace_icmp(socket, target, options).await?)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            log_debug!("No raw socket access ({}), native traceroute to {} using UDP", e, target);
+            (ProbeMode::Udp, trace_udp(target, options).await?)
+        }
+        Err(e) => return Err(anyhow!("Failed to open ICMP socket: {}", e)),
+    };
+
+    let reached = collector.reached_at.is_some();
+    Ok(Trace { target, mode, hops: collector.into_hops(), reached })
+}
+
+fn probe_seq(ttl: u8, attempt: u8) -> u16 {
+    ((attempt as u16) << 8) | (ttl as u16)
+}
+
+fn split_seq(seq: u16) -> (u8, u8) {
+    ((seq & 0xff) as u8, (seq >> 8) as u8)
+}
+
+/// Send times and answers of the probes of one trace
+#[derive(Default)]
+struct Collector {
+    sent: HashMap<(u8, u8), Instant>,
+    hops: BTreeMap<u8, (Option<IpAddr>, Vec<f64>)>,
+    /// Lowest TTL answered by the target itself
+    reached_at: Option<u8>,
+}
+
+impl Collector {
+    fn sent(&mut self, ttl: u8, attempt: u8) {
+        self.sent.insert((ttl, attempt), Instant::now());
+    }
+
+    fn record(&mut self, ttl: u8, attempt: u8, from: IpAddr, reached: bool, at: Instant) {
+        let Some(sent) = self.sent.remove(&(ttl, attempt)) else {
+            return;
+        };
+        let hop = self.hops.entry(ttl).or_default();
+        hop.0.get_or_insert(from);
+        hop.1.push(at.duration_since(sent).as_secs_f64() * 1000.0);
+        if reached {
+            self.reached_at = Some(self.reached_at.map_or(ttl, |last| last.min(ttl)));
+        }
+    }
+
+    /// Whether every hop up to the target has answered every probe
+    fn complete(&self, queries: u8) -> bool {
+        self.reached_at.is_some_and(|last| {
+            (1..=last).all(|ttl| self.hops.get(&ttl).is_some_and(|hop| hop.1.len() >= queries as usize))
+        })
+    }
+
+    /// Hops up to the target, or up to the last answering hop if it was not reached
+    fn into_hops(mut self) -> Vec<TraceHop> {
+        let last = self.reached_at.or_else(|| self.hops.keys().next_back().copied()).unwrap_or(0);
+        (1..=last)
+            .map(|ttl| {
+                let (address, rtts) = self.hops.remove(&ttl).unwrap_or_default();
+                TraceHop { ttl, address, rtts }
+            })
+            .collect()
+    }
+}
+
+fn set_hop_limit(socket: socket2::SockRef<'_>, target: IpAddr, ttl: u8) -> std::io::Result<()> {
+    match target {
+        IpAddr::V4(_) => socket.set_ttl(ttl as u32),
+        IpAddr::V6(_) => socket.set_unicast_hops_v6(ttl as u32),
+    }
+}
+
+fn icmp_socket(target: IpAddr) -> std::io::Result<UdpSocket> {
+    use socket2::{ Domain, Protocol, Socket, Type };
+
+    let socket = match target {
+        IpAddr::V4(_) => Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4))?,
+        IpAddr::V6(_) => Socket::new(Domain::IPV6, Type::RAW, Some(Protocol::ICMPV6))?,
+    };
+    socket.set_nonblocking(true)?;
+    // Raw sockets use recvfrom/sendto like datagram sockets
+    UdpSocket::from_std(std::net::UdpSocket::from(socket))
+}
+
+/// ICMP echo request; the kernel fills in the checksum for ICMPv6
+fn echo_request(target: IpAddr, id: u16, seq: u16) -> Vec<u8> {
+    let kind = if target.is_ipv6() { 128 } else { 8 };
+    let mut packet = vec![kind, 0, 0, 0];
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(ECHO_PAYLOAD);
+    if target.is_ipv4() {
+        let checksum = internet_checksum(&packet);
+        packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    }
+    packet
+}
+
+fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Sequence number of one of our echo requests answered by an ICMP message
+///
+/// IPv4 raw sockets deliver the IP header, IPv6 raw sockets only the ICMPv6
+/// message. Echo replies carry the sequence number directly; Time Exceeded
+/// and Destination Unreachable quote the header of the request that caused them.
+fn parse_icmp_reply(packet: &[u8], ipv6: bool, id: u16) -> Option<u16> {
+    let (icmp, reply_type, error_types, request_type): (&[u8], u8, [u8; 2], u8) = if ipv6 {
+        (packet, 129, [3, 1], 128)
+    } else {
+        let header_len = ((packet.first()? & 0x0f) as usize) * 4;
+        (packet.get(header_len..)?, 0, [11, 3], 8)
+    };
+
+    let kind = *icmp.first()?;
+    let echo = if kind == reply_type {
+        icmp
+    } else if error_types.contains(&kind) {
+        let quoted = icmp.get(8..)?;
+        let quoted_header_len = if ipv6 { 40 } else { ((quoted.first()? & 0x0f) as usize) * 4 };
+        let echo = quoted.get(quoted_header_len..)?;
+        if *echo.first()? != request_type {
+            return None;
+        }
+        echo
+    } else {
+        return None;
+    };
+
+    let fields = echo.get(4..8)?;
+    (u16::from_be_bytes([fields[0], fields[1]]) == id).then(|| u16::from_be_bytes([fields[2], fields[3]]))
+}
+
+async fn trace_icmp(socket: UdpSocket, target: IpAddr, options: &TraceOptions) -> Result<Collector> {
+    let id: u16 = rand::random();
+    let collector = Mutex::new(Collector::default());
+    let deadline = Instant::now() + options.timeout;
+    let destination = SocketAddr::new(target, 0);
+
+    let send = async {
+        for attempt in 0..options.queries {
+            if attempt > 0 {
+                tokio::time::sleep(ROUND_GAP).await;
+            }
+            for ttl in 1..=options.max_hops {
+                set_hop_limit(socket2::SockRef::from(&socket), target, ttl)?;
+                socket.send_to(&echo_request(target, id, probe_seq(ttl, attempt)), destination).await?;
+                collector.lock().unwrap().sent(ttl, attempt);
+            }
+        }
+        Ok::<(), anyhow::Error>(())
+    };
+
+    let receive = async {
+        let mut buffer = [0u8; 1500];
+        while !collector.lock().unwrap().complete(options.queries) {
+            let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await else {
+                break;
+            };
+            let (len, from) = received?;
+            let at = Instant::now();
+            if let Some(seq) = parse_icmp_reply(&buffer[..len], target.is_ipv6(), id) {
+                let (ttl, attempt) = split_seq(seq);
+                collector.lock().unwrap().record(ttl, attempt, from.ip(), from.ip() == target, at);
+            }
+        }
+        Ok::<(), anyhow::Error>(())
+    };
+
+    let (sent, received) = tokio::join!(send, receive);
+    sent?;
+    received?;
+    Ok(collector.into_inner().unwrap())
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn trace_udp(_target: IpAddr, _options: &TraceOptions) -> Result<Collector> {
+    Err(anyhow!("Native traceroute needs raw socket access (CAP_NET_RAW) on this platform"))
+}
+
+#[cfg(target_os = "linux")]
+async fn trace_udp(target: IpAddr, options: &TraceOptions) -> Result<Collector> {
+    use futures::stream::{ FuturesUnordered, StreamExt };
+    use tokio::io::Interest;
+    use tokio::io::unix::AsyncFd;
+
+    let collector = Mutex::new(Collector::default());
+    let deadline = Instant::now() + options.timeout;
+
+    // One socket per TTL, since queued errors do not say which TTL they answer
+    let mut sockets = Vec::with_capacity(options.max_hops as usize);
+    for ttl in 1..=options.max_hops {
+        let bind: SocketAddr = match target {
+            IpAddr::V4(_) => "0.0.0.0:0".parse()?,
+            IpAddr::V6(_) => "[::]:0".parse()?,
+        };
+        let socket = std::net::UdpSocket::bind(bind)?;
+        set_hop_limit(socket2::SockRef::from(&socket), target, ttl)?;
+        errqueue::enable(&socket, target.is_ipv6())?;
+        socket.set_nonblocking(true)?;
+        sockets.push((ttl, AsyncFd::with_interest(socket, Interest::ERROR)?));
+    }
+
+    let send = async {
+        for attempt in 0..options.queries {
+            if attempt > 0 {
+                tokio::time::sleep(ROUND_GAP).await;
+            }
+            for (ttl, socket) in &sockets {
+                let destination = SocketAddr::new(target, BASE_PORT + (*ttl as u16) - 1);
+                // The first payload byte tells the probes of one TTL apart
+                socket.get_ref().send_to(&[attempt; 32], destination)?;
+                collector.lock().unwrap().sent(*ttl, attempt);
+            }
+        }
+        Ok::<(), anyhow::Error>(())
+    };
+
+    let receive = async {
+        let mut pending: FuturesUnordered<_> = sockets
+            .iter()
+            .map(|(ttl, socket)| errqueue::next_errors(*ttl, socket))
+            .collect();
+        while !collector.lock().unwrap().complete(options.queries) {
+            let Ok(Some(next)) = tokio::time::timeout_at(deadline, pending.next()).await else {
+                break;
+            };
+            let (ttl, errors) = next?;
+            for (error, at) in errors {
+                if let Some(from) = error.offender {
+                    collector.lock().unwrap().record(ttl, error.attempt, from, from == target, at);
+                }
+            }
+            if let Some((_, socket)) = sockets.iter().find(|(t, _)| *t == ttl) {
+                pending.push(errqueue::next_errors(ttl, socket));
+            }
+        }
+        Ok::<(), anyhow::Error>(())
+    };
+
+    let (sent, received) = tokio::join!(send, receive);
+    sent?;
+    received?;
+    Ok(collector.into_inner().unwrap())
+}
+
+/// ICMP errors queued on UDP sockets (Linux `IP_RECVERR`)
+#[cfg(target_os = "linux")]
+mod errqueue {
+    use std::io;
+    use std::net::{ IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket };
+    use std::os::fd::AsRawFd;
+    use tokio::io::Interest;
+    use tokio::io::unix::AsyncFd;
+    use tokio::time::Instant;
+
+    /// A queued ICMP error
+    pub(super) struct QueuedError {
+        /// Router or host that sent the ICMP message
+        pub(super) offender: Option<IpAddr>,
+        /// Probe round, from the first byte of the returned payload
+        pub(super) attempt: u8,
+    }
+
+    /// Queue ICMP errors caused by a socket's datagrams
+    pub(super) fn enable(socket: &UdpSocket, ipv6: bool) -> io::Result<()> {
+        let (level, name) = if ipv6 {
+            (libc::SOL_IPV6, libc::IPV6_RECVERR)
+        } else {
+            (libc::SOL_IP, libc::IP_RECVERR)
+        };
+        let on: libc::c_int = 1;
+        // SAFETY: the option value points to a c_int of the given size
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                level,
+                name,
+                (&on as *const libc::c_int).cast(),
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t
+            )
+        };
+        if result == 0 { Ok(()) } else { Err(io::Error::last_os_error()) }
+    }
+
+    /// Wait for errors on a socket and take all queued ones
+    pub(super) async fn next_errors(
+        ttl: u8,
+        socket: &AsyncFd<UdpSocket>
+    ) -> io::Result<(u8, Vec<(QueuedError, Instant)>)> {
+        loop {
+            let mut guard = socket.ready(Interest::ERROR).await?;
+            let mut errors = Vec::new();
+            while let Ok(result) = guard.try_io(|socket| recv_error(socket.get_ref())) {
+                errors.push((result?, Instant::now()));
+            }
+            if !errors.is_empty() {
+                return Ok((ttl, errors));
+            }
+        }
+    }
+
+    fn recv_error(socket: &UdpSocket) -> io::Result<QueuedError> {
+        let mut payload = [0u8; 64];
+        // u64 elements keep the control buffer aligned for cmsghdr
+        let mut control = [0u64; 64];
+        let mut iov = libc::iovec { iov_base: payload.as_mut_ptr().cast(), iov_len: payload.len() };
+
+        // SAFETY: msghdr is plain data; all pointers outlive the recvmsg call
+        let mut message: libc::msghdr = unsafe { std::mem::zeroed() };
+        message.msg_iov = &mut iov;
+        message.msg_iovlen = 1;
+        message.msg_control = control.as_mut_ptr().cast();
+        message.msg_controllen = std::mem::size_of_val(&control) as _;
+
+        // SAFETY: message describes valid buffers
+        let len = unsafe {
+            libc::recvmsg(socket.as_raw_fd(), &mut message, libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT)
+        };
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut offender = None;
+        // SAFETY: the control messages were written by the kernel into message
+        unsafe {
+            let mut header = libc::CMSG_FIRSTHDR(&message);
+            while !header.is_null() {
+                let (level, kind) = ((*header).cmsg_level, (*header).cmsg_type);
+                if (level == libc::SOL_IP && kind == libc::IP_RECVERR) ||
+                    (level == libc::SOL_IPV6 && kind == libc::IPV6_RECVERR)
+                {
+                    let error = libc::CMSG_DATA(header) as *const libc::sock_extended_err;
+                    let origin = std::ptr::read_unaligned(error).ee_origin;
+                    if origin == libc::SO_EE_ORIGIN_ICMP || origin == libc::SO_EE_ORIGIN_ICMP6 {
+                        offender = sockaddr_ip(libc::SO_EE_OFFENDER(error));
+                    }
+                }
+                header = libc::CMSG_NXTHDR(&message, header);
+            }
+        }
+
+        Ok(QueuedError { offender, attempt: payload[0] })
+    }
+
+    /// Address of a sockaddr written by the kernel
+    ///
+    /// # Safety
+    /// `address` must point to a sockaddr_in or sockaddr_in6 as its family says.
+    unsafe fn sockaddr_ip(address: *const libc::sockaddr) -> Option<IpAddr> {
+        // SAFETY: guaranteed by the caller
+        unsafe {
+            match std::ptr::read_unaligned(address).sa_family as libc::c_int {
+                libc::AF_INET => {
+                    let v4 = std::ptr::read_unaligned(address as *const libc::sockaddr_in);
+                    Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(v4.sin_addr.s_addr))))
+                }
+                libc::AF_INET6 => {
+                    let v6 = std::ptr::read_unaligned(address as *const libc::sockaddr_in6);
+                    Some(IpAddr::V6(Ipv6Addr::from(v6.sin6_addr.s6_addr)))
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_icmp_reply() {
+        let target: IpAddr = "192.0.2.1".parse().unwrap();
+        let request = echo_request(target, 0x1234, probe_seq(7, 2));
+        assert_eq!(internet_checksum(&request), 0);
+
+        // IPv4 echo reply: 20 byte IP header, then the echoed request as type 0
+        let mut reply = vec![0x45; 1];
+        reply.extend_from_slice(&[0; 19]);
+        reply.extend_from_slice(&request);
+        reply[20] = 0;
+        assert_eq!(parse_icmp_reply(&reply, false, 0x1234).map(split_seq), Some((7, 2)));
+        assert_eq!(parse_icmp_reply(&reply, false, 0x4321), None);
+
+        // IPv4 time exceeded quoting the request's IP header and first 8 bytes
+        let mut exceeded = vec![0x45];
+        exceeded.extend_from_slice(&[0; 19]);
+        exceeded.extend_from_slice(&[11, 0, 0, 0, 0, 0, 0, 0, 0x45]);
+        exceeded.extend_from_slice(&[0; 19]);
+        exceeded.extend_from_slice(&request[..8]);
+        assert_eq!(parse_icmp_reply(&exceeded, false, 0x1234).map(split_seq), Some((7, 2)));
+
+        // ICMPv6 time exceeded: no outer header, 40 byte quoted IPv6 header
+        let target6: IpAddr = "2001:db8::1".parse().unwrap();
+        let request6 = echo_request(target6, 0x1234, probe_seq(3, 0));
+        let mut exceeded6 = vec![3, 0, 0, 0, 0, 0, 0, 0];
+        exceeded6.extend_from_slice(&[0; 40]);
+        exceeded6.extend_from_slice(&request6[..8]);
+        assert_eq!(parse_icmp_reply(&exceeded6, true, 0x1234).map(split_seq), Some((3, 0)));
+        assert_eq!(parse_icmp_reply(&[128, 0, 0, 0], true, 0x1234), None);
+    }
+
+    #[test]
+    fn test_collector_hops() {
+        let target: IpAddr = "192.0.2.1".parse().unwrap();
+        let router: IpAddr = "198.51.100.1".parse().unwrap();
+        let mut collector = Collector::default();
+        for ttl in 1..=5 {
+            collector.sent(ttl, 0);
+        }
+        let at = Instant::now();
+        collector.record(1, 0, router, false, at);
+        collector.record(3, 0, target, true, at);
+        collector.record(4, 0, target, true, at);
+        // Answers to unknown or already answered probes are ignored
+        collector.record(1, 0, target, true, at);
+        collector.record(9, 0, target, true, at);
+
+        assert!(!collector.complete(1));
+        let hops = collector.into_hops();
+        assert_eq!(hops.len(), 3);
+        assert_eq!(hops[0].address, Some(router));
+        assert_eq!(hops[1], TraceHop { ttl: 2, address: None, rtts: Vec::new() });
+        assert_eq!(hops[2].address, Some(target));
+    }
+}
@@ -0,0 +1,168 @@
+// WHOIS Server - Response Translation
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Translation of non-Latin response lines (`-TRANSLATE` modifier)
+//!
+//! Registries such as JPNIC, KRNIC and CNNIC answer in their local scripts.
+//! With `-TRANSLATE` every line containing non-Latin letters is sent to a
+//! LibreTranslate-compatible API and the translation is appended below the
+//! original as a `%` comment, so the response stays parseable:
+//!
+//! ```text
+//! [組織名]                        株式会社日本レジストリサービス
+//! % [en] [Organization name]      Japan Registry Services Co., Ltd.
+//! ```
+//!
+//! The API is configured with `TRANSLATE_API_URL` (e.g.
+//! `https://libretranslate.com/translate`), `TRANSLATE_API_KEY` if it needs
+//! one, and `TRANSLATE_TARGET` (default `en`).
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::log_debug;
+
+/// Lines translated per response, bounding the cost of one query
+const MAX_LINES: usize = 100;
+const DEFAULT_TARGET: &str = "en";
+
+#[derive(Debug, Deserialize)]
+struct TranslateResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: Vec<String>,
+}
+
+struct Translator {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    target: String,
+}
+
+impl Translator {
+    fn from_env() -> Option<Self> {
+        let _ = dotenv::dotenv();
+        let url = std::env::var("TRANSLATE_API_URL").ok().filter(|u| !u.trim().is_empty())?;
+        let api_key = std::env::var("TRANSLATE_API_KEY").ok().filter(|k| !k.trim().is_empty());
+        let target = std::env::var("TRANSLATE_TARGET")
+            .ok()
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_TARGET.to_string());
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .user_agent("whois-server/1.0")
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        Some(Self { client, url: url.trim().to_string(), api_key, target: target.trim().to_lowercase() })
+    }
+
+    async fn translate(&self, lines: &[&str]) -> Result<Vec<String>> {
+        let mut body = serde_json::json!({
+            "q": lines,
+            "source": "auto",
+            "target": self.target,
+            "format": "text",
+        });
+        if let Some(api_key) = &self.api_key {
+            body["api_key"] = serde_json::Value::String(api_key.trim().to_string());
+        }
+
+        let response = self.client.post(&self.url).json(&body).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("translation API returned HTTP {}", response.status()));
+        }
+        let translated: TranslateResponse = response.limited_json().await?;
+        if translated.translated_text.len() != lines.len() {
+            return Err(anyhow!("translation API returned {} of {} lines", translated.translated_text.len(), lines.len()));
+        }
+        Ok(translated.translated_text)
+    }
+}
+
+/// Whether a letter is outside the Latin script (Basic Latin, Latin-1 and the Latin Extended blocks)
+fn is_non_latin(c: char) -> bool {
+    c.is_alphabetic() && !matches!(c, '\0'..='\u{024f}' | '\u{1e00}'..='\u{1eff}' | '\u{2c60}'..='\u{2c7f}' | '\u{a720}'..='\u{a7ff}')
+}
+
+/// Distinct lines that need translating, in response order
+fn untranslated_lines(response: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in response.lines().map(str::trim_end) {
+        if line.chars().any(is_non_latin) && !lines.contains(&line) {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Add each translation below its original line
+fn interleave(response: &str, translations: &HashMap<&str, String>, target: &str) -> String {
+    let mut output = String::with_capacity(response.len() * 2);
+    for line in response.lines() {
+        output.push_str(line);
+        output.push('\n');
+        if let Some(translation) = translations.get(line.trim_end()).filter(|t| t.trim() != line.trim()) {
+            output.push_str(&format!("% [{}] {}\n", target, translation.trim()));
+        }
+    }
+    output
+}
+
+/// Append translations of non-Latin lines to a response
+pub async fn translate_response(ctx: &RequestContext, response: String) -> String {
+    let lines = untranslated_lines(&response);
+    if lines.is_empty() {
+        return response;
+    }
+    let Some(translator) = Translator::from_env() else {
+        return format!(
+            "{}\n% -TRANSLATE is not enabled on this server (TRANSLATE_API_URL is not set)\n",
+            response.trim_end_matches('\n')
+        );
+    };
+
+    let batch = &lines[..lines.len().min(MAX_LINES)];
+    log_debug!("[{}] Translating {} lines to {}", ctx, batch.len(), translator.target);
+    match translator.translate(batch).await {
+        Ok(translated) => {
+            let translations: HashMap<&str, String> = batch.iter().copied().zip(translated).collect();
+            let mut output = interleave(&response, &translations, &translator.target);
+            if lines.len() > MAX_LINES {
+                output.push_str(&format!("% Only the first {} of {} non-Latin lines were translated\n", MAX_LINES, lines.len()));
+            }
+            output.push_str("% Machine translation, the original lines are authoritative\n");
+            output
+        }
+        Err(e) => {
+            log_debug!("[{}] Translation failed: {}", ctx, e);
+            format!("{}\n% Translation failed: {}\n", response.trim_end_matches('\n'), e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translation_lines() {
+        let response = "% JPRS database\n[Domain Name]                   JPRS.JP\n[組織名]                        株式会社日本レジストリサービス\nnetname: Ñandú-Café\n기관명 : 한국인터넷진흥원\n[組織名]                        株式会社日本レジストリサービス\n";
+        let lines = untranslated_lines(response);
+        assert_eq!(lines, vec!["[組織名]                        株式会社日本レジストリサービス", "기관명 : 한국인터넷진흥원"]);
+
+        let translations: HashMap<&str, String> = HashMap::from([
+            (lines[0], "[Organization name] Japan Registry Services Co., Ltd.".to_string()),
+            (lines[1], "기관명 : 한국인터넷진흥원".to_string()),
+        ]);
+        let output = interleave(response, &translations, "en");
+        assert!(output.contains("株式会社日本レジストリサービス\n% [en] [Organization name] Japan Registry Services Co., Ltd.\n"));
+        assert_eq!(output.matches("% [en]").count(), 2);
+        // Lines the API returns unchanged are not repeated
+        assert!(!output.contains("% [en] 기관명"));
+    }
+}
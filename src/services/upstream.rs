@@ -0,0 +1,35 @@
+// WHOIS Server - Upstream Endpoints
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Overridable upstream API base URLs
+//!
+//! Services build request URLs from `api_base(DEFAULT_BASE)` instead of using
+//! the constant directly. In production this is always the default; tests run
+//! a service inside an override scope to point it at a local fixture server
+//! (see `services::fixtures`).
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+tokio::task_local! {
+    static UPSTREAM_OVERRIDES: Arc<HashMap<&'static str, String>>;
+}
+
+/// Base URL to use for an upstream API
+pub fn api_base(default: &'static str) -> Cow<'static, str> {
+    UPSTREAM_OVERRIDES.try_with(|overrides| overrides.get(default).cloned())
+        .ok()
+        .flatten()
+        .map_or(Cow::Borrowed(default), Cow::Owned)
+}
+
+/// Run `fut` with upstream base URLs replaced
+#[cfg(test)]
+pub(crate) async fn with_upstreams<F: std::future::Future>(
+    overrides: HashMap<&'static str, String>,
+    fut: F
+) -> F::Output {
+    UPSTREAM_OVERRIDES.scope(Arc::new(overrides), fut).await
+}
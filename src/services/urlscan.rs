@@ -0,0 +1,515 @@
+// WHOIS Server - urlscan.io
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! urlscan.io lookups (`-URLSCAN`)
+//!
+//! Summarizes the most recent urlscan.io scan of a URL or domain: verdicts,
+//! contacted IPs and ASNs, detected technologies and the screenshot. A scan
+//! id (UUID) selects a specific scan. Searching works without an API key;
+//! with `URLSCAN_API_KEY` set, targets that were never scanned are submitted
+//! as unlisted scans and polled until the result is ready.
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::core::body_limit::LimitedBody;
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::upstream::api_base;
+use crate::{log_debug, log_warn};
+
+const URLSCAN_API_BASE: &str = "https://urlscan.io/api/v1/";
+/// Contacted IPs/ASNs/technologies shown before the list is truncated
+const LIST_LIMIT: usize = 10;
+/// Delay between polls of a submitted scan
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Longest time to wait for a submitted scan when the request has no deadline
+const MAX_POLL_TIME: Duration = Duration::from_secs(45);
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    results: Vec<SearchHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    #[serde(rename = "_id")]
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmitResponse {
+    uuid: String,
+    #[serde(default)]
+    result: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ScanResult {
+    #[serde(default)]
+    task: Task,
+    #[serde(default)]
+    page: Page,
+    #[serde(default)]
+    lists: Lists,
+    #[serde(default)]
+    verdicts: Verdicts,
+    #[serde(default)]
+    meta: Meta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Task {
+    uuid: Option<String>,
+    url: Option<String>,
+    time: Option<String>,
+    visibility: Option<String>,
+    #[serde(rename = "reportURL")]
+    report_url: Option<String>,
+    #[serde(rename = "screenshotURL")]
+    screenshot_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Page {
+    url: Option<String>,
+    domain: Option<String>,
+    title: Option<String>,
+    ip: Option<String>,
+    asn: Option<String>,
+    asnname: Option<String>,
+    country: Option<String>,
+    server: Option<String>,
+    status: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Lists {
+    #[serde(default)]
+    ips: Vec<String>,
+    #[serde(default)]
+    asns: Vec<String>,
+    #[serde(default)]
+    domains: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Verdicts {
+    #[serde(default)]
+    overall: Verdict,
+    #[serde(default)]
+    community: Verdict,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Verdict {
+    #[serde(default)]
+    score: i64,
+    #[serde(default)]
+    malicious: bool,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    brands: Vec<serde_json::Value>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(rename = "votesTotal", default)]
+    votes_total: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Meta {
+    #[serde(default)]
+    processors: Processors,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Processors {
+    #[serde(default)]
+    wappa: Wappalyzer,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Wappalyzer {
+    #[serde(default)]
+    data: Vec<Technology>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Technology {
+    app: String,
+    #[serde(default)]
+    categories: Vec<TechnologyCategory>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TechnologyCategory {
+    name: String,
+}
+
+/// What a `-URLSCAN` query refers to
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    /// A scan id
+    Scan(String),
+    /// A URL, with the host used for searching existing scans
+    Url { url: String, host: String },
+}
+
+fn is_scan_id(target: &str) -> bool {
+    let parts: Vec<&str> = target.split('-').collect();
+    parts.len() == 5 &&
+        parts.iter().map(|p| p.len()).eq([8, 4, 4, 4, 12]) &&
+        parts.iter().all(|p| p.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Parse a query target into a scan id or a URL
+fn parse_target(target: &str) -> Option<Target> {
+    let target = target.trim();
+    if is_scan_id(target) {
+        return Some(Target::Scan(target.to_ascii_lowercase()));
+    }
+
+    let url = if target.contains("://") { target.to_string() } else { format!("https://{}", target) };
+    let parsed = reqwest::Url::parse(&url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = parsed.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    if !host.contains('.') && !host.contains(':') {
+        return None;
+    }
+    Some(Target::Url { url: parsed.to_string(), host })
+}
+
+/// urlscan.io API client
+struct UrlscanClient {
+    client: reqwest::Client,
+    api_key: Option<String>,
+}
+
+impl UrlscanClient {
+    fn from_env() -> Self {
+        let _ = dotenv::dotenv();
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(20))
+            .user_agent("WhoisServer/1.0 urlscan.io Client")
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        let api_key = std::env::var("URLSCAN_API_KEY").ok().filter(|k| !k.trim().is_empty());
+        Self { client, api_key }
+    }
+
+    fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.get(format!("{}{}", api_base(URLSCAN_API_BASE), path));
+        match &self.api_key {
+            Some(key) => request.header("API-Key", key),
+            None => request,
+        }
+    }
+
+    /// Id of the most recent scan of a host, if any
+    async fn latest_scan(&self, host: &str) -> Result<Option<String>> {
+        let query = format!("domain:{}", host);
+        let response = self.get("search/").query(&[("q", query.as_str()), ("size", "1")]).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("urlscan.io search failed: HTTP {}", response.status()));
+        }
+        let found: SearchResponse = response.limited_json().await?;
+        Ok(found.results.into_iter().next().map(|hit| hit.id))
+    }
+
+    /// Result of a scan, `None` while the scan is still running
+    async fn result(&self, uuid: &str) -> Result<Option<ScanResult>> {
+        let response = self.get(&format!("result/{}/", uuid)).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("urlscan.io result request failed: HTTP {}", response.status()));
+        }
+        Ok(Some(response.limited_json().await?))
+    }
+
+    /// Submit a new unlisted scan
+    async fn submit(&self, api_key: &str, url: &str) -> Result<SubmitResponse> {
+        let response = self.client
+            .post(format!("{}scan/", api_base(URLSCAN_API_BASE)))
+            .header("API-Key", api_key)
+            .json(&serde_json::json!({ "url": url, "visibility": "unlisted" }))
+            .send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response
+                .limited_json::<serde_json::Value>().await
+                .ok()
+                .and_then(|body| body.get("message").and_then(|m| m.as_str()).map(str::to_string))
+                .unwrap_or_else(|| format!("HTTP {}", status));
+            return Err(anyhow!("urlscan.io submission failed: {}", message));
+        }
+        Ok(response.limited_json().await?)
+    }
+
+    /// Wait for a submitted scan, giving up before the request deadline
+    async fn poll(&self, ctx: &RequestContext, uuid: &str) -> Result<Option<ScanResult>> {
+        let budget = ctx.remaining().map_or(MAX_POLL_TIME, |left| left.min(MAX_POLL_TIME));
+        let mut waited = Duration::ZERO;
+        while waited + POLL_INTERVAL < budget {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            waited += POLL_INTERVAL;
+            if let Some(result) = self.result(uuid).await? {
+                return Ok(Some(result));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Look up `target` on urlscan.io
+pub async fn process_urlscan_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let Some(parsed) = parse_target(target) else {
+        return Ok(format!(
+            "Invalid urlscan.io query: {}\nUse a URL, domain or scan id, e.g. example.com-URLSCAN\n",
+            target
+        ));
+    };
+    let client = UrlscanClient::from_env();
+
+    let uuid = match &parsed {
+        Target::Scan(uuid) => uuid.clone(),
+        Target::Url { url, host } => {
+            log_debug!("[{}] Searching urlscan.io for {}", ctx, host);
+            match client.latest_scan(host).await? {
+                Some(uuid) => uuid,
+                None => {
+                    let Some(api_key) = client.api_key.as_deref() else {
+                        return Ok(format!(
+                            "No urlscan.io scans found for: {}\n\
+                             Set URLSCAN_API_KEY to submit new scans.\n",
+                            host
+                        ));
+                    };
+                    let submitted = client.submit(api_key, url).await?;
+                    log_debug!("[{}] Submitted urlscan.io scan {} for {}", ctx, submitted.uuid, url);
+                    return match client.poll(ctx, &submitted.uuid).await? {
+                        Some(result) => Ok(format_scan(target, &result)),
+                        None => Ok(format!(
+                            "urlscan.io scan submitted for: {}\nscan-id: {}\nreport-url: {}\n\
+                             % The scan is still running, query {}-URLSCAN again shortly\n",
+                            url,
+                            submitted.uuid,
+                            submitted.result.unwrap_or_else(|| format!("https://urlscan.io/result/{}/", submitted.uuid)),
+                            submitted.uuid
+                        )),
+                    };
+                }
+            }
+        }
+    };
+
+    match client.result(&uuid).await? {
+        Some(result) => Ok(format_scan(target, &result)),
+        None => {
+            log_warn!("[{}] urlscan.io scan {} has no result", ctx, uuid);
+            Ok(format!("urlscan.io scan not found or still running: {}\n", uuid))
+        }
+    }
+}
+
+/// Verdict of the most recent urlscan.io scan of a host
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HostVerdict {
+    pub malicious: bool,
+    pub score: i64,
+    pub categories: Vec<String>,
+    pub time: Option<String>,
+    pub report_url: Option<String>,
+}
+
+/// Verdict of the latest scan of `host`, `None` when it was never scanned
+pub(crate) async fn latest_verdict(host: &str) -> Result<Option<HostVerdict>> {
+    let client = UrlscanClient::from_env();
+    let Some(uuid) = client.latest_scan(host).await? else {
+        return Ok(None);
+    };
+    Ok(client.result(&uuid).await?.map(|scan| HostVerdict {
+        malicious: scan.verdicts.overall.malicious,
+        score: scan.verdicts.overall.score,
+        categories: scan.verdicts.overall.categories,
+        time: scan.task.time,
+        report_url: scan.task.report_url,
+    }))
+}
+
+/// Name of a brand entry, which is either a string or an object
+fn brand_name(brand: &serde_json::Value) -> Option<String> {
+    brand
+        .as_str()
+        .or_else(|| brand.get("name").and_then(|n| n.as_str()))
+        .map(str::to_string)
+}
+
+fn push_list(output: &mut String, key: &str, values: &[String]) {
+    for value in values.iter().take(LIST_LIMIT) {
+        output.push_str(&format!("{}: {}\n", key, value));
+    }
+    if values.len() > LIST_LIMIT {
+        output.push_str(&format!("% {} more {} entries omitted\n", values.len() - LIST_LIMIT, key));
+    }
+}
+
+/// Format a scan result for WHOIS display
+fn format_scan(target: &str, scan: &ScanResult) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("urlscan.io Report for: {}\n", target));
+    output.push_str("=".repeat(60).as_str());
+    output.push('\n');
+
+    let task = &scan.task;
+    let page = &scan.page;
+    let fields = [
+        ("scan-id", task.uuid.clone()),
+        ("scanned-url", task.url.clone()),
+        ("scan-time", task.time.clone()),
+        ("visibility", task.visibility.clone()),
+        ("page-url", page.url.clone()),
+        ("page-domain", page.domain.clone()),
+        ("page-title", page.title.clone()),
+        ("page-ip", page.ip.clone()),
+        (
+            "page-asn",
+            page.asn.as_ref().map(|asn| match &page.asnname {
+                Some(name) => format!("{} {}", asn, name),
+                None => asn.clone(),
+            }),
+        ),
+        ("page-country", page.country.clone()),
+        ("page-server", page.server.clone()),
+        ("page-status", page.status.clone()),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            output.push_str(&format!("{}: {}\n", key, value));
+        }
+    }
+
+    let overall = &scan.verdicts.overall;
+    output.push_str(&format!(
+        "verdict: {} (score {})\n",
+        if overall.malicious { "malicious" } else { "no classification" },
+        overall.score
+    ));
+    if !overall.categories.is_empty() {
+        output.push_str(&format!("verdict-categories: {}\n", overall.categories.join(", ")));
+    }
+    let brands: Vec<String> = overall.brands.iter().filter_map(brand_name).collect();
+    if !brands.is_empty() {
+        output.push_str(&format!("verdict-brands: {}\n", brands.join(", ")));
+    }
+    if !overall.tags.is_empty() {
+        output.push_str(&format!("verdict-tags: {}\n", overall.tags.join(", ")));
+    }
+    let community = &scan.verdicts.community;
+    if community.votes_total > 0 {
+        output.push_str(&format!(
+            "community-verdict: {} ({} votes)\n",
+            if community.malicious { "malicious" } else { "benign" },
+            community.votes_total
+        ));
+    }
+
+    output.push_str(&format!("contacted-domains: {}\n", scan.lists.domains.len()));
+    push_list(&mut output, "contacted-ip", &scan.lists.ips);
+    let asns: Vec<String> = scan.lists.asns.iter().map(|asn| format!("AS{}", asn.trim_start_matches("AS"))).collect();
+    push_list(&mut output, "contacted-asn", &asns);
+
+    let technologies: Vec<String> = scan.meta.processors.wappa.data
+        .iter()
+        .map(|tech| {
+            let categories: Vec<&str> = tech.categories.iter().map(|c| c.name.as_str()).collect();
+            if categories.is_empty() { tech.app.clone() } else { format!("{} ({})", tech.app, categories.join(", ")) }
+        })
+        .collect();
+    push_list(&mut output, "technology", &technologies);
+
+    let uuid = task.uuid.as_deref().unwrap_or_default();
+    let screenshot = task.screenshot_url.clone().unwrap_or_else(|| format!("https://urlscan.io/screenshots/{}.png", uuid));
+    let report = task.report_url.clone().unwrap_or_else(|| format!("https://urlscan.io/result/{}/", uuid));
+    output.push_str(&format!("screenshot: {}\n", screenshot));
+    output.push_str(&format!("report-url: {}\n", report));
+    output
+}
+
+pub struct UrlscanService;
+
+#[async_trait]
+impl QueryService for UrlscanService {
+    fn name(&self) -> &'static str {
+        "urlscan"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-URLSCAN"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "SECURITY INVESTIGATION",
+            summary: "urlscan.io verdicts, contacted IPs/ASNs, technologies, screenshot",
+            example: "example.com-URLSCAN",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "urlscan.io API"
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_urlscan_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::fixtures::FixtureServer;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            parse_target("example.com"),
+            Some(Target::Url { url: "https://example.com/".to_string(), host: "example.com".to_string() })
+        );
+        assert_eq!(
+            parse_target("http://Example.com/login?x=1"),
+            Some(Target::Url { url: "http://example.com/login?x=1".to_string(), host: "example.com".to_string() })
+        );
+        assert_eq!(
+            parse_target("0196A0D4-3F5B-7A2C-9E1D-4B8C6F2A1E37"),
+            Some(Target::Scan("0196a0d4-3f5b-7a2c-9e1d-4b8c6f2a1e37".to_string()))
+        );
+        assert_eq!(parse_target("localhost"), None);
+        assert_eq!(parse_target("ftp://example.com"), None);
+    }
+
+    #[tokio::test]
+    async fn test_urlscan_fixture() {
+        let mut fixtures = FixtureServer::start("urlscan").await;
+        fixtures.serve(URLSCAN_API_BASE, "search/?q=domain:example.com&size=1", "search_example.json").await;
+        fixtures
+            .serve(URLSCAN_API_BASE, "result/0196a0d4-3f5b-7a2c-9e1d-4b8c6f2a1e37/", "result_example.json").await;
+
+        let ctx = RequestContext::default();
+        let response = fixtures.run(process_urlscan_query(&ctx, "example.com")).await.unwrap();
+        assert!(response.contains("scan-id: 0196a0d4-3f5b-7a2c-9e1d-4b8c6f2a1e37\n"));
+        assert!(response.contains("page-asn: AS15133 EDGECAST, US\n"));
+        assert!(response.contains("verdict: no classification (score 0)\n"));
+        assert!(response.contains("contacted-asn: AS15133\n"));
+        assert!(response.contains("technology: Azure CDN (CDN)\n"));
+        assert!(response.contains("screenshot: https://urlscan.io/screenshots/0196a0d4-3f5b-7a2c-9e1d-4b8c6f2a1e37.png\n"));
+    }
+}
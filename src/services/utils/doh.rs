@@ -9,6 +9,7 @@ use serde::Deserialize;
 use std::collections::HashMap;
 use std::net::{ IpAddr, Ipv4Addr, Ipv6Addr };
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
 use crate::log_debug;
 
 const CLOUDFLARE_DOH_URL: &str = "https://cloudflare-dns.com/dns-query";
@@ -160,7 +161,7 @@ impl DohClient {
         }
 
         let doh_response: DnsResponse = response
-            .json().await
+            .limited_json().await
             .map_err(|e| anyhow::anyhow!("Failed to parse DOH response: {}", e))?;
 
         if doh_response.Status != 0 {
@@ -170,6 +171,36 @@ impl DohClient {
         Ok(doh_response)
     }
 
+    /// DNS query returning DNSSEC records (RRSIG) alongside the answer
+    ///
+    /// Sets the CD bit so answers that fail the resolver's own validation
+    /// are still returned and can be checked locally.
+    pub async fn query_dnssec(&self, name: &str, record_type: &str) -> Result<DnsResponse> {
+        log_debug!("Querying DNS with DNSSEC records: {} type={}", name, record_type);
+
+        let url = format!(
+            "{}?name={}&type={}&do=true&cd=true",
+            CLOUDFLARE_DOH_URL,
+            urlencoding::encode(name),
+            record_type
+        );
+
+        let response = self.client
+            .get(&url)
+            .header("Accept", "application/dns-json")
+            .send().await
+            .map_err(|e| anyhow::anyhow!("DOH request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(anyhow::anyhow!("DOH request failed with HTTP status: {}", status));
+        }
+
+        response
+            .limited_json().await
+            .map_err(|e| anyhow::anyhow!("Failed to parse DOH response: {}", e))
+    }
+
     /// Batch query multiple record types concurrently
     ///
     /// Returns a HashMap with record type as key and list of answers as value
@@ -206,7 +237,7 @@ impl DohClient {
 
                         match response {
                             Ok(resp) if resp.status().is_success() => {
-                                match resp.json::<crate::services::utils::doh::DnsResponse>().await {
+                                match resp.limited_json::<crate::services::utils::doh::DnsResponse>().await {
                                     Ok(doh_response) => Ok((type_str, doh_response)),
                                     Err(_) => Err(type_str),
                                 }
@@ -284,7 +315,7 @@ impl DohClient {
         }
 
         let doh_response: crate::services::utils::doh::DnsResponse = response
-            .json().await
+            .limited_json().await
             .map_err(|e| anyhow::anyhow!("Failed to parse DOH response: {}", e))?;
 
         // Check if query was successful
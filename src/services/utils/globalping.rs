@@ -10,9 +10,13 @@ use reqwest::Client;
 use serde::{ Deserialize, Serialize };
 use std::time::Duration;
 use tokio::time::sleep;
+use crate::core::body_limit::LimitedBody;
 use crate::{ log_debug, log_error, log_warn };
 
 const GLOBALPING_API_BASE: &str = "https://api.globalping.io/v1/measurements";
+const GLOBALPING_PROBES_URL: &str = "https://api.globalping.io/v1/probes";
+/// Most locations accepted in one `@` vantage point selector
+const MAX_VANTAGE_LOCATIONS: usize = 5;
 const MAX_POLL_ATTEMPTS: u32 = 60; // Maximum polling attempts (60 seconds)
 const POLL_INTERVAL: Duration = Duration::from_secs(1);
 
@@ -35,7 +39,7 @@ pub struct GlobalpingRequest {
 }
 
 /// Measurement location filter
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
 pub struct MeasurementLocation {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub magic: Option<String>,
@@ -59,12 +63,56 @@ pub struct MeasurementLocation {
     pub tags: Option<Vec<String>>,
 }
 
-/// Measurement options - different for ping and traceroute
+impl MeasurementLocation {
+    /// Location matched by Globalping's "magic" field (country, city, ASN, network, ...)
+    pub fn magic(value: &str) -> Self {
+        Self {
+            magic: Some(value.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Parse a vantage point selector such as `de`, `as13335` or `de,us,aws+eu`
+///
+/// Each comma-separated entry becomes one magic location. Returns `None` for
+/// empty or malformed selectors.
+pub fn parse_vantage_points(spec: &str) -> Option<Vec<MeasurementLocation>> {
+    let entries: Vec<&str> = spec.split(',').map(str::trim).collect();
+    let valid = entries.len() <= MAX_VANTAGE_LOCATIONS &&
+        entries.iter().all(|entry| {
+            !entry.is_empty() &&
+                entry.len() <= 64 &&
+                entry.chars().all(|c| c.is_alphanumeric() || matches!(c, '+' | '-' | '_' | ' ' | '.'))
+        });
+    valid.then(|| entries.into_iter().map(MeasurementLocation::magic).collect())
+}
+
+/// Split a trailing `@<vantage>` selector off a ping, traceroute or looking
+/// glass query
+///
+/// `8.8.8.8-TRACE@de` becomes (`8.8.8.8-TRACE`, `Some("de")`). Queries of
+/// other types, and selectors that do not parse, are returned unchanged.
+pub fn split_vantage_point(query: &str) -> (&str, Option<String>) {
+    if let Some((rest, spec)) = query.rsplit_once('@') {
+        let upper = rest.to_uppercase();
+        let measured = ["-PING", "-TRACE", "-TRACEROUTE", "-LG"]
+            .iter()
+            .any(|suffix| upper.len() > suffix.len() && upper.ends_with(suffix));
+        if measured && parse_vantage_points(spec).is_some() {
+            return (rest, Some(spec.trim().to_string()));
+        }
+    }
+    (query, None)
+}
+
+/// Measurement options - different for ping, traceroute and HTTP
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum MeasurementOptions {
     Ping(PingOptions),
     Traceroute(TracerouteOptions),
+    Http(HttpOptions),
 }
 
 /// Options specific to ping measurements
@@ -106,6 +154,23 @@ impl Default for TracerouteOptions {
     }
 }
 
+/// Options specific to HTTP measurements
+#[derive(Debug, Serialize)]
+pub struct HttpOptions {
+    pub request: HttpRequestOptions,
+    /// `HTTP`, `HTTPS` or `HTTP2`
+    pub protocol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+/// Request sent by an HTTP measurement
+#[derive(Debug, Serialize)]
+pub struct HttpRequestOptions {
+    pub method: String,
+    pub path: String,
+}
+
 /// Globalping API response - submission confirmation
 #[derive(Debug, Deserialize)]
 pub struct GlobalpingResponse {
@@ -163,19 +228,55 @@ pub struct TestResult {
     #[allow(dead_code)]
     pub resolved_hostname: Option<String>,
     #[serde(default)]
-    pub timings: Option<Vec<Timing>>,
+    pub timings: Option<Timings>,
+    /// Response status of an HTTP measurement
+    #[serde(rename = "statusCode")]
     #[serde(default)]
+    pub status_code: Option<u16>,
+    #[serde(default, deserialize_with = "lenient_stats")]
     pub stats: Option<Stats>,
     #[serde(default)]
     pub hops: Option<Vec<HopResult>>,
 }
 
+/// Timings of a result: one per reply for ping, the phases of the request for HTTP
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Timings {
+    Replies(Vec<Timing>),
+    Http(HttpTimings),
+}
+
+impl Timings {
+    /// Ping replies, empty for HTTP measurements
+    pub fn replies(&self) -> &[Timing] {
+        match self {
+            Timings::Replies(replies) => replies,
+            Timings::Http(_) => &[],
+        }
+    }
+}
+
 /// Timing information for ping
 #[derive(Debug, Deserialize, Clone, Copy)]
 pub struct Timing {
     pub rtt: f64,
 }
 
+/// Phases of an HTTP measurement in milliseconds, absent when not reached
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct HttpTimings {
+    #[serde(default)]
+    pub total: Option<f64>,
+    #[serde(default)]
+    pub tcp: Option<f64>,
+    #[serde(default)]
+    pub tls: Option<f64>,
+    #[serde(rename = "firstByte")]
+    #[serde(default)]
+    pub first_byte: Option<f64>,
+}
+
 /// Statistics for ping
 #[derive(Debug, Deserialize)]
 pub struct Stats {
@@ -190,6 +291,15 @@ pub struct Stats {
     pub drop: u32,
 }
 
+/// Parse ping stats, treating stats of probes that lost packets (null
+/// latencies, fractional loss) as absent instead of failing the whole result
+fn lenient_stats<'de, D>(deserializer: D) -> std::result::Result<Option<Stats>, D::Error>
+    where D: serde::Deserializer<'de>
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
 /// Traceroute hop result (from Globalping API)
 /// The API returns an array of hops with resolved addresses and timings
 #[derive(Debug, Deserialize, Clone)]
@@ -291,6 +401,27 @@ pub struct MeasurementResult {
     pub latency: Option<Vec<LatencyValue>>,
 }
 
+/// An online Globalping probe
+#[derive(Debug, Deserialize)]
+pub struct Probe {
+    pub location: ProbeLocation,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Location of an online probe
+#[derive(Debug, Deserialize)]
+pub struct ProbeLocation {
+    pub continent: String,
+    #[serde(default)]
+    pub region: Option<String>,
+    pub country: String,
+    #[serde(default)]
+    pub city: Option<String>,
+    pub asn: u32,
+    pub network: String,
+}
+
 /// Client for Globalping API
 pub struct GlobalpingClient {
     client: Client,
@@ -344,7 +475,7 @@ impl GlobalpingClient {
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response
-                .text().await
+                .limited_text().await
                 .unwrap_or_else(|_| "Unable to read error".to_string());
             log_error!("Globalping API error: {} - {}", status, error_text);
             return Err(
@@ -353,7 +484,7 @@ impl GlobalpingClient {
         }
 
         let result: GlobalpingResponse = response
-            .json().await
+            .limited_json().await
             .map_err(|e| anyhow::anyhow!("Failed to parse Globalping response: {}", e))?;
 
         log_debug!("Measurement submitted successfully, ID: {}", result.id);
@@ -380,18 +511,41 @@ impl GlobalpingClient {
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response
-                .text().await
+                .limited_text().await
                 .unwrap_or_else(|_| "Unable to read error".to_string());
             return Err(anyhow::anyhow!("Globalping API error: {} - {}", status, error_text));
         }
 
         let result: GlobalpingResult = response
-            .json().await
+            .limited_json().await
             .map_err(|e| anyhow::anyhow!("Failed to parse Globalping result: {}", e))?;
 
         Ok(result)
     }
 
+    /// List the probes that are currently online
+    pub async fn list_probes(&self) -> Result<Vec<Probe>> {
+        let mut req_builder = self.client.get(GLOBALPING_PROBES_URL);
+
+        // Add authorization header if token is available
+        if let Some(token) = &self.api_token {
+            req_builder = req_builder.header("Authorization", &format!("Bearer {}", token));
+        }
+
+        let response = req_builder
+            .send().await
+            .map_err(|e| anyhow::anyhow!("Failed to list probes: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(anyhow::anyhow!("Globalping API error: {}", status));
+        }
+
+        response
+            .limited_json().await
+            .map_err(|e| anyhow::anyhow!("Failed to parse Globalping probes: {}", e))
+    }
+
     /// Wait for measurement to complete and return results
     ///
     /// Polls the measurement status until it completes or times out
@@ -451,4 +605,42 @@ mod tests {
         let negative = LatencyValue::Number(-1.0);
         assert_eq!(negative.as_f64(), None);
     }
+
+    #[test]
+    fn test_stats_of_lossy_probe() {
+        let result: TestResult = serde_json::from_str(
+            r#"{"status":"finished","stats":{"min":null,"max":null,"avg":null,"total":3,"loss":100,"rcv":0}}"#
+        ).unwrap();
+        assert!(result.stats.is_none());
+    }
+
+    #[test]
+    fn test_http_timings() {
+        let result: TestResult = serde_json::from_str(
+            r#"{"status":"finished","statusCode":301,"timings":{"total":120,"dns":5,"tcp":18,"tls":40,"firstByte":50,"download":7}}"#
+        ).unwrap();
+        assert_eq!(result.status_code, Some(301));
+        match result.timings {
+            Some(Timings::Http(timings)) => assert_eq!(timings.tcp, Some(18.0)),
+            other => panic!("unexpected timings: {:?}", other),
+        }
+
+        let ping: TestResult = serde_json::from_str(r#"{"status":"finished","timings":[{"rtt":1.5},{"rtt":2.5}]}"#).unwrap();
+        assert_eq!(ping.timings.unwrap().replies().len(), 2);
+    }
+
+    #[test]
+    fn test_vantage_point_selector() {
+        assert_eq!(split_vantage_point("8.8.8.8-TRACE@de"), ("8.8.8.8-TRACE", Some("de".to_string())));
+        assert_eq!(split_vantage_point("1.1.1.1-ping@as13335"), ("1.1.1.1-ping", Some("as13335".to_string())));
+        assert_eq!(split_vantage_point("example.com-TRACEROUTE@de,us"), ("example.com-TRACEROUTE", Some("de,us".to_string())));
+        assert_eq!(split_vantage_point("1.1.1.0/24-LG@rrc12"), ("1.1.1.0/24-LG", Some("rrc12".to_string())));
+        assert_eq!(split_vantage_point("user@example.com-HIBP"), ("user@example.com-HIBP", None));
+        assert_eq!(split_vantage_point("8.8.8.8-PING@"), ("8.8.8.8-PING@", None));
+        assert_eq!(split_vantage_point("8.8.8.8-PING@a,b,c,d,e,f"), ("8.8.8.8-PING@a,b,c,d,e,f", None));
+
+        let locations = parse_vantage_points("de, aws+eu").unwrap();
+        assert_eq!(locations, vec![MeasurementLocation::magic("de"), MeasurementLocation::magic("aws+eu")]);
+        assert!(parse_vantage_points("de;rm").is_none());
+    }
 }
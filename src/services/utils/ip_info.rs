@@ -7,6 +7,7 @@ use anyhow::Result;
 use reqwest::Client;
 use serde::Deserialize;
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error};
 
 const IPINFO_API_BASE: &str = "https://api.ipinfo.io/lite";
@@ -85,12 +86,12 @@ impl IpInfoClient {
 
         if !response.status().is_success() {
             let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unable to read error".to_string());
+            let error_text = response.limited_text().await.unwrap_or_else(|_| "Unable to read error".to_string());
             log_error!("IPInfo API error: {} - {}", status, error_text);
             return Err(anyhow::anyhow!("IPInfo API returned error: {} - {}", status, error_text));
         }
 
-        let info: IpInfo = response.json().await
+        let info: IpInfo = response.limited_json().await
             .map_err(|e| anyhow::anyhow!("Failed to parse IPInfo response: {}", e))?;
 
         log_debug!("Got IP info: {} -> {}", ip, info.as_name);
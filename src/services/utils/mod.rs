@@ -3,6 +3,8 @@
 pub mod doh;
 pub mod globalping;
 pub mod ip_info;
+pub mod public_http;
+pub mod table;
 
 // Re-export commonly used types from doh
 pub use doh::DohClient;
@@ -11,7 +13,8 @@ pub use doh::DohClient;
 #[allow(dead_code)]
 pub use globalping::{
     GlobalpingClient, GlobalpingRequest, GlobalpingResult,
-    MeasurementOptions, PingOptions, TracerouteOptions, MeasurementLocation
+    MeasurementOptions, PingOptions, TracerouteOptions, HttpOptions, HttpRequestOptions, MeasurementLocation,
+    Probe, parse_vantage_points, split_vantage_point
 };
 
 // Re-export commonly used types from ip_info
@@ -0,0 +1,107 @@
+//! HTTP clients for URLs chosen by clients
+//!
+//! Services that fetch a URL taken from the query must not reach this
+//! server's own network. [`public_only`] makes a reqwest client connect only
+//! to public addresses: host names are resolved by [`PublicResolver`], which
+//! drops internal addresses, and every redirect hop to an address literal is
+//! checked before it is followed.
+
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use reqwest::{ClientBuilder, Url};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use crate::core::is_internal;
+
+/// Resolver that only returns public addresses
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(lookup_public(name.as_str().to_string()))
+    }
+}
+
+async fn lookup_public(host: String) -> Result<Addrs, Box<dyn std::error::Error + Send + Sync>> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+        .await?
+        .filter(|address| !is_internal(address.ip()))
+        .collect();
+    if addresses.is_empty() {
+        return Err(format!("{} has no public address", host).into());
+    }
+    Ok(Box::new(addresses.into_iter()))
+}
+
+/// Whether a URL is http(s) and does not name an internal address
+pub fn is_public_url(url: &Url) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    match url.host() {
+        Some(url::Host::Ipv4(address)) => !is_internal(IpAddr::V4(address)),
+        Some(url::Host::Ipv6(address)) => !is_internal(IpAddr::V6(address)),
+        Some(url::Host::Domain(_)) => true,
+        None => false,
+    }
+}
+
+/// Public addresses of a URL's host, empty if it only has internal ones
+pub async fn public_addresses(url: &Url) -> Vec<IpAddr> {
+    let Some(host) = url.host_str() else {
+        return Vec::new();
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match tokio::net::lookup_host((host, 0)).await {
+        Ok(addresses) => addresses.map(|address| address.ip()).filter(|address| !is_internal(*address)).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Restrict a client to public addresses, following at most `max_redirects`
+pub fn public_only(builder: ClientBuilder, max_redirects: usize) -> ClientBuilder {
+    builder.dns_resolver(Arc::new(PublicResolver)).redirect(
+        Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_redirects {
+                attempt.error("too many redirects")
+            } else if !is_public_url(attempt.url()) {
+                attempt.error("redirect to an internal address")
+            } else {
+                attempt.follow()
+            }
+        })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public_url() {
+        for url in ["https://example.com/", "http://1.1.1.1/", "https://[2606:4700:4700::1111]/"] {
+            assert!(is_public_url(&Url::parse(url).unwrap()), "{}", url);
+        }
+        for url in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.0.0.1/",
+            "http://127.0.0.1:8080/",
+            "http://[::1]/",
+            "http://[::ffff:192.168.1.1]/",
+            "ftp://example.com/",
+        ] {
+            assert!(!is_public_url(&Url::parse(url).unwrap()), "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_public_addresses_skip_internal() {
+        assert!(public_addresses(&Url::parse("http://127.0.0.1/").unwrap()).await.is_empty());
+        assert!(public_addresses(&Url::parse("http://[::1]/").unwrap()).await.is_empty());
+        assert_eq!(public_addresses(&Url::parse("http://1.1.1.1/").unwrap()).await, vec![
+            "1.1.1.1".parse::<IpAddr>().unwrap()
+        ]);
+    }
+}
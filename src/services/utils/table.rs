@@ -0,0 +1,240 @@
+//! Plain-text tables for service responses
+//!
+//! Services build their tables as a [`Table`] and render it with
+//! [`format_table`]. Because every table has the same shape (a header, a
+//! `-|-` separator and ` | ` separated rows), rendered tables can be read
+//! back from a response with [`map_tables`], which is how the `-SORT` and
+//! `-COUNT` modifiers reorder and summarize any service's table.
+
+use std::cmp::Ordering;
+use std::net::IpAddr;
+
+use crate::core::patch::strip_ansi_codes;
+
+/// Rows of cells under a header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: &[&str], rows: Vec<Vec<String>>) -> Self {
+        Self { header: header.iter().map(|title| title.to_string()).collect(), rows }
+    }
+
+    /// Column with the given title (case, spaces and punctuation ignored) or
+    /// 1-based number
+    pub fn column(&self, name: &str) -> Option<usize> {
+        if let Ok(number) = name.parse::<usize>() {
+            return number.checked_sub(1).filter(|&index| index < self.header.len());
+        }
+        let name = normalize(name);
+        self.header.iter().position(|title| normalize(title) == name)
+    }
+
+    /// Sort the rows by a column, numbers, addresses and prefixes by value
+    pub fn sort_by(&mut self, column: usize, descending: bool) {
+        self.rows.sort_by(|a, b| {
+            let ordering = compare_cells(&a[column], &b[column]);
+            if descending { ordering.reverse() } else { ordering }
+        });
+    }
+
+    /// Number of rows per distinct value of a column, most frequent first
+    pub fn count_by(&self, column: usize) -> Table {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for row in &self.rows {
+            match counts.iter_mut().find(|(value, _)| *value == row[column]) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((row[column].clone(), 1)),
+            }
+        }
+        counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| compare_cells(a, b)));
+
+        let rows = counts.into_iter().map(|(value, count)| vec![value, count.to_string()]).collect();
+        Table { header: vec![self.header[column].clone(), "Count".to_string()], rows }
+    }
+
+    /// Render the table with every column padded to its widest cell
+    pub fn render(&self) -> String {
+        let widths: Vec<usize> = (0..self.header.len())
+            .map(|i| self.rows.iter().map(|row| row[i].len()).chain([self.header[i].len()]).max().unwrap_or(0))
+            .collect();
+        let line = |cells: &[String]| {
+            let padded: Vec<String> = cells.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+            format!("{}\n", padded.join(" | ").trim_end())
+        };
+
+        let mut table = line(&self.header);
+        table.push_str(&format!("{}\n", widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().join("-|-")));
+        for row in &self.rows {
+            table.push_str(&line(row));
+        }
+        table
+    }
+}
+
+/// Table of columns padded to their widest cell
+pub fn format_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    Table::new(header, rows.to_vec()).render()
+}
+
+/// Underlined section title
+pub fn section(output: &mut String, title: &str) {
+    output.push_str(&format!("{}\n{}\n\n", title, "=".repeat(title.len())));
+}
+
+fn normalize(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+fn split_cells(line: &str) -> Vec<String> {
+    line.split('|').map(|cell| cell.trim().to_string()).collect()
+}
+
+fn is_separator(line: &str) -> bool {
+    line.contains('|') && line.contains('-') && line.trim_end().chars().all(|c| matches!(c, '-' | '|' | ' '))
+}
+
+/// Replace every table in a response with what `f` makes of it
+///
+/// Tables are read without their colors; lines outside tables are kept as
+/// they are, colors included. Returns `None` when the response contains no
+/// table.
+pub fn map_tables(response: &str, mut f: impl FnMut(Table) -> String) -> Option<String> {
+    let original: Vec<&str> = response.lines().collect();
+    let lines: Vec<String> = original.iter().map(|line| strip_ansi_codes(line)).collect();
+    let mut output = String::new();
+    let mut found = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let header = split_cells(&lines[i]);
+        if header.len() < 2 || !lines.get(i + 1).is_some_and(|line| is_separator(line)) {
+            output.push_str(original[i]);
+            output.push('\n');
+            i += 1;
+            continue;
+        }
+
+        // Rows run until a line with another number of cells
+        let mut rows = Vec::new();
+        i += 2;
+        while let Some(line) = lines.get(i) {
+            let cells = split_cells(line);
+            if !line.contains('|') || cells.len() != header.len() {
+                break;
+            }
+            rows.push(cells);
+            i += 1;
+        }
+        output.push_str(&f(Table { header, rows }));
+        found = true;
+    }
+    found.then_some(output)
+}
+
+/// Compare cells as numbers, then as addresses or prefixes, then naturally
+/// (`AS9` before `AS13335`)
+pub fn compare_cells(a: &str, b: &str) -> Ordering {
+    if let (Ok(a), Ok(b)) = (a.parse::<f64>(), b.parse::<f64>()) {
+        return a.total_cmp(&b);
+    }
+    if let (Some(a), Some(b)) = (parse_prefix(a), parse_prefix(b)) {
+        return a.cmp(&b);
+    }
+    natural_cmp(a, b)
+}
+
+fn parse_prefix(cell: &str) -> Option<(IpAddr, u8)> {
+    match cell.split_once('/') {
+        Some((address, length)) => Some((address.parse().ok()?, length.parse().ok()?)),
+        None => Some((cell.parse().ok()?, 0)),
+    }
+}
+
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let mut x_digits = String::new();
+                while let Some(c) = a.next_if(char::is_ascii_digit) {
+                    x_digits.push(c);
+                }
+                let mut y_digits = String::new();
+                while let Some(c) = b.next_if(char::is_ascii_digit) {
+                    y_digits.push(c);
+                }
+                let x_digits = x_digits.trim_start_matches('0');
+                let y_digits = y_digits.trim_start_matches('0');
+                let ordering = x_digits.len().cmp(&y_digits.len()).then_with(|| x_digits.cmp(y_digits));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> String {
+        let rows = [("2a06:98c0::/29", "US"), ("104.16.0.0/13", "US"), ("1.1.1.0/24", "AU")]
+            .iter()
+            .map(|(prefix, country)| vec![prefix.to_string(), country.to_string(), "CLOUDFLARENET".to_string()])
+            .collect::<Vec<_>>();
+        format!(
+            "% ASN Announced Prefixes Query\n\n{}\n% Total announced prefixes: 3\n",
+            format_table(&["Prefix", "Country", "AS Name"], &rows)
+        )
+    }
+
+    #[test]
+    fn test_map_tables_round_trip() {
+        let response = response();
+        assert_eq!(map_tables(&response, |table| table.render()).unwrap(), response);
+        assert_eq!(map_tables("% no table here\na | b\n", |table| table.render()), None);
+
+        // Colors are kept outside the tables
+        let comment = response().replacen("% ASN", "\x1b[93m% ASN", 1);
+        let colored = comment.replacen("Prefix ", "\x1b[96mPrefix\x1b[0m ", 1);
+        assert_eq!(map_tables(&colored, |table| table.render()).unwrap(), comment);
+    }
+
+    #[test]
+    fn test_sort_and_count() {
+        let sorted = map_tables(&response(), |mut table| {
+            let column = table.column("prefix").unwrap();
+            table.sort_by(column, false);
+            table.render()
+        })
+        .unwrap();
+        let prefixes: Vec<&str> = sorted.lines().skip(4).take(3).map(|line| line.split(' ').next().unwrap()).collect();
+        assert_eq!(prefixes, ["1.1.1.0/24", "104.16.0.0/13", "2a06:98c0::/29"]);
+
+        let counted = map_tables(&response(), |table| table.count_by(table.column("country").unwrap()).render()).unwrap();
+        assert!(counted.contains("Country | Count\n--------|------\nUS      | 2\nAU      | 1\n"));
+
+        let table = Table::new(&["AS Name", "ASN"], Vec::new());
+        assert_eq!(table.column("as_name"), Some(0));
+        assert_eq!(table.column("2"), Some(1));
+        assert_eq!(table.column("3"), None);
+        assert_eq!(compare_cells("AS9", "AS13335"), Ordering::Less);
+        assert_eq!(compare_cells("9.5", "10"), Ordering::Less);
+    }
+}
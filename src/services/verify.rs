@@ -0,0 +1,454 @@
+// WHOIS Server - Resource Ownership Verification
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Ownership verification of ASNs and prefixes (`-VERIFY`)
+//!
+//! `AS213605-VERIFY` issues a challenge token for the resource. The holder
+//! proves control by publishing `whois-verify=<token>` either as a remark in
+//! the RPSL object of the resource (aut-num, inetnum/inet6num or route) or,
+//! for prefixes, as a TXT record at `_whois-verify.<reverse zone>`. Running
+//! the same query again checks both and, once the token is found, records
+//! the resource as verified in LMDB. Responses about a verified resource
+//! then carry a `% verified:` line, and trust-gated features can consult
+//! [`verification_for`] instead of requiring an account.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use cidr::IpCidr;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::VERIFY_LMDB_PATH;
+use crate::core::QueryType;
+use crate::core::query::{is_private_asn, parse_asn};
+use crate::core::request_context::RequestContext;
+use crate::services::registry::{QueryService, ServiceHelp, ServiceResult};
+use crate::services::utils::doh::DohClient;
+use crate::services::whois::query_with_iana_referral;
+use crate::storage::lmdb::LmdbStorage;
+use crate::{log_debug, log_info, log_warn};
+
+/// Prefix of the token in RPSL remarks and TXT records
+const TOKEN_PREFIX: &str = "whois-verify=";
+/// Label under the reverse zone holding the TXT record
+const DNS_LABEL: &str = "_whois-verify";
+/// How long an issued token can be published
+const CHALLENGE_TTL_SECS: u64 = 7 * 24 * 3600;
+/// Verifications must be renewed once a year
+const VERIFICATION_TTL_SECS: u64 = 365 * 24 * 3600;
+const TYPE_TXT: u32 = 16;
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time should be after Unix epoch")
+        .as_secs()
+}
+
+fn format_date(secs: u64) -> String {
+    DateTime::from_timestamp(secs as i64, 0)
+        .map(|time| time.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| secs.to_string())
+}
+
+/// A resource that can be verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resource {
+    Asn(u32),
+    Prefix(IpCidr),
+}
+
+impl Resource {
+    /// Parse an AS number or a prefix in CIDR notation
+    fn parse(target: &str) -> Option<Self> {
+        let target = target.trim();
+        if let Some(asn) = parse_asn(target) {
+            return Some(Resource::Asn(asn));
+        }
+        // Host addresses are not resources; require an explicit prefix length
+        if !target.contains('/') {
+            return None;
+        }
+        target.parse::<IpCidr>().ok().map(Resource::Prefix)
+    }
+
+    /// Canonical form used as storage key and in responses
+    fn key(&self) -> String {
+        match self {
+            Resource::Asn(asn) => format!("AS{}", asn),
+            Resource::Prefix(prefix) => prefix.to_string(),
+        }
+    }
+
+    /// Object type carrying the remark
+    fn object_type(&self) -> &'static str {
+        match self {
+            Resource::Asn(_) => "aut-num",
+            Resource::Prefix(IpCidr::V4(_)) => "inetnum or route",
+            Resource::Prefix(IpCidr::V6(_)) => "inet6num or route6",
+        }
+    }
+
+    /// Name of the TXT record proving control of the reverse zone
+    fn dns_name(&self) -> Option<String> {
+        match self {
+            Resource::Asn(_) => None,
+            Resource::Prefix(prefix) => Some(format!("{}.{}", DNS_LABEL, reverse_zone(prefix))),
+        }
+    }
+}
+
+/// Reverse DNS zone delegated for a prefix
+///
+/// IPv4 zones are cut at octet boundaries (at most three octets, as longer
+/// prefixes are classless delegations of the /24), IPv6 zones at nibbles.
+fn reverse_zone(prefix: &IpCidr) -> String {
+    let length = prefix.network_length() as usize;
+    match prefix.first_address() {
+        IpAddr::V4(addr) => {
+            let octets = length.div_ceil(8).clamp(1, 3);
+            let labels: Vec<String> = addr.octets()[..octets].iter().rev().map(u8::to_string).collect();
+            format!("{}.in-addr.arpa", labels.join("."))
+        }
+        IpAddr::V6(addr) => {
+            let nibbles = length.div_ceil(4).clamp(1, 32);
+            let labels: Vec<String> = addr
+                .octets()
+                .iter()
+                .flat_map(|byte| [byte >> 4, byte & 0x0f])
+                .take(nibbles)
+                .map(|nibble| format!("{:x}", nibble))
+                .collect();
+            let reversed: Vec<&str> = labels.iter().rev().map(String::as_str).collect();
+            format!("{}.ip6.arpa", reversed.join("."))
+        }
+    }
+}
+
+/// Whether `text` contains the published token
+fn contains_token(text: &str, token: &str) -> bool {
+    let expected = format!("{}{}", TOKEN_PREFIX, token);
+    text.lines().any(|line| {
+        line.split(|c: char| c.is_whitespace() || c == '"').any(|word| word.eq_ignore_ascii_case(&expected))
+    })
+}
+
+/// A pending challenge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Challenge {
+    token: String,
+    created_at: u64,
+}
+
+impl Challenge {
+    fn is_expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.created_at) > CHALLENGE_TTL_SECS
+    }
+}
+
+/// A resource whose holder proved control
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verification {
+    pub resource: String,
+    /// `rpsl` or `dns`
+    pub method: String,
+    pub verified_at: u64,
+}
+
+impl Verification {
+    fn is_expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.verified_at) > VERIFICATION_TTL_SECS
+    }
+
+    fn expires_at(&self) -> u64 {
+        self.verified_at + VERIFICATION_TTL_SECS
+    }
+}
+
+/// Challenges and verifications stored in LMDB
+struct VerificationStore {
+    storage: LmdbStorage,
+}
+
+impl VerificationStore {
+    fn new() -> Result<Self> {
+        Ok(Self { storage: LmdbStorage::new(VERIFY_LMDB_PATH)? })
+    }
+
+    fn verification(&self, resource: &str) -> Option<Verification> {
+        let verification: Verification = self.storage.get_json(&format!("verified:{}", resource)).ok()??;
+        (!verification.is_expired(now_secs())).then_some(verification)
+    }
+
+    /// The pending challenge for a resource, `None` if there is none or it expired
+    fn challenge(&self, resource: &str) -> Option<Challenge> {
+        let challenge: Challenge = self.storage.get_json(&format!("challenge:{}", resource)).ok()??;
+        (!challenge.is_expired(now_secs())).then_some(challenge)
+    }
+
+    fn issue_challenge(&self, resource: &str) -> Result<Challenge> {
+        let challenge = Challenge {
+            token: uuid::Uuid::new_v4().simple().to_string(),
+            created_at: now_secs(),
+        };
+        self.storage.put_json(&format!("challenge:{}", resource), &challenge)?;
+        Ok(challenge)
+    }
+
+    fn record_verification(&self, resource: &str, method: &str) -> Result<Verification> {
+        let verification = Verification {
+            resource: resource.to_string(),
+            method: method.to_string(),
+            verified_at: now_secs(),
+        };
+        self.storage.put_json(&format!("verified:{}", resource), &verification)?;
+        self.storage.delete(&format!("challenge:{}", resource))?;
+        Ok(verification)
+    }
+
+    /// Most specific verified prefix containing `ip`
+    fn covering_prefix(&self, ip: IpAddr) -> Option<Verification> {
+        let now = now_secs();
+        self.storage
+            .get_keys_with_prefix("verified:")
+            .ok()?
+            .iter()
+            .filter_map(|key| {
+                let prefix = key.strip_prefix("verified:")?.parse::<IpCidr>().ok()?;
+                prefix.contains(&ip).then_some((prefix, key))
+            })
+            .filter_map(|(prefix, key)| {
+                let verification: Verification = self.storage.get_json(key).ok()??;
+                (!verification.is_expired(now)).then_some((prefix.network_length(), verification))
+            })
+            .max_by_key(|(length, _)| *length)
+            .map(|(_, verification)| verification)
+    }
+}
+
+/// Verification covering the resource a query is about
+///
+/// AS numbers match exactly; addresses match the most specific verified
+/// prefix containing them.
+pub fn verification_for(query_type: &QueryType) -> Option<Verification> {
+    let relevant = matches!(query_type, QueryType::ASN(_) | QueryType::IPv4(_) | QueryType::IPv6(_));
+    // Nothing was ever verified until the first -VERIFY query creates the database
+    if !relevant || !Path::new(VERIFY_LMDB_PATH).exists() {
+        return None;
+    }
+    let store = VerificationStore::new().ok()?;
+    match query_type {
+        QueryType::ASN(asn) => store.verification(&Resource::Asn(parse_asn(asn)?).key()),
+        QueryType::IPv4(ip) => store.covering_prefix(IpAddr::V4(*ip)),
+        QueryType::IPv6(ip) => store.covering_prefix(IpAddr::V6(*ip)),
+        _ => None,
+    }
+}
+
+/// Append the `% verified:` line to a response about a verified resource
+pub fn append_verification_badge(response: String, query_type: &QueryType) -> String {
+    match verification_for(query_type) {
+        Some(verification) => format!("{}\n{}", response.trim_end_matches('\n'), badge_line(&verification)),
+        None => response,
+    }
+}
+
+fn badge_line(verification: &Verification) -> String {
+    format!(
+        "% verified: {} (holder proved control via {} on {})\n",
+        verification.resource,
+        verification.method,
+        format_date(verification.verified_at)
+    )
+}
+
+/// Look for the token in the RPSL object of the resource
+async fn check_rpsl(resource: &Resource, token: &str) -> bool {
+    match query_with_iana_referral(&resource.key()).await {
+        Ok(response) => contains_token(&response, token),
+        Err(e) => {
+            log_debug!("RPSL lookup for {} failed: {}", resource.key(), e);
+            false
+        }
+    }
+}
+
+/// Look for the token in the TXT record under the reverse zone
+async fn check_dns(name: &str, token: &str) -> bool {
+    match DohClient::new().query(name, "TXT").await {
+        Ok(response) => response.Answer
+            .iter()
+            .flatten()
+            .any(|answer| answer.record_type == TYPE_TXT && contains_token(&answer.data, token)),
+        Err(e) => {
+            log_debug!("TXT lookup for {} failed: {}", name, e);
+            false
+        }
+    }
+}
+
+fn format_instructions(resource: &Resource, challenge: &Challenge) -> String {
+    let key = resource.key();
+    let mut output = String::new();
+    output.push_str(&format!("token:          {}{}\n", TOKEN_PREFIX, challenge.token));
+    output.push_str(&format!("expires:        {}\n", format_date(challenge.created_at + CHALLENGE_TTL_SECS)));
+    output.push_str(&format!(
+        "method:         rpsl (add \"remarks: {}{}\" to the {} object of {})\n",
+        TOKEN_PREFIX,
+        challenge.token,
+        resource.object_type(),
+        key
+    ));
+    if let Some(name) = resource.dns_name() {
+        output.push_str(&format!("method:         dns (publish TXT \"{}{}\" at {})\n", TOKEN_PREFIX, challenge.token, name));
+    }
+    output.push_str(&format!("%\n% Query {}-VERIFY again once the token is published.\n", key));
+    output
+}
+
+/// Issue a challenge, or check a pending one
+pub async fn process_verify_query(ctx: &RequestContext, target: &str) -> Result<String> {
+    let Some(resource) = Resource::parse(target) else {
+        return Ok(format!(
+            "% Cannot verify '{}': expected an AS number or a prefix such as 192.0.2.0/24\n",
+            target.trim()
+        ));
+    };
+    if let Resource::Asn(asn) = resource
+        && is_private_asn(asn)
+    {
+        return Ok(format!("% Cannot verify {}: private AS numbers have no RIR object\n", resource.key()));
+    }
+
+    let key = resource.key();
+    let store = VerificationStore::new()?;
+    let mut output = format!("% Ownership verification for {}\n%\n", key);
+
+    if let Some(verification) = store.verification(&key) {
+        output.push_str(&format!("resource:       {}\n", key));
+        output.push_str("status:         verified\n");
+        output.push_str(&format!("method:         {}\n", verification.method));
+        output.push_str(&format!("verified:       {}\n", format_date(verification.verified_at)));
+        output.push_str(&format!("renew-by:       {}\n", format_date(verification.expires_at())));
+        return Ok(output);
+    }
+
+    let Some(challenge) = store.challenge(&key) else {
+        let challenge = store.issue_challenge(&key)?;
+        log_info!("[{}] Issued verification challenge for {}", ctx, key);
+        output.push_str(&format!("resource:       {}\n", key));
+        output.push_str("status:         challenge issued\n");
+        output.push_str(&format_instructions(&resource, &challenge));
+        return Ok(output);
+    };
+
+    let dns_name = resource.dns_name();
+    let (rpsl, dns) = tokio::join!(check_rpsl(&resource, &challenge.token), async {
+        match &dns_name {
+            Some(name) => check_dns(name, &challenge.token).await,
+            None => false,
+        }
+    });
+    let method = match (rpsl, dns) {
+        (true, _) => Some("rpsl"),
+        (false, true) => Some("dns"),
+        (false, false) => None,
+    };
+
+    output.push_str(&format!("resource:       {}\n", key));
+    match method {
+        Some(method) => {
+            let verification = store.record_verification(&key, method)?;
+            log_info!("[{}] Verified {} via {}", ctx, key, method);
+            output.push_str("status:         verified\n");
+            output.push_str(&format!("method:         {}\n", method));
+            output.push_str(&format!("renew-by:       {}\n", format_date(verification.expires_at())));
+        }
+        None => {
+            log_warn!("[{}] Verification token for {} not found yet", ctx, key);
+            output.push_str("status:         pending (token not found)\n");
+            output.push_str(&format_instructions(&resource, &challenge));
+        }
+    }
+    Ok(output)
+}
+
+/// Ownership verification service
+pub struct VerifyService;
+
+#[async_trait]
+impl QueryService for VerifyService {
+    fn name(&self) -> &'static str {
+        "verify"
+    }
+
+    fn suffixes(&self) -> &'static [&'static str] {
+        &["-VERIFY"]
+    }
+
+    fn help(&self) -> ServiceHelp {
+        ServiceHelp {
+            category: "SECURITY INVESTIGATION",
+            summary: "Prove control of an ASN or prefix (RPSL remark or DNS TXT token)",
+            example: "AS213605-VERIFY",
+        }
+    }
+
+    fn upstream(&self) -> &'static str {
+        "RIR WHOIS and reverse DNS (DoH)"
+    }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        // Every call issues or checks a challenge
+        None
+    }
+
+    async fn handle(&self, ctx: &RequestContext, target: &str) -> ServiceResult {
+        process_verify_query(ctx, target).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resource() {
+        assert_eq!(Resource::parse("as213605").unwrap().key(), "AS213605");
+        assert_eq!(Resource::parse("AS1.10").unwrap().key(), "AS65546");
+        assert_eq!(Resource::parse("192.0.2.0/24").unwrap().key(), "192.0.2.0/24");
+        assert_eq!(Resource::parse("2001:db8::/32").unwrap().key(), "2001:db8::/32");
+        assert!(Resource::parse("192.0.2.1").is_none());
+        assert!(Resource::parse("192.0.2.1/24").is_none());
+        assert!(Resource::parse("example.com").is_none());
+    }
+
+    #[test]
+    fn test_reverse_zone() {
+        let zone = |prefix: &str| reverse_zone(&prefix.parse().unwrap());
+        assert_eq!(zone("192.0.2.0/24"), "2.0.192.in-addr.arpa");
+        assert_eq!(zone("198.51.0.0/16"), "51.198.in-addr.arpa");
+        assert_eq!(zone("203.0.113.128/25"), "113.0.203.in-addr.arpa");
+        assert_eq!(zone("10.0.0.0/8"), "10.in-addr.arpa");
+        assert_eq!(zone("2001:db8::/32"), "8.b.d.0.1.0.0.2.ip6.arpa");
+        assert_eq!(zone("2001:db8:1200::/40"), "2.1.8.b.d.0.1.0.0.2.ip6.arpa");
+        assert_eq!(
+            Resource::parse("192.0.2.0/24").unwrap().dns_name().as_deref(),
+            Some("_whois-verify.2.0.192.in-addr.arpa")
+        );
+        assert!(Resource::parse("AS13335").unwrap().dns_name().is_none());
+    }
+
+    #[test]
+    fn test_contains_token() {
+        let object = "aut-num:        AS213605\nremarks:        whois-verify=0123abcd\nsource:         RIPE\n";
+        assert!(contains_token(object, "0123abcd"));
+        assert!(contains_token("\"whois-verify=0123abcd\"", "0123abcd"));
+        assert!(!contains_token(object, "0123"));
+        assert!(!contains_token("remarks: whois-verify=0123abcdef", "0123abcd"));
+    }
+}
@@ -1,20 +1,26 @@
 #![allow(non_snake_case)]
 
 use anyhow::Result;
-use std::time::Duration;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream as AsyncTcpStream;
 use crate::config::{
     DEFAULT_WHOIS_PORT, DEFAULT_WHOIS_SERVER, RADB_WHOIS_PORT, RADB_WHOIS_SERVER, RIPE_WHOIS_PORT, RIPE_WHOIS_SERVER, TIMEOUT_SECONDS,
 };
+use crate::core::provenance::record_source;
+use crate::core::registry_flags::RegistryFlags;
 use crate::services::iana_cache::IanaCache;
+use crate::services::rdap::{is_rate_limited, rdap_fallback};
+use crate::services::tld_bootstrap::{TldRoute, tld_route};
 
 use crate::{log_debug, log_warn};
 
 /// Prepare a query with the --no-referenced flag for RIPE NCC WHOIS server
 /// This flag prevents retrieval of personal data sets to comply with RIPE AUP
 fn prepare_ripe_query(query: &str, server: &str) -> String {
-    if server == RIPE_WHOIS_SERVER {
+    let has_flag = query.split_whitespace().any(|token| token == "-r" || token == "--no-referenced");
+    if server == RIPE_WHOIS_SERVER && !has_flag {
         format!("{} --no-referenced", query)
     } else {
         query.to_string()
@@ -27,10 +33,23 @@ pub async fn query_with_iana_referral(query: &str) -> Result<String> {
     let iana_cache = IanaCache::new()?;
     let whois_server = match iana_cache.get_whois_server(query).await {
         Some(server) => server,
-        None => {
-            log_debug!("No IANA referral found for {}, using default server", query);
-            DEFAULT_WHOIS_SERVER.to_string()
-        }
+        None => match tld_route(query) {
+            Some(TldRoute::NotDelegated(tld)) => {
+                return Err(anyhow::anyhow!(".{} is not a top-level domain delegated in the root zone", tld));
+            }
+            Some(TldRoute::Whois(whois)) => {
+                log_debug!("No IANA WHOIS server for {}, trying RDAP, then {}", query, whois);
+                match rdap_fallback(query, &whois, "no WHOIS server listed at IANA").await {
+                    Ok(rdap_response) => return Ok(rdap_response),
+                    Err(e) => log_debug!("RDAP lookup failed for {}: {}", query, e),
+                }
+                whois
+            }
+            None => {
+                log_debug!("No IANA referral found for {}, using default server", query);
+                DEFAULT_WHOIS_SERVER.to_string()
+            }
+        },
     };
 
     log_debug!("Using WHOIS server: {}", whois_server);
@@ -39,6 +58,15 @@ pub async fn query_with_iana_referral(query: &str) -> Result<String> {
     let prepared_query = prepare_ripe_query(query, &whois_server);
     match query_whois(&prepared_query, &whois_server, DEFAULT_WHOIS_PORT).await {
         Ok(response) => {
+            // A rate-limited server has no data for us, answer from RDAP instead
+            if is_rate_limited(&response) {
+                log_warn!("WHOIS server {} is rate limiting, trying RDAP for: {}", whois_server, query);
+                match rdap_fallback(query, &whois_server, "rate limited").await {
+                    Ok(rdap_response) => return Ok(rdap_response),
+                    Err(e) => log_debug!("RDAP fallback failed for {}: {}", query, e),
+                }
+            }
+
             // Check if response indicates transferred/no data and try RADB fallback
             if should_try_radb_fallback(&response, query) {
                 log_debug!(
@@ -84,6 +112,12 @@ pub async fn query_with_iana_referral(query: &str) -> Result<String> {
                 whois_server, e
             );
 
+            // The registry's RDAP service usually still answers when port 43 is down
+            match rdap_fallback(query, &whois_server, "unreachable").await {
+                Ok(rdap_response) => return Ok(rdap_response),
+                Err(rdap_error) => log_debug!("RDAP fallback failed for {}: {}", query, rdap_error),
+            }
+
             // Query failed, try to refresh IANA cache
             if let Some(refreshed_server) = iana_cache.refresh_cache_on_failure(query).await {
                 log_debug!("Retrying with refreshed server: {}", refreshed_server);
@@ -123,7 +157,109 @@ pub async fn query_with_iana_referral(query: &str) -> Result<String> {
     }
 }
 
+/// Query with registry flags (`AS3333 -B`) passed to the referred server
+///
+/// The flags are translated for the server's query syntax; flags it does
+/// not understand are left out and listed in a comment. Flagged queries go
+/// to the referred server only, without the RDAP and RADB fallbacks, whose
+/// answers would silently ignore the flags.
+pub async fn query_with_registry_flags(query: &str, flags: &RegistryFlags) -> Result<String> {
+    if flags.is_empty() {
+        return query_with_iana_referral(query).await;
+    }
+
+    let iana_cache = IanaCache::new()?;
+    let whois_server = iana_cache
+        .get_whois_server(query).await
+        .unwrap_or_else(|| DEFAULT_WHOIS_SERVER.to_string());
+    let (upstream_query, ignored) = flags.upstream_query(query, &whois_server);
+    log_debug!("Querying {} with registry flags: {}", whois_server, upstream_query);
+
+    let response = query_whois(&prepare_ripe_query(&upstream_query, &whois_server), &whois_server, DEFAULT_WHOIS_PORT).await?;
+    let mut output = format!("% Sent to {}: {}\n", whois_server, upstream_query);
+    if !ignored.is_empty() {
+        output.push_str(&format!("% Not supported by {}, not sent: {}\n", whois_server, ignored.join(" ")));
+    }
+    output.push('\n');
+    output.push_str(&response);
+    Ok(output)
+}
+
+/// Referrals chased after the registry's answer (registrar, then a reseller)
+const MAX_REFERRAL_HOPS: usize = 2;
+
+/// WHOIS server a registry response refers to for registrar data
+fn referral_server(response: &str) -> Option<String> {
+    response.lines().find_map(|line| {
+        let (name, value) = line.trim().split_once(':')?;
+        let name = name.trim();
+        if !name.eq_ignore_ascii_case("Registrar WHOIS Server") && !name.eq_ignore_ascii_case("Whois Server") {
+            return None;
+        }
+        let server = value.trim().trim_start_matches("whois://").trim_end_matches('/').to_lowercase();
+        let valid = server.contains('.') &&
+            server.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+        valid.then_some(server)
+    })
+}
+
+/// Query a domain at its registry and, unless `follow` is off, at the
+/// registrar WHOIS server a thin registry (e.g. Verisign for .com/.net)
+/// refers to
+///
+/// Both sections are returned; a server is never asked twice, so registrars
+/// referring to themselves or each other do not loop.
+pub async fn query_domain_with_referrals(domain: &str, follow: bool) -> Result<String> {
+    let registry_response = query_with_iana_referral(domain).await?;
+    if !follow {
+        return Ok(registry_response);
+    }
+
+    let mut visited = HashSet::new();
+    if let Ok(iana_cache) = IanaCache::new()
+        && let Some(server) = iana_cache.get_whois_server(domain).await
+    {
+        visited.insert(server.to_lowercase());
+    }
+
+    let mut output = registry_response.clone();
+    let mut last_response = registry_response;
+    for _ in 0..MAX_REFERRAL_HOPS {
+        let Some(server) = referral_server(&last_response) else {
+            break;
+        };
+        if !visited.insert(server.clone()) {
+            log_debug!("Not following referral for {} back to {}", domain, server);
+            break;
+        }
+
+        log_debug!("Following registrar referral for {} to {}", domain, server);
+        output = format!("{}\n\n% Registrar WHOIS server: {}\n\n", output.trim_end(), server);
+        match query_whois(domain, &server, DEFAULT_WHOIS_PORT).await {
+            Ok(response) => {
+                output.push_str(response.trim());
+                output.push('\n');
+                last_response = response;
+            }
+            Err(e) => {
+                log_warn!("Registrar referral for {} to {} failed: {}", domain, server, e);
+                output.push_str(&format!("% Referral failed: {}\n", e));
+                break;
+            }
+        }
+    }
+    Ok(output)
+}
+
 pub async fn query_whois(query: &str, server: &str, port: u16) -> Result<String> {
+    let start = Instant::now();
+    let result = fetch_whois(query, server, port).await;
+    let status = if result.is_ok() { "cache miss" } else { "failed" };
+    record_source("upstream", server, start.elapsed(), status);
+    result
+}
+
+async fn fetch_whois(query: &str, server: &str, port: u16) -> Result<String> {
     let address = format!("{}:{}", server, port);
     log_debug!("Querying WHOIS server: {}", address);
 
@@ -323,3 +459,18 @@ pub async fn query_ripe_whois(query: &str) -> Result<String> {
     let prepared_query = prepare_ripe_query(query, RIPE_WHOIS_SERVER);
     query_whois(&prepared_query, RIPE_WHOIS_SERVER, RIPE_WHOIS_PORT).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_referral_server() {
+        let registry = "   Domain Name: EXAMPLE.COM\r\n   Registrar WHOIS Server: whois.markmonitor.com\r\n";
+        assert_eq!(referral_server(registry).as_deref(), Some("whois.markmonitor.com"));
+        assert_eq!(referral_server("Whois Server: whois://WHOIS.Example.net/\n").as_deref(), Some("whois.example.net"));
+        assert_eq!(referral_server("Registrar WHOIS Server: \n"), None);
+        assert_eq!(referral_server("Registrar WHOIS Server: http://www.example.net/whois\n"), None);
+        assert_eq!(referral_server("aut-num: AS13335\n"), None);
+    }
+}
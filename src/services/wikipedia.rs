@@ -20,7 +20,12 @@ use anyhow::Result;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use crate::core::body_limit::LimitedBody;
+use crate::services::upstream::api_base;
 use crate::{log_debug, log_error};
+
+const WIKIPEDIA_API_URL: &str = "https://en.wikipedia.org/w/api.php";
+
 /// Wikipedia API response structures
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WikipediaResponse {
@@ -107,7 +112,7 @@ impl WikipediaService {
             .build()
             .unwrap_or_else(|_| reqwest::Client::new());
 
-        let base_url = "https://en.wikipedia.org/w/api.php".to_string();
+        let base_url = api_base(WIKIPEDIA_API_URL).to_string();
 
         Self { client, base_url }
     }
@@ -168,7 +173,7 @@ impl WikipediaService {
 
         if !status.is_success() {
             let error_text = response
-                .text()
+                .limited_text()
                 .await
                 .unwrap_or_else(|_| "Unable to read error response".to_string());
             log_debug!("Wikipedia search error response: {}", error_text);
@@ -179,7 +184,7 @@ impl WikipediaService {
             ));
         }
 
-        let response_text = response.text().await?;
+        let response_text = response.limited_text().await?;
         log_debug!(
             "Wikipedia search response body: {}",
             &response_text[..std::cmp::min(500, response_text.len())]
@@ -235,7 +240,7 @@ impl WikipediaService {
 
         if !status.is_success() {
             let error_text = response
-                .text()
+                .limited_text()
                 .await
                 .unwrap_or_else(|_| "Unable to read error response".to_string());
             log_debug!("Wikipedia details error response: {}", error_text);
@@ -246,7 +251,7 @@ impl WikipediaService {
             ));
         }
 
-        let response_text = response.text().await?;
+        let response_text = response.limited_text().await?;
         log_debug!(
             "Wikipedia details response body: {}",
             &response_text[..std::cmp::min(500, response_text.len())]
@@ -490,6 +495,7 @@ pub async fn process_wikipedia_query(query: &str) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::fixtures::FixtureServer;
 
     #[test]
     fn test_wikipedia_query_detection() {
@@ -540,4 +546,25 @@ mod tests {
         // Just test that creation doesn't panic
         assert_eq!(service.base_url, "https://en.wikipedia.org/w/api.php");
     }
+
+    #[tokio::test]
+    async fn test_wikipedia_fixtures() {
+        let mut fixtures = FixtureServer::start("wikipedia").await;
+        let search = "?action=query&format=json&list=search&srsearch=Rust&srlimit=5&srnamespace=0\
+                      &srprop=size%7Cwordcount%7Ctimestamp%7Csnippet&utf8=1";
+        let details = "?action=query&format=json&titles=Rust%20(programming%20language)\
+                       &prop=extracts%7Cinfo%7Ccategories%7Clanglinks&exintro=1&explaintext=1&exsectionformat=plain\
+                       &exlimit=1&inprop=url%7Clength%7Ctouched&cllimit=10&lllimit=10&utf8=1";
+        fixtures.serve(WIKIPEDIA_API_URL, search, "search_rust.json").await;
+        fixtures.serve(WIKIPEDIA_API_URL, details, "details_rust.json").await;
+
+        let response = fixtures.run(process_wikipedia_query("Rust-WIKIPEDIA")).await.unwrap();
+        assert!(response.starts_with("Wikipedia Article Information: Rust (programming language)\n"));
+        assert!(response.contains("page-id: 29414838\n"));
+        assert!(response.contains("article-length: 98211 bytes\n"));
+        assert!(response.contains("last-modified: 2024-09-06T08:12:40Z\n"));
+        assert!(response.contains("summary: Rust is a general-purpose programming language"));
+        assert!(response.contains("categories: Concurrent programming languages, Systems programming languages\n"));
+        assert!(response.contains("wikipedia-url: https://en.wikipedia.org/wiki/Rust_(programming_language)\n"));
+    }
 }
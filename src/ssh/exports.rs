@@ -0,0 +1,251 @@
+// WHOIS Server - SSH Result Exports
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Saving query results to files in the SSH frontend
+//!
+//! In an SSH shell session a query can be redirected to a file with
+//! `example.com-SSL > report.txt` (or `>>` to append). Files are written to
+//! a per-client export directory and can be downloaded over SFTP, e.g.
+//! `sftp -P 2222 whois@whois.akae.re:report.txt` or `scp` with OpenSSH 9+
+//! (which uses SFTP).
+//!
+//! Clients authenticated with a public key get a directory keyed by the key
+//! fingerprint; password clients share one directory per IP address. Each
+//! directory is limited to a quota, and files expire after a configurable
+//! time.
+
+use anyhow::{Result, anyhow};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::log_debug;
+
+/// Longest allowed export file name
+const MAX_FILENAME_LEN: usize = 64;
+
+/// Most files kept in one export directory
+pub const MAX_EXPORT_FILES: usize = 100;
+
+/// A query with its output redirected to a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub query: String,
+    pub filename: String,
+    /// `>>`: append instead of overwriting
+    pub append: bool,
+}
+
+/// Split a `<query> > <file>` command
+///
+/// Returns `Ok(None)` when the command has no redirection and an error when
+/// the redirection is malformed.
+pub fn parse_redirect(command: &str) -> Result<Option<Redirect>> {
+    let Some(pos) = command.find('>') else {
+        return Ok(None);
+    };
+
+    let query = command[..pos].trim();
+    let (target, append) = match command[pos + 1..].strip_prefix('>') {
+        Some(rest) => (rest, true),
+        None => (&command[pos + 1..], false),
+    };
+    let filename = target.trim();
+
+    if query.is_empty() {
+        return Err(anyhow!("No query before '>'"));
+    }
+    if !valid_filename(filename) {
+        return Err(anyhow!(
+            "Invalid file name '{}' (letters, digits, '.', '_' and '-', up to {} characters)",
+            filename,
+            MAX_FILENAME_LEN
+        ));
+    }
+
+    Ok(Some(Redirect { query: query.to_string(), filename: filename.to_string(), append }))
+}
+
+/// Whether `name` is a plain file name that stays inside the export directory
+pub fn valid_filename(name: &str) -> bool {
+    !name.is_empty() &&
+        name.len() <= MAX_FILENAME_LEN &&
+        !name.starts_with('.') &&
+        name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Export directory name for a client
+///
+/// Public key clients are identified by their key fingerprint, everyone else
+/// by address.
+pub fn client_identity(key_fingerprint: Option<&str>, addr: Option<IpAddr>) -> String {
+    match (key_fingerprint, addr) {
+        (Some(fingerprint), _) => {
+            let safe: String = fingerprint
+                .chars()
+                .filter_map(|c| match c {
+                    '+' => Some('-'),
+                    '/' => Some('_'),
+                    '=' => None,
+                    c if c.is_ascii_alphanumeric() => Some(c),
+                    _ => None,
+                })
+                .collect();
+            format!("key-{}", safe)
+        }
+        (None, Some(addr)) => format!("ip-{}", addr.to_string().replace(':', "_")),
+        (None, None) => "anonymous".to_string(),
+    }
+}
+
+/// Per-client export directories below the SSH cache directory
+#[derive(Debug, Clone)]
+pub struct ExportStore {
+    root: PathBuf,
+    quota_bytes: u64,
+    ttl: Duration,
+}
+
+impl ExportStore {
+    pub fn new(root: impl Into<PathBuf>, quota_bytes: u64, ttl: Duration) -> Self {
+        Self { root: root.into(), quota_bytes, ttl }
+    }
+
+    pub fn quota_bytes(&self) -> u64 {
+        self.quota_bytes
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Export directory of a client (not created)
+    pub fn client_dir(&self, identity: &str) -> PathBuf {
+        self.root.join(identity)
+    }
+
+    /// Write `content` to a file in the client's export directory
+    ///
+    /// Returns the new size of the file. Fails without writing when the
+    /// directory would exceed its quota or file limit.
+    pub fn save(&self, identity: &str, redirect: &Redirect, content: &str) -> Result<u64> {
+        let dir = self.client_dir(identity);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(&redirect.filename);
+
+        let files = list_files(&dir)?;
+        let existing = files.iter().find(|(name, _)| *name == redirect.filename).map(|(_, size)| *size);
+        let used: u64 = files.iter().map(|(_, size)| size).sum();
+        let new_size = if redirect.append { existing.unwrap_or(0) } else { 0 } + content.len() as u64;
+
+        if existing.is_none() && files.len() >= MAX_EXPORT_FILES {
+            return Err(anyhow!("Too many saved files (limit {}), remove some over SFTP first", MAX_EXPORT_FILES));
+        }
+        if used - existing.unwrap_or(0) + new_size > self.quota_bytes {
+            return Err(anyhow!(
+                "Export quota exceeded ({} of {} bytes used), remove some files over SFTP first",
+                used,
+                self.quota_bytes
+            ));
+        }
+
+        if redirect.append {
+            use std::io::Write;
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+            file.write_all(content.as_bytes())?;
+        } else {
+            std::fs::write(&path, content)?;
+        }
+        log_debug!("Saved SSH export {} ({} bytes)", path.display(), new_size);
+        Ok(new_size)
+    }
+
+    /// Delete files older than the expiry time and empty client directories
+    ///
+    /// Returns the number of deleted files.
+    pub fn purge_expired(&self) -> Result<usize> {
+        if !self.root.exists() {
+            return Ok(0);
+        }
+
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for client in std::fs::read_dir(&self.root)? {
+            let client = client?.path();
+            if !client.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(&client)? {
+                let entry = entry?;
+                let modified = entry.metadata()?.modified()?;
+                if now.duration_since(modified).unwrap_or_default() >= self.ttl {
+                    std::fs::remove_file(entry.path())?;
+                    removed += 1;
+                }
+            }
+            if std::fs::read_dir(&client)?.next().is_none() {
+                std::fs::remove_dir(&client)?;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Names and sizes of the regular files in `dir`
+pub fn list_files(dir: &Path) -> Result<Vec<(String, u64)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            files.push((entry.file_name().to_string_lossy().to_string(), metadata.len()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_redirect() {
+        assert_eq!(parse_redirect("example.com-SSL").unwrap(), None);
+        assert_eq!(
+            parse_redirect("example.com-SSL > report.txt").unwrap(),
+            Some(Redirect { query: "example.com-SSL".to_string(), filename: "report.txt".to_string(), append: false })
+        );
+        assert!(parse_redirect("AS13335>>log.txt").unwrap().unwrap().append);
+        assert!(parse_redirect("AS13335 > ../etc/passwd").is_err());
+        assert!(parse_redirect("AS13335 > .hidden").is_err());
+        assert!(parse_redirect("> report.txt").is_err());
+    }
+
+    #[test]
+    fn test_save_enforces_quota() {
+        let root = std::env::temp_dir().join(format!("whois-exports-{}", std::process::id()));
+        let store = ExportStore::new(&root, 16, Duration::from_secs(3600));
+        let redirect = |filename: &str, append| Redirect { query: "AS13335".to_string(), filename: filename.to_string(), append };
+
+        assert_eq!(store.save("ip-192.0.2.1", &redirect("a.txt", false), "0123456789").unwrap(), 10);
+        assert_eq!(store.save("ip-192.0.2.1", &redirect("a.txt", true), "0123").unwrap(), 14);
+        assert!(store.save("ip-192.0.2.1", &redirect("b.txt", false), "0123").is_err());
+        // Overwriting replaces the old size
+        assert_eq!(store.save("ip-192.0.2.1", &redirect("a.txt", false), "0123456789abcdef").unwrap(), 16);
+
+        assert_eq!(ExportStore::new(&root, 16, Duration::ZERO).purge_expired().unwrap(), 1);
+        assert!(!store.client_dir("ip-192.0.2.1").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_client_identity() {
+        assert_eq!(client_identity(Some("ab+c/d="), None), "key-ab-c_d");
+        assert_eq!(client_identity(None, Some("2001:db8::1".parse().unwrap())), "ip-2001_db8__1");
+    }
+}
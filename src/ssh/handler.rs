@@ -10,9 +10,13 @@ use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use super::exports::{ ExportStore, Redirect, client_identity, parse_redirect };
 use super::history::{ SshConnectionHistory, SshConnectionRecord };
-use crate::{log_debug, log_error, log_info};
+use super::sftp::ExportSftpSession;
+use crate::auth::auth;
+use crate::{log_debug, log_error, log_info, log_warn};
 use crate::core::process_query;
+use crate::core::request_context::{ Frontend, RequestContext };
 
 /// ANSI escape sequence parsing state
 #[derive(Debug, Clone, PartialEq)]
@@ -42,6 +46,14 @@ pub struct WhoisSshHandler {
     history: Arc<SshConnectionHistory>,
     /// Active sessions
     sessions: Arc<Mutex<HashMap<ChannelId, SshSession>>>,
+    /// Open channels, kept for subsystems that take over the channel (SFTP)
+    channels: Arc<Mutex<HashMap<ChannelId, Channel<server::Msg>>>>,
+    /// Saved query results
+    exports: ExportStore,
+    /// Fingerprint of the key the client authenticated with
+    key_fingerprint: Option<String>,
+    /// Directory user the client logged in as, when the auth file requires logins
+    login: Option<String>,
     /// Client address
     client_addr: Option<SocketAddr>,
     /// Server host key
@@ -51,10 +63,18 @@ pub struct WhoisSshHandler {
 
 impl WhoisSshHandler {
     /// Create a new WHOIS SSH handler
-    pub fn new(history: Arc<SshConnectionHistory>, host_key: Arc<key::KeyPair>) -> Self {
+    pub fn new(
+        history: Arc<SshConnectionHistory>,
+        host_key: Arc<key::KeyPair>,
+        exports: ExportStore
+    ) -> Self {
         Self {
             history,
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            channels: Arc::new(Mutex::new(HashMap::new())),
+            exports,
+            key_fingerprint: None,
+            login: None,
             client_addr: None,
             host_key,
         }
@@ -66,8 +86,9 @@ impl WhoisSshHandler {
     }
 
     /// Process a WHOIS query and return the response
-    async fn process_whois_query(&self, query: &str) -> String {
-        let query = query.trim();
+    async fn process_whois_query(&self, query: &str, username: Option<String>) -> String {
+        let query = crate::core::sanitize::sanitize_query(query);
+        let query = query.as_str();
 
         if query.is_empty() {
             return "Error: Empty query\r\n".to_string();
@@ -78,13 +99,28 @@ impl WhoisSshHandler {
             return self.get_connection_history().await;
         }
 
+        // `<query> > <file>` saves the response for download over SFTP
+        match parse_redirect(query) {
+            Ok(Some(redirect)) => {
+                return self.export_query(redirect, username).await;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                return format!("Error: {}\r\n", e);
+            }
+        }
+
         // Detect query type and process
         let query_type = crate::core::analyze_query(query);
         log_debug!("Processing SSH WHOIS query: {} (type: {:?})", query, query_type);
 
         // Use the existing query handling logic from the main server
-        // Note: SSH connections don't provide client IP in the same way, so we pass None
-        match process_query(query, &query_type, None, None).await {
+        let mut ctx = RequestContext::new(Frontend::Ssh).with_identity(self.query_identity(username));
+        if let Some(addr) = self.client_addr {
+            ctx = ctx.with_client_addr(addr.ip());
+        }
+
+        match process_query(query, &query_type, &ctx).await {
             Ok(response) => {
                 // Add CRLF line endings for proper terminal display
                 response.replace('\n', "\r\n") + "\r\n"
@@ -96,6 +132,63 @@ impl WhoisSshHandler {
         }
     }
 
+    /// Run a redirected query and save its response to the export directory
+    async fn export_query(&self, redirect: Redirect, username: Option<String>) -> String {
+        let query_type = crate::core::analyze_query(&redirect.query);
+        let mut ctx = RequestContext::new(Frontend::Ssh).with_identity(self.query_identity(username));
+        if let Some(addr) = self.client_addr {
+            ctx = ctx.with_client_addr(addr.ip());
+        }
+
+        let response = match process_query(&redirect.query, &query_type, &ctx).await {
+            Ok(response) => response,
+            Err(e) => {
+                log_error!("Error processing SSH WHOIS query '{}': {}", redirect.query, e);
+                return format!("Error: {}\r\n", e);
+            }
+        };
+
+        let mut content = response.replace("\r\n", "\n");
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        match self.exports.save(&self.export_identity(), &redirect, &content) {
+            Ok(size) => {
+                format!(
+                    "Saved to {} ({} bytes, kept for {}h). Download with: sftp -P <port> whois@<host>:{}\r\n",
+                    redirect.filename,
+                    size,
+                    self.exports.ttl().as_secs() / 3600,
+                    redirect.filename
+                )
+            }
+            Err(e) => format!("Error: {}\r\n", e),
+        }
+    }
+
+    /// Identity used for per-client settings such as macros
+    ///
+    /// Every SSH client logs in as `whois`, so a client that authenticated
+    /// with a key is told apart by its fingerprint (`SHA256:...`). With
+    /// logins required, the directory user name is the identity.
+    fn query_identity(&self, username: Option<String>) -> Option<String> {
+        if let Some(login) = &self.login {
+            return Some(login.clone());
+        }
+        match &self.key_fingerprint {
+            Some(fingerprint) => Some(format!("SHA256:{}", fingerprint)),
+            None => username,
+        }
+    }
+
+    /// Name of this client's export directory
+    fn export_identity(&self) -> String {
+        client_identity(
+            self.key_fingerprint.as_deref(),
+            self.client_addr.map(|addr| addr.ip())
+        )
+    }
+
     /// Get connection history for the current client IP
     async fn get_connection_history(&self) -> String {
         let client_ip = match self.client_addr {
@@ -154,7 +247,9 @@ impl server::Handler for WhoisSshHandler {
 
         // Initialize session data
         let mut sessions = self.sessions.lock().await;
-        sessions.insert(channel.id(), SshSession {
+        let channel_id = channel.id();
+        self.channels.lock().await.insert(channel_id, channel);
+        sessions.insert(channel_id, SshSession {
             start_time: Utc::now(),
             queries_count: 0,
             username: None,
@@ -172,8 +267,27 @@ impl server::Handler for WhoisSshHandler {
     async fn auth_password(
         &mut self,
         user: &str,
-        _password: &str
+        password: &str
     ) -> Result<server::Auth, Self::Error> {
+        // With logins required, the password is checked against LDAP
+        if let Some(config) = auth().filter(|config| config.requires_ssh()) {
+            return match config.login(user, password, self.client_addr.map(|addr| addr.ip())).await {
+                Ok(Some(login)) => {
+                    log_info!("SSH login successful: user={} ({:?})", login.name, login.permission);
+                    self.login = Some(login.name);
+                    Ok(server::Auth::Accept)
+                }
+                Ok(None) => {
+                    log_info!("SSH login failed: user={}", user);
+                    Ok(server::Auth::Reject { proceed_with_methods: None })
+                }
+                Err(e) => {
+                    log_warn!("SSH login of {} could not be checked: {}", user, e);
+                    Ok(server::Auth::Reject { proceed_with_methods: None })
+                }
+            };
+        }
+
         // Accept only "whois" username for SSH connections
         if user != "whois" {
             log_info!("SSH authentication failed: invalid username '{}'", user);
@@ -196,8 +310,15 @@ impl server::Handler for WhoisSshHandler {
     async fn auth_publickey(
         &mut self,
         user: &str,
-        _public_key: &key::PublicKey
+        public_key: &key::PublicKey
     ) -> Result<server::Auth, Self::Error> {
+        // Keys are not tied to directory users, so logins go through passwords
+        if auth().is_some_and(|config| config.requires_ssh()) {
+            return Ok(server::Auth::Reject {
+                proceed_with_methods: Some(russh::MethodSet::PASSWORD),
+            });
+        }
+
         // Accept only "whois" username for SSH connections
         if user != "whois" {
             log_info!("SSH public key authentication failed: invalid username '{}'", user);
@@ -207,6 +328,7 @@ impl server::Handler for WhoisSshHandler {
         }
 
         log_info!("SSH public key authentication successful: user={}", user);
+        self.key_fingerprint = Some(public_key.fingerprint());
 
         // Store username for session tracking
         let mut sessions = self.sessions.lock().await;
@@ -235,6 +357,7 @@ impl server::Handler for WhoisSshHandler {
         _session: &mut server::Session
     ) -> Result<(), Self::Error> {
         log_debug!("SSH channel closed: {:?}", channel);
+        self.channels.lock().await.remove(&channel);
 
         // Record session in history
         if let Some(client_addr) = self.client_addr {
@@ -286,6 +409,29 @@ impl server::Handler for WhoisSshHandler {
         Ok(())
     }
 
+    async fn subsystem_request(
+        &mut self,
+        channel_id: ChannelId,
+        name: &str,
+        session: &mut server::Session
+    ) -> Result<(), Self::Error> {
+        log_debug!("SSH subsystem request '{}' for channel: {:?}", name, channel_id);
+        let channel = match name {
+            "sftp" => self.channels.lock().await.remove(&channel_id),
+            _ => None,
+        };
+        let Some(channel) = channel else {
+            session.channel_failure(channel_id);
+            return Ok(());
+        };
+
+        let dir = self.exports.client_dir(&self.export_identity());
+        tokio::fs::create_dir_all(&dir).await?;
+        session.channel_success(channel_id);
+        russh_sftp::server::run(channel.into_stream(), ExportSftpSession::new(dir)).await;
+        Ok(())
+    }
+
     async fn shell_request(
         &mut self,
         channel: ChannelId,
@@ -306,6 +452,7 @@ impl server::Handler for WhoisSshHandler {
             \r\n\
             Special commands:\r\n\
             • 'history'    - View your connection history\r\n\
+            • 'QUERY > f'  - Save a result to file f, download over SFTP\r\n\
             • 'help'       - Show all available query types\r\n\
             • 'clear/cls'  - Clear the screen\r\n\
             • 'exit/quit'  - Disconnect from server\r\n\
@@ -390,8 +537,9 @@ impl WhoisSshHandler {
                             session_data.cursor_pos = 0;
 
                             // Process command
+                            let username = session_data.username.clone();
                             drop(sessions); // Release lock before async operation
-                            let response = self.process_whois_query(&command).await;
+                            let response = self.process_whois_query(&command, username).await;
                             session.data(channel, CryptoVec::from_slice(response.as_bytes()));
                         } else {
                             session_data.current_line.clear();
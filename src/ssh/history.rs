@@ -7,8 +7,9 @@ use chrono::{DateTime, Duration, Utc};
 use lmdb::{Cursor, Database, DatabaseFlags, Environment, Transaction, WriteFlags};
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use crate::storage::kv::KvStore;
 use crate::{log_debug, log_info, log_warn};
 /// Maximum number of history records to keep per IP address
 const MAX_RECORDS_PER_IP: usize = 100;
@@ -16,6 +17,9 @@ const MAX_RECORDS_PER_IP: usize = 100;
 /// Maximum age of history records in days
 const MAX_RECORD_AGE_DAYS: i64 = 30;
 
+/// Prefix of bookkeeping keys (schema version) that are not connection records
+const META_KEY_PREFIX: &str = "__meta__";
+
 /// SSH connection history record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SshConnectionRecord {
@@ -44,14 +48,7 @@ impl SshConnectionHistory {
                 .with_context(|| format!("Failed to create directory {parent:?}"))?;
         }
 
-        // On Windows, LMDB requires the path to be a directory, not a file
-        let lmdb_dir = if cfg!(windows) {
-            // Use the directory path directly for Windows
-            db_path.with_extension("")
-        } else {
-            // On Unix-like systems, use the file path
-            db_path.to_path_buf()
-        };
+        let lmdb_dir = Self::lmdb_dir(db_path);
 
         // Create the LMDB directory if it doesn't exist
         if !lmdb_dir.exists() {
@@ -98,6 +95,18 @@ impl SshConnectionHistory {
         Ok(history)
     }
 
+    /// LMDB environment directory used for the given history database path
+    pub fn lmdb_dir(db_path: &Path) -> PathBuf {
+        // On Windows, LMDB requires the path to be a directory, not a file
+        if cfg!(windows) {
+            // Use the directory path directly for Windows
+            db_path.with_extension("")
+        } else {
+            // On Unix-like systems, use the file path
+            db_path.to_path_buf()
+        }
+    }
+
     /// Add a new connection record
     pub fn add_record(&self, record: SshConnectionRecord) -> Result<()> {
         let mut txn = self
@@ -186,6 +195,10 @@ impl SshConnectionHistory {
 
             // Find old records
             for (key, value) in cursor.iter() {
+                if key.starts_with(META_KEY_PREFIX.as_bytes()) {
+                    continue;
+                }
+
                 let record: SshConnectionRecord = match serde_json::from_slice(value) {
                     Ok(r) => r,
                     Err(e) => {
@@ -275,3 +288,68 @@ impl SshConnectionHistory {
         Ok(count)
     }
 }
+
+impl KvStore for SshConnectionHistory {
+    fn name(&self) -> &'static str {
+        "ssh_history"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let txn = self
+            .env
+            .begin_ro_txn()
+            .with_context(|| "Failed to begin read transaction")?;
+
+        match txn.get(self.db, &key) {
+            Ok(bytes) => Ok(Some(std::str::from_utf8(bytes)?.to_string())),
+            Err(lmdb::Error::NotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<()> {
+        let mut txn = self
+            .env
+            .begin_rw_txn()
+            .with_context(|| "Failed to begin write transaction")?;
+        txn.put(self.db, &key, &value, WriteFlags::empty())?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let mut txn = self
+            .env
+            .begin_rw_txn()
+            .with_context(|| "Failed to begin write transaction")?;
+        match txn.del(self.db, &key, None) {
+            Ok(_) => {
+                txn.commit()?;
+                Ok(())
+            }
+            Err(lmdb::Error::NotFound) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn scan(&self, prefix: &str) -> Result<Vec<String>> {
+        let txn = self
+            .env
+            .begin_ro_txn()
+            .with_context(|| "Failed to begin read transaction")?;
+
+        let mut cursor = txn
+            .open_ro_cursor(self.db)
+            .with_context(|| "Failed to open cursor")?;
+
+        let mut keys = Vec::new();
+        for (key, _) in cursor.iter() {
+            let key_str = std::str::from_utf8(key)?;
+            if key_str.starts_with(prefix) && !key_str.starts_with(META_KEY_PREFIX) {
+                keys.push(key_str.to_string());
+            }
+        }
+
+        Ok(keys)
+    }
+}
@@ -9,11 +9,14 @@
 //! - Fixed SSH server certificates stored in ./cache/ssh
 //! - Connection history tracking with LMDB (100 records, 30 days retention)
 //! - Direct WHOIS query processing without command prefixes
+//! - `<query> > <file>` exports, downloadable over the SFTP subsystem
 
 pub mod certificates;
+pub mod exports;
 pub mod handler;
 pub mod history;
 pub mod server;
+pub mod sftp;
 
 #[allow(unused_imports)]
 pub use certificates::SshCertificateManager;
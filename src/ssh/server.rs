@@ -10,8 +10,10 @@ use std::path::Path;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use super::certificates::SshCertificateManager;
+use super::exports::ExportStore;
 use super::handler::WhoisSshHandler;
 use super::history::SshConnectionHistory;
+use crate::core::scheduler::{self, Job, Schedule};
 
 use crate::{log_debug, log_error, log_info, log_warn};
 /// SSH server configuration
@@ -20,6 +22,10 @@ pub struct SshServerConfig {
     pub listen_addr: String,
     pub port: u16,
     pub cache_dir: String,
+    /// Disk space per client for saved query results (`<query> > <file>`)
+    pub export_quota_bytes: u64,
+    /// How long saved query results are kept
+    pub export_ttl: std::time::Duration,
 }
 
 impl Default for SshServerConfig {
@@ -28,6 +34,8 @@ impl Default for SshServerConfig {
             listen_addr: "0.0.0.0".to_string(),
             port: 2222,
             cache_dir: "./cache/ssh".to_string(),
+            export_quota_bytes: 10 * 1024 * 1024,
+            export_ttl: std::time::Duration::from_secs(24 * 3600),
         }
     }
 }
@@ -37,6 +45,7 @@ pub struct SshServer {
     config: SshServerConfig,
     cert_manager: SshCertificateManager,
     history: Arc<SshConnectionHistory>,
+    exports: ExportStore,
     host_key: Option<Arc<key::KeyPair>>,
 }
 
@@ -60,10 +69,17 @@ impl SshServer {
             })?,
         );
 
+        let exports = ExportStore::new(
+            Path::new(&config.cache_dir).join("exports"),
+            config.export_quota_bytes,
+            config.export_ttl,
+        );
+
         Ok(Self {
             config,
             cert_manager,
             history,
+            exports,
             host_key: None,
         })
     }
@@ -123,6 +139,22 @@ impl SshServer {
             ..Default::default()
         });
 
+        // Delete expired exports every hour
+        let exports = self.exports.clone();
+        scheduler::schedule(
+            Job::new("ssh-export-purge", Schedule::Every(std::time::Duration::from_secs(3600)), move || {
+                let exports = exports.clone();
+                async move {
+                    let removed = exports.purge_expired()?;
+                    if removed > 0 {
+                        log_debug!("Removed {} expired SSH exports", removed);
+                    }
+                    Ok(())
+                }
+            })
+            .run_at_start(),
+        );
+
         loop {
             match listener.accept().await {
                 Ok((stream, client_addr)) => {
@@ -131,11 +163,18 @@ impl SshServer {
                     let history = Arc::clone(&self.history);
                     let host_key = Arc::clone(host_key);
                     let config = Arc::clone(&server_config);
+                    let exports = self.exports.clone();
 
                     tokio::spawn(async move {
-                        if let Err(e) =
-                            Self::handle_connection(stream, client_addr, history, host_key, config)
-                                .await
+                        if let Err(e) = Self::handle_connection(
+                            stream,
+                            client_addr,
+                            history,
+                            host_key,
+                            exports,
+                            config,
+                        )
+                        .await
                         {
                             log_error!("SSH connection error from {}: {}", client_addr, e);
                         }
@@ -154,9 +193,10 @@ impl SshServer {
         client_addr: SocketAddr,
         history: Arc<SshConnectionHistory>,
         host_key: Arc<key::KeyPair>,
+        exports: ExportStore,
         config: Arc<server::Config>,
     ) -> Result<()> {
-        let mut handler = WhoisSshHandler::new(history, host_key);
+        let mut handler = WhoisSshHandler::new(history, host_key, exports);
         handler.set_client_addr(client_addr);
 
         let _session = server::run_stream(config, stream, handler)
@@ -0,0 +1,214 @@
+// WHOIS Server - SSH SFTP Subsystem
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Read-only SFTP access to a client's export directory
+//!
+//! The directory is presented as `/`; it contains only the flat files saved
+//! with `<query> > <file>` (see `ssh::exports`). Files can be listed,
+//! downloaded and removed, but not written.
+
+use russh_sftp::protocol::{
+    Attrs,
+    Data,
+    File,
+    FileAttributes,
+    Handle,
+    Name,
+    OpenFlags,
+    Status,
+    StatusCode,
+    Version,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::io::{ AsyncReadExt, AsyncSeekExt };
+
+use super::exports::{ list_files, valid_filename };
+use crate::log_debug;
+
+/// Largest chunk returned by one read request
+const MAX_READ_LEN: u32 = 256 * 1024;
+
+enum OpenHandle {
+    File(PathBuf),
+    Dir {
+        listed: bool,
+    },
+}
+
+/// SFTP session rooted at one export directory
+pub struct ExportSftpSession {
+    dir: PathBuf,
+    handles: HashMap<String, OpenHandle>,
+    next_handle: u64,
+}
+
+impl ExportSftpSession {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, handles: HashMap::new(), next_handle: 0 }
+    }
+
+    fn add_handle(&mut self, handle: OpenHandle) -> String {
+        self.next_handle += 1;
+        let name = self.next_handle.to_string();
+        self.handles.insert(name.clone(), handle);
+        name
+    }
+
+    /// Map a client path to a file in the export directory
+    ///
+    /// `None` is the directory itself; anything outside it is rejected.
+    fn resolve(&self, path: &str) -> Result<Option<PathBuf>, StatusCode> {
+        let name = path.trim_start_matches("./").trim_start_matches('/').trim_end_matches('/');
+        if name.is_empty() || name == "." {
+            return Ok(None);
+        }
+        if !valid_filename(name) {
+            return Err(StatusCode::NoSuchFile);
+        }
+        Ok(Some(self.dir.join(name)))
+    }
+
+    async fn attrs(&self, path: Option<PathBuf>) -> Result<FileAttributes, StatusCode> {
+        let path = path.unwrap_or_else(|| self.dir.clone());
+        let metadata = tokio::fs::metadata(&path).await.map_err(|_| StatusCode::NoSuchFile)?;
+        Ok(FileAttributes::from(&metadata))
+    }
+}
+
+fn ok_status(id: u32) -> Status {
+    Status {
+        id,
+        status_code: StatusCode::Ok,
+        error_message: "Ok".to_string(),
+        language_tag: "en-US".to_string(),
+    }
+}
+
+#[async_trait::async_trait]
+impl russh_sftp::server::Handler for ExportSftpSession {
+    type Error = StatusCode;
+
+    fn unimplemented(&self) -> Self::Error {
+        StatusCode::OpUnsupported
+    }
+
+    async fn init(
+        &mut self,
+        version: u32,
+        _extensions: HashMap<String, String>
+    ) -> Result<Version, Self::Error> {
+        log_debug!("SFTP session started (version {}) for {}", version, self.dir.display());
+        Ok(Version::new())
+    }
+
+    async fn realpath(&mut self, id: u32, path: String) -> Result<Name, Self::Error> {
+        let name = match self.resolve(&path)? {
+            Some(file) => format!("/{}", file.file_name().unwrap_or_default().to_string_lossy()),
+            None => "/".to_string(),
+        };
+        Ok(Name { id, files: vec![File::dummy(name)] })
+    }
+
+    async fn stat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        let attrs = self.attrs(self.resolve(&path)?).await?;
+        Ok(Attrs { id, attrs })
+    }
+
+    async fn lstat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        self.stat(id, path).await
+    }
+
+    async fn fstat(&mut self, id: u32, handle: String) -> Result<Attrs, Self::Error> {
+        let path = match self.handles.get(&handle) {
+            Some(OpenHandle::File(path)) => Some(path.clone()),
+            Some(OpenHandle::Dir { .. }) => None,
+            None => {
+                return Err(StatusCode::Failure);
+            }
+        };
+        let attrs = self.attrs(path).await?;
+        Ok(Attrs { id, attrs })
+    }
+
+    async fn opendir(&mut self, id: u32, path: String) -> Result<Handle, Self::Error> {
+        if self.resolve(&path)?.is_some() {
+            return Err(StatusCode::NoSuchFile);
+        }
+        let handle = self.add_handle(OpenHandle::Dir { listed: false });
+        Ok(Handle { id, handle })
+    }
+
+    async fn readdir(&mut self, id: u32, handle: String) -> Result<Name, Self::Error> {
+        match self.handles.get_mut(&handle) {
+            Some(OpenHandle::Dir { listed }) if !*listed => {
+                *listed = true;
+            }
+            Some(OpenHandle::Dir { .. }) => {
+                return Err(StatusCode::Eof);
+            }
+            _ => {
+                return Err(StatusCode::Failure);
+            }
+        }
+
+        let mut files = Vec::new();
+        for (name, _) in list_files(&self.dir).map_err(|_| StatusCode::Failure)? {
+            let attrs = self.attrs(Some(self.dir.join(&name))).await?;
+            files.push(File::new(name, attrs));
+        }
+        Ok(Name { id, files })
+    }
+
+    async fn open(
+        &mut self,
+        id: u32,
+        filename: String,
+        pflags: OpenFlags,
+        _attrs: FileAttributes
+    ) -> Result<Handle, Self::Error> {
+        if pflags.intersects(OpenFlags::WRITE | OpenFlags::APPEND | OpenFlags::CREATE | OpenFlags::TRUNCATE) {
+            return Err(StatusCode::PermissionDenied);
+        }
+        let path = self.resolve(&filename)?.ok_or(StatusCode::NoSuchFile)?;
+        if !path.is_file() {
+            return Err(StatusCode::NoSuchFile);
+        }
+        let handle = self.add_handle(OpenHandle::File(path));
+        Ok(Handle { id, handle })
+    }
+
+    async fn read(
+        &mut self,
+        id: u32,
+        handle: String,
+        offset: u64,
+        len: u32
+    ) -> Result<Data, Self::Error> {
+        let Some(OpenHandle::File(path)) = self.handles.get(&handle) else {
+            return Err(StatusCode::Failure);
+        };
+
+        let mut file = tokio::fs::File::open(path).await.map_err(|_| StatusCode::NoSuchFile)?;
+        file.seek(std::io::SeekFrom::Start(offset)).await.map_err(|_| StatusCode::Failure)?;
+        let mut data = vec![0; len.min(MAX_READ_LEN) as usize];
+        let read = file.read(&mut data).await.map_err(|_| StatusCode::Failure)?;
+        if read == 0 {
+            return Err(StatusCode::Eof);
+        }
+        data.truncate(read);
+        Ok(Data { id, data })
+    }
+
+    async fn close(&mut self, id: u32, handle: String) -> Result<Status, Self::Error> {
+        self.handles.remove(&handle);
+        Ok(ok_status(id))
+    }
+
+    async fn remove(&mut self, id: u32, filename: String) -> Result<Status, Self::Error> {
+        let path = self.resolve(&filename)?.ok_or(StatusCode::PermissionDenied)?;
+        tokio::fs::remove_file(&path).await.map_err(|_| StatusCode::NoSuchFile)?;
+        Ok(ok_status(id))
+    }
+}
@@ -0,0 +1,369 @@
+// WHOIS Server - Shared Cache Backends
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Cache backends for cached upstream responses, counters and locks
+//!
+//! The default backend keeps entries in a local LMDB database. With the
+//! `redis` feature enabled, a Redis backend can be selected instead so that
+//! several server instances behind a load balancer share cached responses,
+//! rate-limit counters and single-flight locks.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::storage::kv::KvStore;
+use crate::storage::lmdb::LmdbStorage;
+
+/// Operations every cache backend provides
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    /// Backend name for logging
+    fn name(&self) -> &'static str;
+
+    /// Whether the backend is shared between server instances
+    fn is_distributed(&self) -> bool;
+
+    /// Get a value that has not expired
+    async fn get(&self, key: &str) -> Result<Option<String>>;
+
+    /// Store a value that expires after `ttl`
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<()>;
+
+    /// Remove a value
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Time until a value expires, `None` if it is missing or never expires
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>>;
+
+    /// Increment a counter, creating it with the given `ttl` if it does not exist
+    ///
+    /// Returns the counter value after the increment.
+    async fn incr(&self, key: &str, ttl: Duration) -> Result<u64>;
+
+    /// Try to take a lock that is released automatically after `ttl`
+    ///
+    /// Returns true if the lock was acquired.
+    async fn try_lock(&self, key: &str, ttl: Duration) -> Result<bool>;
+
+    /// Release a lock taken with [`CacheStore::try_lock`]
+    async fn unlock(&self, key: &str) -> Result<()>;
+
+    /// Remove every expired entry, returning how many were removed
+    ///
+    /// Backends that expire entries themselves have nothing to purge.
+    async fn purge_expired(&self) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Remove live entries starting with `prefix`, those expiring soonest
+    /// first, until at least `bytes` of values are gone
+    ///
+    /// Returns the bytes removed. Backends that bound their own memory have
+    /// nothing to evict.
+    async fn evict_expiring(&self, _prefix: &str, _bytes: u64) -> Result<u64> {
+        Ok(0)
+    }
+}
+
+/// Shared handle to the configured cache backend
+pub type SharedCacheStore = Arc<dyn CacheStore>;
+
+static CACHE_STORE: OnceLock<SharedCacheStore> = OnceLock::new();
+
+/// Install the cache backend used by the rest of the server
+pub fn init_cache_store(store: SharedCacheStore) {
+    let _ = CACHE_STORE.set(store);
+}
+
+/// The configured cache backend, if one was installed at startup
+pub fn cache_store() -> Option<SharedCacheStore> {
+    CACHE_STORE.get().cloned()
+}
+
+/// The configured cache backend, only if it is shared between instances
+pub fn distributed_cache_store() -> Option<SharedCacheStore> {
+    cache_store().filter(|store| store.is_distributed())
+}
+
+/// Cache backend on top of a local key-value store (single instance)
+pub struct KvCacheStore {
+    store: Arc<dyn KvStore>,
+    /// Serializes read-modify-write operations (counters and locks)
+    write_lock: Mutex<()>,
+}
+
+impl KvCacheStore {
+    pub fn new(store: Arc<dyn KvStore>) -> Self {
+        Self { store, write_lock: Mutex::new(()) }
+    }
+}
+
+#[async_trait]
+impl CacheStore for KvCacheStore {
+    fn name(&self) -> &'static str {
+        self.store.name()
+    }
+
+    fn is_distributed(&self) -> bool {
+        false
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        self.store.get_with_ttl(key)
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<()> {
+        self.store.put_with_ttl(key, value, ttl)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.store.delete(key)
+    }
+
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        self.store.ttl_remaining(key)
+    }
+
+    async fn incr(&self, key: &str, ttl: Duration) -> Result<u64> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let (count, ttl) = match self.store.get_with_ttl(key)? {
+            // Keep the expiry set when the counter was created
+            Some(value) => {
+                let remaining = self.store.ttl_remaining(key)?.unwrap_or(ttl);
+                (value.parse::<u64>().unwrap_or(0) + 1, remaining)
+            }
+            None => (1, ttl),
+        };
+        self.store.put_with_ttl(key, &count.to_string(), ttl)?;
+        Ok(count)
+    }
+
+    async fn try_lock(&self, key: &str, ttl: Duration) -> Result<bool> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if self.store.get_with_ttl(key)?.is_some() {
+            return Ok(false);
+        }
+        self.store.put_with_ttl(key, "locked", ttl)?;
+        Ok(true)
+    }
+
+    async fn unlock(&self, key: &str) -> Result<()> {
+        self.store.delete(key)
+    }
+
+    async fn purge_expired(&self) -> Result<usize> {
+        let mut purged = 0;
+        for key in self.store.scan("")? {
+            // Reading an expired entry removes it; keys without a TTL are skipped
+            if matches!(self.store.get_with_ttl(&key), Ok(None)) {
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+
+    async fn evict_expiring(&self, prefix: &str, bytes: u64) -> Result<u64> {
+        let mut entries = Vec::new();
+        for key in self.store.scan(prefix)? {
+            // Entries without a TTL are skipped
+            if let (Ok(Some(remaining)), Ok(Some(raw))) = (self.store.ttl_remaining(&key), self.store.get(&key)) {
+                entries.push((remaining, (key.len() + raw.len()) as u64, key));
+            }
+        }
+        entries.sort();
+
+        let mut evicted = 0;
+        for (_, size, key) in entries {
+            if evicted >= bytes {
+                break;
+            }
+            self.store.delete(&key)?;
+            evicted += size;
+        }
+        Ok(evicted)
+    }
+}
+
+/// Cache backend on Redis, shared by all instances pointing at the same server
+#[cfg(feature = "redis")]
+pub struct RedisCacheStore {
+    connection: redis::aio::ConnectionManager,
+}
+
+#[cfg(feature = "redis")]
+impl RedisCacheStore {
+    pub async fn connect(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = redis::aio::ConnectionManager::new(client).await?;
+        Ok(Self { connection })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl CacheStore for RedisCacheStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn is_distributed(&self) -> bool {
+        true
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        let mut connection = self.connection.clone();
+        Ok(redis::cmd("GET").arg(key).query_async(&mut connection).await?)
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<()> {
+        let mut connection = self.connection.clone();
+        redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("PX")
+            .arg(ttl.as_millis().max(1) as u64)
+            .query_async::<()>(&mut connection).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let mut connection = self.connection.clone();
+        redis::cmd("DEL").arg(key).query_async::<()>(&mut connection).await?;
+        Ok(())
+    }
+
+    async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        let mut connection = self.connection.clone();
+        // -2 for a missing key, -1 for one without an expiry
+        let millis: i64 = redis::cmd("PTTL").arg(key).query_async(&mut connection).await?;
+        Ok(u64::try_from(millis).ok().map(Duration::from_millis))
+    }
+
+    async fn incr(&self, key: &str, ttl: Duration) -> Result<u64> {
+        let mut connection = self.connection.clone();
+        // Create the counter with its expiry first (no-op if it exists), then
+        // increment; INCR keeps the existing expiry
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(key)
+            .arg(0)
+            .arg("PX")
+            .arg(ttl.as_millis().max(1) as u64)
+            .arg("NX")
+            .ignore()
+            .cmd("INCR")
+            .arg(key)
+            .query_async(&mut connection).await?;
+        Ok(count)
+    }
+
+    async fn try_lock(&self, key: &str, ttl: Duration) -> Result<bool> {
+        let mut connection = self.connection.clone();
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg("locked")
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis().max(1) as u64)
+            .query_async(&mut connection).await?;
+        Ok(reply.is_some())
+    }
+
+    async fn unlock(&self, key: &str) -> Result<()> {
+        self.delete(key).await
+    }
+}
+
+/// Open the cache backend selected on the command line
+pub async fn open_cache_store(backend: &str, redis_url: Option<&str>) -> Result<SharedCacheStore> {
+    match backend {
+        "lmdb" => {
+            let storage = LmdbStorage::new(crate::config::CACHE_LMDB_PATH)?;
+            Ok(Arc::new(KvCacheStore::new(Arc::new(storage))))
+        }
+        #[cfg(feature = "redis")]
+        "redis" => {
+            let url = redis_url.ok_or_else(|| {
+                anyhow::anyhow!("--redis-url is required for the redis cache backend")
+            })?;
+            Ok(Arc::new(RedisCacheStore::connect(url).await?))
+        }
+        #[cfg(not(feature = "redis"))]
+        "redis" => {
+            let _ = redis_url;
+            Err(anyhow::anyhow!("redis cache backend requires building with --features redis"))
+        }
+        other => Err(anyhow::anyhow!("Unknown cache backend: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::kv::MemoryStore;
+
+    fn memory_cache() -> KvCacheStore {
+        KvCacheStore::new(Arc::new(MemoryStore::new()))
+    }
+
+    #[tokio::test]
+    async fn test_set_get_delete() {
+        let cache = memory_cache();
+        cache.set("key", "value", Duration::from_secs(60)).await.unwrap();
+        assert_eq!(cache.get("key").await.unwrap(), Some("value".to_string()));
+        assert!(cache.ttl("key").await.unwrap().is_some_and(|ttl| ttl <= Duration::from_secs(60)));
+
+        cache.delete("key").await.unwrap();
+        assert_eq!(cache.get("key").await.unwrap(), None);
+        assert_eq!(cache.ttl("key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_incr_and_lock() {
+        let cache = memory_cache();
+        assert_eq!(cache.incr("counter", Duration::from_secs(60)).await.unwrap(), 1);
+        assert_eq!(cache.incr("counter", Duration::from_secs(60)).await.unwrap(), 2);
+
+        assert!(cache.try_lock("lock", Duration::from_secs(60)).await.unwrap());
+        assert!(!cache.try_lock("lock", Duration::from_secs(60)).await.unwrap());
+        cache.unlock("lock").await.unwrap();
+        assert!(cache.try_lock("lock", Duration::from_secs(60)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired() {
+        let store = Arc::new(MemoryStore::new());
+        let cache = KvCacheStore::new(store.clone());
+        cache.set("fresh", "value", Duration::from_secs(60)).await.unwrap();
+        store.put("stale", r#"{"value":"old","expires_at":1}"#).unwrap();
+        store.put("__meta__schema_version", "1").unwrap();
+
+        assert_eq!(cache.purge_expired().await.unwrap(), 1);
+        assert!(!store.exists("stale").unwrap());
+        assert!(store.exists("fresh").unwrap());
+        assert!(store.exists("__meta__schema_version").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_evict_expiring_first() {
+        let store = Arc::new(MemoryStore::new());
+        let cache = KvCacheStore::new(store.clone());
+        cache.set("response:soon", "value", Duration::from_secs(60)).await.unwrap();
+        cache.set("response:later", "value", Duration::from_secs(3600)).await.unwrap();
+        cache.set("ratelimit:client", "1", Duration::from_secs(10)).await.unwrap();
+
+        assert!(cache.evict_expiring("response:", 1).await.unwrap() > 0);
+        assert!(!store.exists("response:soon").unwrap());
+        assert!(store.exists("response:later").unwrap());
+        assert!(store.exists("ratelimit:client").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_backend_is_rejected() {
+        assert!(open_cache_store("memcached", None).await.is_err());
+    }
+}
@@ -0,0 +1,221 @@
+// WHOIS Server - Key-Value Store Abstraction
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Key-value store trait with pluggable backends
+//!
+//! [`KvStore`] is the storage interface used by modules that persist data
+//! (statistics, caches). LMDB implements it for production use and
+//! [`MemoryStore`] provides an in-memory backend for tests and as a
+//! non-persistent fallback, so storage logic can be exercised without
+//! touching the filesystem.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::storage::lmdb::LmdbStorage;
+
+/// Basic operations every storage backend provides
+pub trait KvStore: Send + Sync {
+    /// Backend name for logging
+    fn name(&self) -> &'static str;
+
+    /// Get the raw value stored under `key`
+    fn get(&self, key: &str) -> Result<Option<String>>;
+
+    /// Store a raw value under `key`
+    fn put(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Delete `key`; deleting a missing key is not an error
+    fn delete(&self, key: &str) -> Result<()>;
+
+    /// All keys starting with `prefix`
+    fn scan(&self, prefix: &str) -> Result<Vec<String>>;
+
+    /// Check whether `key` exists
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Store a value that expires after `ttl`
+    ///
+    /// Values written with a TTL must be read back with [`KvStore::get_with_ttl`].
+    fn put_with_ttl(&self, key: &str, value: &str, ttl: Duration) -> Result<()> {
+        let entry = TtlEntry {
+            value: value.to_string(),
+            expires_at: unix_now() + ttl.as_secs().max(1),
+        };
+        self.put(key, &serde_json::to_string(&entry)?)
+    }
+
+    /// Get a value written with [`KvStore::put_with_ttl`], removing it if expired
+    fn get_with_ttl(&self, key: &str) -> Result<Option<String>> {
+        let Some(raw) = self.get(key)? else {
+            return Ok(None);
+        };
+        let entry: TtlEntry = serde_json::from_str(&raw)?;
+        if entry.expires_at > unix_now() {
+            Ok(Some(entry.value))
+        } else {
+            self.delete(key)?;
+            Ok(None)
+        }
+    }
+
+    /// Time left before a value written with [`KvStore::put_with_ttl`] expires
+    fn ttl_remaining(&self, key: &str) -> Result<Option<Duration>> {
+        let Some(raw) = self.get(key)? else {
+            return Ok(None);
+        };
+        let entry: TtlEntry = serde_json::from_str(&raw)?;
+        Ok(entry.expires_at
+            .checked_sub(unix_now())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs))
+    }
+}
+
+impl dyn KvStore + '_ {
+    /// Store a serializable value as JSON
+    pub fn put_json<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        self.put(key, &serde_json::to_string(value)?)
+    }
+
+    /// Get a JSON value and deserialize it
+    pub fn get_json<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        match self.get(key)? {
+            Some(json_str) => Ok(Some(serde_json::from_str(&json_str)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Envelope for values stored with a TTL
+#[derive(Debug, Serialize, Deserialize)]
+struct TtlEntry {
+    value: String,
+    expires_at: u64,
+}
+
+impl KvStore for LmdbStorage {
+    fn name(&self) -> &'static str {
+        "lmdb"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        LmdbStorage::get(self, key)
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<()> {
+        LmdbStorage::put(self, key, value)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        LmdbStorage::delete(self, key)
+    }
+
+    fn scan(&self, prefix: &str) -> Result<Vec<String>> {
+        self.get_keys_with_prefix(prefix)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        LmdbStorage::exists(self, key)
+    }
+}
+
+/// In-memory store, used by tests and as a non-persistent fallback
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: RwLock<BTreeMap<String, String>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KvStore for MemoryStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        Ok(entries.get(key).cloned())
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.remove(key);
+        Ok(())
+    }
+
+    fn scan(&self, prefix: &str) -> Result<Vec<String>> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        Ok(entries
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| key.clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_store(store: &dyn KvStore) {
+        store.put("a:1", "one").unwrap();
+        store.put("a:2", "two").unwrap();
+        store.put("b:1", "three").unwrap();
+
+        assert_eq!(store.get("a:1").unwrap(), Some("one".to_string()));
+        assert!(store.exists("b:1").unwrap());
+
+        let mut keys = store.scan("a:").unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["a:1", "a:2"]);
+
+        store.delete("a:1").unwrap();
+        store.delete("missing").unwrap();
+        assert_eq!(store.get("a:1").unwrap(), None);
+
+        store.put_json("json", &(1u64, 2u64)).unwrap();
+        assert_eq!(store.get_json::<(u64, u64)>("json").unwrap(), Some((1, 2)));
+
+        store.put_with_ttl("ttl", "fresh", Duration::from_secs(60)).unwrap();
+        assert_eq!(store.get_with_ttl("ttl").unwrap(), Some("fresh".to_string()));
+        assert!(store.ttl_remaining("ttl").unwrap().is_some());
+
+        let expired = TtlEntry { value: "old".to_string(), expires_at: unix_now() - 1 };
+        store.put("expired", &serde_json::to_string(&expired).unwrap()).unwrap();
+        assert_eq!(store.get_with_ttl("expired").unwrap(), None);
+        assert!(!store.exists("expired").unwrap());
+    }
+
+    #[test]
+    fn test_memory_store() {
+        check_store(&MemoryStore::new());
+    }
+
+    #[test]
+    fn test_lmdb_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LmdbStorage::new(dir.path().to_str().unwrap()).unwrap();
+        check_store(&store);
+    }
+}
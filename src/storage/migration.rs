@@ -0,0 +1,559 @@
+// WHOIS Server - Database Schema Versioning
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Schema versions, migrations and export/import for the LMDB databases
+//!
+//! Every persistent database records the version of its record format under
+//! [`SCHEMA_VERSION_KEY`]. At startup [`run_startup_migrations`] brings each
+//! database up to the version this build understands by applying the
+//! registered [`Migration`]s in order, and refuses to open a database written
+//! by a newer build instead of silently misreading or discarding its data.
+//!
+//! The same registry backs the `db` maintenance subcommand, which reports
+//! schema versions and exports/imports databases as JSON Lines files.
+//!
+//! When changing a stored record format, bump the database's schema version
+//! in [`managed_databases`] and add a migration to the new version. The DN42
+//! registry database is not managed here: it is rebuilt from the registry.
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::{
+    CACHE_LMDB_PATH, CRTWATCH_LMDB_PATH, DIGEST_LMDB_PATH, DbArgs, HISTORY_LMDB_PATH, IANA_LMDB_PATH, ICP_LMDB_PATH, MANRS_LMDB_PATH,
+    MONITOR_LMDB_PATH, PATCHES_LMDB_PATH, PEERINGDB_LMDB_PATH, PEN_LMDB_PATH, STATS_LMDB_PATH, USAGE_LMDB_PATH, VERIFY_LMDB_PATH,
+};
+use crate::log_info;
+use crate::ssh::history::SshConnectionHistory;
+use crate::storage::kv::KvStore;
+use crate::storage::lmdb::LmdbStorage;
+
+/// Key holding the schema version of a database
+pub const SCHEMA_VERSION_KEY: &str = "__meta__schema_version";
+
+/// Prefix of bookkeeping keys, never exported or migrated as records
+const META_KEY_PREFIX: &str = "__meta__";
+
+/// Version of databases written before schema versioning was introduced
+const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+/// A step upgrading a database from `to_version - 1` to `to_version`
+pub struct Migration {
+    pub to_version: u32,
+    pub description: &'static str,
+    pub apply: fn(&dyn KvStore) -> Result<()>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Lmdb,
+    SshHistory,
+}
+
+/// A database whose record format is versioned
+pub struct ManagedDatabase {
+    pub name: &'static str,
+    pub path: PathBuf,
+    /// Schema version written by this build
+    pub schema_version: u32,
+    /// Migrations to `schema_version`, ordered by target version
+    pub migrations: &'static [Migration],
+    backend: Backend,
+}
+
+impl ManagedDatabase {
+    fn lmdb(name: &'static str, path: &str) -> Self {
+        Self {
+            name,
+            path: PathBuf::from(path),
+            schema_version: 1,
+            migrations: &[],
+            backend: Backend::Lmdb,
+        }
+    }
+
+    /// Whether the database has been created on disk
+    pub fn exists(&self) -> bool {
+        match self.backend {
+            Backend::Lmdb => self.path.exists(),
+            Backend::SshHistory => SshConnectionHistory::lmdb_dir(&self.path).exists(),
+        }
+    }
+
+    /// Open the database, creating it if it does not exist
+    pub fn open(&self) -> Result<Box<dyn KvStore>> {
+        let store: Box<dyn KvStore> = match self.backend {
+            Backend::Lmdb => {
+                let path = self.path.to_str().ok_or_else(|| anyhow!("Non UTF-8 database path"))?;
+                Box::new(LmdbStorage::new(path)?)
+            }
+            Backend::SshHistory => Box::new(SshConnectionHistory::new(&self.path)?),
+        };
+        Ok(store)
+    }
+}
+
+/// All databases with a versioned record format
+pub fn managed_databases(ssh_cache_dir: &str) -> Vec<ManagedDatabase> {
+    vec![
+        ManagedDatabase::lmdb("stats", STATS_LMDB_PATH),
+        ManagedDatabase::lmdb("shared_cache", CACHE_LMDB_PATH),
+        ManagedDatabase::lmdb("pen", PEN_LMDB_PATH),
+        ManagedDatabase::lmdb("iana", IANA_LMDB_PATH),
+        ManagedDatabase::lmdb("icp", ICP_LMDB_PATH),
+        ManagedDatabase::lmdb("peeringdb", PEERINGDB_LMDB_PATH),
+        ManagedDatabase::lmdb("manrs", MANRS_LMDB_PATH),
+        ManagedDatabase::lmdb("patches", PATCHES_LMDB_PATH),
+        ManagedDatabase::lmdb("verified", VERIFY_LMDB_PATH),
+        ManagedDatabase::lmdb("crtwatch", CRTWATCH_LMDB_PATH),
+        ManagedDatabase::lmdb("history", HISTORY_LMDB_PATH),
+        ManagedDatabase::lmdb("usage", USAGE_LMDB_PATH),
+        ManagedDatabase::lmdb("monitor", MONITOR_LMDB_PATH),
+        ManagedDatabase::lmdb("digest", DIGEST_LMDB_PATH),
+        ManagedDatabase {
+            name: "ssh_history",
+            path: Path::new(ssh_cache_dir).join("history.lmdb"),
+            schema_version: 1,
+            migrations: &[],
+            backend: Backend::SshHistory,
+        },
+    ]
+}
+
+fn is_meta_key(key: &str) -> bool {
+    key.starts_with(META_KEY_PREFIX)
+}
+
+/// Keys of all records, excluding bookkeeping keys
+fn record_keys(store: &dyn KvStore) -> Result<Vec<String>> {
+    Ok(store
+        .scan("")?
+        .into_iter()
+        .filter(|key| !is_meta_key(key))
+        .collect())
+}
+
+/// Schema version recorded in the database, if any
+pub fn stored_schema_version(store: &dyn KvStore) -> Result<Option<u32>> {
+    match store.get(SCHEMA_VERSION_KEY)? {
+        Some(value) => {
+            let version = value
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid schema version {:?}", value))?;
+            Ok(Some(version))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Schema version of the data currently in the database
+///
+/// An unversioned database holding records predates schema versioning; an
+/// empty one has nothing to migrate and is at the current version.
+fn effective_schema_version(db: &ManagedDatabase, store: &dyn KvStore) -> Result<u32> {
+    match stored_schema_version(store)? {
+        Some(version) => Ok(version),
+        None if record_keys(store)?.is_empty() => Ok(db.schema_version),
+        None => Ok(LEGACY_SCHEMA_VERSION),
+    }
+}
+
+fn set_schema_version(store: &dyn KvStore, version: u32) -> Result<()> {
+    store.put(SCHEMA_VERSION_KEY, &version.to_string())
+}
+
+/// Result of migrating one database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub database: &'static str,
+    pub from_version: u32,
+    pub to_version: u32,
+}
+
+/// Bring a database up to the schema version of this build
+///
+/// Progress is recorded after every step, so an interrupted migration
+/// resumes where it stopped.
+pub fn migrate(db: &ManagedDatabase, store: &dyn KvStore) -> Result<MigrationReport> {
+    let stored = stored_schema_version(store)?;
+    let from_version = effective_schema_version(db, store)?;
+
+    if from_version > db.schema_version {
+        bail!(
+            "Database '{}' has schema version {} but this build supports up to {}; refusing to use it (upgrade the server or restore an export)",
+            db.name,
+            from_version,
+            db.schema_version
+        );
+    }
+
+    let mut version = from_version;
+    for migration in db.migrations.iter().filter(|m| m.to_version > from_version) {
+        if migration.to_version != version + 1 {
+            break;
+        }
+        log_info!(
+            "Migrating {} database to schema v{}: {}",
+            db.name,
+            migration.to_version,
+            migration.description
+        );
+        (migration.apply)(store).with_context(|| {
+            format!("Migration of {} database to schema v{} failed", db.name, migration.to_version)
+        })?;
+        version = migration.to_version;
+        set_schema_version(store, version)?;
+    }
+
+    if version != db.schema_version {
+        bail!(
+            "No migration for {} database from schema v{} to v{}",
+            db.name,
+            version,
+            version + 1
+        );
+    }
+
+    if stored != Some(version) {
+        set_schema_version(store, version)?;
+    }
+
+    Ok(MigrationReport { database: db.name, from_version, to_version: version })
+}
+
+/// Migrate every database before the services open them
+///
+/// The SSH history database is only touched when SSH is enabled or it
+/// already exists.
+pub fn run_startup_migrations(ssh_cache_dir: &str, enable_ssh: bool) -> Result<Vec<MigrationReport>> {
+    let mut reports = Vec::new();
+    for db in managed_databases(ssh_cache_dir) {
+        if db.backend == Backend::SshHistory && !enable_ssh && !db.exists() {
+            continue;
+        }
+        let store = db.open().with_context(|| format!("Failed to open {} database", db.name))?;
+        reports.push(migrate(&db, store.as_ref())?);
+    }
+    Ok(reports)
+}
+
+/// First line of an export file
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportHeader {
+    database: String,
+    schema_version: u32,
+}
+
+/// One record of an export file
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportRecord {
+    key: String,
+    value: String,
+}
+
+fn export_path(db: &ManagedDatabase, dir: &Path) -> PathBuf {
+    dir.join(format!("{}.jsonl", db.name))
+}
+
+/// Write all records of a database to `<dir>/<name>.jsonl`
+///
+/// Returns the number of records written.
+pub fn export_database(db: &ManagedDatabase, store: &dyn KvStore, dir: &Path) -> Result<usize> {
+    let header = ExportHeader {
+        database: db.name.to_string(),
+        schema_version: effective_schema_version(db, store)?,
+    };
+
+    let path = export_path(db, dir);
+    let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, &header)?;
+    writeln!(writer)?;
+
+    let mut count = 0;
+    for key in record_keys(store)? {
+        if let Some(value) = store.get(&key)? {
+            serde_json::to_writer(&mut writer, &ExportRecord { key, value })?;
+            writeln!(writer)?;
+            count += 1;
+        }
+    }
+
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Load records from `<dir>/<name>.jsonl` and migrate them to the current schema
+///
+/// Records are merged into the database, overwriting existing keys. Returns
+/// `None` if there is no export file for this database.
+pub fn import_database(db: &ManagedDatabase, store: &dyn KvStore, dir: &Path) -> Result<Option<usize>> {
+    let path = export_path(db, dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut lines = BufReader::new(file).lines();
+    let header_line = lines
+        .next()
+        .ok_or_else(|| anyhow!("{} is empty", path.display()))??;
+    let header: ExportHeader = serde_json::from_str(&header_line)
+        .with_context(|| format!("{}: invalid export header", path.display()))?;
+
+    if header.database != db.name {
+        bail!("{} contains the {} database, not {}", path.display(), header.database, db.name);
+    }
+    if header.schema_version > db.schema_version {
+        bail!(
+            "{} has schema version {} but this build supports up to {}",
+            path.display(),
+            header.schema_version,
+            db.schema_version
+        );
+    }
+
+    // Existing records and imported records must be in the same format,
+    // otherwise the migration below would run on a mix of both
+    let current_version = effective_schema_version(db, store)?;
+    if current_version != header.schema_version && !record_keys(store)?.is_empty() {
+        bail!(
+            "Cannot merge schema v{} export into {} database at schema v{}; import into an empty database instead",
+            header.schema_version,
+            db.name,
+            current_version
+        );
+    }
+
+    let mut count = 0;
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: ExportRecord = serde_json::from_str(&line)
+            .with_context(|| format!("{}:{}: invalid record", path.display(), index + 2))?;
+        if is_meta_key(&record.key) {
+            continue;
+        }
+        store.put(&record.key, &record.value)?;
+        count += 1;
+    }
+
+    set_schema_version(store, header.schema_version)?;
+    migrate(db, store)?;
+
+    Ok(Some(count))
+}
+
+/// Run the `db` maintenance subcommand
+pub fn run_db_command(args: &DbArgs, ssh_cache_dir: &str) -> Result<()> {
+    let mut databases = managed_databases(ssh_cache_dir);
+    if !args.databases.is_empty() {
+        for name in &args.databases {
+            if !databases.iter().any(|db| db.name == name.as_str()) {
+                let known: Vec<_> = databases.iter().map(|db| db.name).collect();
+                bail!("Unknown database '{}' (known: {})", name, known.join(", "));
+            }
+        }
+        databases.retain(|db| args.databases.iter().any(|name| name.as_str() == db.name));
+    }
+
+    if let Some(dir) = &args.export {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        for db in &databases {
+            if !db.exists() {
+                println!("{:<14} skipped, not created yet", db.name);
+                continue;
+            }
+            let store = db.open()?;
+            let count = export_database(db, store.as_ref(), dir)?;
+            println!("{:<14} exported {} records to {}", db.name, count, export_path(db, dir).display());
+        }
+    } else if let Some(dir) = &args.import {
+        for db in &databases {
+            if !export_path(db, dir).exists() {
+                println!("{:<14} skipped, no export file", db.name);
+                continue;
+            }
+            let store = db.open()?;
+            let count = import_database(db, store.as_ref(), dir)?.unwrap_or(0);
+            println!("{:<14} imported {} records (schema v{})", db.name, count, db.schema_version);
+        }
+    } else if args.migrate {
+        for db in &databases {
+            if !db.exists() {
+                continue;
+            }
+            let store = db.open()?;
+            let report = migrate(db, store.as_ref())?;
+            println!("{:<14} schema v{} -> v{}", db.name, report.from_version, report.to_version);
+        }
+    } else {
+        for db in &databases {
+            if !db.exists() {
+                println!("{:<14} not created ({})", db.name, db.path.display());
+                continue;
+            }
+            let store = db.open()?;
+            let version = match stored_schema_version(store.as_ref())? {
+                Some(version) => format!("v{}", version),
+                None => "unversioned".to_string(),
+            };
+            println!(
+                "{:<14} {:<12} current v{}  {} records  ({})",
+                db.name,
+                version,
+                db.schema_version,
+                record_keys(store.as_ref())?.len(),
+                db.path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::kv::MemoryStore;
+
+    static TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            to_version: 2,
+            description: "rename count to total",
+            apply: |store| {
+                for key in record_keys(store)? {
+                    if let Some(value) = store.get(&key)? {
+                        store.put(&key, &value.replace("count", "total"))?;
+                    }
+                }
+                Ok(())
+            },
+        },
+        Migration {
+            to_version: 3,
+            description: "prefix keys",
+            apply: |store| {
+                for key in record_keys(store)? {
+                    if let Some(value) = store.get(&key)? {
+                        store.delete(&key)?;
+                        store.put(&format!("v3:{}", key), &value)?;
+                    }
+                }
+                Ok(())
+            },
+        },
+    ];
+
+    fn test_database() -> ManagedDatabase {
+        ManagedDatabase {
+            name: "test",
+            path: PathBuf::from("unused"),
+            schema_version: 3,
+            migrations: TEST_MIGRATIONS,
+            backend: Backend::Lmdb,
+        }
+    }
+
+    #[test]
+    fn test_unversioned_data_is_migrated() {
+        let db = test_database();
+        let store = MemoryStore::new();
+        store.put("a", "count=1").unwrap();
+
+        let report = migrate(&db, &store).unwrap();
+        assert_eq!(report.from_version, LEGACY_SCHEMA_VERSION);
+        assert_eq!(report.to_version, 3);
+        assert_eq!(store.get("v3:a").unwrap(), Some("total=1".to_string()));
+        assert_eq!(stored_schema_version(&store).unwrap(), Some(3));
+
+        // Running again is a no-op
+        let report = migrate(&db, &store).unwrap();
+        assert_eq!(report.from_version, 3);
+        assert_eq!(store.get("v3:a").unwrap(), Some("total=1".to_string()));
+    }
+
+    #[test]
+    fn test_empty_database_is_stamped_without_migrating() {
+        let db = test_database();
+        let store = MemoryStore::new();
+        let report = migrate(&db, &store).unwrap();
+        assert_eq!(report.from_version, 3);
+        assert_eq!(stored_schema_version(&store).unwrap(), Some(3));
+    }
+
+    #[test]
+    fn test_newer_schema_is_refused() {
+        let db = test_database();
+        let store = MemoryStore::new();
+        store.put("a", "data").unwrap();
+        set_schema_version(&store, 4).unwrap();
+
+        assert!(migrate(&db, &store).is_err());
+        assert_eq!(store.get("a").unwrap(), Some("data".to_string()));
+    }
+
+    #[test]
+    fn test_missing_migration_step_is_an_error() {
+        let mut db = test_database();
+        db.schema_version = 4;
+        let store = MemoryStore::new();
+        set_schema_version(&store, 3).unwrap();
+        assert!(migrate(&db, &store).is_err());
+    }
+
+    #[test]
+    fn test_export_import_roundtrip_migrates_old_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut old = test_database();
+        old.schema_version = 2;
+        old.migrations = &TEST_MIGRATIONS[..1];
+
+        let source = MemoryStore::new();
+        source.put("a", "count=1").unwrap();
+        source.put("b", "count=2").unwrap();
+        migrate(&old, &source).unwrap();
+        assert_eq!(export_database(&old, &source, dir.path()).unwrap(), 2);
+
+        let db = test_database();
+        let target = MemoryStore::new();
+        assert_eq!(import_database(&db, &target, dir.path()).unwrap(), Some(2));
+        assert_eq!(target.get("v3:a").unwrap(), Some("total=1".to_string()));
+        assert_eq!(target.get("v3:b").unwrap(), Some("total=2".to_string()));
+        assert_eq!(stored_schema_version(&target).unwrap(), Some(3));
+    }
+
+    #[test]
+    fn test_import_refuses_mixing_schema_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut old = test_database();
+        old.schema_version = 2;
+        old.migrations = &TEST_MIGRATIONS[..1];
+        let source = MemoryStore::new();
+        source.put("a", "count=1").unwrap();
+        migrate(&old, &source).unwrap();
+        export_database(&old, &source, dir.path()).unwrap();
+
+        let db = test_database();
+        let target = MemoryStore::new();
+        target.put("v3:existing", "total=9").unwrap();
+        migrate(&db, &target).unwrap();
+        assert!(import_database(&db, &target, dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_registered_migrations_are_contiguous() {
+        for db in managed_databases("./cache/ssh") {
+            let targets: Vec<u32> = db.migrations.iter().map(|m| m.to_version).collect();
+            let expected: Vec<u32> = (LEGACY_SCHEMA_VERSION + 1..=db.schema_version).collect();
+            assert_eq!(targets, expected, "migrations of {} must cover every version", db.name);
+        }
+    }
+}
@@ -1,3 +1,8 @@
+pub mod cache;
+pub mod kv;
 pub mod lmdb;
+pub mod migration;
+pub mod response_cache;
 
+pub use kv::{KvStore, MemoryStore};
 pub use lmdb::*;
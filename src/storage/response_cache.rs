@@ -0,0 +1,218 @@
+// WHOIS Server - Response Cache
+// Copyright (C) 2025 Akaere Networks
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Cache of upstream responses with per-service TTLs
+//!
+//! The query processors look a query up here before contacting upstream
+//! WHOIS servers and HTTP APIs. Entries live in the configured cache backend
+//! (LMDB, or Redis when instances share a cache) under `response:<key>`,
+//! and expire after the TTL of the query type (`core::query::cache_ttl`).
+//! A `-NOCACHE` query skips the lookup; its fresh response still replaces
+//! the cached one. Failed lookups are never cached. Prefetch
+//! (`core::prefetch`) refreshes the entries of popular queries before they
+//! expire.
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::core::provenance::record_source;
+use crate::storage::cache::{CacheStore, cache_store};
+use crate::log_debug;
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Response cache counters exposed through the stats API
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResponseCacheStatsEntry {
+    pub enabled: bool,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+}
+
+fn cache_key(key: &str) -> String {
+    format!("response:{}", key)
+}
+
+/// Whether a response reports a failed lookup instead of data
+///
+/// Several services answer upstream errors with an explanatory text rather
+/// than an error; those must not be served for the whole TTL.
+fn is_failure(response: &str) -> bool {
+    let first_line = response.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+    response.trim().is_empty() ||
+        first_line.starts_with("Error") ||
+        first_line.starts_with("% Error") ||
+        first_line.contains("Query Failed")
+}
+
+/// Serve a response from `store`, or run `fetch` and store its response
+async fn lookup_in<F, Fut>(store: &dyn CacheStore, key: &str, ttl: Duration, bypass: bool, fetch: F) -> Result<String>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let key = cache_key(key);
+    if !bypass {
+        let start = Instant::now();
+        if let Ok(Some(response)) = store.get(&key).await {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            record_source("cache", "response cache", start.elapsed(), "cache hit");
+            return Ok(response);
+        }
+        MISSES.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let response = fetch().await?;
+    if !is_failure(&response)
+        && let Err(e) = store.set(&key, &response, ttl).await
+    {
+        log_debug!("Failed to cache response for {}: {}", key, e);
+    }
+    Ok(response)
+}
+
+/// Answer from the response cache, or run `fetch` and cache its response
+///
+/// `ttl` is `None` for query types that are never cached; `bypass` skips the
+/// lookup (`-NOCACHE`). Without a cache backend `fetch` always runs.
+pub async fn cached_lookup<F, Fut>(key: &str, ttl: Option<Duration>, bypass: bool, fetch: F) -> Result<String>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    match (ttl, cache_store()) {
+        (Some(ttl), Some(store)) => lookup_in(store.as_ref(), key, ttl, bypass, fetch).await,
+        _ => fetch().await,
+    }
+}
+
+/// Fetch the response for `key` again if its entry is missing or expires
+/// within `window`
+async fn refresh_in<F, Fut>(store: &dyn CacheStore, key: &str, ttl: Duration, window: Duration, fetch: F) -> Result<bool>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let key = cache_key(key);
+    if let Ok(Some(remaining)) = store.ttl(&key).await
+        && remaining > window
+    {
+        return Ok(false);
+    }
+
+    let response = fetch().await?;
+    if is_failure(&response) {
+        return Err(anyhow!("upstream reported a failed lookup"));
+    }
+    store.set(&key, &response, ttl).await?;
+    Ok(true)
+}
+
+/// Refresh a cached response ahead of its expiry
+///
+/// Runs `fetch` and stores its response for `ttl` when the entry is missing
+/// or expires within `window`. Returns whether it was refreshed; without a
+/// cache backend nothing is.
+pub async fn refresh_expiring<F, Fut>(key: &str, ttl: Duration, window: Duration, fetch: F) -> Result<bool>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    match cache_store() {
+        Some(store) => refresh_in(store.as_ref(), key, ttl, window, fetch).await,
+        None => Ok(false),
+    }
+}
+
+/// Remove cached responses, those expiring soonest first, until at least
+/// `bytes` are gone
+///
+/// Returns the bytes removed.
+pub async fn evict_responses(bytes: u64) -> Result<u64> {
+    match cache_store() {
+        Some(store) => store.evict_expiring("response:", bytes).await,
+        None => Ok(0),
+    }
+}
+
+/// Current response cache counters
+pub fn response_cache_stats() -> ResponseCacheStatsEntry {
+    let hits = HITS.load(Ordering::Relaxed);
+    let misses = MISSES.load(Ordering::Relaxed);
+    let total = hits + misses;
+    ResponseCacheStatsEntry {
+        enabled: cache_store().is_some(),
+        hits,
+        misses,
+        hit_rate: if total == 0 { 0.0 } else { (hits as f64) / (total as f64) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::cache::KvCacheStore;
+    use crate::storage::kv::MemoryStore;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_lookup_caches_and_bypasses() {
+        let store = KvCacheStore::new(Arc::new(MemoryStore::new()));
+        let ttl = Duration::from_secs(60);
+
+        let first = lookup_in(&store, "geo:1.1.1.1", ttl, false, || async { Ok("first\n".to_string()) }).await;
+        assert_eq!(first.unwrap(), "first\n");
+        let cached = lookup_in(&store, "geo:1.1.1.1", ttl, false, || async { Ok("second\n".to_string()) }).await;
+        assert_eq!(cached.unwrap(), "first\n");
+
+        // -NOCACHE fetches again and refreshes the entry
+        let fresh = lookup_in(&store, "geo:1.1.1.1", ttl, true, || async { Ok("third\n".to_string()) }).await;
+        assert_eq!(fresh.unwrap(), "third\n");
+        assert_eq!(store.get("response:geo:1.1.1.1").await.unwrap(), Some("third\n".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_not_cached() {
+        let store = KvCacheStore::new(Arc::new(MemoryStore::new()));
+        let ttl = Duration::from_secs(60);
+
+        let failed = "SSL Certificate Query Failed for example.com:443\nError: timed out\n";
+        let response = lookup_in(&store, "ssl:example.com", ttl, false, || async { Ok(failed.to_string()) }).await;
+        assert_eq!(response.unwrap(), failed);
+        assert_eq!(store.get("response:ssl:example.com").await.unwrap(), None);
+
+        let error = lookup_in(&store, "ssl:example.com", ttl, false, || async { Err(anyhow::anyhow!("down")) }).await;
+        assert!(error.is_err());
+        assert!(is_failure("% Error: upstream unavailable\n"));
+        assert!(!is_failure("aut-num: AS13335\n"));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_before_expiry() {
+        let store = KvCacheStore::new(Arc::new(MemoryStore::new()));
+        let ttl = Duration::from_secs(300);
+        let window = Duration::from_secs(60);
+
+        let refreshed = refresh_in(&store, "asn:AS13335", ttl, window, || async { Ok("first\n".to_string()) }).await;
+        assert!(refreshed.unwrap());
+        // Far from expiry the entry is left alone
+        let refreshed = refresh_in(&store, "asn:AS13335", ttl, window, || async { Ok("second\n".to_string()) }).await;
+        assert!(!refreshed.unwrap());
+        assert_eq!(store.get("response:asn:AS13335").await.unwrap(), Some("first\n".to_string()));
+
+        // Within the window it is fetched again
+        let refreshed = refresh_in(&store, "asn:AS13335", ttl, ttl, || async { Ok("third\n".to_string()) }).await;
+        assert!(refreshed.unwrap());
+        assert_eq!(store.get("response:asn:AS13335").await.unwrap(), Some("third\n".to_string()));
+
+        let failed = refresh_in(&store, "asn:AS0", ttl, window, || async { Ok("% Error: timed out\n".to_string()) }).await;
+        assert!(failed.is_err());
+        assert_eq!(store.get("response:asn:AS0").await.unwrap(), None);
+    }
+}
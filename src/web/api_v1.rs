@@ -0,0 +1,509 @@
+/*
+ * Versioned JSON API
+ * Copyright (C) 2025 Akaere Networks
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ */
+
+//! Versioned JSON API under `/api/v1/`
+//!
+//! Unlike `/api/whois`, whose shape follows the dashboard, the v1 schemas are
+//! a contract for third-party tools: fields are always present (empty arrays
+//! and `null` instead of omitted keys) and are never renamed or removed
+//! within v1. New optional fields may be added. The OpenAPI document served
+//! at `/api/v1/openapi.json` is generated by [`openapi_spec`] from the same
+//! definitions the tests check the response types against.
+
+use crate::core::query_processor::process_query;
+use crate::core::sanitize::sanitize_query;
+use crate::core::telemetry::query_type_to_string;
+use crate::core::warmup::{ Subsystem, pending_subsystems };
+use crate::core::{ StatsState, analyze_query, get_stats_response, record_request };
+use crate::services::registry::service_registry;
+use crate::web::auth::WebClient;
+use crate::web::json_formatter::{ parse_comments, parse_objects };
+use axum::{
+    Router,
+    extract::{ Query, State },
+    http::StatusCode,
+    response::{ IntoResponse, Json, Response },
+    routing::get,
+};
+use futures::stream::{ self, StreamExt };
+use serde::{ Deserialize, Serialize };
+use serde_json::{ Map, Value, json };
+use std::time::Instant;
+
+/// Most queries accepted in one bulk request
+const MAX_BULK_QUERIES: usize = 25;
+/// Bulk queries processed at the same time
+const BULK_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Deserialize)]
+struct QueryRequest {
+    q: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BulkRequest {
+    queries: Vec<String>,
+}
+
+/// Result of one query
+#[derive(Debug, Serialize)]
+struct QueryResult {
+    query: String,
+    query_type: String,
+    success: bool,
+    output: String,
+    objects: Vec<Map<String, Value>>,
+    comments: Vec<String>,
+    error: Option<String>,
+    processing_time_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct BulkResult {
+    results: Vec<QueryResult>,
+    processing_time_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct StatsResult {
+    total_requests: u64,
+    total_bytes_served: u64,
+    requests_24h: u64,
+    requests_30d: u64,
+    dropped_connections: u64,
+    cache_hit_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct HealthResult {
+    status: &'static str,
+    version: &'static str,
+    services: usize,
+    /// Subsystems still initializing in the background
+    warming_up: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiError {
+    error: String,
+}
+
+fn bad_request(message: &str) -> Response {
+    (StatusCode::BAD_REQUEST, Json(ApiError { error: message.to_string() })).into_response()
+}
+
+/// Routes of the v1 API
+pub fn router() -> Router<StatsState> {
+    Router::new()
+        .route("/api/v1/query", get(query_get).post(query_post))
+        .route("/api/v1/bulk", axum::routing::post(bulk))
+        .route("/api/v1/stats", get(stats))
+        .route("/api/v1/health", get(health))
+        .route("/api/v1/openapi.json", get(openapi))
+}
+
+async fn run_query(query: &str, client: &WebClient, stats: &StatsState) -> QueryResult {
+    let start_time = Instant::now();
+    let query = sanitize_query(query.trim());
+    let query_type = analyze_query(&query);
+    let ctx = client.context();
+
+    let (output, error) = match process_query(&query, &query_type, &ctx).await {
+        Ok(output) => {
+            record_request(stats, output.len()).await;
+            (output, None)
+        }
+        Err(e) => (String::new(), Some(format!("Query processing failed: {}", e))),
+    };
+    QueryResult {
+        query_type: query_type_to_string(&query_type),
+        success: error.is_none() && !output.trim().is_empty(),
+        objects: parse_objects(&output),
+        comments: parse_comments(&output),
+        output,
+        error,
+        query,
+        processing_time_ms: start_time.elapsed().as_millis() as u64,
+    }
+}
+
+// GET /api/v1/query?q=query
+async fn query_get(State(stats): State<StatsState>, client: WebClient, Query(params): Query<QueryRequest>) -> Response {
+    if params.q.trim().is_empty() {
+        return bad_request("Query parameter 'q' is required and cannot be empty");
+    }
+    Json(run_query(&params.q, &client, &stats).await).into_response()
+}
+
+// POST /api/v1/query with JSON body: {"q": "query"}
+async fn query_post(State(stats): State<StatsState>, client: WebClient, Json(request): Json<QueryRequest>) -> Response {
+    if request.q.trim().is_empty() {
+        return bad_request("Query field 'q' is required and cannot be empty");
+    }
+    Json(run_query(&request.q, &client, &stats).await).into_response()
+}
+
+// POST /api/v1/bulk with JSON body: {"queries": ["query", ...]}
+async fn bulk(State(stats): State<StatsState>, client: WebClient, Json(request): Json<BulkRequest>) -> Response {
+    let start_time = Instant::now();
+    if request.queries.is_empty() || request.queries.iter().any(|q| q.trim().is_empty()) {
+        return bad_request("Field 'queries' must be a non-empty list of non-empty queries");
+    }
+    if request.queries.len() > MAX_BULK_QUERIES {
+        return bad_request(&format!("At most {} queries are accepted per bulk request", MAX_BULK_QUERIES));
+    }
+
+    let results: Vec<QueryResult> = stream
+        ::iter(request.queries.iter())
+        .map(|query| run_query(query, &client, &stats))
+        .buffered(BULK_CONCURRENCY)
+        .collect().await;
+    Json(BulkResult { results, processing_time_ms: start_time.elapsed().as_millis() as u64 }).into_response()
+}
+
+async fn stats(State(stats): State<StatsState>) -> Json<StatsResult> {
+    let response = get_stats_response(&stats).await;
+    Json(StatsResult {
+        total_requests: response.total_requests,
+        total_bytes_served: response.total_bytes_served,
+        requests_24h: response.daily_stats_24h.iter().map(|day| day.requests).sum(),
+        requests_30d: response.daily_stats_30d.iter().map(|day| day.requests).sum(),
+        dropped_connections: response.dropped_connections.total,
+        cache_hit_rate: response.response_cache.hit_rate,
+    })
+}
+
+async fn health() -> Json<HealthResult> {
+    Json(HealthResult {
+        status: "ok",
+        version: env!("CARGO_PKG_VERSION"),
+        services: service_registry().len(),
+        warming_up: pending_subsystems().into_iter().map(Subsystem::name).collect(),
+    })
+}
+
+async fn openapi() -> Json<Value> {
+    Json(openapi_spec())
+}
+
+/// Component schemas of the v1 responses
+fn schemas() -> Value {
+    json!({
+        "QueryResult": {
+            "type": "object",
+            "required": ["query", "query_type", "success", "output", "objects", "comments", "error", "processing_time_ms"],
+            "properties": {
+                "query": { "type": "string", "description": "Query as processed, after sanitizing" },
+                "query_type": { "type": "string", "description": "Detected query type, e.g. domain, asn or a service name", "example": "asn" },
+                "success": { "type": "boolean", "description": "Whether the query produced a non-empty response" },
+                "output": { "type": "string", "description": "Plain-text response, as returned over WHOIS" },
+                "objects": {
+                    "type": "array",
+                    "description": "Response split into objects (blank-line separated blocks); repeated attributes become arrays",
+                    "items": { "type": "object", "additionalProperties": true }
+                },
+                "comments": {
+                    "type": "array",
+                    "description": "`%` comment lines, without the prefix",
+                    "items": { "type": "string" }
+                },
+                "error": { "type": "string", "nullable": true, "description": "Error message when processing failed" },
+                "processing_time_ms": { "type": "integer", "minimum": 0 }
+            }
+        },
+        "BulkResult": {
+            "type": "object",
+            "required": ["results", "processing_time_ms"],
+            "properties": {
+                "results": {
+                    "type": "array",
+                    "description": "One result per query, in request order",
+                    "items": { "$ref": "#/components/schemas/QueryResult" }
+                },
+                "processing_time_ms": { "type": "integer", "minimum": 0 }
+            }
+        },
+        "StatsResult": {
+            "type": "object",
+            "required": ["total_requests", "total_bytes_served", "requests_24h", "requests_30d", "dropped_connections", "cache_hit_rate"],
+            "properties": {
+                "total_requests": { "type": "integer", "minimum": 0 },
+                "total_bytes_served": { "type": "integer", "minimum": 0 },
+                "requests_24h": { "type": "integer", "minimum": 0, "description": "Requests in the last 24 hours" },
+                "requests_30d": { "type": "integer", "minimum": 0, "description": "Requests in the last 30 days" },
+                "dropped_connections": { "type": "integer", "minimum": 0, "description": "Connections closed without an answer" },
+                "cache_hit_rate": { "type": "number", "description": "Response cache hit rate in percent" }
+            }
+        },
+        "HealthResult": {
+            "type": "object",
+            "required": ["status", "version", "services"],
+            "properties": {
+                "status": { "type": "string", "enum": ["ok"] },
+                "version": { "type": "string", "description": "Server version" },
+                "services": { "type": "integer", "minimum": 0, "description": "Registered suffix services" },
+                "warming_up": { "type": "array", "items": { "type": "string" }, "description": "Subsystems still starting up; their queries fail until they are ready" }
+            }
+        },
+        "Error": {
+            "type": "object",
+            "required": ["error"],
+            "properties": {
+                "error": { "type": "string" }
+            }
+        }
+    })
+}
+
+fn json_response(schema: &str, description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": { "$ref": format!("#/components/schemas/{}", schema) } } }
+    })
+}
+
+/// OpenAPI document of the v1 API
+pub fn openapi_spec() -> Value {
+    let query_body = json!({
+        "required": true,
+        "content": {
+            "application/json": {
+                "schema": {
+                    "type": "object",
+                    "required": ["q"],
+                    "properties": { "q": { "type": "string", "example": "AS13335" } }
+                }
+            }
+        }
+    });
+    let query_responses = json!({
+        "200": json_response("QueryResult", "Query result; failed lookups have success false and an error"),
+        "400": json_response("Error", "Missing or empty query")
+    });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Akaere Networks WHOIS Server API",
+            "description": "Versioned JSON API. Fields of the v1 schemas are never renamed or removed; new optional fields may be added.",
+            "version": format!("1 (server {})", env!("CARGO_PKG_VERSION")),
+            "license": { "name": "AGPL-3.0-or-later", "url": "https://www.gnu.org/licenses/agpl-3.0.html" }
+        },
+        "servers": [
+            { "url": "http://localhost:9999", "description": "Local development server" },
+            { "url": "https://whois.akae.re", "description": "Production server" }
+        ],
+        "paths": {
+            "/api/v1/query": {
+                "get": {
+                    "summary": "Run one query",
+                    "tags": ["v1"],
+                    "parameters": [{
+                        "name": "q",
+                        "in": "query",
+                        "required": true,
+                        "description": "Any query accepted over WHOIS, including suffixes and modifiers",
+                        "schema": { "type": "string" },
+                        "example": "AS13335"
+                    }],
+                    "responses": query_responses.clone()
+                },
+                "post": {
+                    "summary": "Run one query",
+                    "tags": ["v1"],
+                    "requestBody": query_body,
+                    "responses": query_responses
+                }
+            },
+            "/api/v1/bulk": {
+                "post": {
+                    "summary": format!("Run up to {} queries", MAX_BULK_QUERIES),
+                    "tags": ["v1"],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "required": ["queries"],
+                                    "properties": {
+                                        "queries": {
+                                            "type": "array",
+                                            "minItems": 1,
+                                            "maxItems": MAX_BULK_QUERIES,
+                                            "items": { "type": "string" },
+                                            "example": ["example.com", "1.1.1.1", "AS13335"]
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": json_response("BulkResult", "Results in request order"),
+                        "400": json_response("Error", "Empty, oversized or invalid query list")
+                    }
+                }
+            },
+            "/api/v1/stats": {
+                "get": {
+                    "summary": "Request and cache counters",
+                    "tags": ["v1"],
+                    "responses": { "200": json_response("StatsResult", "Server statistics") }
+                }
+            },
+            "/api/v1/health": {
+                "get": {
+                    "summary": "Liveness check",
+                    "tags": ["v1"],
+                    "responses": { "200": json_response("HealthResult", "The server is running") }
+                }
+            },
+            "/api/v1/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "tags": ["v1"],
+                    "responses": { "200": { "description": "OpenAPI 3.0 document" } }
+                }
+            }
+        },
+        "components": { "schemas": schemas() },
+        "tags": [{ "name": "v1", "description": "Stable JSON API" }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fields published in v1; they may gain siblings but never disappear
+    const FROZEN_V1: &[(&str, &[&str])] = &[
+        (
+            "QueryResult",
+            &["query", "query_type", "success", "output", "objects", "comments", "error", "processing_time_ms"],
+        ),
+        ("BulkResult", &["results", "processing_time_ms"]),
+        (
+            "StatsResult",
+            &["total_requests", "total_bytes_served", "requests_24h", "requests_30d", "dropped_connections", "cache_hit_rate"],
+        ),
+        ("HealthResult", &["status", "version", "services"]),
+        ("Error", &["error"]),
+    ];
+
+    /// Check a serialized value against a component schema: same keys, matching types
+    fn assert_matches(schemas: &Value, name: &str, value: &Value) {
+        let schema = &schemas[name];
+        let properties = schema["properties"].as_object().unwrap();
+        let object = value.as_object().unwrap_or_else(|| panic!("{} is not an object", name));
+
+        let mut documented: Vec<&String> = properties.keys().collect();
+        let mut serialized: Vec<&String> = object.keys().collect();
+        documented.sort();
+        serialized.sort();
+        assert_eq!(serialized, documented, "{} fields differ from its schema", name);
+
+        for (field, property) in properties {
+            let field_value = &object[field];
+            if field_value.is_null() {
+                assert_eq!(property["nullable"], true, "{}.{} is null but not nullable", name, field);
+                continue;
+            }
+            let matches = match property["type"].as_str().unwrap() {
+                "string" => field_value.is_string(),
+                "integer" => field_value.is_u64(),
+                "number" => field_value.is_number(),
+                "boolean" => field_value.is_boolean(),
+                "object" => field_value.is_object(),
+                "array" => {
+                    let items = field_value.as_array().unwrap();
+                    if let Some(reference) = property["items"]["$ref"].as_str() {
+                        let item_schema = reference.trim_start_matches("#/components/schemas/");
+                        items.iter().for_each(|item| assert_matches(schemas, item_schema, item));
+                    }
+                    true
+                }
+                other => panic!("unexpected schema type {}", other),
+            };
+            assert!(matches, "{}.{} does not match its schema type", name, field);
+        }
+    }
+
+    fn query_result(error: Option<&str>) -> QueryResult {
+        let output = "% Information related to AS13335\n\naut-num: AS13335\nas-name: CLOUDFLARENET\n".to_string();
+        QueryResult {
+            query: "AS13335".to_string(),
+            query_type: "asn".to_string(),
+            success: error.is_none(),
+            objects: parse_objects(&output),
+            comments: parse_comments(&output),
+            output,
+            error: error.map(str::to_string),
+            processing_time_ms: 12,
+        }
+    }
+
+    #[test]
+    fn test_responses_match_schemas() {
+        let schemas = schemas();
+        let samples = [
+            ("QueryResult", serde_json::to_value(query_result(None)).unwrap()),
+            ("QueryResult", serde_json::to_value(query_result(Some("upstream timed out"))).unwrap()),
+            (
+                "BulkResult",
+                serde_json::to_value(BulkResult { results: vec![query_result(None)], processing_time_ms: 30 }).unwrap(),
+            ),
+            (
+                "StatsResult",
+                serde_json::to_value(StatsResult {
+                    total_requests: 10,
+                    total_bytes_served: 2048,
+                    requests_24h: 4,
+                    requests_30d: 10,
+                    dropped_connections: 1,
+                    cache_hit_rate: 50.0,
+                }).unwrap(),
+            ),
+            ("HealthResult", serde_json::to_value(HealthResult { status: "ok", version: "1.0.0", services: 37, warming_up: vec!["DN42 System"] }).unwrap()),
+            ("Error", serde_json::to_value(ApiError { error: "bad".to_string() }).unwrap()),
+        ];
+        for (name, value) in &samples {
+            assert_matches(&schemas, name, value);
+        }
+    }
+
+    #[test]
+    fn test_v1_schemas_are_stable() {
+        let schemas = schemas();
+        for (name, fields) in FROZEN_V1 {
+            let required: Vec<&str> = schemas[name]["required"]
+                .as_array()
+                .unwrap_or_else(|| panic!("schema {} was removed", name))
+                .iter()
+                .map(|field| field.as_str().unwrap())
+                .collect();
+            for field in *fields {
+                assert!(required.contains(field), "v1 field {}.{} is no longer required", name, field);
+            }
+        }
+    }
+
+    #[test]
+    fn test_openapi_references_resolve() {
+        let spec = openapi_spec();
+        let text = spec.to_string();
+        let schemas = spec["components"]["schemas"].as_object().unwrap();
+        for reference in text.split("\"#/components/schemas/").skip(1) {
+            let name = reference.split('"').next().unwrap();
+            assert!(schemas.contains_key(name), "dangling reference to {}", name);
+        }
+        assert!(spec["paths"].as_object().unwrap().keys().all(|path| path.starts_with("/api/v1/")));
+    }
+}
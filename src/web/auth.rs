@@ -0,0 +1,177 @@
+/*
+ * Web frontend login
+ * Copyright (C) 2025 Akaere Networks
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ */
+
+//! Login requirement for the web frontend
+//!
+//! When the auth file sets `web = true`, every route except the health checks
+//! needs `Authorization: Bearer <OIDC token>` or HTTP Basic credentials that
+//! bind to LDAP. Statistics and metrics additionally need the `admin` class.
+//! Users and client addresses with too many failed logins get `429 Too Many
+//! Requests` until their lockout ends, without the directory being asked.
+//!
+//! Handlers take a [`WebClient`] to build the request context of their
+//! queries, so the logged-in user and the client address reach per-client
+//! features (IPAM, audit events) just as they do for port 43 and SSH.
+
+use crate::auth::{ AuthConfig, AuthenticatedUser, Permission, auth };
+use crate::core::request_context::{ Frontend, RequestContext };
+use crate::log_warn;
+use axum::{
+    async_trait,
+    extract::{ ConnectInfo, FromRequestParts, Request },
+    http::{ StatusCode, header, request::Parts },
+    middleware::Next,
+    response::{ IntoResponse, Response },
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::convert::Infallible;
+use std::net::{ IpAddr, SocketAddr };
+
+/// Routes for load balancers and monitoring, served without a login
+const PUBLIC_PATHS: &[&str] = &["/api/v1/health", "/pixiv-proxy-health"];
+
+/// Permission class a route needs
+fn required_permission(path: &str) -> Permission {
+    match path {
+        "/api/stats" | "/api/v1/stats" | "/metrics" => Permission::Admin,
+        _ => Permission::Query,
+    }
+}
+
+fn unauthorized(config: &AuthConfig) -> Response {
+    let mut challenges = Vec::new();
+    if config.has_ldap() {
+        challenges.push("Basic realm=\"whois-server\"");
+    }
+    if config.has_oidc() {
+        challenges.push("Bearer realm=\"whois-server\"");
+    }
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, challenges.join(", "))],
+        "Authentication required\n",
+    ).into_response()
+}
+
+/// Middleware rejecting requests without a sufficient login
+pub async fn require_login(mut request: Request, next: Next) -> Response {
+    let Some(config) = auth().filter(|config| config.requires_web()) else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    if PUBLIC_PATHS.contains(&path) {
+        return next.run(request).await;
+    }
+    let required = required_permission(path);
+
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let result = if let Some(token) = authorization.strip_prefix("Bearer ") {
+        if !config.has_oidc() {
+            return unauthorized(config);
+        }
+        config.verify_token(token.trim()).await
+    } else if let Some(encoded) = authorization.strip_prefix("Basic ") {
+        let credentials = STANDARD.decode(encoded.trim())
+            .ok()
+            .and_then(|decoded| String::from_utf8(decoded).ok());
+        let Some((user, password)) = credentials.as_deref().and_then(|c| c.split_once(':')) else {
+            return unauthorized(config);
+        };
+        if !config.has_ldap() {
+            return unauthorized(config);
+        }
+        let client = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+        if config.login_blocked(user, client) {
+            return (StatusCode::TOO_MANY_REQUESTS, "Too many failed logins, try again later\n").into_response();
+        }
+        config.login(user, password, client).await
+    } else {
+        return unauthorized(config);
+    };
+
+    match result {
+        Ok(Some(user)) if user.permission >= required => {
+            request.extensions_mut().insert(user);
+            next.run(request).await
+        }
+        Ok(Some(user)) => {
+            (StatusCode::FORBIDDEN, format!("{} may not access this resource\n", user.name)).into_response()
+        }
+        Ok(None) => unauthorized(config),
+        Err(e) => {
+            log_warn!("Web login could not be checked: {}", e);
+            (StatusCode::SERVICE_UNAVAILABLE, "Authentication backend unavailable\n").into_response()
+        }
+    }
+}
+
+/// Address and logged-in user of a web request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WebClient {
+    pub addr: Option<IpAddr>,
+    pub user: Option<String>,
+}
+
+impl WebClient {
+    /// Context for the queries of this request
+    pub fn context(&self) -> RequestContext {
+        let ctx = RequestContext::new(Frontend::Web).with_identity(self.user.clone());
+        match self.addr {
+            Some(addr) => ctx.with_client_addr(addr),
+            None => ctx,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for WebClient {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(WebClient {
+            addr: parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip()),
+            user: parts.extensions.get::<AuthenticatedUser>().map(|user| user.name.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_permission() {
+        assert_eq!(required_permission("/metrics"), Permission::Admin);
+        assert_eq!(required_permission("/api/v1/stats"), Permission::Admin);
+        assert_eq!(required_permission("/api/v1/query"), Permission::Query);
+        assert_eq!(required_permission("/"), Permission::Query);
+    }
+
+    #[tokio::test]
+    async fn test_web_client_context() {
+        let mut request = axum::http::Request::builder().uri("/api/v1/query?q=AS13335").body(()).unwrap();
+        request.extensions_mut().insert(ConnectInfo("192.0.2.10:52000".parse::<SocketAddr>().unwrap()));
+        request.extensions_mut().insert(AuthenticatedUser { name: "alice".to_string(), permission: Permission::Query });
+        let (mut parts, _) = request.into_parts();
+
+        let client = WebClient::from_request_parts(&mut parts, &()).await.unwrap();
+        let ctx = client.context();
+        assert_eq!(ctx.frontend, Frontend::Web);
+        assert_eq!(ctx.identity.as_deref(), Some("alice"));
+        assert_eq!(ctx.client_addr, Some("192.0.2.10".parse().unwrap()));
+
+        assert_eq!(WebClient::default().context().identity, None);
+    }
+}
@@ -18,6 +18,8 @@
 
 use crate::core::query_processor::process_query;
 use crate::core::{ StatsState, analyze_query, get_stats_response };
+use crate::core::sanitize::sanitize_query;
+use crate::web::auth::WebClient;
 use crate::web::json_formatter::{ JsonFormatter, WhoisApiResponse };
 use crate::web::pixiv_proxy::{ proxy_pixiv_image, proxy_health };
 use crate::config;
@@ -29,6 +31,7 @@ use axum::{
     routing::{ get, post },
 };
 use serde::Deserialize;
+use std::net::SocketAddr;
 use std::time::Instant;
 use tower_http::cors::CorsLayer;
 
@@ -46,10 +49,13 @@ pub async fn run_web_server(
         .route("/docs", get(api_docs))
         .route("/api/openapi.json", get(openapi_spec))
         .route("/api/stats", get(get_stats_api))
+        .route("/metrics", get(metrics))
+        .route("/api/signing-key", get(signing_key))
         .route("/api/whois", get(whois_api_get))
         .route("/api/whois", post(whois_api_post))
         .route("/raw/:query", get(raw_whois_query))
-        .route("/pixiv/:query", get(pixiv_json_query));
+        .route("/pixiv/:query", get(pixiv_json_query))
+        .merge(crate::web::api_v1::router());
 
     // 如果启用了 Pixiv 代理,添加代理路由
     if config::pixiv_proxy_enabled() {
@@ -58,10 +64,19 @@ pub async fn run_web_server(
             .route("/pixiv-proxy-health", get(proxy_health));
     }
 
-    let app = app.layer(CorsLayer::permissive()).with_state(stats);
+    if config::graphql_enabled() {
+        app = app.merge(crate::web::graphql::router());
+    }
+
+    // Logins are checked inside CORS so that preflight requests are still answered
+    let app = app
+        .layer(axum::middleware::from_fn(crate::web::auth::require_login))
+        .layer(CorsLayer::permissive())
+        .with_state(stats);
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-    axum::serve(listener, app).await?;
+    // Client addresses are needed for login throttling and per-client features
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
     Ok(())
 }
 
@@ -77,9 +92,18 @@ async fn get_stats_api(State(stats): State<StatsState>) -> impl IntoResponse {
     }
 }
 
+// Prometheus metrics
+async fn metrics(State(stats): State<StatsState>) -> impl IntoResponse {
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        crate::core::metrics::render_metrics(&stats).await,
+    )
+}
+
 // GET /api/whois?q=query
 async fn whois_api_get(
     State(stats): State<StatsState>,
+    client: WebClient,
     Query(params): Query<ApiQuery>
 ) -> impl IntoResponse {
     let start_time = Instant::now();
@@ -97,12 +121,13 @@ async fn whois_api_get(
         );
     }
 
-    process_whois_query(query, stats, start_time).await
+    process_whois_query(query, &client, stats, start_time).await
 }
 
 // POST /api/whois with JSON body: {"q": "query"}
 async fn whois_api_post(
     State(stats): State<StatsState>,
+    client: WebClient,
     Json(query_data): Json<ApiQuery>
 ) -> impl IntoResponse {
     let start_time = Instant::now();
@@ -120,22 +145,26 @@ async fn whois_api_post(
         );
     }
 
-    process_whois_query(query, stats, start_time).await
+    process_whois_query(query, &client, stats, start_time).await
 }
 
 async fn process_whois_query(
     query: &str,
+    client: &WebClient,
     stats: StatsState,
     start_time: Instant
 ) -> Json<WhoisApiResponse> {
     let formatter = JsonFormatter::new();
+    let query = sanitize_query(query);
+    let query = query.as_str();
 
     // 检测查询类型
     let query_type_str = detect_query_type(query);
     let query_type = analyze_query(query);
 
     // 处理查询
-    match process_query(query, &query_type, None, None).await {
+    let ctx = client.context();
+    match process_query(query, &query_type, &ctx).await {
         Ok(result) => {
             // 更新统计信息
             {
@@ -235,6 +264,14 @@ async fn api_docs() -> impl IntoResponse {
     Html(html)
 }
 
+// GET /api/signing-key - minisign public key for -SIGNED responses
+async fn signing_key() -> impl IntoResponse {
+    match crate::core::signing::signer() {
+        Some(signer) => (StatusCode::OK, signer.public_key_file()),
+        None => (StatusCode::NOT_FOUND, "Response signing is not enabled on this server\n".to_string()),
+    }
+}
+
 // OpenAPI规范JSON
 async fn openapi_spec() -> impl IntoResponse {
     let spec = include_str!("openapi.json");
@@ -244,14 +281,16 @@ async fn openapi_spec() -> impl IntoResponse {
 // GET /raw/:query - 返回原始WHOIS结果，不做任何JSON处理
 async fn raw_whois_query(
     Path(query_param): Path<String>,
-    State(stats): State<StatsState>
+    State(stats): State<StatsState>,
+    client: WebClient
 ) -> impl IntoResponse {
     let query = urlencoding
         ::decode(&query_param)
         .unwrap_or_else(|_| std::borrow::Cow::Borrowed(&query_param))
         .to_string();
 
-    let query = query.trim();
+    let query = sanitize_query(&query);
+    let query = query.as_str();
 
     if query.is_empty() {
         return (
@@ -264,7 +303,8 @@ async fn raw_whois_query(
     let query_type = analyze_query(query);
 
     // 处理查询
-    match process_query(query, &query_type, None, None).await {
+    let ctx = client.context();
+    match process_query(query, &query_type, &ctx).await {
         Ok(result) => {
             // 更新统计信息
             {
@@ -0,0 +1,615 @@
+/*
+ * GraphQL Endpoint for Composed Lookups
+ * Copyright (C) 2025 Akaere Networks
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ */
+
+//! Optional GraphQL endpoint (`POST /graphql`)
+//!
+//! A dashboard showing a domain's WHOIS, DNS, SSL and geolocation data would
+//! otherwise make one REST call per lookup. Here one request selects the
+//! lookups it needs, they are resolved concurrently by the regular query
+//! pipeline, and the answer comes back as one typed response:
+//!
+//! ```graphql
+//! query Overview($name: String!) {
+//!   domain(name: $name) { whois { output } dns { attributes { name value } } ssl { success error } }
+//! }
+//! ```
+//!
+//! Only the part of GraphQL this schema needs is implemented: one query
+//! operation with aliases, string arguments and variables. Fragments,
+//! directives, mutations and subscriptions are rejected. The endpoint is
+//! enabled with `GRAPHQL_ENABLED=true`; the schema is served as SDL at
+//! `/graphql/schema`.
+
+use crate::core::query_processor::process_query;
+use crate::core::request_context::RequestContext;
+use crate::core::{ QueryType, StatsState, analyze_query, record_request };
+use crate::web::auth::WebClient;
+use crate::web::json_formatter::{ JsonFormatter, WhoisField };
+use anyhow::{ Result, anyhow };
+use axum::{ Router, extract::State, response::{ IntoResponse, Json }, routing::{ get, post } };
+use serde::Deserialize;
+use serde_json::{ Map, Value, json };
+use std::collections::HashMap;
+
+/// Schema in GraphQL SDL, served at `/graphql/schema`
+const SCHEMA: &str = r#"type Query {
+  # Lookups of one domain; every selected lookup runs concurrently
+  domain(name: String!): Domain!
+}
+
+type Domain {
+  name: String!
+  whois: Lookup!
+  dns: Lookup!
+  ssl: Lookup!
+  geo: Lookup!
+}
+
+type Lookup {
+  # Query run through the regular pipeline, e.g. example.com-DNS
+  query: String!
+  success: Boolean!
+  output: String
+  error: String
+  # key: value lines of the output
+  attributes: [Attribute!]!
+}
+
+type Attribute {
+  name: String!
+  value: String!
+}
+"#;
+
+/// Object types with their fields and, for object-valued fields, the field type
+const TYPES: &[(&str, &[(&str, Option<&str>)])] = &[
+    ("Query", &[("domain", Some("Domain"))]),
+    (
+        "Domain",
+        &[("name", None), ("whois", Some("Lookup")), ("dns", Some("Lookup")), ("ssl", Some("Lookup")), ("geo", Some("Lookup"))],
+    ),
+    (
+        "Lookup",
+        &[("query", None), ("success", None), ("output", None), ("error", None), ("attributes", Some("Attribute"))],
+    ),
+    ("Attribute", &[("name", None), ("value", None)]),
+];
+
+/// Most `domain` fields in one request
+const MAX_DOMAINS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct GraphqlRequest {
+    query: String,
+    #[serde(default)]
+    variables: Option<Map<String, Value>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Punct(char),
+    Name(String),
+    Str(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Argument {
+    Str(String),
+    Variable(String),
+}
+
+/// A selected field
+#[derive(Debug, PartialEq)]
+struct Field {
+    alias: Option<String>,
+    name: String,
+    arguments: Vec<(String, Argument)>,
+    selections: Vec<Field>,
+}
+
+impl Field {
+    /// Key of the field in the response
+    fn key(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() || c == ',' || c == '\u{feff}' => {}
+            '#' => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '{' | '}' | '(' | ')' | ':' | '!' | '$' | '[' | ']' => tokens.push(Token::Punct(c)),
+            '.' => return Err(anyhow!("Fragments are not supported")),
+            '@' => return Err(anyhow!("Directives are not supported")),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') =>
+                            match chars.next() {
+                                Some('n') => value.push('\n'),
+                                Some('t') => value.push('\t'),
+                                Some('r') => value.push('\r'),
+                                Some(c @ ('"' | '\\' | '/')) => value.push(c),
+                                Some('u') => {
+                                    let hex: String = chars.by_ref().take(4).collect();
+                                    let code = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                                    value.push(code.ok_or_else(|| anyhow!("Invalid unicode escape \\u{}", hex))?);
+                                }
+                                _ => return Err(anyhow!("Invalid escape sequence in string")),
+                            }
+                        Some('\n') | None => return Err(anyhow!("Unterminated string")),
+                        Some(c) => value.push(c),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                tokens.push(Token::Name(name));
+            }
+            other => return Err(anyhow!("Unexpected character '{}'", other)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn at(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.advance() {
+            Some(Token::Punct(found)) if found == c => Ok(()),
+            other => Err(anyhow!("Expected '{}', found {}", c, describe(other.as_ref()))),
+        }
+    }
+
+    fn name(&mut self) -> Result<String> {
+        match self.advance() {
+            Some(Token::Name(name)) => Ok(name),
+            other => Err(anyhow!("Expected a name, found {}", describe(other.as_ref()))),
+        }
+    }
+
+    /// `query Name($var: String!)`, or a bare selection set
+    fn document(&mut self) -> Result<Vec<Field>> {
+        match self.peek() {
+            Some(Token::Name(keyword)) if keyword == "query" => {
+                self.advance();
+                if matches!(self.peek(), Some(Token::Name(_))) {
+                    self.advance();
+                }
+                if self.at('(') {
+                    self.variable_definitions()?;
+                }
+            }
+            Some(Token::Name(keyword)) => {
+                return Err(anyhow!("Only query operations are supported, found '{}'", keyword));
+            }
+            _ => {}
+        }
+        let selections = self.selection_set()?;
+        if self.peek().is_some() {
+            return Err(anyhow!("Only one operation per request is supported"));
+        }
+        Ok(selections)
+    }
+
+    /// Variable types are not checked beyond being `String`
+    fn variable_definitions(&mut self) -> Result<()> {
+        self.expect('(')?;
+        while !self.at(')') {
+            self.expect('$')?;
+            let variable = self.name()?;
+            self.expect(':')?;
+            let kind = self.name()?;
+            if kind != "String" {
+                return Err(anyhow!("Variable ${} must be a String, not {}", variable, kind));
+            }
+            if self.at('!') {
+                self.advance();
+            }
+        }
+        self.expect(')')
+    }
+
+    fn selection_set(&mut self) -> Result<Vec<Field>> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        while !self.at('}') {
+            if self.peek().is_none() {
+                return Err(anyhow!("Unterminated selection set"));
+            }
+            fields.push(self.field()?);
+        }
+        self.advance();
+        if fields.is_empty() {
+            return Err(anyhow!("Empty selection set"));
+        }
+        Ok(fields)
+    }
+
+    fn field(&mut self) -> Result<Field> {
+        let first = self.name()?;
+        let (alias, name) = if self.at(':') {
+            self.advance();
+            (Some(first), self.name()?)
+        } else {
+            (None, first)
+        };
+
+        let mut arguments = Vec::new();
+        if self.at('(') {
+            self.advance();
+            while !self.at(')') {
+                let argument = self.name()?;
+                self.expect(':')?;
+                let value = match self.advance() {
+                    Some(Token::Str(value)) => Argument::Str(value),
+                    Some(Token::Punct('$')) => Argument::Variable(self.name()?),
+                    other => {
+                        return Err(anyhow!("Argument '{}' must be a string, found {}", argument, describe(other.as_ref())));
+                    }
+                };
+                arguments.push((argument, value));
+            }
+            self.advance();
+        }
+
+        let selections = if self.at('{') { self.selection_set()? } else { Vec::new() };
+        Ok(Field { alias, name, arguments, selections })
+    }
+}
+
+fn describe(token: Option<&Token>) -> String {
+    match token {
+        Some(Token::Punct(c)) => format!("'{}'", c),
+        Some(Token::Name(name)) => format!("'{}'", name),
+        Some(Token::Str(value)) => format!("\"{}\"", value),
+        None => "end of query".to_string(),
+    }
+}
+
+fn parse_document(source: &str) -> Result<Vec<Field>> {
+    Parser { tokens: tokenize(source)?, pos: 0 }.document()
+}
+
+/// Check selections against [`TYPES`]
+fn validate(parent: &str, fields: &[Field]) -> Result<()> {
+    let (_, type_fields) = TYPES.iter().find(|(name, _)| *name == parent).expect("validated types are in TYPES");
+    let mut keys: Vec<&str> = Vec::new();
+    for field in fields {
+        if keys.contains(&field.key()) {
+            return Err(anyhow!("Field '{}' is selected twice on {}; use an alias", field.key(), parent));
+        }
+        keys.push(field.key());
+
+        let expected_arguments: &[&str] = if parent == "Query" { &["name"] } else { &[] };
+        if let Some((argument, _)) = field.arguments.iter().find(|(argument, _)| !expected_arguments.contains(&argument.as_str())) {
+            return Err(anyhow!("Unknown argument '{}' on {}.{}", argument, parent, field.name));
+        }
+
+        if field.name == "__typename" {
+            if !field.selections.is_empty() {
+                return Err(anyhow!("__typename cannot have a selection set"));
+            }
+            continue;
+        }
+        match type_fields.iter().find(|(name, _)| *name == field.name) {
+            None => {
+                return Err(anyhow!("Unknown field '{}' on {}", field.name, parent));
+            }
+            Some((_, Some(child))) => {
+                if field.selections.is_empty() {
+                    return Err(anyhow!("Field {}.{} of type {} needs a selection set", parent, field.name, child));
+                }
+                validate(child, &field.selections)?;
+            }
+            Some((_, None)) => {
+                if !field.selections.is_empty() {
+                    return Err(anyhow!("Scalar field {}.{} cannot have a selection set", parent, field.name));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Query run for a lookup field of `Domain`
+fn lookup_query(domain: &str, lookup: &str) -> Option<String> {
+    match lookup {
+        "whois" => Some(domain.to_string()),
+        "dns" => Some(format!("{}-DNS", domain)),
+        "ssl" => Some(format!("{}-SSL", domain)),
+        "geo" => Some(format!("{}-GEO", domain)),
+        _ => None,
+    }
+}
+
+/// The `name` argument of a `domain` field, which must be a plain domain
+fn domain_name(field: &Field, variables: &Map<String, Value>) -> Result<String> {
+    let value = match field.arguments.iter().find(|(argument, _)| argument == "name") {
+        Some((_, Argument::Str(value))) => value.clone(),
+        Some((_, Argument::Variable(variable))) =>
+            match variables.get(variable) {
+                Some(Value::String(value)) => value.clone(),
+                Some(_) => {
+                    return Err(anyhow!("Variable ${} must be a string", variable));
+                }
+                None => {
+                    return Err(anyhow!("Variable ${} is not defined", variable));
+                }
+            }
+        None => {
+            return Err(anyhow!("Field 'domain' needs a 'name' argument"));
+        }
+    };
+    let name = value.trim().to_lowercase();
+    // Suffixes and other query types are not accepted as names
+    if !matches!(analyze_query(&name), QueryType::Domain(_)) {
+        return Err(anyhow!("'{}' is not a domain name", value));
+    }
+    Ok(name)
+}
+
+/// Distinct queries needed to answer a validated document
+fn plan(fields: &[Field], variables: &Map<String, Value>) -> Result<Vec<String>> {
+    let domains: Vec<&Field> = fields.iter().filter(|field| field.name == "domain").collect();
+    if domains.len() > MAX_DOMAINS {
+        return Err(anyhow!("At most {} domains can be queried per request", MAX_DOMAINS));
+    }
+    let mut queries: Vec<String> = Vec::new();
+    for field in domains {
+        let name = domain_name(field, variables)?;
+        for query in field.selections.iter().filter_map(|selection| lookup_query(&name, &selection.name)) {
+            if !queries.contains(&query) {
+                queries.push(query);
+            }
+        }
+    }
+    Ok(queries)
+}
+
+/// Outcome of one query
+#[derive(Debug)]
+struct LookupResult {
+    output: Option<String>,
+    error: Option<String>,
+    attributes: Vec<WhoisField>,
+}
+
+async fn resolve(query: String, ctx: &RequestContext) -> (String, LookupResult) {
+    let result = match process_query(&query, &analyze_query(&query), ctx).await {
+        Ok(output) => {
+            let attributes = JsonFormatter::new().format_response(&query, output.clone(), "graphql", 0).fields.unwrap_or_default();
+            LookupResult { output: Some(output), error: None, attributes }
+        }
+        Err(e) => LookupResult { output: None, error: Some(e.to_string()), attributes: Vec::new() },
+    };
+    (query, result)
+}
+
+fn render_lookup(selections: &[Field], query: &str, result: &LookupResult) -> Value {
+    let mut object = Map::new();
+    for field in selections {
+        let value = match field.name.as_str() {
+            "__typename" => json!("Lookup"),
+            "query" => json!(query),
+            "success" => json!(result.error.is_none() && result.output.as_deref().is_some_and(|o| !o.trim().is_empty())),
+            "output" => json!(result.output),
+            "error" => json!(result.error),
+            "attributes" => {
+                let attributes = result.attributes.iter().map(|attribute| {
+                    let mut object = Map::new();
+                    for selection in &field.selections {
+                        let value = match selection.name.as_str() {
+                            "__typename" => json!("Attribute"),
+                            "name" => json!(attribute.name),
+                            _ => json!(attribute.value),
+                        };
+                        object.insert(selection.key().to_string(), value);
+                    }
+                    Value::Object(object)
+                });
+                Value::Array(attributes.collect())
+            }
+            _ => Value::Null,
+        };
+        object.insert(field.key().to_string(), value);
+    }
+    Value::Object(object)
+}
+
+/// Build `data` from resolved lookups
+fn render(fields: &[Field], variables: &Map<String, Value>, results: &HashMap<String, LookupResult>) -> Result<Value> {
+    let mut data = Map::new();
+    for field in fields {
+        if field.name == "__typename" {
+            data.insert(field.key().to_string(), json!("Query"));
+            continue;
+        }
+        let name = domain_name(field, variables)?;
+        let mut domain = Map::new();
+        for selection in &field.selections {
+            let value = match (selection.name.as_str(), lookup_query(&name, &selection.name)) {
+                ("__typename", _) => json!("Domain"),
+                ("name", _) => json!(name),
+                (_, Some(query)) =>
+                    match results.get(&query) {
+                        Some(result) => render_lookup(&selection.selections, &query, result),
+                        None => Value::Null,
+                    }
+                _ => Value::Null,
+            };
+            domain.insert(selection.key().to_string(), value);
+        }
+        data.insert(field.key().to_string(), Value::Object(domain));
+    }
+    Ok(Value::Object(data))
+}
+
+fn error_response(error: anyhow::Error) -> Value {
+    json!({ "errors": [{ "message": error.to_string() }] })
+}
+
+/// Execute a GraphQL request, running its lookups with `ctx`
+pub async fn execute(request: GraphqlRequest, ctx: &RequestContext) -> Value {
+    let variables = request.variables.unwrap_or_default();
+    let fields = match parse_document(&request.query) {
+        Ok(fields) => fields,
+        Err(e) => {
+            return error_response(e);
+        }
+    };
+    let queries = match validate("Query", &fields).and_then(|_| plan(&fields, &variables)) {
+        Ok(queries) => queries,
+        Err(e) => {
+            return error_response(e);
+        }
+    };
+
+    let results: HashMap<String, LookupResult> = futures::future::join_all(queries.into_iter().map(|query| resolve(query, ctx))).await.into_iter().collect();
+    match render(&fields, &variables, &results) {
+        Ok(data) => json!({ "data": data }),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Routes of the GraphQL endpoint
+pub fn router() -> Router<StatsState> {
+    Router::new().route("/graphql", post(graphql)).route("/graphql/schema", get(schema))
+}
+
+// POST /graphql with JSON body: {"query": "...", "variables": {...}}
+async fn graphql(State(stats): State<StatsState>, client: WebClient, Json(request): Json<GraphqlRequest>) -> Json<Value> {
+    let response = execute(request, &client.context()).await;
+    if response.get("data").is_some() {
+        record_request(&stats, response.to_string().len()).await;
+    }
+    Json(response)
+}
+
+async fn schema() -> impl IntoResponse {
+    ([(axum::http::header::CONTENT_TYPE, "text/plain; charset=utf-8")], SCHEMA)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(name: &str) -> Map<String, Value> {
+        json!({ "name": name }).as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_parse_and_plan() {
+        let document = r#"
+            query Overview($name: String!) {
+                # Two domains, one through a variable
+                main: domain(name: $name) { name whois { output } dns { success } }
+                other: domain(name: "Example.NET") { ssl { error } geo { query } }
+            }
+        "#;
+        let fields = parse_document(document).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].key(), "main");
+        assert_eq!(fields[0].arguments, vec![("name".to_string(), Argument::Variable("name".to_string()))]);
+        validate("Query", &fields).unwrap();
+
+        let queries = plan(&fields, &variables("example.com")).unwrap();
+        assert_eq!(queries, vec!["example.com", "example.com-DNS", "example.net-SSL", "example.net-GEO"]);
+    }
+
+    #[test]
+    fn test_rejected_documents() {
+        let invalid = [
+            "{ domain(name: \"example.com\") { whois } }",
+            "{ domain(name: \"example.com\") { mx { output } } }",
+            "{ domain(name: \"example.com\") { name { value } } }",
+            "{ domain(name: \"example.com\", type: \"A\") { name } }",
+            "{ domain(name: \"example.com\") { name name } }",
+            "mutation { domain(name: \"example.com\") { name } }",
+            "{ domain(name: \"example.com\") { ...Lookups } }",
+            "{ domain(name: 42) { name } }",
+            "{ domain(name: \"example.com\") { name }",
+        ];
+        for document in invalid {
+            let result = parse_document(document).and_then(|fields| validate("Query", &fields));
+            assert!(result.is_err(), "{} was accepted", document);
+        }
+
+        // Queries of other types cannot be smuggled in as domain names
+        let fields = parse_document("{ domain(name: \"example.com-TRACE\") { whois { output } } }").unwrap();
+        assert!(plan(&fields, &Map::new()).is_err());
+        let fields = parse_document("query ($name: String) { domain(name: $name) { name } }").unwrap();
+        assert!(plan(&fields, &Map::new()).is_err());
+    }
+
+    #[test]
+    fn test_render_response() {
+        let fields = parse_document(
+            "{ __typename domain(name: \"example.com\") { name whois { success rows: attributes { name value } } ssl { success error } } }"
+        ).unwrap();
+        validate("Query", &fields).unwrap();
+
+        let results = HashMap::from([
+            (
+                "example.com".to_string(),
+                LookupResult {
+                    output: Some("Domain Name: EXAMPLE.COM\n".to_string()),
+                    error: None,
+                    attributes: vec![WhoisField { name: "Domain Name".to_string(), value: "EXAMPLE.COM".to_string() }],
+                },
+            ),
+            (
+                "example.com-SSL".to_string(),
+                LookupResult { output: None, error: Some("connection refused".to_string()), attributes: Vec::new() },
+            ),
+        ]);
+        let data = render(&fields, &Map::new(), &results).unwrap();
+        assert_eq!(
+            data,
+            json!({
+                "__typename": "Query",
+                "domain": {
+                    "name": "example.com",
+                    "whois": { "success": true, "rows": [{ "name": "Domain Name", "value": "EXAMPLE.COM" }] },
+                    "ssl": { "success": false, "error": "connection refused" }
+                }
+            })
+        );
+    }
+}
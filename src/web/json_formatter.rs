@@ -9,6 +9,7 @@
  */
 
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WhoisApiResponse {
@@ -17,6 +18,13 @@ pub struct WhoisApiResponse {
     pub query_type: String,
     pub raw_output: Option<String>,
     pub fields: Option<Vec<WhoisField>>,
+    /// Response split into objects (blank-line separated blocks); repeated
+    /// attributes become arrays
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub objects: Option<Vec<Map<String, Value>>>,
+    /// `%` comment lines, without the prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<Vec<String>>,
     pub error: Option<String>,
     pub metadata: ResponseMetadata,
 }
@@ -50,6 +58,8 @@ impl JsonFormatter {
         processing_time_ms: u64,
     ) -> WhoisApiResponse {
         let fields = self.parse_whois_fields(&raw_output);
+        let objects = parse_objects(&raw_output);
+        let comments = parse_comments(&raw_output);
 
         WhoisApiResponse {
             success: !raw_output.trim().is_empty(),
@@ -61,6 +71,8 @@ impl JsonFormatter {
             } else {
                 Some(fields)
             },
+            objects: (!objects.is_empty()).then_some(objects),
+            comments: (!comments.is_empty()).then_some(comments),
             error: None,
             metadata: ResponseMetadata {
                 timestamp: chrono::Utc::now().to_rfc3339(),
@@ -84,6 +96,8 @@ impl JsonFormatter {
             query_type: query_type.to_string(),
             raw_output: None,
             fields: None,
+            objects: None,
+            comments: None,
             error: Some(error_message.to_string()),
             metadata: ResponseMetadata {
                 timestamp: chrono::Utc::now().to_rfc3339(),
@@ -123,3 +137,104 @@ impl Default for JsonFormatter {
         Self::new()
     }
 }
+
+/// Group `key: value` attributes into objects
+///
+/// Blank lines end an object, like RPSL objects in a WHOIS response. An
+/// attribute that appears more than once in an object becomes an array of
+/// its values, and indented continuation lines are appended to the previous
+/// value. Comment lines and lines without an attribute are skipped.
+pub fn parse_objects(raw_output: &str) -> Vec<Map<String, Value>> {
+    let mut objects = Vec::new();
+    let mut current = Map::new();
+    let mut last_key: Option<String> = None;
+
+    for line in raw_output.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            if !current.is_empty() {
+                objects.push(std::mem::take(&mut current));
+            }
+            last_key = None;
+            continue;
+        }
+        if trimmed.starts_with('%') || trimmed.starts_with('#') {
+            continue;
+        }
+
+        // Continuation of the previous attribute
+        if (line.starts_with(' ') || line.starts_with('\t') || line.starts_with('+'))
+            && let Some(key) = &last_key
+        {
+            let continuation = trimmed.trim_start_matches('+').trim();
+            if let Some(value) = current.get_mut(key) {
+                let target = match value {
+                    Value::Array(values) => values.last_mut(),
+                    other => Some(other),
+                };
+                if let Some(Value::String(text)) = target
+                    && !continuation.is_empty()
+                {
+                    if !text.is_empty() {
+                        text.push('\n');
+                    }
+                    text.push_str(continuation);
+                }
+            }
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() || key.split_whitespace().count() > 4 {
+            continue;
+        }
+        let value = Value::String(value.trim().to_string());
+        match current.get_mut(key) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Value::Array(vec![first, value]);
+            }
+            None => {
+                current.insert(key.to_string(), value);
+            }
+        }
+        last_key = Some(key.to_string());
+    }
+
+    if !current.is_empty() {
+        objects.push(current);
+    }
+    objects
+}
+
+/// Comment lines (`%` prefix) of a response, without the prefix
+pub fn parse_comments(raw_output: &str) -> Vec<String> {
+    raw_output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix('%'))
+        .map(|comment| comment.trim().to_string())
+        .filter(|comment| !comment.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_objects() {
+        let raw = "% Information related to AS13335\n\naut-num: AS13335\nas-name: CLOUDFLARENET\n\
+                   remarks: first\nremarks: second\n  continued\n\nroute: 1.1.1.0/24\norigin: AS13335\n";
+        let objects = parse_objects(raw);
+
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0]["aut-num"], "AS13335");
+        assert_eq!(objects[0]["remarks"], serde_json::json!(["first", "second\ncontinued"]));
+        assert_eq!(objects[1]["origin"], "AS13335");
+        assert_eq!(parse_comments(raw), vec!["Information related to AS13335"]);
+    }
+}
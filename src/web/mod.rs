@@ -1,4 +1,7 @@
+pub mod api_v1;
+pub mod auth;
 pub mod dashboard;
+pub mod graphql;
 pub mod json_formatter;
 pub mod pixiv_proxy;
 
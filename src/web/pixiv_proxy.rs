@@ -22,6 +22,7 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use reqwest::Client;
+use crate::core::body_limit::LimitedBody;
 use crate::{log_debug, log_error, log_warn};
 /// Pixiv image reverse proxy handler
 /// Proxies requests to i.pximg.net with proper headers to bypass restrictions
@@ -91,7 +92,7 @@ pub async fn proxy_pixiv_image(Path(path): Path<String>) -> Response {
     log_debug!("Content-Type: {}", content_type);
 
     // Get the image bytes
-    let bytes = match response.bytes().await {
+    let bytes = match response.limited_bytes().await {
         Ok(b) => b,
         Err(e) => {
             log_error!("Failed to read response body: {}", e);